@@ -69,6 +69,108 @@ fn generate_bulk_response(num_mbeans: usize) -> String {
     format!("[{}]", responses.join(","))
 }
 
+/// Wildcard read of `num_mbeans` MBeans with scalar and composite attributes
+fn generate_wildcard_response(num_mbeans: usize) -> String {
+    let mbeans: Vec<String> = (0..num_mbeans)
+        .map(|i| {
+            format!(
+                r#""com.example:type=Cache,name=Cache{i}": {{
+                    "Name": "Cache{i}",
+                    "Enabled": true,
+                    "HitCount": {hits},
+                    "MissCount": {misses},
+                    "HitRatio": {ratio},
+                    "EvictionCount": {evictions},
+                    "Size": {{"entries": {entries}, "bytes": {bytes}, "max": 1073741824}},
+                    "Latency": {{"p50": 0.25, "p99": 4.5, "max": 120.75}}
+                }}"#,
+                hits = i * 7919,
+                misses = i * 13,
+                ratio = 1.0 - 1.0 / (i as f64 + 2.0),
+                evictions = i % 97,
+                entries = i * 31,
+                bytes = i * 65536,
+            )
+        })
+        .collect();
+
+    format!(
+        r#"{{
+            "request": {{"mbean": "com.example:type=Cache,*", "type": "read"}},
+            "value": {{{}}},
+            "timestamp": 1609459200,
+            "status": 200
+        }}"#,
+        mbeans.join(",")
+    )
+}
+
+/// Bulk read of the built-in JVM MBeans, as returned by a JDK 17 Jolokia agent
+const JVM_BULK_JSON: &str = r#"[
+    {
+        "request": {"mbean": "java.lang:type=Memory", "type": "read"},
+        "value": {
+            "ObjectPendingFinalizationCount": 0,
+            "Verbose": false,
+            "HeapMemoryUsage": {"init": 268435456, "committed": 536870912, "max": 4294967296, "used": 134217728},
+            "NonHeapMemoryUsage": {"init": 7667712, "committed": 61341696, "max": -1, "used": 57512464},
+            "ObjectName": {"objectName": "java.lang:type=Memory"}
+        },
+        "timestamp": 1609459200,
+        "status": 200
+    },
+    {
+        "request": {"mbean": "java.lang:type=Threading", "type": "read"},
+        "value": {
+            "ThreadAllocatedMemoryEnabled": true,
+            "ThreadAllocatedMemorySupported": true,
+            "ThreadContentionMonitoringEnabled": false,
+            "ThreadContentionMonitoringSupported": true,
+            "ThreadCpuTimeEnabled": true,
+            "ThreadCpuTimeSupported": true,
+            "CurrentThreadCpuTimeSupported": true,
+            "ObjectMonitorUsageSupported": true,
+            "SynchronizerUsageSupported": true,
+            "DaemonThreadCount": 18,
+            "PeakThreadCount": 25,
+            "ThreadCount": 22,
+            "TotalStartedThreadCount": 40,
+            "CurrentThreadCpuTime": 8392000,
+            "CurrentThreadUserTime": 0,
+            "CurrentThreadAllocatedBytes": 1249800,
+            "AllThreadIds": [1, 2, 3, 4, 5, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28],
+            "ObjectName": {"objectName": "java.lang:type=Threading"}
+        },
+        "timestamp": 1609459200,
+        "status": 200
+    },
+    {
+        "request": {"mbean": "java.lang:type=GarbageCollector,*", "type": "read"},
+        "value": {
+            "java.lang:name=G1 Young Generation,type=GarbageCollector": {
+                "LastGcInfo": null,
+                "CollectionCount": 42,
+                "CollectionTime": 1234,
+                "Name": "G1 Young Generation",
+                "Valid": true,
+                "MemoryPoolNames": ["G1 Eden Space", "G1 Survivor Space", "G1 Old Gen"],
+                "ObjectName": {"objectName": "java.lang:name=G1 Young Generation,type=GarbageCollector"}
+            },
+            "java.lang:name=G1 Old Generation,type=GarbageCollector": {
+                "LastGcInfo": null,
+                "CollectionCount": 5,
+                "CollectionTime": 567,
+                "Name": "G1 Old Generation",
+                "Valid": true,
+                "MemoryPoolNames": ["G1 Eden Space", "G1 Survivor Space", "G1 Old Gen"],
+                "ObjectName": {"objectName": "java.lang:name=G1 Old Generation,type=GarbageCollector"}
+            }
+        },
+        "timestamp": 1609459200,
+        "status": 200
+    }
+]"#;
+
 fn create_test_engine() -> TransformEngine {
     let mut ruleset = RuleSet::new();
 
@@ -134,6 +236,15 @@ fn bench_json_parsing(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_jvm_json_parsing(c: &mut Criterion) {
+    c.bench_function("scrape/jvm_json_parsing", |b| {
+        b.iter(|| {
+            let result = parse_bulk_response(JVM_BULK_JSON).unwrap();
+            std::hint::black_box(result);
+        })
+    });
+}
+
 fn bench_bulk_json_parsing(c: &mut Criterion) {
     let mut group = c.benchmark_group("scrape/bulk_json_parsing");
 
@@ -153,6 +264,24 @@ fn bench_bulk_json_parsing(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_large_wildcard_parsing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scrape/large_wildcard_parsing");
+
+    for size in [1000, 5000].iter() {
+        let json = generate_wildcard_response(*size);
+
+        group.throughput(Throughput::Bytes(json.len() as u64));
+        group.bench_with_input(BenchmarkId::new("mbeans", size), &json, |b, json| {
+            b.iter(|| {
+                let result = parse_response(json).unwrap();
+                std::hint::black_box(result);
+            })
+        });
+    }
+
+    group.finish();
+}
+
 fn bench_transform_processing(c: &mut Criterion) {
     let engine = create_test_engine();
     let mut group = c.benchmark_group("scrape/transform");
@@ -208,6 +337,46 @@ fn bench_bulk_transform(c: &mut Criterion) {
     group.finish();
 }
 
+/// 500 rules that never match, ahead of the one that does
+fn create_large_ruleset_engine() -> TransformEngine {
+    let mut rules: Vec<Rule> = (0..500)
+        .map(|i| {
+            Rule::builder(format!(r"com\.vendor{}<type=(\w+)><(\w+)>", i))
+                .name(format!("vendor{}_$1_$2", i))
+                .build()
+        })
+        .collect();
+    rules.push(
+        Rule::builder(r"com\.example<type=Service,name=(\w+)><(\w+)>")
+            .name("app_service_$2")
+            .label("service", "$1")
+            .build(),
+    );
+    let ruleset = RuleSet::from_rules(rules);
+    ruleset.compile_all().unwrap();
+    TransformEngine::new(ruleset)
+}
+
+fn bench_large_ruleset(c: &mut Criterion) {
+    let engine = create_large_ruleset_engine();
+    let mut group = c.benchmark_group("scrape/large_ruleset");
+
+    let responses = parse_bulk_response(&generate_bulk_response(1000)).unwrap();
+    group.throughput(Throughput::Elements(responses.len() as u64));
+    for parallel in [false, true] {
+        let engine = engine.clone().with_parallel(parallel);
+        let mode = if parallel { "parallel" } else { "sequential" };
+        group.bench_with_input(BenchmarkId::new(mode, 1000), &responses, |b, responses| {
+            b.iter(|| {
+                let metrics = engine.transform(responses).unwrap_or_default();
+                std::hint::black_box(metrics);
+            })
+        });
+    }
+
+    group.finish();
+}
+
 fn bench_prometheus_formatting(c: &mut Criterion) {
     let engine = create_test_engine();
     let formatter = PrometheusFormatter::new();
@@ -298,9 +467,12 @@ fn bench_latency_target(c: &mut Criterion) {
 criterion_group!(
     benches,
     bench_json_parsing,
+    bench_jvm_json_parsing,
     bench_bulk_json_parsing,
+    bench_large_wildcard_parsing,
     bench_transform_processing,
     bench_bulk_transform,
+    bench_large_ruleset,
     bench_prometheus_formatting,
     bench_full_scrape_pipeline,
     bench_latency_target,
@@ -0,0 +1,70 @@
+//! Build script capturing build metadata for `rjmx_exporter_build_info`
+//!
+//! Sets `RJMX_BUILD_COMMIT`, `RJMX_BUILD_RUSTC` and `RJMX_BUILD_FEATURES`
+//! for `env!`. The commit can be overridden with the `RJMX_BUILD_COMMIT`
+//! environment variable, e.g. in Docker builds without a `.git` directory.
+//!
+//! With the `grpc` feature, also generates the admin API from
+//! `proto/admin.proto`, using a vendored `protoc`.
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=RJMX_BUILD_COMMIT");
+    // A missing file would rerun the script on every build
+    for path in [".git/HEAD", ".git/index"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+
+    let commit = env::var("RJMX_BUILD_COMMIT")
+        .ok()
+        .filter(|commit| !commit.is_empty())
+        .or_else(|| command_output("git", &["rev-parse", "--short=12", "HEAD"]))
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc = command_output(&rustc, &["--version"]).unwrap_or_else(|| "unknown".to_string());
+
+    // Cargo sets CARGO_FEATURE_<NAME> for each enabled feature
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| {
+            key.strip_prefix("CARGO_FEATURE_")
+                .map(|name| name.to_ascii_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=RJMX_BUILD_COMMIT={}", commit);
+    println!("cargo:rustc-env=RJMX_BUILD_RUSTC={}", rustc);
+    println!("cargo:rustc-env=RJMX_BUILD_FEATURES={}", features.join(","));
+
+    #[cfg(feature = "grpc")]
+    compile_protos();
+}
+
+/// Generate the gRPC admin API server and client
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    println!("cargo:rerun-if-changed=proto/admin.proto");
+    // Builds must not depend on a system protoc
+    let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc is available");
+    env::set_var("PROTOC", protoc);
+    tonic_prost_build::configure()
+        .compile_protos(&["proto/admin.proto"], &["proto"])
+        .expect("proto/admin.proto compiles");
+}
+
+/// Trimmed stdout of a successful command
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let stdout = stdout.trim();
+    (!stdout.is_empty()).then(|| stdout.to_string())
+}
@@ -15,12 +15,26 @@
 //! - `--tls-enabled`: Enable TLS/HTTPS for the metrics endpoint (env: RJMX_TLS_ENABLED)
 //! - `--tls-cert-file`: Path to TLS certificate file (env: RJMX_TLS_CERT_FILE)
 //! - `--tls-key-file`: Path to TLS private key file (env: RJMX_TLS_KEY_FILE)
+//! - `--systemd-socket`: Serve on the socket passed by systemd (env: RJMX_SYSTEMD_SOCKET)
+//! - `--preset`: Enable a bundled rule preset, repeatable (env: RJMX_PRESETS)
 //! - `--validate`: Validate configuration without starting server
+//! - `--lenient`: Report validation problems without failing (with `--validate`)
+//! - `--report`: Write a JSON validation report to a file (with `--validate`)
+//! - `--online`: Also check the config against the running Jolokia agent (with `--validate`)
 //! - `--dry-run`: Test configuration and show parsed rules
+//! - `--profile-rules`: Time each rule against sample MBeans (with `--dry-run`)
+//! - `--sample`: Recorded Jolokia responses to profile against (with `--profile-rules`)
 //! - `--log-level` / `-l`: Log level (trace/debug/info/warn/error, env: RJMX_LOG_LEVEL)
 //! - `--output-format`: Output format for validate/dry-run (text/json/yaml)
 //! - `--startup-time`: Measure and display startup time
 //!
+//! # Subcommands
+//!
+//! - `schema`: Print the JSON Schema of the configuration file
+//! - `generate-config`: Write a starter configuration from the MBeans of a running JVM
+//! - `scrape-once`: Run one scrape with the loaded configuration and print the metrics
+//! - `diff`: Compare the series two configurations produce from recorded Jolokia responses
+//!
 //! # Precedence
 //!
 //! Configuration values are resolved in the following order (highest to lowest priority):
@@ -29,7 +43,7 @@
 //! 3. Configuration file
 //! 4. Default values
 
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 /// rJMX-Exporter - High-performance JMX Metric Exporter written in Rust
@@ -65,7 +79,7 @@ pub struct Cli {
     pub bind_address: Option<String>,
 
     /// Metrics endpoint path (overrides config file)
-    /// Must start with '/' and not conflict with '/' or '/health'
+    /// Must start with '/' and not conflict with '/', '/health', '/livez' or '/readyz'
     #[arg(long, value_name = "PATH", env = "RJMX_METRICS_PATH")]
     pub metrics_path: Option<String>,
 
@@ -97,14 +111,52 @@ pub struct Cli {
     #[arg(long, value_name = "FILE", env = "RJMX_TLS_KEY_FILE")]
     pub tls_key_file: Option<String>,
 
+    /// Serve on the socket passed by systemd socket activation (LISTEN_FDS)
+    /// instead of binding the configured port
+    #[arg(long, env = "RJMX_SYSTEMD_SOCKET")]
+    pub systemd_socket: bool,
+
+    /// Enable a bundled rule preset (repeatable; added to `presets` from the config file)
+    #[arg(
+        long = "preset",
+        value_name = "NAME",
+        env = "RJMX_PRESETS",
+        value_delimiter = ',',
+        value_parser = clap::builder::PossibleValuesParser::new(crate::config::presets::NAMES)
+    )]
+    pub presets: Vec<String>,
+
     /// Validate configuration without starting server
     #[arg(long)]
     pub validate: bool,
 
+    /// Report validation problems without failing the process (requires --validate)
+    #[arg(long, requires = "validate")]
+    pub lenient: bool,
+
+    /// Write a machine-readable JSON validation report (requires --validate)
+    #[arg(long, value_name = "FILE", requires = "validate")]
+    pub report: Option<PathBuf>,
+
+    /// Also connect to Jolokia, read the configured MBeans and report rule
+    /// coverage (requires --validate)
+    #[arg(long, requires = "validate")]
+    pub online: bool,
+
     /// Test configuration and show parsed rules
     #[arg(long)]
     pub dry_run: bool,
 
+    /// Also time each rule's compile and average match against sample MBeans
+    /// (requires --dry-run)
+    #[arg(long, requires = "dry_run")]
+    pub profile_rules: bool,
+
+    /// Recorded Jolokia responses to profile against, instead of reading
+    /// the configured MBeans live (requires --profile-rules)
+    #[arg(long, value_name = "FILE", requires = "profile_rules")]
+    pub sample: Option<PathBuf>,
+
     /// Log level
     #[arg(
         short,
@@ -115,13 +167,78 @@ pub struct Cli {
     )]
     pub log_level: LogLevel,
 
-    /// Output format for --validate and --dry-run
+    /// Output format for --validate, --dry-run and diff
     #[arg(long, value_enum, default_value = "text")]
     pub output_format: OutputFormat,
 
     /// Measure and display startup time
     #[arg(long)]
     pub startup_time: bool,
+
+    /// Subcommand to run instead of the exporter
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Subcommands
+#[derive(Clone, Debug, PartialEq, Eq, Subcommand)]
+pub enum Command {
+    /// Print the JSON Schema of the configuration file
+    Schema,
+
+    /// Write a starter configuration from the MBeans of a running JVM
+    GenerateConfig {
+        /// Jolokia URL of the JVM to inspect
+        #[arg(long, value_name = "URL")]
+        jolokia_url: String,
+
+        /// Jolokia authentication username
+        #[arg(long, value_name = "USERNAME", env = "RJMX_USERNAME")]
+        username: Option<String>,
+
+        /// Jolokia authentication password
+        #[arg(long, value_name = "PASSWORD", env = "RJMX_PASSWORD")]
+        password: Option<String>,
+
+        /// Only inspect these MBean domains (repeatable; default: all but
+        /// JMImplementation and jolokia)
+        #[arg(long = "domain", value_name = "DOMAIN", value_delimiter = ',')]
+        domains: Vec<String>,
+
+        /// Write the configuration to a file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Run one scrape with the loaded configuration and print the metrics
+    ScrapeOnce {
+        /// Write the metrics to a file instead of stdout (replaced atomically)
+        #[arg(short, long, value_name = "FILE")]
+        output_file: Option<PathBuf>,
+
+        /// Write a JSON summary of the scrape (series, unmatched MBeans, errors)
+        #[arg(long, value_name = "FILE")]
+        report: Option<PathBuf>,
+
+        /// Also fail if an MBean is read but matched by no rule
+        #[arg(long)]
+        fail_on_unmatched: bool,
+    },
+
+    /// Compare the series two configurations produce from recorded Jolokia responses
+    Diff {
+        /// Configuration currently deployed
+        #[arg(long, value_name = "FILE")]
+        old: PathBuf,
+
+        /// Configuration to review
+        #[arg(long, value_name = "FILE")]
+        new: PathBuf,
+
+        /// Recorded Jolokia response or bulk response array (JSON)
+        #[arg(long, value_name = "FILE")]
+        input: PathBuf,
+    },
 }
 
 /// Log level options
@@ -232,6 +349,75 @@ mod tests {
         assert_eq!(cli.log_level, LogLevel::Info);
         assert_eq!(cli.output_format, OutputFormat::Text);
         assert!(!cli.startup_time);
+        assert_eq!(cli.command, None);
+    }
+
+    #[test]
+    fn test_cli_schema_command() {
+        let cli = Cli::parse_from(["rjmx-exporter", "schema"]);
+        assert_eq!(cli.command, Some(Command::Schema));
+        assert!(Cli::try_parse_from(["rjmx-exporter", "schemas"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_generate_config_command() {
+        let cli = Cli::parse_from([
+            "rjmx-exporter",
+            "generate-config",
+            "--jolokia-url",
+            "http://app:8778/jolokia",
+            "--domain",
+            "java.lang,kafka.server",
+            "-o",
+            "starter.yaml",
+        ]);
+        let Some(Command::GenerateConfig {
+            jolokia_url,
+            domains,
+            output,
+            ..
+        }) = cli.command
+        else {
+            panic!("expected generate-config, got {:?}", cli.command);
+        };
+        assert_eq!(jolokia_url, "http://app:8778/jolokia");
+        assert_eq!(domains, vec!["java.lang", "kafka.server"]);
+        assert_eq!(output, Some(PathBuf::from("starter.yaml")));
+
+        // The Jolokia URL is required
+        assert!(Cli::try_parse_from(["rjmx-exporter", "generate-config"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_scrape_once_command() {
+        let cli = Cli::parse_from(["rjmx-exporter", "-c", "app.yaml", "scrape-once"]);
+        assert_eq!(cli.config, PathBuf::from("app.yaml"));
+        assert_eq!(
+            cli.command,
+            Some(Command::ScrapeOnce {
+                output_file: None,
+                report: None,
+                fail_on_unmatched: false,
+            })
+        );
+
+        let cli = Cli::parse_from([
+            "rjmx-exporter",
+            "scrape-once",
+            "--output-file",
+            "/var/lib/node_exporter/jvm.prom",
+            "--report",
+            "summary.json",
+            "--fail-on-unmatched",
+        ]);
+        assert_eq!(
+            cli.command,
+            Some(Command::ScrapeOnce {
+                output_file: Some(PathBuf::from("/var/lib/node_exporter/jvm.prom")),
+                report: Some(PathBuf::from("summary.json")),
+                fail_on_unmatched: true,
+            })
+        );
     }
 
     #[test]
@@ -259,6 +445,48 @@ mod tests {
         assert_eq!(cli.output_format, OutputFormat::Json);
     }
 
+    #[test]
+    fn test_cli_profile_rules() {
+        let cli = Cli::parse_from([
+            "rjmx-exporter",
+            "--dry-run",
+            "--profile-rules",
+            "--sample",
+            "kafka.json",
+        ]);
+        assert!(cli.profile_rules);
+        assert_eq!(cli.sample, Some(PathBuf::from("kafka.json")));
+
+        // Profiling is part of the dry run
+        assert!(Cli::try_parse_from(["rjmx-exporter", "--profile-rules"]).is_err());
+        assert!(Cli::try_parse_from(["rjmx-exporter", "--dry-run", "--sample", "s.json"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_lenient_report() {
+        let cli = Cli::parse_from([
+            "rjmx-exporter",
+            "--validate",
+            "--lenient",
+            "--report",
+            "report.json",
+        ]);
+        assert!(cli.lenient);
+        assert_eq!(cli.report, Some(PathBuf::from("report.json")));
+        assert!(!cli.online);
+
+        // --lenient and --report only make sense with --validate
+        assert!(Cli::try_parse_from(["rjmx-exporter", "--lenient"]).is_err());
+        assert!(Cli::try_parse_from(["rjmx-exporter", "--report", "r.json"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_online() {
+        let cli = Cli::parse_from(["rjmx-exporter", "--validate", "--online"]);
+        assert!(cli.online);
+        assert!(Cli::try_parse_from(["rjmx-exporter", "--online"]).is_err());
+    }
+
     #[test]
     fn test_cli_startup_time() {
         let cli = Cli::parse_from(["rjmx-exporter", "--startup-time"]);
@@ -309,6 +537,26 @@ mod tests {
         assert_eq!(cli.tls_key_file, Some("/path/to/key.pem".to_string()));
     }
 
+    #[test]
+    fn test_cli_presets() {
+        let cli = Cli::parse_from(["rjmx-exporter"]);
+        assert!(cli.presets.is_empty());
+
+        let cli = Cli::parse_from(["rjmx-exporter", "--preset", "kafka", "--preset", "tomcat"]);
+        assert_eq!(cli.presets, vec!["kafka", "tomcat"]);
+
+        assert!(Cli::try_parse_from(["rjmx-exporter", "--preset", "weblogic"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_systemd_socket() {
+        let cli = Cli::parse_from(["rjmx-exporter"]);
+        assert!(!cli.systemd_socket);
+
+        let cli = Cli::parse_from(["rjmx-exporter", "--systemd-socket"]);
+        assert!(cli.systemd_socket);
+    }
+
     #[test]
     fn test_cli_tls_disabled() {
         let cli = Cli::parse_from(["rjmx-exporter", "--tls-enabled", "false"]);
@@ -0,0 +1,162 @@
+//! Per-query attribute filters
+//!
+//! `include_attributes` and `exclude_attributes` of a collect entry are
+//! regular expressions matched against whole attribute names. Explicit
+//! attribute lists are narrowed before the request is sent; attributes of
+//! full and wildcard reads are dropped from the response before it reaches
+//! the rules.
+
+use regex::Regex;
+use serde_json::Value;
+
+use super::{JolokiaResponse, MBeanValue};
+
+/// Attribute name patterns of one MBean query
+#[derive(Debug, Clone, Default)]
+pub struct AttributePatterns {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl AttributePatterns {
+    /// Compile include and exclude patterns, anchored to the whole name
+    ///
+    /// # Errors
+    /// Returns the first pattern that is not a valid regular expression.
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, regex::Error> {
+        let compile = |patterns: &[String]| -> Result<Vec<Regex>, regex::Error> {
+            patterns
+                .iter()
+                .map(|p| Regex::new(&format!("^(?:{})$", p)))
+                .collect()
+        };
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    /// Check whether no pattern is set, so every attribute is accepted
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Check whether an attribute is collected
+    ///
+    /// An attribute must match an include pattern, if there are any, and
+    /// no exclude pattern.
+    pub fn accepts(&self, attribute: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|re| re.is_match(attribute)))
+            && !self.exclude.iter().any(|re| re.is_match(attribute))
+    }
+
+    /// Drop the attributes of a response that are not collected
+    ///
+    /// Single-attribute reads are left alone; their attribute was checked
+    /// before the request.
+    pub fn retain(&self, response: &mut JolokiaResponse) {
+        if self.is_empty() || response.status != 200 {
+            return;
+        }
+        if matches!(response.request.attribute, Some(Value::String(_))) {
+            return;
+        }
+        match &mut response.value {
+            MBeanValue::Composite(attributes) => {
+                attributes.retain(|name, _| self.accepts(name));
+            }
+            MBeanValue::Wildcard(mbeans) => {
+                for attributes in mbeans.values_mut() {
+                    attributes.retain(|name, _| self.accepts(name));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl PartialEq for AttributePatterns {
+    fn eq(&self, other: &Self) -> bool {
+        let sources = |patterns: &[Regex]| -> Vec<String> {
+            patterns.iter().map(|re| re.as_str().to_string()).collect()
+        };
+        sources(&self.include) == sources(&other.include)
+            && sources(&self.exclude) == sources(&other.exclude)
+    }
+}
+
+impl Eq for AttributePatterns {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::parse_response;
+
+    fn patterns(include: &[&str], exclude: &[&str]) -> AttributePatterns {
+        let owned = |p: &[&str]| p.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        AttributePatterns::new(&owned(include), &owned(exclude)).unwrap()
+    }
+
+    #[test]
+    fn test_accepts() {
+        let all = AttributePatterns::default();
+        assert!(all.is_empty());
+        assert!(all.accepts("LastGcInfo"));
+
+        let filter = patterns(&["Collection.*"], &["CollectionTime"]);
+        assert!(filter.accepts("CollectionCount"));
+        assert!(!filter.accepts("CollectionTime"));
+        assert!(!filter.accepts("LastGcInfo"));
+
+        // Patterns match the whole name
+        let filter = patterns(&[], &["Name"]);
+        assert!(!filter.accepts("Name"));
+        assert!(filter.accepts("ObjectName"));
+
+        assert!(AttributePatterns::new(&["(".to_string()], &[]).is_err());
+    }
+
+    #[test]
+    fn test_retain() {
+        let filter = patterns(&[], &["LastGcInfo", "ObjectName"]);
+
+        let mut response = parse_response(
+            r#"{
+                "request": {"mbean": "java.lang:type=GarbageCollector,*", "type": "read"},
+                "value": {
+                    "java.lang:name=G1 Young Generation,type=GarbageCollector": {
+                        "CollectionCount": 10,
+                        "LastGcInfo": {"duration": 5},
+                        "ObjectName": {"objectName": "java.lang:name=G1 Young Generation,type=GarbageCollector"}
+                    }
+                },
+                "status": 200,
+                "timestamp": 1609459200
+            }"#,
+        )
+        .unwrap();
+        filter.retain(&mut response);
+        let MBeanValue::Wildcard(mbeans) = &response.value else {
+            panic!("expected wildcard value");
+        };
+        let attributes = mbeans.values().next().unwrap();
+        assert_eq!(attributes.len(), 1);
+        assert!(attributes.contains_key("CollectionCount"));
+
+        let mut response = parse_response(
+            r#"{
+                "request": {"mbean": "java.lang:type=Memory", "type": "read"},
+                "value": {"ObjectName": {"objectName": "java.lang:type=Memory"}, "Verbose": false},
+                "status": 200,
+                "timestamp": 1609459200
+            }"#,
+        )
+        .unwrap();
+        filter.retain(&mut response);
+        let MBeanValue::Composite(attributes) = &response.value else {
+            panic!("expected composite value");
+        };
+        assert!(!attributes.contains_key("ObjectName"));
+        assert!(attributes.contains_key("Verbose"));
+    }
+}
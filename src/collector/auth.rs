@@ -0,0 +1,410 @@
+//! Authentication of outbound Jolokia requests
+//!
+//! A [`JolokiaClient`](super::JolokiaClient) hands every request to its
+//! [`AuthProvider`] right before sending it, including retries and
+//! failovers. HTTP Basic, bearer tokens, a static header and AWS Signature
+//! Version 4 (for Jolokia behind API Gateway) are built in; other schemes
+//! implement the trait without changes to the client.
+
+use std::fmt;
+use std::time::SystemTime;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::Request;
+use sha2::{Digest, Sha256};
+
+use super::parser::CollectResult;
+use crate::error::CollectorError;
+
+/// Adds credentials to outbound requests
+pub trait AuthProvider: Send + Sync + fmt::Debug {
+    /// Authenticate a request that is about to be sent
+    fn authenticate(&self, request: &mut Request) -> CollectResult<()>;
+}
+
+/// HTTP Basic authentication
+pub struct BasicAuth {
+    header: HeaderValue,
+}
+
+impl BasicAuth {
+    /// Credentials sent with every request
+    pub fn new(username: &str, password: &str) -> Self {
+        let encoded = STANDARD.encode(format!("{}:{}", username, password));
+        Self {
+            header: sensitive(format!("Basic {}", encoded))
+                .expect("base64 is a valid header value"),
+        }
+    }
+}
+
+impl fmt::Debug for BasicAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BasicAuth")
+    }
+}
+
+impl AuthProvider for BasicAuth {
+    fn authenticate(&self, request: &mut Request) -> CollectResult<()> {
+        request
+            .headers_mut()
+            .insert(AUTHORIZATION, self.header.clone());
+        Ok(())
+    }
+}
+
+/// Bearer token in the `Authorization` header
+pub struct BearerAuth {
+    header: HeaderValue,
+}
+
+impl BearerAuth {
+    /// Fails if the token is not a valid header value
+    pub fn new(token: &str) -> CollectResult<Self> {
+        Ok(Self {
+            header: sensitive(format!("Bearer {}", token))?,
+        })
+    }
+}
+
+impl fmt::Debug for BearerAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BearerAuth")
+    }
+}
+
+impl AuthProvider for BearerAuth {
+    fn authenticate(&self, request: &mut Request) -> CollectResult<()> {
+        request
+            .headers_mut()
+            .insert(AUTHORIZATION, self.header.clone());
+        Ok(())
+    }
+}
+
+/// Static header, e.g. an API key expected by a gateway
+pub struct HeaderAuth {
+    name: HeaderName,
+    value: HeaderValue,
+}
+
+impl HeaderAuth {
+    /// Fails if the name or value is not valid in a header
+    pub fn new(name: &str, value: &str) -> CollectResult<Self> {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| CollectorError::RequestAuth(format!("header name '{}': {}", name, e)))?;
+        Ok(Self {
+            name,
+            value: sensitive(value.to_string())?,
+        })
+    }
+}
+
+impl fmt::Debug for HeaderAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HeaderAuth")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AuthProvider for HeaderAuth {
+    fn authenticate(&self, request: &mut Request) -> CollectResult<()> {
+        request
+            .headers_mut()
+            .insert(self.name.clone(), self.value.clone());
+        Ok(())
+    }
+}
+
+/// AWS Signature Version 4, for Jolokia behind API Gateway or another
+/// IAM-authenticated AWS endpoint
+///
+/// Signs the host, the request time and, with temporary credentials, the
+/// session token, together with the method, path, query and body.
+pub struct AwsSigV4Auth {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+    region: String,
+    service: String,
+}
+
+impl AwsSigV4Auth {
+    /// Sign requests for `service` (e.g. `execute-api`) in `region`
+    pub fn new(access_key_id: &str, secret_access_key: &str, region: &str, service: &str) -> Self {
+        Self {
+            access_key_id: access_key_id.to_string(),
+            secret_access_key: secret_access_key.to_string(),
+            session_token: None,
+            region: region.to_string(),
+            service: service.to_string(),
+        }
+    }
+
+    /// Send the session token of temporary credentials
+    pub fn with_session_token(mut self, token: &str) -> Self {
+        self.session_token = Some(token.to_string());
+        self
+    }
+
+    /// Sign `request` as if sent at `time`
+    fn sign(&self, request: &mut Request, time: SystemTime) -> CollectResult<()> {
+        let timestamp = amz_date(time);
+        let date = &timestamp[..8];
+
+        let url = request.url();
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => {
+                return Err(CollectorError::RequestAuth(format!(
+                    "cannot sign request without a host: {}",
+                    url
+                )))
+            }
+        };
+
+        let mut headers = vec![("host", host), ("x-amz-date", timestamp.clone())];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect();
+
+        let mut query: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(key, value)| (uri_encode(&key, true), uri_encode(&value, true)))
+            .collect();
+        query.sort();
+        let canonical_query = query
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let payload = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .unwrap_or_default();
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            request.method(),
+            uri_encode(url.path(), false),
+            canonical_query,
+            canonical_headers,
+            signed_headers,
+            hex(&Sha256::digest(payload))
+        );
+
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            timestamp,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let key = [self.region.as_str(), self.service.as_str(), "aws4_request"]
+            .iter()
+            .fold(
+                hmac_sha256(
+                    format!("AWS4{}", self.secret_access_key).as_bytes(),
+                    date.as_bytes(),
+                ),
+                |key, part| hmac_sha256(&key, part.as_bytes()),
+            );
+        let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key_id, scope, signed_headers, signature
+        );
+        let request_headers = request.headers_mut();
+        request_headers.insert("x-amz-date", header_value(timestamp)?);
+        if let Some(token) = &self.session_token {
+            request_headers.insert("x-amz-security-token", sensitive(token.clone())?);
+        }
+        request_headers.insert(AUTHORIZATION, sensitive(authorization)?);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for AwsSigV4Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AwsSigV4Auth")
+            .field("access_key_id", &self.access_key_id)
+            .field("region", &self.region)
+            .field("service", &self.service)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AuthProvider for AwsSigV4Auth {
+    fn authenticate(&self, request: &mut Request) -> CollectResult<()> {
+        self.sign(request, SystemTime::now())
+    }
+}
+
+fn header_value(value: String) -> CollectResult<HeaderValue> {
+    HeaderValue::try_from(value)
+        .map_err(|e| CollectorError::RequestAuth(format!("invalid header value: {}", e)))
+}
+
+/// Header value that is kept out of debug output
+fn sensitive(value: String) -> CollectResult<HeaderValue> {
+    let mut value = header_value(value)?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+/// `20261017T135536Z`
+fn amz_date(time: SystemTime) -> String {
+    let (year, month, day, hour, minute, second, _) = crate::server::utc_fields(time);
+    format!(
+        "{}{:02}{:02}T{:02}{:02}{:02}Z",
+        year, month, day, hour, minute, second
+    )
+}
+
+/// Percent-encode everything but unreserved characters (and `/` in paths)
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// HMAC-SHA256 (RFC 2104)
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.map(|b| b ^ byte);
+
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(data)
+        .finalize();
+    Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn request(method: reqwest::Method, url: &str) -> Request {
+        Request::new(method, url.parse().unwrap())
+    }
+
+    #[test]
+    fn test_basic_auth() {
+        let mut req = request(reqwest::Method::POST, "http://localhost:8778/jolokia");
+        BasicAuth::new("user", "pass")
+            .authenticate(&mut req)
+            .unwrap();
+        assert_eq!(req.headers()[AUTHORIZATION], "Basic dXNlcjpwYXNz");
+        assert!(req.headers()[AUTHORIZATION].is_sensitive());
+    }
+
+    #[test]
+    fn test_bearer_and_header_auth() {
+        let mut req = request(reqwest::Method::POST, "http://localhost:8778/jolokia");
+        BearerAuth::new("t0ken")
+            .unwrap()
+            .authenticate(&mut req)
+            .unwrap();
+        HeaderAuth::new("X-Api-Key", "k3y")
+            .unwrap()
+            .authenticate(&mut req)
+            .unwrap();
+        assert_eq!(req.headers()[AUTHORIZATION], "Bearer t0ken");
+        assert_eq!(req.headers()["x-api-key"], "k3y");
+
+        assert!(HeaderAuth::new("bad header", "value").is_err());
+        assert!(BearerAuth::new("line\nbreak").is_err());
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_sigv4_get_vanilla() {
+        // "get-vanilla" from the AWS Signature Version 4 test suite
+        let auth = AwsSigV4Auth::new(
+            "AKIDEXAMPLE",
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "us-east-1",
+            "service",
+        );
+        let mut req = request(reqwest::Method::GET, "https://example.amazonaws.com/");
+        // 2015-08-30T12:36:00Z
+        let time = UNIX_EPOCH + Duration::from_secs(1_440_938_160);
+        auth.sign(&mut req, time).unwrap();
+
+        assert_eq!(req.headers()["x-amz-date"], "20150830T123600Z");
+        assert_eq!(
+            req.headers()[AUTHORIZATION],
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn test_sigv4_session_token() {
+        let auth = AwsSigV4Auth::new("AKID", "secret", "eu-west-1", "execute-api")
+            .with_session_token("session");
+        let mut req = request(
+            reqwest::Method::POST,
+            "https://api.example.com:8443/jolokia",
+        );
+        auth.authenticate(&mut req).unwrap();
+
+        assert_eq!(req.headers()["x-amz-security-token"], "session");
+        let authorization = req.headers()[AUTHORIZATION].to_str().unwrap();
+        assert!(authorization.contains("/eu-west-1/execute-api/aws4_request"));
+        assert!(authorization.contains("SignedHeaders=host;x-amz-date;x-amz-security-token"));
+        assert!(!format!("{:?}", auth).contains("secret"));
+    }
+
+    #[test]
+    fn test_uri_encode() {
+        assert_eq!(uri_encode("/read/a:b=c,d", false), "/read/a%3Ab%3Dc%2Cd");
+        assert_eq!(uri_encode("a/b c", true), "a%2Fb%20c");
+    }
+}
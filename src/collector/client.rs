@@ -1,23 +1,377 @@
 //! Jolokia HTTP client
 //!
 //! Async HTTP client with connection pooling and timeout support.
+//!
+//! Requests are sent as JSON via POST by default. Hardened Jolokia
+//! deployments that only allow GET are supported through the GET protocol,
+//! where the request is encoded in the URL path
+//! (`<base>/read/<mbean>/<attribute>`). A client configured for POST switches
+//! to GET permanently the first time the agent answers with
+//! `405 Method Not Allowed`.
+//!
+//! A client can have fallback URLs for HA setups with several Jolokia
+//! replicas. Requests go to the first healthy URL; a connection error,
+//! timeout or 5xx response fails over to the next one and puts the failed
+//! URL in a cooldown, during which it is only tried after the others. Host
+//! names are resolved for every new connection, so DNS changes are picked up
+//! once pooled connections expire.
+//!
+//! Without an explicit proxy, the `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY`
+//! environment variables are honored. An explicit proxy also skips the hosts
+//! listed in `NO_PROXY`.
+//!
+//! Bulk reads can be split into batches of a fixed number of MBeans, sent
+//! concurrently, so that large reads stay below the agent's maximum POST
+//! size. A batch the agent rejects with `413 Payload Too Large` or `500` is
+//! halved and retried, down to single MBeans.
+//!
+//! An agent answering `429 Too Many Requests` or `503 Service Unavailable`
+//! with a `Retry-After` header puts the URL that sent it into backoff, for at
+//! most the retry policy's `max_backoff`. Until that time has passed, requests
+//! go to the other URLs; once every URL is backing off, they fail with
+//! [`CollectorError::Throttled`] without being sent, and retries wait for the
+//! backoff if it is within the retry policy's `max_delay`. A successful
+//! response ends the backoff of its URL.
+//!
+//! Credentials are added by an [`AuthProvider`] just before each request is
+//! sent, so signatures are fresh on retries and failovers.
+//!
+//! [`measure_traffic`] counts the requests and response bytes of everything
+//! a future sends through any client, e.g. one scrape.
 
-use reqwest::{Client, ClientBuilder};
+use rand::Rng;
+use reqwest::header::HeaderMap;
+use reqwest::{Client, ClientBuilder, NoProxy, Proxy, RequestBuilder};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
-use tracing::{debug, instrument, warn};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, info, info_span, instrument, warn};
 
-use super::parser::{parse_bulk_response, parse_response, CollectResult, JolokiaResponse};
+use super::auth::{AuthProvider, BasicAuth};
+use super::parser::{
+    parse_bulk_response_filtered, parse_response_filtered, AttributeFilter, CollectResult,
+    JolokiaResponse,
+};
+use super::poll::changed_since_history;
 use crate::error::CollectorError;
 
+/// MBean and operation configuring Jolokia's attribute history
+const HISTORY_MBEAN: &str = "jolokia:type=Config";
+const HISTORY_OPERATION: &str = "setHistoryEntriesForAttribute";
+
+/// How long a failed endpoint is only tried after the healthy ones
+const FAILOVER_COOLDOWN: Duration = Duration::from_secs(30);
+
+tokio::task_local! {
+    /// Counters of the enclosing [`measure_traffic`] call
+    static TRAFFIC: Arc<TrafficCounters>;
+}
+
+/// Jolokia traffic caused by a piece of work
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Traffic {
+    /// HTTP requests sent, including retries and failovers
+    pub requests: u64,
+    /// Bytes of response bodies received
+    pub response_bytes: u64,
+}
+
+#[derive(Debug, Default)]
+struct TrafficCounters {
+    requests: AtomicU64,
+    response_bytes: AtomicU64,
+}
+
+impl TrafficCounters {
+    fn snapshot(&self) -> Traffic {
+        Traffic {
+            requests: self.requests.load(Ordering::Relaxed),
+            response_bytes: self.response_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Run `future`, counting the Jolokia requests it sends and the bytes it receives
+///
+/// Only requests made from within `future` are counted, so concurrent
+/// scrapes sharing a client are measured separately.
+pub async fn measure_traffic<F: Future>(future: F) -> (F::Output, Traffic) {
+    let counters = Arc::new(TrafficCounters::default());
+    let output = TRAFFIC.scope(Arc::clone(&counters), future).await;
+    (output, counters.snapshot())
+}
+
+/// Add to the counters of the enclosing [`measure_traffic`] call, if any
+fn record_traffic(requests: u64, response_bytes: u64) {
+    let _ = TRAFFIC.try_with(|counters| {
+        counters.requests.fetch_add(requests, Ordering::Relaxed);
+        counters
+            .response_bytes
+            .fetch_add(response_bytes, Ordering::Relaxed);
+    });
+}
+
+/// Read a response body of at most `limit` bytes
+///
+/// Fails without reading the body if `Content-Length` announces more, and
+/// otherwise as soon as the chunks read so far pass the limit.
+async fn read_limited(mut response: reqwest::Response, limit: u64) -> CollectResult<String> {
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit)
+    {
+        return Err(CollectorError::ResponseTooLarge { limit });
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(CollectorError::HttpResponse)?
+    {
+        record_traffic(0, chunk.len() as u64);
+        if (body.len() + chunk.len()) as u64 > limit {
+            return Err(CollectorError::ResponseTooLarge { limit });
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8(body)
+        .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned()))
+}
+
+/// `User-Agent` sent with every Jolokia request unless configured otherwise
+pub const USER_AGENT: &str = concat!("rjmx-exporter/", env!("CARGO_PKG_VERSION"));
+
 /// Jolokia HTTP client
 #[derive(Clone)]
 pub struct JolokiaClient {
     client: Client,
-    base_url: String,
+    /// Endpoint URLs and their health; shared between clones
+    endpoints: Arc<Endpoints>,
     #[allow(dead_code)]
     default_timeout: Duration,
-    auth: Option<(String, String)>,
+    auth: Option<Arc<dyn AuthProvider>>,
+    http_method: HttpMethod,
+    /// Set once the agent rejected a POST; shared between clones
+    post_rejected: Arc<AtomicBool>,
+    attribute_filter: Option<AttributeFilter>,
+    /// Retry policy of `collect_with_fallback`
+    retry: RetryConfig,
+    /// Maximum MBeans per bulk request (`None` sends one request)
+    bulk_batch_size: Option<usize>,
+    /// Whether the agent returns ObjectNames with sorted keys
+    canonical_naming: bool,
+    /// Largest response body read before the request is aborted
+    max_response_bytes: Option<u64>,
+    /// Headers added to every request, such as cookies of a session gateway
+    headers: HeaderMap,
+}
+
+/// Backoff requested by the agent with `Retry-After`
+#[derive(Debug, Clone, Copy)]
+struct Throttle {
+    status: u16,
+    until: Instant,
+}
+
+/// Current backoff of a client, see [`JolokiaClient::backoff`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    /// Status of the response that requested it (429 or 503)
+    pub status: u16,
+    /// Time left until requests are sent again
+    pub remaining: Duration,
+}
+
+/// Error of a request skipped during `backoff`
+fn throttled(backoff: Backoff) -> CollectorError {
+    CollectorError::Throttled {
+        status: backoff.status,
+        retry_after: backoff.remaining,
+    }
+}
+
+/// Parse a `Retry-After` header value (delay in seconds or an HTTP date)
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(at.duration_since(now).unwrap_or_default())
+}
+
+/// HTTP method used to talk to Jolokia
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpMethod {
+    /// JSON request body (supports bulk reads)
+    #[default]
+    Post,
+    /// Request encoded in the URL path
+    Get,
+}
+
+/// Jolokia agent version, as returned by a `version` request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentVersion {
+    /// Agent version (e.g. `1.7.2`)
+    pub agent: String,
+    /// Jolokia protocol version (e.g. `7.2`)
+    pub protocol: String,
+}
+
+/// Jolokia protocol major versions the exporter supports (Jolokia 1.x and 2.x)
+pub const SUPPORTED_PROTOCOLS: &[u32] = &[7, 8];
+
+impl AgentVersion {
+    /// Whether the agent speaks a supported protocol version
+    pub fn is_supported(&self) -> bool {
+        self.protocol
+            .split('.')
+            .next()
+            .and_then(|major| major.trim().parse::<u32>().ok())
+            .is_some_and(|major| SUPPORTED_PROTOCOLS.contains(&major))
+    }
+}
+
+/// Connection settings of the underlying HTTP client
+///
+/// The defaults suit a handful of scrapes per minute; high-frequency scraping
+/// benefits from a larger pool and, where the agent supports it, HTTP/2.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct HttpClientOptions {
+    /// Idle connections kept open per host
+    pub pool_max_idle_per_host: usize,
+    /// Seconds an idle connection stays in the pool (0 keeps it indefinitely)
+    pub pool_idle_timeout_secs: u64,
+    /// Use HTTP/2 without upgrade or ALPN negotiation
+    pub http2_prior_knowledge: bool,
+    /// TCP keep-alive interval in seconds (0 disables keep-alive probes)
+    pub tcp_keepalive_secs: u64,
+    /// Timeout for establishing a connection, within the request timeout
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connect_timeout_ms: Option<u64>,
+    /// Proxy for all requests, set from `jolokia.proxy_url`
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub proxy: Option<ProxyOptions>,
+}
+
+/// Explicit HTTP proxy of the client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyOptions {
+    /// Proxy URL (e.g. `http://proxy.corp:3128`)
+    pub url: String,
+    /// Basic auth credentials for the proxy
+    pub credentials: Option<(String, String)>,
+}
+
+impl Default for HttpClientOptions {
+    fn default() -> Self {
+        Self {
+            pool_max_idle_per_host: 10,
+            pool_idle_timeout_secs: 30,
+            http2_prior_knowledge: false,
+            tcp_keepalive_secs: 15,
+            connect_timeout_ms: None,
+            proxy: None,
+        }
+    }
+}
+
+/// Endpoint URLs in priority order, with the time each one last failed
+#[derive(Debug)]
+struct Endpoints {
+    urls: Vec<String>,
+    /// Cleared once the endpoint answers again
+    failed_at: Vec<Mutex<Option<Instant>>>,
+    /// Backoff requested by each endpoint with `Retry-After`
+    throttled: Vec<Mutex<Option<Throttle>>>,
+}
+
+impl Endpoints {
+    fn new(urls: Vec<String>) -> Self {
+        let failed_at = urls.iter().map(|_| Mutex::new(None)).collect();
+        let throttled = urls.iter().map(|_| Mutex::new(None)).collect();
+        Self {
+            urls,
+            failed_at,
+            throttled,
+        }
+    }
+
+    /// Backoff of an endpoint, if it has not passed yet
+    fn backoff(&self, index: usize) -> Option<Backoff> {
+        let throttle = (*self.throttled[index].lock().ok()?)?;
+        let remaining = throttle.until.checked_duration_since(Instant::now())?;
+        (!remaining.is_zero()).then_some(Backoff {
+            status: throttle.status,
+            remaining,
+        })
+    }
+
+    /// Enter backoff for `retry_after`, as requested by a `status` response
+    fn start_backoff(&self, index: usize, status: u16, retry_after: Duration) {
+        if let Ok(mut throttle) = self.throttled[index].lock() {
+            warn!(
+                url = %self.urls[index],
+                status,
+                retry_after_seconds = retry_after.as_secs_f64(),
+                "Jolokia agent is throttling requests, backing off"
+            );
+            *throttle = Some(Throttle {
+                status,
+                until: Instant::now() + retry_after,
+            });
+        }
+    }
+
+    /// Leave backoff after a successful response
+    fn end_backoff(&self, index: usize) {
+        if let Ok(mut throttle) = self.throttled[index].lock() {
+            if throttle.take().is_some() {
+                info!(url = %self.urls[index], "Jolokia agent stopped throttling");
+            }
+        }
+    }
+
+    /// Indices to try: healthy endpoints first, both groups in priority order
+    fn order(&self) -> Vec<usize> {
+        let (healthy, cooling): (Vec<usize>, Vec<usize>) =
+            (0..self.urls.len()).partition(|&index| !self.in_cooldown(index));
+        healthy.into_iter().chain(cooling).collect()
+    }
+
+    fn in_cooldown(&self, index: usize) -> bool {
+        self.failed_at[index]
+            .lock()
+            .ok()
+            .and_then(|failed_at| *failed_at)
+            .is_some_and(|at| at.elapsed() < FAILOVER_COOLDOWN)
+    }
+
+    fn mark_failed(&self, index: usize, error: &CollectorError) {
+        if let Ok(mut failed_at) = self.failed_at[index].lock() {
+            if failed_at.is_none() {
+                warn!(url = %self.urls[index], error = %error, "Jolokia endpoint failed, failing over");
+            }
+            *failed_at = Some(Instant::now());
+        }
+    }
+
+    fn mark_ok(&self, index: usize) {
+        if let Ok(mut failed_at) = self.failed_at[index].lock() {
+            if failed_at.take().is_some() {
+                info!(url = %self.urls[index], "Jolokia endpoint recovered");
+            }
+        }
+    }
 }
 
 /// Jolokia request struct
@@ -37,6 +391,24 @@ enum AttributeSpec {
     Multiple(Vec<String>),
 }
 
+impl JolokiaRequest {
+    /// Read request for the given attributes of an MBean
+    ///
+    /// `None` or an empty slice reads all attributes.
+    fn read(mbean: &str, attributes: Option<&[String]>) -> Self {
+        let attribute = match attributes {
+            None | Some([]) => None,
+            Some([attribute]) => Some(AttributeSpec::Single(attribute.clone())),
+            Some(attributes) => Some(AttributeSpec::Multiple(attributes.to_vec())),
+        };
+        Self {
+            request_type: "read".to_string(),
+            mbean: mbean.to_string(),
+            attribute,
+        }
+    }
+}
+
 /// Retry configuration
 #[derive(Debug, Clone)]
 pub struct RetryConfig {
@@ -48,6 +420,34 @@ pub struct RetryConfig {
     pub max_delay: Duration,
     /// Delay multiplier
     pub multiplier: f64,
+    /// Random spread of each delay as a fraction of it (0.2 = +/-20%)
+    pub jitter: f64,
+    /// Upper bound of a backoff requested with `Retry-After`
+    pub max_backoff: Duration,
+}
+
+impl RetryConfig {
+    /// Retry policy that sends every request once
+    pub fn disabled() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Randomize `delay` by up to `jitter` in either direction
+    fn jittered(&self, delay: Duration) -> Duration {
+        let jitter = if self.jitter.is_finite() {
+            self.jitter.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        if jitter == 0.0 {
+            return delay;
+        }
+        let factor = 1.0 + jitter * rand::rng().random_range(-1.0..=1.0);
+        delay.mul_f64(factor)
+    }
 }
 
 impl Default for RetryConfig {
@@ -57,6 +457,8 @@ impl Default for RetryConfig {
             initial_delay: Duration::from_millis(100),
             max_delay: Duration::from_secs(2),
             multiplier: 2.0,
+            jitter: 0.2,
+            max_backoff: Duration::from_secs(300),
         }
     }
 }
@@ -73,27 +475,150 @@ impl JolokiaClient {
     /// let client = JolokiaClient::new("http://localhost:8778/jolokia", 5000)?;
     /// ```
     pub fn new(base_url: &str, timeout_ms: u64) -> CollectResult<Self> {
-        let client = ClientBuilder::new()
+        Self::with_options(base_url, timeout_ms, &HttpClientOptions::default())
+    }
+
+    /// Create a new client with custom connection settings
+    pub fn with_options(
+        base_url: &str,
+        timeout_ms: u64,
+        options: &HttpClientOptions,
+    ) -> CollectResult<Self> {
+        let nonzero_secs = |secs| (secs > 0).then(|| Duration::from_secs(secs));
+        let mut builder = ClientBuilder::new()
             .timeout(Duration::from_millis(timeout_ms))
-            .pool_max_idle_per_host(10)
-            .pool_idle_timeout(Duration::from_secs(30))
-            .build()
-            .map_err(CollectorError::HttpClientInit)?;
+            .user_agent(USER_AGENT)
+            .pool_max_idle_per_host(options.pool_max_idle_per_host)
+            .pool_idle_timeout(nonzero_secs(options.pool_idle_timeout_secs))
+            .tcp_keepalive(nonzero_secs(options.tcp_keepalive_secs));
+        if let Some(connect_timeout_ms) = options.connect_timeout_ms {
+            builder = builder.connect_timeout(Duration::from_millis(connect_timeout_ms));
+        }
+        if options.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        if let Some(proxy) = &options.proxy {
+            let mut explicit = Proxy::all(&proxy.url)
+                .map_err(CollectorError::HttpClientInit)?
+                .no_proxy(NoProxy::from_env());
+            if let Some((username, password)) = &proxy.credentials {
+                explicit = explicit.basic_auth(username, password);
+            }
+            builder = builder.proxy(explicit);
+        }
+        let client = builder.build().map_err(CollectorError::HttpClientInit)?;
 
         Ok(Self {
             client,
-            base_url: base_url.trim_end_matches('/').to_string(),
+            endpoints: Arc::new(Endpoints::new(vec![normalize_url(base_url)])),
             default_timeout: Duration::from_millis(timeout_ms),
             auth: None,
+            http_method: HttpMethod::default(),
+            post_rejected: Arc::new(AtomicBool::new(false)),
+            attribute_filter: None,
+            retry: RetryConfig::disabled(),
+            bulk_batch_size: None,
+            canonical_naming: true,
+            max_response_bytes: None,
+            headers: HeaderMap::new(),
         })
     }
 
     /// Set Basic Auth credentials
-    pub fn with_auth(mut self, username: &str, password: &str) -> Self {
-        self.auth = Some((username.to_string(), password.to_string()));
+    pub fn with_auth(self, username: &str, password: &str) -> Self {
+        self.with_auth_provider(Arc::new(BasicAuth::new(username, password)))
+    }
+
+    /// Authenticate every request with `provider`
+    pub fn with_auth_provider(mut self, provider: Arc<dyn AuthProvider>) -> Self {
+        self.auth = Some(provider);
         self
     }
 
+    /// Add URLs tried in order when the preceding ones fail
+    pub fn with_fallback_urls<S: AsRef<str>>(mut self, urls: &[S]) -> Self {
+        let mut all = self.endpoints.urls.clone();
+        all.extend(urls.iter().map(|url| normalize_url(url.as_ref())));
+        self.endpoints = Arc::new(Endpoints::new(all));
+        self
+    }
+
+    /// Endpoint URLs in priority order
+    pub fn urls(&self) -> &[String] {
+        &self.endpoints.urls
+    }
+
+    /// Backoff requested by the agent, if every endpoint is backing off
+    ///
+    /// The backoff that passes first is returned.
+    pub fn backoff(&self) -> Option<Backoff> {
+        (0..self.endpoints.urls.len())
+            .map(|index| self.endpoints.backoff(index))
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .min_by_key(|backoff| backoff.remaining)
+    }
+
+    /// Set the HTTP method used for requests
+    pub fn with_http_method(mut self, method: HttpMethod) -> Self {
+        self.http_method = method;
+        self
+    }
+
+    /// Retry failed reads of `collect_with_fallback` according to `retry`
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Split bulk reads into concurrent requests of at most `size` MBeans
+    pub fn with_bulk_batch_size(mut self, size: usize) -> Self {
+        self.bulk_batch_size = Some(size.max(1));
+        self
+    }
+
+    /// Ask the agent for ObjectNames with keys in declared instead of sorted order
+    ///
+    /// Sends Jolokia's `canonicalNaming=false` processing parameter with
+    /// every request.
+    pub fn with_canonical_naming(mut self, canonical: bool) -> Self {
+        self.canonical_naming = canonical;
+        self
+    }
+
+    /// Add `headers` to every request, replacing defaults such as `User-Agent`
+    ///
+    /// Credentials of the auth provider are added afterwards and take
+    /// precedence.
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Abort reading responses larger than `bytes`
+    ///
+    /// The body is read chunk by chunk, so an oversized response is dropped
+    /// as soon as it passes the limit rather than buffered in full.
+    pub fn with_max_response_bytes(mut self, bytes: u64) -> Self {
+        self.max_response_bytes = Some(bytes);
+        self
+    }
+
+    /// Skip converting wildcard attributes rejected by `filter`
+    pub fn with_attribute_filter(mut self, filter: AttributeFilter) -> Self {
+        self.attribute_filter = Some(filter);
+        self
+    }
+
+    /// HTTP method currently in use, taking a previous 405 fallback into account
+    pub fn http_method(&self) -> HttpMethod {
+        if self.post_rejected.load(Ordering::Relaxed) {
+            HttpMethod::Get
+        } else {
+            self.http_method
+        }
+    }
+
     /// Read a single MBean
     #[instrument(skip(self), fields(mbean = %mbean))]
     pub async fn read_mbean(
@@ -101,44 +626,21 @@ impl JolokiaClient {
         mbean: &str,
         attributes: Option<&[String]>,
     ) -> CollectResult<JolokiaResponse> {
-        let request = JolokiaRequest {
-            request_type: "read".to_string(),
-            mbean: mbean.to_string(),
-            attribute: attributes.and_then(|attrs| {
-                if attrs.is_empty() {
-                    None // Empty slice means "all attributes" - don't send attribute field
-                } else if attrs.len() == 1 {
-                    Some(AttributeSpec::Single(attrs[0].clone()))
-                } else {
-                    Some(AttributeSpec::Multiple(attrs.to_vec()))
-                }
-            }),
-        };
+        let request = JolokiaRequest::read(mbean, attributes);
 
         debug!("Sending Jolokia read request");
 
-        let mut req = self.client.post(&self.base_url).json(&request);
+        let path = read_path(mbean, attributes);
+        let body = self.execute(&request, &path).await?;
 
-        if let Some((username, password)) = &self.auth {
-            req = req.basic_auth(username, Some(password));
-        }
-
-        let response = req.send().await.map_err(CollectorError::HttpRequest)?;
-
-        let status = response.status();
-        if !status.is_success() {
-            return Err(CollectorError::HttpStatus(status.as_u16()));
-        }
-
-        let body = response
-            .text()
-            .await
-            .map_err(CollectorError::HttpResponse)?;
-
-        parse_response(&body)
+        info_span!("parse")
+            .in_scope(|| parse_response_filtered(&body, self.attribute_filter.as_ref()))
     }
 
     /// Bulk Read - read multiple MBeans in a single request
+    ///
+    /// With a bulk batch size, the MBeans are read in concurrent batches;
+    /// the read fails if any batch fails.
     #[instrument(skip(self, mbeans), fields(count = mbeans.len()))]
     pub async fn read_mbeans_bulk(
         &self,
@@ -148,47 +650,79 @@ impl JolokiaClient {
             return Ok(vec![]);
         }
 
-        let requests: Vec<JolokiaRequest> = mbeans
-            .iter()
-            .map(|(mbean, attrs)| JolokiaRequest {
-                request_type: "read".to_string(),
-                mbean: mbean.to_string(),
-                attribute: attrs.and_then(|a| {
-                    if a.is_empty() {
-                        None // Empty slice means "all attributes" - don't send attribute field
-                    } else if a.len() == 1 {
-                        Some(AttributeSpec::Single(a[0].clone()))
-                    } else {
-                        Some(AttributeSpec::Multiple(a.to_vec()))
-                    }
-                }),
-            })
-            .collect();
-
-        debug!(
-            "Sending Jolokia bulk read request for {} mbeans",
-            requests.len()
-        );
-
-        let mut req = self.client.post(&self.base_url).json(&requests);
+        // The GET protocol has no bulk form: read each MBean separately
+        if self.http_method() == HttpMethod::Get {
+            return self.read_each(mbeans).await;
+        }
 
-        if let Some((username, password)) = &self.auth {
-            req = req.basic_auth(username, Some(password));
+        let batch_size = self.bulk_batch_size.unwrap_or(mbeans.len());
+        let batches = mbeans
+            .chunks(batch_size)
+            .map(|batch| self.read_batch(batch));
+        let mut responses = Vec::with_capacity(mbeans.len());
+        for batch in futures_util::future::join_all(batches).await {
+            responses.extend(batch?);
         }
+        Ok(responses)
+    }
 
-        let response = req.send().await.map_err(CollectorError::HttpRequest)?;
+    /// Read a batch of MBeans in one bulk request
+    ///
+    /// A batch rejected with 413 or 500 is split in half. The halves are
+    /// read one after the other, so an agent failing every request costs
+    /// only a logarithmic number of attempts.
+    fn read_batch<'a>(
+        &'a self,
+        mbeans: &'a [(&'a str, Option<&'a [String]>)],
+    ) -> Pin<Box<dyn Future<Output = CollectResult<Vec<JolokiaResponse>>> + Send + 'a>> {
+        Box::pin(async move {
+            let requests: Vec<JolokiaRequest> = mbeans
+                .iter()
+                .map(|(mbean, attrs)| JolokiaRequest::read(mbean, *attrs))
+                .collect();
 
-        let status = response.status();
-        if !status.is_success() {
-            return Err(CollectorError::HttpStatus(status.as_u16()));
-        }
+            debug!(
+                "Sending Jolokia bulk read request for {} mbeans",
+                requests.len()
+            );
 
-        let body = response
-            .text()
-            .await
-            .map_err(CollectorError::HttpResponse)?;
+            match self.send_post(&requests).await {
+                Ok(body) => info_span!("parse").in_scope(|| {
+                    parse_bulk_response_filtered(&body, self.attribute_filter.as_ref())
+                }),
+                Err(CollectorError::HttpStatus(405)) => {
+                    self.fall_back_to_get();
+                    self.read_each(mbeans).await
+                }
+                Err(CollectorError::HttpStatus(status @ (413 | 500))) if mbeans.len() > 1 => {
+                    warn!(
+                        status,
+                        mbeans = mbeans.len(),
+                        "Jolokia rejected bulk request, splitting it in half"
+                    );
+                    let (first, second) = mbeans.split_at(mbeans.len() / 2);
+                    let mut responses = self.read_batch(first).await?;
+                    responses.extend(self.read_batch(second).await?);
+                    Ok(responses)
+                }
+                Err(e) => Err(e),
+            }
+        })
+    }
 
-        parse_bulk_response(&body)
+    /// Read MBeans one at a time via GET
+    async fn read_each(
+        &self,
+        mbeans: &[(&str, Option<&[String]>)],
+    ) -> CollectResult<Vec<JolokiaResponse>> {
+        let mut responses = Vec::with_capacity(mbeans.len());
+        for (mbean, attrs) in mbeans {
+            let body = self.send_get(&read_path(mbean, *attrs)).await?;
+            let response = info_span!("parse")
+                .in_scope(|| parse_response_filtered(&body, self.attribute_filter.as_ref()))?;
+            responses.push(response);
+        }
+        Ok(responses)
     }
 
     /// Search MBeans by pattern
@@ -206,23 +740,8 @@ impl JolokiaClient {
             mbean: pattern.to_string(),
         };
 
-        let mut req = self.client.post(&self.base_url).json(&request);
-
-        if let Some((username, password)) = &self.auth {
-            req = req.basic_auth(username, Some(password));
-        }
-
-        let response = req.send().await.map_err(CollectorError::HttpRequest)?;
-
-        let status = response.status();
-        if !status.is_success() {
-            return Err(CollectorError::HttpStatus(status.as_u16()));
-        }
-
-        let body = response
-            .text()
-            .await
-            .map_err(CollectorError::HttpResponse)?;
+        let path = format!("search/{}", escape_path_segment(pattern));
+        let body = self.execute(&request, &path).await?;
 
         #[derive(Deserialize)]
         struct SearchResponse {
@@ -243,6 +762,265 @@ impl JolokiaClient {
         Ok(parsed.value)
     }
 
+    /// Keep the last `max_entries` values of an MBean attribute on the agent
+    ///
+    /// Registers the attribute with Jolokia's history store through the
+    /// `setHistoryEntriesForAttribute` operation of `jolokia:type=Config`.
+    /// Later reads of the attribute carry its earlier values in `history`.
+    #[instrument(skip(self))]
+    pub async fn set_history_limit(
+        &self,
+        mbean: &str,
+        attribute: &str,
+        max_entries: u32,
+    ) -> CollectResult<()> {
+        #[derive(Serialize)]
+        struct ExecRequest<'a> {
+            #[serde(rename = "type")]
+            request_type: &'static str,
+            mbean: &'static str,
+            operation: &'static str,
+            arguments: (&'a str, &'a str, Option<&'a str>, Option<&'a str>, u32),
+        }
+
+        #[derive(Deserialize)]
+        struct ExecResponse {
+            status: u16,
+            error: Option<String>,
+        }
+
+        let request = ExecRequest {
+            request_type: "exec",
+            mbean: HISTORY_MBEAN,
+            operation: HISTORY_OPERATION,
+            arguments: (mbean, attribute, None, None, max_entries),
+        };
+        let path = format!(
+            "exec/{}/{}/{}/{}/[null]/[null]/{}",
+            escape_path_segment(HISTORY_MBEAN),
+            HISTORY_OPERATION,
+            escape_path_segment(mbean),
+            escape_path_segment(attribute),
+            max_entries
+        );
+        let body = self.execute(&request, &path).await?;
+
+        let parsed: ExecResponse =
+            serde_json::from_str(&body).map_err(|e| CollectorError::JsonParse(e.to_string()))?;
+        if parsed.status != 200 {
+            return Err(CollectorError::JolokiaError {
+                status: parsed.status,
+                message: parsed
+                    .error
+                    .unwrap_or_else(|| "History registration failed".to_string()),
+            });
+        }
+        Ok(())
+    }
+
+    /// Read a single MBean and compare it with the values in its history
+    ///
+    /// Returns the response and whether any attribute changed since the
+    /// agent's previous read of it: `None` if the response has no history
+    /// for every attribute (see [`Self::set_history_limit`]).
+    #[instrument(skip(self), fields(mbean = %mbean))]
+    pub async fn read_mbean_history(
+        &self,
+        mbean: &str,
+        attributes: &[String],
+    ) -> CollectResult<(JolokiaResponse, Option<bool>)> {
+        let request = JolokiaRequest::read(mbean, Some(attributes));
+        let body = self
+            .execute(&request, &read_path(mbean, Some(attributes)))
+            .await?;
+
+        info_span!("parse").in_scope(|| {
+            let response = parse_response_filtered(&body, self.attribute_filter.as_ref())?;
+            let changed = changed_since_history(&body, attributes);
+            Ok((response, changed))
+        })
+    }
+
+    /// Read the agent version
+    ///
+    /// The cheapest request Jolokia answers, so it doubles as a check of
+    /// connectivity and credentials.
+    #[instrument(skip(self))]
+    pub async fn version(&self) -> CollectResult<AgentVersion> {
+        #[derive(Serialize)]
+        struct VersionRequest {
+            #[serde(rename = "type")]
+            request_type: &'static str,
+        }
+
+        #[derive(Deserialize)]
+        struct VersionResponse {
+            value: Option<AgentVersion>,
+            status: u16,
+            error: Option<String>,
+        }
+
+        let request = VersionRequest {
+            request_type: "version",
+        };
+        let body = self.execute(&request, "version").await?;
+
+        let parsed: VersionResponse =
+            serde_json::from_str(&body).map_err(|e| CollectorError::JsonParse(e.to_string()))?;
+
+        match parsed.value {
+            Some(version) if parsed.status == 200 => Ok(version),
+            _ => Err(CollectorError::JolokiaError {
+                status: parsed.status,
+                message: parsed
+                    .error
+                    .unwrap_or_else(|| "Version request failed".to_string()),
+            }),
+        }
+    }
+
+    /// Send a request using the current HTTP method, returning the response body
+    ///
+    /// `get_path` is the GET-protocol form of `request`, relative to the base URL.
+    /// A POST rejected with 405 is retried once via GET, and later requests
+    /// use GET directly.
+    async fn execute<T: Serialize + ?Sized>(
+        &self,
+        request: &T,
+        get_path: &str,
+    ) -> CollectResult<String> {
+        if self.http_method() == HttpMethod::Get {
+            return self.send_get(get_path).await;
+        }
+
+        match self.send_post(request).await {
+            Err(CollectorError::HttpStatus(405)) => {
+                self.fall_back_to_get();
+                self.send_get(get_path).await
+            }
+            result => result,
+        }
+    }
+
+    /// Switch this client (and its clones) to GET after a 405 response
+    fn fall_back_to_get(&self) {
+        if !self.post_rejected.swap(true, Ordering::Relaxed) {
+            warn!(
+                url = %self.endpoints.urls[0],
+                "Jolokia rejected POST with 405 Method Not Allowed, falling back to GET"
+            );
+        }
+    }
+
+    /// POST a JSON request body
+    async fn send_post<T: Serialize + ?Sized>(&self, request: &T) -> CollectResult<String> {
+        self.send(|base_url| self.processing(self.client.post(base_url).json(request)))
+            .await
+    }
+
+    /// GET a request encoded in the URL path
+    async fn send_get(&self, path: &str) -> CollectResult<String> {
+        self.send(|base_url| {
+            let url = format!("{}/{}", base_url, path);
+            debug!(url = %url, "Sending Jolokia GET request");
+            self.processing(self.client.get(url))
+        })
+        .await
+    }
+
+    /// Add the processing parameters that differ from Jolokia's defaults
+    fn processing(&self, request: RequestBuilder) -> RequestBuilder {
+        if self.canonical_naming {
+            request
+        } else {
+            request.query(&[("canonicalNaming", "false")])
+        }
+    }
+
+    /// Send a request built for an endpoint URL, failing over between endpoints
+    ///
+    /// Only errors that another replica might not have (connection errors,
+    /// timeouts, 429 and 5xx responses) fail over; the last error is returned
+    /// if no endpoint answers. Endpoints backing off are skipped.
+    async fn send(&self, build: impl Fn(&str) -> RequestBuilder) -> CollectResult<String> {
+        let endpoints = &self.endpoints;
+        if endpoints.urls.len() == 1 {
+            if let Some(backoff) = endpoints.backoff(0) {
+                return Err(throttled(backoff));
+            }
+            return self.send_to(0, build(&endpoints.urls[0])).await;
+        }
+
+        let mut last = None;
+        for index in endpoints.order() {
+            if endpoints.backoff(index).is_some() {
+                continue;
+            }
+            let result = self.send_to(index, build(&endpoints.urls[index])).await;
+            match &result {
+                Ok(_) => {
+                    endpoints.mark_ok(index);
+                    return result;
+                }
+                Err(e) if e.is_retryable() => endpoints.mark_failed(index, e),
+                Err(_) => return result,
+            }
+            last = Some(result);
+        }
+        last.unwrap_or_else(|| {
+            Err(self
+                .backoff()
+                .map_or(CollectorError::MaxRetriesExceeded, throttled))
+        })
+    }
+
+    /// Attach credentials, send to endpoint `index`, and read the body of a
+    /// successful response
+    async fn send_to(&self, index: usize, req: RequestBuilder) -> CollectResult<String> {
+        #[cfg(feature = "otlp")]
+        let req = crate::telemetry::inject(req);
+        let mut request = req.build().map_err(CollectorError::HttpRequest)?;
+        request.headers_mut().extend(self.headers.clone());
+        if let Some(auth) = &self.auth {
+            auth.authenticate(&mut request)?;
+        }
+
+        record_traffic(1, 0);
+        let response = self
+            .client
+            .execute(request)
+            .await
+            .map_err(CollectorError::HttpRequest)?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let status = status.as_u16();
+            if matches!(status, 429 | 503) {
+                let retry_after = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| parse_retry_after(value, SystemTime::now()));
+                if let Some(retry_after) = retry_after {
+                    let retry_after = retry_after.min(self.retry.max_backoff);
+                    self.endpoints.start_backoff(index, status, retry_after);
+                }
+            }
+            return Err(CollectorError::HttpStatus(status));
+        }
+        self.endpoints.end_backoff(index);
+
+        let Some(limit) = self.max_response_bytes else {
+            let body = response
+                .text()
+                .await
+                .map_err(CollectorError::HttpResponse)?;
+            record_traffic(0, body.len() as u64);
+            return Ok(body);
+        };
+        read_limited(response, limit).await
+    }
+
     /// Read a single MBean with retry logic
     pub async fn read_mbean_with_retry(
         &self,
@@ -285,13 +1063,23 @@ impl JolokiaClient {
             }
 
             if attempt < config.max_retries {
+                let mut sleep = config.jittered(delay);
+                if let Some(backoff) = self.backoff() {
+                    // Waiting longer would stall the scrape; a later one
+                    // resumes once the backoff has passed
+                    if backoff.remaining > config.max_delay {
+                        break;
+                    }
+                    sleep = sleep.max(backoff.remaining);
+                }
                 warn!(
+                    mbean = %mbean,
                     attempt = attempt + 1,
                     max = config.max_retries,
-                    delay_ms = delay.as_millis() as u64,
+                    delay_ms = sleep.as_millis() as u64,
                     "Request failed, retrying"
                 );
-                tokio::time::sleep(delay).await;
+                tokio::time::sleep(sleep).await;
                 // Safe multiplier: clamp to valid range to prevent panic
                 let safe_multiplier = if config.multiplier.is_finite() && config.multiplier > 0.0 {
                     config.multiplier
@@ -315,6 +1103,8 @@ impl JolokiaClient {
     }
 
     /// Collection with fallback - allows partial failures
+    ///
+    /// Each read is retried according to the policy set with [`Self::with_retry`].
     pub async fn collect_with_fallback(
         &self,
         mbeans: &[String],
@@ -323,7 +1113,9 @@ impl JolokiaClient {
         let mut results = Vec::new();
 
         for mbean in mbeans {
-            let result = self.read_mbean(mbean, attributes).await;
+            let result = self
+                .read_mbean_with_retry(mbean, attributes, &self.retry)
+                .await;
 
             match &result {
                 Ok(response) if response.status == 200 => {
@@ -353,10 +1145,84 @@ impl JolokiaClient {
     }
 }
 
+/// Strip trailing slashes so GET paths can be appended
+fn normalize_url(url: &str) -> String {
+    url.trim_end_matches('/').to_string()
+}
+
+/// Build the GET-protocol path for a read request
+///
+/// Multiple attributes are joined with commas, which Jolokia accepts for
+/// GET reads.
+fn read_path(mbean: &str, attributes: Option<&[String]>) -> String {
+    let mut path = format!("read/{}", escape_path_segment(mbean));
+    if let Some(attrs) = attributes.filter(|a| !a.is_empty()) {
+        let joined = attrs
+            .iter()
+            .map(|a| escape_path_segment(a))
+            .collect::<Vec<_>>()
+            .join(",");
+        path.push('/');
+        path.push_str(&joined);
+    }
+    path
+}
+
+/// Escape a value for use as one segment of a Jolokia GET path
+///
+/// Jolokia's own escaping comes first (`!` -> `!!`, `/` -> `!/`,
+/// `"` -> `!"`), then anything outside a conservative safe set is
+/// percent-encoded. Slashes are never percent-encoded, since many servlet
+/// containers reject `%2F` in paths.
+fn escape_path_segment(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '!' => escaped.push_str("!!"),
+            '/' => escaped.push_str("!/"),
+            '"' => escaped.push_str("!\""),
+            _ => escaped.push(c),
+        }
+    }
+
+    let mut encoded = String::with_capacity(escaped.len());
+    for byte in escaped.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~'
+            | b'!'
+            | b'*'
+            | b':'
+            | b'='
+            | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_agent_version_supported() {
+        let version = |protocol: &str| AgentVersion {
+            agent: "1.7.2".to_string(),
+            protocol: protocol.to_string(),
+        };
+        assert!(version("7.2").is_supported());
+        assert!(version("8.0").is_supported());
+        assert!(!version("6.1").is_supported());
+        assert!(!version("9.0").is_supported());
+        assert!(!version("unknown").is_supported());
+    }
+
     #[test]
     fn test_client_new() {
         let client = JolokiaClient::new("http://localhost:8778/jolokia", 5000);
@@ -371,11 +1237,147 @@ mod tests {
         assert!(client.auth.is_some());
     }
 
+    #[test]
+    fn test_client_with_options() {
+        let options = HttpClientOptions {
+            pool_max_idle_per_host: 100,
+            pool_idle_timeout_secs: 0,
+            http2_prior_knowledge: true,
+            tcp_keepalive_secs: 0,
+            connect_timeout_ms: Some(500),
+            proxy: Some(ProxyOptions {
+                url: "http://proxy.corp:3128".to_string(),
+                credentials: Some(("user".to_string(), "pass".to_string())),
+            }),
+        };
+        let client = JolokiaClient::with_options("http://localhost:8778/jolokia", 5000, &options);
+        assert!(client.is_ok());
+    }
+
+    #[test]
+    fn test_client_fallback_urls() {
+        let client = JolokiaClient::new("http://jolokia-0:8778/jolokia/", 5000)
+            .unwrap()
+            .with_fallback_urls(&["http://jolokia-1:8778/jolokia"]);
+        assert_eq!(
+            client.urls(),
+            [
+                "http://jolokia-0:8778/jolokia",
+                "http://jolokia-1:8778/jolokia"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_endpoint_order() {
+        let endpoints = Endpoints::new(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        assert_eq!(endpoints.order(), vec![0, 1, 2]);
+
+        // A failed endpoint moves behind the healthy ones until it recovers
+        endpoints.mark_failed(0, &CollectorError::HttpStatus(503));
+        assert_eq!(endpoints.order(), vec![1, 2, 0]);
+        endpoints.mark_ok(0);
+        assert_eq!(endpoints.order(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_client_http_method() {
+        let client = JolokiaClient::new("http://localhost:8778/jolokia", 5000).unwrap();
+        assert_eq!(client.http_method(), HttpMethod::Post);
+
+        let client = client.with_http_method(HttpMethod::Get);
+        assert_eq!(client.http_method(), HttpMethod::Get);
+    }
+
+    #[test]
+    fn test_escape_path_segment() {
+        assert_eq!(
+            escape_path_segment("java.lang:type=Memory"),
+            "java.lang:type=Memory"
+        );
+        assert_eq!(escape_path_segment("a/b!c"), "a!/b!!c");
+        assert_eq!(escape_path_segment("say \"hi\""), "say%20!%22hi!%22");
+        assert_eq!(
+            escape_path_segment("java.lang:type=GarbageCollector,name=G1 Young Generation"),
+            "java.lang:type=GarbageCollector%2Cname=G1%20Young%20Generation"
+        );
+        assert_eq!(escape_path_segment("100%"), "100%25");
+    }
+
+    #[test]
+    fn test_read_path() {
+        assert_eq!(
+            read_path("java.lang:type=Memory", None),
+            "read/java.lang:type=Memory"
+        );
+        assert_eq!(
+            read_path("java.lang:type=Memory", Some(&[])),
+            "read/java.lang:type=Memory"
+        );
+        let attrs = vec!["HeapMemoryUsage".to_string(), "Verbose".to_string()];
+        assert_eq!(
+            read_path("java.lang:type=Memory", Some(&attrs)),
+            "read/java.lang:type=Memory/HeapMemoryUsage,Verbose"
+        );
+        let attrs = vec!["a/b".to_string()];
+        assert_eq!(
+            read_path("jboss.web:path=/app", Some(&attrs)),
+            "read/jboss.web:path=!/app/a!/b"
+        );
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_445_412_480);
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        // 2015-10-21 07:28:30 UTC, 30s after `now`
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        // Dates in the past mean "now"
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
     #[test]
     fn test_retry_config_default() {
         let config = RetryConfig::default();
         assert_eq!(config.max_retries, 3);
         assert_eq!(config.initial_delay, Duration::from_millis(100));
+        assert_eq!(RetryConfig::disabled().max_retries, 0);
+    }
+
+    #[test]
+    fn test_retry_jitter() {
+        let delay = Duration::from_millis(1000);
+        let config = RetryConfig {
+            jitter: 0.2,
+            ..RetryConfig::default()
+        };
+        for _ in 0..100 {
+            let jittered = config.jittered(delay);
+            assert!(
+                jittered >= Duration::from_millis(800) && jittered <= Duration::from_millis(1200)
+            );
+        }
+
+        let config = RetryConfig {
+            jitter: 0.0,
+            ..RetryConfig::default()
+        };
+        assert_eq!(config.jittered(delay), delay);
+        let config = RetryConfig {
+            jitter: f64::NAN,
+            ..RetryConfig::default()
+        };
+        assert_eq!(config.jittered(delay), delay);
     }
 
     #[test]
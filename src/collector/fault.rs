@@ -0,0 +1,142 @@
+//! Fault injection for resilience testing
+//!
+//! Only built with the `fault-injection` feature. Injects artificial latency,
+//! request errors, or malformed responses into the collector path, so alerting
+//! and partial-failure handling can be exercised in staging without breaking
+//! the real Jolokia agent.
+
+use std::time::Duration;
+
+use rand::Rng;
+use tracing::debug;
+
+use super::parser::{parse_response, CollectResult, JolokiaResponse};
+use crate::error::CollectorError;
+
+/// Body returned for injected malformed responses
+const MALFORMED_BODY: &str = r#"{"status": 200, "value": {"#;
+
+/// Faults applied to collector reads
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjector {
+    latency: Duration,
+    error_rate: f64,
+    malformed_rate: f64,
+    mbeans: Vec<String>,
+}
+
+impl FaultInjector {
+    /// Create an injector that does nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delay every matching read
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Fail this fraction (0.0-1.0) of matching reads with a connection error
+    pub fn with_error_rate(mut self, rate: f64) -> Self {
+        self.error_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Answer this fraction (0.0-1.0) of matching reads with unparseable JSON
+    pub fn with_malformed_rate(mut self, rate: f64) -> Self {
+        self.malformed_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Restrict faults to MBeans containing one of these substrings (all if empty)
+    pub fn with_mbeans(mut self, mbeans: Vec<String>) -> Self {
+        self.mbeans = mbeans;
+        self
+    }
+
+    /// Check whether faults apply to an MBean
+    pub fn matches(&self, mbean: &str) -> bool {
+        self.mbeans.is_empty() || self.mbeans.iter().any(|m| mbean.contains(m.as_str()))
+    }
+
+    /// Apply faults to a read of `mbean`
+    ///
+    /// Returns `Some` with the injected result if the read should not reach
+    /// Jolokia, or `None` to perform the real read (after any added latency).
+    pub async fn inject(&self, mbean: &str) -> Option<CollectResult<JolokiaResponse>> {
+        if !self.matches(mbean) {
+            return None;
+        }
+
+        if !self.latency.is_zero() {
+            debug!(mbean = %mbean, latency_ms = self.latency.as_millis() as u64, "Injecting latency");
+            tokio::time::sleep(self.latency).await;
+        }
+
+        let (error_roll, malformed_roll) = {
+            let mut rng = rand::rng();
+            (rng.random::<f64>(), rng.random::<f64>())
+        };
+
+        if error_roll < self.error_rate {
+            debug!(mbean = %mbean, "Injecting request error");
+            return Some(Err(CollectorError::ConnectionFailed(
+                "injected fault".to_string(),
+            )));
+        }
+
+        if malformed_roll < self.malformed_rate {
+            debug!(mbean = %mbean, "Injecting malformed response");
+            return Some(parse_response(MALFORMED_BODY));
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let all = FaultInjector::new();
+        assert!(all.matches("java.lang:type=Memory"));
+
+        let kafka = FaultInjector::new().with_mbeans(vec!["kafka.".to_string()]);
+        assert!(kafka.matches("kafka.server:type=BrokerTopicMetrics"));
+        assert!(!kafka.matches("java.lang:type=Memory"));
+    }
+
+    #[tokio::test]
+    async fn test_inject() {
+        let none = FaultInjector::new();
+        assert!(none.inject("java.lang:type=Memory").await.is_none());
+
+        let errors = FaultInjector::new().with_error_rate(1.0);
+        assert!(matches!(
+            errors.inject("java.lang:type=Memory").await,
+            Some(Err(CollectorError::ConnectionFailed(_)))
+        ));
+
+        let malformed = FaultInjector::new().with_malformed_rate(1.0);
+        assert!(matches!(
+            malformed.inject("java.lang:type=Memory").await,
+            Some(Err(CollectorError::JsonParse(_)))
+        ));
+
+        let filtered = FaultInjector::new()
+            .with_error_rate(1.0)
+            .with_mbeans(vec!["kafka.".to_string()]);
+        assert!(filtered.inject("java.lang:type=Memory").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_inject_latency() {
+        let slow = FaultInjector::new().with_latency(Duration::from_millis(20));
+        let start = std::time::Instant::now();
+        assert!(slow.inject("java.lang:type=Memory").await.is_none());
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,405 @@
+//! Typed parsing of well-known JVM MBeans
+//!
+//! Full reads of `java.lang:type=Memory`, `java.lang:type=Threading` and
+//! `java.lang:type=GarbageCollector` have a fixed shape, so they are
+//! deserialized straight into structs instead of being split into raw fields
+//! and converted value by value.
+//!
+//! The structs deny unknown fields and accept only the JSON types the JVM
+//! reports. Any other response (a newer JVM with more attributes, a vendor
+//! extension, a proxy rewriting values) is declined and goes through the
+//! generic parser, so no attribute is ever lost.
+//!
+//! With the `simd-json` feature, responses simd-json accepts do not reach
+//! this path.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Deserializer};
+use serde_json::value::RawValue;
+
+use super::parser::{filter_attribute, AttributeFilter, AttributeValue, MBeanValue, RequestInfo};
+
+/// MBeans with a typed shape
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KnownMBean {
+    Memory,
+    Threading,
+    GarbageCollector,
+}
+
+impl KnownMBean {
+    /// Select the shape of a request, if it is a full read of a known MBean
+    fn of(request: &RequestInfo) -> Option<Self> {
+        if request.attribute.is_some() {
+            return None;
+        }
+        let properties = request.mbean.strip_prefix("java.lang:")?;
+        match properties {
+            "type=Memory" => Some(Self::Memory),
+            "type=Threading" => Some(Self::Threading),
+            _ if properties
+                .split(',')
+                .any(|property| property == "type=GarbageCollector") =>
+            {
+                Some(Self::GarbageCollector)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Parse the value of a known MBean, or `None` to use the generic parser
+pub(super) fn parse_known(
+    request: &RequestInfo,
+    raw: &RawValue,
+    filter: Option<&AttributeFilter>,
+) -> Option<MBeanValue> {
+    let json = raw.get();
+    match KnownMBean::of(request)? {
+        KnownMBean::Memory => serde_json::from_str::<Memory>(json)
+            .ok()
+            .map(|memory| MBeanValue::Composite(memory.into_attributes())),
+        KnownMBean::Threading => serde_json::from_str::<Threading>(json)
+            .ok()
+            .map(|threading| MBeanValue::Composite(threading.into_attributes())),
+        KnownMBean::GarbageCollector if is_pattern(&request.mbean) => {
+            let collectors: HashMap<String, GarbageCollector> = serde_json::from_str(json).ok()?;
+            if collectors.is_empty() {
+                return Some(MBeanValue::Composite(HashMap::new()));
+            }
+            let wildcard = collectors
+                .into_iter()
+                .map(|(mbean, collector)| {
+                    let mut attributes = collector.into_attributes();
+                    if filter.is_some() {
+                        attributes = attributes
+                            .into_iter()
+                            .filter_map(|(name, value)| {
+                                let value = filter_attribute(&mbean, &name, value, filter)?;
+                                Some((name, value))
+                            })
+                            .collect();
+                    }
+                    (mbean, attributes)
+                })
+                .collect();
+            Some(MBeanValue::Wildcard(wildcard))
+        }
+        KnownMBean::GarbageCollector => serde_json::from_str::<GarbageCollector>(json)
+            .ok()
+            .map(|collector| MBeanValue::Composite(collector.into_attributes())),
+    }
+}
+
+/// Check whether an ObjectName is a pattern, which Jolokia answers per MBean
+fn is_pattern(mbean: &str) -> bool {
+    mbean.contains(['*', '?'])
+}
+
+/// Conversion of typed attribute values
+trait IntoAttribute {
+    fn into_attribute(self) -> AttributeValue;
+}
+
+impl IntoAttribute for i64 {
+    fn into_attribute(self) -> AttributeValue {
+        AttributeValue::Integer(self)
+    }
+}
+
+impl IntoAttribute for bool {
+    fn into_attribute(self) -> AttributeValue {
+        AttributeValue::Boolean(self)
+    }
+}
+
+impl IntoAttribute for String {
+    fn into_attribute(self) -> AttributeValue {
+        AttributeValue::String(self)
+    }
+}
+
+impl IntoAttribute for AttributeValue {
+    fn into_attribute(self) -> AttributeValue {
+        self
+    }
+}
+
+impl<T: IntoAttribute> IntoAttribute for Vec<T> {
+    fn into_attribute(self) -> AttributeValue {
+        AttributeValue::Array(
+            self.into_iter()
+                .map(IntoAttribute::into_attribute)
+                .collect(),
+        )
+    }
+}
+
+/// Keep an explicit `null` apart from a missing attribute
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Define a typed MBean: one optional field per attribute
+macro_rules! known_mbean {
+    ($name:ident { $($field:ident: $ty:ty => $attribute:literal,)* }) => {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct $name {
+            $(
+                #[serde(rename = $attribute, default, deserialize_with = "present")]
+                $field: Option<Option<$ty>>,
+            )*
+        }
+
+        impl $name {
+            /// Attributes present in the response, by name
+            fn into_attributes(self) -> HashMap<String, AttributeValue> {
+                let attributes = [$(
+                    (
+                        $attribute,
+                        self.$field
+                            .map(|value| value.map_or(AttributeValue::Null, IntoAttribute::into_attribute)),
+                    ),
+                )*];
+                let mut map = HashMap::with_capacity(
+                    attributes.iter().filter(|(_, value)| value.is_some()).count(),
+                );
+                map.extend(
+                    attributes
+                        .into_iter()
+                        .filter_map(|(name, value)| Some((name.to_string(), value?))),
+                );
+                map
+            }
+        }
+
+    };
+}
+
+/// `java.lang.management.MemoryUsage`, always reported in full
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MemoryUsage {
+    init: i64,
+    committed: i64,
+    max: i64,
+    used: i64,
+}
+
+impl IntoAttribute for MemoryUsage {
+    fn into_attribute(self) -> AttributeValue {
+        AttributeValue::Object(HashMap::from([
+            ("init".to_string(), AttributeValue::Integer(self.init)),
+            (
+                "committed".to_string(),
+                AttributeValue::Integer(self.committed),
+            ),
+            ("max".to_string(), AttributeValue::Integer(self.max)),
+            ("used".to_string(), AttributeValue::Integer(self.used)),
+        ]))
+    }
+}
+
+known_mbean!(Memory {
+    heap_memory_usage: MemoryUsage => "HeapMemoryUsage",
+    non_heap_memory_usage: MemoryUsage => "NonHeapMemoryUsage",
+    object_pending_finalization_count: i64 => "ObjectPendingFinalizationCount",
+    verbose: bool => "Verbose",
+    object_name: AttributeValue => "ObjectName",
+});
+
+known_mbean!(Threading {
+    thread_count: i64 => "ThreadCount",
+    peak_thread_count: i64 => "PeakThreadCount",
+    daemon_thread_count: i64 => "DaemonThreadCount",
+    total_started_thread_count: i64 => "TotalStartedThreadCount",
+    current_thread_cpu_time: i64 => "CurrentThreadCpuTime",
+    current_thread_user_time: i64 => "CurrentThreadUserTime",
+    current_thread_allocated_bytes: i64 => "CurrentThreadAllocatedBytes",
+    total_thread_allocated_bytes: i64 => "TotalThreadAllocatedBytes",
+    all_thread_ids: Vec<i64> => "AllThreadIds",
+    current_thread_cpu_time_supported: bool => "CurrentThreadCpuTimeSupported",
+    object_monitor_usage_supported: bool => "ObjectMonitorUsageSupported",
+    synchronizer_usage_supported: bool => "SynchronizerUsageSupported",
+    thread_allocated_memory_enabled: bool => "ThreadAllocatedMemoryEnabled",
+    thread_allocated_memory_supported: bool => "ThreadAllocatedMemorySupported",
+    thread_contention_monitoring_enabled: bool => "ThreadContentionMonitoringEnabled",
+    thread_contention_monitoring_supported: bool => "ThreadContentionMonitoringSupported",
+    thread_cpu_time_enabled: bool => "ThreadCpuTimeEnabled",
+    thread_cpu_time_supported: bool => "ThreadCpuTimeSupported",
+    object_name: AttributeValue => "ObjectName",
+});
+
+known_mbean!(GarbageCollector {
+    collection_count: i64 => "CollectionCount",
+    collection_time: i64 => "CollectionTime",
+    name: String => "Name",
+    valid: bool => "Valid",
+    memory_pool_names: Vec<String> => "MemoryPoolNames",
+    last_gc_info: AttributeValue => "LastGcInfo",
+    object_name: AttributeValue => "ObjectName",
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(mbean: &str, attribute: Option<&str>) -> RequestInfo {
+        RequestInfo {
+            mbean: mbean.to_string(),
+            attribute: attribute.map(Into::into),
+            request_type: "read".to_string(),
+        }
+    }
+
+    fn raw(json: &str) -> Box<RawValue> {
+        RawValue::from_string(json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_known_mbean_selection() {
+        let cases = [
+            ("java.lang:type=Memory", None, Some(KnownMBean::Memory)),
+            (
+                "java.lang:type=Threading",
+                None,
+                Some(KnownMBean::Threading),
+            ),
+            (
+                "java.lang:type=GarbageCollector,*",
+                None,
+                Some(KnownMBean::GarbageCollector),
+            ),
+            (
+                "java.lang:name=G1 Young Generation,type=GarbageCollector",
+                None,
+                Some(KnownMBean::GarbageCollector),
+            ),
+            ("java.lang:type=Memory", Some("HeapMemoryUsage"), None),
+            ("java.lang:type=MemoryPool,name=Metaspace", None, None),
+            ("com.example:type=Memory", None, None),
+        ];
+        for (mbean, attribute, expected) in cases {
+            assert_eq!(
+                KnownMBean::of(&request(mbean, attribute)),
+                expected,
+                "{}",
+                mbean
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        let value = parse_known(
+            &request("java.lang:type=Memory", None),
+            &raw(r#"{
+                "HeapMemoryUsage": {"init": 1, "committed": 2, "max": -1, "used": 3},
+                "ObjectPendingFinalizationCount": 0,
+                "Verbose": false,
+                "ObjectName": null
+            }"#),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            value.get_composite_number("ObjectPendingFinalizationCount"),
+            Some(0.0)
+        );
+        let MBeanValue::Composite(map) = value else {
+            panic!("expected composite");
+        };
+        assert_eq!(map.len(), 4);
+        assert!(matches!(map["ObjectName"], AttributeValue::Null));
+        let AttributeValue::Object(heap) = &map["HeapMemoryUsage"] else {
+            panic!("expected object");
+        };
+        assert!(matches!(heap["max"], AttributeValue::Integer(-1)));
+        assert!(!map.contains_key("NonHeapMemoryUsage"));
+    }
+
+    #[test]
+    fn test_parse_threading() {
+        let value = parse_known(
+            &request("java.lang:type=Threading", None),
+            &raw(r#"{
+                "ThreadCpuTimeEnabled": true,
+                "DaemonThreadCount": 18,
+                "ThreadCount": 22,
+                "AllThreadIds": [1, 2, 12],
+                "ObjectName": {"objectName": "java.lang:type=Threading"}
+            }"#),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(value.get_composite_number("ThreadCount"), Some(22.0));
+        let MBeanValue::Composite(map) = value else {
+            panic!("expected composite");
+        };
+        assert_eq!(map.len(), 5);
+        assert!(matches!(&map["AllThreadIds"], AttributeValue::Array(ids) if ids.len() == 3));
+        assert!(matches!(map["ObjectName"], AttributeValue::Object(_)));
+    }
+
+    #[test]
+    fn test_parse_gc_wildcard_filtered() {
+        let json = r#"{
+            "java.lang:name=G1 Young Generation,type=GarbageCollector": {
+                "CollectionCount": 10,
+                "CollectionTime": 120,
+                "Name": "G1 Young Generation",
+                "MemoryPoolNames": ["G1 Eden Space", "G1 Survivor Space"],
+                "LastGcInfo": {"duration": 5, "id": 3}
+            }
+        }"#;
+        let filter = AttributeFilter::new(|_, attribute| {
+            attribute == "CollectionCount" || attribute == "LastGcInfo<duration>"
+        });
+
+        let value = parse_known(
+            &request("java.lang:type=GarbageCollector,*", None),
+            &raw(json),
+            Some(&filter),
+        )
+        .unwrap();
+        let MBeanValue::Wildcard(mbeans) = value else {
+            panic!("expected wildcard");
+        };
+        let attrs = &mbeans["java.lang:name=G1 Young Generation,type=GarbageCollector"];
+        assert_eq!(attrs.len(), 2);
+        assert!(matches!(
+            attrs["CollectionCount"],
+            AttributeValue::Integer(10)
+        ));
+        let AttributeValue::Object(gc_info) = &attrs["LastGcInfo"] else {
+            panic!("expected object");
+        };
+        assert_eq!(gc_info.len(), 1);
+    }
+
+    #[test]
+    fn test_unexpected_shape_declined() {
+        let memory = request("java.lang:type=Memory", None);
+        // Unknown attribute
+        assert!(parse_known(&memory, &raw(r#"{"Verbose": false, "Extra": 1}"#), None).is_none());
+        // Fractional count
+        assert!(parse_known(
+            &memory,
+            &raw(r#"{"ObjectPendingFinalizationCount": 1.5}"#),
+            None
+        )
+        .is_none());
+        // Missing usage field
+        assert!(parse_known(&memory, &raw(r#"{"HeapMemoryUsage": {"used": 1}}"#), None).is_none());
+        // Not a known MBean
+        assert!(parse_known(&request("a:type=b", None), &raw("{}"), None).is_none());
+    }
+}
@@ -11,22 +11,156 @@
 //! let response = client.read_mbean("java.lang:type=Memory", None).await?;
 //! ```
 
+mod attributes;
+mod auth;
 mod client;
+#[cfg(feature = "fault-injection")]
+mod fault;
+mod known;
 mod parser;
+mod poll;
+mod schedule;
+mod search;
+mod selection;
+#[cfg(feature = "simd-json")]
+mod simd;
 
-pub use client::{JolokiaClient, RetryConfig};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use tracing::{debug, warn};
+
+pub use attributes::AttributePatterns;
+pub use auth::{AuthProvider, AwsSigV4Auth, BasicAuth, BearerAuth, HeaderAuth};
+pub use client::{
+    measure_traffic, AgentVersion, Backoff, HttpClientOptions, HttpMethod, JolokiaClient,
+    ProxyOptions, RetryConfig, Traffic, SUPPORTED_PROTOCOLS, USER_AGENT,
+};
+#[cfg(feature = "fault-injection")]
+pub use fault::FaultInjector;
 pub use parser::{
-    parse_bulk_response, parse_response, AttributeValue, CollectResult, JolokiaResponse,
-    MBeanValue, ObjectName, RequestInfo,
+    parse_bulk_response, parse_bulk_response_filtered, parse_response, parse_response_filtered,
+    AttributeFilter, AttributeValue, CollectResult, JolokiaResponse, MBeanError, MBeanValue,
+    ObjectName, RequestInfo,
 };
+pub use schedule::{format_rfc3339, CronSchedule, QuietWindow};
+pub use search::{is_excluded, is_pattern, object_name_matches, search_pattern};
+pub use selection::MBeanSelection;
+
+use poll::PollCache;
+
+/// Default MBeans to collect when nothing is configured
+pub const DEFAULT_MBEANS: &[&str] = &[
+    "java.lang:type=Memory",
+    "java.lang:type=Threading",
+    "java.lang:type=ClassLoading",
+    "java.lang:type=OperatingSystem",
+    "java.lang:type=Runtime",
+    "java.lang:type=GarbageCollector,*",
+];
+
+/// A single MBean query: an ObjectName (or pattern) and optional attributes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MBeanQuery {
+    /// MBean ObjectName or ObjectName pattern
+    pub object_name: String,
+    /// Specific attributes to query (None for all attributes)
+    pub attributes: Option<Vec<String>>,
+    /// Daily windows during which the query is skipped
+    pub quiet_hours: Vec<QuietWindow>,
+    /// Resolve the pattern with a Jolokia search and read only the matches
+    pub search: bool,
+    /// Patterns of MBeans dropped from the search results
+    pub exclude: Vec<String>,
+    /// Attributes collected, applied before transformation
+    pub attribute_patterns: AttributePatterns,
+    /// Minimum time between reads; the last responses are replayed in between
+    pub poll_interval: Option<Duration>,
+    /// Register the attributes with the agent's history store and replay
+    /// the last responses only while they are unchanged
+    pub history: bool,
+}
+
+impl MBeanQuery {
+    /// Create a query for all attributes of an MBean
+    pub fn new(object_name: impl Into<String>) -> Self {
+        Self {
+            object_name: object_name.into(),
+            attributes: None,
+            quiet_hours: Vec::new(),
+            search: false,
+            exclude: Vec::new(),
+            attribute_patterns: AttributePatterns::default(),
+            poll_interval: None,
+            history: false,
+        }
+    }
+
+    /// Restrict the query to specific attributes
+    pub fn with_attributes(mut self, attributes: Vec<String>) -> Self {
+        self.attributes = Some(attributes);
+        self
+    }
+
+    /// Set the daily windows during which the query is skipped
+    pub fn with_quiet_hours(mut self, quiet_hours: Vec<QuietWindow>) -> Self {
+        self.quiet_hours = quiet_hours;
+        self
+    }
+
+    /// Resolve the pattern with a search before reading, skipping MBeans
+    /// matched by `exclude`
+    pub fn with_search(mut self, exclude: Vec<String>) -> Self {
+        self.search = true;
+        self.exclude = exclude;
+        self
+    }
+
+    /// Collect only the attributes accepted by `patterns`
+    pub fn with_attribute_patterns(mut self, patterns: AttributePatterns) -> Self {
+        self.attribute_patterns = patterns;
+        self
+    }
+
+    /// Read the query at most once per `interval`, replaying the last
+    /// responses in between
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = Some(interval);
+        self
+    }
+
+    /// Track the listed attributes in Jolokia's history, so the polled
+    /// responses are only replayed while the agent reports no change
+    pub fn with_history(mut self) -> Self {
+        self.history = true;
+        self
+    }
+
+    /// Apply the attribute patterns to an explicit attribute list
+    ///
+    /// Returns `None` if none of the listed attributes is left to read.
+    fn narrowed(mut self) -> Option<Self> {
+        if let Some(attributes) = self.attributes.as_mut().filter(|a| !a.is_empty()) {
+            attributes.retain(|a| self.attribute_patterns.accepts(a));
+            if attributes.is_empty() {
+                debug!(mbean = %self.object_name, "Skipping MBean with all attributes excluded");
+                return None;
+            }
+        }
+        Some(self)
+    }
+
+    /// Check whether the query is inside one of its quiet windows
+    pub fn is_quiet_at(&self, time: SystemTime) -> bool {
+        self.quiet_hours.iter().any(|w| w.contains(time))
+    }
+}
 
 /// MBean collection configuration
 #[derive(Debug, Clone)]
 pub struct CollectConfig {
-    /// List of MBean ObjectNames to query
-    pub mbeans: Vec<String>,
-    /// Specific attributes to query (None for all attributes)
-    pub attributes: Option<Vec<String>>,
+    /// MBean queries to run on each collection
+    pub mbeans: Vec<MBeanQuery>,
     /// Request timeout in milliseconds
     pub timeout_ms: u64,
 }
@@ -35,7 +169,6 @@ impl Default for CollectConfig {
     fn default() -> Self {
         Self {
             mbeans: vec![],
-            attributes: None,
             timeout_ms: 5000,
         }
     }
@@ -43,22 +176,231 @@ impl Default for CollectConfig {
 
 /// Collector struct - configuration-based collection wrapper
 pub struct Collector {
-    client: JolokiaClient,
+    client: Arc<JolokiaClient>,
     config: CollectConfig,
+    polled: PollCache,
+    #[cfg(feature = "fault-injection")]
+    faults: Option<FaultInjector>,
 }
 
 impl Collector {
     /// Create a new Collector
     pub fn new(base_url: &str, config: CollectConfig) -> CollectResult<Self> {
         let client = JolokiaClient::new(base_url, config.timeout_ms)?;
-        Ok(Self { client, config })
+        Ok(Self::with_client(Arc::new(client), config))
+    }
+
+    /// Create a Collector sharing an existing client
+    pub fn with_client(client: Arc<JolokiaClient>, config: CollectConfig) -> Self {
+        Self {
+            client,
+            config,
+            polled: PollCache::default(),
+            #[cfg(feature = "fault-injection")]
+            faults: None,
+        }
     }
 
-    /// Collect configured MBeans
+    /// Inject faults into every read (resilience testing only)
+    #[cfg(feature = "fault-injection")]
+    pub fn with_fault_injector(mut self, faults: FaultInjector) -> Self {
+        self.faults = Some(faults);
+        self
+    }
+
+    /// Collect configured MBeans, allowing partial failures
     pub async fn collect(&self) -> Vec<(String, CollectResult<JolokiaResponse>)> {
-        self.client
-            .collect_with_fallback(&self.config.mbeans, self.config.attributes.as_deref())
+        self.collect_filtered(|query| Some(query.clone())).await
+    }
+
+    /// Collect configured MBeans after passing each query through `plan`
+    ///
+    /// Queries inside a quiet window are skipped. `plan` may narrow the
+    /// remaining queries (e.g. drop attributes served from a cache) or
+    /// return `None` to skip them for this collection. Queries with a poll
+    /// interval replay their last successful read until the interval ends;
+    /// with history, only if the agent reported no change on that read.
+    pub async fn collect_filtered<F>(
+        &self,
+        plan: F,
+    ) -> Vec<(String, CollectResult<JolokiaResponse>)>
+    where
+        F: Fn(&MBeanQuery) -> Option<MBeanQuery>,
+    {
+        let mut results = Vec::with_capacity(self.config.mbeans.len());
+        let now = SystemTime::now();
+
+        let active = self.config.mbeans.iter().filter(|query| {
+            let quiet = query.is_quiet_at(now);
+            if quiet {
+                debug!(mbean = %query.object_name, "Skipping MBean during quiet hours");
+            }
+            !quiet
+        });
+
+        for query in active.filter_map(&plan).filter_map(MBeanQuery::narrowed) {
+            #[cfg(feature = "fault-injection")]
+            if let Some(faults) = &self.faults {
+                if let Some(result) = faults.inject(&query.object_name).await {
+                    results.push((query.object_name.clone(), result));
+                    continue;
+                }
+            }
+
+            if let Some(interval) = query.poll_interval {
+                if let Some(replayed) = self.polled.replay(&query.object_name, interval) {
+                    debug!(mbean = %query.object_name, "Replaying polled MBean");
+                    results.extend(replayed.into_iter().map(|(mbean, r)| (mbean, Ok(r))));
+                    continue;
+                }
+            }
+
+            let start = results.len();
+            let mut changed = None;
+            if query.search {
+                results.extend(self.collect_search(&query).await);
+            } else if query.history {
+                let (result, history_changed) = self.read_history(&query).await;
+                changed = history_changed;
+                results.push((query.object_name.clone(), result));
+            } else {
+                results.extend(
+                    self.client
+                        .collect_with_fallback(
+                            std::slice::from_ref(&query.object_name),
+                            query.attributes.as_deref(),
+                        )
+                        .await,
+                );
+            }
+            for (_, result) in &mut results[start..] {
+                if let Ok(response) = result {
+                    query.attribute_patterns.retain(response);
+                }
+            }
+            if query.poll_interval.is_some() {
+                if query.history && changed != Some(false) {
+                    debug!(mbean = %query.object_name, "MBean changed since its last read");
+                    self.polled.remove(&query.object_name);
+                } else {
+                    self.store_polled(&query.object_name, &results[start..]);
+                }
+            }
+        }
+
+        results
+    }
+
+    /// Read a query with history, registering its attributes first if needed
+    ///
+    /// Returns the read and whether the agent saw any attribute change since
+    /// its previous read, if the response carried the history.
+    async fn read_history(
+        &self,
+        query: &MBeanQuery,
+    ) -> (CollectResult<JolokiaResponse>, Option<bool>) {
+        let attributes = query.attributes.as_deref().unwrap_or_default();
+        if self.polled.needs_history(&query.object_name) {
+            let mut registered = true;
+            for attribute in attributes {
+                // One entry is enough to compare with the previous read
+                if let Err(e) = self
+                    .client
+                    .set_history_limit(&query.object_name, attribute, 1)
+                    .await
+                {
+                    warn!(
+                        mbean = %query.object_name,
+                        attribute = %attribute,
+                        error = %e,
+                        "Failed to register attribute history"
+                    );
+                    registered = false;
+                    break;
+                }
+            }
+            if registered {
+                self.polled.history_registered(&query.object_name);
+            }
+        }
+
+        match self
+            .client
+            .read_mbean_history(&query.object_name, attributes)
             .await
+        {
+            Ok((response, changed)) => {
+                if response.status == 200 {
+                    self.polled
+                        .history_read(&query.object_name, changed.is_some());
+                }
+                (Ok(response), changed)
+            }
+            Err(e) => {
+                tracing::error!(
+                    mbean = %query.object_name,
+                    error = %e,
+                    "Failed to collect MBean"
+                );
+                (Err(e), None)
+            }
+        }
+    }
+
+    /// Keep the responses of a polled query for replay
+    ///
+    /// A read with any failure is not kept, so the query is read again on the
+    /// next collection.
+    fn store_polled(
+        &self,
+        object_name: &str,
+        results: &[(String, CollectResult<JolokiaResponse>)],
+    ) {
+        let responses: Option<Vec<(String, JolokiaResponse)>> = results
+            .iter()
+            .map(|(mbean, result)| match result {
+                Ok(response) if response.status == 200 => Some((mbean.clone(), response.clone())),
+                _ => None,
+            })
+            .collect();
+        match responses {
+            Some(responses) => self.polled.store(object_name, responses),
+            None => self.polled.remove(object_name),
+        }
+    }
+
+    /// Search the MBeans matching a query and read them in one request
+    ///
+    /// A failed search or bulk read is reported once, under the pattern.
+    async fn collect_search(
+        &self,
+        query: &MBeanQuery,
+    ) -> Vec<(String, CollectResult<JolokiaResponse>)> {
+        let names = match self.client.search_mbeans(&query.object_name).await {
+            Ok(names) => names,
+            Err(e) => return vec![(query.object_name.clone(), Err(e))],
+        };
+        let names: Vec<String> = names
+            .into_iter()
+            .filter(|name| !query.exclude.iter().any(|p| is_excluded(p, name)))
+            .collect();
+        debug!(
+            pattern = %query.object_name,
+            matched = names.len(),
+            "Resolved MBean pattern"
+        );
+
+        let reads: Vec<(&str, Option<&[String]>)> = names
+            .iter()
+            .map(|name| (name.as_str(), query.attributes.as_deref()))
+            .collect();
+        match self.client.read_mbeans_bulk(&reads).await {
+            Ok(responses) => responses
+                .into_iter()
+                .map(|response| (response.request.mbean.clone(), Ok(response)))
+                .collect(),
+            Err(e) => vec![(query.object_name.clone(), Err(e))],
+        }
     }
 
     /// Bulk collection (single HTTP request)
@@ -67,12 +409,17 @@ impl Collector {
             .config
             .mbeans
             .iter()
-            .map(|m| (m.as_str(), self.config.attributes.as_deref()))
+            .map(|q| (q.object_name.as_str(), q.attributes.as_deref()))
             .collect();
 
         self.client.read_mbeans_bulk(&mbeans).await
     }
 
+    /// Return the collection configuration
+    pub fn config(&self) -> &CollectConfig {
+        &self.config
+    }
+
     /// Return reference to client
     pub fn client(&self) -> &JolokiaClient {
         &self.client
@@ -1,11 +1,25 @@
 //! Jolokia JSON response parser
 //!
 //! Parses Jolokia API responses and converts them to internal data structures.
+//!
+//! Values are kept as borrowed slices of the response body until they are
+//! needed, and object keys are borrowed unless they contain escapes. With the
+//! `simd-json` feature, responses are first parsed with simd-json into a
+//! borrowed tree; anything it rejects is parsed again with serde_json.
+//! Full reads of well-known JVM MBeans are deserialized into typed structs
+//! (see `known`).
 
+use indexmap::IndexMap;
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde::Deserialize;
+use serde_json::value::RawValue;
 use serde_json::Value;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
 
+use super::known;
 use crate::error::CollectorError;
 
 /// Collector operation result type
@@ -26,6 +40,25 @@ pub struct JolokiaResponse {
     pub error: Option<String>,
     /// Error type (on failure)
     pub error_type: Option<String>,
+    /// MBeans of a wildcard read that Jolokia reported an error for instead
+    /// of their attributes; they are left out of `value`
+    pub mbean_errors: Vec<MBeanError>,
+}
+
+/// Error reported for one MBean of a successful wildcard read
+///
+/// Jolokia answers such MBeans with `{"error": ..., "error_type": ...,
+/// "status": ...}` in place of their attributes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MBeanError {
+    /// Concrete MBean name
+    pub mbean: String,
+    /// Error message
+    pub error: String,
+    /// Java exception class, if reported
+    pub error_type: Option<String>,
+    /// Status of the MBean's read, if reported
+    pub status: Option<u16>,
 }
 
 /// Request information
@@ -102,6 +135,30 @@ impl AttributeValue {
         }
     }
 
+    /// Path segment and value of the array element at `index`
+    ///
+    /// Jolokia serializes TabularData without a single index column as an
+    /// array of `{"key": ..., "value": ...}` rows; such rows are named by
+    /// their key and stand for their value. Other elements are named by
+    /// their index.
+    pub fn array_element(&self, index: usize) -> (String, &AttributeValue) {
+        match self.table_row() {
+            Some((key, value)) => (key, value),
+            None => (index.to_string(), self),
+        }
+    }
+
+    /// Key and value of a TabularData row
+    fn table_row(&self) -> Option<(String, &AttributeValue)> {
+        let AttributeValue::Object(fields) = self else {
+            return None;
+        };
+        if fields.len() != 2 {
+            return None;
+        }
+        Some((fields.get("key")?.as_string()?, fields.get("value")?))
+    }
+
     /// Convert to string
     pub fn as_string(&self) -> Option<String> {
         match self {
@@ -114,30 +171,130 @@ impl AttributeValue {
     }
 }
 
+/// Decides which attributes of a wildcard read are converted
+///
+/// Called with the concrete MBean name and an attribute path: the attribute
+/// name for scalar values, or `Attribute<key>` for each key of a composite,
+/// extended with `<key>` or `<element>` for each level of nested composites
+/// and arrays. Rejected scalar attributes and keys are skipped without being
+/// converted, so large wildcard reads only pay for the values that are
+/// actually used.
+#[derive(Clone)]
+pub struct AttributeFilter {
+    accepts: Arc<AttributePredicate>,
+    /// Told about rejected numeric values, which are converted only for it
+    on_rejected: Option<Arc<RejectedHook>>,
+}
+
+/// Predicate over (MBean, attribute path)
+type AttributePredicate = dyn Fn(&str, &str) -> bool + Send + Sync;
+
+/// Callback with the (MBean, attribute path) of a rejected numeric value
+type RejectedHook = dyn Fn(&str, &str) + Send + Sync;
+
+impl AttributeFilter {
+    /// Create a filter from a predicate over (MBean, attribute path)
+    pub fn new(filter: impl Fn(&str, &str) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            accepts: Arc::new(filter),
+            on_rejected: None,
+        }
+    }
+
+    /// Report the numeric values the filter rejects to `hook`
+    ///
+    /// Rejected values are then converted to tell numbers from other
+    /// values, which costs part of what the filter saves.
+    pub fn with_rejected_hook(mut self, hook: impl Fn(&str, &str) + Send + Sync + 'static) -> Self {
+        self.on_rejected = Some(Arc::new(hook));
+        self
+    }
+
+    /// Check whether an attribute path of an MBean should be converted
+    pub fn accepts(&self, mbean: &str, attribute: &str) -> bool {
+        (self.accepts)(mbean, attribute)
+    }
+
+    /// Check whether a scalar value at an attribute path should be converted
+    ///
+    /// `value` converts it, and is only called for rejected values when a
+    /// hook is set.
+    pub fn accepts_value(
+        &self,
+        mbean: &str,
+        attribute: &str,
+        value: impl FnOnce() -> AttributeValue,
+    ) -> bool {
+        if self.accepts(mbean, attribute) {
+            return true;
+        }
+        if let Some(hook) = &self.on_rejected {
+            if value().as_f64().is_some() {
+                hook(mbean, attribute);
+            }
+        }
+        false
+    }
+}
+
+impl fmt::Debug for AttributeFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AttributeFilter")
+    }
+}
+
 /// Parse a single response
 pub fn parse_response(json: &str) -> CollectResult<JolokiaResponse> {
-    let raw: RawJolokiaResponse =
-        serde_json::from_str(json).map_err(|e| CollectorError::JsonParse(e.to_string()))?;
+    parse_response_filtered(json, None)
+}
+
+/// Parse a single response, converting only wildcard attributes accepted by `filter`
+pub fn parse_response_filtered(
+    json: &str,
+    filter: Option<&AttributeFilter>,
+) -> CollectResult<JolokiaResponse> {
+    #[cfg(feature = "simd-json")]
+    if let Some(response) = super::simd::parse_response(json, filter) {
+        return Ok(response);
+    }
+
+    let raw: RawJolokiaResponse = serde_json::from_str(json).map_err(json_error)?;
 
-    convert_raw_response(raw)
+    convert_raw_response(raw, filter)
 }
 
 /// Parse bulk response
 pub fn parse_bulk_response(json: &str) -> CollectResult<Vec<JolokiaResponse>> {
-    let raw_responses: Vec<RawJolokiaResponse> =
-        serde_json::from_str(json).map_err(|e| CollectorError::JsonParse(e.to_string()))?;
+    parse_bulk_response_filtered(json, None)
+}
+
+/// Parse bulk response, converting only wildcard attributes accepted by `filter`
+pub fn parse_bulk_response_filtered(
+    json: &str,
+    filter: Option<&AttributeFilter>,
+) -> CollectResult<Vec<JolokiaResponse>> {
+    #[cfg(feature = "simd-json")]
+    if let Some(responses) = super::simd::parse_bulk_response(json, filter) {
+        return Ok(responses);
+    }
+
+    let raw_responses: Vec<RawJolokiaResponse> = serde_json::from_str(json).map_err(json_error)?;
 
     raw_responses
         .into_iter()
-        .map(convert_raw_response)
+        .map(|raw| convert_raw_response(raw, filter))
         .collect()
 }
 
 /// Internal struct for parsing
+///
+/// The value is kept as a borrowed slice of the input and converted lazily,
+/// so error responses and filtered attributes are never materialized.
 #[derive(Deserialize)]
-struct RawJolokiaResponse {
+struct RawJolokiaResponse<'a> {
     request: RequestInfo,
-    value: Option<Value>,
+    #[serde(borrow)]
+    value: Option<&'a RawValue>,
     status: u16,
     #[serde(default)]
     timestamp: u64,
@@ -145,7 +302,10 @@ struct RawJolokiaResponse {
     error_type: Option<String>,
 }
 
-fn convert_raw_response(raw: RawJolokiaResponse) -> CollectResult<JolokiaResponse> {
+fn convert_raw_response(
+    raw: RawJolokiaResponse<'_>,
+    filter: Option<&AttributeFilter>,
+) -> CollectResult<JolokiaResponse> {
     // Handle error response
     if raw.status != 200 {
         return Ok(JolokiaResponse {
@@ -155,11 +315,16 @@ fn convert_raw_response(raw: RawJolokiaResponse) -> CollectResult<JolokiaRespons
             timestamp: raw.timestamp,
             error: raw.error,
             error_type: raw.error_type,
+            mbean_errors: Vec::new(),
         });
     }
 
+    let mut mbean_errors = Vec::new();
     let value = match raw.value {
-        Some(v) => parse_mbean_value(v)?,
+        Some(v) => match known::parse_known(&raw.request, v, filter) {
+            Some(value) => value,
+            None => parse_mbean_value(v, filter, &mut mbean_errors)?,
+        },
         None => MBeanValue::Null,
     };
 
@@ -170,85 +335,354 @@ fn convert_raw_response(raw: RawJolokiaResponse) -> CollectResult<JolokiaRespons
         timestamp: raw.timestamp,
         error: raw.error,
         error_type: raw.error_type,
+        mbean_errors,
     })
 }
 
-fn parse_mbean_value(value: Value) -> CollectResult<MBeanValue> {
-    match value {
-        Value::Null => Ok(MBeanValue::Null),
-        Value::Bool(b) => Ok(MBeanValue::Boolean(b)),
-        Value::Number(n) => {
-            let f = n.as_f64().ok_or_else(|| {
-                CollectorError::JsonParse(format!("Number {} cannot be represented as f64", n))
-            })?;
-            Ok(MBeanValue::Number(f))
-        }
-        Value::String(s) => Ok(MBeanValue::String(s)),
-        Value::Array(arr) => {
-            let parsed: Vec<AttributeValue> = arr
-                .into_iter()
-                .map(parse_attribute_value)
-                .collect::<CollectResult<_>>()?;
-            Ok(MBeanValue::Array(parsed))
+/// Error entry of a wildcard read, or `None` for the attributes of an MBean
+///
+/// An entry is an error if it has a string `error` and a `status` or
+/// `error_type`, so an MBean with an `error` attribute is not mistaken for one.
+fn raw_mbean_error(mbean: &str, fields: &[(Cow<'_, str>, &RawValue)]) -> Option<MBeanError> {
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.get())
+    };
+    let error = serde_json::from_str::<String>(field("error")?).ok()?;
+    let status = field("status").and_then(|status| serde_json::from_str::<u16>(status).ok());
+    let error_type =
+        field("error_type").and_then(|error_type| serde_json::from_str::<String>(error_type).ok());
+    if status.is_none() && error_type.is_none() {
+        return None;
+    }
+    Some(MBeanError {
+        mbean: mbean.to_string(),
+        error,
+        error_type,
+        status,
+    })
+}
+
+fn json_error(e: serde_json::Error) -> CollectorError {
+    CollectorError::JsonParse(e.to_string())
+}
+
+/// Check whether a raw JSON value is an object, without parsing it
+fn is_object(raw: &RawValue) -> bool {
+    raw.get().trim_start().starts_with('{')
+}
+
+fn is_array(raw: &RawValue) -> bool {
+    raw.get().trim_start().starts_with('[')
+}
+
+fn is_nested(raw: &RawValue) -> bool {
+    is_object(raw) || is_array(raw)
+}
+
+/// Split a raw JSON object into its fields, leaving the values unparsed
+fn raw_fields(raw: &RawValue) -> CollectResult<Vec<(Cow<'_, str>, &RawValue)>> {
+    serde_json::from_str::<RawFields<'_>>(raw.get())
+        .map(|fields| fields.0)
+        .map_err(json_error)
+}
+
+/// Fields of a JSON object in document order
+struct RawFields<'a>(Vec<(Cow<'a, str>, &'a RawValue)>);
+
+/// Object key, borrowed from the input unless it contains escapes
+#[derive(Deserialize)]
+struct FieldName<'a>(#[serde(borrow)] Cow<'a, str>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for RawFields<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FieldsVisitor;
+
+        impl<'de> Visitor<'de> for FieldsVisitor {
+            type Value = RawFields<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a JSON object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut fields = Vec::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some((FieldName(key), value)) = map.next_entry()? {
+                    fields.push((key, value));
+                }
+                Ok(RawFields(fields))
+            }
         }
-        Value::Object(map) => {
-            // Check if this is a wildcard response (all values are objects and keys are MBean ObjectNames)
-            let is_wildcard = map
-                .iter()
-                .all(|(k, v)| k.contains(':') && k.contains('=') && v.is_object());
-
-            if is_wildcard && !map.is_empty() {
-                let mut result = HashMap::new();
-                for (mbean_name, attrs) in map {
-                    if let Value::Object(attr_map) = attrs {
-                        let parsed_attrs: HashMap<String, AttributeValue> = attr_map
-                            .into_iter()
-                            .map(|(k, v)| Ok((k, parse_attribute_value(v)?)))
-                            .collect::<CollectResult<_>>()?;
-                        result.insert(mbean_name, parsed_attrs);
-                    }
+
+        deserializer.deserialize_map(FieldsVisitor)
+    }
+}
+
+fn parse_attribute_value(raw: &RawValue) -> CollectResult<AttributeValue> {
+    serde_json::from_str(raw.get()).map_err(json_error)
+}
+
+/// Parse a response value; MBeans of a wildcard read that failed are added
+/// to `errors` instead of the value
+fn parse_mbean_value(
+    raw: &RawValue,
+    filter: Option<&AttributeFilter>,
+    errors: &mut Vec<MBeanError>,
+) -> CollectResult<MBeanValue> {
+    if !is_object(raw) {
+        return Ok(match parse_attribute_value(raw)? {
+            AttributeValue::Integer(i) => MBeanValue::Number(i as f64),
+            AttributeValue::Float(f) => MBeanValue::Number(f),
+            AttributeValue::String(s) => MBeanValue::String(s),
+            AttributeValue::Boolean(b) => MBeanValue::Boolean(b),
+            AttributeValue::Null => MBeanValue::Null,
+            AttributeValue::Array(arr) => MBeanValue::Array(arr),
+            AttributeValue::Object(map) => MBeanValue::Composite(map),
+        });
+    }
+
+    let fields = raw_fields(raw)?;
+
+    // Check if this is a wildcard response (all values are objects and keys are MBean ObjectNames)
+    let is_wildcard = fields
+        .iter()
+        .all(|(k, v)| k.contains(':') && k.contains('=') && is_object(v));
+
+    if is_wildcard && !fields.is_empty() {
+        let mut result = HashMap::with_capacity(fields.len());
+        for (mbean_name, attrs) in fields {
+            let attrs = raw_fields(attrs)?;
+            // Checked before filtering, which would drop the error fields
+            if let Some(error) = raw_mbean_error(&mbean_name, &attrs) {
+                errors.push(error);
+                continue;
+            }
+            let mut parsed_attrs = HashMap::new();
+            for (attr_name, value) in attrs {
+                if let Some(parsed) =
+                    parse_wildcard_attribute(&mbean_name, &attr_name, value, filter)?
+                {
+                    parsed_attrs.insert(attr_name.into_owned(), parsed);
                 }
-                Ok(MBeanValue::Wildcard(result))
-            } else {
-                // Regular CompositeData
-                let parsed: HashMap<String, AttributeValue> = map
-                    .into_iter()
-                    .map(|(k, v)| Ok((k, parse_attribute_value(v)?)))
-                    .collect::<CollectResult<_>>()?;
-                Ok(MBeanValue::Composite(parsed))
             }
+            result.insert(mbean_name.into_owned(), parsed_attrs);
         }
+        Ok(MBeanValue::Wildcard(result))
+    } else {
+        // Regular CompositeData
+        let parsed: HashMap<String, AttributeValue> = fields
+            .into_iter()
+            .map(|(k, v)| Ok((k.into_owned(), parse_attribute_value(v)?)))
+            .collect::<CollectResult<_>>()?;
+        Ok(MBeanValue::Composite(parsed))
     }
 }
 
-fn parse_attribute_value(value: Value) -> CollectResult<AttributeValue> {
-    match value {
-        Value::Null => Ok(AttributeValue::Null),
-        Value::Bool(b) => Ok(AttributeValue::Boolean(b)),
-        Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                Ok(AttributeValue::Integer(i))
-            } else {
-                Ok(AttributeValue::Float(n.as_f64().ok_or_else(|| {
-                    CollectorError::JsonParse(format!("Number {} cannot be represented as f64", n))
-                })?))
+/// Convert one attribute of a wildcard entry, or `None` if the filter rejects it
+///
+/// Composite attributes are filtered per key (`Attribute<key>`), nested
+/// composites and arrays by their leaves; an attribute with no accepted
+/// leaves is dropped.
+fn parse_wildcard_attribute(
+    mbean: &str,
+    attribute: &str,
+    raw: &RawValue,
+    filter: Option<&AttributeFilter>,
+) -> CollectResult<Option<AttributeValue>> {
+    let Some(filter) = filter else {
+        return parse_attribute_value(raw).map(Some);
+    };
+
+    if is_array(raw) {
+        return Ok(filter_value(
+            mbean,
+            attribute,
+            parse_attribute_value(raw)?,
+            filter,
+        ));
+    }
+    if !is_object(raw) {
+        let value = || parse_attribute_value(raw).unwrap_or(AttributeValue::Null);
+        if !filter.accepts_value(mbean, attribute, value) {
+            return Ok(None);
+        }
+        return parse_attribute_value(raw).map(Some);
+    }
+
+    let mut kept = HashMap::new();
+    for (key, value) in raw_fields(raw)? {
+        let path = format!("{}<{}>", attribute, key);
+        if is_nested(value) {
+            // Nested composites and arrays are filtered by their leaves
+            if let Some(value) = filter_value(mbean, &path, parse_attribute_value(value)?, filter) {
+                kept.insert(key.into_owned(), value);
             }
+        } else if filter.accepts_value(mbean, &path, || {
+            parse_attribute_value(value).unwrap_or(AttributeValue::Null)
+        }) {
+            kept.insert(key.into_owned(), parse_attribute_value(value)?);
         }
-        Value::String(s) => Ok(AttributeValue::String(s)),
-        Value::Array(arr) => {
-            let parsed: Vec<AttributeValue> = arr
+    }
+    Ok((!kept.is_empty()).then_some(AttributeValue::Object(kept)))
+}
+
+/// Apply the filter to an already converted attribute of a wildcard entry
+///
+/// Same rules as [`parse_wildcard_attribute`], for values that were not
+/// parsed lazily.
+pub(super) fn filter_attribute(
+    mbean: &str,
+    attribute: &str,
+    value: AttributeValue,
+    filter: Option<&AttributeFilter>,
+) -> Option<AttributeValue> {
+    match filter {
+        Some(filter) => filter_value(mbean, attribute, value, filter),
+        None => Some(value),
+    }
+}
+
+/// Keep the parts of a value at `path` the filter accepts
+///
+/// Composites and arrays are filtered down to their leaves, whose paths
+/// extend `path` with `<key>` or `<element>` (see
+/// [`AttributeValue::array_element`]). Rejected array elements become nulls
+/// so the others keep their index. `None` if nothing is accepted.
+pub(super) fn filter_value(
+    mbean: &str,
+    path: &str,
+    value: AttributeValue,
+    filter: &AttributeFilter,
+) -> Option<AttributeValue> {
+    match value {
+        AttributeValue::Object(fields) => {
+            let kept: HashMap<String, AttributeValue> = fields
                 .into_iter()
-                .map(parse_attribute_value)
-                .collect::<CollectResult<_>>()?;
-            Ok(AttributeValue::Array(parsed))
+                .filter_map(|(key, value)| {
+                    let value = filter_value(mbean, &format!("{}<{}>", path, key), value, filter)?;
+                    Some((key, value))
+                })
+                .collect();
+            (!kept.is_empty()).then_some(AttributeValue::Object(kept))
         }
-        Value::Object(map) => {
-            let parsed: HashMap<String, AttributeValue> = map
+        AttributeValue::Array(items) => {
+            let items: Vec<AttributeValue> = items
                 .into_iter()
-                .map(|(k, v)| Ok((k, parse_attribute_value(v)?)))
-                .collect::<CollectResult<_>>()?;
-            Ok(AttributeValue::Object(parsed))
+                .enumerate()
+                .map(|(index, item)| filter_element(mbean, path, index, item, filter))
+                .collect();
+            items
+                .iter()
+                .any(|item| !matches!(item, AttributeValue::Null))
+                .then_some(AttributeValue::Array(items))
+        }
+        value => filter
+            .accepts_value(mbean, path, || value.clone())
+            .then_some(value),
+    }
+}
+
+/// Filter an array element, keeping TabularData rows in their shape
+fn filter_element(
+    mbean: &str,
+    path: &str,
+    index: usize,
+    item: AttributeValue,
+    filter: &AttributeFilter,
+) -> AttributeValue {
+    let (segment, _) = item.array_element(index);
+    let path = format!("{}<{}>", path, segment);
+    if item.table_row().is_none() {
+        return filter_value(mbean, &path, item, filter).unwrap_or(AttributeValue::Null);
+    }
+    let AttributeValue::Object(mut row) = item else {
+        unreachable!("table rows are objects");
+    };
+    let value = row.remove("value").unwrap_or(AttributeValue::Null);
+    match filter_value(mbean, &path, value, filter) {
+        Some(value) => {
+            row.insert("value".to_string(), value);
+            AttributeValue::Object(row)
+        }
+        None => AttributeValue::Null,
+    }
+}
+
+impl<'de> Deserialize<'de> for AttributeValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(AttributeValueVisitor)
+    }
+}
+
+/// Builds attribute values directly from the JSON stream, without an
+/// intermediate `serde_json::Value` tree
+struct AttributeValueVisitor;
+
+impl<'de> Visitor<'de> for AttributeValueVisitor {
+    type Value = AttributeValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<Self::Value, E> {
+        Ok(AttributeValue::Boolean(b))
+    }
+
+    fn visit_i64<E: de::Error>(self, i: i64) -> Result<Self::Value, E> {
+        Ok(AttributeValue::Integer(i))
+    }
+
+    fn visit_u64<E: de::Error>(self, u: u64) -> Result<Self::Value, E> {
+        Ok(match i64::try_from(u) {
+            Ok(i) => AttributeValue::Integer(i),
+            Err(_) => AttributeValue::Float(u as f64),
+        })
+    }
+
+    fn visit_f64<E: de::Error>(self, f: f64) -> Result<Self::Value, E> {
+        Ok(AttributeValue::Float(f))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+        Ok(AttributeValue::String(s.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, s: String) -> Result<Self::Value, E> {
+        Ok(AttributeValue::String(s))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(AttributeValue::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(AttributeValue::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
         }
+        Ok(AttributeValue::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut values = HashMap::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, value)) = map.next_entry()? {
+            values.insert(key, value);
+        }
+        Ok(AttributeValue::Object(values))
     }
 }
 
@@ -309,52 +743,76 @@ impl MBeanValue {
 pub struct ObjectName {
     /// Domain (e.g., "java.lang")
     pub domain: String,
-    /// Properties (e.g., {"type": "Memory"})
-    pub properties: HashMap<String, String>,
+    /// Properties (e.g., {"type": "Memory"}) in declared order, with quoted
+    /// values unquoted
+    pub properties: IndexMap<String, String>,
+    /// Whether the property list ends with the `*` wildcard
+    pub property_list_pattern: bool,
+    /// Whether the domain or a value contains an unescaped `*` or `?`
+    pub value_pattern: bool,
 }
 
 impl ObjectName {
     /// Parse ObjectName string
     ///
-    /// # Limitations
-    /// - Quoted keys/values are NOT fully supported
+    /// Follows the JMX ObjectName grammar: values may be quoted
+    /// (`name="a,b"`) with `\"`, `\\`, `\n`, `\*` and `\?` escapes, `*`
+    /// and `?` in the domain or a value make a pattern, and a `*` element
+    /// matches any other properties. Whitespace around keys and unquoted
+    /// values is trimmed.
     ///
     /// # Errors
     /// Returns `InvalidObjectName` if:
     /// - Missing domain/properties separator (':')
     /// - Any property segment is not in key=value format
-    /// - No properties are defined
+    /// - A key or unquoted value contains a reserved character, or a quoted
+    ///   value is not terminated
+    /// - A key is repeated
+    /// - No properties are defined and the property list is not `*`
     pub fn parse(s: &str) -> CollectResult<Self> {
-        let parts: Vec<&str> = s.splitn(2, ':').collect();
-        if parts.len() != 2 {
-            return Err(CollectorError::InvalidObjectName(s.to_string()));
-        }
+        let invalid = || CollectorError::InvalidObjectName(s.to_string());
 
-        let domain = parts[0].trim().to_string();
+        let (domain, list) = s.split_once(':').ok_or_else(invalid)?;
+        let domain = domain.trim();
         if domain.is_empty() {
-            return Err(CollectorError::InvalidObjectName(s.to_string()));
+            return Err(invalid());
         }
 
-        let mut properties = HashMap::new();
+        let mut name = Self {
+            domain: domain.to_string(),
+            properties: IndexMap::new(),
+            property_list_pattern: false,
+            value_pattern: domain.contains(['*', '?']),
+        };
 
-        for prop in parts[1].split(',') {
-            let kv: Vec<&str> = prop.splitn(2, '=').collect();
-            if kv.len() != 2 {
-                return Err(CollectorError::InvalidObjectName(s.to_string()));
+        for prop in split_properties(list) {
+            let prop = prop.trim();
+            if prop == "*" && !name.property_list_pattern {
+                name.property_list_pattern = true;
+                continue;
+            }
+            let (key, value) = prop.split_once('=').ok_or_else(invalid)?;
+            let key = key.trim();
+            if key.is_empty() || key.contains([':', ',', '=', '*', '?', '"']) {
+                return Err(invalid());
             }
-            let key = kv[0].trim();
-            let value = kv[1].trim();
-            if key.is_empty() {
-                return Err(CollectorError::InvalidObjectName(s.to_string()));
+            let (value, pattern) = parse_value(value.trim()).ok_or_else(invalid)?;
+            name.value_pattern |= pattern;
+            if name.properties.insert(key.to_string(), value).is_some() {
+                return Err(invalid());
             }
-            properties.insert(key.to_string(), value.to_string());
         }
 
-        if properties.is_empty() {
-            return Err(CollectorError::InvalidObjectName(s.to_string()));
+        if name.properties.is_empty() && !name.property_list_pattern {
+            return Err(invalid());
         }
 
-        Ok(Self { domain, properties })
+        Ok(name)
+    }
+
+    /// Whether this is an ObjectName pattern rather than a concrete name
+    pub fn is_pattern(&self) -> bool {
+        self.property_list_pattern || self.value_pattern
     }
 
     /// Generate string for Prometheus labels
@@ -388,6 +846,61 @@ impl ObjectName {
     }
 }
 
+/// Split an ObjectName property list at commas outside quoted values
+fn split_properties(list: &str) -> Vec<&str> {
+    let mut props = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (i, c) in list.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                props.push(&list[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    props.push(&list[start..]);
+    props
+}
+
+/// Decode a property value, returning it and whether it contains wildcards
+///
+/// Returns `None` for a value that is neither a valid quoted nor unquoted
+/// value.
+fn parse_value(value: &str) -> Option<(String, bool)> {
+    let Some(quoted) = value.strip_prefix('"') else {
+        if value.is_empty() || value.contains([':', ',', '=', '"']) {
+            return None;
+        }
+        return Some((value.to_string(), value.contains(['*', '?'])));
+    };
+
+    let mut decoded = String::with_capacity(quoted.len());
+    let mut pattern = false;
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                'n' => decoded.push('\n'),
+                c @ ('\\' | '"' | '*' | '?') => decoded.push(c),
+                _ => return None,
+            },
+            // The closing quote must end the value
+            '"' => return chars.as_str().is_empty().then_some((decoded, pattern)),
+            '*' | '?' => {
+                pattern = true;
+                decoded.push(c);
+            }
+            '\n' => return None,
+            _ => decoded.push(c),
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -513,6 +1026,183 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_wildcard_response_filtered() {
+        let json = r#"{
+            "request": {"mbean": "java.lang:type=MemoryPool,name=*", "type": "read"},
+            "value": {
+                "java.lang:type=MemoryPool,name=Eden": {
+                    "Name": "Eden",
+                    "Usage": {"used": 10, "max": 100},
+                    "UsageThreshold": {"nested": {"deep": [1, 2, 3]}}
+                }
+            },
+            "status": 200
+        }"#;
+
+        let filter = AttributeFilter::new(|mbean, attribute| {
+            assert_eq!(mbean, "java.lang:type=MemoryPool,name=Eden");
+            attribute == "Usage<used>"
+        });
+        let response = parse_response_filtered(json, Some(&filter)).unwrap();
+        let MBeanValue::Wildcard(map) = &response.value else {
+            panic!("Expected Wildcard value");
+        };
+        let attrs = &map["java.lang:type=MemoryPool,name=Eden"];
+        assert_eq!(attrs.len(), 1);
+        let Some(AttributeValue::Object(usage)) = attrs.get("Usage") else {
+            panic!("Expected Usage composite");
+        };
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage["used"].as_f64(), Some(10.0));
+
+        // Without a filter every attribute is converted
+        let response = parse_response(json).unwrap();
+        let MBeanValue::Wildcard(map) = &response.value else {
+            panic!("Expected Wildcard value");
+        };
+        assert_eq!(map["java.lang:type=MemoryPool,name=Eden"].len(), 3);
+
+        // The hook hears about rejected numbers, not strings
+        let rejected = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = Arc::clone(&rejected);
+        let filter = AttributeFilter::new(|_, attribute| attribute == "Usage<used>")
+            .with_rejected_hook(move |_, attribute| {
+                seen.lock().unwrap().push(attribute.to_string())
+            });
+        parse_response_filtered(json, Some(&filter)).unwrap();
+        let mut rejected = rejected.lock().unwrap().clone();
+        rejected.sort();
+        assert_eq!(
+            rejected,
+            vec![
+                "Usage<max>",
+                "UsageThreshold<nested><deep><0>",
+                "UsageThreshold<nested><deep><1>",
+                "UsageThreshold<nested><deep><2>",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_wildcard_mbean_errors() {
+        let json = r#"{
+            "request": {"mbean": "java.lang:type=MemoryPool,*", "type": "read"},
+            "value": {
+                "java.lang:name=Eden,type=MemoryPool": {"Usage": {"used": 10}},
+                "java.lang:name=Broken,type=MemoryPool": {
+                    "error": "java.lang.UnsupportedOperationException : Usage threshold is not supported",
+                    "error_type": "java.lang.UnsupportedOperationException",
+                    "status": 500
+                },
+                "java.lang:name=Odd,type=MemoryPool": {"error": "not an error entry"}
+            },
+            "status": 200
+        }"#;
+
+        // The filter rejects the error fields, which must not hide the error
+        let filter = AttributeFilter::new(|_, attribute| attribute == "Usage<used>");
+        for filter in [None, Some(&filter)] {
+            let response = parse_response_filtered(json, filter).unwrap();
+            assert_eq!(
+                response.mbean_errors,
+                vec![MBeanError {
+                    mbean: "java.lang:name=Broken,type=MemoryPool".to_string(),
+                    error:
+                        "java.lang.UnsupportedOperationException : Usage threshold is not supported"
+                            .to_string(),
+                    error_type: Some("java.lang.UnsupportedOperationException".to_string()),
+                    status: Some(500),
+                }]
+            );
+            let MBeanValue::Wildcard(map) = &response.value else {
+                panic!("Expected Wildcard value");
+            };
+            assert!(map.contains_key("java.lang:name=Eden,type=MemoryPool"));
+            assert!(!map.contains_key("java.lang:name=Broken,type=MemoryPool"));
+            // An `error` attribute alone is an attribute
+            assert!(map.contains_key("java.lang:name=Odd,type=MemoryPool"));
+        }
+    }
+
+    #[test]
+    fn test_parse_wildcard_response_filtered_nested() {
+        let json = r#"{
+            "request": {"mbean": "java.lang:type=GarbageCollector,*", "type": "read"},
+            "value": {
+                "java.lang:name=G1 Young Generation,type=GarbageCollector": {
+                    "LastGcInfo": {
+                        "duration": 12,
+                        "memoryUsageAfterGc": {
+                            "G1 Eden Space": {"used": 0, "max": -1},
+                            "G1 Old Gen": {"used": 50, "max": 400}
+                        }
+                    },
+                    "Pools": [
+                        {"key": "G1 Eden Space", "value": {"used": 1}},
+                        {"key": "G1 Old Gen", "value": {"used": 2}}
+                    ]
+                }
+            },
+            "status": 200
+        }"#;
+
+        let filter = AttributeFilter::new(|_, attribute| {
+            attribute == "LastGcInfo<memoryUsageAfterGc><G1 Old Gen><used>"
+                || attribute == "Pools<G1 Old Gen><used>"
+        });
+        let response = parse_response_filtered(json, Some(&filter)).unwrap();
+        let MBeanValue::Wildcard(map) = &response.value else {
+            panic!("Expected Wildcard value");
+        };
+        let attrs = &map["java.lang:name=G1 Young Generation,type=GarbageCollector"];
+        let Some(AttributeValue::Object(info)) = attrs.get("LastGcInfo") else {
+            panic!("Expected LastGcInfo composite");
+        };
+        assert_eq!(info.len(), 1);
+        let AttributeValue::Object(after) = &info["memoryUsageAfterGc"] else {
+            panic!("Expected memoryUsageAfterGc composite");
+        };
+        let AttributeValue::Object(old) = &after["G1 Old Gen"] else {
+            panic!("Expected G1 Old Gen composite");
+        };
+        assert_eq!(after.len(), 1);
+        assert_eq!(old.len(), 1);
+        assert_eq!(old["used"].as_f64(), Some(50.0));
+
+        // Rejected rows become nulls, so the other rows keep their index
+        let Some(AttributeValue::Array(pools)) = attrs.get("Pools") else {
+            panic!("Expected Pools array");
+        };
+        assert!(matches!(pools[0], AttributeValue::Null));
+        let (key, value) = pools[1].array_element(1);
+        assert_eq!(key, "G1 Old Gen");
+        assert!(matches!(value, AttributeValue::Object(row) if row["used"].as_f64() == Some(2.0)));
+    }
+
+    #[test]
+    fn test_parse_attribute_values() {
+        let json = r#"{"i": -3, "u": 18446744073709551615, "f": 1.5, "s": "x", "b": true,
+            "n": null, "a": [1, "y"], "o": {"k": 2}}"#;
+        let AttributeValue::Object(map) = serde_json::from_str(json).unwrap() else {
+            panic!("Expected object");
+        };
+        assert!(matches!(map["i"], AttributeValue::Integer(-3)));
+        assert!(matches!(map["u"], AttributeValue::Float(_)));
+        assert!(matches!(map["f"], AttributeValue::Float(f) if f == 1.5));
+        assert!(matches!(&map["s"], AttributeValue::String(s) if s == "x"));
+        assert!(matches!(map["b"], AttributeValue::Boolean(true)));
+        assert!(matches!(map["n"], AttributeValue::Null));
+        assert!(matches!(&map["a"], AttributeValue::Array(a) if a.len() == 2));
+        assert!(matches!(&map["o"], AttributeValue::Object(o) if o.len() == 1));
+
+        let malformed = r#"{"request": {"mbean": "a:b=c", "type": "read"}, "value": {"#;
+        assert!(matches!(
+            parse_response(malformed),
+            Err(CollectorError::JsonParse(_))
+        ));
+    }
+
     #[test]
     fn test_object_name_parse() {
         let name = ObjectName::parse("java.lang:type=Memory").unwrap();
@@ -541,6 +1231,55 @@ mod tests {
         assert!(ObjectName::parse("java.lang:").is_err());
     }
 
+    #[test]
+    fn test_object_name_parse_quoted() {
+        let name =
+            ObjectName::parse(r#"java.lang:type=GarbageCollector,name="G1 Young Generation""#)
+                .unwrap();
+        assert_eq!(name.properties["name"], "G1 Young Generation");
+        assert!(!name.is_pattern());
+
+        // Quoted values may hold reserved characters and escapes
+        let name = ObjectName::parse(
+            r#"org.apache.cassandra.db:type=Tables,keyspace="a,b=c:d",table="say \"hi\"\n\*""#,
+        )
+        .unwrap();
+        assert_eq!(name.properties["keyspace"], "a,b=c:d");
+        assert_eq!(name.properties["table"], "say \"hi\"\n*");
+        assert!(!name.is_pattern());
+
+        assert!(ObjectName::parse(r#"d:name="open"#).is_err());
+        assert!(ObjectName::parse(r#"d:name="a"b"#).is_err());
+        assert!(ObjectName::parse(r#"d:name="\x""#).is_err());
+        assert!(ObjectName::parse("d:name=a:b").is_err());
+        assert!(ObjectName::parse("d:name=a,name=b").is_err());
+    }
+
+    #[test]
+    fn test_object_name_parse_pattern() {
+        let name = ObjectName::parse("kafka.server:type=BrokerTopicMetrics,*").unwrap();
+        assert!(name.property_list_pattern);
+        assert_eq!(name.properties.len(), 1);
+        assert!(name.is_pattern());
+
+        let name = ObjectName::parse("*:*").unwrap();
+        assert!(name.properties.is_empty());
+        assert!(name.value_pattern && name.property_list_pattern);
+
+        let name = ObjectName::parse(r#"kafka.server:name=Bytes*,topic="or?ers""#).unwrap();
+        assert!(name.value_pattern && !name.property_list_pattern);
+
+        assert!(ObjectName::parse("d:*,*").is_err());
+        assert!(ObjectName::parse("d:ty*pe=x").is_err());
+    }
+
+    #[test]
+    fn test_object_name_parse_keeps_key_order() {
+        let name = ObjectName::parse("kafka.server:type=Fetch,name=BytesIn,topic=a").unwrap();
+        let keys: Vec<&str> = name.properties.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["type", "name", "topic"]);
+    }
+
     #[test]
     fn test_object_name_parse_with_whitespace() {
         // Whitespace should be trimmed
@@ -592,10 +1331,12 @@ mod tests {
     fn test_to_label_string_with_special_chars() {
         let name = ObjectName {
             domain: "java.lang".to_string(),
-            properties: HashMap::from([
+            properties: IndexMap::from([
                 ("type".to_string(), "GarbageCollector".to_string()),
                 ("name".to_string(), "G1 \"Young\" Gen".to_string()),
             ]),
+            property_list_pattern: false,
+            value_pattern: false,
         };
 
         let label_str = name.to_label_string();
@@ -0,0 +1,226 @@
+//! Reduced-rate polling of rarely-changing MBeans
+//!
+//! Jolokia has no read that returns only the attributes changed since the
+//! last request. Queries with a poll interval are instead read at most once
+//! per interval; in between, the responses of the last successful read are
+//! replayed, so the series stay exported while the JVM is spared the reads.
+//!
+//! Queries with history register their attributes with Jolokia's history
+//! store, so every read also returns the value the agent saw on the previous
+//! read. Their responses are only replayed while that history shows no
+//! change; a changed MBean is read again on the next collection.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use super::parser::JolokiaResponse;
+
+/// Last successful responses of polled queries, keyed by ObjectName
+#[derive(Debug, Default)]
+pub(super) struct PollCache {
+    reads: Mutex<HashMap<String, PolledRead>>,
+    /// Queries whose attributes are registered with the agent's history
+    /// store, and whether a read has returned their history since
+    history: Mutex<HashMap<String, bool>>,
+}
+
+#[derive(Debug)]
+struct PolledRead {
+    read_at: Instant,
+    responses: Vec<(String, JolokiaResponse)>,
+}
+
+impl PollCache {
+    /// Responses to replay for a query read less than `interval` ago
+    ///
+    /// Timestamps are advanced by the time since the read, so the agent's
+    /// clock skew is still estimated correctly from replayed responses.
+    pub(super) fn replay(
+        &self,
+        object_name: &str,
+        interval: Duration,
+    ) -> Option<Vec<(String, JolokiaResponse)>> {
+        let reads = self.reads.lock().ok()?;
+        let read = reads.get(object_name)?;
+        let elapsed = read.read_at.elapsed();
+        if elapsed >= interval {
+            return None;
+        }
+        Some(
+            read.responses
+                .iter()
+                .map(|(mbean, response)| {
+                    let mut response = response.clone();
+                    response.timestamp += elapsed.as_secs();
+                    (mbean.clone(), response)
+                })
+                .collect(),
+        )
+    }
+
+    /// Store the responses of a read, replacing any earlier ones
+    pub(super) fn store(&self, object_name: &str, responses: Vec<(String, JolokiaResponse)>) {
+        let Ok(mut reads) = self.reads.lock() else {
+            tracing::error!("Mutex poisoned while storing polled read");
+            return;
+        };
+        reads.insert(
+            object_name.to_string(),
+            PolledRead {
+                read_at: Instant::now(),
+                responses,
+            },
+        );
+    }
+
+    /// Forget a query, so it is read again on the next collection
+    pub(super) fn remove(&self, object_name: &str) {
+        if let Ok(mut reads) = self.reads.lock() {
+            reads.remove(object_name);
+        }
+    }
+
+    /// Check whether a query's history still has to be registered
+    pub(super) fn needs_history(&self, object_name: &str) -> bool {
+        self.history
+            .lock()
+            .map_or(true, |history| !history.contains_key(object_name))
+    }
+
+    /// Note that a query's history was registered with the agent
+    pub(super) fn history_registered(&self, object_name: &str) {
+        if let Ok(mut history) = self.history.lock() {
+            history.entry(object_name.to_string()).or_insert(false);
+        }
+    }
+
+    /// Note whether a read of a registered query returned its history
+    ///
+    /// The first read after registration has no history yet. A later read
+    /// without it means the agent lost the registration, e.g. because the
+    /// JVM restarted, so the history is registered again.
+    pub(super) fn history_read(&self, object_name: &str, has_history: bool) {
+        let Ok(mut history) = self.history.lock() else {
+            return;
+        };
+        match history.get_mut(object_name) {
+            Some(seen) if has_history => *seen = true,
+            Some(true) => {
+                history.remove(object_name);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether a read changed any attribute since the agent's previous read
+///
+/// Compares each attribute's value in a Jolokia read response body with the
+/// newest entry of its `history`. Returns `None` if the body cannot be parsed
+/// or lacks the history of an attribute.
+pub(super) fn changed_since_history(body: &str, attributes: &[String]) -> Option<bool> {
+    let response: Value = serde_json::from_str(body).ok()?;
+    let value = response.get("value")?;
+    let history = response.get("history")?;
+    let previous = |history: &Value| history.as_array()?.first()?.get("value").cloned();
+    // A single attribute is answered with its bare value and history
+    if history.is_array() {
+        return Some(previous(history)? != *value);
+    }
+    let mut changed = false;
+    for attribute in attributes {
+        let current = value.get(attribute)?;
+        changed |= previous(history.get(attribute)?)? != *current;
+    }
+    Some(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::parse_response;
+
+    const MBEAN: &str = "java.lang:type=Runtime";
+
+    fn response() -> JolokiaResponse {
+        parse_response(
+            r#"{
+                "request": {"mbean": "java.lang:type=Runtime", "type": "read"},
+                "value": {"Uptime": 1000},
+                "status": 200,
+                "timestamp": 1700000000
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_replay_within_interval() {
+        let cache = PollCache::default();
+        assert!(cache.replay(MBEAN, Duration::from_secs(60)).is_none());
+
+        cache.store(MBEAN, vec![(MBEAN.to_string(), response())]);
+        let replayed = cache.replay(MBEAN, Duration::from_secs(60)).unwrap();
+        assert_eq!(replayed.len(), 1);
+        assert_eq!(replayed[0].0, MBEAN);
+        assert_eq!(replayed[0].1.timestamp, 1700000000);
+    }
+
+    #[test]
+    fn test_replay_after_interval() {
+        let cache = PollCache::default();
+        cache.store(MBEAN, vec![(MBEAN.to_string(), response())]);
+        assert!(cache.replay(MBEAN, Duration::ZERO).is_none());
+    }
+
+    #[test]
+    fn test_changed_since_history() {
+        let attributes = ["Count".to_string(), "Name".to_string()];
+        let body = |count: u64| {
+            format!(
+                r#"{{"value": {{"Count": {}, "Name": "a"}}, "status": 200,
+                    "history": {{"Count": [{{"value": 3, "timestamp": 1}}],
+                                 "Name": [{{"value": "a", "timestamp": 1}}]}}}}"#,
+                count
+            )
+        };
+        assert_eq!(changed_since_history(&body(3), &attributes), Some(false));
+        assert_eq!(changed_since_history(&body(4), &attributes), Some(true));
+
+        let first_read = r#"{"value": {"Count": 3, "Name": "a"}, "status": 200}"#;
+        assert_eq!(changed_since_history(first_read, &attributes), None);
+        let partial = r#"{"value": {"Count": 3, "Name": "a"}, "status": 200,
+            "history": {"Count": [{"value": 3, "timestamp": 1}]}}"#;
+        assert_eq!(changed_since_history(partial, &attributes), None);
+
+        let single = r#"{"value": 7, "status": 200, "history": [{"value": 7, "timestamp": 1}]}"#;
+        assert_eq!(changed_since_history(single, &attributes[..1]), Some(false));
+    }
+
+    #[test]
+    fn test_history_registration() {
+        let cache = PollCache::default();
+        assert!(cache.needs_history(MBEAN));
+        cache.history_registered(MBEAN);
+        assert!(!cache.needs_history(MBEAN));
+
+        // The first read after registering has no history yet
+        cache.history_read(MBEAN, false);
+        assert!(!cache.needs_history(MBEAN));
+        cache.history_read(MBEAN, true);
+        // Losing it afterwards registers it again
+        cache.history_read(MBEAN, false);
+        assert!(cache.needs_history(MBEAN));
+    }
+
+    #[test]
+    fn test_remove() {
+        let cache = PollCache::default();
+        cache.store(MBEAN, vec![(MBEAN.to_string(), response())]);
+        cache.remove(MBEAN);
+        assert!(cache.replay(MBEAN, Duration::from_secs(60)).is_none());
+    }
+}
@@ -0,0 +1,415 @@
+//! Time-of-day collection windows and scrape schedules
+//!
+//! Quiet hours suppress individual MBean queries during a daily time window,
+//! e.g. to avoid reading heavyweight per-topic MBeans during peak traffic.
+//! Windows are expressed in UTC as `HH:MM` and may wrap past midnight.
+//!
+//! Cron schedules pick the minutes at which a target is scraped in
+//! background collection, using the five standard cron fields in UTC.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+/// Daily time window during which a query is not collected
+///
+/// `start` is inclusive and `end` is exclusive. A window whose end is
+/// before its start wraps past midnight (e.g. `22:00`-`06:00`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "QuietWindowSpec", into = "QuietWindowSpec")]
+pub struct QuietWindow {
+    /// Window start, in minutes after midnight UTC
+    start: u16,
+    /// Window end, in minutes after midnight UTC
+    end: u16,
+}
+
+/// Serialized form of a quiet window
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+struct QuietWindowSpec {
+    start: String,
+    end: String,
+}
+
+impl QuietWindow {
+    /// Create a window from `HH:MM` start and end times (UTC)
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if either time is malformed or
+    /// the window is empty.
+    pub fn parse(start: &str, end: &str) -> Result<Self, String> {
+        let start = parse_time_of_day(start)?;
+        let end = parse_time_of_day(end)?;
+        if start == end {
+            return Err("quiet window start and end must differ".to_string());
+        }
+        Ok(Self { start, end })
+    }
+
+    /// Check whether a minute of the day (0..1440) falls inside the window
+    pub fn contains_minute(&self, minute: u16) -> bool {
+        if self.start < self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+
+    /// Check whether a point in time falls inside the window
+    pub fn contains(&self, time: SystemTime) -> bool {
+        self.contains_minute(minute_of_day_utc(time))
+    }
+}
+
+impl TryFrom<QuietWindowSpec> for QuietWindow {
+    type Error = String;
+
+    fn try_from(spec: QuietWindowSpec) -> Result<Self, Self::Error> {
+        Self::parse(&spec.start, &spec.end)
+    }
+}
+
+impl From<QuietWindow> for QuietWindowSpec {
+    fn from(window: QuietWindow) -> Self {
+        Self {
+            start: format_time_of_day(window.start),
+            end: format_time_of_day(window.end),
+        }
+    }
+}
+
+impl std::fmt::Display for QuietWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{} UTC",
+            format_time_of_day(self.start),
+            format_time_of_day(self.end)
+        )
+    }
+}
+
+/// Cron-like schedule of background scrapes
+///
+/// Five space-separated fields in UTC: minute (0-59), hour (0-23), day of
+/// month (1-31), month (1-12) and day of week (0-6, Sunday is 0 or 7). Each
+/// field is `*` or a comma-separated list of values and `a-b` ranges, each
+/// optionally followed by `/step`. As in cron, when both day fields are
+/// restricted a day matches if either does.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "String", into = "String")]
+pub struct CronSchedule {
+    source: String,
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    /// Parse a five-field cron expression
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if a field is malformed or out
+    /// of range, or if the expression never matches (e.g. `0 0 30 2 *`).
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "invalid schedule '{}', expected 5 fields (minute hour day month weekday)",
+                expression
+            ));
+        };
+        // Sunday may be written as 7
+        let weekdays = parse_cron_field(weekday, 0, 7, "day of week")?;
+        let schedule = Self {
+            source: fields.join(" "),
+            minutes: parse_cron_field(minute, 0, 59, "minute")?,
+            hours: parse_cron_field(hour, 0, 23, "hour")? as u32,
+            days: parse_cron_field(day, 1, 31, "day of month")? as u32,
+            months: parse_cron_field(month, 1, 12, "month")? as u16,
+            weekdays: ((weekdays | weekdays >> 7) & 0x7f) as u8,
+            any_day: day.starts_with('*'),
+            any_weekday: weekday.starts_with('*'),
+        };
+        if schedule.next_after(UNIX_EPOCH).is_none() {
+            return Err(format!("schedule '{}' never matches", expression));
+        }
+        Ok(schedule)
+    }
+
+    /// First scheduled minute strictly after `time`
+    ///
+    /// Returns `None` if the schedule has no match in the following four
+    /// years, which only happens for dates that do not exist.
+    pub fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        const MINUTES_PER_DAY: u64 = 24 * 60;
+        let start = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()) / 60 + 1;
+        let end = start + 4 * 366 * MINUTES_PER_DAY;
+        let mut minute = start;
+        while minute < end {
+            let day = minute / MINUTES_PER_DAY;
+            if !self.matches_day(day) {
+                minute = (day + 1) * MINUTES_PER_DAY;
+                continue;
+            }
+            let of_day = minute % MINUTES_PER_DAY;
+            if self.hours & (1 << (of_day / 60)) == 0 {
+                minute = day * MINUTES_PER_DAY + (of_day / 60 + 1) * 60;
+                continue;
+            }
+            if self.minutes & (1 << (of_day % 60)) == 0 {
+                minute += 1;
+                continue;
+            }
+            return Some(UNIX_EPOCH + Duration::from_secs(minute * 60));
+        }
+        None
+    }
+
+    /// Check whether a day, counted from 1970-01-01, is scheduled
+    fn matches_day(&self, day: u64) -> bool {
+        let (_, month, day_of_month) = civil_from_days(day);
+        if self.months & (1 << month) == 0 {
+            return false;
+        }
+        // 1970-01-01 was a Thursday
+        let weekday = (day + 4) % 7;
+        let day_matches = self.days & (1 << day_of_month) != 0;
+        let weekday_matches = self.weekdays & (1 << weekday) != 0;
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day_matches || weekday_matches,
+            _ => day_matches && weekday_matches,
+        }
+    }
+}
+
+impl TryFrom<String> for CronSchedule {
+    type Error = String;
+
+    fn try_from(expression: String) -> Result<Self, Self::Error> {
+        Self::parse(&expression)
+    }
+}
+
+impl From<CronSchedule> for String {
+    fn from(schedule: CronSchedule) -> Self {
+        schedule.source
+    }
+}
+
+impl std::fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} UTC", self.source)
+    }
+}
+
+/// Parse one cron field into a bit set of the values it selects
+fn parse_cron_field(field: &str, min: u64, max: u64, name: &str) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for item in field.split(',') {
+        let invalid = || format!("invalid {} '{}'", name, item);
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u64>().map_err(|_| invalid())?),
+            None => (item, 1),
+        };
+        let (first, last) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((first, last)) => (
+                    first.parse().map_err(|_| invalid())?,
+                    last.parse().map_err(|_| invalid())?,
+                ),
+                None => {
+                    let value = range.parse().map_err(|_| invalid())?;
+                    // `5/15` runs from 5 to the end of the range
+                    (value, if item.contains('/') { max } else { value })
+                }
+            },
+        };
+        if step == 0 || first < min || last > max || first > last {
+            return Err(format!(
+                "{} '{}' is out of range ({}-{})",
+                name, item, min, max
+            ));
+        }
+        for value in (first..=last).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+/// Year, month (1-12) and day of month of a day counted from 1970-01-01
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Format a point in time as an RFC 3339 UTC timestamp, to the second
+pub fn format_rfc3339(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_from_days(secs / 86_400);
+    let of_day = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        of_day / 3600,
+        of_day / 60 % 60,
+        of_day % 60
+    )
+}
+
+/// Parse `HH:MM` into minutes after midnight
+fn parse_time_of_day(s: &str) -> Result<u16, String> {
+    let (hours, minutes) = s
+        .trim()
+        .split_once(':')
+        .ok_or_else(|| format!("invalid time '{}', expected HH:MM", s))?;
+    let hours: u16 = hours
+        .parse()
+        .map_err(|_| format!("invalid hour in '{}'", s))?;
+    let minutes: u16 = minutes
+        .parse()
+        .map_err(|_| format!("invalid minute in '{}'", s))?;
+    if hours > 23 || minutes > 59 {
+        return Err(format!("time '{}' is out of range (00:00-23:59)", s));
+    }
+    Ok(hours * 60 + minutes)
+}
+
+/// Format minutes after midnight as `HH:MM`
+fn format_time_of_day(minute: u16) -> String {
+    format!("{:02}:{:02}", minute / 60, minute % 60)
+}
+
+/// Minutes after midnight UTC for a point in time
+fn minute_of_day_utc(time: SystemTime) -> u16 {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    ((secs / 60) % u64::from(MINUTES_PER_DAY)) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_and_display() {
+        let window = QuietWindow::parse("09:00", "17:30").unwrap();
+        assert_eq!(window.to_string(), "09:00-17:30 UTC");
+
+        assert!(QuietWindow::parse("9", "17:00").is_err());
+        assert!(QuietWindow::parse("24:00", "17:00").is_err());
+        assert!(QuietWindow::parse("09:60", "17:00").is_err());
+        assert!(QuietWindow::parse("09:00", "09:00").is_err());
+    }
+
+    #[test]
+    fn test_contains_minute() {
+        let day = QuietWindow::parse("09:00", "17:00").unwrap();
+        assert!(day.contains_minute(9 * 60));
+        assert!(day.contains_minute(16 * 60 + 59));
+        assert!(!day.contains_minute(17 * 60));
+        assert!(!day.contains_minute(8 * 60 + 59));
+
+        let night = QuietWindow::parse("22:00", "06:00").unwrap();
+        assert!(night.contains_minute(23 * 60));
+        assert!(night.contains_minute(0));
+        assert!(night.contains_minute(5 * 60 + 59));
+        assert!(!night.contains_minute(6 * 60));
+        assert!(!night.contains_minute(12 * 60));
+    }
+
+    #[test]
+    fn test_contains_time() {
+        let window = QuietWindow::parse("01:00", "02:00").unwrap();
+        // 1970-01-02 01:30 UTC
+        let time = UNIX_EPOCH + Duration::from_secs(86_400 + 90 * 60);
+        assert!(window.contains(time));
+        assert!(!window.contains(UNIX_EPOCH));
+    }
+
+    #[test]
+    fn test_cron_parse() {
+        let schedule = CronSchedule::parse("*/15  2-4 * * 1,3").unwrap();
+        assert_eq!(schedule.to_string(), "*/15 2-4 * * 1,3 UTC");
+
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("* 5-2 * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("* * 0 * *").is_err());
+        assert!(CronSchedule::parse("0 0 30 2 *").is_err());
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        // 2024-03-01 10:07:30 UTC, a Friday
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_287_650);
+        let next = |expression: &str| {
+            format_rfc3339(
+                CronSchedule::parse(expression)
+                    .unwrap()
+                    .next_after(time)
+                    .unwrap(),
+            )
+        };
+        assert_eq!(format_rfc3339(time), "2024-03-01T10:07:30Z");
+        assert_eq!(next("* * * * *"), "2024-03-01T10:08:00Z");
+        assert_eq!(next("*/15 * * * *"), "2024-03-01T10:15:00Z");
+        assert_eq!(next("5 * * * *"), "2024-03-01T11:05:00Z");
+        assert_eq!(next("0 2 * * *"), "2024-03-02T02:00:00Z");
+        // Sunday, as 0 and as 7
+        assert_eq!(next("0 0 * * 0"), "2024-03-03T00:00:00Z");
+        assert_eq!(next("0 0 * * 7"), "2024-03-03T00:00:00Z");
+        // Either day field matches when both are restricted
+        assert_eq!(next("0 0 15 * 1"), "2024-03-04T00:00:00Z");
+        assert_eq!(next("0 0 29 2 *"), "2028-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn test_cron_serde() {
+        let schedule: CronSchedule = serde_yaml::from_str("\"0 */6 * * *\"").unwrap();
+        assert_eq!(
+            serde_yaml::to_string(&schedule).unwrap().trim(),
+            "0 */6 * * *"
+        );
+        assert!(serde_yaml::from_str::<CronSchedule>("\"0 24 * * *\"").is_err());
+    }
+
+    #[test]
+    fn test_serde() {
+        let window: QuietWindow = serde_yaml::from_str("start: \"22:00\"\nend: \"06:00\"").unwrap();
+        assert!(window.contains_minute(23 * 60));
+
+        let yaml = serde_yaml::to_string(&window).unwrap();
+        assert!(yaml.contains("22:00"));
+
+        let invalid: Result<QuietWindow, _> =
+            serde_yaml::from_str("start: \"25:00\"\nend: \"06:00\"");
+        assert!(invalid.is_err());
+    }
+}
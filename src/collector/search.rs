@@ -0,0 +1,161 @@
+//! Query-time MBean whitelists
+//!
+//! `whitelistObjectNames` globs are resolved with a Jolokia `search` before
+//! reading, so blacklisted MBeans under a whitelisted pattern are never
+//! read and only the matching MBeans are fetched, in one bulk request.
+//!
+//! Patterns follow JMX ObjectName pattern syntax: `*` and `?` in the domain
+//! and in property values, and a trailing `*` in the property list for
+//! "any other properties". A bare domain (`kafka.server`) stands for every
+//! MBean in it. Quoted values are matched unquoted.
+
+use super::parser::ObjectName;
+
+/// Translate a whitelist glob into a Jolokia search pattern
+///
+/// A bare domain or a domain with an empty property list selects every MBean
+/// of the domain; anything else is already a valid ObjectName pattern.
+pub fn search_pattern(glob: &str) -> String {
+    let glob = glob.trim();
+    match glob.split_once(':') {
+        None => format!("{}:*", glob),
+        Some((_, "")) => format!("{}*", glob),
+        Some(_) => glob.to_string(),
+    }
+}
+
+/// Check whether an ObjectName or ObjectName pattern contains wildcards
+pub fn is_pattern(object_name: &str) -> bool {
+    object_name.contains(['*', '?'])
+}
+
+/// Check whether a blacklist entry excludes a concrete MBean
+///
+/// Entries match as ObjectName patterns, or as substrings like the query
+/// level blacklist.
+pub fn is_excluded(pattern: &str, name: &str) -> bool {
+    name.contains(pattern) || object_name_matches(pattern, name)
+}
+
+/// Match a concrete ObjectName against an ObjectName pattern
+pub fn object_name_matches(pattern: &str, name: &str) -> bool {
+    let (Ok(pattern), Ok(name)) = (
+        ObjectName::parse(&search_pattern(pattern)),
+        ObjectName::parse(name),
+    ) else {
+        return false;
+    };
+    if !glob_matches(&pattern.domain, &name.domain) {
+        return false;
+    }
+
+    let matched = pattern.properties.iter().all(|(key, value)| {
+        name.properties
+            .get(key)
+            .is_some_and(|v| glob_matches(value, v))
+    });
+    matched && (pattern.property_list_pattern || pattern.properties.len() == name.properties.len())
+}
+
+/// Match text against a glob with `*` (any run) and `?` (one character)
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text it has consumed up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_pattern() {
+        assert_eq!(search_pattern("kafka.server"), "kafka.server:*");
+        assert_eq!(search_pattern("kafka.server:"), "kafka.server:*");
+        assert_eq!(
+            search_pattern("kafka.server:type=BrokerTopicMetrics,*"),
+            "kafka.server:type=BrokerTopicMetrics,*"
+        );
+        assert!(is_pattern("java.lang:*"));
+        assert!(!is_pattern("java.lang:type=Memory"));
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("Bytes*", "BytesInPerSec"));
+        assert!(glob_matches("*PerSec", "BytesInPerSec"));
+        assert!(glob_matches("B?tes*Sec", "BytesInPerSec"));
+        assert!(glob_matches("*In*Sec", "BytesInPerSecInSec"));
+        assert!(!glob_matches("Bytes", "BytesIn"));
+        assert!(!glob_matches("*Out*", "BytesInPerSec"));
+    }
+
+    #[test]
+    fn test_object_name_matches() {
+        let name = "kafka.server:type=BrokerTopicMetrics,name=BytesInPerSec,topic=orders";
+
+        assert!(object_name_matches("kafka.server:*", name));
+        assert!(object_name_matches("kafka.*", name));
+        assert!(object_name_matches(
+            "kafka.server:type=BrokerTopicMetrics,*",
+            name
+        ));
+        assert!(object_name_matches(
+            "kafka.server:name=Bytes*,topic=*,type=BrokerTopicMetrics",
+            name
+        ));
+        // Without `*`, the property list must match exactly
+        assert!(!object_name_matches(
+            "kafka.server:type=BrokerTopicMetrics",
+            name
+        ));
+        assert!(!object_name_matches("kafka.network:*", name));
+        assert!(!object_name_matches(
+            "kafka.server:type=ReplicaManager,*",
+            name
+        ));
+
+        let quoted = r#"org.apache.cassandra.metrics:type=Table,keyspace="a,b",name=Reads"#;
+        assert!(object_name_matches(
+            "org.apache.cassandra.metrics:keyspace=\"a,*\",*",
+            quoted
+        ));
+        assert!(!object_name_matches(
+            "org.apache.cassandra.metrics:keyspace=a,*",
+            quoted
+        ));
+    }
+
+    #[test]
+    fn test_is_excluded() {
+        let name = "java.lang:type=MemoryPool,name=Metaspace";
+        assert!(is_excluded("java.lang:type=MemoryPool,*", name));
+        assert!(is_excluded("MemoryPool", name));
+        assert!(!is_excluded("java.lang:type=GarbageCollector,*", name));
+    }
+}
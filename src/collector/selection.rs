@@ -0,0 +1,157 @@
+//! Per-scrape MBean selection
+//!
+//! Named MBean groups (`collect_groups` in the config) can be selected for a
+//! single scrape with Prometheus-style query parameters, so one exporter can
+//! serve several scrape jobs with different metric sets and intervals:
+//!
+//! ```text
+//! /metrics?collect[]=memory&collect[]=gc
+//! /metrics?exclude[]=kafka
+//! ```
+//!
+//! Without parameters every configured MBean is collected.
+
+use std::collections::HashMap;
+
+/// MBeans selected for one scrape
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MBeanSelection {
+    /// Patterns an MBean must match (all MBeans if `None`)
+    include: Option<Vec<String>>,
+    /// Patterns that exclude an MBean
+    exclude: Vec<String>,
+}
+
+impl MBeanSelection {
+    /// Select every MBean
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Build a selection from group names
+    ///
+    /// Returns the first unknown group name as the error.
+    pub fn from_groups(
+        groups: &HashMap<String, Vec<String>>,
+        collect: &[String],
+        exclude: &[String],
+    ) -> Result<Self, String> {
+        let patterns = |names: &[String]| -> Result<Vec<String>, String> {
+            let mut patterns = Vec::new();
+            for name in names {
+                let group = groups.get(name).ok_or_else(|| name.clone())?;
+                patterns.extend(group.iter().cloned());
+            }
+            Ok(patterns)
+        };
+
+        Ok(Self {
+            include: if collect.is_empty() {
+                None
+            } else {
+                Some(patterns(collect)?)
+            },
+            exclude: patterns(exclude)?,
+        })
+    }
+
+    /// Build a selection from a request query string
+    ///
+    /// Reads `collect[]` and `exclude[]` (also without brackets); other
+    /// parameters are ignored.
+    pub fn from_query(
+        groups: &HashMap<String, Vec<String>>,
+        query: Option<&str>,
+    ) -> Result<Self, String> {
+        let mut collect = Vec::new();
+        let mut exclude = Vec::new();
+
+        for (key, value) in url::form_urlencoded::parse(query.unwrap_or_default().as_bytes()) {
+            match key.as_ref() {
+                "collect[]" | "collect" => collect.push(value.into_owned()),
+                "exclude[]" | "exclude" => exclude.push(value.into_owned()),
+                _ => {}
+            }
+        }
+
+        Self::from_groups(groups, &collect, &exclude)
+    }
+
+    /// Check whether every MBean is selected
+    pub fn is_all(&self) -> bool {
+        self.include.is_none() && self.exclude.is_empty()
+    }
+
+    /// Check whether an MBean is selected
+    ///
+    /// Group patterns match as substrings of the ObjectName, like
+    /// `blacklistObjectNames`.
+    pub fn matches(&self, object_name: &str) -> bool {
+        let included = self
+            .include
+            .as_ref()
+            .is_none_or(|patterns| patterns.iter().any(|p| object_name.contains(p.as_str())));
+        included
+            && !self
+                .exclude
+                .iter()
+                .any(|p| object_name.contains(p.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn groups() -> HashMap<String, Vec<String>> {
+        HashMap::from([
+            (
+                "memory".to_string(),
+                vec![
+                    "java.lang:type=Memory".to_string(),
+                    "java.lang:type=MemoryPool".to_string(),
+                ],
+            ),
+            (
+                "gc".to_string(),
+                vec!["java.lang:type=GarbageCollector".to_string()],
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_select_all() {
+        let selection = MBeanSelection::from_query(&groups(), None).unwrap();
+        assert!(selection.is_all());
+        assert!(selection.matches("java.lang:type=Threading"));
+    }
+
+    #[test]
+    fn test_collect_groups() {
+        let selection =
+            MBeanSelection::from_query(&groups(), Some("collect%5B%5D=memory&collect[]=gc"))
+                .unwrap();
+        assert!(selection.matches("java.lang:type=Memory"));
+        assert!(selection.matches("java.lang:type=MemoryPool,name=Eden"));
+        assert!(selection.matches("java.lang:type=GarbageCollector,*"));
+        assert!(!selection.matches("java.lang:type=Threading"));
+    }
+
+    #[test]
+    fn test_exclude_groups() {
+        let selection = MBeanSelection::from_query(&groups(), Some("exclude[]=gc")).unwrap();
+        assert!(!selection.is_all());
+        assert!(selection.matches("java.lang:type=Memory"));
+        assert!(!selection.matches("java.lang:type=GarbageCollector,*"));
+    }
+
+    #[test]
+    fn test_unknown_group() {
+        assert_eq!(
+            MBeanSelection::from_query(&groups(), Some("collect[]=kafka")),
+            Err("kafka".to_string())
+        );
+        // Unrelated parameters are ignored
+        assert!(MBeanSelection::from_query(&groups(), Some("module=kafka")).is_ok());
+    }
+}
@@ -0,0 +1,386 @@
+//! simd-json parsing of Jolokia responses (`simd-json` feature)
+//!
+//! The body is copied into a scratch buffer, which simd-json parses in place
+//! into a tree whose strings borrow from it. The tree is then converted the
+//! same way as the serde_json path: error responses are not converted, and
+//! wildcard attributes rejected by the filter are skipped.
+//!
+//! Conversion returns `None` for anything unexpected, and the caller parses
+//! the body again with serde_json, which then produces the result or the
+//! error message.
+
+use std::collections::HashMap;
+
+use simd_json::{BorrowedValue, StaticNode};
+use tracing::debug;
+
+use super::parser::{
+    filter_value, AttributeFilter, AttributeValue, JolokiaResponse, MBeanError, MBeanValue,
+    RequestInfo,
+};
+
+/// Parse a single response
+pub(super) fn parse_response(
+    json: &str,
+    filter: Option<&AttributeFilter>,
+) -> Option<JolokiaResponse> {
+    let mut buffer = json.as_bytes().to_vec();
+    let value = parse(&mut buffer)?;
+    convert_response(&value, filter)
+}
+
+/// Parse a bulk response
+pub(super) fn parse_bulk_response(
+    json: &str,
+    filter: Option<&AttributeFilter>,
+) -> Option<Vec<JolokiaResponse>> {
+    let mut buffer = json.as_bytes().to_vec();
+    let BorrowedValue::Array(responses) = parse(&mut buffer)? else {
+        return None;
+    };
+    responses
+        .iter()
+        .map(|response| convert_response(response, filter))
+        .collect()
+}
+
+fn parse(buffer: &mut [u8]) -> Option<BorrowedValue<'_>> {
+    simd_json::to_borrowed_value(buffer)
+        .inspect_err(|e| debug!(error = %e, "simd-json rejected response, using serde_json"))
+        .ok()
+}
+
+fn convert_response(
+    value: &BorrowedValue<'_>,
+    filter: Option<&AttributeFilter>,
+) -> Option<JolokiaResponse> {
+    let BorrowedValue::Object(fields) = value else {
+        return None;
+    };
+
+    let request = convert_request(fields.get("request")?)?;
+    let status = match fields.get("status")? {
+        BorrowedValue::Static(StaticNode::I64(i)) => u16::try_from(*i).ok()?,
+        BorrowedValue::Static(StaticNode::U64(u)) => u16::try_from(*u).ok()?,
+        _ => return None,
+    };
+    let timestamp = match fields.get("timestamp") {
+        None => 0,
+        Some(BorrowedValue::Static(StaticNode::I64(i))) => u64::try_from(*i).ok()?,
+        Some(BorrowedValue::Static(StaticNode::U64(u))) => *u,
+        Some(_) => return None,
+    };
+    let error = optional_string(fields.get("error"))?;
+    let error_type = optional_string(fields.get("error_type"))?;
+
+    // Error responses are not converted
+    let mut mbean_errors = Vec::new();
+    let value = match fields.get("value") {
+        Some(value) if status == 200 => convert_mbean_value(value, filter, &mut mbean_errors)?,
+        _ => MBeanValue::Null,
+    };
+
+    Some(JolokiaResponse {
+        request,
+        value,
+        status,
+        timestamp,
+        error,
+        error_type,
+        mbean_errors,
+    })
+}
+
+fn convert_request(value: &BorrowedValue<'_>) -> Option<RequestInfo> {
+    let BorrowedValue::Object(fields) = value else {
+        return None;
+    };
+    let BorrowedValue::String(mbean) = fields.get("mbean")? else {
+        return None;
+    };
+    let BorrowedValue::String(request_type) = fields.get("type")? else {
+        return None;
+    };
+    let attribute = match fields.get("attribute") {
+        None | Some(BorrowedValue::Static(StaticNode::Null)) => None,
+        Some(attribute) => Some(serde_json::to_value(attribute).ok()?),
+    };
+
+    Some(RequestInfo {
+        mbean: mbean.to_string(),
+        attribute,
+        request_type: request_type.to_string(),
+    })
+}
+
+/// A missing or null string is `Some(None)`; any other type is `None`
+fn optional_string(value: Option<&BorrowedValue<'_>>) -> Option<Option<String>> {
+    match value {
+        None | Some(BorrowedValue::Static(StaticNode::Null)) => Some(None),
+        Some(BorrowedValue::String(s)) => Some(Some(s.to_string())),
+        Some(_) => None,
+    }
+}
+
+fn convert_mbean_value(
+    value: &BorrowedValue<'_>,
+    filter: Option<&AttributeFilter>,
+    errors: &mut Vec<MBeanError>,
+) -> Option<MBeanValue> {
+    let BorrowedValue::Object(fields) = value else {
+        return Some(match convert_attribute_value(value) {
+            AttributeValue::Integer(i) => MBeanValue::Number(i as f64),
+            AttributeValue::Float(f) => MBeanValue::Number(f),
+            AttributeValue::String(s) => MBeanValue::String(s),
+            AttributeValue::Boolean(b) => MBeanValue::Boolean(b),
+            AttributeValue::Null => MBeanValue::Null,
+            AttributeValue::Array(arr) => MBeanValue::Array(arr),
+            AttributeValue::Object(map) => MBeanValue::Composite(map),
+        });
+    };
+
+    // Wildcard response: all values are objects and keys are MBean ObjectNames
+    let is_wildcard = fields
+        .iter()
+        .all(|(k, v)| k.contains(':') && k.contains('=') && matches!(v, BorrowedValue::Object(_)));
+
+    if is_wildcard && !fields.is_empty() {
+        let mut result = HashMap::with_capacity(fields.len());
+        for (mbean_name, attrs) in fields.iter() {
+            let BorrowedValue::Object(attrs) = attrs else {
+                return None;
+            };
+            // Checked before filtering, which would drop the error fields
+            if let Some(error) = mbean_error(mbean_name, attrs) {
+                errors.push(error);
+                continue;
+            }
+            let mut parsed_attrs = HashMap::new();
+            for (attr_name, value) in attrs.iter() {
+                if let Some(parsed) =
+                    convert_wildcard_attribute(mbean_name, attr_name, value, filter)
+                {
+                    parsed_attrs.insert(attr_name.to_string(), parsed);
+                }
+            }
+            result.insert(mbean_name.to_string(), parsed_attrs);
+        }
+        Some(MBeanValue::Wildcard(result))
+    } else {
+        // Regular CompositeData
+        let parsed = fields
+            .iter()
+            .map(|(k, v)| (k.to_string(), convert_attribute_value(v)))
+            .collect();
+        Some(MBeanValue::Composite(parsed))
+    }
+}
+
+/// Error entry of a wildcard read, with the same rules as the serde_json path
+fn mbean_error(mbean: &str, fields: &simd_json::borrowed::Object<'_>) -> Option<MBeanError> {
+    let BorrowedValue::String(error) = fields.get("error")? else {
+        return None;
+    };
+    let status = match fields.get("status") {
+        Some(BorrowedValue::Static(StaticNode::I64(i))) => u16::try_from(*i).ok(),
+        Some(BorrowedValue::Static(StaticNode::U64(u))) => u16::try_from(*u).ok(),
+        _ => None,
+    };
+    let error_type = match fields.get("error_type") {
+        Some(BorrowedValue::String(error_type)) => Some(error_type.to_string()),
+        _ => None,
+    };
+    if status.is_none() && error_type.is_none() {
+        return None;
+    }
+    Some(MBeanError {
+        mbean: mbean.to_string(),
+        error: error.to_string(),
+        error_type,
+        status,
+    })
+}
+
+/// Convert one attribute of a wildcard entry, or `None` if the filter rejects it
+fn convert_wildcard_attribute(
+    mbean: &str,
+    attribute: &str,
+    value: &BorrowedValue<'_>,
+    filter: Option<&AttributeFilter>,
+) -> Option<AttributeValue> {
+    let Some(filter) = filter else {
+        return Some(convert_attribute_value(value));
+    };
+
+    if let BorrowedValue::Array(_) = value {
+        return filter_value(mbean, attribute, convert_attribute_value(value), filter);
+    }
+    let BorrowedValue::Object(fields) = value else {
+        return filter
+            .accepts_value(mbean, attribute, || convert_attribute_value(value))
+            .then(|| convert_attribute_value(value));
+    };
+
+    let mut kept = HashMap::new();
+    for (key, value) in fields.iter() {
+        let path = format!("{}<{}>", attribute, key);
+        match value {
+            // Nested composites and arrays are filtered by their leaves
+            BorrowedValue::Object(_) | BorrowedValue::Array(_) => {
+                if let Some(value) =
+                    filter_value(mbean, &path, convert_attribute_value(value), filter)
+                {
+                    kept.insert(key.to_string(), value);
+                }
+            }
+            _ if filter.accepts_value(mbean, &path, || convert_attribute_value(value)) => {
+                kept.insert(key.to_string(), convert_attribute_value(value));
+            }
+            _ => {}
+        }
+    }
+    (!kept.is_empty()).then_some(AttributeValue::Object(kept))
+}
+
+fn convert_attribute_value(value: &BorrowedValue<'_>) -> AttributeValue {
+    match value {
+        BorrowedValue::Static(StaticNode::I64(i)) => AttributeValue::Integer(*i),
+        BorrowedValue::Static(StaticNode::U64(u)) => match i64::try_from(*u) {
+            Ok(i) => AttributeValue::Integer(i),
+            Err(_) => AttributeValue::Float(*u as f64),
+        },
+        BorrowedValue::Static(StaticNode::F64(f)) => AttributeValue::Float(*f),
+        BorrowedValue::Static(StaticNode::Bool(b)) => AttributeValue::Boolean(*b),
+        BorrowedValue::Static(StaticNode::Null) => AttributeValue::Null,
+        BorrowedValue::String(s) => AttributeValue::String(s.to_string()),
+        BorrowedValue::Array(values) => {
+            AttributeValue::Array(values.iter().map(convert_attribute_value).collect())
+        }
+        BorrowedValue::Object(fields) => AttributeValue::Object(
+            fields
+                .iter()
+                .map(|(k, v)| (k.to_string(), convert_attribute_value(v)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_composite() {
+        let json = r#"{
+            "request": {"mbean": "java.lang:type=Memory", "attribute": "HeapMemoryUsage", "type": "read"},
+            "value": {"used": 123456789, "max": 18446744073709551615, "ratio": 0.5, "name": "heap"},
+            "timestamp": 1609459200,
+            "status": 200
+        }"#;
+
+        let response = parse_response(json, None).unwrap();
+        assert_eq!(response.request.mbean, "java.lang:type=Memory");
+        assert_eq!(
+            response.request.attribute,
+            Some(serde_json::json!("HeapMemoryUsage"))
+        );
+        assert_eq!(response.timestamp, 1609459200);
+        let MBeanValue::Composite(map) = &response.value else {
+            panic!("expected composite, got {:?}", response.value);
+        };
+        assert!(matches!(map["used"], AttributeValue::Integer(123456789)));
+        assert!(matches!(map["max"], AttributeValue::Float(_)));
+        assert!(matches!(map["ratio"], AttributeValue::Float(f) if f == 0.5));
+        assert!(matches!(&map["name"], AttributeValue::String(s) if s == "heap"));
+    }
+
+    #[test]
+    fn test_parse_error_response() {
+        let json = r#"[{
+            "request": {"mbean": "java.lang:type=Missing", "type": "read"},
+            "value": {"ignored": true},
+            "error_type": "javax.management.InstanceNotFoundException",
+            "error": "No MBean",
+            "status": 404
+        }]"#;
+
+        let responses = parse_bulk_response(json, None).unwrap();
+        assert_eq!(responses[0].status, 404);
+        assert_eq!(responses[0].error.as_deref(), Some("No MBean"));
+        assert!(matches!(responses[0].value, MBeanValue::Null));
+    }
+
+    #[test]
+    fn test_parse_filtered_wildcard() {
+        let json = r#"{
+            "request": {"mbean": "java.lang:type=GarbageCollector,*", "type": "read"},
+            "value": {
+                "java.lang:name=G1 Young Generation,type=GarbageCollector": {
+                    "CollectionCount": 10,
+                    "Name": "G1 Young Generation",
+                    "LastGcInfo": {"duration": 5, "id": 3}
+                }
+            },
+            "status": 200
+        }"#;
+        let filter = AttributeFilter::new(|_, attribute| {
+            attribute == "CollectionCount" || attribute == "LastGcInfo<duration>"
+        });
+
+        let response = parse_response(json, Some(&filter)).unwrap();
+        let MBeanValue::Wildcard(mbeans) = &response.value else {
+            panic!("expected wildcard, got {:?}", response.value);
+        };
+        let attrs = &mbeans["java.lang:name=G1 Young Generation,type=GarbageCollector"];
+        assert_eq!(attrs.len(), 2);
+        assert!(matches!(
+            attrs["CollectionCount"],
+            AttributeValue::Integer(10)
+        ));
+        let AttributeValue::Object(gc_info) = &attrs["LastGcInfo"] else {
+            panic!("expected object");
+        };
+        assert_eq!(gc_info.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_wildcard_mbean_errors() {
+        let json = r#"{
+            "request": {"mbean": "java.lang:type=MemoryPool,*", "type": "read"},
+            "value": {
+                "java.lang:name=Eden,type=MemoryPool": {"Usage": {"used": 10}},
+                "java.lang:name=Broken,type=MemoryPool": {"error": "Not supported", "status": 500}
+            },
+            "status": 200
+        }"#;
+        let filter = AttributeFilter::new(|_, attribute| attribute == "Usage<used>");
+
+        let response = parse_response(json, Some(&filter)).unwrap();
+        assert_eq!(response.mbean_errors.len(), 1);
+        assert_eq!(
+            response.mbean_errors[0].mbean,
+            "java.lang:name=Broken,type=MemoryPool"
+        );
+        assert_eq!(response.mbean_errors[0].status, Some(500));
+        assert_eq!(response.mbean_errors[0].error_type, None);
+        let MBeanValue::Wildcard(mbeans) = &response.value else {
+            panic!("expected wildcard, got {:?}", response.value);
+        };
+        assert_eq!(mbeans.len(), 1);
+    }
+
+    #[test]
+    fn test_fall_back_on_unexpected_shape() {
+        // serde_json reports these, so the simd path declines them
+        assert!(parse_response("{not json", None).is_none());
+        assert!(
+            parse_response(r#"{"request": {"mbean": "a:b=c", "type": "read"}}"#, None).is_none()
+        );
+        assert!(parse_response(
+            r#"{"request": {"mbean": "a:b=c", "type": "read"}, "status": "200"}"#,
+            None
+        )
+        .is_none());
+        assert!(parse_bulk_response(r#"{"not": "an array"}"#, None).is_none());
+    }
+}
@@ -0,0 +1,399 @@
+//! Loading of unmodified prometheus/jmx_exporter config files
+//!
+//! jmx_exporter connects to the JVM over RMI and has a few options rJMX does
+//! not. A config file using any of them is translated before it is
+//! deserialized, so an existing jmx_exporter config can be loaded as is:
+//!
+//! | jmx_exporter | rJMX |
+//! |--------------|------|
+//! | `hostPort`, `jmxUrl`, `ssl` | `jolokia.url` (same host, Jolokia port 8778) |
+//! | `username`, `password` | `jolokia.username`, `jolokia.password` |
+//! | `includeObjectNames` | `whitelistObjectNames` |
+//! | `excludeObjectNames` | `blacklistObjectNames` |
+//! | `startDelaySeconds` | `start_delay_seconds` |
+//! | `rules[].type: GAUGE` | `rules[].type: gauge` |
+//! | `rules[]` without `pattern` | `pattern: ".*"` |
+//! | no `rules`, or a rule without `name` matching everything | `export_unmatched: true` |
+//!
+//! Options without an equivalent are dropped with a warning. Settings in a
+//! `jolokia:` section take precedence over the translated ones.
+
+use serde_yaml::{Mapping, Value};
+
+use super::ConfigError;
+
+/// Port of the Jolokia agent assumed when deriving the URL from the JMX address
+const DEFAULT_JOLOKIA_PORT: u16 = 8778;
+
+/// Top-level options that only exist in jmx_exporter configs
+const UPSTREAM_KEYS: &[&str] = &[
+    "hostPort",
+    "jmxUrl",
+    "ssl",
+    "username",
+    "password",
+    "startDelaySeconds",
+    "includeObjectNames",
+    "excludeObjectNames",
+    "includeObjectNameAttributes",
+    "excludeObjectNameAttributes",
+    "autoExcludeObjectNameAttributes",
+    "httpServer",
+    "metricCustomizers",
+];
+
+/// Options without an rJMX equivalent, with a hint for the warning
+const UNSUPPORTED_KEYS: &[(&str, &str)] = &[
+    (
+        "includeObjectNameAttributes",
+        "use collect[].attributes to select attributes",
+    ),
+    (
+        "excludeObjectNameAttributes",
+        "use collect[].attributes to select attributes",
+    ),
+    (
+        "autoExcludeObjectNameAttributes",
+        "attributes are never excluded automatically",
+    ),
+    ("httpServer", "use server.auth and server.tls"),
+    ("metricCustomizers", "use relabel_configs"),
+];
+
+/// Check whether a config document uses jmx_exporter-only options
+pub fn is_jmx_exporter_config(doc: &Value) -> bool {
+    let Some(mapping) = doc.as_mapping() else {
+        return false;
+    };
+    UPSTREAM_KEYS.iter().any(|key| mapping.contains_key(*key))
+}
+
+/// Translate a jmx_exporter config document in place
+///
+/// Returns a warning for every option that was dropped.
+///
+/// # Errors
+///
+/// Returns `ConfigError::ValidationError` if a connection option has the
+/// wrong type or the JMX address cannot be parsed.
+pub fn translate(doc: &mut Value) -> Result<Vec<String>, ConfigError> {
+    let mut warnings = Vec::new();
+    let Some(mapping) = doc.as_mapping_mut() else {
+        return Ok(warnings);
+    };
+
+    let host_port = take_string(mapping, "hostPort")?;
+    let jmx_url = take_string(mapping, "jmxUrl")?;
+    let ssl = match mapping.remove("ssl") {
+        None | Some(Value::Null) => false,
+        Some(Value::Bool(ssl)) => ssl,
+        Some(_) => return Err(invalid("ssl must be a boolean")),
+    };
+    let username = take_string(mapping, "username")?;
+    let password = take_string(mapping, "password")?;
+
+    let host = match (&jmx_url, &host_port) {
+        (Some(url), _) => Some(jmx_url_host(url)?),
+        (None, Some(host_port)) => Some(host_port_host(host_port)?),
+        (None, None) => None,
+    };
+
+    let jolokia = jolokia_section(mapping)?;
+    if let Some(host) = host {
+        if !jolokia.contains_key("url") && !jolokia.contains_key("urls") {
+            let scheme = if ssl { "https" } else { "http" };
+            let url = format!("{}://{}:{}/jolokia", scheme, host, DEFAULT_JOLOKIA_PORT);
+            warnings.push(format!(
+                "{} is a JMX RMI address; using Jolokia at {} (set jolokia.url to override)",
+                if jmx_url.is_some() {
+                    "jmxUrl"
+                } else {
+                    "hostPort"
+                },
+                url
+            ));
+            jolokia.insert("url".into(), url.into());
+        }
+    }
+    for (key, value) in [("username", username), ("password", password)] {
+        if let Some(value) = value {
+            if !jolokia.contains_key(key) {
+                jolokia.insert(key.into(), value.into());
+            }
+        }
+    }
+
+    for (from, to) in [
+        ("includeObjectNames", "whitelistObjectNames"),
+        ("excludeObjectNames", "blacklistObjectNames"),
+    ] {
+        if let Some(names) = mapping.remove(from) {
+            append_names(mapping, to, names)?;
+        }
+    }
+
+    if let Some(delay) = mapping.remove("startDelaySeconds") {
+        if !mapping.contains_key("start_delay_seconds") {
+            mapping.insert("start_delay_seconds".into(), delay);
+        }
+    }
+
+    for (key, hint) in UNSUPPORTED_KEYS {
+        if mapping.remove(*key).is_some() {
+            warnings.push(format!(
+                "{} is not supported and was ignored; {}",
+                key, hint
+            ));
+        }
+    }
+
+    // Without rules, jmx_exporter exports every value under a default name
+    let mut export_unmatched = !mapping.contains_key("rules");
+    if let Some(Value::Sequence(rules)) = mapping.get_mut("rules") {
+        let mut index = 0;
+        rules.retain_mut(|rule| {
+            let keep = translate_rule(rule, index, &mut warnings, &mut export_unmatched);
+            index += 1;
+            keep
+        });
+    }
+    if export_unmatched && !mapping.contains_key("export_unmatched") {
+        mapping.insert("export_unmatched".into(), true.into());
+    }
+
+    Ok(warnings)
+}
+
+/// Translate one rule, returning `false` if it has to be dropped
+///
+/// A catch-all rule without a name (jmx_exporter's default export) is
+/// replaced by `export_unmatched`.
+fn translate_rule(
+    rule: &mut Value,
+    index: usize,
+    warnings: &mut Vec<String>,
+    export_unmatched: &mut bool,
+) -> bool {
+    let Some(rule) = rule.as_mapping_mut() else {
+        return true;
+    };
+
+    if !rule.contains_key("name") {
+        match rule.get("pattern") {
+            None | Some(Value::Null) => *export_unmatched = true,
+            Some(Value::String(pattern)) if pattern == ".*" => *export_unmatched = true,
+            _ => warnings.push(format!(
+                "rules[{}] has no name; default metric names are only supported for \
+                 catch-all rules (export_unmatched), rule dropped",
+                index
+            )),
+        }
+        return false;
+    }
+    if !rule.contains_key("pattern") {
+        rule.insert("pattern".into(), ".*".into());
+    }
+    if let Some(Value::String(metric_type)) = rule.get_mut("type") {
+        *metric_type = metric_type.to_lowercase();
+    }
+
+    true
+}
+
+/// The `jolokia:` section, created if missing
+fn jolokia_section(mapping: &mut Mapping) -> Result<&mut Mapping, ConfigError> {
+    let jolokia = mapping
+        .entry("jolokia".into())
+        .or_insert_with(|| Value::Mapping(Mapping::new()));
+    if jolokia.is_null() {
+        *jolokia = Value::Mapping(Mapping::new());
+    }
+    jolokia
+        .as_mapping_mut()
+        .ok_or_else(|| invalid("jolokia must be a mapping"))
+}
+
+/// Append ObjectName patterns to a native list option
+fn append_names(mapping: &mut Mapping, key: &str, names: Value) -> Result<(), ConfigError> {
+    let names = match names {
+        Value::Null => return Ok(()),
+        Value::Sequence(names) => names,
+        name @ Value::String(_) => vec![name],
+        _ => return Err(invalid(&format!("{} must be a list of ObjectNames", key))),
+    };
+    match mapping
+        .entry(key.into())
+        .or_insert_with(|| Value::Sequence(Vec::new()))
+    {
+        Value::Sequence(existing) => {
+            existing.extend(names);
+            Ok(())
+        }
+        _ => Err(invalid(&format!("{} must be a list of ObjectNames", key))),
+    }
+}
+
+/// Remove a string option
+fn take_string(mapping: &mut Mapping, key: &str) -> Result<Option<String>, ConfigError> {
+    match mapping.remove(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(Value::Number(value)) => Ok(Some(value.to_string())),
+        Some(_) => Err(invalid(&format!("{} must be a string", key))),
+    }
+}
+
+/// Host of a `host:port` address
+fn host_port_host(host_port: &str) -> Result<&str, ConfigError> {
+    match host_port.rsplit_once(':') {
+        Some((host, _)) if !host.is_empty() => Ok(host),
+        _ => Err(invalid(&format!(
+            "hostPort '{}' must have the form host:port",
+            host_port
+        ))),
+    }
+}
+
+/// Host of a JMX service URL, e.g.
+/// `service:jmx:rmi:///jndi/rmi://kafka-1:9999/jmxrmi`
+fn jmx_url_host(url: &str) -> Result<&str, ConfigError> {
+    let address = url
+        .rsplit_once("://")
+        .map(|(_, address)| address)
+        .unwrap_or_default();
+    let address = address.split('/').next().unwrap_or_default();
+    let host = match address.rsplit_once(':') {
+        Some((host, _)) => host,
+        None => address,
+    };
+    if host.is_empty() {
+        return Err(invalid(&format!("jmxUrl '{}' has no host", url)));
+    }
+    Ok(host)
+}
+
+fn invalid(message: &str) -> ConfigError {
+    ConfigError::ValidationError(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translated(yaml: &str) -> (Value, Vec<String>) {
+        let mut doc: Value = serde_yaml::from_str(yaml).unwrap();
+        assert!(is_jmx_exporter_config(&doc));
+        let warnings = translate(&mut doc).unwrap();
+        (doc, warnings)
+    }
+
+    #[test]
+    fn test_detection() {
+        let native: Value =
+            serde_yaml::from_str("jolokia:\n  url: http://localhost:8778/jolokia\nrules: []\n")
+                .unwrap();
+        assert!(!is_jmx_exporter_config(&native));
+
+        // attrNameSnakeCase is a native rule option
+        let snake: Value =
+            serde_yaml::from_str("rules:\n  - {pattern: a, name: b, attrNameSnakeCase: true}\n")
+                .unwrap();
+        assert!(!is_jmx_exporter_config(&snake));
+    }
+
+    #[test]
+    fn test_connection_options() {
+        let (doc, warnings) =
+            translated("hostPort: kafka-1:9999\nusername: monitor\npassword: secret\nssl: true\n");
+        assert_eq!(doc["jolokia"]["url"], "https://kafka-1:8778/jolokia");
+        assert_eq!(doc["jolokia"]["username"], "monitor");
+        assert_eq!(doc["jolokia"]["password"], "secret");
+        assert!(doc.get("hostPort").is_none());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("hostPort"), "{}", warnings[0]);
+
+        let (doc, _) = translated("jmxUrl: service:jmx:rmi:///jndi/rmi://10.0.0.5:9999/jmxrmi\n");
+        assert_eq!(doc["jolokia"]["url"], "http://10.0.0.5:8778/jolokia");
+
+        // An explicit jolokia section wins
+        let (doc, warnings) =
+            translated("hostPort: kafka-1:9999\njolokia:\n  url: http://kafka-1:8080/jolokia\n");
+        assert_eq!(doc["jolokia"]["url"], "http://kafka-1:8080/jolokia");
+        assert!(warnings.is_empty());
+
+        let mut doc: Value = serde_yaml::from_str("hostPort: kafka-1\n").unwrap();
+        assert!(translate(&mut doc).is_err());
+    }
+
+    #[test]
+    fn test_object_names_and_unsupported_keys() {
+        let (doc, warnings) = translated(
+            r#"
+startDelaySeconds: 10
+includeObjectNames: ["kafka.server:*"]
+excludeObjectNames: "kafka.server:type=app-info,*"
+whitelistObjectNames: ["java.lang:*"]
+httpServer:
+  authentication: {}
+"#,
+        );
+        assert_eq!(
+            doc["whitelistObjectNames"],
+            serde_yaml::from_str::<Value>("[\"java.lang:*\", \"kafka.server:*\"]").unwrap()
+        );
+        assert_eq!(
+            doc["blacklistObjectNames"][0],
+            "kafka.server:type=app-info,*"
+        );
+        assert_eq!(doc["start_delay_seconds"], 10);
+        assert!(doc.get("startDelaySeconds").is_none());
+        assert!(doc.get("httpServer").is_none());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("httpServer"));
+    }
+
+    #[test]
+    fn test_rules() {
+        let (doc, warnings) = translated(
+            r#"
+hostPort: localhost:9999
+rules:
+  - pattern: "java.lang<type=Memory><HeapMemoryUsage>(\\w+)"
+    name: jvm_memory_heap_$1
+    type: GAUGE
+    attrNameSnakeCase: true
+  - name: everything
+  - pattern: "java.nio<.*>"
+"#,
+        );
+        let rules = doc["rules"].as_sequence().unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0]["type"], "gauge");
+        assert_eq!(rules[0]["attrNameSnakeCase"], true);
+        assert_eq!(rules[1]["pattern"], ".*");
+        assert!(doc.get("export_unmatched").is_none());
+        assert_eq!(warnings.len(), 2);
+        assert!(
+            warnings[1].starts_with("rules[2] has no name"),
+            "{}",
+            warnings[1]
+        );
+    }
+
+    #[test]
+    fn test_default_export() {
+        let (doc, warnings) = translated("hostPort: localhost:9999\n");
+        assert_eq!(doc["export_unmatched"], true);
+        assert_eq!(warnings.len(), 1);
+
+        let (doc, _) = translated(
+            "hostPort: localhost:9999\nrules:\n  - pattern: 'a<(\\w+)>'\n    name: a_$1\n  - pattern: '.*'\n",
+        );
+        assert_eq!(doc["export_unmatched"], true);
+        assert_eq!(doc["rules"].as_sequence().unwrap().len(), 1);
+
+        let (doc, _) =
+            translated("hostPort: localhost:9999\nrules:\n  - pattern: 'a'\n    name: a\n");
+        assert!(doc.get("export_unmatched").is_none());
+    }
+}
@@ -0,0 +1,276 @@
+//! Config file includes
+//!
+//! A config file can pull in other files, e.g. a shared base config plus
+//! per-application rule files:
+//!
+//! ```yaml
+//! include:
+//!   - base.yaml
+//!   - rules/kafka.yaml
+//!
+//! jolokia:
+//!   url: "http://kafka-1:8778/jolokia"
+//! ```
+//!
+//! Paths are resolved relative to the including file, and included files may
+//! include further files. Documents are merged in a fixed order: the including
+//! file first, then each include in listed order (depth-first):
+//!
+//! - Mappings are merged key by key.
+//! - Lists (`rules`, `collect`, ...) are concatenated in merge order, so the
+//!   including file's rules are tried first.
+//! - Other values keep the first definition in merge order.
+//!
+//! A file reached through several includes is merged once, at its first
+//! occurrence. Include cycles are rejected.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde_yaml::{Mapping, Value};
+
+use super::ConfigError;
+
+/// Top-level key listing files to include
+pub const INCLUDE_KEY: &str = "include";
+
+/// Load a config document from a file, resolving its includes
+///
+/// # Errors
+///
+/// Returns `ConfigError::ReadError` if the file itself cannot be read, and
+/// `ConfigError::ValidationError` for unreadable or invalid included files
+/// and include cycles.
+pub fn load_document(path: &Path) -> Result<Value, ConfigError> {
+    let contents = std::fs::read_to_string(path)?;
+    let doc: Value = serde_yaml::from_str(&contents)?;
+
+    let mut resolver = Resolver::default();
+    let canonical = path.canonicalize()?;
+    resolver.seen.insert(canonical.clone());
+    resolver.stack.push(canonical);
+    resolver.expand(doc, parent_dir(path))
+}
+
+/// Resolve the includes of a parsed document, relative to `base_dir`
+pub fn resolve_includes(doc: Value, base_dir: &Path) -> Result<Value, ConfigError> {
+    Resolver::default().expand(doc, base_dir)
+}
+
+/// Directory that relative includes of `path` are resolved against
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+/// Include resolution state
+#[derive(Default)]
+struct Resolver {
+    /// Files currently being expanded, outermost first (for cycle detection)
+    stack: Vec<PathBuf>,
+    /// Files already merged
+    seen: HashSet<PathBuf>,
+}
+
+impl Resolver {
+    /// Merge a document with its includes
+    fn expand(&mut self, mut doc: Value, dir: &Path) -> Result<Value, ConfigError> {
+        doc.apply_merge()?;
+
+        let includes = match doc.as_mapping_mut() {
+            Some(mapping) => include_paths(mapping)?,
+            None => Vec::new(),
+        };
+
+        for include in includes {
+            let path = dir.join(&include);
+            let canonical = path.canonicalize().map_err(|e| {
+                ConfigError::ValidationError(format!(
+                    "Failed to read included config {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+
+            if let Some(start) = self.stack.iter().position(|p| *p == canonical) {
+                let cycle: Vec<String> = self.stack[start..]
+                    .iter()
+                    .chain(std::iter::once(&canonical))
+                    .map(|p| p.display().to_string())
+                    .collect();
+                return Err(ConfigError::ValidationError(format!(
+                    "Config include cycle: {}",
+                    cycle.join(" -> ")
+                )));
+            }
+            if !self.seen.insert(canonical.clone()) {
+                tracing::debug!(path = %path.display(), "Config already included, skipping");
+                continue;
+            }
+
+            let included = self.load(&path, canonical)?;
+            doc = merge(doc, included);
+        }
+
+        Ok(doc)
+    }
+
+    /// Read and expand an included file
+    fn load(&mut self, path: &Path, canonical: PathBuf) -> Result<Value, ConfigError> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ConfigError::ValidationError(format!(
+                "Failed to read included config {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let doc: Value = serde_yaml::from_str(&contents).map_err(|e| {
+            ConfigError::ValidationError(format!(
+                "Failed to parse included config {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        self.stack.push(canonical);
+        let expanded = self.expand(doc, parent_dir(path));
+        self.stack.pop();
+        expanded
+    }
+}
+
+/// Remove and return the `include:` entries of a document
+fn include_paths(mapping: &mut Mapping) -> Result<Vec<String>, ConfigError> {
+    let invalid = || {
+        ConfigError::ValidationError(
+            "include must be a file path or a list of file paths".to_string(),
+        )
+    };
+
+    match mapping.remove(INCLUDE_KEY) {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::String(path)) => Ok(vec![path]),
+        Some(Value::Sequence(paths)) => paths
+            .into_iter()
+            .map(|p| match p {
+                Value::String(path) => Ok(path),
+                _ => Err(invalid()),
+            })
+            .collect(),
+        Some(_) => Err(invalid()),
+    }
+}
+
+/// Merge `other` into `base`; `base` comes first in merge order
+fn merge(base: Value, other: Value) -> Value {
+    match (base, other) {
+        (Value::Mapping(mut base), Value::Mapping(other)) => {
+            for (key, value) in other {
+                match base.get_mut(&key) {
+                    Some(existing) => *existing = merge(std::mem::take(existing), value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+            Value::Mapping(base)
+        }
+        (Value::Sequence(mut base), Value::Sequence(other)) => {
+            base.extend(other);
+            Value::Sequence(base)
+        }
+        (Value::Null, other) => other,
+        (base, _) => base,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn names(doc: &Value) -> Vec<&str> {
+        doc["rules"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|r| r["name"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_order() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "base.yaml",
+            "server:\n  port: 9000\n  path: /base\nrules:\n  - {pattern: b, name: base}\n",
+        );
+        write(
+            dir.path(),
+            "rules/app.yaml",
+            "include: ../base.yaml\nrules:\n  - {pattern: a, name: app}\n",
+        );
+        let main = write(
+            dir.path(),
+            "main.yaml",
+            "include: [rules/app.yaml, base.yaml]\nserver:\n  port: 8080\nrules:\n  - {pattern: m, name: main}\n",
+        );
+
+        let doc = load_document(&main).unwrap();
+        // base.yaml is reached twice but merged once, after app.yaml
+        assert_eq!(names(&doc), vec!["main", "app", "base"]);
+        assert_eq!(doc["server"]["port"], 8080);
+        assert_eq!(doc["server"]["path"], "/base");
+        assert!(doc.get(INCLUDE_KEY).is_none());
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "a.yaml", "include: b.yaml\n");
+        write(dir.path(), "b.yaml", "include: [a.yaml]\n");
+
+        let err = load_document(&dir.path().join("a.yaml")).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("include cycle"), "{}", message);
+        assert!(message.contains("a.yaml -> "), "{}", message);
+    }
+
+    #[test]
+    fn test_include_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = write(dir.path(), "missing.yaml", "include: nope.yaml\n");
+        let err = load_document(&missing).unwrap_err();
+        assert!(matches!(err, ConfigError::ValidationError(_)));
+        assert!(err.to_string().contains("nope.yaml"));
+
+        let invalid = write(dir.path(), "invalid.yaml", "include: {a: 1}\n");
+        assert!(load_document(&invalid).is_err());
+
+        // A missing top-level file is a read error, so callers can fall back
+        let err = load_document(&dir.path().join("absent.yaml")).unwrap_err();
+        assert!(matches!(err, ConfigError::ReadError(_)));
+    }
+
+    #[test]
+    fn test_merge_values() {
+        let base: Value = serde_yaml::from_str("a: 1\nb: null\nc: [1]\n").unwrap();
+        let other: Value = serde_yaml::from_str("a: 2\nb: 3\nc: [2]\nd: 4\n").unwrap();
+        let merged = merge(base, other);
+        assert_eq!(
+            merged,
+            serde_yaml::from_str::<Value>("a: 1\nb: 3\nc: [1, 2]\nd: 4\n").unwrap()
+        );
+    }
+}
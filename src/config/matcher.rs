@@ -0,0 +1,231 @@
+//! Structured rule matchers
+//!
+//! Instead of a `pattern:` regex over the flattened MBean name, a rule can
+//! `match:` MBeans by domain, ObjectName properties and attribute:
+//!
+//! ```yaml
+//! rules:
+//!   - match:
+//!       domain: java.lang
+//!       type: GarbageCollector
+//!       name: "*"
+//!       attribute: CollectionCount
+//!     name: jvm_gc_collection_count
+//!     type: counter
+//!     labels:
+//!       gc: "$name"
+//! ```
+//!
+//! `domain`, `attribute` and `key` (the key of a composite value, such as
+//! `used` in `HeapMemoryUsage`) are reserved; every other entry is a property
+//! the MBean must have, and properties not listed may also be present.
+//! Values are literal apart from the `*` and `?` wildcards. A value containing
+//! a wildcard is captured as a named group after its key, keeping only
+//! letters and digits (`client-id` becomes `$clientid`). Without `attribute`,
+//! any attribute matches and is captured as `$attribute`; without `key`,
+//! simple and composite values both match and the composite key is captured
+//! as `$key`.
+//!
+//! Matchers are compiled into `pattern` when the configuration is loaded, so
+//! both forms can be mixed in `rules` and the rest of the exporter only sees
+//! patterns.
+
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::transformer::KeyOrder;
+
+/// Segment of a flattened name holding a property the matcher does not list
+const OTHER_PROPERTY: &str = "(?:<[^<>=]+=[^<>]*>)";
+
+/// MBeans and attributes selected by a rule's `match:` section
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RuleMatcher {
+    /// MBean domain (e.g. `java.lang`)
+    pub domain: String,
+
+    /// Attribute name (any attribute if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribute: Option<String>,
+
+    /// Key of a composite attribute value (any key, or none, if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+
+    /// ObjectName properties the MBean must have
+    #[serde(flatten)]
+    #[schemars(with = "std::collections::BTreeMap<String, String>")]
+    pub properties: IndexMap<String, String>,
+}
+
+impl RuleMatcher {
+    /// Regex over flattened names equivalent to this matcher
+    ///
+    /// Properties are expected in `key_order`: sorted for `canonical`, as
+    /// listed for `declared`.
+    pub fn to_pattern(&self, key_order: KeyOrder) -> String {
+        let mut pattern = format!("^{}", glob_regex("domain", &self.domain));
+
+        let mut properties: Vec<(&String, &String)> = self.properties.iter().collect();
+        if key_order == KeyOrder::Canonical {
+            properties.sort_by_key(|(key, _)| *key);
+        }
+        for (key, value) in properties {
+            pattern.push_str(&format!(
+                "{}*?<{}={}>",
+                OTHER_PROPERTY,
+                regex::escape(key),
+                glob_regex(key, value)
+            ));
+        }
+        pattern.push_str(OTHER_PROPERTY);
+        pattern.push('*');
+
+        match &self.attribute {
+            Some(attribute) => {
+                pattern.push_str(&format!("<{}>", glob_regex("attribute", attribute)));
+            }
+            None => pattern.push_str("<(?<attribute>[^<>=]+)>"),
+        }
+        match &self.key {
+            Some(key) => pattern.push_str(&format!("<{}>", glob_regex("key", key))),
+            None => pattern.push_str("(?:<(?<key>[^<>=]+)>)?"),
+        }
+        pattern.push('$');
+        pattern
+    }
+}
+
+/// Regex for a value with `*` and `?` wildcards, captured after `key` if it has any
+fn glob_regex(key: &str, value: &str) -> String {
+    let mut regex = String::with_capacity(value.len());
+    let mut buf = [0; 4];
+    for c in value.chars() {
+        match c {
+            '*' => regex.push_str("[^<>]*"),
+            '?' => regex.push_str("[^<>]"),
+            _ => regex.push_str(&regex::escape(c.encode_utf8(&mut buf))),
+        }
+    }
+    if value.contains(['*', '?']) {
+        format!("(?<{}>{})", group_name(key), regex)
+    } else {
+        regex
+    }
+}
+
+/// Capture group name for a key: its ASCII letters and digits, starting with a letter
+fn group_name(key: &str) -> String {
+    let name: String = key.chars().filter(char::is_ascii_alphanumeric).collect();
+    if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name
+    } else {
+        format!("p{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformer::Rule;
+
+    fn parse(yaml: &str) -> RuleMatcher {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn captures(pattern: &str, name: &str) -> Option<Vec<(String, String)>> {
+        let rule = Rule::new(pattern, "test", Default::default());
+        let regex = rule.compile().unwrap();
+        let captures = regex.captures(name)?;
+        Some(
+            regex
+                .capture_names()
+                .flatten()
+                .filter_map(|group| {
+                    let value = captures.name(group)?;
+                    Some((group.to_string(), value.as_str().to_string()))
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_literal_matcher() {
+        let matcher =
+            parse("domain: java.lang\ntype: GarbageCollector\nattribute: CollectionCount\n");
+        let pattern = matcher.to_pattern(KeyOrder::Canonical);
+
+        let gc = "java.lang<name=G1 Young Generation><type=GarbageCollector><CollectionCount>";
+        assert_eq!(captures(&pattern, gc), Some(vec![]));
+        assert!(captures(&pattern, "java.lang<type=GarbageCollector><CollectionTime>").is_none());
+        assert!(captures(&pattern, "java.lang<type=Memory><CollectionCount>").is_none());
+        assert!(captures(
+            &pattern,
+            "javaXlang<type=GarbageCollector><CollectionCount>"
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_wildcard_captures() {
+        let matcher = parse("domain: kafka.*\ntype: BrokerTopicMetrics\nname: \"*PerSec\"\n");
+        let pattern = matcher.to_pattern(KeyOrder::Canonical);
+
+        let name = "kafka.server<name=BytesInPerSec><topic=orders><type=BrokerTopicMetrics><Count>";
+        let mut found = captures(&pattern, name).unwrap();
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                ("attribute".to_string(), "Count".to_string()),
+                ("domain".to_string(), "kafka.server".to_string()),
+                ("name".to_string(), "BytesInPerSec".to_string()),
+            ]
+        );
+        assert!(captures(
+            &pattern,
+            "kafka.server<name=Bytes><type=BrokerTopicMetrics><Count>"
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_composite_key() {
+        let matcher = parse("domain: java.lang\ntype: Memory\nattribute: HeapMemoryUsage\n");
+        let pattern = matcher.to_pattern(KeyOrder::Canonical);
+        assert_eq!(
+            captures(&pattern, "java.lang<type=Memory><HeapMemoryUsage><used>"),
+            Some(vec![("key".to_string(), "used".to_string())])
+        );
+
+        let matcher =
+            parse("domain: java.lang\ntype: Memory\nattribute: HeapMemoryUsage\nkey: max\n");
+        let pattern = matcher.to_pattern(KeyOrder::Canonical);
+        assert!(captures(&pattern, "java.lang<type=Memory><HeapMemoryUsage><max>").is_some());
+        assert!(captures(&pattern, "java.lang<type=Memory><HeapMemoryUsage><used>").is_none());
+    }
+
+    #[test]
+    fn test_key_order() {
+        let matcher = parse("domain: kafka.server\ntype: Fetch\nclient-id: \"*\"\n");
+        let declared = "kafka.server<type=Fetch><client-id=c1><Count>";
+        let canonical = "kafka.server<client-id=c1><type=Fetch><Count>";
+
+        let pattern = matcher.to_pattern(KeyOrder::Declared);
+        assert!(captures(&pattern, declared)
+            .unwrap()
+            .contains(&("clientid".to_string(), "c1".to_string())));
+        assert!(captures(&pattern, canonical).is_none());
+
+        let pattern = matcher.to_pattern(KeyOrder::Canonical);
+        assert!(captures(&pattern, canonical).is_some());
+        assert!(captures(&pattern, declared).is_none());
+    }
+
+    #[test]
+    fn test_group_name() {
+        assert_eq!(group_name("client-id"), "clientid");
+        assert_eq!(group_name("1st"), "p1st");
+    }
+}
@@ -0,0 +1,4465 @@
+//! Configuration management for rJMX-Exporter
+//!
+//! Handles loading and validating configuration from YAML files.
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, COOKIE, USER_AGENT};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+use thiserror::Error;
+
+use crate::collector::{
+    AttributePatterns, CronSchedule, HttpClientOptions, HttpMethod, QuietWindow,
+};
+use crate::transformer::{
+    ConflictPolicy, CounterResetMode, DerivedMetric, Expr, KeyOrder, MetricType, RelabelAction,
+};
+
+pub mod compat;
+pub mod include;
+pub mod matcher;
+pub mod presets;
+pub mod redact;
+pub mod templates;
+
+pub use matcher::RuleMatcher;
+pub use templates::RuleTemplate;
+
+/// Configuration errors
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    /// Error reading the configuration file
+    #[error("Failed to read config file: {0}")]
+    ReadError(#[from] std::io::Error),
+
+    /// Error parsing the configuration file
+    #[error("Failed to parse config file: {0}")]
+    ParseError(#[from] serde_yaml::Error),
+
+    /// Configuration validation error
+    #[error("Invalid configuration: {0}")]
+    ValidationError(String),
+}
+
+/// Paths served by the exporter itself, which the metrics path must not shadow
+pub const RESERVED_PATHS: &[&str] = &[
+    "/",
+    "/health",
+    "/livez",
+    "/readyz",
+    "/metrics.json",
+    "/debug/rules",
+    "/debug/mbeans",
+    "/debug/pprof/profile",
+    "/api/v1/targets",
+];
+
+/// Main configuration structure
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Config {
+    /// Jolokia endpoint configuration
+    #[serde(default)]
+    pub jolokia: JolokiaConfig,
+
+    /// Jolokia targets read from Prometheus `file_sd` files, replacing `jolokia.url`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_sd: Option<FileSdConfig>,
+
+    /// Add and remove targets at runtime through `/api/v1/targets`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub targets_api: Option<TargetsApiConfig>,
+
+    /// Scrape targets on a schedule and serve `/metrics` from the results
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<BackgroundConfig>,
+
+    /// Persist the latest scrape and serve it after a restart until a live scrape completes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<SnapshotConfig>,
+
+    /// gRPC admin API for fleet-management tooling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc: Option<GrpcConfig>,
+
+    /// Push scraped metrics to a StatsD server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statsd: Option<StatsdConfig>,
+
+    /// HTTP server configuration
+    #[serde(default)]
+    pub server: ServerConfig,
+
+    /// Metric transformation rules
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+
+    /// Bundled rule presets appended after `rules` (e.g. `kafka`, `tomcat`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub presets: Vec<String>,
+
+    /// Parameterized rule templates (expanded into `rules` at load time)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub rule_templates: HashMap<String, RuleTemplate>,
+
+    /// Convert metric names to lowercase (jmx_exporter compatible)
+    #[serde(rename = "lowercaseOutputName", default)]
+    pub lowercase_output_name: bool,
+
+    /// Convert label names to lowercase (jmx_exporter compatible)
+    #[serde(rename = "lowercaseOutputLabelNames", default)]
+    pub lowercase_output_label_names: bool,
+
+    /// Order of ObjectName keys in flattened names: `canonical` (sorted) or
+    /// `declared` (as registered, jmx_exporter compatible)
+    #[serde(rename = "keyOrder", default)]
+    pub key_order: KeyOrder,
+
+    /// Append `_total` to the names of counters that lack it
+    #[serde(default)]
+    pub counter_total_suffix: bool,
+
+    /// What to export when a counter decreases: the `raw` value (default) or
+    /// a `monotonic` value carrying the values before each reset
+    #[serde(default)]
+    pub counter_resets: CounterResetMode,
+
+    /// Export values no rule matches under a name generated from the MBean
+    /// and attribute, like jmx_exporter without rules
+    #[serde(default)]
+    pub export_unmatched: bool,
+
+    /// How samples of one metric name with different TYPE/HELP are resolved
+    #[serde(default)]
+    pub metric_conflict_policy: ConflictPolicy,
+
+    /// Match rules against MBeans on all cores (requires the `parallel` feature)
+    #[serde(default)]
+    pub parallel_rule_matching: bool,
+
+    /// Seconds after which cached values and tracked series expire (never if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub series_ttl_seconds: Option<u64>,
+
+    /// Seconds after startup during which `/metrics` answers 503
+    /// (jmx_exporter `startDelaySeconds`)
+    #[serde(default)]
+    pub start_delay_seconds: u64,
+
+    /// MBean whitelist patterns (glob patterns, jmx_exporter compatible)
+    #[serde(rename = "whitelistObjectNames", default)]
+    pub whitelist_object_names: Vec<String>,
+
+    /// MBean blacklist patterns (glob patterns, jmx_exporter compatible)
+    #[serde(rename = "blacklistObjectNames", default)]
+    pub blacklist_object_names: Vec<String>,
+
+    /// Explicit MBean queries (takes precedence over `whitelistObjectNames`)
+    #[serde(default)]
+    pub collect: Vec<CollectEntry>,
+
+    /// Named MBean groups selectable per scrape with `?collect[]=<group>`
+    ///
+    /// Each group lists ObjectName substrings, matched like `blacklistObjectNames`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub collect_groups: HashMap<String, Vec<String>>,
+
+    /// Named rule modules selectable per scrape with `?module=<name>`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub modules: HashMap<String, ModuleConfig>,
+
+    /// Relabeling applied to every generated metric after rule application
+    #[serde(default)]
+    pub relabel_configs: Vec<RelabelConfig>,
+
+    /// Metric renames (old name -> new name) applied after relabeling
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub renames: HashMap<String, RenameConfig>,
+
+    /// Metrics computed from the other metrics of a scrape, after renames
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub derived_metrics: Vec<DerivedMetricConfig>,
+
+    /// Labels added to every exported metric, after relabeling and renames
+    ///
+    /// Labels already set on a metric are kept.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub external_labels: BTreeMap<String, String>,
+
+    /// Cardinality guardrails
+    #[serde(default)]
+    pub limits: LimitsConfig,
+
+    /// Dead-man's-switch heartbeat
+    #[serde(default)]
+    pub heartbeat: HeartbeatConfig,
+
+    /// Shutdown drain behavior
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+
+    /// Fault injection for resilience testing (requires the `fault-injection` feature)
+    #[serde(default, skip_serializing_if = "FaultInjectionConfig::is_disabled")]
+    pub fault_injection: FaultInjectionConfig,
+
+    /// OpenTelemetry trace export (requires the `otlp` feature)
+    #[serde(default, skip_serializing_if = "TracingConfig::is_disabled")]
+    pub tracing: TracingConfig,
+
+    /// Reject keys that match no option instead of warning about them
+    #[serde(default)]
+    pub strict: bool,
+
+    /// Keys of the loaded document that match no option (e.g. `rules[0].valuefactor`)
+    #[serde(skip)]
+    pub unknown_keys: Vec<String>,
+}
+
+/// Named group of rules and MBean queries
+///
+/// A module's rules are tried before the top-level `rules`; its `collect`
+/// list replaces the top-level MBean selection when non-empty.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ModuleConfig {
+    /// Module-specific transformation rules (templates are expanded)
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+
+    /// MBean queries for the module
+    #[serde(default)]
+    pub collect: Vec<CollectEntry>,
+}
+
+/// Explicit MBean query
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CollectEntry {
+    /// MBean ObjectName or ObjectName pattern (e.g. "java.lang:type=GarbageCollector,*")
+    #[serde(alias = "objectName")]
+    pub object_name: String,
+
+    /// Attributes to read (all attributes if unset)
+    #[serde(default)]
+    pub attributes: Option<Vec<String>>,
+
+    /// Daily UTC windows (`start`/`end` as `HH:MM`) during which this entry is not collected
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quiet_hours: Vec<QuietWindow>,
+
+    /// Regexes of attribute names to collect (all attributes if empty)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_attributes: Vec<String>,
+
+    /// Regexes of attribute names to skip before transformation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_attributes: Vec<String>,
+
+    /// Read at most once per this many seconds, replaying the last values in
+    /// between (every scrape if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_interval_seconds: Option<u64>,
+
+    /// Track `attributes` in Jolokia's history and replay the last values
+    /// only while the agent reports them unchanged
+    #[serde(default)]
+    pub history: bool,
+}
+
+impl CollectEntry {
+    /// Compile `include_attributes` and `exclude_attributes`
+    pub fn attribute_patterns(&self) -> Result<AttributePatterns, regex::Error> {
+        AttributePatterns::new(&self.include_attributes, &self.exclude_attributes)
+    }
+
+    /// Check that the attribute patterns compile
+    fn validate_attribute_patterns(&self) -> Result<(), String> {
+        self.attribute_patterns()
+            .map(|_| ())
+            .map_err(|e| format!("invalid attribute pattern: {}", e))
+    }
+
+    /// Check the entry's patterns and poll interval
+    fn validate(&self) -> Result<(), String> {
+        self.validate_attribute_patterns()?;
+        if self.poll_interval_seconds == Some(0) {
+            return Err("poll_interval_seconds must be greater than 0".to_string());
+        }
+        if self.history {
+            if self.poll_interval_seconds.is_none() {
+                return Err("history requires poll_interval_seconds".to_string());
+            }
+            if self.attributes.as_ref().is_none_or(Vec::is_empty) {
+                return Err("history requires an explicit attributes list".to_string());
+            }
+            if crate::collector::is_pattern(&self.object_name) {
+                return Err("history cannot be used with an ObjectName pattern".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Target of a metric rename
+///
+/// Either just the new name, or `{to, keep_old}` to keep emitting the old
+/// name as well during a migration window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum RenameConfig {
+    /// New metric name
+    Name(String),
+    /// New metric name, optionally keeping the old series
+    Detailed {
+        /// New metric name
+        to: String,
+        /// Also emit the series under the old name
+        #[serde(default)]
+        keep_old: bool,
+    },
+}
+
+impl RenameConfig {
+    /// New metric name
+    pub fn to(&self) -> &str {
+        match self {
+            RenameConfig::Name(to) | RenameConfig::Detailed { to, .. } => to,
+        }
+    }
+
+    /// Whether the old series is still emitted
+    pub fn keep_old(&self) -> bool {
+        matches!(self, RenameConfig::Detailed { keep_old: true, .. })
+    }
+}
+
+/// Metric computed from other metrics by an arithmetic expression
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DerivedMetricConfig {
+    /// Name of the derived metric
+    pub name: String,
+
+    /// Expression over metric names, e.g. `heap_used_bytes / heap_max_bytes`
+    pub expr: String,
+
+    /// Metric type
+    #[serde(default)]
+    pub r#type: DerivedMetricType,
+
+    /// Optional help text for the metric
+    #[serde(default)]
+    pub help: Option<String>,
+}
+
+/// Type of a derived metric
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DerivedMetricType {
+    /// Value that can go up and down
+    #[default]
+    Gauge,
+    /// Monotonically increasing value
+    Counter,
+    /// Type not specified
+    Untyped,
+}
+
+impl From<DerivedMetricType> for MetricType {
+    fn from(metric_type: DerivedMetricType) -> Self {
+        match metric_type {
+            DerivedMetricType::Gauge => MetricType::Gauge,
+            DerivedMetricType::Counter => MetricType::Counter,
+            DerivedMetricType::Untyped => MetricType::Untyped,
+        }
+    }
+}
+
+impl DerivedMetricConfig {
+    /// Check the name and parse the expression into a derived metric
+    pub fn to_derived(&self) -> Result<DerivedMetric, String> {
+        if !is_valid_metric_name(&self.name) {
+            return Err(format!("invalid name '{}'", self.name));
+        }
+        let expr =
+            Expr::parse_metrics(&self.expr).map_err(|e| format!("expr '{}': {}", self.expr, e))?;
+        if expr.metrics().is_empty() {
+            return Err(format!("expr '{}' references no metric", self.expr));
+        }
+        let mut derived = DerivedMetric::new(&self.name, expr).with_type(self.r#type.into());
+        if let Some(help) = &self.help {
+            derived = derived.with_help(help);
+        }
+        Ok(derived)
+    }
+}
+
+/// Cardinality guardrail configuration
+///
+/// Series exceeding these limits are dropped and counted in
+/// `rjmx_series_dropped_total`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct LimitsConfig {
+    /// Maximum number of series emitted per scrape (unlimited if unset)
+    #[serde(default)]
+    pub max_series_per_scrape: Option<usize>,
+
+    /// Maximum label value length in bytes (unlimited if unset)
+    #[serde(default)]
+    pub max_label_value_length: Option<usize>,
+}
+
+/// File-based target discovery
+///
+/// Every `*.json`, `*.yml` and `*.yaml` file in `directory` lists target
+/// groups in Prometheus `file_sd` format. All discovered targets are scraped
+/// with the `jolokia` settings, and the directory is re-read when a file
+/// changes.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FileSdConfig {
+    /// Directory holding the target files
+    pub directory: String,
+
+    /// Seconds between checks for changed files
+    #[serde(default = "default_file_sd_refresh_interval")]
+    pub refresh_interval_seconds: u64,
+}
+
+/// Runtime target management
+///
+/// `POST /api/v1/targets` adds a target and `DELETE /api/v1/targets` removes
+/// one, behind `server.auth`. Added targets are scraped like `file_sd`
+/// targets, replacing `jolokia.url`, and are saved to `state_file` if set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TargetsApiConfig {
+    /// File the added targets are saved to and loaded from, in `file_sd` format
+    #[serde(default)]
+    pub state_file: Option<String>,
+}
+
+/// Background collection
+///
+/// Targets are scraped on their own schedule instead of on each request, and
+/// `/metrics` serves the latest results. `file_sd` targets may override
+/// `interval_seconds` with a `__scrape_interval__` label. A target that fails
+/// or takes longer than its interval is backed off, doubling the interval up
+/// to `max_interval_seconds` until a scrape is healthy again. With
+/// `schedule` (or a `__scrape_schedule__` label), a target is instead scraped
+/// at the minutes its cron schedule selects.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BackgroundConfig {
+    /// Seconds between scrapes of a target
+    #[serde(default = "default_background_interval")]
+    pub interval_seconds: u64,
+
+    /// Fraction of the interval randomly added to or taken from each wait
+    #[serde(default = "default_background_jitter")]
+    pub jitter: f64,
+
+    /// Upper bound of the backed-off interval, in seconds
+    #[serde(default = "default_background_max_interval")]
+    pub max_interval_seconds: u64,
+
+    /// Cron schedule (5 fields, UTC) replacing the interval for every target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<CronSchedule>,
+}
+
+/// On-disk snapshot of the latest scrape
+///
+/// Complete full scrapes are written to `path`. After a restart, `/metrics`
+/// serves the saved metrics, marked stale, while the first live scrape is in
+/// flight. Snapshots older than `max_age_seconds` are not served.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SnapshotConfig {
+    /// File the snapshot is written to and loaded from
+    pub path: String,
+
+    /// Age in seconds beyond which a saved snapshot is ignored
+    #[serde(default = "default_snapshot_max_age")]
+    pub max_age_seconds: u64,
+
+    /// Minimum seconds between two writes of the snapshot file
+    #[serde(default = "default_snapshot_write_interval")]
+    pub write_interval_seconds: u64,
+}
+
+/// StatsD push sink configuration
+///
+/// Every `interval_seconds`, the metrics of a full scrape are sent to
+/// `address` as DogStatsD gauges over UDP. With `delta`, only series whose
+/// value changed since the previous push are sent, with a full push every
+/// `full_resync_seconds`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StatsdConfig {
+    /// StatsD server address (`host:port`)
+    pub address: String,
+
+    /// Seconds between pushes
+    #[serde(default = "default_statsd_interval")]
+    pub interval_seconds: u64,
+
+    /// Send only series whose value changed since the previous push
+    #[serde(default)]
+    pub delta: bool,
+
+    /// Seconds between full pushes in delta mode
+    #[serde(default = "default_statsd_full_resync")]
+    pub full_resync_seconds: u64,
+}
+
+/// gRPC admin API configuration
+///
+/// Serves the `rjmx.admin.v1.Admin` service on its own listener. Requires a
+/// build with the `grpc` feature. When `bearer_token` is set, calls must send
+/// it in the `authorization` metadata.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GrpcConfig {
+    /// Address the admin API listens on (`IP:port`)
+    #[serde(default = "default_grpc_listen_address")]
+    pub listen_address: String,
+
+    /// Bearer token required on every call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer_token: Option<String>,
+
+    /// File holding the bearer token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer_token_file: Option<String>,
+}
+
+/// Dead-man's-switch heartbeat configuration
+///
+/// When `url` is set, the exporter POSTs to it every `interval_seconds`, but
+/// only while the most recent scrape of every target succeeded within
+/// `max_scrape_age_seconds`. An external service (healthchecks.io style)
+/// alerts when the pings stop.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HeartbeatConfig {
+    /// URL to ping (heartbeat disabled if unset)
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// Seconds between heartbeat attempts
+    #[serde(default = "default_heartbeat_interval")]
+    pub interval_seconds: u64,
+
+    /// Maximum age of the last successful scrape, in seconds
+    #[serde(default = "default_heartbeat_max_scrape_age")]
+    pub max_scrape_age_seconds: u64,
+}
+
+/// Shutdown configuration
+///
+/// On SIGTERM or Ctrl+C the exporter stops accepting connections, answers
+/// scrapes on open connections with 503, and waits up to
+/// `grace_period_secs` for in-flight scrapes to finish before exiting.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ShutdownConfig {
+    /// Seconds to wait for in-flight scrapes (0 drops them immediately)
+    #[serde(default = "default_shutdown_grace_period")]
+    pub grace_period_secs: u64,
+}
+
+/// Fault injection configuration (testing only)
+///
+/// Only honored by builds with the `fault-injection` feature; enabling it in
+/// any other build is a validation error.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct FaultInjectionConfig {
+    /// Turn fault injection on
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Latency added to every affected read, in milliseconds
+    #[serde(default)]
+    pub latency_ms: u64,
+
+    /// Fraction (0.0-1.0) of affected reads that fail with a request error
+    #[serde(default)]
+    pub error_rate: f64,
+
+    /// Fraction (0.0-1.0) of affected reads that return malformed JSON
+    #[serde(default)]
+    pub malformed_rate: f64,
+
+    /// Only affect MBeans containing one of these substrings (all if empty)
+    #[serde(default)]
+    pub mbeans: Vec<String>,
+}
+
+impl FaultInjectionConfig {
+    /// Check whether fault injection is off
+    pub fn is_disabled(&self) -> bool {
+        !self.enabled
+    }
+}
+
+/// OpenTelemetry trace export configuration
+///
+/// Only honored by builds with the `otlp` feature; setting an endpoint in
+/// any other build is a validation error.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TracingConfig {
+    /// OTLP/HTTP traces endpoint, e.g. `http://otel-collector:4318/v1/traces`
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+
+    /// `service.name` resource attribute of exported spans
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+
+    /// Fraction (0.0-1.0) of scrapes traced, unless the caller already decided
+    #[serde(default = "default_sample_ratio")]
+    pub sample_ratio: f64,
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: default_service_name(),
+            sample_ratio: default_sample_ratio(),
+        }
+    }
+}
+
+impl TracingConfig {
+    /// Check whether trace export is off
+    pub fn is_disabled(&self) -> bool {
+        self.otlp_endpoint.is_none()
+    }
+}
+
+fn default_service_name() -> String {
+    "rjmx-exporter".to_string()
+}
+
+fn default_sample_ratio() -> f64 {
+    1.0
+}
+
+/// Jolokia endpoint configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct JolokiaConfig {
+    /// Jolokia endpoint URL
+    #[serde(default = "default_jolokia_url")]
+    pub url: String,
+
+    /// Jolokia replicas in priority order, failed over on errors
+    ///
+    /// When set, takes precedence over `url`, which becomes the first entry.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub urls: Vec<String>,
+
+    /// Optional username for basic auth
+    pub username: Option<String>,
+
+    /// Optional password for basic auth
+    pub password: Option<String>,
+
+    /// File holding the basic auth password, read when the config is loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_file: Option<String>,
+
+    /// Authentication scheme, replacing `username` and `password`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<JolokiaAuthConfig>,
+
+    /// HTTP proxy for Jolokia requests, replacing `HTTP_PROXY`/`HTTPS_PROXY`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_url: Option<String>,
+
+    /// Basic auth username for the proxy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_username: Option<String>,
+
+    /// Basic auth password for the proxy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_password: Option<String>,
+
+    /// File holding the proxy password, read when the config is loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_password_file: Option<String>,
+
+    /// Request timeout in milliseconds
+    #[serde(default = "default_timeout")]
+    pub timeout_ms: u64,
+
+    /// HTTP method for Jolokia requests (`post` or `get`)
+    ///
+    /// With `post`, the exporter falls back to `get` automatically if the
+    /// agent answers 405 Method Not Allowed.
+    #[serde(default)]
+    pub http_method: HttpMethod,
+
+    /// Connection pool, keep-alive and HTTP/2 settings
+    #[serde(default)]
+    pub http: HttpClientOptions,
+
+    /// `User-Agent` of Jolokia requests (default: `rjmx-exporter/<version>`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+
+    /// Extra headers sent with every Jolokia request
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+
+    /// Cookies sent with every Jolokia request, e.g. a gateway session
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub cookies: BTreeMap<String, String>,
+
+    /// Retries of failed MBean reads
+    #[serde(default)]
+    pub retry: JolokiaRetryConfig,
+
+    /// Maximum MBeans per bulk request; larger reads are split into
+    /// concurrent batches (unset sends one request)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bulk_batch_size: Option<usize>,
+
+    /// Largest Jolokia response body read, in bytes; larger responses fail
+    /// the request (unset reads responses of any size)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_response_bytes: Option<u64>,
+
+    /// Warn when the agent's clock is off by more than this many seconds
+    /// (`0` disables the warning; the skew is still exported)
+    #[serde(default = "default_clock_skew_warn_seconds")]
+    pub clock_skew_warn_seconds: f64,
+
+    /// Labels added to every metric read from this target, after relabeling
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+/// Retry policy for MBean reads
+///
+/// Connection errors, timeouts and 5xx responses are retried with
+/// exponential backoff. Retries are disabled unless `max_retries` is set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct JolokiaRetryConfig {
+    /// Retries after the first attempt (0 disables retries)
+    #[serde(default)]
+    pub max_retries: u32,
+
+    /// Delay before the first retry in milliseconds
+    #[serde(default = "default_retry_initial_delay_ms")]
+    pub initial_delay_ms: u64,
+
+    /// Upper bound of the delay in milliseconds
+    #[serde(default = "default_retry_max_delay_ms")]
+    pub max_delay_ms: u64,
+
+    /// Factor applied to the delay after each retry
+    #[serde(default = "default_retry_multiplier")]
+    pub multiplier: f64,
+
+    /// Random spread of each delay as a fraction of it (0.0 - 1.0)
+    #[serde(default = "default_retry_jitter")]
+    pub jitter: f64,
+
+    /// Upper bound of a backoff requested with `Retry-After` in milliseconds
+    #[serde(default = "default_retry_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+}
+
+/// Authentication of requests to Jolokia
+///
+/// Secrets can be read from a file with the matching `*_file` option when
+/// the config is loaded.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JolokiaAuthConfig {
+    /// HTTP Basic auth
+    Basic {
+        /// Basic auth username
+        username: String,
+        /// Basic auth password
+        #[serde(default)]
+        password: Option<String>,
+        /// File holding the password
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        password_file: Option<String>,
+    },
+    /// Bearer token in the `Authorization` header
+    Bearer {
+        /// Bearer token
+        #[serde(default)]
+        token: Option<String>,
+        /// File holding the token
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token_file: Option<String>,
+    },
+    /// Static header, e.g. an API key
+    Header {
+        /// Header name
+        name: String,
+        /// Header value
+        #[serde(default)]
+        value: Option<String>,
+        /// File holding the header value
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        value_file: Option<String>,
+    },
+    /// AWS Signature Version 4, e.g. for Jolokia behind API Gateway
+    ///
+    /// Credentials default to `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`
+    /// and `AWS_SESSION_TOKEN`.
+    AwsSigv4 {
+        /// AWS region (e.g. `eu-west-1`)
+        region: String,
+        /// Signing name of the service
+        #[serde(default = "default_sigv4_service")]
+        service: String,
+        /// Access key ID
+        #[serde(default)]
+        access_key_id: Option<String>,
+        /// Secret access key
+        #[serde(default)]
+        secret_access_key: Option<String>,
+        /// Session token of temporary credentials
+        #[serde(default)]
+        session_token: Option<String>,
+    },
+}
+
+/// HTTP server configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ServerConfig {
+    /// Server port
+    #[serde(default = "default_port")]
+    pub port: u16,
+
+    /// Metrics endpoint path
+    #[serde(default = "default_metrics_path")]
+    pub path: String,
+
+    /// Server bind address (IP address or "localhost")
+    ///
+    /// Supported values:
+    /// - IP addresses: "0.0.0.0", "127.0.0.1", "::1", etc.
+    /// - "localhost" (maps to 127.0.0.1)
+    #[serde(default = "default_bind_address")]
+    pub bind_address: String,
+
+    /// Addresses to listen on, one listener each (`IP:port`, IPv6 in
+    /// brackets like `[::]:9090`); replaces `bind_address` and `port` when set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub listen: Vec<String>,
+
+    /// TLS configuration for HTTPS support
+    #[serde(default)]
+    pub tls: TlsConfig,
+
+    /// Compress responses (gzip/deflate) when the client sends `Accept-Encoding`
+    #[serde(default = "default_true")]
+    pub compression: bool,
+
+    /// `/readyz` fails if the last successful scrape is older than this, in seconds
+    #[serde(default = "default_readiness_max_scrape_age")]
+    pub readiness_max_scrape_age_seconds: u64,
+
+    /// Seconds subtracted from Prometheus' `X-Prometheus-Scrape-Timeout-Seconds`
+    /// to get the deadline for Jolokia requests, leaving time to respond
+    #[serde(default = "default_scrape_timeout_offset")]
+    pub scrape_timeout_offset_seconds: f64,
+
+    /// Authentication required on the metrics endpoint (disabled if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthConfig>,
+
+    /// Rate limit on the metrics endpoint (unlimited if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitConfig>,
+
+    /// Request headers copied onto every scraped metric (header name -> label name)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub header_labels: HashMap<String, String>,
+
+    /// Require a listening socket passed by systemd instead of binding `port`
+    ///
+    /// Socket-activated sockets (`LISTEN_FDS`) are used even when this is
+    /// false; setting it makes a missing socket a startup error.
+    #[serde(default)]
+    pub systemd_socket: bool,
+
+    /// Access log written to stdout for every request (`off`, `common`, `json`)
+    #[serde(default)]
+    pub access_log: AccessLogFormat,
+}
+
+/// Format of the per-request access log
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLogFormat {
+    /// No access log
+    #[default]
+    Off,
+    /// NCSA Common Log Format
+    Common,
+    /// One JSON object per line
+    Json,
+}
+
+/// Authentication for the exporter's own endpoints
+///
+/// Either HTTP Basic credentials (`username` + `password_sha256`), a static
+/// `bearer_token`, or both. Passwords are stored as the hex-encoded SHA-256
+/// of the plaintext, e.g. `printf '%s' secret | sha256sum`.
+///
+/// `password_file` and `bearer_token_file` read the plaintext password or the
+/// token from a file (e.g. a mounted Kubernetes secret) when the config is
+/// loaded.
+///
+/// `tenants` adds bearer tokens that only see part of the metrics.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AuthConfig {
+    /// Basic auth username
+    #[serde(default)]
+    pub username: Option<String>,
+
+    /// Hex-encoded SHA-256 hash of the basic auth password
+    #[serde(default)]
+    pub password_sha256: Option<String>,
+
+    /// File holding the plaintext basic auth password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_file: Option<String>,
+
+    /// Static bearer token
+    #[serde(default)]
+    pub bearer_token: Option<String>,
+
+    /// File holding the bearer token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer_token_file: Option<String>,
+
+    /// Identities restricted to a subset of the metrics
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tenants: Vec<TenantConfig>,
+}
+
+/// Bearer token whose scrapes only return matching series
+///
+/// A series is visible if its name starts with one of `metric_prefixes` (any
+/// name if empty) and every label of `match_labels` matches. Tenants cannot
+/// use the debug endpoints.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TenantConfig {
+    /// Tenant name, used in logs
+    pub name: String,
+
+    /// Bearer token identifying the tenant
+    #[serde(default)]
+    pub bearer_token: Option<String>,
+
+    /// File holding the bearer token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer_token_file: Option<String>,
+
+    /// Metric name prefixes the tenant may see (all names if empty)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metric_prefixes: Vec<String>,
+
+    /// Label name -> regex (fully anchored) a visible series must match
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub match_labels: BTreeMap<String, String>,
+}
+
+impl TenantConfig {
+    /// Check the tenant's token, unique among `tokens`, and its filters
+    fn validate(&self, tokens: &[&str]) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+        match self.bearer_token.as_deref() {
+            None | Some("") => return Err("bearer_token must be set".to_string()),
+            Some(token) if tokens.contains(&token) => {
+                return Err("bearer_token is already used by another identity".to_string());
+            }
+            Some(_) => {}
+        }
+        for (label, pattern) in &self.match_labels {
+            regex::Regex::new(pattern)
+                .map_err(|e| format!("match_labels.{}: invalid regex: {}", label, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Token-bucket rate limit on the metrics endpoint
+///
+/// Protects fragile JVMs from misconfigured scrapers. Requests over the
+/// limit get 429 with `Retry-After`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RateLimitConfig {
+    /// Sustained number of scrapes allowed per minute
+    pub max_scrapes_per_minute: u32,
+
+    /// Scrapes allowed back to back (default: `max_scrapes_per_minute`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burst: Option<u32>,
+
+    /// Whether each source IP gets its own budget
+    #[serde(default)]
+    pub per: RateLimitScope,
+}
+
+impl RateLimitConfig {
+    /// Bucket capacity
+    pub fn burst(&self) -> u32 {
+        self.burst.unwrap_or(self.max_scrapes_per_minute)
+    }
+}
+
+/// Clients sharing a rate limit budget
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitScope {
+    /// One budget for all clients
+    #[default]
+    Global,
+    /// One budget per source IP address
+    SourceIp,
+}
+
+/// TLS configuration for HTTPS support
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TlsConfig {
+    /// Enable TLS/HTTPS (default: false)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Path to the TLS certificate file (PEM format)
+    #[serde(default)]
+    pub cert_file: Option<String>,
+
+    /// Path to the TLS private key file (PEM format)
+    #[serde(default)]
+    pub key_file: Option<String>,
+
+    /// Seconds between checks for rotated certificate files (0 disables reloading)
+    #[serde(default = "default_tls_reload_interval")]
+    pub reload_interval_secs: u64,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cert_file: None,
+            key_file: None,
+            reload_interval_secs: default_tls_reload_interval(),
+        }
+    }
+}
+
+/// Metric transformation rule
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Rule {
+    /// MBean pattern to match (regex)
+    #[serde(default)]
+    pub pattern: String,
+
+    /// Structured alternative to `pattern`, compiled into it at load time
+    #[serde(rename = "match", default, skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<RuleMatcher>,
+
+    /// What to do with matching values: emit a metric (default) or drop them
+    #[serde(default, skip_serializing_if = "RuleAction::is_emit")]
+    pub action: RuleAction,
+
+    /// Prometheus metric name (supports $1, $2, etc. for capture groups)
+    #[serde(default)]
+    pub name: String,
+
+    /// Metric type (gauge, counter, histogram, untyped)
+    #[serde(default = "default_metric_type")]
+    pub r#type: String,
+
+    /// Optional help text for the metric
+    pub help: Option<String>,
+
+    /// Optional static labels to add to the metric
+    #[serde(default)]
+    pub labels: std::collections::HashMap<String, String>,
+
+    /// Value extraction expression (jmx_exporter compatible)
+    /// Supports attribute references like "$1" for capture groups
+    pub value: Option<String>,
+
+    /// Value multiplication factor (jmx_exporter compatible)
+    /// The extracted value will be multiplied by this factor; a constant
+    /// expression such as `"1/1024"` is evaluated at load time
+    #[serde(
+        rename = "valueFactor",
+        default,
+        deserialize_with = "deserialize_value_factor"
+    )]
+    #[schemars(with = "Option<ValueFactor>")]
+    pub value_factor: Option<f64>,
+
+    /// Arithmetic expression of the value `x` replacing it (e.g. `x / 1048576`)
+    #[serde(
+        rename = "valueTransform",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub value_transform: Option<String>,
+
+    /// Label names to drop from metrics produced by this rule
+    #[serde(rename = "labelDrops", default)]
+    pub label_drops: Vec<String>,
+
+    /// Label renames (old name -> new name) for metrics produced by this rule
+    #[serde(rename = "labelRenames", default)]
+    pub label_renames: std::collections::HashMap<String, String>,
+
+    /// Collect matched values once and reuse them until reload (jmx_exporter compatible)
+    #[serde(default)]
+    pub cache: bool,
+
+    /// Convert captured values to snake_case in the metric name (jmx_exporter compatible)
+    #[serde(rename = "attrNameSnakeCase", default)]
+    pub attr_name_snake_case: bool,
+
+    /// Replace whitespace in captured values with `_` in label values
+    #[serde(rename = "normalizeLabelValues", default)]
+    pub normalize_label_values: bool,
+
+    /// Also emit histogram rules as Prometheus native histograms over the
+    /// protobuf exposition format
+    #[serde(
+        rename = "nativeHistogram",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub native_histogram: Option<NativeHistogramConfig>,
+
+    /// Example flattened names and the metric this rule should produce for
+    /// them, checked by `--validate` and `--dry-run`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<RuleTest>,
+}
+
+/// `valueFactor` as written in the config: a number or a constant expression
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum ValueFactor {
+    Number(f64),
+    Expression(String),
+}
+
+/// Deserialize `valueFactor`, evaluating constant expressions
+fn deserialize_value_factor<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<ValueFactor>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(ValueFactor::Number(factor)) => Ok(Some(factor)),
+        Some(ValueFactor::Expression(expr)) => Expr::constant(&expr)
+            .map(Some)
+            .map_err(|e| serde::de::Error::custom(format!("valueFactor '{}': {}", expr, e))),
+    }
+}
+
+/// What a rule does with the values it matches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    /// Produce a metric
+    #[default]
+    Emit,
+    /// Discard the values, before any other rule is tried
+    Drop,
+}
+
+impl RuleAction {
+    fn is_emit(&self) -> bool {
+        *self == Self::Emit
+    }
+}
+
+/// Example input of a rule and its expected output
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RuleTest {
+    /// Flattened MBean name (e.g. `java.lang<type=Memory><HeapMemoryUsage><used>`)
+    pub input: String,
+
+    /// Expected metric name (not checked if unset, as for drop rules)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect_name: Option<String>,
+
+    /// Expected labels, all of them (not checked if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect_labels: Option<std::collections::BTreeMap<String, String>>,
+}
+
+impl Rule {
+    /// Check that the rule has a metric name, unless it drops values
+    fn validate_action(&self) -> Result<(), String> {
+        if self.action == RuleAction::Emit && self.name.is_empty() {
+            return Err("name is required unless action is drop".to_string());
+        }
+        Ok(())
+    }
+
+    /// Check that `valueTransform` parses and is not combined with `valueFactor`
+    fn validate_value_transform(&self) -> Result<(), String> {
+        let Some(transform) = &self.value_transform else {
+            return Ok(());
+        };
+        if self.value_factor.is_some() {
+            return Err("set either valueFactor or valueTransform, not both".to_string());
+        }
+        Expr::parse(transform)
+            .map(|_| ())
+            .map_err(|e| format!("valueTransform '{}': {}", transform, e))
+    }
+
+    /// Check the native histogram options of this rule
+    fn validate_native_histogram(&self) -> Result<(), String> {
+        let Some(native) = &self.native_histogram else {
+            return Ok(());
+        };
+        if !self.r#type.eq_ignore_ascii_case("histogram") {
+            return Err("nativeHistogram requires type: histogram".to_string());
+        }
+        if native.schema.is_some() && native.bucket_factor.is_some() {
+            return Err("nativeHistogram: set either schema or bucketFactor, not both".to_string());
+        }
+        if let Some(schema) = native.schema {
+            if !(NativeHistogramConfig::MIN_SCHEMA..=NativeHistogramConfig::MAX_SCHEMA)
+                .contains(&schema)
+            {
+                return Err(format!(
+                    "nativeHistogram.schema must be between {} and {}",
+                    NativeHistogramConfig::MIN_SCHEMA,
+                    NativeHistogramConfig::MAX_SCHEMA
+                ));
+            }
+        }
+        if let Some(factor) = native.bucket_factor {
+            if !factor.is_finite() || factor <= 1.0 {
+                return Err("nativeHistogram.bucketFactor must be greater than 1".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Native histogram resolution for a histogram rule
+///
+/// Native buckets grow exponentially by `2^(2^-schema)`; either set the
+/// schema directly or give the largest acceptable growth factor between
+/// neighbouring buckets and the finest schema within it is used.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct NativeHistogramConfig {
+    /// Bucket schema, from -4 (factor 65536) to 8 (factor ~1.0027)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<i8>,
+
+    /// Maximum growth factor between neighbouring buckets (e.g. 1.1)
+    #[serde(
+        rename = "bucketFactor",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub bucket_factor: Option<f64>,
+}
+
+impl NativeHistogramConfig {
+    /// Coarsest supported schema
+    pub const MIN_SCHEMA: i8 = -4;
+    /// Finest supported schema
+    pub const MAX_SCHEMA: i8 = 8;
+    /// Schema used when neither schema nor bucketFactor is set
+    pub const DEFAULT_SCHEMA: i8 = 3;
+
+    /// Schema to encode with
+    ///
+    /// A bucket factor picks the coarsest schema whose growth factor does not
+    /// exceed it, as the Go client library does.
+    pub fn resolved_schema(&self) -> i8 {
+        if let Some(schema) = self.schema {
+            return schema;
+        }
+        match self.bucket_factor {
+            Some(factor) if factor > 1.0 => {
+                let schema = -factor.log2().log2().floor();
+                schema.clamp(f64::from(Self::MIN_SCHEMA), f64::from(Self::MAX_SCHEMA)) as i8
+            }
+            _ => Self::DEFAULT_SCHEMA,
+        }
+    }
+}
+
+/// Prometheus-style relabel configuration
+///
+/// Applied to generated metrics after rule application.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RelabelConfig {
+    /// Labels whose values are concatenated to form the source value
+    /// (`__name__` refers to the metric name)
+    #[serde(default)]
+    pub source_labels: Vec<String>,
+
+    /// Separator placed between concatenated source label values
+    #[serde(default = "default_relabel_separator")]
+    pub separator: String,
+
+    /// Regex matched against the source value (or label names for labeldrop/labelkeep)
+    #[serde(default = "default_relabel_regex")]
+    pub regex: String,
+
+    /// Label to write for `replace` actions
+    #[serde(default)]
+    pub target_label: Option<String>,
+
+    /// Replacement template for `replace` actions
+    #[serde(default = "default_relabel_replacement")]
+    pub replacement: String,
+
+    /// Relabel action (replace, keep, drop, labeldrop, labelkeep)
+    #[serde(default)]
+    pub action: RelabelAction,
+}
+
+/// Read a secret from a file, dropping the trailing newline editors and
+/// `echo` leave behind
+fn read_secret(path: &str, key: &str) -> Result<String, ConfigError> {
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        ConfigError::ValidationError(format!("Failed to read {} {}: {}", key, path, e))
+    })?;
+    let secret = contents.trim_end_matches(['\r', '\n']);
+    if secret.is_empty() {
+        return Err(ConfigError::ValidationError(format!(
+            "{} {} is empty",
+            key, path
+        )));
+    }
+    Ok(secret.to_string())
+}
+
+/// Location of an ignored key, in the `rules[3].name` form used by validation
+fn key_path(path: &serde_ignored::Path) -> String {
+    use serde_ignored::Path;
+
+    match path {
+        Path::Root => String::new(),
+        Path::Seq { parent, index } => format!("{}[{}]", key_path(parent), index),
+        Path::Map { parent, key } => match key_path(parent) {
+            parent if parent.is_empty() => key.clone(),
+            parent => format!("{}.{}", parent, key),
+        },
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => key_path(parent),
+    }
+}
+
+/// Error for a secret configured both inline and through its `_file` option
+/// Read `file` into `secret`, which must not be set as well
+fn read_secret_into(
+    secret: &mut Option<String>,
+    file: &Option<String>,
+    key: &str,
+) -> Result<(), ConfigError> {
+    if let Some(path) = file {
+        if secret.is_some() {
+            return Err(secret_conflict(key));
+        }
+        *secret = Some(read_secret(path, &format!("{}_file", key))?);
+    }
+    Ok(())
+}
+
+fn secret_conflict(key: &str) -> ConfigError {
+    ConfigError::ValidationError(format!("{} and {}_file are mutually exclusive", key, key))
+}
+
+// Default value functions
+fn default_jolokia_url() -> String {
+    "http://localhost:8778/jolokia".to_string()
+}
+
+fn default_timeout() -> u64 {
+    5000
+}
+
+fn default_retry_initial_delay_ms() -> u64 {
+    100
+}
+
+fn default_retry_max_delay_ms() -> u64 {
+    2000
+}
+
+fn default_retry_multiplier() -> f64 {
+    2.0
+}
+
+fn default_retry_jitter() -> f64 {
+    0.2
+}
+
+fn default_retry_max_backoff_ms() -> u64 {
+    300_000
+}
+
+fn default_sigv4_service() -> String {
+    "execute-api".to_string()
+}
+
+fn default_clock_skew_warn_seconds() -> f64 {
+    5.0
+}
+
+fn default_file_sd_refresh_interval() -> u64 {
+    30
+}
+
+fn default_background_interval() -> u64 {
+    15
+}
+
+fn default_snapshot_max_age() -> u64 {
+    600
+}
+
+fn default_snapshot_write_interval() -> u64 {
+    60
+}
+
+fn default_statsd_interval() -> u64 {
+    15
+}
+
+fn default_statsd_full_resync() -> u64 {
+    300
+}
+
+fn default_grpc_listen_address() -> String {
+    "127.0.0.1:9091".to_string()
+}
+
+fn default_background_jitter() -> f64 {
+    0.1
+}
+
+fn default_background_max_interval() -> u64 {
+    300
+}
+
+fn default_heartbeat_interval() -> u64 {
+    60
+}
+
+fn default_heartbeat_max_scrape_age() -> u64 {
+    300
+}
+
+fn default_shutdown_grace_period() -> u64 {
+    10
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_readiness_max_scrape_age() -> u64 {
+    300
+}
+
+fn default_scrape_timeout_offset() -> f64 {
+    0.5
+}
+
+/// Check that a name is a valid Prometheus metric name
+fn is_valid_metric_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+/// Check that a name is a valid Prometheus label name (and not reserved)
+fn is_valid_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    !name.starts_with("__")
+        && chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn default_tls_reload_interval() -> u64 {
+    60
+}
+
+fn default_port() -> u16 {
+    9090
+}
+
+fn default_metrics_path() -> String {
+    "/metrics".to_string()
+}
+
+fn default_bind_address() -> String {
+    "0.0.0.0".to_string()
+}
+
+fn default_metric_type() -> String {
+    "untyped".to_string()
+}
+
+fn default_relabel_separator() -> String {
+    ";".to_string()
+}
+
+fn default_relabel_regex() -> String {
+    "(.*)".to_string()
+}
+
+fn default_relabel_replacement() -> String {
+    "$1".to_string()
+}
+
+impl Default for JolokiaConfig {
+    fn default() -> Self {
+        Self {
+            url: default_jolokia_url(),
+            urls: Vec::new(),
+            username: None,
+            password: None,
+            password_file: None,
+            auth: None,
+            proxy_url: None,
+            proxy_username: None,
+            proxy_password: None,
+            proxy_password_file: None,
+            timeout_ms: default_timeout(),
+            http_method: HttpMethod::default(),
+            http: HttpClientOptions::default(),
+            user_agent: None,
+            headers: BTreeMap::new(),
+            cookies: BTreeMap::new(),
+            retry: JolokiaRetryConfig::default(),
+            bulk_batch_size: None,
+            max_response_bytes: None,
+            clock_skew_warn_seconds: default_clock_skew_warn_seconds(),
+            labels: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for JolokiaRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            initial_delay_ms: default_retry_initial_delay_ms(),
+            max_delay_ms: default_retry_max_delay_ms(),
+            multiplier: default_retry_multiplier(),
+            jitter: default_retry_jitter(),
+            max_backoff_ms: default_retry_max_backoff_ms(),
+        }
+    }
+}
+
+impl JolokiaConfig {
+    /// Replicas tried after `url`, in order
+    pub fn fallback_urls(&self) -> &[String] {
+        self.urls.get(1..).unwrap_or_default()
+    }
+
+    /// Headers added to every Jolokia request: `user_agent`, `headers` and `cookies`
+    ///
+    /// Fails with a description of the first invalid entry.
+    pub fn request_headers(&self) -> Result<HeaderMap, String> {
+        let mut headers = HeaderMap::new();
+        if let Some(user_agent) = &self.user_agent {
+            let value = HeaderValue::from_str(user_agent).map_err(|_| {
+                format!(
+                    "jolokia.user_agent: '{}' is not a valid header value",
+                    user_agent
+                )
+            })?;
+            headers.insert(USER_AGENT, value);
+        }
+        for (name, value) in &self.headers {
+            let header = HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                format!(
+                    "jolokia.headers: '{}' is not a valid HTTP header name",
+                    name
+                )
+            })?;
+            let mut value = HeaderValue::from_str(value)
+                .map_err(|_| format!("jolokia.headers.{}: not a valid header value", name))?;
+            value.set_sensitive(true);
+            headers.append(header, value);
+        }
+        if !self.cookies.is_empty() {
+            let is_cookie_octet =
+                |c: char| c.is_ascii_graphic() && !matches!(c, '"' | ',' | ';' | '\\');
+            let mut pairs = Vec::with_capacity(self.cookies.len());
+            for (name, value) in &self.cookies {
+                if name.is_empty() || !name.chars().all(|c| is_cookie_octet(c) && c != '=') {
+                    return Err(format!(
+                        "jolokia.cookies: '{}' is not a valid cookie name",
+                        name
+                    ));
+                }
+                if !value.chars().all(is_cookie_octet) {
+                    return Err(format!(
+                        "jolokia.cookies.{}: not a valid cookie value",
+                        name
+                    ));
+                }
+                pairs.push(format!("{}={}", name, value));
+            }
+            let mut value = HeaderValue::from_str(&pairs.join("; "))
+                .map_err(|_| "jolokia.cookies: not a valid header value".to_string())?;
+            value.set_sensitive(true);
+            headers.insert(COOKIE, value);
+        }
+        Ok(headers)
+    }
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            interval_seconds: default_heartbeat_interval(),
+            max_scrape_age_seconds: default_heartbeat_max_scrape_age(),
+        }
+    }
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            grace_period_secs: default_shutdown_grace_period(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Addresses to listen on: `listen`, or else `bind_address` and `port`
+    pub fn listen_addresses(&self) -> Result<Vec<SocketAddr>, ConfigError> {
+        if self.listen.is_empty() {
+            // "localhost" is the only host name accepted
+            let ip: IpAddr = if self.bind_address == "localhost" {
+                IpAddr::V4(Ipv4Addr::LOCALHOST)
+            } else {
+                self.bind_address.parse().map_err(|e| {
+                    ConfigError::ValidationError(format!(
+                        "Invalid bind_address '{}': {}. Use an IP address (e.g., '0.0.0.0', '127.0.0.1') or 'localhost'.",
+                        self.bind_address, e
+                    ))
+                })?
+            };
+            return Ok(vec![SocketAddr::new(ip, self.port)]);
+        }
+
+        let mut addresses = Vec::with_capacity(self.listen.len());
+        for (i, entry) in self.listen.iter().enumerate() {
+            let address: SocketAddr = entry.parse().map_err(|_| {
+                ConfigError::ValidationError(format!(
+                    "server.listen[{}] '{}' is not an IP:port address (IPv6 in brackets, e.g. '[::]:9090')",
+                    i, entry
+                ))
+            })?;
+            if address.port() == 0 {
+                return Err(ConfigError::ValidationError(format!(
+                    "server.listen[{}] '{}': port must be greater than 0",
+                    i, entry
+                )));
+            }
+            if addresses.contains(&address) {
+                return Err(ConfigError::ValidationError(format!(
+                    "server.listen[{}] '{}' is listed more than once",
+                    i, entry
+                )));
+            }
+            addresses.push(address);
+        }
+        Ok(addresses)
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            port: default_port(),
+            path: default_metrics_path(),
+            bind_address: default_bind_address(),
+            listen: Vec::new(),
+            tls: TlsConfig::default(),
+            compression: true,
+            readiness_max_scrape_age_seconds: default_readiness_max_scrape_age(),
+            scrape_timeout_offset_seconds: default_scrape_timeout_offset(),
+            auth: None,
+            rate_limit: None,
+            header_labels: HashMap::new(),
+            systemd_socket: false,
+            access_log: AccessLogFormat::Off,
+        }
+    }
+}
+
+/// Configuration overrides from CLI arguments and environment variables
+///
+/// These are applied on top of config file values.
+/// Fields are Option to indicate "no override" vs "explicit override".
+///
+/// The precedence order is:
+/// 1. CLI arguments (highest priority)
+/// 2. Environment variables
+/// 3. Configuration file
+/// 4. Default values (lowest priority)
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    /// Server port override
+    pub port: Option<u16>,
+    /// Server bind address override
+    pub bind_address: Option<String>,
+    /// Metrics endpoint path override
+    pub metrics_path: Option<String>,
+    /// Jolokia URL override
+    pub jolokia_url: Option<String>,
+    /// Jolokia timeout override (milliseconds)
+    pub jolokia_timeout: Option<u64>,
+    /// Jolokia username override
+    pub username: Option<String>,
+    /// Jolokia password override
+    pub password: Option<String>,
+    /// TLS enabled override
+    pub tls_enabled: Option<bool>,
+    /// TLS certificate file path override
+    pub tls_cert_file: Option<String>,
+    /// TLS private key file path override
+    pub tls_key_file: Option<String>,
+    /// Systemd socket activation override
+    pub systemd_socket: Option<bool>,
+    /// Bundled rule presets to enable in addition to `presets` from the file
+    pub presets: Option<Vec<String>>,
+}
+
+impl Config {
+    /// Load configuration from a YAML file
+    ///
+    /// Files listed under `include:` are merged in, resolved relative to the
+    /// including file.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the configuration file
+    ///
+    /// # Errors
+    /// Returns an error if the file or an included file cannot be read or
+    /// parsed, includes form a cycle, or validation fails
+    ///
+    /// # Note
+    /// - If the file doesn't exist, returns `ConfigError::ReadError`
+    /// - Use `Config::load_or_default()` if you want fallback to defaults
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let config = Self::from_document(include::load_document(path.as_ref())?)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Load the configuration file of a running exporter again
+    ///
+    /// The overrides given at startup are applied on top of the file, and
+    /// the result is validated like the startup configuration.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed, or validation fails
+    pub fn reload<P: AsRef<Path>>(
+        path: P,
+        overrides: &ConfigOverrides,
+    ) -> Result<Self, ConfigError> {
+        let mut config = Self::load(path)?;
+        config.apply_overrides(overrides);
+        config.validate_final()?;
+        Ok(config)
+    }
+
+    /// Parse and validate configuration from a YAML string
+    ///
+    /// Includes are resolved relative to the current directory, and rule
+    /// templates are expanded before the document is deserialized.
+    ///
+    /// # Errors
+    /// Returns an error if the YAML is invalid, an include or template cannot
+    /// be expanded, or validation fails
+    pub fn from_yaml(contents: &str) -> Result<Self, ConfigError> {
+        let value: serde_yaml::Value = serde_yaml::from_str(contents)?;
+        let config = Self::from_document(include::resolve_includes(value, Path::new("."))?)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Build configuration from a merged document without validating it
+    ///
+    /// jmx_exporter configs are translated first (see [`compat`]).
+    fn from_document(mut value: serde_yaml::Value) -> Result<Self, ConfigError> {
+        if compat::is_jmx_exporter_config(&value) {
+            tracing::info!("Translating jmx_exporter config");
+            for warning in compat::translate(&mut value)? {
+                tracing::warn!("{}", warning);
+            }
+        }
+        templates::expand_rule_templates(&mut value)?;
+        let mut unknown_keys = Vec::new();
+        let mut config: Self =
+            serde_ignored::deserialize(value, |path| unknown_keys.push(key_path(&path)))?;
+        config.compile_matchers()?;
+        if !config.strict {
+            for key in &unknown_keys {
+                tracing::warn!(key = %key, "Ignoring unknown config key");
+            }
+        }
+        config.unknown_keys = unknown_keys;
+        config.read_secret_files()?;
+        if let Some(primary) = config.jolokia.urls.first() {
+            config.jolokia.url = primary.clone();
+        }
+        Ok(config)
+    }
+
+    /// Compile the `match:` section of rules into their `pattern`
+    fn compile_matchers(&mut self) -> Result<(), ConfigError> {
+        let key_order = self.key_order;
+        let modules = self
+            .modules
+            .iter_mut()
+            .map(|(name, module)| (format!("modules.{}.rules", name), &mut module.rules));
+        for (section, rules) in
+            std::iter::once(("rules".to_string(), &mut self.rules)).chain(modules)
+        {
+            for (idx, rule) in rules.iter_mut().enumerate() {
+                let Some(matcher) = &rule.matcher else {
+                    continue;
+                };
+                // A serialized config carries both, with the compiled pattern
+                let pattern = matcher.to_pattern(key_order);
+                if !rule.pattern.is_empty() && rule.pattern != pattern {
+                    return Err(ConfigError::ValidationError(format!(
+                        "{}[{}]: set either pattern or match, not both",
+                        section, idx
+                    )));
+                }
+                rule.pattern = pattern;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fill in credentials from the `*_file` options
+    ///
+    /// Setting both a secret and its file is an error, so it is always clear
+    /// which one is in effect.
+    fn read_secret_files(&mut self) -> Result<(), ConfigError> {
+        if let Some(path) = &self.jolokia.password_file {
+            if self.jolokia.password.is_some() {
+                return Err(secret_conflict("jolokia.password"));
+            }
+            self.jolokia.password = Some(read_secret(path, "jolokia.password_file")?);
+        }
+
+        read_secret_into(
+            &mut self.jolokia.proxy_password,
+            &self.jolokia.proxy_password_file,
+            "jolokia.proxy_password",
+        )?;
+
+        match &mut self.jolokia.auth {
+            Some(JolokiaAuthConfig::Basic {
+                password,
+                password_file,
+                ..
+            }) => read_secret_into(password, password_file, "jolokia.auth.password")?,
+            Some(JolokiaAuthConfig::Bearer { token, token_file }) => {
+                read_secret_into(token, token_file, "jolokia.auth.token")?
+            }
+            Some(JolokiaAuthConfig::Header {
+                value, value_file, ..
+            }) => read_secret_into(value, value_file, "jolokia.auth.value")?,
+            Some(JolokiaAuthConfig::AwsSigv4 { .. }) | None => {}
+        }
+
+        if let Some(auth) = &mut self.server.auth {
+            if let Some(path) = &auth.password_file {
+                if auth.password_sha256.is_some() {
+                    return Err(ConfigError::ValidationError(
+                        "server.auth.password_sha256 and server.auth.password_file are mutually exclusive"
+                            .to_string(),
+                    ));
+                }
+                let password = read_secret(path, "server.auth.password_file")?;
+                auth.password_sha256 = Some(format!("{:x}", Sha256::digest(password.as_bytes())));
+            }
+            if let Some(path) = &auth.bearer_token_file {
+                if auth.bearer_token.is_some() {
+                    return Err(secret_conflict("server.auth.bearer_token"));
+                }
+                auth.bearer_token = Some(read_secret(path, "server.auth.bearer_token_file")?);
+            }
+            for (idx, tenant) in auth.tenants.iter_mut().enumerate() {
+                let key = format!("server.auth.tenants[{}].bearer_token", idx);
+                read_secret_into(&mut tenant.bearer_token, &tenant.bearer_token_file, &key)?;
+            }
+        }
+
+        if let Some(grpc) = &mut self.grpc {
+            read_secret_into(
+                &mut grpc.bearer_token,
+                &grpc.bearer_token_file,
+                "grpc.bearer_token",
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Load configuration from a YAML file, falling back to defaults if not found
+    ///
+    /// Use this for optional configuration files (e.g., when running without explicit config)
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let config = Self::load_or_default_unchecked(path)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Like `load_or_default`, but skips semantic validation
+    ///
+    /// Used by lenient validation, which reports every problem instead of
+    /// stopping at the first one. Read and parse errors are still returned.
+    pub fn load_or_default_unchecked<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+
+        match include::load_document(path) {
+            Ok(value) => Self::from_document(value),
+            Err(ConfigError::ReadError(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::warn!(
+                    path = %path.display(),
+                    "Config file not found, using defaults"
+                );
+                Ok(Self::default())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Configured rules followed by the rules of every enabled preset
+    ///
+    /// Unknown preset names are skipped; `validate()` rejects them.
+    pub fn effective_rules(&self) -> impl Iterator<Item = &Rule> {
+        self.rules.iter().chain(
+            self.presets
+                .iter()
+                .filter_map(|name| presets::rules(name))
+                .flatten(),
+        )
+    }
+
+    /// Resolve a named module into the configuration used to serve it
+    ///
+    /// The module's rules are placed before the top-level rules, and its
+    /// `collect` list replaces the top-level one when non-empty. Returns
+    /// `None` for unknown modules.
+    pub fn module(&self, name: &str) -> Option<Config> {
+        let module = self.modules.get(name)?;
+        let mut config = self.clone();
+        config.modules.clear();
+        config.rules = module.rules.iter().chain(&self.rules).cloned().collect();
+        if !module.collect.is_empty() {
+            config.collect = module.collect.clone();
+        }
+        Some(config)
+    }
+
+    /// Apply overrides from CLI/environment variables
+    ///
+    /// This method modifies the config in-place, applying any overrides
+    /// that are set (Some values). The precedence is:
+    /// CLI args > Env vars > Config file > Defaults
+    ///
+    /// Note: clap handles CLI > Env precedence automatically when using
+    /// the `env` attribute, so by the time we receive ConfigOverrides,
+    /// the correct precedence is already applied.
+    pub fn apply_overrides(&mut self, overrides: &ConfigOverrides) {
+        if let Some(port) = overrides.port {
+            tracing::debug!(port, "Applying port override");
+            self.server.port = port;
+            // The port applies to every listen address; invalid ones are
+            // left for validation to report
+            for entry in &mut self.server.listen {
+                if let Ok(mut address) = entry.parse::<SocketAddr>() {
+                    address.set_port(port);
+                    *entry = address.to_string();
+                }
+            }
+        }
+
+        if let Some(ref bind_address) = overrides.bind_address {
+            tracing::debug!(bind_address, "Applying bind_address override");
+            self.server.bind_address = bind_address.clone();
+            // An explicit bind address means a single listener
+            self.server.listen.clear();
+        }
+
+        if let Some(ref metrics_path) = overrides.metrics_path {
+            tracing::debug!(metrics_path, "Applying metrics_path override");
+            self.server.path = metrics_path.clone();
+        }
+
+        if let Some(ref jolokia_url) = overrides.jolokia_url {
+            tracing::debug!(jolokia_url, "Applying jolokia_url override");
+            self.jolokia.url = jolokia_url.clone();
+            self.jolokia.urls.clear();
+        }
+
+        if let Some(timeout) = overrides.jolokia_timeout {
+            tracing::debug!(timeout_ms = timeout, "Applying jolokia_timeout override");
+            self.jolokia.timeout_ms = timeout;
+        }
+
+        if let Some(ref username) = overrides.username {
+            tracing::debug!("Applying username override");
+            self.jolokia.username = Some(username.clone());
+            self.jolokia.auth = None;
+        }
+
+        if let Some(ref password) = overrides.password {
+            tracing::debug!("Applying password override");
+            self.jolokia.password = Some(password.clone());
+        }
+
+        if let Some(tls_enabled) = overrides.tls_enabled {
+            tracing::debug!(tls_enabled, "Applying tls_enabled override");
+            self.server.tls.enabled = tls_enabled;
+        }
+
+        if let Some(systemd_socket) = overrides.systemd_socket {
+            tracing::debug!(systemd_socket, "Applying systemd_socket override");
+            self.server.systemd_socket = systemd_socket;
+        }
+
+        if let Some(ref names) = overrides.presets {
+            for name in names {
+                if !self.presets.contains(name) {
+                    tracing::debug!(preset = %name, "Enabling preset from override");
+                    self.presets.push(name.clone());
+                }
+            }
+        }
+
+        if let Some(ref tls_cert_file) = overrides.tls_cert_file {
+            tracing::debug!(tls_cert_file, "Applying tls_cert_file override");
+            self.server.tls.cert_file = Some(tls_cert_file.clone());
+        }
+
+        if let Some(ref tls_key_file) = overrides.tls_key_file {
+            tracing::debug!(tls_key_file, "Applying tls_key_file override");
+            self.server.tls.key_file = Some(tls_key_file.clone());
+        }
+    }
+
+    /// Validate the final configuration after all overrides are applied
+    ///
+    /// This performs validation that was skipped in the initial load
+    /// because CLI/env overrides may change values.
+    pub fn validate_final(&self) -> Result<(), ConfigError> {
+        // Validate port
+        Self::validate_port(self.server.port)?;
+        self.server.listen_addresses()?;
+
+        // Validate metrics path (in case it was overridden)
+        if !self.server.path.starts_with('/') {
+            return Err(ConfigError::ValidationError(
+                "Metrics path must start with '/'".to_string(),
+            ));
+        }
+
+        if RESERVED_PATHS.contains(&self.server.path.as_str()) {
+            return Err(ConfigError::ValidationError(format!(
+                "Metrics path must not conflict with reserved paths ({})",
+                RESERVED_PATHS.join(", ")
+            )));
+        }
+
+        // Validate TLS configuration
+        if self.server.tls.enabled {
+            if self.server.tls.cert_file.is_none() {
+                return Err(ConfigError::ValidationError(
+                    "TLS is enabled but cert_file is not specified".to_string(),
+                ));
+            }
+            if self.server.tls.key_file.is_none() {
+                return Err(ConfigError::ValidationError(
+                    "TLS is enabled but key_file is not specified".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validate the configuration
+    ///
+    /// Note: Port validation is intentionally NOT done here because CLI arguments
+    /// may override the port value. Port validation should be done after all
+    /// overrides are applied (see main.rs).
+    pub(crate) fn validate(&self) -> Result<(), ConfigError> {
+        if self.strict && !self.unknown_keys.is_empty() {
+            return Err(ConfigError::ValidationError(format!(
+                "Unknown config keys (strict mode): {}",
+                self.unknown_keys.join(", ")
+            )));
+        }
+
+        if !self.server.path.starts_with('/') {
+            return Err(ConfigError::ValidationError(
+                "Metrics path must start with '/'".to_string(),
+            ));
+        }
+
+        if RESERVED_PATHS.contains(&self.server.path.as_str()) {
+            return Err(ConfigError::ValidationError(format!(
+                "Metrics path must not conflict with reserved paths ({})",
+                RESERVED_PATHS.join(", ")
+            )));
+        }
+
+        // Validate TLS configuration
+        if self.server.tls.enabled {
+            if self.server.tls.cert_file.is_none() {
+                return Err(ConfigError::ValidationError(
+                    "TLS is enabled but cert_file is not specified".to_string(),
+                ));
+            }
+            if self.server.tls.key_file.is_none() {
+                return Err(ConfigError::ValidationError(
+                    "TLS is enabled but key_file is not specified".to_string(),
+                ));
+            }
+        }
+
+        let offset = self.server.scrape_timeout_offset_seconds;
+        if !(offset.is_finite() && offset >= 0.0) {
+            return Err(ConfigError::ValidationError(
+                "server.scrape_timeout_offset_seconds must not be negative".to_string(),
+            ));
+        }
+
+        if let Some(auth) = &self.jolokia.auth {
+            if self.jolokia.username.is_some() || self.jolokia.password.is_some() {
+                return Err(ConfigError::ValidationError(
+                    "jolokia.auth and jolokia.username/password are mutually exclusive".to_string(),
+                ));
+            }
+            let missing = match auth {
+                JolokiaAuthConfig::Basic { password, .. } => {
+                    password.is_none().then_some("password")
+                }
+                JolokiaAuthConfig::Bearer { token, .. } => token.is_none().then_some("token"),
+                JolokiaAuthConfig::Header { value, .. } => value.is_none().then_some("value"),
+                JolokiaAuthConfig::AwsSigv4 { .. } => None,
+            };
+            if let Some(field) = missing {
+                return Err(ConfigError::ValidationError(format!(
+                    "jolokia.auth.{} (or {}_file) is required",
+                    field, field
+                )));
+            }
+        }
+
+        if let Some(proxy_url) = &self.jolokia.proxy_url {
+            if !matches!(url::Url::parse(proxy_url), Ok(url) if matches!(url.scheme(), "http" | "https"))
+            {
+                return Err(ConfigError::ValidationError(format!(
+                    "jolokia.proxy_url must be an http(s) URL: {}",
+                    proxy_url
+                )));
+            }
+        } else if self.jolokia.proxy_username.is_some() {
+            return Err(ConfigError::ValidationError(
+                "jolokia.proxy_username requires jolokia.proxy_url".to_string(),
+            ));
+        }
+        if self.jolokia.proxy_username.is_some() != self.jolokia.proxy_password.is_some() {
+            return Err(ConfigError::ValidationError(
+                "jolokia.proxy_username and jolokia.proxy_password must be set together"
+                    .to_string(),
+            ));
+        }
+
+        if let Some(file_sd) = &self.file_sd {
+            if file_sd.refresh_interval_seconds == 0 {
+                return Err(ConfigError::ValidationError(
+                    "file_sd.refresh_interval_seconds must be greater than 0".to_string(),
+                ));
+            }
+            if !self.modules.is_empty() {
+                return Err(ConfigError::ValidationError(
+                    "file_sd cannot be combined with modules".to_string(),
+                ));
+            }
+        }
+
+        if let Some(background) = &self.background {
+            if background.interval_seconds == 0 {
+                return Err(ConfigError::ValidationError(
+                    "background.interval_seconds must be greater than 0".to_string(),
+                ));
+            }
+            if !(0.0..=1.0).contains(&background.jitter) {
+                return Err(ConfigError::ValidationError(
+                    "background.jitter must be between 0 and 1".to_string(),
+                ));
+            }
+            if background.max_interval_seconds < background.interval_seconds {
+                return Err(ConfigError::ValidationError(
+                    "background.max_interval_seconds must be at least background.interval_seconds"
+                        .to_string(),
+                ));
+            }
+        }
+
+        if let Some(targets_api) = &self.targets_api {
+            if self.server.auth.is_none() {
+                return Err(ConfigError::ValidationError(
+                    "targets_api requires server.auth".to_string(),
+                ));
+            }
+            if !self.modules.is_empty() {
+                return Err(ConfigError::ValidationError(
+                    "targets_api cannot be combined with modules".to_string(),
+                ));
+            }
+            if targets_api
+                .state_file
+                .as_deref()
+                .is_some_and(|path| path.trim().is_empty())
+            {
+                return Err(ConfigError::ValidationError(
+                    "targets_api.state_file must not be empty".to_string(),
+                ));
+            }
+        }
+
+        if let Some(snapshot) = &self.snapshot {
+            if snapshot.path.trim().is_empty() {
+                return Err(ConfigError::ValidationError(
+                    "snapshot.path must not be empty".to_string(),
+                ));
+            }
+            if snapshot.max_age_seconds == 0 {
+                return Err(ConfigError::ValidationError(
+                    "snapshot.max_age_seconds must be greater than 0".to_string(),
+                ));
+            }
+        }
+
+        if let Some(statsd) = &self.statsd {
+            if statsd
+                .address
+                .rsplit_once(':')
+                .is_none_or(|(host, port)| host.is_empty() || port.parse::<u16>().is_err())
+            {
+                return Err(ConfigError::ValidationError(format!(
+                    "statsd.address must be a host:port address: {}",
+                    statsd.address
+                )));
+            }
+            if statsd.interval_seconds == 0 {
+                return Err(ConfigError::ValidationError(
+                    "statsd.interval_seconds must be greater than 0".to_string(),
+                ));
+            }
+            if statsd.delta && statsd.full_resync_seconds < statsd.interval_seconds {
+                return Err(ConfigError::ValidationError(
+                    "statsd.full_resync_seconds must not be less than statsd.interval_seconds"
+                        .to_string(),
+                ));
+            }
+        }
+
+        if let Some(grpc) = &self.grpc {
+            if !cfg!(feature = "grpc") {
+                return Err(ConfigError::ValidationError(
+                    "grpc requires a build with the 'grpc' feature".to_string(),
+                ));
+            }
+            if grpc.listen_address.parse::<std::net::SocketAddr>().is_err() {
+                return Err(ConfigError::ValidationError(format!(
+                    "grpc.listen_address must be an IP:port address: {}",
+                    grpc.listen_address
+                )));
+            }
+            if grpc.bearer_token.as_deref() == Some("") {
+                return Err(ConfigError::ValidationError(
+                    "grpc.bearer_token must not be empty".to_string(),
+                ));
+            }
+        }
+
+        if self.jolokia.http.connect_timeout_ms == Some(0) {
+            return Err(ConfigError::ValidationError(
+                "jolokia.http.connect_timeout_ms must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.series_ttl_seconds == Some(0) {
+            return Err(ConfigError::ValidationError(
+                "series_ttl_seconds must be greater than 0".to_string(),
+            ));
+        }
+
+        let skew = self.jolokia.clock_skew_warn_seconds;
+        if !(skew.is_finite() && skew >= 0.0) {
+            return Err(ConfigError::ValidationError(
+                "jolokia.clock_skew_warn_seconds must be zero or positive".to_string(),
+            ));
+        }
+
+        let retry = &self.jolokia.retry;
+        if !(retry.multiplier.is_finite() && retry.multiplier >= 1.0) {
+            return Err(ConfigError::ValidationError(
+                "jolokia.retry.multiplier must be at least 1.0".to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&retry.jitter) {
+            return Err(ConfigError::ValidationError(
+                "jolokia.retry.jitter must be between 0.0 and 1.0".to_string(),
+            ));
+        }
+        if retry.initial_delay_ms > retry.max_delay_ms {
+            return Err(ConfigError::ValidationError(
+                "jolokia.retry.initial_delay_ms must not exceed max_delay_ms".to_string(),
+            ));
+        }
+
+        if self.jolokia.bulk_batch_size == Some(0) {
+            return Err(ConfigError::ValidationError(
+                "jolokia.bulk_batch_size must be greater than 0".to_string(),
+            ));
+        }
+
+        self.jolokia
+            .request_headers()
+            .map_err(ConfigError::ValidationError)?;
+
+        if self.jolokia.max_response_bytes == Some(0) {
+            return Err(ConfigError::ValidationError(
+                "jolokia.max_response_bytes must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.jolokia.urls.iter().any(String::is_empty) {
+            return Err(ConfigError::ValidationError(
+                "jolokia.urls must not contain empty URLs".to_string(),
+            ));
+        }
+
+        // Validate endpoint authentication
+        if let Some(auth) = &self.server.auth {
+            match (&auth.username, &auth.password_sha256) {
+                (Some(_), None) | (None, Some(_)) => {
+                    return Err(ConfigError::ValidationError(
+                        "server.auth requires both username and password_sha256 for basic auth"
+                            .to_string(),
+                    ));
+                }
+                (None, None) if auth.bearer_token.is_none() && auth.tenants.is_empty() => {
+                    return Err(ConfigError::ValidationError(
+                        "server.auth must configure basic auth, bearer_token or tenants"
+                            .to_string(),
+                    ));
+                }
+                _ => {}
+            }
+            if let Some(hash) = &auth.password_sha256 {
+                if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(ConfigError::ValidationError(
+                        "server.auth.password_sha256 must be a 64-character hex SHA-256 digest"
+                            .to_string(),
+                    ));
+                }
+            }
+            if auth.bearer_token.as_deref().is_some_and(str::is_empty) {
+                return Err(ConfigError::ValidationError(
+                    "server.auth.bearer_token must not be empty".to_string(),
+                ));
+            }
+            let mut tokens: Vec<&str> = auth.bearer_token.as_deref().into_iter().collect();
+            for (idx, tenant) in auth.tenants.iter().enumerate() {
+                tenant.validate(&tokens).map_err(|e| {
+                    ConfigError::ValidationError(format!("server.auth.tenants[{}]: {}", idx, e))
+                })?;
+                tokens.extend(tenant.bearer_token.as_deref());
+            }
+        }
+
+        if let Some(rate_limit) = &self.server.rate_limit {
+            if rate_limit.max_scrapes_per_minute == 0 {
+                return Err(ConfigError::ValidationError(
+                    "server.rate_limit.max_scrapes_per_minute must be greater than 0".to_string(),
+                ));
+            }
+            if rate_limit.burst == Some(0) {
+                return Err(ConfigError::ValidationError(
+                    "server.rate_limit.burst must be greater than 0".to_string(),
+                ));
+            }
+        }
+
+        // Validate per-scrape collect groups
+        for (name, patterns) in &self.collect_groups {
+            if name.is_empty() {
+                return Err(ConfigError::ValidationError(
+                    "collect_groups: group name must not be empty".to_string(),
+                ));
+            }
+            if patterns.is_empty() || patterns.iter().any(String::is_empty) {
+                return Err(ConfigError::ValidationError(format!(
+                    "collect_groups.{}: must list at least one non-empty ObjectName pattern",
+                    name
+                )));
+            }
+        }
+
+        // Validate static labels
+        let static_labels = self
+            .jolokia
+            .labels
+            .keys()
+            .map(|name| ("jolokia.labels", name))
+            .chain(
+                self.external_labels
+                    .keys()
+                    .map(|name| ("external_labels", name)),
+            );
+        for (section, name) in static_labels {
+            if !is_valid_label_name(name) {
+                return Err(ConfigError::ValidationError(format!(
+                    "{}: '{}' is not a valid label name",
+                    section, name
+                )));
+            }
+        }
+
+        // Validate header-derived labels
+        for (header, label) in &self.server.header_labels {
+            if axum::http::HeaderName::from_bytes(header.as_bytes()).is_err() {
+                return Err(ConfigError::ValidationError(format!(
+                    "server.header_labels: '{}' is not a valid HTTP header name",
+                    header
+                )));
+            }
+            if !is_valid_label_name(label) {
+                return Err(ConfigError::ValidationError(format!(
+                    "server.header_labels: '{}' is not a valid label name",
+                    label
+                )));
+            }
+        }
+
+        // Validate rule patterns are valid regex
+        for (idx, rule) in self.rules.iter().enumerate() {
+            // Basic regex validation - full validation happens in transformer
+            if rule.pattern.is_empty() {
+                return Err(ConfigError::ValidationError(format!(
+                    "Rule {} has empty pattern",
+                    idx
+                )));
+            }
+            rule.validate_action()
+                .and_then(|()| rule.validate_value_transform())
+                .and_then(|()| rule.validate_native_histogram())
+                .map_err(|e| ConfigError::ValidationError(format!("Rule {}: {}", idx, e)))?;
+        }
+
+        // Validate rule presets
+        for (idx, name) in self.presets.iter().enumerate() {
+            if !presets::exists(name) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Unknown preset '{}' (available: {})",
+                    name,
+                    presets::NAMES.join(", ")
+                )));
+            }
+            if self.presets[..idx].contains(name) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Preset '{}' is listed more than once",
+                    name
+                )));
+            }
+        }
+
+        // Validate explicit MBean queries
+        for (idx, entry) in self.collect.iter().enumerate() {
+            if entry.object_name.trim().is_empty() {
+                return Err(ConfigError::ValidationError(format!(
+                    "Collect entry {} has empty object_name",
+                    idx
+                )));
+            }
+            entry.validate().map_err(|e| {
+                ConfigError::ValidationError(format!("Collect entry {}: {}", idx, e))
+            })?;
+        }
+
+        // Validate named modules
+        for (name, module) in &self.modules {
+            if name.is_empty() {
+                return Err(ConfigError::ValidationError(
+                    "modules: module name must not be empty".to_string(),
+                ));
+            }
+            if module.rules.is_empty() && module.collect.is_empty() {
+                return Err(ConfigError::ValidationError(format!(
+                    "Module '{}' must define rules or collect entries",
+                    name
+                )));
+            }
+            if let Some(idx) = module.rules.iter().position(|r| r.pattern.is_empty()) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Module '{}' rule {} has empty pattern",
+                    name, idx
+                )));
+            }
+            for (idx, rule) in module.rules.iter().enumerate() {
+                rule.validate_action()
+                    .and_then(|()| rule.validate_value_transform())
+                    .and_then(|()| rule.validate_native_histogram())
+                    .map_err(|e| {
+                        ConfigError::ValidationError(format!(
+                            "Module '{}' rule {}: {}",
+                            name, idx, e
+                        ))
+                    })?;
+            }
+            if let Some(idx) = module
+                .collect
+                .iter()
+                .position(|e| e.object_name.trim().is_empty())
+            {
+                return Err(ConfigError::ValidationError(format!(
+                    "Module '{}' collect entry {} has empty object_name",
+                    name, idx
+                )));
+            }
+            for (idx, entry) in module.collect.iter().enumerate() {
+                entry.validate().map_err(|e| {
+                    ConfigError::ValidationError(format!(
+                        "Module '{}' collect entry {}: {}",
+                        name, idx, e
+                    ))
+                })?;
+            }
+        }
+
+        // Validate cardinality limits
+        if self.limits.max_series_per_scrape == Some(0) {
+            return Err(ConfigError::ValidationError(
+                "limits.max_series_per_scrape must be greater than 0".to_string(),
+            ));
+        }
+        if self.limits.max_label_value_length == Some(0) {
+            return Err(ConfigError::ValidationError(
+                "limits.max_label_value_length must be greater than 0".to_string(),
+            ));
+        }
+
+        // Validate heartbeat
+        if let Some(url) = &self.heartbeat.url {
+            if url::Url::parse(url).is_err() {
+                return Err(ConfigError::ValidationError(format!(
+                    "heartbeat.url '{}' is not a valid URL",
+                    url
+                )));
+            }
+            if self.heartbeat.interval_seconds == 0 {
+                return Err(ConfigError::ValidationError(
+                    "heartbeat.interval_seconds must be greater than 0".to_string(),
+                ));
+            }
+            if self.heartbeat.max_scrape_age_seconds == 0 {
+                return Err(ConfigError::ValidationError(
+                    "heartbeat.max_scrape_age_seconds must be greater than 0".to_string(),
+                ));
+            }
+        }
+
+        // Validate fault injection
+        if self.fault_injection.enabled {
+            if !cfg!(feature = "fault-injection") {
+                return Err(ConfigError::ValidationError(
+                    "fault_injection requires a build with the 'fault-injection' feature"
+                        .to_string(),
+                ));
+            }
+            for (name, rate) in [
+                ("error_rate", self.fault_injection.error_rate),
+                ("malformed_rate", self.fault_injection.malformed_rate),
+            ] {
+                if !(0.0..=1.0).contains(&rate) {
+                    return Err(ConfigError::ValidationError(format!(
+                        "fault_injection.{} must be between 0.0 and 1.0",
+                        name
+                    )));
+                }
+            }
+        }
+
+        // Validate trace export
+        if self.parallel_rule_matching && !cfg!(feature = "parallel") {
+            return Err(ConfigError::ValidationError(
+                "parallel_rule_matching requires a build with the 'parallel' feature".to_string(),
+            ));
+        }
+
+        if let Some(endpoint) = &self.tracing.otlp_endpoint {
+            if !cfg!(feature = "otlp") {
+                return Err(ConfigError::ValidationError(
+                    "tracing.otlp_endpoint requires a build with the 'otlp' feature".to_string(),
+                ));
+            }
+            if !matches!(url::Url::parse(endpoint), Ok(url) if matches!(url.scheme(), "http" | "https"))
+            {
+                return Err(ConfigError::ValidationError(format!(
+                    "tracing.otlp_endpoint must be an http(s) URL: {}",
+                    endpoint
+                )));
+            }
+            if !(0.0..=1.0).contains(&self.tracing.sample_ratio) {
+                return Err(ConfigError::ValidationError(
+                    "tracing.sample_ratio must be between 0.0 and 1.0".to_string(),
+                ));
+            }
+        }
+
+        // Validate metric renames
+        for (from, rename) in &self.renames {
+            let to = rename.to();
+            if !is_valid_metric_name(to) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Rename of '{}' has invalid target name '{}'",
+                    from, to
+                )));
+            }
+            if to == from {
+                return Err(ConfigError::ValidationError(format!(
+                    "Rename of '{}' has the same source and target name",
+                    from
+                )));
+            }
+            if self.renames.contains_key(to) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Rename of '{}' targets '{}', which is renamed again; chained renames are not supported",
+                    from, to
+                )));
+            }
+        }
+
+        // Validate derived metrics
+        for (idx, derived) in self.derived_metrics.iter().enumerate() {
+            derived.to_derived().map_err(|e| {
+                ConfigError::ValidationError(format!("Derived metric {}: {}", idx, e))
+            })?;
+        }
+
+        // Validate relabel configs
+        for (idx, relabel) in self.relabel_configs.iter().enumerate() {
+            if relabel.action == RelabelAction::Replace && relabel.target_label.is_none() {
+                return Err(ConfigError::ValidationError(format!(
+                    "Relabel config {} uses action 'replace' but has no target_label",
+                    idx
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Convert config rules to transformer RuleSet
+    //
+    // Note: Requires transformer module - implement when transformer is complete
+    // pub fn to_ruleset(&self) -> crate::transformer::RuleSet {
+    //     todo!("Implement when transformer module is complete")
+    // }
+
+    /// Validate the final port value
+    ///
+    /// Call this after applying CLI overrides to ensure the port is valid.
+    pub fn validate_port(port: u16) -> Result<(), ConfigError> {
+        if port == 0 {
+            return Err(ConfigError::ValidationError(
+                "Server port must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// JSON Schema of the configuration file
+    ///
+    /// Describes the document after includes and rule templates are expanded,
+    /// so `include:` and template references in `rules` are not part of it.
+    pub fn json_schema() -> schemars::Schema {
+        schemars::schema_for!(Config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = Config::default();
+        assert_eq!(config.server.port, 9090);
+        assert_eq!(config.server.path, "/metrics");
+    }
+
+    #[test]
+    fn test_config_validation_path() {
+        let mut config = Config::default();
+        config.server.path = "no-slash".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_port_validation() {
+        assert!(Config::validate_port(0).is_err());
+        assert!(Config::validate_port(8080).is_ok());
+        assert!(Config::validate_port(9090).is_ok());
+    }
+
+    #[test]
+    fn test_rule_pattern_validation() {
+        let mut config = Config::default();
+        config.rules.push(Rule {
+            pattern: String::new(),
+            matcher: None,
+            action: RuleAction::Emit,
+            name: "test_metric".to_string(),
+            r#type: "gauge".to_string(),
+            help: None,
+            labels: std::collections::HashMap::new(),
+            value: None,
+            value_factor: None,
+            value_transform: None,
+            label_drops: vec![],
+            label_renames: std::collections::HashMap::new(),
+            cache: false,
+            attr_name_snake_case: false,
+            normalize_label_values: false,
+            native_histogram: None,
+            tests: vec![],
+        });
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_native_histogram_config() {
+        let yaml = r#"
+rules:
+  - pattern: "metrics<name=(.+)><>Latency"
+    name: app_$1_latency_seconds
+    type: histogram
+    nativeHistogram:
+      bucketFactor: 1.1
+"#;
+        let config = Config::from_yaml(yaml).unwrap();
+        let native = config.rules[0].native_histogram.as_ref().unwrap();
+        assert_eq!(native.bucket_factor, Some(1.1));
+        assert_eq!(native.resolved_schema(), 3);
+
+        let schema = |schema: Option<i8>, bucket_factor: Option<f64>| {
+            NativeHistogramConfig {
+                schema,
+                bucket_factor,
+            }
+            .resolved_schema()
+        };
+        assert_eq!(schema(None, None), 3);
+        assert_eq!(schema(Some(-2), None), -2);
+        assert_eq!(schema(None, Some(2.0)), 0);
+        assert_eq!(schema(None, Some(1.09)), 4);
+        assert_eq!(schema(None, Some(1.0001)), 8);
+        assert_eq!(schema(None, Some(1e30)), -4);
+
+        for invalid in [
+            "rules:\n  - pattern: a\n    name: b\n    type: gauge\n    nativeHistogram: {}\n",
+            "rules:\n  - pattern: a\n    name: b\n    type: histogram\n    nativeHistogram:\n      schema: 9\n",
+            "rules:\n  - pattern: a\n    name: b\n    type: histogram\n    nativeHistogram:\n      bucketFactor: 1.0\n",
+            "rules:\n  - pattern: a\n    name: b\n    type: histogram\n    nativeHistogram:\n      schema: 2\n      bucketFactor: 1.5\n",
+        ] {
+            assert!(Config::from_yaml(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_jolokia_http_options() {
+        let config = Config::default();
+        assert_eq!(config.jolokia.http, HttpClientOptions::default());
+
+        let yaml = r#"
+jolokia:
+  http:
+    pool_max_idle_per_host: 64
+    http2_prior_knowledge: true
+    connect_timeout_ms: 250
+"#;
+        let config = Config::from_yaml(yaml).unwrap();
+        assert_eq!(config.jolokia.http.pool_max_idle_per_host, 64);
+        assert!(config.jolokia.http.http2_prior_knowledge);
+        assert_eq!(config.jolokia.http.connect_timeout_ms, Some(250));
+        // Unset options keep their defaults
+        assert_eq!(config.jolokia.http.tcp_keepalive_secs, 15);
+
+        assert!(Config::from_yaml("jolokia:\n  http:\n    connect_timeout_ms: 0\n").is_err());
+    }
+
+    #[test]
+    fn test_series_ttl() {
+        let config = Config::from_yaml("series_ttl_seconds: 600\n").unwrap();
+        assert_eq!(config.series_ttl_seconds, Some(600));
+        assert_eq!(Config::default().series_ttl_seconds, None);
+        assert!(Config::from_yaml("series_ttl_seconds: 0\n").is_err());
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let yaml = r#"
+servre:
+  port: 9090
+jolokia:
+  http:
+    pool_size: 4
+rules:
+  - pattern: "java.lang<type=Memory>"
+    name: "a"
+    valuefactor: 0.001
+"#;
+        let config = Config::from_yaml(yaml).unwrap();
+        assert_eq!(
+            config.unknown_keys,
+            vec!["servre", "jolokia.http.pool_size", "rules[0].valuefactor"]
+        );
+
+        let err = Config::from_yaml(&format!("strict: true\n{}", yaml)).unwrap_err();
+        assert!(err.to_string().contains("rules[0].valuefactor"), "{}", err);
+        assert!(Config::from_yaml("strict: true\nserver:\n  port: 9090\n").is_ok());
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let schema = serde_json::to_value(Config::json_schema()).unwrap();
+        assert!(schema["properties"]["strict"].is_object());
+        assert!(schema["properties"].get("unknown_keys").is_none());
+        assert!(schema["$defs"]["Rule"]["properties"]["valueFactor"].is_object());
+        assert_eq!(
+            schema["$defs"]["RelabelAction"]["enum"],
+            serde_json::json!(["replace", "keep", "drop", "labeldrop", "labelkeep"])
+        );
+    }
+
+    #[test]
+    fn test_clock_skew_warn_seconds() {
+        assert_eq!(Config::default().jolokia.clock_skew_warn_seconds, 5.0);
+        let config = Config::from_yaml("jolokia:\n  clock_skew_warn_seconds: 0\n").unwrap();
+        assert_eq!(config.jolokia.clock_skew_warn_seconds, 0.0);
+        assert!(Config::from_yaml("jolokia:\n  clock_skew_warn_seconds: -1\n").is_err());
+    }
+
+    #[test]
+    fn test_jolokia_retry() {
+        let config = Config::default();
+        assert_eq!(config.jolokia.retry.max_retries, 0);
+
+        let yaml = r#"
+jolokia:
+  retry:
+    max_retries: 2
+    initial_delay_ms: 50
+    jitter: 0.5
+"#;
+        let config = Config::from_yaml(yaml).unwrap();
+        assert_eq!(config.jolokia.retry.max_retries, 2);
+        assert_eq!(config.jolokia.retry.initial_delay_ms, 50);
+        assert_eq!(config.jolokia.retry.max_delay_ms, 2000);
+        assert_eq!(config.jolokia.retry.jitter, 0.5);
+        assert_eq!(config.jolokia.retry.max_backoff_ms, 300_000);
+
+        for invalid in [
+            "jolokia:\n  retry:\n    multiplier: 0.5\n",
+            "jolokia:\n  retry:\n    jitter: 1.5\n",
+            "jolokia:\n  retry:\n    initial_delay_ms: 5000\n",
+        ] {
+            assert!(Config::from_yaml(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_jolokia_bulk_batch_size() {
+        assert_eq!(Config::default().jolokia.bulk_batch_size, None);
+        let config = Config::from_yaml("jolokia:\n  bulk_batch_size: 50\n").unwrap();
+        assert_eq!(config.jolokia.bulk_batch_size, Some(50));
+        assert!(Config::from_yaml("jolokia:\n  bulk_batch_size: 0\n").is_err());
+    }
+
+    #[test]
+    fn test_jolokia_request_headers() {
+        assert!(Config::default()
+            .jolokia
+            .request_headers()
+            .unwrap()
+            .is_empty());
+
+        let config = Config::from_yaml(
+            r#"
+jolokia:
+  user_agent: "monitoring/1.0"
+  headers:
+    X-Gateway-Tenant: payments
+  cookies:
+    JSESSIONID: abc123
+    route: node-1
+"#,
+        )
+        .unwrap();
+        let headers = config.jolokia.request_headers().unwrap();
+        assert_eq!(headers[USER_AGENT], "monitoring/1.0");
+        assert_eq!(headers["x-gateway-tenant"], "payments");
+        assert_eq!(headers[COOKIE], "JSESSIONID=abc123; route=node-1");
+
+        for yaml in [
+            "jolokia:\n  headers:\n    'Bad Header': x\n",
+            "jolokia:\n  headers:\n    X-Ok: \"line\\nbreak\"\n",
+            "jolokia:\n  cookies:\n    'a=b': x\n",
+            "jolokia:\n  cookies:\n    session: 'a;b'\n",
+            "jolokia:\n  user_agent: \"bad\\nagent\"\n",
+        ] {
+            let err = Config::from_yaml(yaml).unwrap_err().to_string();
+            assert!(err.contains("jolokia."), "{}: {}", yaml, err);
+        }
+    }
+
+    #[test]
+    fn test_jolokia_max_response_bytes() {
+        assert_eq!(Config::default().jolokia.max_response_bytes, None);
+        let config = Config::from_yaml("jolokia:\n  max_response_bytes: 67108864\n").unwrap();
+        assert_eq!(config.jolokia.max_response_bytes, Some(64 * 1024 * 1024));
+        assert!(Config::from_yaml("jolokia:\n  max_response_bytes: 0\n").is_err());
+    }
+
+    #[test]
+    fn test_jolokia_http_method_parsing() {
+        let config = Config::default();
+        assert_eq!(config.jolokia.http_method, HttpMethod::Post);
+
+        let config: Config = serde_yaml::from_str("jolokia:\n  http_method: get\n").unwrap();
+        assert_eq!(config.jolokia.http_method, HttpMethod::Get);
+
+        let invalid: Result<Config, _> = serde_yaml::from_str("jolokia:\n  http_method: put\n");
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_relabel_config_parsing() {
+        let yaml = r#"
+relabel_configs:
+  - action: labeldrop
+    regex: "thread_id"
+  - source_labels: [pool]
+    regex: "G1 (.*)"
+    target_label: pool
+rules:
+  - pattern: "java.lang<type=(\\w+)>"
+    name: "jvm_$1"
+    labelDrops: ["name"]
+    labelRenames:
+      type: mbean_type
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.relabel_configs.len(), 2);
+        assert_eq!(config.relabel_configs[0].action, RelabelAction::LabelDrop);
+        assert_eq!(config.relabel_configs[1].action, RelabelAction::Replace);
+        assert_eq!(config.relabel_configs[1].separator, ";");
+        assert_eq!(config.relabel_configs[1].replacement, "$1");
+        assert_eq!(config.rules[0].label_drops, vec!["name".to_string()]);
+        assert_eq!(
+            config.rules[0].label_renames.get("type"),
+            Some(&"mbean_type".to_string())
+        );
+    }
+
+    #[test]
+    fn test_collect_config() {
+        let yaml = r#"
+collect:
+  - object_name: "java.lang:type=Memory"
+    attributes: [HeapMemoryUsage]
+  - objectName: "java.lang:type=GarbageCollector,*"
+    quiet_hours:
+      - start: "09:00"
+        end: "17:00"
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.collect.len(), 2);
+        assert!(config.collect[0].quiet_hours.is_empty());
+        assert_eq!(config.collect[1].quiet_hours.len(), 1);
+        assert_eq!(
+            config.collect[0].attributes,
+            Some(vec!["HeapMemoryUsage".to_string()])
+        );
+        assert_eq!(
+            config.collect[1].object_name,
+            "java.lang:type=GarbageCollector,*"
+        );
+        assert!(config.collect[1].attributes.is_none());
+
+        let yaml = r#"
+collect:
+  - object_name: "java.lang:type=Memory"
+    quiet_hours:
+      - start: "9am"
+        end: "17:00"
+"#;
+        assert!(serde_yaml::from_str::<Config>(yaml).is_err());
+
+        let yaml = r#"
+collect:
+  - object_name: ""
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_collect_attribute_patterns() {
+        let yaml = r#"
+collect:
+  - object_name: "java.lang:type=GarbageCollector,*"
+    include_attributes: ["Collection.*"]
+    exclude_attributes: ["LastGcInfo"]
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        let patterns = config.collect[0].attribute_patterns().unwrap();
+        assert!(patterns.accepts("CollectionCount"));
+        assert!(!patterns.accepts("LastGcInfo"));
+
+        let yaml = r#"
+collect:
+  - object_name: "java.lang:type=Memory"
+    exclude_attributes: ["(Heap"]
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("Collect entry 0"), "{}", err);
+    }
+
+    #[test]
+    fn test_collect_poll_interval() {
+        let yaml = r#"
+collect:
+  - object_name: "java.lang:type=Runtime"
+    poll_interval_seconds: 300
+  - object_name: "java.lang:type=Memory"
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.collect[0].poll_interval_seconds, Some(300));
+        assert_eq!(config.collect[1].poll_interval_seconds, None);
+
+        let yaml = r#"
+collect:
+  - object_name: "java.lang:type=Runtime"
+    poll_interval_seconds: 0
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("poll_interval_seconds"), "{}", err);
+    }
+
+    #[test]
+    fn test_collect_history() {
+        let yaml = r#"
+collect:
+  - object_name: "java.lang:type=Runtime"
+    attributes: [StartTime, VmVersion]
+    poll_interval_seconds: 300
+    history: true
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        assert!(config.collect[0].history);
+
+        for (entry, message) in [
+            (
+                "object_name: \"java.lang:type=Runtime\"\n    attributes: [StartTime]",
+                "requires poll_interval_seconds",
+            ),
+            (
+                "object_name: \"java.lang:type=Runtime\"\n    poll_interval_seconds: 300",
+                "explicit attributes",
+            ),
+            (
+                "object_name: \"java.lang:type=GarbageCollector,*\"\n    attributes: [CollectionCount]\n    poll_interval_seconds: 300",
+                "ObjectName pattern",
+            ),
+        ] {
+            let yaml = format!("collect:\n  - {}\n    history: true\n", entry);
+            let config: Config = serde_yaml::from_str(&yaml).unwrap();
+            let err = config.validate().unwrap_err().to_string();
+            assert!(err.contains(message), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_server_compression_config() {
+        assert!(Config::default().server.compression);
+
+        let config: Config = serde_yaml::from_str("server:\n  compression: false\n").unwrap();
+        assert!(!config.server.compression);
+    }
+
+    #[test]
+    fn test_server_rate_limit_config() {
+        assert!(Config::default().server.rate_limit.is_none());
+
+        let yaml = r#"
+server:
+  rate_limit:
+    max_scrapes_per_minute: 6
+    per: source_ip
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        let rate_limit = config.server.rate_limit.clone().unwrap();
+        assert_eq!(rate_limit.max_scrapes_per_minute, 6);
+        assert_eq!(rate_limit.burst(), 6);
+        assert_eq!(rate_limit.per, RateLimitScope::SourceIp);
+        assert!(config.validate().is_ok());
+
+        config.server.rate_limit.as_mut().unwrap().burst = Some(0);
+        assert!(config.validate().is_err());
+        config
+            .server
+            .rate_limit
+            .as_mut()
+            .unwrap()
+            .max_scrapes_per_minute = 0;
+        config.server.rate_limit.as_mut().unwrap().burst = None;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_server_scrape_timeout_offset() {
+        assert_eq!(Config::default().server.scrape_timeout_offset_seconds, 0.5);
+
+        let mut config: Config =
+            serde_yaml::from_str("server:\n  scrape_timeout_offset_seconds: 1.5\n").unwrap();
+        assert_eq!(config.server.scrape_timeout_offset_seconds, 1.5);
+        assert!(config.validate().is_ok());
+
+        config.server.scrape_timeout_offset_seconds = -1.0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_server_auth_config() {
+        let yaml = r#"
+server:
+  auth:
+    username: prometheus
+    password_sha256: "2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b"
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        let auth = config.server.auth.unwrap();
+        assert_eq!(auth.username.as_deref(), Some("prometheus"));
+        assert!(auth.bearer_token.is_none());
+
+        let config: Config =
+            serde_yaml::from_str("server:\n  auth:\n    bearer_token: token\n").unwrap();
+        assert!(config.validate().is_ok());
+
+        let config: Config = serde_yaml::from_str("server:\n  auth: {}\n").unwrap();
+        assert!(config.validate().is_err());
+
+        let config: Config =
+            serde_yaml::from_str("server:\n  auth:\n    username: prometheus\n").unwrap();
+        assert!(config.validate().is_err());
+
+        let config: Config = serde_yaml::from_str(
+            "server:\n  auth:\n    username: prometheus\n    password_sha256: secret\n",
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_load_with_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("rules")).unwrap();
+        std::fs::write(
+            dir.path().join("rules/jvm.yaml"),
+            r#"
+rules:
+  - pattern: "java\\.lang<type=Threading><ThreadCount>"
+    name: "jvm_threads_current"
+collect:
+  - object_name: "java.lang:type=Threading"
+"#,
+        )
+        .unwrap();
+        let main = dir.path().join("config.yaml");
+        std::fs::write(
+            &main,
+            r#"
+include: [rules/jvm.yaml]
+server:
+  port: 9100
+rules:
+  - pattern: "java\\.lang<type=Memory><HeapMemoryUsage><used>"
+    name: "jvm_heap_used_bytes"
+"#,
+        )
+        .unwrap();
+
+        let config = Config::load(&main).unwrap();
+        assert_eq!(config.server.port, 9100);
+        let names: Vec<&str> = config.rules.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["jvm_heap_used_bytes", "jvm_threads_current"]);
+        assert_eq!(config.collect.len(), 1);
+
+        // A broken include is an error, not a fallback to defaults
+        std::fs::write(&main, "include: missing.yaml\n").unwrap();
+        assert!(Config::load_or_default(&main).is_err());
+    }
+
+    #[test]
+    fn test_jmx_exporter_config() {
+        // Unmodified jmx_exporter config
+        let yaml = r#"
+startDelaySeconds: 0
+hostPort: kafka-1:9999
+username: monitor
+password: secret
+ssl: false
+lowercaseOutputName: true
+includeObjectNames: ["kafka.server:*"]
+rules:
+  - pattern: "kafka.server<type=(.+), name=(.+)><>Count"
+    name: kafka_server_$1_$2_total
+    type: COUNTER
+    attrNameSnakeCase: true
+"#;
+        let config = Config::from_yaml(yaml).unwrap();
+        assert_eq!(config.jolokia.url, "http://kafka-1:8778/jolokia");
+        assert_eq!(config.jolokia.username.as_deref(), Some("monitor"));
+        assert_eq!(config.jolokia.password.as_deref(), Some("secret"));
+        assert!(config.lowercase_output_name);
+        assert_eq!(config.start_delay_seconds, 0);
+        assert_eq!(config.whitelist_object_names, vec!["kafka.server:*"]);
+        assert_eq!(config.rules.len(), 1);
+        assert_eq!(config.rules[0].r#type, "counter");
+        assert!(config.rules[0].attr_name_snake_case);
+    }
+
+    #[test]
+    fn test_secret_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let jolokia_password = dir.path().join("jolokia-password");
+        let auth_password = dir.path().join("auth-password");
+        let token = dir.path().join("token");
+        std::fs::write(&jolokia_password, "jmx-secret\n").unwrap();
+        std::fs::write(&auth_password, "secret\n").unwrap();
+        std::fs::write(&token, "scrape-token").unwrap();
+
+        let yaml = format!(
+            "jolokia:\n  password_file: {}\nserver:\n  auth:\n    username: prometheus\n    password_file: {}\n    bearer_token_file: {}\n",
+            jolokia_password.display(),
+            auth_password.display(),
+            token.display()
+        );
+        let config = Config::from_yaml(&yaml).unwrap();
+        assert_eq!(config.jolokia.password.as_deref(), Some("jmx-secret"));
+        let auth = config.server.auth.as_ref().unwrap();
+        assert_eq!(
+            auth.password_sha256.as_deref(),
+            Some("2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b")
+        );
+        assert_eq!(auth.bearer_token.as_deref(), Some("scrape-token"));
+        assert!(config.validate().is_ok());
+
+        // Inline secret and file together
+        let yaml = format!(
+            "jolokia:\n  password: inline\n  password_file: {}\n",
+            jolokia_password.display()
+        );
+        let err = Config::from_yaml(&yaml).unwrap_err();
+        assert!(err.to_string().contains("mutually exclusive"), "{}", err);
+
+        // Missing and empty files
+        let err =
+            Config::from_yaml("jolokia:\n  password_file: /nonexistent/secret\n").unwrap_err();
+        assert!(err.to_string().contains("jolokia.password_file"), "{}", err);
+
+        let empty = dir.path().join("empty");
+        std::fs::write(&empty, "\n").unwrap();
+        let yaml = format!(
+            "server:\n  auth:\n    bearer_token_file: {}\n",
+            empty.display()
+        );
+        assert!(Config::from_yaml(&yaml).is_err());
+    }
+
+    #[test]
+    fn test_jolokia_proxy() {
+        let config = Config::from_yaml(
+            "jolokia:\n  proxy_url: http://proxy.corp:3128\n  proxy_username: monitor\n  proxy_password: secret\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.jolokia.proxy_url.as_deref(),
+            Some("http://proxy.corp:3128")
+        );
+
+        for yaml in [
+            "jolokia:\n  proxy_url: ftp://proxy.corp\n",
+            "jolokia:\n  proxy_username: monitor\n  proxy_password: secret\n",
+            "jolokia:\n  proxy_url: http://proxy.corp:3128\n  proxy_username: monitor\n",
+        ] {
+            assert!(Config::from_yaml(yaml).is_err(), "{}", yaml);
+        }
+    }
+
+    #[test]
+    fn test_file_sd() {
+        let config = Config::from_yaml("file_sd:\n  directory: /etc/rjmx/targets\n").unwrap();
+        let file_sd = config.file_sd.unwrap();
+        assert_eq!(file_sd.directory, "/etc/rjmx/targets");
+        assert_eq!(file_sd.refresh_interval_seconds, 30);
+
+        for yaml in [
+            "file_sd:\n  directory: targets\n  refresh_interval_seconds: 0\n",
+            "file_sd:\n  directory: targets\nmodules:\n  kafka:\n    rules: []\n",
+        ] {
+            assert!(Config::from_yaml(yaml).is_err(), "{}", yaml);
+        }
+    }
+
+    #[test]
+    fn test_key_order() {
+        assert_eq!(Config::default().key_order, KeyOrder::Canonical);
+        let config = Config::from_yaml("keyOrder: declared\n").unwrap();
+        assert_eq!(config.key_order, KeyOrder::Declared);
+        assert!(Config::from_yaml("keyOrder: sorted\n").is_err());
+    }
+
+    #[test]
+    fn test_background() {
+        let config = Config::from_yaml("background: {}\n").unwrap();
+        let background = config.background.unwrap();
+        assert_eq!(background.interval_seconds, 15);
+        assert_eq!(background.jitter, 0.1);
+        assert_eq!(background.max_interval_seconds, 300);
+        assert!(background.schedule.is_none());
+        assert!(Config::default().background.is_none());
+
+        let config = Config::from_yaml("background:\n  schedule: '*/5 8-18 * * 1-5'\n").unwrap();
+        let schedule = config.background.unwrap().schedule.unwrap();
+        assert_eq!(schedule.to_string(), "*/5 8-18 * * 1-5 UTC");
+
+        for yaml in [
+            "background:\n  schedule: '* * *'\n",
+            "background:\n  interval_seconds: 0\n",
+            "background:\n  jitter: 1.5\n",
+            "background:\n  interval_seconds: 60\n  max_interval_seconds: 30\n",
+        ] {
+            assert!(Config::from_yaml(yaml).is_err(), "{}", yaml);
+        }
+    }
+
+    #[test]
+    fn test_rule_action() {
+        let config = Config::from_yaml(
+            "rules:\n  - pattern: 'java.lang<type=Compilation>'\n    action: drop\n",
+        )
+        .unwrap();
+        assert_eq!(config.rules[0].action, RuleAction::Drop);
+        assert!(config.rules[0].name.is_empty());
+
+        let err = Config::from_yaml("rules:\n  - pattern: 'java.lang<type=Compilation>'\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Rule 0: name is required"), "{}", err);
+        assert!(Config::from_yaml("rules:\n  - pattern: x\n    action: keep\n").is_err());
+    }
+
+    #[test]
+    fn test_value_expressions() {
+        let yaml = r#"
+rules:
+  - pattern: "java.lang<type=Memory><HeapMemoryUsage>(\\w+)"
+    name: jvm_memory_heap_$1_kibibytes
+    valueFactor: "1/1024"
+  - pattern: "java.lang<type=Memory><NonHeapMemoryUsage>(\\w+)"
+    name: jvm_memory_nonheap_$1_mebibytes
+    valueTransform: "x / 1048576"
+"#;
+        let config = Config::from_yaml(yaml).unwrap();
+        assert_eq!(config.rules[0].value_factor, Some(1.0 / 1024.0));
+        assert_eq!(
+            config.rules[1].value_transform.as_deref(),
+            Some("x / 1048576")
+        );
+
+        let err =
+            Config::from_yaml("rules:\n  - pattern: x\n    name: y\n    valueFactor: 'x * 2'\n")
+                .unwrap_err()
+                .to_string();
+        assert!(err.contains("x is not allowed"), "{}", err);
+        let err = Config::from_yaml(
+            "rules:\n  - pattern: x\n    name: y\n    valueFactor: 2\n    valueTransform: x\n",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("Rule 0: set either valueFactor"), "{}", err);
+        let err =
+            Config::from_yaml("rules:\n  - pattern: x\n    name: y\n    valueTransform: 'x /'\n")
+                .unwrap_err()
+                .to_string();
+        assert!(err.contains("valueTransform 'x /'"), "{}", err);
+    }
+
+    #[test]
+    fn test_derived_metrics_config() {
+        let yaml = r#"
+derived_metrics:
+  - name: jvm_memory_heap_utilization_ratio
+    expr: jvm_memory_heap_used_bytes / jvm_memory_heap_max_bytes
+    help: Fraction of the maximum heap in use
+"#;
+        let config = Config::from_yaml(yaml).unwrap();
+        assert_eq!(config.derived_metrics.len(), 1);
+        assert_eq!(config.derived_metrics[0].r#type, DerivedMetricType::Gauge);
+        let derived = config.derived_metrics[0].to_derived().unwrap();
+        assert_eq!(derived.metric_type, MetricType::Gauge);
+        assert_eq!(
+            derived.help.as_deref(),
+            Some("Fraction of the maximum heap in use")
+        );
+
+        let err =
+            Config::from_yaml("derived_metrics:\n  - name: a\n    expr: b\n    type: histogram\n")
+                .unwrap_err()
+                .to_string();
+        assert!(err.contains("unknown variant `histogram`"), "{}", err);
+
+        for (yaml, error) in [
+            (
+                "derived_metrics:\n  - name: 1bad\n    expr: a\n",
+                "invalid name",
+            ),
+            (
+                "derived_metrics:\n  - name: a\n    expr: 'b /'\n",
+                "expr 'b /'",
+            ),
+            (
+                "derived_metrics:\n  - name: a\n    expr: '1 + 2'\n",
+                "expr '1 + 2' references no metric",
+            ),
+        ] {
+            let err = Config::from_yaml(yaml).unwrap_err().to_string();
+            assert!(
+                err.contains(&format!("Derived metric 0: {}", error)),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let config = Config::from_yaml("snapshot:\n  path: /var/lib/rjmx/snapshot.json\n").unwrap();
+        let snapshot = config.snapshot.unwrap();
+        assert_eq!(snapshot.path, "/var/lib/rjmx/snapshot.json");
+        assert_eq!(snapshot.max_age_seconds, 600);
+        assert!(Config::default().snapshot.is_none());
+
+        for yaml in [
+            "snapshot:\n  path: ''\n",
+            "snapshot:\n  path: snapshot.json\n  max_age_seconds: 0\n",
+        ] {
+            assert!(Config::from_yaml(yaml).is_err(), "{}", yaml);
+        }
+    }
+
+    #[test]
+    fn test_targets_api() {
+        let yaml = "targets_api:\n  state_file: /var/lib/rjmx/targets.json\nserver:\n  auth:\n    bearer_token: admin\n";
+        let config = Config::from_yaml(yaml).unwrap();
+        assert_eq!(
+            config.targets_api.unwrap().state_file.as_deref(),
+            Some("/var/lib/rjmx/targets.json")
+        );
+
+        let error = Config::from_yaml("targets_api: {}\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("requires server.auth"), "{}", error);
+    }
+
+    #[test]
+    fn test_grpc() {
+        assert!(Config::default().grpc.is_none());
+        let config = Config::from_yaml("grpc:\n  bearer_token: secret\n");
+        if !cfg!(feature = "grpc") {
+            let error = config.unwrap_err().to_string();
+            assert!(error.contains("'grpc' feature"), "{}", error);
+            return;
+        }
+        let grpc = config.unwrap().grpc.unwrap();
+        assert_eq!(grpc.listen_address, "127.0.0.1:9091");
+        assert_eq!(grpc.bearer_token.as_deref(), Some("secret"));
+        let token_file = GrpcConfig {
+            bearer_token: None,
+            bearer_token_file: Some("/run/secrets/grpc-token".to_string()),
+            ..grpc
+        };
+        let yaml = serde_yaml::to_string(&token_file).unwrap();
+        assert!(!yaml.contains("bearer_token:"), "{}", yaml);
+
+        for yaml in [
+            "grpc:\n  listen_address: localhost:9091\n",
+            "grpc:\n  bearer_token: ''\n",
+        ] {
+            assert!(Config::from_yaml(yaml).is_err(), "{}", yaml);
+        }
+    }
+
+    #[test]
+    fn test_auth_tenants() {
+        let yaml = |tenant: &str| {
+            format!(
+                "server:\n  auth:\n    bearer_token: admin\n    tenants:\n      - {}\n",
+                tenant
+            )
+        };
+        let config = Config::from_yaml(&yaml(
+            "{name: payments, bearer_token: pay, metric_prefixes: [payments_], match_labels: {app: 'pay.*'}}",
+        ))
+        .unwrap();
+        let tenant = &config.server.auth.unwrap().tenants[0];
+        assert_eq!(tenant.metric_prefixes, vec!["payments_"]);
+        assert_eq!(tenant.match_labels["app"], "pay.*");
+
+        for (tenant, error) in [
+            ("{name: '', bearer_token: pay}", "name must not be empty"),
+            ("{name: payments}", "bearer_token must be set"),
+            (
+                "{name: payments, bearer_token: admin}",
+                "bearer_token is already used",
+            ),
+            (
+                "{name: payments, bearer_token: pay, match_labels: {app: '('}}",
+                "match_labels.app: invalid regex",
+            ),
+        ] {
+            let err = Config::from_yaml(&yaml(tenant)).unwrap_err().to_string();
+            assert!(
+                err.contains(&format!("server.auth.tenants[0]: {}", error)),
+                "{}",
+                err
+            );
+        }
+
+        // Tenants alone are enough
+        assert!(Config::from_yaml(
+            "server:\n  auth:\n    tenants:\n      - {name: a, bearer_token: t}\n"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_rule_matchers() {
+        let yaml = r#"
+keyOrder: declared
+rules:
+  - match:
+      domain: java.lang
+      type: Memory
+      attribute: HeapMemoryUsage
+    name: jvm_memory_heap_$key
+modules:
+  gc:
+    rules:
+      - match:
+          domain: java.lang
+          type: GarbageCollector
+          name: "*"
+        name: jvm_gc_$attribute
+"#;
+        let config = Config::from_yaml(yaml).unwrap();
+        let matcher = config.rules[0].matcher.as_ref().unwrap();
+        assert_eq!(
+            config.rules[0].pattern,
+            matcher.to_pattern(KeyOrder::Declared)
+        );
+        assert!(config.modules["gc"].rules[0].pattern.contains("(?<name>"));
+
+        // Serialized configs carry the compiled pattern and load again
+        let reloaded = Config::from_yaml(&serde_yaml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(reloaded.rules[0].pattern, config.rules[0].pattern);
+
+        let both = "rules:\n  - pattern: 'java.lang<type=Memory>'\n    match:\n      domain: java.lang\n    name: x\n";
+        let err = Config::from_yaml(both).unwrap_err().to_string();
+        assert!(
+            err.contains("rules[0]: set either pattern or match"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_jolokia_auth() {
+        let dir = tempfile::tempdir().unwrap();
+        let token = dir.path().join("token");
+        std::fs::write(&token, "jolokia-token\n").unwrap();
+
+        let yaml = format!(
+            "jolokia:\n  auth:\n    type: bearer\n    token_file: {}\n",
+            token.display()
+        );
+        let config = Config::from_yaml(&yaml).unwrap();
+        assert!(matches!(
+            &config.jolokia.auth,
+            Some(JolokiaAuthConfig::Bearer { token: Some(token), .. }) if token == "jolokia-token"
+        ));
+        assert!(config.validate().is_ok());
+
+        let config =
+            Config::from_yaml("jolokia:\n  auth:\n    type: aws_sigv4\n    region: eu-west-1\n")
+                .unwrap();
+        assert!(matches!(
+            &config.jolokia.auth,
+            Some(JolokiaAuthConfig::AwsSigv4 { service, access_key_id: None, .. }) if service == "execute-api"
+        ));
+        assert!(config.validate().is_ok());
+
+        // Missing secret
+        let err = Config::from_yaml("jolokia:\n  auth:\n    type: header\n    name: X-Api-Key\n")
+            .unwrap_err();
+        assert!(err.to_string().contains("jolokia.auth.value"), "{}", err);
+
+        // Together with the legacy basic auth options
+        let err = Config::from_yaml(
+            "jolokia:\n  username: admin\n  password: secret\n  auth:\n    type: bearer\n    token: t\n",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("mutually exclusive"), "{}", err);
+    }
+
+    #[test]
+    fn test_presets_config() {
+        let yaml = r#"
+presets: [tomcat]
+rules:
+  - pattern: "custom"
+    name: "custom_metric"
+"#;
+        let mut config = Config::from_yaml(yaml).unwrap();
+        let rules: Vec<&Rule> = config.effective_rules().collect();
+        assert_eq!(rules[0].name, "custom_metric");
+        assert_eq!(rules.len(), 1 + presets::rules("tomcat").unwrap().len());
+
+        // Overrides add presets without duplicating configured ones
+        config.apply_overrides(&ConfigOverrides {
+            presets: Some(vec!["tomcat".to_string(), "kafka".to_string()]),
+            ..Default::default()
+        });
+        assert_eq!(config.presets, vec!["tomcat", "kafka"]);
+
+        assert!(Config::from_yaml("presets: [weblogic]").is_err());
+        assert!(Config::from_yaml("presets: [kafka, kafka]").is_err());
+    }
+
+    #[test]
+    fn test_modules_config() {
+        let yaml = r#"
+rules:
+  - pattern: "java\\.lang<type=Threading><ThreadCount>"
+    name: "jvm_threads_current"
+collect:
+  - object_name: "java.lang:type=Threading"
+modules:
+  kafka:
+    rules:
+      - pattern: "kafka\\.server<type=(\\w+)><>Count"
+        name: "kafka_$1_total"
+    collect:
+      - object_name: "kafka.server:type=BrokerTopicMetrics,*"
+  jvm:
+    rules:
+      - pattern: "java\\.lang<type=Memory><HeapMemoryUsage><used>"
+        name: "jvm_heap_used_bytes"
+"#;
+        let config = Config::from_yaml(yaml).unwrap();
+        assert!(config.module("unknown").is_none());
+
+        let kafka = config.module("kafka").unwrap();
+        assert!(kafka.modules.is_empty());
+        assert_eq!(kafka.rules.len(), 2);
+        assert_eq!(kafka.rules[0].name, "kafka_$1_total");
+        assert_eq!(kafka.rules[1].name, "jvm_threads_current");
+        assert_eq!(
+            kafka.collect[0].object_name,
+            "kafka.server:type=BrokerTopicMetrics,*"
+        );
+
+        // Without its own collect list, a module keeps the top-level one
+        let jvm = config.module("jvm").unwrap();
+        assert_eq!(jvm.collect[0].object_name, "java.lang:type=Threading");
+
+        assert!(Config::from_yaml("modules:\n  empty: {}\n").is_err());
+    }
+
+    #[test]
+    fn test_collect_groups_config() {
+        let yaml = r#"
+collect_groups:
+  memory: ["java.lang:type=Memory", "java.lang:type=MemoryPool"]
+  gc: ["java.lang:type=GarbageCollector"]
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.collect_groups["memory"].len(), 2);
+
+        for invalid in [
+            "collect_groups:\n  memory: []\n",
+            "collect_groups:\n  memory: [\"\"]\n",
+        ] {
+            let config: Config = serde_yaml::from_str(invalid).unwrap();
+            assert!(config.validate().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_static_labels_config() {
+        let yaml = r#"
+jolokia:
+  labels:
+    broker: kafka-1
+external_labels:
+  cluster: prod
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.jolokia.labels["broker"], "kafka-1");
+        assert_eq!(config.external_labels["cluster"], "prod");
+
+        for invalid in [
+            "external_labels:\n  k8s-cluster: prod\n",
+            "jolokia:\n  labels:\n    __broker: kafka-1\n",
+        ] {
+            let config: Config = serde_yaml::from_str(invalid).unwrap();
+            assert!(config.validate().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_header_labels_config() {
+        let yaml = r#"
+server:
+  header_labels:
+    X-Scrape-Shard: shard
+    X-Tenant: tenant
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.server.header_labels["X-Scrape-Shard"], "shard");
+        assert!(Config::default().server.header_labels.is_empty());
+
+        for invalid in [
+            "server:\n  header_labels:\n    \"X Shard\": shard\n",
+            "server:\n  header_labels:\n    X-Shard: scrape-shard\n",
+            "server:\n  header_labels:\n    X-Shard: __shard\n",
+        ] {
+            let config: Config = serde_yaml::from_str(invalid).unwrap();
+            assert!(config.validate().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parallel_rule_matching_config() {
+        assert!(!Config::default().parallel_rule_matching);
+
+        let config: Config = serde_yaml::from_str("parallel_rule_matching: true\n").unwrap();
+        assert!(config.parallel_rule_matching);
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "parallel"));
+    }
+
+    #[test]
+    fn test_fault_injection_config() {
+        assert!(Config::default().fault_injection.is_disabled());
+
+        let yaml = r#"
+fault_injection:
+  enabled: true
+  latency_ms: 500
+  error_rate: 0.1
+  mbeans: ["kafka."]
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.fault_injection.latency_ms, 500);
+        assert_eq!(config.fault_injection.mbeans, vec!["kafka.".to_string()]);
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "fault-injection"));
+
+        // Disabled sections are accepted by every build
+        let config: Config = serde_yaml::from_str("fault_injection:\n  error_rate: 0.5\n").unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_tracing_config() {
+        let config = Config::default();
+        assert!(config.tracing.is_disabled());
+        assert_eq!(config.tracing.service_name, "rjmx-exporter");
+
+        let config: Config = serde_yaml::from_str(
+            "tracing:\n  otlp_endpoint: http://otel-collector:4318/v1/traces\n  sample_ratio: 0.25\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.tracing.otlp_endpoint.as_deref(),
+            Some("http://otel-collector:4318/v1/traces")
+        );
+        assert_eq!(config.tracing.sample_ratio, 0.25);
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "otlp"));
+    }
+
+    #[cfg(feature = "otlp")]
+    #[test]
+    fn test_tracing_validation() {
+        let config: Config =
+            serde_yaml::from_str("tracing:\n  otlp_endpoint: otel-collector:4318\n").unwrap();
+        assert!(config.validate().is_err());
+
+        let config: Config = serde_yaml::from_str(
+            "tracing:\n  otlp_endpoint: http://otel-collector:4318\n  sample_ratio: 2\n",
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[cfg(feature = "fault-injection")]
+    #[test]
+    fn test_fault_injection_rate_validation() {
+        let config: Config =
+            serde_yaml::from_str("fault_injection:\n  enabled: true\n  error_rate: 1.5\n").unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_renames_config() {
+        let yaml = r#"
+renames:
+  jvm_threads_ThreadCount: jvm_threads_current
+  jvm_memory_heap_used_bytes:
+    to: jvm_memory_used_bytes
+    keep_old: true
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+
+        let simple = &config.renames["jvm_threads_ThreadCount"];
+        assert_eq!(simple.to(), "jvm_threads_current");
+        assert!(!simple.keep_old());
+
+        let detailed = &config.renames["jvm_memory_heap_used_bytes"];
+        assert_eq!(detailed.to(), "jvm_memory_used_bytes");
+        assert!(detailed.keep_old());
+
+        for invalid in [
+            "renames:\n  a: \"1bad\"\n",
+            "renames:\n  a: a\n",
+            "renames:\n  a: b\n  b: c\n",
+        ] {
+            let config: Config = serde_yaml::from_str(invalid).unwrap();
+            assert!(config.validate().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_statsd_config() {
+        assert!(Config::default().statsd.is_none());
+
+        let config =
+            Config::from_yaml("statsd:\n  address: localhost:8125\n  delta: true\n").unwrap();
+        let statsd = config.statsd.unwrap();
+        assert_eq!(statsd.interval_seconds, 15);
+        assert!(statsd.delta);
+        assert_eq!(statsd.full_resync_seconds, 300);
+
+        for (yaml, error) in [
+            ("statsd:\n  address: localhost\n", "statsd.address"),
+            ("statsd:\n  address: ':8125'\n", "statsd.address"),
+            (
+                "statsd:\n  address: localhost:8125\n  interval_seconds: 0\n",
+                "statsd.interval_seconds",
+            ),
+            (
+                "statsd:\n  address: localhost:8125\n  delta: true\n  full_resync_seconds: 5\n",
+                "statsd.full_resync_seconds",
+            ),
+        ] {
+            let err = Config::from_yaml(yaml).unwrap_err().to_string();
+            assert!(err.contains(error), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_heartbeat_config() {
+        let config = Config::default();
+        assert!(config.heartbeat.url.is_none());
+        assert_eq!(config.heartbeat.interval_seconds, 60);
+        assert_eq!(config.heartbeat.max_scrape_age_seconds, 300);
+
+        let yaml = r#"
+heartbeat:
+  url: "https://hc-ping.com/uuid"
+  interval_seconds: 30
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.heartbeat.url.as_deref(),
+            Some("https://hc-ping.com/uuid")
+        );
+        assert_eq!(config.heartbeat.interval_seconds, 30);
+
+        let config: Config = serde_yaml::from_str("heartbeat:\n  url: not a url\n").unwrap();
+        assert!(config.validate().is_err());
+
+        let config: Config =
+            serde_yaml::from_str("heartbeat:\n  url: http://x\n  interval_seconds: 0\n").unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_shutdown_config() {
+        assert_eq!(Config::default().shutdown.grace_period_secs, 10);
+
+        let config: Config = serde_yaml::from_str("shutdown:\n  grace_period_secs: 0\n").unwrap();
+        assert_eq!(config.shutdown.grace_period_secs, 0);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_access_log_config() {
+        assert_eq!(Config::default().server.access_log, AccessLogFormat::Off);
+
+        for (value, format) in [
+            ("off", AccessLogFormat::Off),
+            ("common", AccessLogFormat::Common),
+            ("json", AccessLogFormat::Json),
+        ] {
+            let yaml = format!("server:\n  access_log: {}\n", value);
+            let config: Config = serde_yaml::from_str(&yaml).unwrap();
+            assert_eq!(config.server.access_log, format);
+        }
+
+        assert!(serde_yaml::from_str::<Config>("server:\n  access_log: combined\n").is_err());
+    }
+
+    #[test]
+    fn test_limits_config() {
+        let config = Config::default();
+        assert!(config.limits.max_series_per_scrape.is_none());
+        assert!(config.limits.max_label_value_length.is_none());
+
+        let yaml = r#"
+limits:
+  max_series_per_scrape: 10000
+  max_label_value_length: 256
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.limits.max_series_per_scrape, Some(10000));
+        assert_eq!(config.limits.max_label_value_length, Some(256));
+
+        let yaml = r#"
+limits:
+  max_series_per_scrape: 0
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_from_yaml_expands_rule_templates() {
+        let yaml = r#"
+rule_templates:
+  kafka_group:
+    params: [group]
+    rules:
+      - pattern: "kafka.server<type={{group}}, name=(\\w+)><>Count"
+        name: "kafka_{{group}}_$1_total"
+        type: counter
+rules:
+  - template: kafka_group
+    params: { group: BrokerTopicMetrics }
+  - template: kafka_group
+    params: { group: ReplicaManager }
+"#;
+        let config = Config::from_yaml(yaml).unwrap();
+        assert_eq!(config.rules.len(), 2);
+        assert_eq!(config.rules[0].name, "kafka_BrokerTopicMetrics_$1_total");
+        assert_eq!(config.rules[1].r#type, "counter");
+        assert!(config.rule_templates.contains_key("kafka_group"));
+
+        let yaml = "rules:\n  - template: missing\n";
+        assert!(matches!(
+            Config::from_yaml(yaml),
+            Err(ConfigError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_relabel_replace_requires_target_label() {
+        let yaml = r#"
+relabel_configs:
+  - source_labels: [pool]
+    action: replace
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_jmx_exporter_compat_fields() {
+        let yaml = r#"
+lowercaseOutputName: true
+lowercaseOutputLabelNames: true
+whitelistObjectNames:
+  - "java.lang:*"
+  - "com.example:*"
+blacklistObjectNames:
+  - "java.lang:type=MemoryPool,*"
+rules:
+  - pattern: "java.lang<type=Memory><HeapMemoryUsage>(\\w+)"
+    name: "jvm_memory_heap_$1_bytes"
+    type: gauge
+    value: "$1"
+    valueFactor: 1.0
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.lowercase_output_name);
+        assert!(config.lowercase_output_label_names);
+        assert_eq!(config.whitelist_object_names.len(), 2);
+        assert_eq!(config.blacklist_object_names.len(), 1);
+        assert_eq!(config.rules.len(), 1);
+        assert_eq!(config.rules[0].value, Some("$1".to_string()));
+        assert_eq!(config.rules[0].value_factor, Some(1.0));
+    }
+
+    #[test]
+    fn test_tls_config_default() {
+        let config = TlsConfig::default();
+        assert!(!config.enabled);
+        assert!(config.cert_file.is_none());
+        assert!(config.key_file.is_none());
+        assert_eq!(config.reload_interval_secs, 60);
+
+        let config: Config =
+            serde_yaml::from_str("server:\n  tls:\n    reload_interval_secs: 0\n").unwrap();
+        assert_eq!(config.server.tls.reload_interval_secs, 0);
+    }
+
+    #[test]
+    fn test_tls_config_enabled_without_cert() {
+        let yaml = r#"
+server:
+  tls:
+    enabled: true
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_tls_config_enabled_without_key() {
+        let yaml = r#"
+server:
+  tls:
+    enabled: true
+    cert_file: "/path/to/cert.pem"
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_tls_config_valid() {
+        let yaml = r#"
+server:
+  tls:
+    enabled: true
+    cert_file: "/path/to/cert.pem"
+    key_file: "/path/to/key.pem"
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        assert!(config.server.tls.enabled);
+        assert_eq!(
+            config.server.tls.cert_file,
+            Some("/path/to/cert.pem".to_string())
+        );
+        assert_eq!(
+            config.server.tls.key_file,
+            Some("/path/to/key.pem".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tls_config_disabled_no_files_required() {
+        let yaml = r#"
+server:
+  tls:
+    enabled: false
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        assert!(!config.server.tls.enabled);
+    }
+
+    #[test]
+    fn test_apply_tls_overrides() {
+        let mut config = Config::default();
+        assert!(!config.server.tls.enabled);
+        assert!(config.server.tls.cert_file.is_none());
+        assert!(config.server.tls.key_file.is_none());
+
+        let overrides = ConfigOverrides {
+            tls_enabled: Some(true),
+            tls_cert_file: Some("/path/to/cert.pem".to_string()),
+            tls_key_file: Some("/path/to/key.pem".to_string()),
+            ..Default::default()
+        };
+
+        config.apply_overrides(&overrides);
+
+        assert!(config.server.tls.enabled);
+        assert_eq!(
+            config.server.tls.cert_file,
+            Some("/path/to/cert.pem".to_string())
+        );
+        assert_eq!(
+            config.server.tls.key_file,
+            Some("/path/to/key.pem".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_final_with_tls() {
+        let mut config = Config::default();
+        config.server.tls.enabled = true;
+        config.server.tls.cert_file = Some("/path/to/cert.pem".to_string());
+        config.server.tls.key_file = Some("/path/to/key.pem".to_string());
+
+        assert!(config.validate_final().is_ok());
+    }
+
+    #[test]
+    fn test_validate_final_tls_missing_cert() {
+        let mut config = Config::default();
+        config.server.tls.enabled = true;
+        config.server.tls.key_file = Some("/path/to/key.pem".to_string());
+
+        assert!(config.validate_final().is_err());
+    }
+
+    #[test]
+    fn test_validate_final_tls_missing_key() {
+        let mut config = Config::default();
+        config.server.tls.enabled = true;
+        config.server.tls.cert_file = Some("/path/to/cert.pem".to_string());
+
+        assert!(config.validate_final().is_err());
+    }
+
+    #[test]
+    fn test_config_overrides_default() {
+        let overrides = ConfigOverrides::default();
+        assert!(overrides.port.is_none());
+        assert!(overrides.bind_address.is_none());
+        assert!(overrides.metrics_path.is_none());
+        assert!(overrides.jolokia_url.is_none());
+        assert!(overrides.jolokia_timeout.is_none());
+        assert!(overrides.username.is_none());
+        assert!(overrides.password.is_none());
+        assert!(overrides.tls_enabled.is_none());
+        assert!(overrides.tls_cert_file.is_none());
+        assert!(overrides.tls_key_file.is_none());
+    }
+
+    #[test]
+    fn test_apply_overrides_port() {
+        let mut config = Config::default();
+        assert_eq!(config.server.port, 9090);
+
+        let overrides = ConfigOverrides {
+            port: Some(8080),
+            ..Default::default()
+        };
+        config.apply_overrides(&overrides);
+        assert_eq!(config.server.port, 8080);
+    }
+
+    #[test]
+    fn test_apply_overrides_bind_address() {
+        let mut config = Config::default();
+        assert_eq!(config.server.bind_address, "0.0.0.0");
+
+        let overrides = ConfigOverrides {
+            bind_address: Some("127.0.0.1".to_string()),
+            ..Default::default()
+        };
+        config.apply_overrides(&overrides);
+        assert_eq!(config.server.bind_address, "127.0.0.1");
+    }
+
+    #[test]
+    fn test_listen_addresses() {
+        let mut config = Config::default();
+        assert_eq!(
+            config.server.listen_addresses().unwrap(),
+            vec!["0.0.0.0:9090".parse::<SocketAddr>().unwrap()]
+        );
+        config.server.bind_address = "localhost".to_string();
+        assert_eq!(
+            config.server.listen_addresses().unwrap(),
+            vec!["127.0.0.1:9090".parse::<SocketAddr>().unwrap()]
+        );
+
+        let mut config: Config =
+            serde_yaml::from_str("server:\n  listen: [\"0.0.0.0:9100\", \"[::]:9100\"]\n").unwrap();
+        assert_eq!(
+            config.server.listen_addresses().unwrap(),
+            vec![
+                "0.0.0.0:9100".parse::<SocketAddr>().unwrap(),
+                "[::]:9100".parse::<SocketAddr>().unwrap()
+            ]
+        );
+
+        // --port moves every listener, --bind-address replaces them
+        config.apply_overrides(&ConfigOverrides {
+            port: Some(9200),
+            ..Default::default()
+        });
+        assert_eq!(config.server.listen, vec!["0.0.0.0:9200", "[::]:9200"]);
+        config.apply_overrides(&ConfigOverrides {
+            bind_address: Some("::1".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(
+            config.server.listen_addresses().unwrap(),
+            vec!["[::1]:9200".parse::<SocketAddr>().unwrap()]
+        );
+
+        for listen in [
+            "[\"::\"]",
+            "[\"localhost:9090\"]",
+            "[\"0.0.0.0:0\"]",
+            "[\"[::]:9090\", \"[::]:9090\"]",
+        ] {
+            let config: Config =
+                serde_yaml::from_str(&format!("server:\n  listen: {}\n", listen)).unwrap();
+            assert!(config.validate_final().is_err(), "{}", listen);
+        }
+    }
+
+    #[test]
+    fn test_apply_overrides_metrics_path() {
+        let mut config = Config::default();
+        assert_eq!(config.server.path, "/metrics");
+
+        let overrides = ConfigOverrides {
+            metrics_path: Some("/custom-metrics".to_string()),
+            ..Default::default()
+        };
+        config.apply_overrides(&overrides);
+        assert_eq!(config.server.path, "/custom-metrics");
+    }
+
+    #[test]
+    fn test_apply_overrides_jolokia_url() {
+        let mut config = Config::default();
+        assert_eq!(config.jolokia.url, "http://localhost:8778/jolokia");
+
+        let overrides = ConfigOverrides {
+            jolokia_url: Some("http://example.com:9999/jolokia".to_string()),
+            ..Default::default()
+        };
+        config.apply_overrides(&overrides);
+        assert_eq!(config.jolokia.url, "http://example.com:9999/jolokia");
+    }
+
+    #[test]
+    fn test_jolokia_urls() {
+        let yaml = r#"
+jolokia:
+  urls:
+    - "http://jolokia-0:8778/jolokia"
+    - "http://jolokia-1:8778/jolokia"
+"#;
+        let mut config = Config::from_yaml(yaml).unwrap();
+        assert_eq!(config.jolokia.url, "http://jolokia-0:8778/jolokia");
+        assert_eq!(
+            config.jolokia.fallback_urls(),
+            ["http://jolokia-1:8778/jolokia"]
+        );
+
+        // A single URL override replaces the whole list
+        config.apply_overrides(&ConfigOverrides {
+            jolokia_url: Some("http://example.com:9999/jolokia".to_string()),
+            ..Default::default()
+        });
+        assert!(config.jolokia.fallback_urls().is_empty());
+
+        assert!(Config::from_yaml("jolokia:\n  urls: [\"\"]\n").is_err());
+    }
+
+    #[test]
+    fn test_apply_overrides_jolokia_timeout() {
+        let mut config = Config::default();
+        assert_eq!(config.jolokia.timeout_ms, 5000);
+
+        let overrides = ConfigOverrides {
+            jolokia_timeout: Some(10000),
+            ..Default::default()
+        };
+        config.apply_overrides(&overrides);
+        assert_eq!(config.jolokia.timeout_ms, 10000);
+    }
+
+    #[test]
+    fn test_apply_overrides_credentials() {
+        let mut config = Config::default();
+        assert!(config.jolokia.username.is_none());
+        assert!(config.jolokia.password.is_none());
+
+        let overrides = ConfigOverrides {
+            username: Some("admin".to_string()),
+            password: Some("secret".to_string()),
+            ..Default::default()
+        };
+        config.apply_overrides(&overrides);
+        assert_eq!(config.jolokia.username, Some("admin".to_string()));
+        assert_eq!(config.jolokia.password, Some("secret".to_string()));
+    }
+
+    #[test]
+    fn test_apply_overrides_all() {
+        let mut config = Config::default();
+
+        let overrides = ConfigOverrides {
+            port: Some(8080),
+            bind_address: Some("127.0.0.1".to_string()),
+            metrics_path: Some("/custom-metrics".to_string()),
+            jolokia_url: Some("http://example.com:9999/jolokia".to_string()),
+            jolokia_timeout: Some(15000),
+            username: Some("user".to_string()),
+            password: Some("pass".to_string()),
+            tls_enabled: Some(true),
+            tls_cert_file: Some("/path/to/cert.pem".to_string()),
+            tls_key_file: Some("/path/to/key.pem".to_string()),
+            systemd_socket: Some(true),
+            presets: Some(vec!["kafka".to_string()]),
+        };
+        config.apply_overrides(&overrides);
+
+        assert_eq!(config.server.port, 8080);
+        assert_eq!(config.server.bind_address, "127.0.0.1");
+        assert_eq!(config.server.path, "/custom-metrics");
+        assert_eq!(config.jolokia.url, "http://example.com:9999/jolokia");
+        assert_eq!(config.jolokia.timeout_ms, 15000);
+        assert_eq!(config.jolokia.username, Some("user".to_string()));
+        assert_eq!(config.jolokia.password, Some("pass".to_string()));
+        assert!(config.server.tls.enabled);
+        assert_eq!(
+            config.server.tls.cert_file,
+            Some("/path/to/cert.pem".to_string())
+        );
+        assert_eq!(
+            config.server.tls.key_file,
+            Some("/path/to/key.pem".to_string())
+        );
+        assert!(config.server.systemd_socket);
+        assert_eq!(config.presets, vec!["kafka".to_string()]);
+    }
+
+    #[test]
+    fn test_apply_overrides_none_preserves_config() {
+        let mut config = Config::default();
+        config.server.port = 8080;
+        config.jolokia.url = "http://custom:8778/jolokia".to_string();
+
+        let overrides = ConfigOverrides::default();
+        config.apply_overrides(&overrides);
+
+        // Should preserve original values when overrides are None
+        assert_eq!(config.server.port, 8080);
+        assert_eq!(config.jolokia.url, "http://custom:8778/jolokia");
+    }
+
+    #[test]
+    fn test_validate_final_valid() {
+        let config = Config::default();
+        assert!(config.validate_final().is_ok());
+    }
+
+    #[test]
+    fn test_validate_final_invalid_port() {
+        let mut config = Config::default();
+        config.server.port = 0;
+        assert!(config.validate_final().is_err());
+    }
+
+    #[test]
+    fn test_validate_final_invalid_metrics_path() {
+        let mut config = Config::default();
+        config.server.path = "no-slash".to_string();
+        let err = config.validate_final();
+        assert!(err.is_err());
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .contains("Metrics path must start with '/'"));
+    }
+
+    #[test]
+    fn test_validate_final_conflicting_metrics_path() {
+        let mut config = Config::default();
+        config.server.path = "/health".to_string();
+        let err = config.validate_final();
+        assert!(err.is_err());
+        assert!(err.unwrap_err().to_string().contains("must not conflict"));
+
+        for path in ["/livez", "/readyz"] {
+            config.server.path = path.to_string();
+            assert!(config.validate_final().is_err());
+        }
+    }
+}
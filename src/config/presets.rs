@@ -0,0 +1,244 @@
+//! Bundled rule presets
+//!
+//! Curated rule sets for popular Java middleware are embedded in the binary
+//! and activated by name, instead of every deployment re-writing the same
+//! patterns:
+//!
+//! ```yaml
+//! presets: [kafka, tomcat]
+//! ```
+//!
+//! or `--preset kafka` on the command line. Preset rules are appended after
+//! the configured `rules` (and module rules), so user rules always win.
+//!
+//! Preset files use the same format as the `rules:` section of a config file
+//! and may declare their own `rule_templates`.
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+
+use super::{templates, ConfigError, Rule};
+
+/// Names of the bundled presets
+pub const NAMES: &[&str] = &["activemq", "cassandra", "jvm", "kafka", "tomcat"];
+
+/// Embedded preset sources, keyed by name
+const SOURCES: &[(&str, &str)] = &[
+    ("activemq", include_str!("presets/activemq.yaml")),
+    ("cassandra", include_str!("presets/cassandra.yaml")),
+    ("jvm", include_str!("presets/jvm.yaml")),
+    ("kafka", include_str!("presets/kafka.yaml")),
+    ("tomcat", include_str!("presets/tomcat.yaml")),
+];
+
+/// Parsed presets (the bundled files are checked by the tests below)
+static PRESETS: Lazy<HashMap<&'static str, Vec<Rule>>> = Lazy::new(|| {
+    SOURCES
+        .iter()
+        .map(|(name, source)| {
+            let rules = parse(source)
+                .unwrap_or_else(|e| panic!("bundled preset '{}' is invalid: {}", name, e));
+            (*name, rules)
+        })
+        .collect()
+});
+
+/// Check whether a preset name is known
+pub fn exists(name: &str) -> bool {
+    NAMES.contains(&name)
+}
+
+/// Rules of a bundled preset, or `None` if the name is unknown
+pub fn rules(name: &str) -> Option<&'static [Rule]> {
+    PRESETS.get(name).map(Vec::as_slice)
+}
+
+/// Source YAML of a bundled preset, or `None` if the name is unknown
+pub fn source(name: &str) -> Option<&'static str> {
+    SOURCES
+        .iter()
+        .find(|(preset, _)| *preset == name)
+        .map(|(_, source)| *source)
+}
+
+/// Parse a preset document into rules, expanding its rule templates
+fn parse(source: &str) -> Result<Vec<Rule>, ConfigError> {
+    #[derive(serde::Deserialize)]
+    struct Preset {
+        #[serde(default)]
+        rules: Vec<Rule>,
+    }
+
+    let mut value: serde_yaml::Value = serde_yaml::from_str(source)?;
+    templates::expand_rule_templates(&mut value)?;
+    let preset: Preset = serde_yaml::from_value(value)?;
+    Ok(preset.rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformer::{MetricType, Rule as TransformRule};
+
+    #[test]
+    fn test_sources_match_names() {
+        let names: Vec<&str> = SOURCES.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, NAMES);
+        assert!(exists("kafka"));
+        assert!(!exists("weblogic"));
+        assert!(rules("weblogic").is_none());
+        assert!(source("tomcat").unwrap().contains("GlobalRequestProcessor"));
+    }
+
+    #[test]
+    fn test_presets_compile() {
+        for name in NAMES {
+            let rules = rules(name).unwrap();
+            assert!(!rules.is_empty(), "{}", name);
+            for rule in rules {
+                assert!(
+                    matches!(rule.r#type.as_str(), "gauge" | "counter" | "untyped"),
+                    "{}: {}",
+                    name,
+                    rule.name
+                );
+                TransformRule::new(&rule.pattern, &rule.name, MetricType::Untyped)
+                    .compile()
+                    .unwrap_or_else(|e| panic!("{}: {}: {}", name, rule.pattern, e));
+            }
+        }
+    }
+
+    #[test]
+    fn test_jvm_preset() {
+        let config = crate::config::Config::from_yaml("presets: [jvm]").unwrap();
+        let engine = crate::server::build_engine(&config).unwrap();
+        let responses = crate::testing::parse_fixture(
+            r#"[
+                {
+                    "request": {"mbean": "java.lang:type=MemoryPool,*", "type": "read"},
+                    "value": {
+                        "java.lang:name=G1 Eden Space,type=MemoryPool": {
+                            "Usage": {"used": 1024, "committed": 2048, "max": -1, "init": 512},
+                            "CollectionUsage": {"used": 0, "committed": 2048, "max": -1, "init": 512},
+                            "Name": "G1 Eden Space"
+                        }
+                    },
+                    "status": 200
+                },
+                {
+                    "request": {"mbean": "java.lang:type=GarbageCollector,*", "type": "read"},
+                    "value": {
+                        "java.lang:name=G1 Young Generation,type=GarbageCollector": {
+                            "CollectionCount": 7,
+                            "CollectionTime": 250,
+                            "LastGcInfo": {
+                                "duration": 15,
+                                "memoryUsageBeforeGc": {"G1 Eden Space": {"used": 4096, "max": -1}},
+                                "memoryUsageAfterGc": {"G1 Eden Space": {"used": 0, "max": -1}}
+                            }
+                        }
+                    },
+                    "status": 200
+                }
+            ]"#,
+        )
+        .unwrap();
+
+        let metrics = engine.transform(&responses).unwrap();
+        let value = |name: &str, label: (&str, &str)| {
+            metrics
+                .iter()
+                .find(|m| m.name == name && m.labels.get(label.0) == Some(label.1))
+                .unwrap_or_else(|| panic!("{} missing", name))
+                .value
+        };
+        let pool = ("pool", "G1_Eden_Space");
+        assert_eq!(value("jvm_memory_pool_bytes_used", pool), 1024.0);
+        assert_eq!(value("jvm_memory_pool_bytes_committed", pool), 2048.0);
+        assert_eq!(value("jvm_memory_pool_collection_used_bytes", pool), 0.0);
+        let gc = ("gc", "G1_Young_Generation");
+        assert_eq!(value("jvm_gc_collection_seconds_count", gc), 7.0);
+        assert_eq!(value("jvm_gc_collection_seconds_sum", gc), 0.25);
+        assert_eq!(value("jvm_gc_last_duration_seconds", gc), 0.015);
+        let before = metrics
+            .iter()
+            .find(|m| m.name == "jvm_gc_last_memory_used_before_bytes")
+            .unwrap();
+        assert_eq!(before.value, 4096.0);
+        assert_eq!(before.labels.get("gc"), Some("G1_Young_Generation"));
+        assert_eq!(before.labels.get("pool"), Some("G1_Eden_Space"));
+        assert_eq!(value("jvm_gc_last_memory_used_after_bytes", pool), 0.0);
+
+        // Summary parts keep jmx_exporter's names with `_total` suffixing on
+        let config =
+            crate::config::Config::from_yaml("presets: [jvm]\ncounter_total_suffix: true").unwrap();
+        let engine = crate::server::build_engine(&config).unwrap();
+        let names: Vec<String> = engine
+            .transform(&responses)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.name)
+            .collect();
+        assert!(names.iter().any(|n| n == "jvm_gc_collection_seconds_count"));
+        assert!(names.iter().any(|n| n == "jvm_gc_collection_seconds_sum"));
+        assert!(!names.iter().any(|n| n.ends_with("_total")));
+    }
+
+    #[test]
+    fn test_preset_patterns_match() {
+        let cases = [
+            (
+                "kafka",
+                "kafka.server<name=BytesInPerSec><topic=orders><type=BrokerTopicMetrics><Count>",
+                "kafka_server_brokertopicmetrics_bytes_in_total",
+            ),
+            (
+                "kafka",
+                "kafka.network<name=RequestsPerSec><request=Produce><type=RequestMetrics><version=9><Count>",
+                "kafka_network_requests_total",
+            ),
+            (
+                "tomcat",
+                "Catalina<name=\"http-nio-8080\"><type=GlobalRequestProcessor><requestCount>",
+                "tomcat_requests_total",
+            ),
+            (
+                "cassandra",
+                "org.apache.cassandra.metrics<name=Latency><scope=Read><type=ClientRequest><Count>",
+                "cassandra_client_requests_total",
+            ),
+            (
+                "jvm",
+                "java.lang<name=G1 Eden Space><type=MemoryPool><Usage><used>",
+                "jvm_memory_pool_bytes_$3",
+            ),
+            (
+                "jvm",
+                "java.lang<type=GarbageCollector><name=G1 Young Generation><CollectionTime>",
+                "jvm_gc_collection_seconds_sum",
+            ),
+            (
+                "activemq",
+                "org.apache.activemq<brokerName=localhost><destinationName=orders><destinationType=Queue><type=Broker><QueueSize>",
+                "activemq_destination_queue_size",
+            ),
+        ];
+
+        for (preset, input, expected) in cases {
+            let matched = rules(preset).unwrap().iter().find(|rule| {
+                TransformRule::new(&rule.pattern, &rule.name, MetricType::Untyped)
+                    .matches(input)
+                    .unwrap()
+                    .is_some()
+            });
+            assert_eq!(
+                matched.map(|r| r.name.as_str()),
+                Some(expected),
+                "{}",
+                input
+            );
+        }
+    }
+}
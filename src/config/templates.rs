@@ -0,0 +1,476 @@
+//! Rule templates
+//!
+//! Families of nearly identical rules (one per Kafka metric group, one per
+//! cache name, ...) can be declared once under `rule_templates:` and
+//! instantiated from `rules:` with different parameters. Templates are
+//! expanded at load time, before the configuration is deserialized, so the
+//! rest of the exporter only ever sees plain rules.
+//!
+//! YAML merge keys (`<<: *anchor`) are resolved before expansion, so anchors
+//! can still be used inside templates and rules.
+//!
+//! # Example Configuration (YAML)
+//!
+//! ```yaml
+//! rule_templates:
+//!   kafka_group:
+//!     params: [group, prefix]
+//!     rules:
+//!       - pattern: "kafka.server<type={{group}}, name=(\\w+)><>Count"
+//!         name: "{{prefix}}_$1_total"
+//!         type: counter
+//!
+//! rules:
+//!   - template: kafka_group
+//!     params: { group: BrokerTopicMetrics, prefix: kafka_topic }
+//!   - template: kafka_group
+//!     params: { group: ReplicaManager, prefix: kafka_replica }
+//! ```
+//!
+//! Parameter values are inserted verbatim; they are not regex-escaped.
+
+use std::collections::{HashMap, HashSet};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_yaml::value::TaggedValue;
+use serde_yaml::{Mapping, Value};
+
+use super::ConfigError;
+
+/// Top-level key holding template definitions
+pub const TEMPLATES_KEY: &str = "rule_templates";
+
+/// Top-level key holding named rule modules
+pub const MODULES_KEY: &str = "modules";
+
+/// Rule entry key naming the template to instantiate
+pub const TEMPLATE_KEY: &str = "template";
+
+/// Rule entry key holding template parameters
+pub const PARAMS_KEY: &str = "params";
+
+/// Placeholder syntax: `{{name}}` with optional inner whitespace
+static PLACEHOLDER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").expect("valid placeholder regex")
+});
+
+/// A parameterized rule template
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct RuleTemplate {
+    /// Declared parameter names
+    #[serde(default)]
+    pub params: Vec<String>,
+
+    /// Rule bodies containing `{{param}}` placeholders
+    #[serde(default)]
+    #[schemars(with = "Vec<serde_json::Value>")]
+    pub rules: Vec<Value>,
+}
+
+/// Expand template references in the `rules:` sections of a raw config document
+///
+/// Every `rules:` entry of the form `{template: name, params: {...}}` is
+/// replaced, in place, by the template's rules with placeholders substituted.
+/// Rules of named modules (`modules.<name>.rules`) are expanded as well.
+///
+/// # Errors
+///
+/// Returns `ConfigError::ValidationError` if a template is unknown, a
+/// declared parameter is missing, an undeclared parameter is supplied, or a
+/// template body references an undeclared placeholder.
+pub fn expand_rule_templates(root: &mut Value) -> Result<(), ConfigError> {
+    root.apply_merge()?;
+
+    let Some(doc) = root.as_mapping_mut() else {
+        return Ok(());
+    };
+
+    let templates: HashMap<String, RuleTemplate> = match doc.get(TEMPLATES_KEY) {
+        Some(value) if !value.is_null() => serde_yaml::from_value(value.clone())?,
+        _ => HashMap::new(),
+    };
+
+    for (name, template) in &templates {
+        validate_template(name, template)?;
+    }
+
+    if let Some(Value::Sequence(rules)) = doc.get_mut("rules") {
+        expand_rules(rules, &templates)?;
+    }
+
+    if let Some(Value::Mapping(modules)) = doc.get_mut(MODULES_KEY) {
+        for (_, module) in modules.iter_mut() {
+            if let Some(Value::Sequence(rules)) = module.get_mut("rules") {
+                expand_rules(rules, &templates)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace template references in one rule list
+fn expand_rules(
+    rules: &mut Vec<Value>,
+    templates: &HashMap<String, RuleTemplate>,
+) -> Result<(), ConfigError> {
+    let mut expanded = Vec::with_capacity(rules.len());
+    for (idx, entry) in rules.drain(..).enumerate() {
+        match entry.as_mapping().and_then(|m| m.get(TEMPLATE_KEY)) {
+            Some(reference) => {
+                let mapping = entry.as_mapping().expect("checked above");
+                expanded.extend(instantiate(idx, reference, mapping, templates)?);
+            }
+            None => expanded.push(entry),
+        }
+    }
+    *rules = expanded;
+    Ok(())
+}
+
+/// Check that a template only references declared parameters
+fn validate_template(name: &str, template: &RuleTemplate) -> Result<(), ConfigError> {
+    let declared: HashSet<&str> = template.params.iter().map(String::as_str).collect();
+
+    for rule in &template.rules {
+        if rule
+            .as_mapping()
+            .is_some_and(|m| m.contains_key(TEMPLATE_KEY))
+        {
+            return Err(ConfigError::ValidationError(format!(
+                "Rule template '{}' must not reference another template",
+                name
+            )));
+        }
+
+        let mut used = Vec::new();
+        collect_placeholders(rule, &mut used);
+        if let Some(unknown) = used.iter().find(|p| !declared.contains(p.as_str())) {
+            return Err(ConfigError::ValidationError(format!(
+                "Rule template '{}' references undeclared parameter '{}'",
+                name, unknown
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Instantiate a template reference found at `rules[idx]`
+fn instantiate(
+    idx: usize,
+    reference: &Value,
+    entry: &Mapping,
+    templates: &HashMap<String, RuleTemplate>,
+) -> Result<Vec<Value>, ConfigError> {
+    let name = reference.as_str().ok_or_else(|| {
+        ConfigError::ValidationError(format!("Rule {} has a non-string template name", idx))
+    })?;
+
+    let template = templates.get(name).ok_or_else(|| {
+        ConfigError::ValidationError(format!(
+            "Rule {} references unknown rule template '{}'",
+            idx, name
+        ))
+    })?;
+
+    if let Some(key) = entry
+        .keys()
+        .find(|k| k.as_str() != Some(TEMPLATE_KEY) && k.as_str() != Some(PARAMS_KEY))
+    {
+        return Err(ConfigError::ValidationError(format!(
+            "Rule {} instantiates template '{}' and must only set '{}' and '{}', found {:?}",
+            idx, name, TEMPLATE_KEY, PARAMS_KEY, key
+        )));
+    }
+
+    let params = parse_params(idx, name, entry.get(PARAMS_KEY))?;
+
+    for param in &template.params {
+        if !params.contains_key(param) {
+            return Err(ConfigError::ValidationError(format!(
+                "Rule {} is missing parameter '{}' for template '{}'",
+                idx, param, name
+            )));
+        }
+    }
+    if let Some(extra) = params.keys().find(|k| !template.params.contains(k)) {
+        return Err(ConfigError::ValidationError(format!(
+            "Rule {} passes undeclared parameter '{}' to template '{}'",
+            idx, extra, name
+        )));
+    }
+
+    Ok(template
+        .rules
+        .iter()
+        .map(|rule| substitute(rule, &params))
+        .collect())
+}
+
+/// Parse a `params` mapping; scalar values are stringified
+fn parse_params(
+    idx: usize,
+    template: &str,
+    value: Option<&Value>,
+) -> Result<HashMap<String, String>, ConfigError> {
+    let mapping = match value {
+        None | Some(Value::Null) => return Ok(HashMap::new()),
+        Some(Value::Mapping(m)) => m,
+        Some(_) => {
+            return Err(ConfigError::ValidationError(format!(
+                "Rule {} has non-mapping params for template '{}'",
+                idx, template
+            )))
+        }
+    };
+
+    mapping
+        .iter()
+        .map(|(k, v)| {
+            let key = k.as_str().map(str::to_string);
+            let value = match v {
+                Value::String(s) => Some(s.clone()),
+                Value::Number(n) => Some(n.to_string()),
+                Value::Bool(b) => Some(b.to_string()),
+                _ => None,
+            };
+            match (key, value) {
+                (Some(k), Some(v)) => Ok((k, v)),
+                _ => Err(ConfigError::ValidationError(format!(
+                    "Rule {} has a non-scalar parameter for template '{}'",
+                    idx, template
+                ))),
+            }
+        })
+        .collect()
+}
+
+/// Recursively collect placeholder names used in a value
+fn collect_placeholders(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::String(s) => {
+            out.extend(PLACEHOLDER.captures_iter(s).map(|c| c[1].to_string()));
+        }
+        Value::Sequence(seq) => seq.iter().for_each(|v| collect_placeholders(v, out)),
+        Value::Mapping(map) => {
+            for (k, v) in map {
+                collect_placeholders(k, out);
+                collect_placeholders(v, out);
+            }
+        }
+        Value::Tagged(tagged) => collect_placeholders(&tagged.value, out),
+        _ => {}
+    }
+}
+
+/// Recursively substitute placeholders in string scalars and mapping keys
+fn substitute(value: &Value, params: &HashMap<String, String>) -> Value {
+    match value {
+        Value::String(s) => Value::String(
+            PLACEHOLDER
+                .replace_all(s, |caps: &regex::Captures| params[&caps[1]].clone())
+                .into_owned(),
+        ),
+        Value::Sequence(seq) => {
+            Value::Sequence(seq.iter().map(|v| substitute(v, params)).collect())
+        }
+        Value::Mapping(map) => Value::Mapping(
+            map.iter()
+                .map(|(k, v)| (substitute(k, params), substitute(v, params)))
+                .collect(),
+        ),
+        Value::Tagged(tagged) => Value::Tagged(Box::new(TaggedValue {
+            tag: tagged.tag.clone(),
+            value: substitute(&tagged.value, params),
+        })),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(yaml: &str) -> Result<Value, ConfigError> {
+        let mut value: Value = serde_yaml::from_str(yaml).unwrap();
+        expand_rule_templates(&mut value)?;
+        Ok(value)
+    }
+
+    #[test]
+    fn test_expand_preserves_rule_order() {
+        let value = expand(
+            r#"
+rule_templates:
+  group:
+    params: [group, prefix]
+    rules:
+      - pattern: "kafka.server<type={{group}}, name=(\\w+)><>Count"
+        name: "{{ prefix }}_$1_total"
+        type: counter
+        labels:
+          "{{prefix}}_group": "{{group}}"
+rules:
+  - pattern: "first"
+    name: "first"
+  - template: group
+    params: { group: BrokerTopicMetrics, prefix: kafka_topic }
+  - template: group
+    params: { group: ReplicaManager, prefix: kafka_replica }
+"#,
+        )
+        .unwrap();
+
+        let rules = value["rules"].as_sequence().unwrap();
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules[0]["name"].as_str(), Some("first"));
+        assert_eq!(
+            rules[1]["pattern"].as_str(),
+            Some("kafka.server<type=BrokerTopicMetrics, name=(\\w+)><>Count")
+        );
+        assert_eq!(rules[1]["name"].as_str(), Some("kafka_topic_$1_total"));
+        assert_eq!(
+            rules[1]["labels"]["kafka_topic_group"].as_str(),
+            Some("BrokerTopicMetrics")
+        );
+        assert_eq!(rules[2]["name"].as_str(), Some("kafka_replica_$1_total"));
+    }
+
+    #[test]
+    fn test_expand_with_merge_keys() {
+        let value = expand(
+            r#"
+base: &base
+  type: counter
+  help: "shared help"
+rule_templates:
+  cache:
+    params: [cache]
+    rules:
+      - <<: *base
+        pattern: "cache<name={{cache}}><>hits"
+        name: "cache_{{cache}}_hits_total"
+rules:
+  - template: cache
+    params: { cache: users }
+"#,
+        )
+        .unwrap();
+
+        let rule = &value["rules"][0];
+        assert_eq!(rule["type"].as_str(), Some("counter"));
+        assert_eq!(rule["help"].as_str(), Some("shared help"));
+        assert_eq!(rule["name"].as_str(), Some("cache_users_hits_total"));
+    }
+
+    #[test]
+    fn test_expand_tagged_values() {
+        let value = expand(
+            r#"
+rule_templates:
+  cache:
+    params: [cache]
+    rules:
+      - pattern: "cache<name={{cache}}><>hits"
+        name: !custom "cache_{{cache}}_hits_total"
+rules:
+  - template: cache
+    params: { cache: users }
+"#,
+        )
+        .unwrap();
+
+        let Value::Tagged(name) = &value["rules"][0]["name"] else {
+            panic!("tag was dropped: {:?}", value["rules"][0]["name"]);
+        };
+        assert_eq!(name.tag, "custom");
+        assert_eq!(name.value.as_str(), Some("cache_users_hits_total"));
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        let unknown = "rules:\n  - template: missing\n";
+        assert!(expand(unknown).is_err());
+
+        let undeclared = r#"
+rule_templates:
+  t:
+    params: [a]
+    rules:
+      - pattern: "{{b}}"
+        name: x
+"#;
+        assert!(expand(undeclared).is_err());
+
+        let missing_param = r#"
+rule_templates:
+  t:
+    params: [a]
+    rules:
+      - pattern: "{{a}}"
+        name: x
+rules:
+  - template: t
+"#;
+        assert!(expand(missing_param).is_err());
+
+        let extra_param = r#"
+rule_templates:
+  t:
+    params: [a]
+    rules:
+      - pattern: "{{a}}"
+        name: x
+rules:
+  - template: t
+    params: { a: 1, b: 2 }
+"#;
+        assert!(expand(extra_param).is_err());
+
+        let extra_key = r#"
+rule_templates:
+  t:
+    rules:
+      - pattern: "p"
+        name: x
+rules:
+  - template: t
+    name: override
+"#;
+        assert!(expand(extra_key).is_err());
+    }
+
+    #[test]
+    fn test_expand_module_rules() {
+        let value = expand(
+            r#"
+rule_templates:
+  counter:
+    params: [name]
+    rules:
+      - pattern: "{{name}}<>Count"
+        name: "{{name}}_total"
+modules:
+  kafka:
+    rules:
+      - template: counter
+        params: { name: kafka }
+"#,
+        )
+        .unwrap();
+
+        let rules = value["modules"]["kafka"]["rules"].as_sequence().unwrap();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0]["name"], "kafka_total");
+    }
+
+    #[test]
+    fn test_expand_without_templates_is_noop() {
+        let value = expand("rules:\n  - pattern: p\n    name: n\n").unwrap();
+        assert_eq!(value["rules"][0]["pattern"].as_str(), Some("p"));
+    }
+}
@@ -0,0 +1,301 @@
+//! Series diff between two configurations
+//!
+//! Runs the rules of two configurations over the same recorded Jolokia
+//! responses and compares the series they produce, so the blast radius of a
+//! rule change can be reviewed before it is deployed. Recordings use the
+//! fixture format of [`crate::testing`].
+//!
+//! A series is identified by its name and labels. A removed series whose
+//! labels and value reappear under another name is reported as renamed.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::collector::JolokiaResponse;
+use crate::config::Config;
+use crate::testing::{load_config, load_fixture};
+use crate::transformer::PrometheusMetric;
+
+/// Differences between the series of two configurations
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct SeriesDiff {
+    /// Series produced by the old configuration
+    pub old_series: usize,
+    /// Series produced by the new configuration
+    pub new_series: usize,
+    /// Series only the new configuration produces
+    pub added: Vec<String>,
+    /// Series only the old configuration produces
+    pub removed: Vec<String>,
+    /// Series whose name changed, with the same labels and value
+    pub renamed: Vec<Renamed>,
+    /// Series produced by both, with different values
+    pub changed: Vec<Changed>,
+}
+
+/// Series produced under a new name
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Renamed {
+    pub from: String,
+    pub to: String,
+}
+
+/// Series whose value changed
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Changed {
+    pub series: String,
+    pub old: f64,
+    pub new: f64,
+}
+
+/// Series of one configuration, keyed by identity
+struct Series {
+    name: String,
+    labels: String,
+    value: f64,
+}
+
+impl Series {
+    fn key(&self) -> String {
+        format!("{}{}", self.name, self.labels)
+    }
+}
+
+/// Load two configuration files and diff their output for a recording
+pub fn diff_files(old: &Path, new: &Path, input: &Path) -> Result<SeriesDiff> {
+    let old = load_config(old)?;
+    let new = load_config(new)?;
+    let responses = load_fixture(input)?;
+    diff_configs(&old, &new, &responses)
+}
+
+/// Diff the series two configurations produce from the same responses
+pub fn diff_configs(
+    old: &Config,
+    new: &Config,
+    responses: &[JolokiaResponse],
+) -> Result<SeriesDiff> {
+    let old = series(old, responses)?;
+    let new = series(new, responses)?;
+
+    let mut diff = SeriesDiff {
+        old_series: old.len(),
+        new_series: new.len(),
+        ..SeriesDiff::default()
+    };
+    let mut removed: Vec<&Series> = Vec::new();
+    for (key, series) in &old {
+        match new.get(key) {
+            Some(current) if !same_value(series.value, current.value) => {
+                diff.changed.push(Changed {
+                    series: key.clone(),
+                    old: series.value,
+                    new: current.value,
+                })
+            }
+            Some(_) => {}
+            None => removed.push(series),
+        }
+    }
+    let mut added: Vec<&Series> = new
+        .iter()
+        .filter(|(key, _)| !old.contains_key(*key))
+        .map(|(_, series)| series)
+        .collect();
+
+    // Pair removed and added series with the same labels and value
+    removed.retain(|series| {
+        let renamed = added.iter().position(|candidate| {
+            candidate.labels == series.labels && same_value(candidate.value, series.value)
+        });
+        match renamed {
+            Some(index) => {
+                let to = added.remove(index);
+                diff.renamed.push(Renamed {
+                    from: series.key(),
+                    to: to.key(),
+                });
+                false
+            }
+            None => true,
+        }
+    });
+    diff.removed = removed.iter().map(|series| series.key()).collect();
+    diff.added = added.iter().map(|series| series.key()).collect();
+    Ok(diff)
+}
+
+impl SeriesDiff {
+    /// Whether the configurations produce the same series and values
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.renamed.is_empty()
+            && self.changed.is_empty()
+    }
+
+    /// Human-readable report, one series per line
+    pub fn render_text(&self) -> String {
+        let mut text = format!(
+            "Series: {} -> {} ({} added, {} removed, {} renamed, {} changed)\n",
+            self.old_series,
+            self.new_series,
+            self.added.len(),
+            self.removed.len(),
+            self.renamed.len(),
+            self.changed.len()
+        );
+        for series in &self.added {
+            let _ = writeln!(text, "+ {}", series);
+        }
+        for series in &self.removed {
+            let _ = writeln!(text, "- {}", series);
+        }
+        for renamed in &self.renamed {
+            let _ = writeln!(text, "~ {} -> {}", renamed.from, renamed.to);
+        }
+        for changed in &self.changed {
+            let _ = writeln!(
+                text,
+                "* {}: {} -> {}",
+                changed.series, changed.old, changed.new
+            );
+        }
+        text
+    }
+}
+
+/// Series a configuration produces from the responses
+fn series(config: &Config, responses: &[JolokiaResponse]) -> Result<BTreeMap<String, Series>> {
+    let engine = crate::server::build_engine(config)?;
+    let metrics = engine.transform(responses)?;
+    Ok(metrics
+        .iter()
+        .map(|metric| {
+            let series = Series {
+                name: metric.name.clone(),
+                labels: format_labels(metric),
+                value: metric.value,
+            };
+            (series.key(), series)
+        })
+        .collect())
+}
+
+/// Labels in exposition form, sorted by name
+fn format_labels(metric: &PrometheusMetric) -> String {
+    if metric.labels.is_empty() {
+        return String::new();
+    }
+    let mut labels: Vec<(&str, &str)> = metric.labels.iter().collect();
+    labels.sort_unstable();
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| format!("{}={:?}", name, value))
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+fn same_value(a: f64, b: f64) -> bool {
+    a == b || (a.is_nan() && b.is_nan())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::parse_fixture;
+
+    const FIXTURE: &str = r#"[
+        {
+            "request": {"mbean": "java.lang:type=Threading", "attribute": "ThreadCount", "type": "read"},
+            "value": 12,
+            "status": 200
+        },
+        {
+            "request": {"mbean": "java.lang:type=Memory", "attribute": "HeapMemoryUsage", "type": "read"},
+            "value": {"used": 100, "max": 400},
+            "status": 200
+        },
+        {
+            "request": {"mbean": "java.lang:type=ClassLoading", "attribute": "LoadedClassCount", "type": "read"},
+            "value": 3000,
+            "status": 200
+        }
+    ]"#;
+
+    #[test]
+    fn test_diff_configs() {
+        let old = Config::from_yaml(
+            r#"
+rules:
+  - pattern: "java\\.lang<type=Threading><ThreadCount>"
+    name: jvm_threads
+  - pattern: "java\\.lang<type=Memory><HeapMemoryUsage><(\\w+)>"
+    name: jvm_memory_heap_$1_bytes
+  - pattern: "java\\.lang<type=ClassLoading><LoadedClassCount>"
+    name: jvm_classes_loaded
+"#,
+        )
+        .unwrap();
+        let new = Config::from_yaml(
+            r#"
+rules:
+  - pattern: "java\\.lang<type=Threading><ThreadCount>"
+    name: jvm_threads_current
+  - pattern: "java\\.lang<type=Memory><HeapMemoryUsage><(\\w+)>"
+    name: jvm_memory_heap_$1_bytes
+    valueFactor: 1024
+    labels:
+      area: heap
+  - pattern: "java\\.lang<type=ClassLoading><LoadedClassCount>"
+    name: jvm_classes_loaded
+    valueFactor: 2
+"#,
+        )
+        .unwrap();
+        let responses = parse_fixture(FIXTURE).unwrap();
+
+        let diff = diff_configs(&old, &new, &responses).unwrap();
+        assert_eq!(diff.old_series, 4);
+        assert_eq!(diff.new_series, 4);
+        assert_eq!(
+            diff.renamed,
+            vec![Renamed {
+                from: "jvm_threads".to_string(),
+                to: "jvm_threads_current".to_string(),
+            }]
+        );
+        assert_eq!(
+            diff.added,
+            vec![
+                r#"jvm_memory_heap_max_bytes{area="heap"}"#,
+                r#"jvm_memory_heap_used_bytes{area="heap"}"#
+            ]
+        );
+        assert_eq!(
+            diff.removed,
+            vec!["jvm_memory_heap_max_bytes", "jvm_memory_heap_used_bytes"]
+        );
+        assert_eq!(
+            diff.changed,
+            vec![Changed {
+                series: "jvm_classes_loaded".to_string(),
+                old: 3000.0,
+                new: 6000.0,
+            }]
+        );
+        let text = diff.render_text();
+        assert!(text.starts_with("Series: 4 -> 4 (2 added, 2 removed, 1 renamed, 1 changed)"));
+        assert!(
+            text.contains("~ jvm_threads -> jvm_threads_current\n"),
+            "{}",
+            text
+        );
+
+        assert!(diff_configs(&old, &old, &responses).unwrap().is_empty());
+    }
+}
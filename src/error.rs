@@ -6,6 +6,68 @@ use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use thiserror::Error;
 
+/// Category of a scrape error, exported as the `category` label of
+/// `rjmx_scrape_errors_total`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    /// The agent could not be reached
+    Connect,
+    /// A request or the whole scrape ran out of time
+    Timeout,
+    /// The agent rejected the credentials, or they could not be sent
+    Auth,
+    /// The response was not valid Jolokia JSON, or too large to read
+    Parse,
+    /// The agent answered with an error, e.g. an unknown MBean
+    Jolokia,
+    /// The agent asked to back off (429, or 503 with `Retry-After`)
+    Throttled,
+    /// Responses could not be turned into metrics
+    Transform,
+}
+
+impl ErrorCategory {
+    /// All categories, in exposition order
+    pub const ALL: [ErrorCategory; 7] = [
+        ErrorCategory::Connect,
+        ErrorCategory::Timeout,
+        ErrorCategory::Auth,
+        ErrorCategory::Parse,
+        ErrorCategory::Jolokia,
+        ErrorCategory::Throttled,
+        ErrorCategory::Transform,
+    ];
+
+    /// Label value of the category
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::Connect => "connect",
+            ErrorCategory::Timeout => "timeout",
+            ErrorCategory::Auth => "auth",
+            ErrorCategory::Parse => "parse",
+            ErrorCategory::Jolokia => "jolokia",
+            ErrorCategory::Throttled => "throttled",
+            ErrorCategory::Transform => "transform",
+        }
+    }
+
+    /// Category of an error status, of the HTTP response or reported by
+    /// Jolokia for a single MBean
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            401 | 403 => ErrorCategory::Auth,
+            429 => ErrorCategory::Throttled,
+            _ => ErrorCategory::Jolokia,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Rule parsing and regex related errors
 #[derive(Error, Debug)]
 pub enum RuleError {
@@ -48,6 +110,10 @@ pub enum TransformError {
     /// Missing capture group
     #[error("Missing capture group ${group} in pattern")]
     MissingCaptureGroup { group: usize },
+
+    /// Samples of one metric name with different TYPE or HELP
+    #[error("Conflicting metadata for metric '{name}': {reason}")]
+    MetadataConflict { name: String, reason: String },
 }
 
 /// Application error type
@@ -129,6 +195,21 @@ pub enum CollectorError {
     /// Authentication failed
     #[error("Authentication failed")]
     AuthenticationFailed,
+
+    /// Credentials could not be added to a request
+    #[error("Failed to authenticate request: {0}")]
+    RequestAuth(String),
+
+    /// Response body larger than `jolokia.max_response_bytes`
+    #[error("Response exceeded the limit of {limit} bytes")]
+    ResponseTooLarge { limit: u64 },
+
+    /// Request not sent while the agent's `Retry-After` backoff lasts
+    #[error("Target throttled (status {status}), retry after {}s", retry_after.as_secs_f64().ceil())]
+    Throttled {
+        status: u16,
+        retry_after: std::time::Duration,
+    },
 }
 
 impl CollectorError {
@@ -140,10 +221,36 @@ impl CollectorError {
                 | CollectorError::HttpResponse(_)
                 | CollectorError::Timeout(..)
                 | CollectorError::ConnectionFailed(_)
-                | CollectorError::HttpStatus(500..=599)
+                | CollectorError::HttpStatus(429 | 500..=599)
+                | CollectorError::Throttled { .. }
         )
     }
 
+    /// Category of the error for `rjmx_scrape_errors_total`
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            CollectorError::HttpClientInit(_)
+            | CollectorError::HttpRequest(_)
+            | CollectorError::HttpResponse(_)
+            | CollectorError::ConnectionFailed(_)
+            | CollectorError::MaxRetriesExceeded => ErrorCategory::Connect,
+            CollectorError::Timeout(_) => ErrorCategory::Timeout,
+            CollectorError::AuthenticationFailed | CollectorError::RequestAuth(_) => {
+                ErrorCategory::Auth
+            }
+            CollectorError::JsonParse(_) | CollectorError::ResponseTooLarge { .. } => {
+                ErrorCategory::Parse
+            }
+            CollectorError::Throttled { .. } => ErrorCategory::Throttled,
+            CollectorError::HttpStatus(status) | CollectorError::JolokiaError { status, .. } => {
+                ErrorCategory::from_status(*status)
+            }
+            CollectorError::MBeanNotFound(_) | CollectorError::InvalidObjectName(_) => {
+                ErrorCategory::Jolokia
+            }
+        }
+    }
+
     /// Extract HTTP status code
     pub fn http_status(&self) -> Option<u16> {
         match self {
@@ -205,3 +312,49 @@ impl IntoResponse for AppError {
 
 /// Result type alias for application errors
 pub type AppResult<T> = Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collector_error_category() {
+        assert_eq!(
+            CollectorError::ConnectionFailed("refused".to_string()).category(),
+            ErrorCategory::Connect
+        );
+        assert_eq!(
+            CollectorError::MaxRetriesExceeded.category(),
+            ErrorCategory::Connect
+        );
+        assert_eq!(
+            CollectorError::Timeout(Some(5000)).category(),
+            ErrorCategory::Timeout
+        );
+        assert_eq!(
+            CollectorError::HttpStatus(401).category(),
+            ErrorCategory::Auth
+        );
+        assert_eq!(
+            CollectorError::JolokiaError {
+                status: 403,
+                message: "denied".to_string()
+            }
+            .category(),
+            ErrorCategory::Auth
+        );
+        assert_eq!(
+            CollectorError::JsonParse("eof".to_string()).category(),
+            ErrorCategory::Parse
+        );
+        assert_eq!(
+            CollectorError::HttpStatus(500).category(),
+            ErrorCategory::Jolokia
+        );
+        assert_eq!(
+            CollectorError::HttpStatus(429).category(),
+            ErrorCategory::Throttled
+        );
+        assert_eq!(ErrorCategory::Transform.to_string(), "transform");
+    }
+}
@@ -0,0 +1,427 @@
+//! Starter configuration from a running JVM (`generate-config`)
+//!
+//! Writing the first rules means knowing which MBeans a JVM exposes and how
+//! their names flatten. [`discover`] lists the MBean domains through Jolokia
+//! and reads each one with a wildcard, the same request the exporter sends
+//! for a `<domain>:*` query. [`render_config`] then groups the numeric values
+//! by domain and MBean `type`, and emits one rule per group: the attribute
+//! (and composite key) becomes part of the metric name, the other ObjectName
+//! properties become labels.
+//!
+//! Every value is exported as a gauge, since Jolokia does not tell counters
+//! apart. The output is meant to be trimmed and refined by hand.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use tracing::{debug, warn};
+
+use crate::collector::{
+    AttributeValue, CollectResult, JolokiaClient, JolokiaResponse, MBeanValue, ObjectName,
+};
+use crate::transformer::rules::push_snake_case;
+
+/// Domains left out unless asked for: the MBean server's and Jolokia's own
+const INTERNAL_DOMAINS: &[&str] = &["JMImplementation", "jolokia"];
+
+/// Attribute names listed in a rule comment before eliding the rest
+const LISTED_ATTRIBUTES: usize = 6;
+
+/// Read every MBean of the selected domains
+///
+/// With an empty `domains`, all domains except the internal ones are read.
+/// A domain that cannot be read is skipped with a warning.
+pub async fn discover(
+    client: &JolokiaClient,
+    domains: &[String],
+) -> CollectResult<Vec<JolokiaResponse>> {
+    let names = client.search_mbeans("*:*").await?;
+    let found: BTreeSet<&str> = names
+        .iter()
+        .filter_map(|name| name.split_once(':').map(|(domain, _)| domain))
+        .filter(|domain| {
+            if domains.is_empty() {
+                !INTERNAL_DOMAINS.contains(domain)
+            } else {
+                domains.iter().any(|d| d == domain)
+            }
+        })
+        .collect();
+
+    let mut responses = Vec::with_capacity(found.len());
+    for domain in found {
+        match client.read_mbean(&format!("{}:*", domain), None).await {
+            Ok(response) if response.status == 200 => responses.push(response),
+            Ok(response) => warn!(
+                domain,
+                status = response.status,
+                error = response.error.as_deref().unwrap_or_default(),
+                "Skipping domain that could not be read"
+            ),
+            Err(e) => warn!(domain, error = %e, "Skipping domain that could not be read"),
+        }
+    }
+    Ok(responses)
+}
+
+/// MBeans that share one generated rule
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Group {
+    domain: String,
+    /// Value of the `type` property, matched literally
+    type_name: Option<String>,
+    /// Other property keys, captured as labels (sorted)
+    label_keys: Vec<String>,
+    /// Whether the values are keys of composite attributes
+    composite: bool,
+}
+
+/// Values found for one group
+#[derive(Debug, Default)]
+struct GroupValues {
+    attributes: BTreeSet<String>,
+    values: usize,
+}
+
+/// Render a starter configuration for the numeric values in `responses`
+///
+/// Returns `None` if no numeric value was found.
+pub fn render_config(jolokia_url: &str, responses: &[JolokiaResponse]) -> Option<String> {
+    let groups = group_values(responses);
+    if groups.is_empty() {
+        return None;
+    }
+
+    let domains: BTreeSet<&str> = groups.keys().map(|group| group.domain.as_str()).collect();
+    let values: usize = groups.values().map(|found| found.values).sum();
+
+    let mut out = String::new();
+    out.push_str("# Generated by `rjmx-exporter generate-config`\n");
+    out.push_str(&format!("# from {}\n", jolokia_url));
+    out.push_str(&format!(
+        "# {} values in {} domains, {} rules\n",
+        values,
+        domains.len(),
+        groups.len()
+    ));
+    out.push_str("#\n");
+    out.push_str("# Every value is exported as a gauge: set `type: counter` where values only\n");
+    out.push_str("# grow (e.g. *Count, *Time), add `help` texts, and drop what is not needed.\n");
+    out.push_str("# `rjmx-exporter --validate --online` shows what each rule matches.\n\n");
+
+    out.push_str("jolokia:\n");
+    out.push_str(&format!("  url: {}\n\n", quote(jolokia_url)));
+
+    out.push_str("collect:\n");
+    for domain in &domains {
+        out.push_str(&format!(
+            "  - object_name: {}\n",
+            quote(&format!("{}:*", domain))
+        ));
+    }
+
+    out.push_str("\nrules:\n");
+    let mut current_domain = None;
+    for (group, found) in &groups {
+        if current_domain != Some(&group.domain) {
+            if current_domain.is_some() {
+                out.push('\n');
+            }
+            out.push_str(&format!("  # {}\n", group.domain));
+            current_domain = Some(&group.domain);
+        }
+        render_rule(group, found, &mut out);
+    }
+    Some(out)
+}
+
+/// Sort every numeric value into its group
+fn group_values(responses: &[JolokiaResponse]) -> BTreeMap<Group, GroupValues> {
+    let mut groups: BTreeMap<Group, GroupValues> = BTreeMap::new();
+    let mut add = |object_name: &ObjectName, attribute: &str, composite: bool| {
+        let mut label_keys: Vec<String> = object_name
+            .properties
+            .keys()
+            .filter(|key| *key != "type")
+            .cloned()
+            .collect();
+        label_keys.sort();
+        let group = Group {
+            domain: object_name.domain.clone(),
+            type_name: object_name.properties.get("type").cloned(),
+            label_keys,
+            composite,
+        };
+        let found = groups.entry(group).or_default();
+        found.attributes.insert(attribute.to_string());
+        found.values += 1;
+    };
+
+    for response in responses.iter().filter(|r| r.status == 200) {
+        let MBeanValue::Wildcard(mbeans) = &response.value else {
+            debug!(mbean = %response.request.mbean, "Skipping non-wildcard response");
+            continue;
+        };
+        for (mbean, attributes) in mbeans {
+            let Ok(object_name) = ObjectName::parse(mbean) else {
+                debug!(mbean = %mbean, "Skipping MBean with unsupported ObjectName");
+                continue;
+            };
+            // Names the generated `\w+` captures cannot match are left out
+            for (attribute, value) in attributes.iter().filter(|(a, _)| is_word(a)) {
+                match value {
+                    AttributeValue::Integer(_) | AttributeValue::Float(_) => {
+                        add(&object_name, attribute, false);
+                    }
+                    AttributeValue::Object(composite) => {
+                        let numeric = composite.iter().filter(|(key, value)| {
+                            is_word(key)
+                                && matches!(
+                                    value,
+                                    AttributeValue::Integer(_) | AttributeValue::Float(_)
+                                )
+                        });
+                        for _ in numeric {
+                            add(&object_name, attribute, true);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    groups
+}
+
+/// Append the rule for one group
+fn render_rule(group: &Group, found: &GroupValues, out: &mut String) {
+    // Properties appear in key order in flattened names
+    let mut keys: Vec<&str> = group.label_keys.iter().map(String::as_str).collect();
+    if group.type_name.is_some() {
+        keys.push("type");
+        keys.sort_unstable();
+    }
+
+    let mut pattern = format!("^{}", regex::escape(&group.domain));
+    let mut labels = Vec::new();
+    for key in keys {
+        match (&group.type_name, key) {
+            (Some(type_name), "type") => {
+                pattern.push_str(&format!("<type={}>", regex::escape(type_name)));
+            }
+            _ => {
+                pattern.push_str(&format!("<{}=([^<>]+)>", regex::escape(key)));
+                labels.push((label_name(key), labels.len() + 1));
+            }
+        }
+    }
+    let attribute_group = labels.len() + 1;
+    pattern.push_str("<(\\w+)>");
+    let mut name = metric_prefix(group);
+    name.push_str(&format!("_${}", attribute_group));
+    if group.composite {
+        pattern.push_str("<(\\w+)>");
+        name.push_str(&format!("_${}", attribute_group + 1));
+    }
+    pattern.push('$');
+
+    let listed: Vec<&str> = found
+        .attributes
+        .iter()
+        .take(LISTED_ATTRIBUTES)
+        .map(String::as_str)
+        .collect();
+    let elided = if found.attributes.len() > LISTED_ATTRIBUTES {
+        ", ..."
+    } else {
+        ""
+    };
+    let noun = if found.values == 1 { "value" } else { "values" };
+    out.push_str(&format!(
+        "  # {} {}: {}{}\n",
+        found.values,
+        noun,
+        listed.join(", "),
+        elided
+    ));
+    out.push_str(&format!("  - pattern: {}\n", quote(&pattern)));
+    out.push_str(&format!("    name: {}\n", quote(&name)));
+    out.push_str("    type: gauge\n");
+    out.push_str("    attrNameSnakeCase: true\n");
+    if !labels.is_empty() {
+        out.push_str("    labels:\n");
+        for (label, index) in labels {
+            out.push_str(&format!(
+                "      {}: {}\n",
+                label,
+                quote(&format!("${}", index))
+            ));
+        }
+    }
+}
+
+/// Metric name prefix: the domain, then the `type` in snake_case
+fn metric_prefix(group: &Group) -> String {
+    let mut prefix = sanitize(&group.domain);
+    if let Some(type_name) = &group.type_name {
+        let mut snake = String::new();
+        push_snake_case(type_name, &mut snake);
+        prefix.push('_');
+        prefix.push_str(&sanitize(&snake));
+    }
+    prefix
+}
+
+/// Label name for an ObjectName property key
+fn label_name(key: &str) -> String {
+    let mut snake = String::new();
+    push_snake_case(key, &mut snake);
+    sanitize(&snake)
+}
+
+/// Lower-case `[a-z0-9_]` identifier, not starting with a digit
+fn sanitize(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c.is_ascii_alphanumeric() {
+            result.push(c.to_ascii_lowercase());
+        } else if !result.is_empty() && !result.ends_with('_') {
+            result.push('_');
+        }
+    }
+    while result.ends_with('_') {
+        result.pop();
+    }
+    if result.is_empty() || result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert(0, '_');
+    }
+    result
+}
+
+fn is_word(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Single-quoted YAML scalar
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::parse_response;
+    use crate::config::Config;
+    use crate::server::build_engine;
+    use crate::validation::check_config;
+
+    fn responses() -> Vec<JolokiaResponse> {
+        let memory = r#"{
+            "request": {"mbean": "java.lang:*", "type": "read"},
+            "value": {
+                "java.lang:type=Memory": {
+                    "HeapMemoryUsage": {"used": 100, "max": 200, "init": 10, "committed": 150},
+                    "ObjectPendingFinalizationCount": 0,
+                    "Verbose": false
+                },
+                "java.lang:name=G1 Young Generation,type=GarbageCollector": {
+                    "CollectionCount": 10,
+                    "CollectionTime": 50,
+                    "Name": "G1 Young Generation"
+                },
+                "java.lang:name=G1 Old Generation,type=GarbageCollector": {
+                    "CollectionCount": 1,
+                    "CollectionTime": 20,
+                    "Name": "G1 Old Generation"
+                }
+            },
+            "status": 200
+        }"#;
+        let kafka = r#"{
+            "request": {"mbean": "kafka.server:*", "type": "read"},
+            "value": {
+                "kafka.server:name=BytesInPerSec,topic=orders,type=BrokerTopicMetrics": {
+                    "Count": 1000,
+                    "OneMinuteRate": 12.5,
+                    "RateUnit": "SECONDS"
+                }
+            },
+            "status": 200
+        }"#;
+        vec![
+            parse_response(memory).unwrap(),
+            parse_response(kafka).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_render_config() {
+        let yaml = render_config("http://localhost:8778/jolokia", &responses()).unwrap();
+
+        assert!(yaml.contains("# 11 values in 2 domains, 4 rules"));
+        assert!(yaml.contains("  - object_name: 'java.lang:*'\n"));
+        assert!(yaml.contains("  - object_name: 'kafka.server:*'\n"));
+        assert!(yaml.contains(
+            "  - pattern: '^java\\.lang<name=([^<>]+)><type=GarbageCollector><(\\w+)>$'\n    \
+             name: 'java_lang_garbage_collector_$2'\n"
+        ));
+        assert!(yaml.contains("  # 4 values: CollectionCount, CollectionTime\n"));
+        assert!(yaml.contains(
+            "  - pattern: '^java\\.lang<type=Memory><(\\w+)><(\\w+)>$'\n    \
+             name: 'java_lang_memory_$1_$2'\n"
+        ));
+        assert!(yaml.contains("      topic: '$2'\n"));
+    }
+
+    #[test]
+    fn test_generated_config_matches_values() {
+        let yaml = render_config("http://localhost:8778/jolokia", &responses()).unwrap();
+        let config = Config::from_yaml(&yaml).unwrap();
+        let issues = check_config(&config);
+        assert!(issues.is_empty(), "{:?}", issues);
+
+        let engine = build_engine(&config).unwrap();
+        let mut names: Vec<String> = engine
+            .transform(&responses())
+            .unwrap()
+            .into_iter()
+            .map(|metric| metric.name)
+            .collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(
+            names,
+            vec![
+                "java_lang_garbage_collector_collection_count",
+                "java_lang_garbage_collector_collection_time",
+                "java_lang_memory_heap_memory_usage_committed",
+                "java_lang_memory_heap_memory_usage_init",
+                "java_lang_memory_heap_memory_usage_max",
+                "java_lang_memory_heap_memory_usage_used",
+                "java_lang_memory_object_pending_finalization_count",
+                "kafka_server_broker_topic_metrics_count",
+                "kafka_server_broker_topic_metrics_one_minute_rate",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nothing_numeric() {
+        let response = parse_response(
+            r#"{
+                "request": {"mbean": "app:*", "type": "read"},
+                "value": {"app:type=Info": {"Version": "1.0"}},
+                "status": 200
+            }"#,
+        )
+        .unwrap();
+        assert!(render_config("http://localhost:8778/jolokia", &[response]).is_none());
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("kafka.server"), "kafka_server");
+        assert_eq!(sanitize("my--app."), "my_app");
+        assert_eq!(sanitize("9lives"), "_9lives");
+        assert_eq!(label_name("clientId"), "client_id");
+    }
+}
@@ -0,0 +1,150 @@
+//! Dead-man's-switch heartbeat
+//!
+//! Periodically POSTs to an external URL (healthchecks.io style) while the
+//! exporter is scraping successfully. The external service alerts when the
+//! pings stop, which also covers the case where Prometheus itself is down:
+//! without scrapes, the last successful scrape ages out and pings stop.
+
+use std::time::Duration;
+
+use reqwest::Client;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::config::HeartbeatConfig;
+use crate::metrics::{internal_metrics, InternalMetrics};
+
+/// Upper bound for a single heartbeat request
+const MAX_PING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Heartbeat pinger
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    client: Client,
+    url: String,
+    interval: Duration,
+    max_scrape_age: Duration,
+}
+
+impl Heartbeat {
+    /// Create a heartbeat for a URL
+    pub fn new(url: &str, interval: Duration, max_scrape_age: Duration) -> reqwest::Result<Self> {
+        let client = Client::builder()
+            .timeout(interval.min(MAX_PING_TIMEOUT))
+            .build()?;
+
+        Ok(Self {
+            client,
+            url: url.to_string(),
+            interval,
+            max_scrape_age,
+        })
+    }
+
+    /// Create a heartbeat from configuration, or `None` if no URL is configured
+    pub fn from_config(config: &HeartbeatConfig) -> reqwest::Result<Option<Self>> {
+        config
+            .url
+            .as_deref()
+            .map(|url| {
+                Self::new(
+                    url,
+                    Duration::from_secs(config.interval_seconds),
+                    Duration::from_secs(config.max_scrape_age_seconds),
+                )
+            })
+            .transpose()
+    }
+
+    /// Send one heartbeat if all targets are healthy
+    ///
+    /// Returns `true` if a ping was sent and accepted.
+    pub async fn beat(&self, metrics: &InternalMetrics) -> bool {
+        if !metrics.all_targets_healthy(self.max_scrape_age) {
+            debug!("Skipping heartbeat: last scrape failed or is stale");
+            return false;
+        }
+
+        match self.client.post(&self.url).send().await {
+            Ok(response) if response.status().is_success() => {
+                debug!(status = response.status().as_u16(), "Heartbeat sent");
+                true
+            }
+            Ok(response) => {
+                warn!(
+                    status = response.status().as_u16(),
+                    "Heartbeat endpoint returned non-success status"
+                );
+                false
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to send heartbeat");
+                false
+            }
+        }
+    }
+
+    /// Run the heartbeat loop in the background
+    pub fn spawn(self) -> JoinHandle<()> {
+        info!(
+            interval_seconds = self.interval.as_secs(),
+            max_scrape_age_seconds = self.max_scrape_age.as_secs(),
+            "Heartbeat enabled"
+        );
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                self.beat(internal_metrics()).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_from_config() {
+        let config = HeartbeatConfig::default();
+        assert!(Heartbeat::from_config(&config).unwrap().is_none());
+
+        let config = HeartbeatConfig {
+            url: Some("http://localhost/ping".to_string()),
+            ..Default::default()
+        };
+        let heartbeat = Heartbeat::from_config(&config).unwrap().unwrap();
+        assert_eq!(heartbeat.interval, Duration::from_secs(60));
+        assert_eq!(heartbeat.max_scrape_age, Duration::from_secs(300));
+    }
+
+    #[tokio::test]
+    async fn test_beat_only_when_healthy() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/ping"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let url = format!("{}/ping", mock_server.uri());
+        let heartbeat =
+            Heartbeat::new(&url, Duration::from_secs(60), Duration::from_secs(60)).unwrap();
+        let metrics = InternalMetrics::new();
+
+        // No scrape yet
+        assert!(!heartbeat.beat(&metrics).await);
+
+        metrics.record_scrape_failure("target", 0.1);
+        assert!(!heartbeat.beat(&metrics).await);
+
+        metrics.record_scrape_success("target", 0.1);
+        assert!(heartbeat.beat(&metrics).await);
+    }
+}
@@ -6,10 +6,17 @@
 pub mod cli;
 pub mod collector;
 pub mod config;
+pub mod diff;
 pub mod error;
+pub mod generate;
+pub mod heartbeat;
 pub mod metrics;
 pub mod server;
+#[cfg(feature = "otlp")]
+pub mod telemetry;
+pub mod testing;
 pub mod transformer;
+pub mod validation;
 
 use anyhow::Result;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
@@ -24,7 +31,12 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilte
 pub fn init_logging(level: &str) -> Result<()> {
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
 
-    tracing_subscriber::registry()
+    let registry = tracing_subscriber::registry();
+    // Filled in by `telemetry::init` once the configuration is loaded
+    #[cfg(feature = "otlp")]
+    let registry = registry.with(telemetry::DeferredLayer);
+
+    registry
         .with(filter)
         .with(tracing_subscriber::fmt::layer())
         .try_init()
@@ -3,19 +3,32 @@
 //! This binary provides a Prometheus-compatible metrics endpoint
 //! that collects JMX metrics from Java applications via Jolokia.
 
+use std::path::Path;
 use std::time::Instant;
 
 use anyhow::Result;
 use clap::Parser;
 use tracing::info;
+use tracing_subscriber::EnvFilter;
 
 use rjmx_exporter::{
-    cli::{Cli, OutputFormat},
+    cli::{Cli, Command, OutputFormat},
+    collector::JolokiaClient,
     config::{Config, ConfigOverrides},
-    server,
+    diff, generate, server, testing,
     transformer::convert_java_regex,
+    validation::{
+        check_config, check_online, check_rule_tests, live_sample, profile_rules, OnlineReport,
+        ProfileReport, ValidationReport,
+    },
 };
 
+/// Jolokia timeout for `generate-config`, which reads whole domains at once
+const GENERATE_TIMEOUT_MS: u64 = 30_000;
+
+/// Rules listed in the text output of `--profile-rules`
+const SLOWEST_RULES: usize = 5;
+
 /// Create ConfigOverrides from CLI arguments
 ///
 /// CLI arguments include values from environment variables (handled by clap),
@@ -32,6 +45,8 @@ fn cli_to_overrides(cli: &Cli) -> ConfigOverrides {
         tls_enabled: cli.tls_enabled,
         tls_cert_file: cli.tls_cert_file.clone(),
         tls_key_file: cli.tls_key_file.clone(),
+        systemd_socket: cli.systemd_socket.then_some(true),
+        presets: (!cli.presets.is_empty()).then(|| cli.presets.clone()),
     }
 }
 
@@ -39,15 +54,57 @@ fn cli_to_overrides(cli: &Cli) -> ConfigOverrides {
 async fn main() -> Result<()> {
     // Record startup time
     let start_time = Instant::now();
+    rjmx_exporter::metrics::init_internal_metrics();
 
     // Parse CLI arguments
     let cli = Cli::parse();
 
-    // Initialize logging
-    rjmx_exporter::init_logging(&cli.log_level.to_string())?;
+    match &cli.command {
+        Some(Command::Schema) => {
+            println!("{}", serde_json::to_string_pretty(&Config::json_schema())?);
+            return Ok(());
+        }
+        Some(Command::GenerateConfig {
+            jolokia_url,
+            username,
+            password,
+            domains,
+            output,
+        }) => {
+            // Logs go to stderr, so the configuration can be piped from stdout
+            init_stderr_logging(&cli);
+            let mut client = JolokiaClient::new(jolokia_url, GENERATE_TIMEOUT_MS)?;
+            if let (Some(username), Some(password)) = (username, password) {
+                client = client.with_auth(username, password);
+            }
+            return generate_config(&client, jolokia_url, domains, output.as_deref()).await;
+        }
+        Some(Command::Diff { old, new, input }) => {
+            init_stderr_logging(&cli);
+            return diff_configs(old, new, input, cli.output_format);
+        }
+        // Metrics are printed to stdout, so logs go to stderr
+        Some(Command::ScrapeOnce { .. }) => init_stderr_logging(&cli),
+        // Building the engine for the profile logs, so keep the report on stdout parseable
+        None if cli.profile_rules => init_stderr_logging(&cli),
+        None => rjmx_exporter::init_logging(&cli.log_level.to_string())?,
+    }
 
     // Load configuration from file
-    let mut config = Config::load_or_default(&cli.config)?;
+    // Lenient validation skips load-time checks so every problem ends up in the report
+    let loaded = if cli.validate && cli.lenient {
+        Config::load_or_default_unchecked(&cli.config)
+    } else {
+        Config::load_or_default(&cli.config)
+    };
+    let mut config = match loaded {
+        Ok(config) => config,
+        Err(e) if cli.validate && (cli.lenient || cli.report.is_some()) => {
+            let report = ValidationReport::load_failure(cli.config.display().to_string(), &e);
+            return finish_validation(&report, &cli);
+        }
+        Err(e) => return Err(e.into()),
+    };
 
     // Apply CLI/env overrides (precedence: CLI > Env > Config file > Defaults)
     let overrides = cli_to_overrides(&cli);
@@ -55,17 +112,45 @@ async fn main() -> Result<()> {
 
     // Handle --validate mode
     if cli.validate {
-        return validate_config(&config, &cli);
+        return validate_config(&config, &cli).await;
     }
 
     // Handle --dry-run mode
     if cli.dry_run {
-        return dry_run(&config, &cli);
+        let profile = if cli.profile_rules {
+            let responses = match &cli.sample {
+                Some(path) => testing::load_fixture(path)?,
+                None => live_sample(&config).await?,
+            };
+            Some(profile_rules(&config, &responses)?)
+        } else {
+            None
+        };
+        return dry_run(&config, &cli, profile.as_ref());
     }
 
     // Validate final configuration after all overrides are applied
     config.validate_final()?;
 
+    if let Some(Command::ScrapeOnce {
+        output_file,
+        report,
+        fail_on_unmatched,
+    }) = &cli.command
+    {
+        return scrape_once(
+            config,
+            output_file.as_deref(),
+            report.as_deref(),
+            *fail_on_unmatched,
+        )
+        .await;
+    }
+
+    // Export scrape traces once the configuration is known
+    #[cfg(feature = "otlp")]
+    let tracer_provider = rjmx_exporter::telemetry::init(&config.tracing)?;
+
     // Calculate startup duration
     let startup_duration = start_time.elapsed();
 
@@ -86,112 +171,271 @@ async fn main() -> Result<()> {
     }
 
     // Start server (port is now part of config)
-    server::run(config).await?;
+    let source = server::ConfigSource {
+        path: cli.config.clone(),
+        overrides,
+    };
+    let result = server::run(config, Some(source)).await;
+
+    // Flush spans still buffered by the batch exporter
+    #[cfg(feature = "otlp")]
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            tracing::warn!(error = %e, "Failed to flush OTLP spans");
+        }
+    }
 
-    Ok(())
+    result
 }
 
-/// Validate configuration and display results
-///
-/// Note: Config already has CLI/env overrides applied at this point
-fn validate_config(config: &Config, cli: &Cli) -> Result<()> {
-    let mut errors: Vec<String> = Vec::new();
+/// Log to stderr instead of stdout, for subcommands that print their result
+fn init_stderr_logging(cli: &Cli) {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(EnvFilter::new(cli.log_level.to_string()))
+        .init();
+}
 
-    // Validate port (overrides already applied to config)
-    if let Err(e) = Config::validate_port(config.server.port) {
-        errors.push(format!("Invalid port: {}", e));
+/// Scrape once and write the metrics to stdout or a file
+///
+/// Fails after writing the metrics and the report if any MBean read or
+/// transform failed, or with `fail_on_unmatched`, if an MBean matched no rule.
+async fn scrape_once(
+    config: Config,
+    output_file: Option<&Path>,
+    report: Option<&Path>,
+    fail_on_unmatched: bool,
+) -> Result<()> {
+    let (text, summary) = server::scrape_once(config).await?;
+
+    match output_file {
+        Some(path) => {
+            // Rename into place so readers such as the node_exporter textfile
+            // collector never see a partially written file
+            let mut tmp = path.as_os_str().to_owned();
+            tmp.push(".tmp");
+            std::fs::write(&tmp, text)?;
+            std::fs::rename(&tmp, path)?;
+        }
+        None => print!("{}", text),
     }
-
-    // Validate metrics path
-    if !config.server.path.starts_with('/') {
-        errors.push("Metrics path must start with '/'".to_string());
-    } else if config.server.path == "/" || config.server.path == "/health" {
-        errors.push("Metrics path must not conflict with '/' or '/health'".to_string());
+    if let Some(path) = report {
+        summary
+            .write_json(path)
+            .map_err(|e| anyhow::anyhow!("Failed to write report to {}: {}", path.display(), e))?;
     }
 
-    // Validate TLS configuration
-    if config.server.tls.enabled {
-        if config.server.tls.cert_file.is_none() {
-            errors.push("TLS is enabled but cert_file is not specified".to_string());
+    if !summary.errors.is_empty() {
+        eprintln!("Scrape errors:");
+        for error in &summary.errors {
+            eprintln!("  - {}", error);
         }
-        if config.server.tls.key_file.is_none() {
-            errors.push("TLS is enabled but key_file is not specified".to_string());
+        anyhow::bail!("Scrape finished with {} error(s)", summary.errors.len());
+    }
+    if fail_on_unmatched && !summary.unmatched_mbeans.is_empty() {
+        eprintln!("MBeans matched by no rule:");
+        for mbean in &summary.unmatched_mbeans {
+            eprintln!("  - {}", mbean);
         }
+        anyhow::bail!(
+            "{} MBean(s) matched by no rule",
+            summary.unmatched_mbeans.len()
+        );
     }
+    Ok(())
+}
 
-    // Validate rule patterns (convert Java regex to Rust regex)
-    for (i, rule) in config.rules.iter().enumerate() {
-        match convert_java_regex(&rule.pattern) {
-            Ok(converted_pattern) => {
-                if let Err(e) = regex::Regex::new(&converted_pattern) {
-                    errors.push(format!(
-                        "Rule {}: Invalid regex after conversion: {} (original: {}, converted: {})",
-                        i, e, rule.pattern, converted_pattern
-                    ));
-                }
-            }
-            Err(e) => {
-                errors.push(format!("Rule {}: Regex conversion error: {}", i, e));
-            }
+/// Print the series differences between two configurations for a recording
+fn diff_configs(old: &Path, new: &Path, input: &Path, format: OutputFormat) -> Result<()> {
+    let diff = diff::diff_files(old, new, input)?;
+    match format {
+        OutputFormat::Text => print!("{}", diff.render_text()),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(&diff)?),
+    }
+    Ok(())
+}
+
+/// Write a starter configuration for the MBeans of a running JVM
+async fn generate_config(
+    client: &JolokiaClient,
+    jolokia_url: &str,
+    domains: &[String],
+    output: Option<&Path>,
+) -> Result<()> {
+    let responses = generate::discover(client, domains).await?;
+    let yaml = generate::render_config(jolokia_url, &responses)
+        .ok_or_else(|| anyhow::anyhow!("No numeric MBean attributes found at {}", jolokia_url))?;
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, yaml)?;
+            eprintln!("Configuration written to {}", path.display());
         }
+        None => print!("{}", yaml),
     }
+    Ok(())
+}
 
-    let is_valid = errors.is_empty();
+/// Validate configuration and display results
+///
+/// Note: Config already has CLI/env overrides applied at this point
+async fn validate_config(config: &Config, cli: &Cli) -> Result<()> {
+    let mut issues = check_config(config);
+    let online = if cli.online {
+        let (online, online_issues) = check_online(config).await;
+        issues.extend(online_issues);
+        Some(online)
+    } else {
+        None
+    };
+    let mut report = ValidationReport::new(cli.config.display().to_string(), issues);
+    if let Some(online) = online {
+        report = report.with_online(online);
+    }
+    let errors: Vec<&str> = report.errors().map(|i| i.message.as_str()).collect();
+    let warnings: Vec<&str> = report.warnings().map(|i| i.message.as_str()).collect();
 
     match cli.output_format {
         OutputFormat::Text => {
-            if is_valid {
+            if report.valid {
                 println!("Configuration is valid");
                 println!("  Config file: {}", cli.config.display());
                 println!("  Jolokia URL: {}", config.jolokia.url);
                 println!("  Server port: {}", config.server.port);
                 println!("  Bind address: {}", config.server.bind_address);
+                if !config.server.listen.is_empty() {
+                    println!("  Listen: {}", config.server.listen.join(", "));
+                }
                 println!("  Metrics path: {}", config.server.path);
                 println!("  TLS enabled: {}", config.server.tls.enabled);
-                println!("  Rules: {}", config.rules.len());
+                println!("  Rules: {}", config.effective_rules().count());
             } else {
                 eprintln!("Configuration validation failed:");
                 for error in &errors {
                     eprintln!("  - {}", error);
                 }
             }
+            if !warnings.is_empty() {
+                eprintln!("Warnings:");
+                for warning in &warnings {
+                    eprintln!("  - {}", warning);
+                }
+            }
+            if let Some(online) = &report.online {
+                print_online(online);
+            }
         }
         OutputFormat::Json => {
             let result = serde_json::json!({
-                "valid": is_valid,
+                "valid": report.valid,
                 "config_file": cli.config.display().to_string(),
                 "jolokia_url": config.jolokia.url,
                 "server_port": config.server.port,
                 "bind_address": config.server.bind_address,
+                "listen": config.server.listen,
                 "metrics_path": config.server.path,
                 "tls_enabled": config.server.tls.enabled,
-                "rules_count": config.rules.len(),
-                "errors": errors
+                "rules_count": config.effective_rules().count(),
+                "errors": errors,
+                "warnings": warnings,
+                "online": report.online
             });
             println!("{}", serde_json::to_string_pretty(&result)?);
         }
         OutputFormat::Yaml => {
             let result = serde_json::json!({
-                "valid": is_valid,
+                "valid": report.valid,
                 "config_file": cli.config.display().to_string(),
                 "jolokia_url": config.jolokia.url,
                 "server_port": config.server.port,
                 "bind_address": config.server.bind_address,
+                "listen": config.server.listen,
                 "metrics_path": config.server.path,
                 "tls_enabled": config.server.tls.enabled,
-                "rules_count": config.rules.len(),
-                "errors": errors
+                "rules_count": config.effective_rules().count(),
+                "errors": errors,
+                "warnings": warnings,
+                "online": report.online
             });
             println!("{}", serde_yaml::to_string(&result)?);
         }
     }
 
-    if is_valid {
+    write_report(&report, cli)?;
+    validation_result(&report, cli)
+}
+
+/// Print the results of the online checks
+fn print_online(online: &OnlineReport) {
+    println!("Online checks:");
+    for endpoint in &online.endpoints {
+        match (&endpoint.version, &endpoint.error) {
+            (Some(version), _) => println!(
+                "  Jolokia {}: agent {} (protocol {})",
+                endpoint.url, version.agent, version.protocol
+            ),
+            (None, error) => println!(
+                "  Jolokia {}: failed ({})",
+                endpoint.url,
+                error.as_deref().unwrap_or("unknown error")
+            ),
+        }
+    }
+    for mbean in &online.mbeans {
+        match &mbean.error {
+            None => println!(
+                "  MBean {}: {} values, {} matched by rules",
+                mbean.object_name, mbean.values, mbean.matched
+            ),
+            Some(error) => println!("  MBean {}: failed ({})", mbean.object_name, error),
+        }
+    }
+    if !online.rules.is_empty() {
+        println!(
+            "  Rules matching collected values: {} of {}",
+            online.covered_rules(),
+            online.rules.len()
+        );
+    }
+}
+
+/// Report a configuration that could not be loaded at all
+fn finish_validation(report: &ValidationReport, cli: &Cli) -> Result<()> {
+    match cli.output_format {
+        OutputFormat::Text => {
+            eprintln!("Configuration validation failed:");
+            for issue in &report.issues {
+                eprintln!("  - {}", issue.message);
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(report)?),
+        OutputFormat::Yaml => println!("{}", serde_yaml::to_string(report)?),
+    }
+
+    write_report(report, cli)?;
+    validation_result(report, cli)
+}
+
+/// Write the validation report if `--report` was given
+fn write_report(report: &ValidationReport, cli: &Cli) -> Result<()> {
+    if let Some(ref path) = cli.report {
+        report
+            .write_json(path)
+            .map_err(|e| anyhow::anyhow!("Failed to write report to {}: {}", path.display(), e))?;
+        info!(path = %path.display(), "Validation report written");
+    }
+    Ok(())
+}
+
+/// Exit status for validation: lenient mode never fails
+fn validation_result(report: &ValidationReport, cli: &Cli) -> Result<()> {
+    if report.valid || cli.lenient {
         Ok(())
     } else {
         anyhow::bail!(
             "Configuration validation failed with {} error(s)",
-            errors.len()
+            report.error_count
         )
     }
 }
@@ -199,7 +443,7 @@ fn validate_config(config: &Config, cli: &Cli) -> Result<()> {
 /// Dry run: test configuration and show parsed rules
 ///
 /// Note: Config already has CLI/env overrides applied at this point
-fn dry_run(config: &Config, cli: &Cli) -> Result<()> {
+fn dry_run(config: &Config, cli: &Cli, profile: Option<&ProfileReport>) -> Result<()> {
     let mut errors: Vec<String> = Vec::new();
 
     // Validate port (overrides already applied to config)
@@ -210,7 +454,7 @@ fn dry_run(config: &Config, cli: &Cli) -> Result<()> {
     // Compile all rules to verify they work
     let mut compiled_rules: Vec<serde_json::Value> = Vec::new();
 
-    for (i, rule) in config.rules.iter().enumerate() {
+    for (i, rule) in config.effective_rules().enumerate() {
         let conversion_result = convert_java_regex(&rule.pattern);
         let (converted_pattern, conversion_error) = match conversion_result {
             Ok(p) => (p, None),
@@ -235,6 +479,12 @@ fn dry_run(config: &Config, cli: &Cli) -> Result<()> {
             "type": rule.r#type,
             "help": rule.help,
             "labels": rule.labels,
+            "label_drops": rule.label_drops,
+            "label_renames": rule.label_renames,
+            "cache": rule.cache,
+            "attr_name_snake_case": rule.attr_name_snake_case,
+            "normalize_label_values": rule.normalize_label_values,
+            "tests": rule.tests.len(),
             "valid": is_valid,
             "conversion_error": conversion_error,
             "regex_error": regex_result.as_ref().err().map(|e| e.to_string())
@@ -243,6 +493,13 @@ fn dry_run(config: &Config, cli: &Cli) -> Result<()> {
         compiled_rules.push(rule_info);
     }
 
+    // Examples embedded in rules
+    errors.extend(
+        check_rule_tests(config)
+            .into_iter()
+            .map(|issue| issue.message),
+    );
+
     let valid_count = compiled_rules
         .iter()
         .filter(|r| r["valid"].as_bool().unwrap_or(false))
@@ -253,7 +510,7 @@ fn dry_run(config: &Config, cli: &Cli) -> Result<()> {
             println!("Dry run completed");
             println!(
                 "Loaded {} rule(s) ({} valid)",
-                config.rules.len(),
+                config.effective_rules().count(),
                 valid_count
             );
             println!();
@@ -262,6 +519,9 @@ fn dry_run(config: &Config, cli: &Cli) -> Result<()> {
             println!("  Jolokia URL: {}", config.jolokia.url);
             println!("  Server port: {}", config.server.port);
             println!("  Bind address: {}", config.server.bind_address);
+            if !config.server.listen.is_empty() {
+                println!("  Listen: {}", config.server.listen.join(", "));
+            }
             println!("  Metrics path: {}", config.server.path);
             println!("  TLS enabled: {}", config.server.tls.enabled);
 
@@ -295,6 +555,11 @@ fn dry_run(config: &Config, cli: &Cli) -> Result<()> {
                     println!("  Help: {}", help);
                 }
 
+                let tests = rule_info["tests"].as_u64().unwrap_or(0);
+                if tests > 0 {
+                    println!("  Tests: {}", tests);
+                }
+
                 if let Some(error) = rule_info["conversion_error"].as_str() {
                     println!("  Conversion Error: {}", error);
                 }
@@ -305,6 +570,21 @@ fn dry_run(config: &Config, cli: &Cli) -> Result<()> {
 
                 println!();
             }
+
+            if let Some(profile) = profile {
+                println!(
+                    "Rule profile ({} sample names, slowest first):",
+                    profile.names
+                );
+                for rule in profile.rules.iter().take(SLOWEST_RULES) {
+                    println!(
+                        "  Rule {}: {:.2}us per match, compiled in {:.1}us, {} match(es)",
+                        rule.index, rule.average_match_micros, rule.compile_micros, rule.matches
+                    );
+                    println!("    Pattern: {}", rule.pattern);
+                }
+                println!();
+            }
         }
         OutputFormat::Json => {
             let result = serde_json::json!({
@@ -313,11 +593,13 @@ fn dry_run(config: &Config, cli: &Cli) -> Result<()> {
                 "jolokia_url": config.jolokia.url,
                 "server_port": config.server.port,
                 "bind_address": config.server.bind_address,
+                "listen": config.server.listen,
                 "metrics_path": config.server.path,
                 "tls_enabled": config.server.tls.enabled,
-                "rules_count": config.rules.len(),
+                "rules_count": config.effective_rules().count(),
                 "valid_rules_count": valid_count,
                 "rules": compiled_rules,
+                "profile": profile,
                 "errors": errors
             });
             println!("{}", serde_json::to_string_pretty(&result)?);
@@ -329,11 +611,13 @@ fn dry_run(config: &Config, cli: &Cli) -> Result<()> {
                 "jolokia_url": config.jolokia.url,
                 "server_port": config.server.port,
                 "bind_address": config.server.bind_address,
+                "listen": config.server.listen,
                 "metrics_path": config.server.path,
                 "tls_enabled": config.server.tls.enabled,
-                "rules_count": config.rules.len(),
+                "rules_count": config.effective_rules().count(),
                 "valid_rules_count": valid_count,
                 "rules": compiled_rules,
+                "profile": profile,
                 "errors": errors
             });
             println!("{}", serde_yaml::to_string(&result)?);
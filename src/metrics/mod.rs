@@ -0,0 +1,1698 @@
+//! Internal observability metrics for rJMX-Exporter
+//!
+//! This module provides detailed metrics about the exporter's own operation
+//! for better observability and debugging.
+//!
+//! # Metrics
+//!
+//! ## Per-target metrics
+//! - `rjmx_scrape_success_total{target="..."}` - Counter of successful scrapes
+//! - `rjmx_scrape_failure_total{target="..."}` - Counter of failed scrapes
+//! - `rjmx_scrape_cancelled_total{target="..."}` - Counter of scrapes abandoned by the client
+//! - `rjmx_scrape_errors_total{target="...",category="..."}` - Counter of scrape errors by [`ErrorCategory`]
+//! - `rjmx_scrape_duration_seconds{target="..."}` - Histogram of scrape durations
+//! - `rjmx_mbeans_discovered{target="..."}` - Gauge of MBeans returned by the last scrape
+//! - `rjmx_mbeans_scraped{target="..."}` - Gauge of MBeans that produced metrics in the last scrape
+//! - `rjmx_attributes_transformed{target="..."}` - Gauge of attributes converted to metrics in the last scrape
+//! - `rjmx_mbeans_unmatched{target="..."}` - Gauge of MBeans that produced no metrics in the last scrape
+//! - `rjmx_series_emitted{target="..."}` - Gauge of series exposed by the last scrape
+//! - `rjmx_scrape_jolokia_requests{target="..."}` - Gauge of Jolokia requests sent by the last scrape
+//! - `rjmx_scrape_response_bytes{target="..."}` - Gauge of Jolokia response bytes received by the last scrape
+//! - `rjmx_jolokia_requests_total{target="..."}` - Counter of Jolokia requests sent by scrapes
+//! - `rjmx_jolokia_response_bytes_total{target="..."}` - Counter of Jolokia response bytes received by scrapes
+//! - `rjmx_target_clock_skew_seconds{target="..."}` - Gauge of how far the agent's clock is ahead (positive) or behind
+//! - `rjmx_target_backoff_seconds{target="..."}` - Gauge of the `Retry-After` backoff left after the last scrape
+//! - `rjmx_target_scrape_interval_seconds{target="..."}` - Gauge of the current background scrape interval
+//! - `rjmx_jolokia_agent_info{target="...",version="...",protocol="..."}` - Jolokia agent version, once checked
+//!
+//! ## Per-MBean metrics
+//! - `rjmx_mbean_errors_total{mbean="..."}` - Counter of MBeans a wildcard read reported an error for
+//!
+//! ## Per-rule metrics
+//! - `rjmx_rule_matches_total{rule="..."}` - Counter of rule matches
+//! - `rjmx_rule_errors_total{rule="..."}` - Counter of rule errors
+//!
+//! ## Connection pool metrics
+//! - `rjmx_http_connections_active` - Gauge of active HTTP connections
+//! - `rjmx_http_connections_idle` - Gauge of idle HTTP connections
+//!
+//! ## Config metrics
+//! - `rjmx_config_reload_total` - Counter of config reloads
+//! - `rjmx_config_last_reload_timestamp` - Timestamp of last config reload
+//! - `rjmx_config_rollback_total` - Counter of reloads that kept or restored the previous config
+//!
+//! ## Cardinality guardrail metrics
+//! - `rjmx_series_dropped_total{reason="..."}` - Counter of series dropped by limits
+//!
+//! ## Metric semantics
+//! - `rjmx_counter_decreases_total` - Counter of counter series that went down between scrapes
+//! - `rjmx_counter_resets_total{metric="..."}` - Counter of decreases (resets) per counter metric name
+//!
+//! ## Build and process metrics
+//! - `rjmx_exporter_build_info{version,commit,rustc,features}` - Constant 1, labelled with build metadata
+//! - `rjmx_process_start_time_seconds` - Unix timestamp at which the exporter started
+//!
+//! ## TLS metrics
+//! - `rjmx_tls_cert_expiry_timestamp` - Expiry (notAfter) of the served certificate, when TLS is enabled
+//! - `rjmx_tls_reload_total` - Counter of TLS certificate reloads
+//!
+//! ## Process metrics
+//! - `process_*` and `rjmx_process_tokio_*` - Resource usage of the exporter, see [`process`]
+//!
+//! ## Runtime metrics
+//! - `rjmx_tokio_*` - Tokio scheduler metrics with the `tokio-metrics` feature, see `runtime`
+
+pub mod process;
+#[cfg(feature = "tokio-metrics")]
+pub mod runtime;
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use dashmap::DashMap;
+
+use crate::error::ErrorCategory;
+use crate::transformer::{MetricType, PrometheusMetric};
+
+/// Git commit the exporter was built from, or `unknown`
+pub const BUILD_COMMIT: &str = env!("RJMX_BUILD_COMMIT");
+
+/// Version of the compiler that built the exporter
+pub const BUILD_RUSTC: &str = env!("RJMX_BUILD_RUSTC");
+
+/// Comma-separated Cargo features the exporter was built with
+pub const BUILD_FEATURES: &str = env!("RJMX_BUILD_FEATURES");
+
+/// Default histogram buckets for scrape duration (in seconds)
+/// Aligned with Prometheus conventions for HTTP request durations
+pub const DEFAULT_HISTOGRAM_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Thread-safe counter using atomic operations
+#[derive(Debug, Default)]
+pub struct Counter {
+    value: AtomicU64,
+}
+
+impl Counter {
+    /// Create a new counter initialized to 0
+    pub fn new() -> Self {
+        Self {
+            value: AtomicU64::new(0),
+        }
+    }
+
+    /// Increment the counter by 1
+    pub fn inc(&self) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Increment the counter by a specific amount
+    pub fn inc_by(&self, n: u64) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Get the current value
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    /// Reset the counter to 0
+    pub fn reset(&self) {
+        self.value.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Clone for Counter {
+    fn clone(&self) -> Self {
+        Self {
+            value: AtomicU64::new(self.get()),
+        }
+    }
+}
+
+/// Thread-safe gauge using atomic operations
+#[derive(Debug, Default)]
+pub struct Gauge {
+    /// Stored as bits of f64 for atomic operations
+    value: AtomicU64,
+}
+
+impl Gauge {
+    /// Create a new gauge initialized to 0
+    pub fn new() -> Self {
+        Self {
+            value: AtomicU64::new(0.0_f64.to_bits()),
+        }
+    }
+
+    /// Set the gauge to a specific value
+    pub fn set(&self, v: f64) {
+        self.value.store(v.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Get the current value
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.value.load(Ordering::Relaxed))
+    }
+
+    /// Increment the gauge by a specific amount
+    pub fn inc(&self, v: f64) {
+        loop {
+            let current = self.value.load(Ordering::Relaxed);
+            let new = f64::from_bits(current) + v;
+            if self
+                .value
+                .compare_exchange_weak(current, new.to_bits(), Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+            {
+                break;
+            }
+        }
+    }
+
+    /// Decrement the gauge by a specific amount
+    pub fn dec(&self, v: f64) {
+        self.inc(-v);
+    }
+
+    /// Set the gauge to the current Unix timestamp
+    pub fn set_to_current_time(&self) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or(0.0);
+        self.set(timestamp);
+    }
+}
+
+impl Clone for Gauge {
+    fn clone(&self) -> Self {
+        Self {
+            value: AtomicU64::new(self.value.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Thread-safe histogram for measuring distributions
+#[derive(Debug)]
+pub struct Histogram {
+    /// Bucket boundaries (upper bounds)
+    buckets: Vec<f64>,
+    /// Bucket counters (count of observations <= bucket boundary)
+    bucket_counts: Vec<AtomicU64>,
+    /// Sum of all observed values
+    sum: AtomicU64,
+    /// Total count of observations
+    count: AtomicU64,
+}
+
+impl Histogram {
+    /// Create a new histogram with the given bucket boundaries
+    pub fn new(buckets: &[f64]) -> Self {
+        let mut sorted_buckets: Vec<f64> = buckets.to_vec();
+        sorted_buckets.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        // Add +Inf bucket if not present
+        if sorted_buckets
+            .last()
+            .map(|v| !v.is_infinite())
+            .unwrap_or(true)
+        {
+            sorted_buckets.push(f64::INFINITY);
+        }
+
+        let bucket_counts = (0..sorted_buckets.len())
+            .map(|_| AtomicU64::new(0))
+            .collect();
+
+        Self {
+            buckets: sorted_buckets,
+            bucket_counts,
+            sum: AtomicU64::new(0.0_f64.to_bits()),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    /// Create a histogram with default buckets for scrape durations
+    pub fn with_default_buckets() -> Self {
+        Self::new(DEFAULT_HISTOGRAM_BUCKETS)
+    }
+
+    /// Observe a value
+    pub fn observe(&self, v: f64) {
+        // Update count
+        self.count.fetch_add(1, Ordering::Relaxed);
+
+        // Update sum (atomic f64 add)
+        loop {
+            let current = self.sum.load(Ordering::Relaxed);
+            let new = f64::from_bits(current) + v;
+            if self
+                .sum
+                .compare_exchange_weak(current, new.to_bits(), Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+            {
+                break;
+            }
+        }
+
+        // Update bucket counts
+        for (i, &bound) in self.buckets.iter().enumerate() {
+            if v <= bound {
+                self.bucket_counts[i].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Get the sum of all observations
+    pub fn get_sum(&self) -> f64 {
+        f64::from_bits(self.sum.load(Ordering::Relaxed))
+    }
+
+    /// Get the total count of observations
+    pub fn get_count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Get bucket boundaries and their cumulative counts
+    pub fn get_buckets(&self) -> Vec<(f64, u64)> {
+        self.buckets
+            .iter()
+            .zip(self.bucket_counts.iter())
+            .map(|(&bound, count)| (bound, count.load(Ordering::Relaxed)))
+            .collect()
+    }
+}
+
+impl Clone for Histogram {
+    fn clone(&self) -> Self {
+        Self {
+            buckets: self.buckets.clone(),
+            bucket_counts: self
+                .bucket_counts
+                .iter()
+                .map(|c| AtomicU64::new(c.load(Ordering::Relaxed)))
+                .collect(),
+            sum: AtomicU64::new(self.sum.load(Ordering::Relaxed)),
+            count: AtomicU64::new(self.count.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::with_default_buckets()
+    }
+}
+
+/// Reference point for `LastScrape` timestamps
+fn scrape_epoch() -> Instant {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    *EPOCH.get_or_init(Instant::now)
+}
+
+/// Time and outcome of the last scrape, packed into one atomic word
+///
+/// 0 means no scrape yet; otherwise bit 0 is the success flag and the
+/// remaining bits hold nanoseconds since `scrape_epoch()` plus one.
+#[derive(Debug, Default)]
+struct LastScrape(AtomicU64);
+
+impl LastScrape {
+    fn record(&self, success: bool) {
+        let nanos = scrape_epoch().elapsed().as_nanos() as u64;
+        self.0
+            .store(((nanos + 1) << 1) | u64::from(success), Ordering::Relaxed);
+    }
+
+    fn get(&self) -> Option<(Instant, bool)> {
+        let packed = self.0.load(Ordering::Relaxed);
+        if packed == 0 {
+            return None;
+        }
+        let at = scrape_epoch() + Duration::from_nanos((packed >> 1) - 1);
+        Some((at, packed & 1 == 1))
+    }
+}
+
+/// Per-target metrics
+#[derive(Debug)]
+pub struct TargetMetrics {
+    /// Counter of successful scrapes
+    pub scrape_success_total: Counter,
+    /// Counter of failed scrapes
+    pub scrape_failure_total: Counter,
+    /// Counter of scrapes abandoned before they completed
+    pub scrape_cancelled_total: Counter,
+    /// Counters of scrape errors, indexed like [`ErrorCategory::ALL`]
+    scrape_errors_total: [Counter; ErrorCategory::ALL.len()],
+    /// Histogram of scrape durations
+    pub scrape_duration_seconds: Histogram,
+    /// MBeans returned by the last successful scrape
+    pub mbeans_discovered: Gauge,
+    /// MBeans that produced at least one metric in the last successful scrape
+    pub mbeans_scraped: Gauge,
+    /// Attributes converted to metrics in the last successful scrape
+    pub attributes_transformed: Gauge,
+    /// MBeans that produced no metric in the last successful scrape
+    pub mbeans_unmatched: Gauge,
+    /// Series exposed by the last scrape
+    pub series_emitted: Gauge,
+    /// Jolokia requests sent by the last scrape
+    pub jolokia_requests: Gauge,
+    /// Jolokia response bytes received by the last scrape
+    pub response_bytes: Gauge,
+    /// Jolokia requests sent by all scrapes
+    pub jolokia_requests_total: Counter,
+    /// Jolokia response bytes received by all scrapes
+    pub response_bytes_total: Counter,
+    /// Duration of the last scrape in seconds
+    pub last_scrape_duration_seconds: Gauge,
+    /// Agent clock minus local clock, from the last scrape's response timestamps
+    pub clock_skew_seconds: Gauge,
+    /// Backoff requested by the agent that was left after the last scrape
+    pub backoff_seconds: Gauge,
+    /// Current interval between background scrapes, 0 outside background collection
+    pub scrape_interval_seconds: Gauge,
+    /// Jolokia agent and protocol version, from the last version request
+    agent_version: Mutex<Option<(String, String)>>,
+    /// When the last scrape finished and whether it succeeded
+    last_scrape: LastScrape,
+}
+
+impl TargetMetrics {
+    /// When the last scrape finished and whether it succeeded
+    pub fn last_scrape(&self) -> Option<(Instant, bool)> {
+        self.last_scrape.get()
+    }
+
+    /// Jolokia agent and protocol version, if a version request answered
+    pub fn agent_version(&self) -> Option<(String, String)> {
+        self.agent_version.lock().ok()?.clone()
+    }
+
+    /// Counter of scrape errors of a category
+    pub fn scrape_errors_total(&self, category: ErrorCategory) -> &Counter {
+        let index = ErrorCategory::ALL
+            .iter()
+            .position(|c| *c == category)
+            .unwrap_or_default();
+        &self.scrape_errors_total[index]
+    }
+
+    fn record_scrape(&self, success: bool, duration_seconds: f64) {
+        if success {
+            self.scrape_success_total.inc();
+        } else {
+            self.scrape_failure_total.inc();
+        }
+        self.scrape_duration_seconds.observe(duration_seconds);
+        self.last_scrape_duration_seconds.set(duration_seconds);
+        self.last_scrape.record(success);
+    }
+}
+
+impl Default for TargetMetrics {
+    fn default() -> Self {
+        Self {
+            scrape_success_total: Counter::new(),
+            scrape_failure_total: Counter::new(),
+            scrape_cancelled_total: Counter::new(),
+            scrape_errors_total: Default::default(),
+            scrape_duration_seconds: Histogram::with_default_buckets(),
+            mbeans_discovered: Gauge::new(),
+            mbeans_scraped: Gauge::new(),
+            attributes_transformed: Gauge::new(),
+            mbeans_unmatched: Gauge::new(),
+            series_emitted: Gauge::new(),
+            jolokia_requests: Gauge::new(),
+            response_bytes: Gauge::new(),
+            jolokia_requests_total: Counter::new(),
+            response_bytes_total: Counter::new(),
+            last_scrape_duration_seconds: Gauge::new(),
+            clock_skew_seconds: Gauge::new(),
+            backoff_seconds: Gauge::new(),
+            scrape_interval_seconds: Gauge::new(),
+            agent_version: Mutex::default(),
+            last_scrape: LastScrape::default(),
+        }
+    }
+}
+
+/// Number of recently matched MBeans kept per rule
+pub const RECENT_MATCHES: usize = 5;
+
+/// Most recently matched MBean names, newest first and without duplicates
+#[derive(Debug, Default)]
+pub struct RecentMatches(Mutex<VecDeque<String>>);
+
+impl RecentMatches {
+    /// Record a matched MBean name
+    pub fn record(&self, mbean: &str) {
+        let mut recent = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.front().is_some_and(|newest| newest == mbean) {
+            return;
+        }
+        recent.retain(|name| name != mbean);
+        recent.push_front(mbean.to_string());
+        recent.truncate(RECENT_MATCHES);
+    }
+
+    /// Recently matched MBean names, newest first
+    pub fn get(&self) -> Vec<String> {
+        let recent = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        recent.iter().cloned().collect()
+    }
+}
+
+impl Clone for RecentMatches {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.get().into()))
+    }
+}
+
+/// Per-rule metrics
+#[derive(Debug, Clone, Default)]
+pub struct RuleMetrics {
+    /// Counter of rule matches
+    pub matches_total: Counter,
+    /// Counter of rule errors
+    pub errors_total: Counter,
+    /// MBeans the rule matched most recently
+    pub recent_matches: RecentMatches,
+}
+
+/// Connection pool metrics
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionPoolMetrics {
+    /// Gauge of active HTTP connections
+    pub active: Gauge,
+    /// Gauge of idle HTTP connections
+    pub idle: Gauge,
+}
+
+/// Config metrics
+#[derive(Debug, Clone, Default)]
+pub struct ConfigMetrics {
+    /// Counter of config reloads
+    pub reload_total: Counter,
+    /// Timestamp of last config reload
+    pub last_reload_timestamp: Gauge,
+    /// Counter of reloads that kept or restored the previous config
+    pub rollback_total: Counter,
+}
+
+/// Cardinality guardrail metrics
+#[derive(Debug, Default)]
+pub struct LimitMetrics {
+    /// Series dropped, keyed by limit reason
+    series_dropped: DashMap<String, Counter>,
+}
+
+/// Per-MBean metrics
+#[derive(Debug, Default)]
+pub struct MBeanMetrics {
+    /// Errors of MBeans in wildcard reads, keyed by MBean name
+    errors: DashMap<String, Counter>,
+}
+
+/// Metric semantics checks
+#[derive(Debug, Default)]
+pub struct SemanticsMetrics {
+    /// Counter series that decreased between scrapes
+    pub counter_decreases_total: Counter,
+    /// Decreases keyed by counter metric name
+    counter_resets: DashMap<String, Counter>,
+}
+
+/// TLS certificate metrics
+#[derive(Debug, Default)]
+pub struct TlsMetrics {
+    /// Unix timestamp at which the served certificate expires (None without TLS)
+    cert_expiry_timestamp: RwLock<Option<f64>>,
+    /// Counter of certificate reloads
+    pub reload_total: Counter,
+}
+
+/// Get the handle stored under `key`, registering it on first use
+///
+/// The key is only allocated when it is not registered yet.
+fn handle<T: Default>(map: &DashMap<String, Arc<T>>, key: &str) -> Arc<T> {
+    if let Some(existing) = map.get(key) {
+        return Arc::clone(&existing);
+    }
+    Arc::clone(&map.entry(key.to_string()).or_default())
+}
+
+/// Internal metrics registry
+///
+/// Thread-safe registry for all internal observability metrics. Per-target
+/// and per-rule metrics live in sharded maps and are handed out as shared
+/// handles, so recording only touches atomics once a handle is registered.
+#[derive(Debug, Clone)]
+pub struct InternalMetrics {
+    /// Per-target metrics, keyed by target name/URL
+    targets: Arc<DashMap<String, Arc<TargetMetrics>>>,
+    /// Per-rule metrics, keyed by rule pattern
+    rules: Arc<DashMap<String, Arc<RuleMetrics>>>,
+    /// Connection pool metrics
+    pub connections: Arc<ConnectionPoolMetrics>,
+    /// Config metrics
+    pub config: Arc<ConfigMetrics>,
+    /// Cardinality guardrail metrics
+    pub limits: Arc<LimitMetrics>,
+    /// Per-MBean metrics
+    pub mbeans: Arc<MBeanMetrics>,
+    /// Metric semantics checks
+    pub semantics: Arc<SemanticsMetrics>,
+    /// TLS certificate metrics
+    pub tls: Arc<TlsMetrics>,
+    /// Unix timestamp at which the registry was created
+    process_start_time: Gauge,
+}
+
+impl Default for InternalMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InternalMetrics {
+    /// Create a new internal metrics registry
+    pub fn new() -> Self {
+        let metrics = Self {
+            targets: Arc::new(DashMap::new()),
+            rules: Arc::new(DashMap::new()),
+            connections: Arc::new(ConnectionPoolMetrics::default()),
+            config: Arc::new(ConfigMetrics::default()),
+            limits: Arc::new(LimitMetrics::default()),
+            mbeans: Arc::new(MBeanMetrics::default()),
+            semantics: Arc::new(SemanticsMetrics::default()),
+            tls: Arc::new(TlsMetrics::default()),
+            process_start_time: Gauge::new(),
+        };
+
+        // Record initial config load timestamp
+        metrics.config.last_reload_timestamp.set_to_current_time();
+        metrics.process_start_time.set_to_current_time();
+
+        metrics
+    }
+
+    /// Get or create the metrics handle for a target
+    ///
+    /// The handle stays registered; callers on hot paths can keep it.
+    pub fn target(&self, target: &str) -> Arc<TargetMetrics> {
+        handle(&self.targets, target)
+    }
+
+    /// Record a successful scrape for a target
+    pub fn record_scrape_success(&self, target: &str, duration_seconds: f64) {
+        self.target(target).record_scrape(true, duration_seconds);
+    }
+
+    /// Record a failed scrape for a target
+    pub fn record_scrape_failure(&self, target: &str, duration_seconds: f64) {
+        self.target(target).record_scrape(false, duration_seconds);
+    }
+
+    /// Record a scrape abandoned before it completed, e.g. by a client disconnect
+    pub fn record_scrape_cancelled(&self, target: &str) {
+        self.target(target).scrape_cancelled_total.inc();
+    }
+
+    /// Record an error of a scrape
+    pub fn record_scrape_error(&self, target: &str, category: ErrorCategory) {
+        self.target(target).scrape_errors_total(category).inc();
+    }
+
+    /// Forget a target that is no longer scraped, dropping its series
+    pub fn remove_target(&self, target: &str) {
+        self.targets.remove(target);
+    }
+
+    /// All registered targets, ordered by name
+    pub fn targets(&self) -> Vec<(String, Arc<TargetMetrics>)> {
+        let mut targets: Vec<_> = self
+            .targets
+            .iter()
+            .map(|entry| (entry.key().clone(), Arc::clone(entry.value())))
+            .collect();
+        targets.sort_by(|a, b| a.0.cmp(&b.0));
+        targets
+    }
+
+    /// Check that the last scrape of every target succeeded within `max_age`
+    ///
+    /// Returns `false` until at least one scrape has been recorded.
+    pub fn all_targets_healthy(&self, max_age: Duration) -> bool {
+        !self.targets.is_empty()
+            && self.targets.iter().all(|t| {
+                t.last_scrape()
+                    .is_some_and(|(at, success)| success && at.elapsed() <= max_age)
+            })
+    }
+
+    /// Record how much of a target's JMX surface the last scrape covered
+    pub fn record_coverage(
+        &self,
+        target: &str,
+        mbeans_discovered: usize,
+        mbeans_scraped: usize,
+        attributes_transformed: usize,
+    ) {
+        let metrics = self.target(target);
+        metrics.mbeans_discovered.set(mbeans_discovered as f64);
+        metrics.mbeans_scraped.set(mbeans_scraped as f64);
+        metrics
+            .mbeans_unmatched
+            .set(mbeans_discovered.saturating_sub(mbeans_scraped) as f64);
+        metrics
+            .attributes_transformed
+            .set(attributes_transformed as f64);
+    }
+
+    /// Record the Jolokia traffic of a scrape
+    pub fn record_traffic(&self, target: &str, requests: u64, response_bytes: u64) {
+        let metrics = self.target(target);
+        metrics.jolokia_requests.set(requests as f64);
+        metrics.response_bytes.set(response_bytes as f64);
+        metrics.jolokia_requests_total.inc_by(requests);
+        metrics.response_bytes_total.inc_by(response_bytes);
+    }
+
+    /// Record the number of series a scrape exposed
+    pub fn record_series_emitted(&self, target: &str, series: usize) {
+        self.target(target).series_emitted.set(series as f64);
+    }
+
+    /// Record the clock skew measured for a target, returning the previous value
+    pub fn record_clock_skew(&self, target: &str, skew_seconds: f64) -> f64 {
+        let metrics = self.target(target);
+        let previous = metrics.clock_skew_seconds.get();
+        metrics.clock_skew_seconds.set(skew_seconds);
+        previous
+    }
+
+    /// Record the backoff requested by a target, 0 if there is none
+    pub fn record_backoff(&self, target: &str, seconds: f64) {
+        self.target(target).backoff_seconds.set(seconds);
+    }
+
+    /// Record the interval until a target's next background scrape
+    pub fn record_scrape_interval(&self, target: &str, seconds: f64) {
+        self.target(target).scrape_interval_seconds.set(seconds);
+    }
+
+    /// Record the Jolokia agent and protocol version of a target
+    pub fn record_agent_version(&self, target: &str, agent: &str, protocol: &str) {
+        if let Ok(mut version) = self.target(target).agent_version.lock() {
+            *version = Some((agent.to_string(), protocol.to_string()));
+        }
+    }
+
+    /// Get or create the metrics handle for a rule
+    ///
+    /// The handle stays registered; callers on hot paths can keep it.
+    pub fn rule(&self, pattern: &str) -> Arc<RuleMetrics> {
+        handle(&self.rules, pattern)
+    }
+
+    /// Metrics of a rule, without registering it
+    pub fn find_rule(&self, pattern: &str) -> Option<Arc<RuleMetrics>> {
+        self.rules.get(pattern).map(|rule| Arc::clone(&rule))
+    }
+
+    /// Number of matches recorded for a rule, without registering it
+    pub fn rule_matches(&self, pattern: &str) -> u64 {
+        self.find_rule(pattern)
+            .map_or(0, |rule| rule.matches_total.get())
+    }
+
+    /// Record a rule match against an MBean
+    pub fn record_rule_match(&self, pattern: &str, mbean: &str) {
+        let rule = self.rule(pattern);
+        rule.matches_total.inc();
+        rule.recent_matches.record(mbean);
+    }
+
+    /// Record a rule error
+    pub fn record_rule_error(&self, pattern: &str) {
+        self.rule(pattern).errors_total.inc();
+    }
+
+    /// Record a config reload
+    pub fn record_config_reload(&self) {
+        self.config.reload_total.inc();
+        self.config.last_reload_timestamp.set_to_current_time();
+    }
+
+    /// Record a reload that kept or restored the previous config
+    pub fn record_config_rollback(&self) {
+        self.config.rollback_total.inc();
+    }
+
+    /// Record series dropped by a cardinality limit
+    pub fn record_series_dropped(&self, reason: &str, count: u64) {
+        if count == 0 {
+            return;
+        }
+        let dropped = &self.limits.series_dropped;
+        match dropped.get(reason) {
+            Some(counter) => counter.inc_by(count),
+            None => dropped.entry(reason.to_string()).or_default().inc_by(count),
+        }
+    }
+
+    /// Get the number of series dropped for a reason
+    pub fn series_dropped(&self, reason: &str) -> u64 {
+        self.limits
+            .series_dropped
+            .get(reason)
+            .map_or(0, |counter| counter.get())
+    }
+
+    /// Record an MBean a wildcard read reported an error for
+    pub fn record_mbean_error(&self, mbean: &str) {
+        let errors = &self.mbeans.errors;
+        match errors.get(mbean) {
+            Some(counter) => counter.inc(),
+            None => errors.entry(mbean.to_string()).or_default().inc(),
+        }
+    }
+
+    /// Get the number of errors recorded for an MBean
+    pub fn mbean_errors(&self, mbean: &str) -> u64 {
+        self.mbeans
+            .errors
+            .get(mbean)
+            .map_or(0, |counter| counter.get())
+    }
+
+    /// Record counter series that decreased between scrapes
+    pub fn record_counter_decreases(&self, count: u64) {
+        self.semantics.counter_decreases_total.inc_by(count);
+    }
+
+    /// Record a reset of a series of the counter metric `name`
+    pub fn record_counter_reset(&self, name: &str) {
+        let resets = &self.semantics.counter_resets;
+        match resets.get(name) {
+            Some(counter) => counter.inc(),
+            None => resets.entry(name.to_string()).or_default().inc(),
+        }
+    }
+
+    /// Get the number of resets recorded for the counter metric `name`
+    pub fn counter_resets(&self, name: &str) -> u64 {
+        self.semantics
+            .counter_resets
+            .get(name)
+            .map_or(0, |counter| counter.get())
+    }
+
+    /// Record the expiry timestamp of the served TLS certificate
+    pub fn set_tls_cert_expiry(&self, timestamp: f64) {
+        if let Ok(mut expiry) = self.tls.cert_expiry_timestamp.write() {
+            *expiry = Some(timestamp);
+        }
+    }
+
+    /// Get the expiry timestamp of the served TLS certificate, if known
+    pub fn tls_cert_expiry(&self) -> Option<f64> {
+        self.tls
+            .cert_expiry_timestamp
+            .read()
+            .ok()
+            .and_then(|expiry| *expiry)
+    }
+
+    /// Record a TLS certificate reload
+    pub fn record_tls_reload(&self) {
+        self.tls.reload_total.inc();
+    }
+
+    /// Update connection pool metrics
+    pub fn update_connections(&self, active: f64, idle: f64) {
+        self.connections.active.set(active);
+        self.connections.idle.set(idle);
+    }
+
+    /// Format all internal metrics as Prometheus metrics
+    pub fn to_prometheus_metrics(&self) -> Vec<PrometheusMetric> {
+        let mut metrics = Vec::new();
+
+        // Build and process metrics
+        metrics.push(
+            PrometheusMetric::new("rjmx_exporter_build_info", 1.0)
+                .with_type(MetricType::Gauge)
+                .with_help("Build information of rJMX-Exporter")
+                .with_label("version", env!("CARGO_PKG_VERSION"))
+                .with_label("commit", BUILD_COMMIT)
+                .with_label("rustc", BUILD_RUSTC)
+                .with_label("features", BUILD_FEATURES),
+        );
+        metrics.push(
+            PrometheusMetric::new(
+                "rjmx_process_start_time_seconds",
+                self.process_start_time.get(),
+            )
+            .with_type(MetricType::Gauge)
+            .with_help("Start time of the process since unix epoch in seconds"),
+        );
+
+        // Per-target metrics
+        for entry in self.targets.iter() {
+            let (target, target_metrics) = entry.pair();
+            // Scrape success counter
+            metrics.push(
+                PrometheusMetric::new(
+                    "rjmx_scrape_success_total",
+                    target_metrics.scrape_success_total.get() as f64,
+                )
+                .with_type(MetricType::Counter)
+                .with_help("Total number of successful scrapes")
+                .with_label("target", target),
+            );
+
+            // Scrape failure counter
+            metrics.push(
+                PrometheusMetric::new(
+                    "rjmx_scrape_failure_total",
+                    target_metrics.scrape_failure_total.get() as f64,
+                )
+                .with_type(MetricType::Counter)
+                .with_help("Total number of failed scrapes")
+                .with_label("target", target),
+            );
+
+            metrics.push(
+                PrometheusMetric::new(
+                    "rjmx_scrape_cancelled_total",
+                    target_metrics.scrape_cancelled_total.get() as f64,
+                )
+                .with_type(MetricType::Counter)
+                .with_help("Total number of scrapes abandoned before completing")
+                .with_label("target", target),
+            );
+
+            for category in ErrorCategory::ALL {
+                metrics.push(
+                    PrometheusMetric::new(
+                        "rjmx_scrape_errors_total",
+                        target_metrics.scrape_errors_total(category).get() as f64,
+                    )
+                    .with_type(MetricType::Counter)
+                    .with_help("Total number of scrape errors by category")
+                    .with_label("target", target)
+                    .with_label("category", category.as_str()),
+                );
+            }
+
+            // Scrape duration histogram
+            // Note: Histogram metrics use the base name with _bucket/_sum/_count suffixes
+            // The formatter will group these and emit a single "# TYPE ... histogram" line
+            let histogram = &target_metrics.scrape_duration_seconds;
+            for (bound, count) in histogram.get_buckets() {
+                let le = if bound.is_infinite() {
+                    "+Inf".to_string()
+                } else {
+                    format!("{}", bound)
+                };
+                metrics.push(
+                    PrometheusMetric::new("rjmx_scrape_duration_seconds_bucket", count as f64)
+                        .with_type(MetricType::Histogram)
+                        .with_help("Histogram of scrape durations")
+                        .with_label("target", target)
+                        .with_label("le", &le),
+                );
+            }
+            metrics.push(
+                PrometheusMetric::new("rjmx_scrape_duration_seconds_sum", histogram.get_sum())
+                    .with_type(MetricType::Histogram)
+                    .with_help("Histogram of scrape durations")
+                    .with_label("target", target),
+            );
+            metrics.push(
+                PrometheusMetric::new(
+                    "rjmx_scrape_duration_seconds_count",
+                    histogram.get_count() as f64,
+                )
+                .with_type(MetricType::Histogram)
+                .with_help("Histogram of scrape durations")
+                .with_label("target", target),
+            );
+
+            // Coverage gauges
+            metrics.push(
+                PrometheusMetric::new(
+                    "rjmx_mbeans_discovered",
+                    target_metrics.mbeans_discovered.get(),
+                )
+                .with_type(MetricType::Gauge)
+                .with_help("Number of MBeans returned by the last scrape")
+                .with_label("target", target),
+            );
+            metrics.push(
+                PrometheusMetric::new("rjmx_mbeans_scraped", target_metrics.mbeans_scraped.get())
+                    .with_type(MetricType::Gauge)
+                    .with_help("Number of MBeans that produced metrics in the last scrape")
+                    .with_label("target", target),
+            );
+            metrics.push(
+                PrometheusMetric::new(
+                    "rjmx_attributes_transformed",
+                    target_metrics.attributes_transformed.get(),
+                )
+                .with_type(MetricType::Gauge)
+                .with_help("Number of attributes converted to metrics in the last scrape")
+                .with_label("target", target),
+            );
+            metrics.push(
+                PrometheusMetric::new(
+                    "rjmx_mbeans_unmatched",
+                    target_metrics.mbeans_unmatched.get(),
+                )
+                .with_type(MetricType::Gauge)
+                .with_help("Number of MBeans that produced no metrics in the last scrape")
+                .with_label("target", target),
+            );
+
+            // Scrape budget
+            metrics.push(
+                PrometheusMetric::new("rjmx_series_emitted", target_metrics.series_emitted.get())
+                    .with_type(MetricType::Gauge)
+                    .with_help("Number of series exposed by the last scrape")
+                    .with_label("target", target),
+            );
+            metrics.push(
+                PrometheusMetric::new(
+                    "rjmx_scrape_jolokia_requests",
+                    target_metrics.jolokia_requests.get(),
+                )
+                .with_type(MetricType::Gauge)
+                .with_help("Number of Jolokia requests sent by the last scrape")
+                .with_label("target", target),
+            );
+            metrics.push(
+                PrometheusMetric::new(
+                    "rjmx_scrape_response_bytes",
+                    target_metrics.response_bytes.get(),
+                )
+                .with_type(MetricType::Gauge)
+                .with_help("Bytes of Jolokia responses received by the last scrape")
+                .with_label("target", target),
+            );
+            metrics.push(
+                PrometheusMetric::new(
+                    "rjmx_jolokia_requests_total",
+                    target_metrics.jolokia_requests_total.get() as f64,
+                )
+                .with_type(MetricType::Counter)
+                .with_help("Total number of Jolokia requests sent by scrapes")
+                .with_label("target", target),
+            );
+            metrics.push(
+                PrometheusMetric::new(
+                    "rjmx_jolokia_response_bytes_total",
+                    target_metrics.response_bytes_total.get() as f64,
+                )
+                .with_type(MetricType::Counter)
+                .with_help("Total bytes of Jolokia responses received by scrapes")
+                .with_label("target", target),
+            );
+            metrics.push(
+                PrometheusMetric::new(
+                    "rjmx_target_clock_skew_seconds",
+                    target_metrics.clock_skew_seconds.get(),
+                )
+                .with_type(MetricType::Gauge)
+                .with_help("Seconds the Jolokia agent's clock is ahead of the exporter's")
+                .with_label("target", target),
+            );
+            metrics.push(
+                PrometheusMetric::new(
+                    "rjmx_target_backoff_seconds",
+                    target_metrics.backoff_seconds.get(),
+                )
+                .with_type(MetricType::Gauge)
+                .with_help("Seconds left of the backoff requested by the target with Retry-After")
+                .with_label("target", target),
+            );
+            let interval = target_metrics.scrape_interval_seconds.get();
+            if interval > 0.0 {
+                metrics.push(
+                    PrometheusMetric::new("rjmx_target_scrape_interval_seconds", interval)
+                        .with_type(MetricType::Gauge)
+                        .with_help("Seconds between background scrapes of the target")
+                        .with_label("target", target),
+                );
+            }
+            if let Some((version, protocol)) = target_metrics.agent_version() {
+                metrics.push(
+                    PrometheusMetric::new("rjmx_jolokia_agent_info", 1.0)
+                        .with_type(MetricType::Gauge)
+                        .with_help("Version of the target's Jolokia agent")
+                        .with_label("target", target)
+                        .with_label("version", &version)
+                        .with_label("protocol", &protocol),
+                );
+            }
+        }
+
+        // Per-rule metrics
+        for entry in self.rules.iter() {
+            let (pattern, rule_metrics) = entry.pair();
+            metrics.push(
+                PrometheusMetric::new(
+                    "rjmx_rule_matches_total",
+                    rule_metrics.matches_total.get() as f64,
+                )
+                .with_type(MetricType::Counter)
+                .with_help("Total number of rule matches")
+                .with_label("rule", pattern),
+            );
+
+            metrics.push(
+                PrometheusMetric::new(
+                    "rjmx_rule_errors_total",
+                    rule_metrics.errors_total.get() as f64,
+                )
+                .with_type(MetricType::Counter)
+                .with_help("Total number of rule errors")
+                .with_label("rule", pattern),
+            );
+        }
+
+        // Connection pool metrics
+        metrics.push(
+            PrometheusMetric::new(
+                "rjmx_http_connections_active",
+                self.connections.active.get(),
+            )
+            .with_type(MetricType::Gauge)
+            .with_help("Number of active HTTP connections"),
+        );
+
+        metrics.push(
+            PrometheusMetric::new("rjmx_http_connections_idle", self.connections.idle.get())
+                .with_type(MetricType::Gauge)
+                .with_help("Number of idle HTTP connections"),
+        );
+
+        // Config metrics
+        metrics.push(
+            PrometheusMetric::new(
+                "rjmx_config_reload_total",
+                self.config.reload_total.get() as f64,
+            )
+            .with_type(MetricType::Counter)
+            .with_help("Total number of configuration reloads"),
+        );
+
+        metrics.push(
+            PrometheusMetric::new(
+                "rjmx_config_last_reload_timestamp",
+                self.config.last_reload_timestamp.get(),
+            )
+            .with_type(MetricType::Gauge)
+            .with_help("Unix timestamp of the last configuration reload"),
+        );
+
+        metrics.push(
+            PrometheusMetric::new(
+                "rjmx_config_rollback_total",
+                self.config.rollback_total.get() as f64,
+            )
+            .with_type(MetricType::Counter)
+            .with_help("Total number of configuration reloads rolled back"),
+        );
+
+        // Cardinality guardrail metrics
+        for entry in self.limits.series_dropped.iter() {
+            let (reason, counter) = entry.pair();
+            metrics.push(
+                PrometheusMetric::new("rjmx_series_dropped_total", counter.get() as f64)
+                    .with_type(MetricType::Counter)
+                    .with_help("Total number of series dropped by cardinality limits")
+                    .with_label("reason", reason),
+            );
+        }
+
+        for entry in self.mbeans.errors.iter() {
+            let (mbean, counter) = entry.pair();
+            metrics.push(
+                PrometheusMetric::new("rjmx_mbean_errors_total", counter.get() as f64)
+                    .with_type(MetricType::Counter)
+                    .with_help("Total number of errors reported for MBeans of wildcard reads")
+                    .with_label("mbean", mbean),
+            );
+        }
+
+        metrics.push(
+            PrometheusMetric::new(
+                "rjmx_counter_decreases_total",
+                self.semantics.counter_decreases_total.get() as f64,
+            )
+            .with_type(MetricType::Counter)
+            .with_help("Total number of counter series that decreased between scrapes"),
+        );
+        for entry in self.semantics.counter_resets.iter() {
+            let (name, counter) = entry.pair();
+            metrics.push(
+                PrometheusMetric::new("rjmx_counter_resets_total", counter.get() as f64)
+                    .with_type(MetricType::Counter)
+                    .with_help("Total number of resets of counter series, by metric name")
+                    .with_label("metric", name),
+            );
+        }
+
+        // TLS metrics (only when serving HTTPS)
+        if let Some(expiry) = self.tls_cert_expiry() {
+            metrics.push(
+                PrometheusMetric::new("rjmx_tls_cert_expiry_timestamp", expiry)
+                    .with_type(MetricType::Gauge)
+                    .with_help("Unix timestamp at which the served TLS certificate expires"),
+            );
+            metrics.push(
+                PrometheusMetric::new("rjmx_tls_reload_total", self.tls.reload_total.get() as f64)
+                    .with_type(MetricType::Counter)
+                    .with_help("Total number of TLS certificate reloads"),
+            );
+        }
+
+        metrics
+    }
+
+    /// Format internal metrics as Prometheus exposition format string
+    pub fn format_prometheus(&self) -> String {
+        use crate::transformer::PrometheusFormatter;
+
+        let metrics = self.to_prometheus_metrics();
+        let formatter = PrometheusFormatter::new();
+        formatter.format(&metrics)
+    }
+}
+
+/// Global internal metrics instance
+///
+/// Use this for convenient access to internal metrics throughout the application.
+static INTERNAL_METRICS: std::sync::OnceLock<InternalMetrics> = std::sync::OnceLock::new();
+
+/// Get the global internal metrics instance
+pub fn internal_metrics() -> &'static InternalMetrics {
+    INTERNAL_METRICS.get_or_init(InternalMetrics::new)
+}
+
+/// Initialize or reset the global internal metrics
+///
+/// Note: This can only be called once. Subsequent calls will return the existing instance.
+pub fn init_internal_metrics() -> &'static InternalMetrics {
+    internal_metrics()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_operations() {
+        let counter = Counter::new();
+        assert_eq!(counter.get(), 0);
+
+        counter.inc();
+        assert_eq!(counter.get(), 1);
+
+        counter.inc_by(5);
+        assert_eq!(counter.get(), 6);
+
+        counter.reset();
+        assert_eq!(counter.get(), 0);
+    }
+
+    #[test]
+    fn test_gauge_operations() {
+        let gauge = Gauge::new();
+        assert_eq!(gauge.get(), 0.0);
+
+        gauge.set(42.5);
+        assert_eq!(gauge.get(), 42.5);
+
+        gauge.inc(7.5);
+        assert_eq!(gauge.get(), 50.0);
+
+        gauge.dec(10.0);
+        assert_eq!(gauge.get(), 40.0);
+    }
+
+    #[test]
+    fn test_histogram_operations() {
+        let histogram = Histogram::new(&[0.1, 0.5, 1.0]);
+
+        histogram.observe(0.05);
+        histogram.observe(0.3);
+        histogram.observe(0.8);
+        histogram.observe(2.0);
+
+        assert_eq!(histogram.get_count(), 4);
+
+        let buckets = histogram.get_buckets();
+        // 0.05 <= 0.1
+        assert_eq!(buckets[0], (0.1, 1));
+        // 0.05, 0.3 <= 0.5
+        assert_eq!(buckets[1], (0.5, 2));
+        // 0.05, 0.3, 0.8 <= 1.0
+        assert_eq!(buckets[2], (1.0, 3));
+        // All values <= +Inf
+        assert_eq!(buckets[3].1, 4);
+    }
+
+    #[test]
+    fn test_histogram_default_buckets() {
+        let histogram = Histogram::with_default_buckets();
+        let buckets = histogram.get_buckets();
+
+        // Should have all default buckets plus +Inf
+        assert_eq!(buckets.len(), DEFAULT_HISTOGRAM_BUCKETS.len() + 1);
+    }
+
+    #[test]
+    fn test_internal_metrics_target() {
+        let metrics = InternalMetrics::new();
+
+        metrics.record_scrape_success("target1", 0.05);
+        metrics.record_scrape_success("target1", 0.10);
+        metrics.record_scrape_failure("target1", 0.50);
+        metrics.record_scrape_cancelled("target1");
+
+        let target_metrics = metrics.target("target1");
+        assert_eq!(target_metrics.scrape_success_total.get(), 2);
+        assert_eq!(target_metrics.scrape_failure_total.get(), 1);
+        assert_eq!(target_metrics.scrape_cancelled_total.get(), 1);
+        // Cancelled scrapes have no duration or result
+        assert_eq!(target_metrics.scrape_duration_seconds.get_count(), 3);
+        assert_eq!(target_metrics.last_scrape_duration_seconds.get(), 0.50);
+
+        metrics.record_scrape_success("target0", 0.01);
+        let names: Vec<String> = metrics
+            .targets()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["target0", "target1"]);
+    }
+
+    #[test]
+    fn test_scrape_errors_by_category() {
+        let metrics = InternalMetrics::new();
+
+        metrics.record_scrape_error("target1", ErrorCategory::Auth);
+        metrics.record_scrape_error("target1", ErrorCategory::Auth);
+        metrics.record_scrape_error("target1", ErrorCategory::Timeout);
+
+        let target_metrics = metrics.target("target1");
+        assert_eq!(
+            target_metrics
+                .scrape_errors_total(ErrorCategory::Auth)
+                .get(),
+            2
+        );
+        assert_eq!(
+            target_metrics
+                .scrape_errors_total(ErrorCategory::Timeout)
+                .get(),
+            1
+        );
+        assert_eq!(
+            target_metrics
+                .scrape_errors_total(ErrorCategory::Connect)
+                .get(),
+            0
+        );
+
+        // Every category is exposed, so rate() works before the first error
+        let output = metrics.format_prometheus();
+        assert!(output.contains(r#"rjmx_scrape_errors_total{category="auth",target="target1"} 2"#));
+        assert!(
+            output.contains(r#"rjmx_scrape_errors_total{category="connect",target="target1"} 0"#)
+        );
+        assert!(
+            output.contains(r#"rjmx_scrape_errors_total{category="transform",target="target1"} 0"#)
+        );
+    }
+
+    #[test]
+    fn test_internal_metrics_rule() {
+        let metrics = InternalMetrics::new();
+
+        metrics.record_rule_match("pattern1", "java.lang:type=Memory");
+        metrics.record_rule_match("pattern1", "java.lang:type=Threading");
+        metrics.record_rule_error("pattern1");
+
+        let rule_metrics = metrics.rule("pattern1");
+        assert_eq!(rule_metrics.matches_total.get(), 2);
+        assert_eq!(metrics.rule_matches("pattern1"), 2);
+        assert_eq!(metrics.rule_matches("unknown"), 0);
+        assert!(metrics.find_rule("unknown").is_none());
+        assert_eq!(rule_metrics.errors_total.get(), 1);
+        assert_eq!(
+            rule_metrics.recent_matches.get(),
+            vec!["java.lang:type=Threading", "java.lang:type=Memory"]
+        );
+    }
+
+    #[test]
+    fn test_recent_matches() {
+        let recent = RecentMatches::default();
+        for i in 0..RECENT_MATCHES + 2 {
+            recent.record(&format!("mbean{}", i));
+        }
+        let names = recent.get();
+        assert_eq!(names.len(), RECENT_MATCHES);
+        assert_eq!(names[0], format!("mbean{}", RECENT_MATCHES + 1));
+
+        // A repeated match moves to the front instead of being duplicated
+        recent.record("mbean3");
+        recent.record("mbean3");
+        let names = recent.get();
+        assert_eq!(names.len(), RECENT_MATCHES);
+        assert_eq!(names[0], "mbean3");
+        assert_eq!(names.iter().filter(|n| *n == "mbean3").count(), 1);
+    }
+
+    #[test]
+    fn test_internal_metrics_handles_are_shared() {
+        let metrics = InternalMetrics::new();
+        let handle = metrics.rule("pattern1");
+        assert!(Arc::ptr_eq(&handle, &metrics.rule("pattern1")));
+
+        // Recording through the registry is visible through an existing handle
+        metrics.record_rule_match("pattern1", "java.lang:type=Memory");
+        assert_eq!(handle.matches_total.get(), 1);
+
+        let target = metrics.target("target1");
+        assert!(target.last_scrape().is_none());
+        metrics.record_scrape_failure("target1", 0.1);
+        assert_eq!(
+            target.last_scrape().map(|(_, success)| success),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn test_internal_metrics_concurrent_recording() {
+        let metrics = InternalMetrics::new();
+
+        std::thread::scope(|scope| {
+            for t in 0..8 {
+                let metrics = &metrics;
+                scope.spawn(move || {
+                    for i in 0..1000 {
+                        metrics.record_rule_match(
+                            &format!("pattern{}", i % 10),
+                            "java.lang:type=Memory",
+                        );
+                        metrics.record_scrape_success(&format!("target{}", t % 2), 0.01);
+                    }
+                });
+            }
+        });
+
+        let matches: u64 = (0..10)
+            .map(|i| metrics.rule(&format!("pattern{}", i)).matches_total.get())
+            .sum();
+        assert_eq!(matches, 8000);
+        assert_eq!(metrics.target("target0").scrape_success_total.get(), 4000);
+        assert_eq!(metrics.target("target1").scrape_success_total.get(), 4000);
+    }
+
+    #[test]
+    fn test_internal_metrics_connections() {
+        let metrics = InternalMetrics::new();
+
+        metrics.update_connections(5.0, 10.0);
+
+        assert_eq!(metrics.connections.active.get(), 5.0);
+        assert_eq!(metrics.connections.idle.get(), 10.0);
+    }
+
+    #[test]
+    fn test_internal_metrics_config() {
+        let metrics = InternalMetrics::new();
+
+        let initial_timestamp = metrics.config.last_reload_timestamp.get();
+        assert!(initial_timestamp > 0.0);
+
+        metrics.record_config_reload();
+
+        assert_eq!(metrics.config.reload_total.get(), 1);
+        assert!(metrics.config.last_reload_timestamp.get() >= initial_timestamp);
+
+        metrics.record_config_rollback();
+        assert_eq!(metrics.config.rollback_total.get(), 1);
+    }
+
+    #[test]
+    fn test_internal_metrics_series_dropped() {
+        let metrics = InternalMetrics::new();
+
+        metrics.record_series_dropped("max_series_per_scrape", 5);
+        metrics.record_series_dropped("max_series_per_scrape", 3);
+        metrics.record_series_dropped("max_label_value_length", 0);
+
+        assert_eq!(metrics.series_dropped("max_series_per_scrape"), 8);
+        assert_eq!(metrics.series_dropped("max_label_value_length"), 0);
+
+        let output = metrics.format_prometheus();
+        assert!(output.contains("rjmx_series_dropped_total{reason=\"max_series_per_scrape\"} 8"));
+        assert!(!output.contains("max_label_value_length"));
+    }
+
+    #[test]
+    fn test_internal_metrics_tls() {
+        let metrics = InternalMetrics::new();
+        assert!(metrics.tls_cert_expiry().is_none());
+        assert!(!metrics
+            .format_prometheus()
+            .contains("rjmx_tls_cert_expiry_timestamp"));
+
+        metrics.set_tls_cert_expiry(1_900_000_000.0);
+        metrics.record_tls_reload();
+        assert_eq!(metrics.tls_cert_expiry(), Some(1_900_000_000.0));
+
+        let output = metrics.format_prometheus();
+        assert!(output.contains("rjmx_tls_cert_expiry_timestamp 1900000000"));
+        assert!(output.contains("rjmx_tls_reload_total 1"));
+    }
+
+    #[test]
+    fn test_build_info() {
+        let metrics = InternalMetrics::new();
+        let prometheus_metrics = metrics.to_prometheus_metrics();
+
+        let build_info = prometheus_metrics
+            .iter()
+            .find(|m| m.name == "rjmx_exporter_build_info")
+            .unwrap();
+        assert_eq!(build_info.value, 1.0);
+        assert!(!BUILD_COMMIT.is_empty());
+        assert!(BUILD_RUSTC.starts_with("rustc"));
+
+        let output = metrics.format_prometheus();
+        assert!(output.contains(&format!("version=\"{}\"", env!("CARGO_PKG_VERSION"))));
+        assert!(output.contains(&format!("commit=\"{}\"", BUILD_COMMIT)));
+
+        let start_time = prometheus_metrics
+            .iter()
+            .find(|m| m.name == "rjmx_process_start_time_seconds")
+            .unwrap();
+        assert!(start_time.value > 1_600_000_000.0);
+    }
+
+    #[test]
+    fn test_to_prometheus_metrics() {
+        let metrics = InternalMetrics::new();
+
+        metrics.record_scrape_success("test-target", 0.1);
+        metrics.record_rule_match("test-pattern", "java.lang:type=Memory");
+        metrics.update_connections(1.0, 2.0);
+
+        let prometheus_metrics = metrics.to_prometheus_metrics();
+
+        // Check that we have metrics for all categories
+        let metric_names: Vec<&str> = prometheus_metrics.iter().map(|m| m.name.as_str()).collect();
+
+        assert!(metric_names.contains(&"rjmx_scrape_success_total"));
+        assert!(metric_names.contains(&"rjmx_scrape_failure_total"));
+        assert!(metric_names.contains(&"rjmx_scrape_duration_seconds_bucket"));
+        assert!(metric_names.contains(&"rjmx_scrape_duration_seconds_sum"));
+        assert!(metric_names.contains(&"rjmx_scrape_duration_seconds_count"));
+        assert!(metric_names.contains(&"rjmx_mbeans_discovered"));
+        assert!(metric_names.contains(&"rjmx_mbeans_scraped"));
+        assert!(metric_names.contains(&"rjmx_attributes_transformed"));
+        assert!(metric_names.contains(&"rjmx_mbeans_unmatched"));
+        assert!(metric_names.contains(&"rjmx_series_emitted"));
+        assert!(metric_names.contains(&"rjmx_scrape_jolokia_requests"));
+        assert!(metric_names.contains(&"rjmx_jolokia_response_bytes_total"));
+        assert!(metric_names.contains(&"rjmx_target_clock_skew_seconds"));
+        assert!(metric_names.contains(&"rjmx_target_backoff_seconds"));
+        assert!(metric_names.contains(&"rjmx_rule_matches_total"));
+        assert!(metric_names.contains(&"rjmx_rule_errors_total"));
+        assert!(metric_names.contains(&"rjmx_http_connections_active"));
+        assert!(metric_names.contains(&"rjmx_http_connections_idle"));
+        assert!(metric_names.contains(&"rjmx_config_reload_total"));
+        assert!(metric_names.contains(&"rjmx_config_last_reload_timestamp"));
+        assert!(metric_names.contains(&"rjmx_config_rollback_total"));
+    }
+
+    #[test]
+    fn test_all_targets_healthy() {
+        let metrics = InternalMetrics::new();
+        let max_age = Duration::from_secs(60);
+        assert!(!metrics.all_targets_healthy(max_age));
+
+        metrics.record_scrape_success("a", 0.1);
+        metrics.record_scrape_success("b", 0.1);
+        assert!(metrics.all_targets_healthy(max_age));
+
+        metrics.record_scrape_failure("b", 0.1);
+        assert!(!metrics.all_targets_healthy(max_age));
+
+        metrics.record_scrape_success("b", 0.1);
+        assert!(metrics.all_targets_healthy(max_age));
+
+        metrics.record_scrape_failure("b", 0.1);
+        metrics.remove_target("b");
+        assert!(metrics.all_targets_healthy(max_age));
+    }
+
+    #[test]
+    fn test_record_coverage() {
+        let metrics = InternalMetrics::new();
+        metrics.record_coverage("target", 10, 4, 25);
+        metrics.record_coverage("target", 12, 5, 30);
+
+        let target_metrics = metrics.target("target");
+        assert_eq!(target_metrics.mbeans_discovered.get(), 12.0);
+        assert_eq!(target_metrics.mbeans_scraped.get(), 5.0);
+        assert_eq!(target_metrics.attributes_transformed.get(), 30.0);
+        assert_eq!(target_metrics.mbeans_unmatched.get(), 7.0);
+    }
+
+    #[test]
+    fn test_record_traffic() {
+        let metrics = InternalMetrics::new();
+        metrics.record_traffic("target", 3, 2048);
+        metrics.record_traffic("target", 2, 1024);
+        metrics.record_series_emitted("target", 40);
+
+        let target_metrics = metrics.target("target");
+        assert_eq!(target_metrics.jolokia_requests.get(), 2.0);
+        assert_eq!(target_metrics.response_bytes.get(), 1024.0);
+        assert_eq!(target_metrics.jolokia_requests_total.get(), 5);
+        assert_eq!(target_metrics.response_bytes_total.get(), 3072);
+        assert_eq!(target_metrics.series_emitted.get(), 40.0);
+    }
+
+    #[test]
+    fn test_record_clock_skew() {
+        let metrics = InternalMetrics::new();
+        assert_eq!(metrics.record_clock_skew("target", 12.0), 0.0);
+        assert_eq!(metrics.record_clock_skew("target", -3.0), 12.0);
+        assert_eq!(metrics.target("target").clock_skew_seconds.get(), -3.0);
+    }
+
+    #[test]
+    fn test_record_agent_version() {
+        let metrics = InternalMetrics::new();
+        assert_eq!(metrics.target("target").agent_version(), None);
+        metrics.record_agent_version("target", "1.7.2", "7.2");
+
+        let info = metrics
+            .to_prometheus_metrics()
+            .into_iter()
+            .find(|m| m.name == "rjmx_jolokia_agent_info")
+            .unwrap();
+        assert_eq!(info.labels.get("target"), Some("target"));
+        assert_eq!(info.labels.get("version"), Some("1.7.2"));
+        assert_eq!(info.labels.get("protocol"), Some("7.2"));
+    }
+
+    #[test]
+    fn test_record_mbean_error() {
+        let metrics = InternalMetrics::new();
+        let mbean = "java.lang:name=Broken,type=MemoryPool";
+        metrics.record_mbean_error(mbean);
+        metrics.record_mbean_error(mbean);
+        assert_eq!(metrics.mbean_errors(mbean), 2);
+        assert_eq!(metrics.mbean_errors("java.lang:type=Memory"), 0);
+
+        let errors = metrics
+            .to_prometheus_metrics()
+            .into_iter()
+            .find(|m| m.name == "rjmx_mbean_errors_total")
+            .unwrap();
+        assert_eq!(errors.labels.get("mbean"), Some(mbean));
+        assert_eq!(errors.value, 2.0);
+    }
+
+    #[test]
+    fn test_format_prometheus() {
+        let metrics = InternalMetrics::new();
+        metrics.record_scrape_success("localhost:8778", 0.05);
+
+        let output = metrics.format_prometheus();
+
+        assert!(output.contains("rjmx_scrape_success_total"));
+        assert!(output.contains("target=\"localhost:8778\""));
+        assert!(output.contains("# HELP"));
+        assert!(output.contains("# TYPE"));
+    }
+
+    #[test]
+    fn test_counter_clone() {
+        let counter = Counter::new();
+        counter.inc_by(42);
+
+        let cloned = counter.clone();
+        assert_eq!(cloned.get(), 42);
+
+        // Ensure independence
+        counter.inc();
+        assert_eq!(counter.get(), 43);
+        assert_eq!(cloned.get(), 42);
+    }
+
+    #[test]
+    fn test_gauge_clone() {
+        let gauge = Gauge::new();
+        gauge.set(3.5);
+
+        let cloned = gauge.clone();
+        assert_eq!(cloned.get(), 3.5);
+
+        // Ensure independence
+        gauge.set(2.5);
+        assert_eq!(gauge.get(), 2.5);
+        assert_eq!(cloned.get(), 3.5);
+    }
+
+    #[test]
+    fn test_histogram_clone() {
+        let histogram = Histogram::new(&[1.0, 5.0, 10.0]);
+        histogram.observe(2.0);
+        histogram.observe(7.0);
+
+        let cloned = histogram.clone();
+        assert_eq!(cloned.get_count(), 2);
+
+        // Ensure independence
+        histogram.observe(1.0);
+        assert_eq!(histogram.get_count(), 3);
+        assert_eq!(cloned.get_count(), 2);
+    }
+}
@@ -0,0 +1,248 @@
+//! Process self-metrics
+//!
+//! Resource usage of the exporter itself, read from `/proc/self` on each
+//! scrape, plus task counts of the tokio runtime serving it:
+//!
+//! - `process_cpu_seconds_total` - User and system CPU time spent
+//! - `process_resident_memory_bytes` - Resident memory size
+//! - `process_virtual_memory_bytes` - Virtual memory size
+//! - `process_open_fds` - Open file descriptors
+//! - `process_max_fds` - Limit on open file descriptors
+//! - `process_threads` - OS threads
+//! - `rjmx_process_tokio_workers` - Worker threads of the runtime
+//! - `rjmx_process_tokio_alive_tasks` - Tasks spawned and not yet finished
+//! - `rjmx_process_tokio_global_queue_depth` - Tasks waiting in the global queue
+//!
+//! The `process_*` names follow the Prometheus client library conventions.
+//! Values that cannot be read (e.g. `/proc` is missing on non-Linux
+//! platforms) are left out.
+
+use std::fs;
+use std::path::Path;
+
+use crate::transformer::{MetricType, PrometheusMetric};
+
+/// Clock ticks per second of `/proc/<pid>/stat` times
+///
+/// `USER_HZ` is part of the kernel ABI and is 100 on all mainstream
+/// architectures.
+const CLOCK_TICKS_PER_SECOND: f64 = 100.0;
+
+/// Resource usage read from procfs
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessStats {
+    /// User and system CPU time in seconds
+    pub cpu_seconds: Option<f64>,
+    /// Resident memory in bytes
+    pub resident_memory_bytes: Option<f64>,
+    /// Virtual memory in bytes
+    pub virtual_memory_bytes: Option<f64>,
+    /// Open file descriptors
+    pub open_fds: Option<f64>,
+    /// Soft limit on open file descriptors
+    pub max_fds: Option<f64>,
+    /// OS threads
+    pub threads: Option<f64>,
+}
+
+impl ProcessStats {
+    /// Read the stats of the current process
+    pub fn current() -> Self {
+        Self::read(Path::new("/proc/self"))
+    }
+
+    /// Read the stats of a process from its procfs directory
+    pub fn read(dir: &Path) -> Self {
+        let mut stats = Self::default();
+
+        if let Ok(stat) = fs::read_to_string(dir.join("stat")) {
+            stats.cpu_seconds = parse_cpu_seconds(&stat);
+        }
+        if let Ok(status) = fs::read_to_string(dir.join("status")) {
+            stats.resident_memory_bytes = status_kb(&status, "VmRSS").map(|kb| kb * 1024.0);
+            stats.virtual_memory_bytes = status_kb(&status, "VmSize").map(|kb| kb * 1024.0);
+            stats.threads = status_value(&status, "Threads").and_then(|v| v.parse().ok());
+        }
+        if let Ok(fds) = fs::read_dir(dir.join("fd")) {
+            stats.open_fds = Some(fds.count() as f64);
+        }
+        if let Ok(limits) = fs::read_to_string(dir.join("limits")) {
+            stats.max_fds = parse_max_fds(&limits);
+        }
+
+        stats
+    }
+}
+
+/// Sum `utime` and `stime` of a `/proc/<pid>/stat` line
+fn parse_cpu_seconds(stat: &str) -> Option<f64> {
+    // The command name may contain spaces and parentheses; fields after it
+    // start with the state (field 3), so utime (14) and stime (15) are at
+    // indices 11 and 12.
+    let (_, fields) = stat.rsplit_once(')')?;
+    let fields: Vec<&str> = fields.split_whitespace().collect();
+    let utime: f64 = fields.get(11)?.parse().ok()?;
+    let stime: f64 = fields.get(12)?.parse().ok()?;
+    Some((utime + stime) / CLOCK_TICKS_PER_SECOND)
+}
+
+/// Value of a `Key:` line in `/proc/<pid>/status`
+fn status_value<'a>(status: &'a str, key: &str) -> Option<&'a str> {
+    status.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name == key).then(|| value.trim())
+    })
+}
+
+/// Value of a `Key: <n> kB` line in `/proc/<pid>/status`
+fn status_kb(status: &str, key: &str) -> Option<f64> {
+    status_value(status, key)?
+        .strip_suffix("kB")?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Soft limit of `Max open files` in `/proc/<pid>/limits`
+fn parse_max_fds(limits: &str) -> Option<f64> {
+    let line = limits
+        .lines()
+        .find(|line| line.starts_with("Max open files"))?;
+    let soft = line["Max open files".len()..].split_whitespace().next()?;
+    soft.parse().ok()
+}
+
+/// Collect process and runtime metrics of the exporter
+pub fn process_metrics() -> Vec<PrometheusMetric> {
+    let stats = ProcessStats::current();
+    let mut metrics = Vec::new();
+
+    let mut push = |name: &str, help: &str, kind: MetricType, value: Option<f64>| {
+        if let Some(value) = value {
+            metrics.push(
+                PrometheusMetric::new(name, value)
+                    .with_type(kind)
+                    .with_help(help),
+            );
+        }
+    };
+
+    push(
+        "process_cpu_seconds_total",
+        "Total user and system CPU time spent in seconds",
+        MetricType::Counter,
+        stats.cpu_seconds,
+    );
+    push(
+        "process_resident_memory_bytes",
+        "Resident memory size in bytes",
+        MetricType::Gauge,
+        stats.resident_memory_bytes,
+    );
+    push(
+        "process_virtual_memory_bytes",
+        "Virtual memory size in bytes",
+        MetricType::Gauge,
+        stats.virtual_memory_bytes,
+    );
+    push(
+        "process_open_fds",
+        "Number of open file descriptors",
+        MetricType::Gauge,
+        stats.open_fds,
+    );
+    push(
+        "process_max_fds",
+        "Maximum number of open file descriptors",
+        MetricType::Gauge,
+        stats.max_fds,
+    );
+    push(
+        "process_threads",
+        "Number of OS threads in the process",
+        MetricType::Gauge,
+        stats.threads,
+    );
+
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        let runtime = handle.metrics();
+        push(
+            "rjmx_process_tokio_workers",
+            "Number of worker threads of the tokio runtime",
+            MetricType::Gauge,
+            Some(runtime.num_workers() as f64),
+        );
+        push(
+            "rjmx_process_tokio_alive_tasks",
+            "Number of tokio tasks spawned and not yet finished",
+            MetricType::Gauge,
+            Some(runtime.num_alive_tasks() as f64),
+        );
+        push(
+            "rjmx_process_tokio_global_queue_depth",
+            "Number of tokio tasks waiting in the global queue",
+            MetricType::Gauge,
+            Some(runtime.global_queue_depth() as f64),
+        );
+    }
+
+    metrics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_seconds() {
+        let stat = "4242 (rjmx (exporter)) S 1 4242 4242 0 -1 4194560 1200 0 0 0 \
+                    250 75 0 0 20 0 9 0 1000 123456789 2048 18446744073709551615";
+        assert_eq!(parse_cpu_seconds(stat), Some(3.25));
+        assert_eq!(parse_cpu_seconds("4242 (rjmx) S 1"), None);
+    }
+
+    #[test]
+    fn test_parse_status() {
+        let status =
+            "Name:\trjmx-exporter\nVmSize:\t  123456 kB\nVmRSS:\t    2048 kB\nThreads:\t9\n";
+        assert_eq!(status_kb(status, "VmRSS"), Some(2048.0));
+        assert_eq!(status_kb(status, "VmSize"), Some(123456.0));
+        assert_eq!(status_value(status, "Threads"), Some("9"));
+        assert_eq!(status_kb(status, "VmSwap"), None);
+    }
+
+    #[test]
+    fn test_parse_max_fds() {
+        let limits = "Limit                     Soft Limit           Hard Limit           Units     \n\
+                      Max cpu time              unlimited            unlimited            seconds   \n\
+                      Max open files            1024                 524288               files     \n";
+        assert_eq!(parse_max_fds(limits), Some(1024.0));
+        assert_eq!(parse_max_fds(""), None);
+    }
+
+    #[test]
+    fn test_missing_procfs() {
+        let stats = ProcessStats::read(Path::new("/nonexistent/proc/self"));
+        assert_eq!(stats, ProcessStats::default());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_process_metrics() {
+        let metrics = process_metrics();
+        let names: Vec<&str> = metrics.iter().map(|m| m.name.as_str()).collect();
+
+        assert!(names.contains(&"process_cpu_seconds_total"));
+        assert!(names.contains(&"process_resident_memory_bytes"));
+        assert!(names.contains(&"process_open_fds"));
+        assert!(names.contains(&"process_threads"));
+        assert!(names.contains(&"rjmx_process_tokio_workers"));
+        assert!(names.contains(&"rjmx_process_tokio_alive_tasks"));
+
+        let rss = metrics
+            .iter()
+            .find(|m| m.name == "process_resident_memory_bytes")
+            .unwrap();
+        assert!(rss.value > 0.0);
+    }
+}
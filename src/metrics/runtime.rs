@@ -0,0 +1,129 @@
+//! Tokio runtime metrics
+//!
+//! Per-worker scheduler metrics of the runtime serving the exporter, for
+//! diagnosing scrapes that stall under load. Built with
+//! `cargo build --features tokio-metrics`:
+//!
+//! - `rjmx_tokio_worker_busy_seconds_total{worker="..."}` - Time the worker spent running tasks
+//! - `rjmx_tokio_worker_park_total{worker="..."}` - Times the worker parked for lack of work
+//!
+//! Builds with `RUSTFLAGS="--cfg tokio_unstable"` additionally expose
+//! tokio's unstable metrics:
+//!
+//! - `rjmx_tokio_spawned_tasks_total` - Tasks spawned since the runtime started
+//! - `rjmx_tokio_blocking_threads` - Threads of the blocking pool
+//! - `rjmx_tokio_blocking_queue_depth` - Tasks waiting for a blocking thread
+//! - `rjmx_tokio_worker_local_queue_depth{worker="..."}` - Tasks queued on the worker
+//!
+//! Worker count, alive tasks and global queue depth are always exported by
+//! [`super::process`].
+
+use tokio::runtime::{Handle, RuntimeMetrics};
+
+use crate::transformer::{MetricType, PrometheusMetric};
+
+/// Collect metrics of the current tokio runtime
+///
+/// Returns nothing outside a runtime.
+pub fn runtime_metrics() -> Vec<PrometheusMetric> {
+    match Handle::try_current() {
+        Ok(handle) => collect(&handle.metrics()),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn collect(runtime: &RuntimeMetrics) -> Vec<PrometheusMetric> {
+    let mut metrics = Vec::new();
+
+    for worker in 0..runtime.num_workers() {
+        let label = worker.to_string();
+        metrics.push(
+            PrometheusMetric::new(
+                "rjmx_tokio_worker_busy_seconds_total",
+                runtime.worker_total_busy_duration(worker).as_secs_f64(),
+            )
+            .with_type(MetricType::Counter)
+            .with_help("Total time the tokio worker spent running tasks")
+            .with_label("worker", &label),
+        );
+        metrics.push(
+            PrometheusMetric::new(
+                "rjmx_tokio_worker_park_total",
+                runtime.worker_park_count(worker) as f64,
+            )
+            .with_type(MetricType::Counter)
+            .with_help("Total number of times the tokio worker parked")
+            .with_label("worker", &label),
+        );
+    }
+
+    #[cfg(tokio_unstable)]
+    collect_unstable(runtime, &mut metrics);
+
+    metrics
+}
+
+#[cfg(tokio_unstable)]
+fn collect_unstable(runtime: &RuntimeMetrics, metrics: &mut Vec<PrometheusMetric>) {
+    metrics.push(
+        PrometheusMetric::new(
+            "rjmx_tokio_spawned_tasks_total",
+            runtime.spawned_tasks_count() as f64,
+        )
+        .with_type(MetricType::Counter)
+        .with_help("Total number of tokio tasks spawned"),
+    );
+    metrics.push(
+        PrometheusMetric::new(
+            "rjmx_tokio_blocking_threads",
+            runtime.num_blocking_threads() as f64,
+        )
+        .with_type(MetricType::Gauge)
+        .with_help("Number of threads in the tokio blocking pool"),
+    );
+    metrics.push(
+        PrometheusMetric::new(
+            "rjmx_tokio_blocking_queue_depth",
+            runtime.blocking_queue_depth() as f64,
+        )
+        .with_type(MetricType::Gauge)
+        .with_help("Number of tasks waiting for a tokio blocking thread"),
+    );
+    for worker in 0..runtime.num_workers() {
+        metrics.push(
+            PrometheusMetric::new(
+                "rjmx_tokio_worker_local_queue_depth",
+                runtime.worker_local_queue_depth(worker) as f64,
+            )
+            .with_type(MetricType::Gauge)
+            .with_help("Number of tasks in the tokio worker's local queue")
+            .with_label("worker", &worker.to_string()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outside_runtime() {
+        assert!(runtime_metrics().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_runtime_metrics() {
+        tokio::spawn(async {}).await.unwrap();
+
+        let metrics = runtime_metrics();
+        let busy: Vec<_> = metrics
+            .iter()
+            .filter(|m| m.name == "rjmx_tokio_worker_busy_seconds_total")
+            .collect();
+        assert_eq!(busy.len(), 2);
+        assert_eq!(busy[1].labels.get("worker"), Some("1"));
+        assert!(metrics
+            .iter()
+            .any(|m| m.name == "rjmx_tokio_worker_park_total"));
+    }
+}
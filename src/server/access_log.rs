@@ -0,0 +1,384 @@
+//! Access logging middleware
+//!
+//! With `server.access_log` set to `common` or `json`, every request is
+//! written to stdout as one line once its response body has been sent, so
+//! the byte count and duration cover the whole streamed `/metrics` payload.
+//! Requests rejected by auth or the rate limiter are logged too.
+
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{ConnectInfo, Request, State},
+    http::{header, Method, StatusCode, Version},
+    middleware::Next,
+    response::Response,
+};
+use http_body::{Frame, SizeHint};
+use serde::Serialize;
+
+use crate::config::AccessLogFormat;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Access log format and destination
+pub struct AccessLog {
+    format: AccessLogFormat,
+    writer: Box<dyn Fn(&str) + Send + Sync>,
+}
+
+impl AccessLog {
+    /// Access log writing to stdout
+    pub fn new(format: AccessLogFormat) -> Self {
+        Self::with_writer(format, |line| println!("{}", line))
+    }
+
+    /// Access log handing every line to `writer`
+    pub fn with_writer(
+        format: AccessLogFormat,
+        writer: impl Fn(&str) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            format,
+            writer: Box::new(writer),
+        }
+    }
+
+    fn write(&self, entry: &Entry) {
+        let line = match self.format {
+            AccessLogFormat::Off => return,
+            AccessLogFormat::Common => entry.common(),
+            AccessLogFormat::Json => entry.json(),
+        };
+        (self.writer)(&line);
+    }
+}
+
+/// One logged request
+#[derive(Debug, Clone)]
+struct Entry {
+    time: SystemTime,
+    remote_addr: Option<SocketAddr>,
+    method: Method,
+    target: String,
+    version: Version,
+    user_agent: Option<String>,
+    status: StatusCode,
+    bytes: u64,
+    duration: Duration,
+}
+
+impl Entry {
+    /// NCSA Common Log Format line
+    fn common(&self) -> String {
+        let host = self
+            .remote_addr
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let bytes = if self.bytes == 0 {
+            "-".to_string()
+        } else {
+            self.bytes.to_string()
+        };
+        format!(
+            "{} - - [{}] \"{} {} {:?}\" {} {}",
+            host,
+            clf_time(self.time),
+            self.method,
+            self.target,
+            self.version,
+            self.status.as_u16(),
+            bytes
+        )
+    }
+
+    /// JSON object line
+    fn json(&self) -> String {
+        #[derive(Serialize)]
+        struct Line<'a> {
+            time: String,
+            remote_addr: Option<String>,
+            method: &'a str,
+            path: &'a str,
+            protocol: String,
+            status: u16,
+            bytes: u64,
+            duration_ms: f64,
+            user_agent: Option<&'a str>,
+        }
+
+        let line = Line {
+            time: rfc3339_time(self.time),
+            remote_addr: self.remote_addr.map(|addr| addr.to_string()),
+            method: self.method.as_str(),
+            path: &self.target,
+            protocol: format!("{:?}", self.version),
+            status: self.status.as_u16(),
+            bytes: self.bytes,
+            duration_ms: self.duration.as_micros() as f64 / 1000.0,
+            user_agent: self.user_agent.as_deref(),
+        };
+        serde_json::to_string(&line).unwrap_or_default()
+    }
+}
+
+/// Log every request once its response has been sent
+pub async fn log_requests(
+    State(log): State<Arc<AccessLog>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let start = Instant::now();
+    let time = SystemTime::now();
+    let remote_addr = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
+    let method = request.method().clone();
+    let target = request
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let version = request.version();
+    let user_agent = request
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let response = next.run(request).await;
+    let entry = Entry {
+        time,
+        remote_addr,
+        method,
+        target,
+        version,
+        user_agent,
+        status: response.status(),
+        bytes: 0,
+        duration: Duration::ZERO,
+    };
+
+    response.map(|body| {
+        Body::new(LoggedBody {
+            inner: body,
+            bytes: 0,
+            pending: Some((log, entry, start)),
+        })
+    })
+}
+
+/// Response body that counts the bytes sent and logs the request when dropped
+///
+/// The server drops the body once it has been written out or the client
+/// went away, so the line is written exactly once either way.
+struct LoggedBody {
+    inner: Body,
+    bytes: u64,
+    pending: Option<(Arc<AccessLog>, Entry, Instant)>,
+}
+
+impl http_body::Body for LoggedBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let poll = Pin::new(&mut self.inner).poll_frame(cx);
+        if let Poll::Ready(Some(Ok(frame))) = &poll {
+            if let Some(data) = frame.data_ref() {
+                self.bytes += data.len() as u64;
+            }
+        }
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for LoggedBody {
+    fn drop(&mut self) {
+        if let Some((log, mut entry, start)) = self.pending.take() {
+            entry.bytes = self.bytes;
+            entry.duration = start.elapsed();
+            log.write(&entry);
+        }
+    }
+}
+
+/// UTC date and time of day split into fields
+pub(crate) fn utc_fields(time: SystemTime) -> (i64, u32, u32, u64, u64, u64, u32) {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let of_day = secs % 86_400;
+    (
+        year,
+        month,
+        day,
+        of_day / 3600,
+        of_day % 3600 / 60,
+        of_day % 60,
+        since_epoch.subsec_millis(),
+    )
+}
+
+/// `17/Oct/2026:13:55:36 +0000`
+fn clf_time(time: SystemTime) -> String {
+    let (year, month, day, hour, minute, second, _) = utc_fields(time);
+    format!(
+        "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        hour,
+        minute,
+        second
+    )
+}
+
+/// `2026-10-17T13:55:36.123Z`
+fn rfc3339_time(time: SystemTime) -> String {
+    let (year, month, day, hour, minute, second, millis) = utc_fields(time);
+    format!(
+        "{}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year, month, day, hour, minute, second, millis
+    )
+}
+
+/// Proleptic Gregorian date of a day count since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    use axum::{middleware, routing::get, Router};
+    use tower::Service;
+
+    fn entry() -> Entry {
+        Entry {
+            // 2026-10-17T13:55:36.250Z
+            time: UNIX_EPOCH + Duration::from_millis(1_792_245_336_250),
+            remote_addr: Some("10.0.0.7:51234".parse().unwrap()),
+            method: Method::GET,
+            target: "/metrics?debug=1".to_string(),
+            version: Version::HTTP_11,
+            user_agent: Some("Prometheus/2.53.0".to_string()),
+            status: StatusCode::OK,
+            bytes: 5123,
+            duration: Duration::from_micros(12_345),
+        }
+    }
+
+    #[test]
+    fn test_time_formats() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+
+        let time = entry().time;
+        assert_eq!(clf_time(time), "17/Oct/2026:13:55:36 +0000");
+        assert_eq!(rfc3339_time(time), "2026-10-17T13:55:36.250Z");
+    }
+
+    #[test]
+    fn test_common_format() {
+        assert_eq!(
+            entry().common(),
+            "10.0.0.7 - - [17/Oct/2026:13:55:36 +0000] \"GET /metrics?debug=1 HTTP/1.1\" 200 5123"
+        );
+
+        let empty = Entry {
+            remote_addr: None,
+            status: StatusCode::NOT_MODIFIED,
+            bytes: 0,
+            ..entry()
+        };
+        assert!(empty.common().starts_with("- - - ["));
+        assert!(empty.common().ends_with("\" 304 -"));
+    }
+
+    #[test]
+    fn test_json_format() {
+        let line: serde_json::Value = serde_json::from_str(&entry().json()).unwrap();
+        assert_eq!(
+            line,
+            serde_json::json!({
+                "time": "2026-10-17T13:55:36.250Z",
+                "remote_addr": "10.0.0.7:51234",
+                "method": "GET",
+                "path": "/metrics?debug=1",
+                "protocol": "HTTP/1.1",
+                "status": 200,
+                "bytes": 5123,
+                "duration_ms": 12.345,
+                "user_agent": "Prometheus/2.53.0",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_log_requests() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::new(AccessLog::with_writer(AccessLogFormat::Json, {
+            let lines = lines.clone();
+            move |line| lines.lock().unwrap().push(line.to_string())
+        }));
+        let mut app = Router::new()
+            .route("/metrics", get(|| async { "up 1\n" }))
+            .layer(middleware::from_fn_with_state(log, log_requests));
+
+        let response = app
+            .call(
+                Request::get("/metrics")
+                    .header(header::USER_AGENT, "test")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert!(lines.lock().unwrap().is_empty());
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"up 1\n");
+
+        let lines = lines.lock().unwrap();
+        assert_eq!(lines.len(), 1);
+        let line: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(line["path"], "/metrics");
+        assert_eq!(line["status"], 200);
+        assert_eq!(line["bytes"], 5);
+        assert_eq!(line["user_agent"], "test");
+        assert!(line["remote_addr"].is_null());
+    }
+}
@@ -0,0 +1,308 @@
+//! Authentication middleware for the exporter's own endpoints
+//!
+//! Supports HTTP Basic auth (password checked against a SHA-256 hash) and
+//! static bearer tokens, as configured under `server.auth`. Credentials are
+//! compared in constant time.
+//!
+//! Tenant tokens (`server.auth.tenants`) are only let through to the metrics
+//! endpoints, with the tenant's [`MetricFilter`] attached to the request.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use tracing::debug;
+
+use crate::config::{AuthConfig, TenantConfig};
+use crate::transformer::PrometheusMetric;
+
+/// Realm advertised in `WWW-Authenticate` challenges
+const REALM: &str = "rjmx-exporter";
+
+/// Series a tenant may see
+#[derive(Debug)]
+pub struct MetricFilter {
+    /// Tenant name, for logs
+    pub tenant: String,
+    /// Allowed metric name prefixes (all names if empty)
+    prefixes: Vec<String>,
+    /// Anchored regexes that label values must match
+    labels: Vec<(String, Regex)>,
+}
+
+impl MetricFilter {
+    /// Compile the filter of a tenant
+    ///
+    /// Invalid regexes are rejected by config validation; here they hide
+    /// every series instead.
+    fn new(tenant: &TenantConfig) -> Self {
+        let never = || Regex::new("$^").expect("valid regex");
+        Self {
+            tenant: tenant.name.clone(),
+            prefixes: tenant.metric_prefixes.clone(),
+            labels: tenant
+                .match_labels
+                .iter()
+                .map(|(label, pattern)| {
+                    let regex =
+                        Regex::new(&format!("^(?:{})$", pattern)).unwrap_or_else(|_| never());
+                    (label.clone(), regex)
+                })
+                .collect(),
+        }
+    }
+
+    /// Check whether the tenant may see a series
+    ///
+    /// A missing label is matched as the empty string, as in Prometheus.
+    pub fn allows(&self, metric: &PrometheusMetric) -> bool {
+        (self.prefixes.is_empty() || self.prefixes.iter().any(|p| metric.name.starts_with(p)))
+            && self
+                .labels
+                .iter()
+                .all(|(label, regex)| regex.is_match(metric.labels.get(label).unwrap_or_default()))
+    }
+}
+
+/// Credentials accepted by the auth layer
+pub struct Auth {
+    config: AuthConfig,
+    /// Token and compiled filter of each tenant
+    tenants: Vec<(String, Arc<MetricFilter>)>,
+    /// Paths tenants may request
+    metrics_paths: Vec<String>,
+}
+
+impl Auth {
+    /// Compile the configured credentials; tenants may only request `metrics_paths`
+    pub fn new(config: &AuthConfig, metrics_paths: &[&str]) -> Self {
+        Self {
+            tenants: config
+                .tenants
+                .iter()
+                .filter_map(|tenant| {
+                    let token = tenant.bearer_token.clone()?;
+                    Some((token, Arc::new(MetricFilter::new(tenant))))
+                })
+                .collect(),
+            config: config.clone(),
+            metrics_paths: metrics_paths.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+}
+
+/// Identity established from a request's credentials
+#[derive(Debug)]
+enum Identity {
+    /// Configured basic auth or bearer token: unrestricted
+    Full,
+    /// Tenant token: restricted to the filter's series
+    Tenant(Arc<MetricFilter>),
+}
+
+/// Reject requests that do not carry valid credentials
+pub async fn require_auth(
+    State(auth): State<Arc<Auth>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let identity = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|value| authenticate(&auth, value));
+
+    match identity {
+        Some(Identity::Full) => next.run(request).await,
+        Some(Identity::Tenant(filter)) => {
+            let path = request.uri().path();
+            if !auth.metrics_paths.iter().any(|p| p == path) {
+                debug!(path = %path, tenant = %filter.tenant, "Rejecting tenant request");
+                return (StatusCode::FORBIDDEN, "Forbidden").into_response();
+            }
+            request.extensions_mut().insert(filter);
+            next.run(request).await
+        }
+        None => {
+            debug!(path = %request.uri().path(), "Rejecting unauthenticated request");
+            unauthorized(&auth.config)
+        }
+    }
+}
+
+/// Identify an `Authorization` header value
+fn authenticate(auth: &Auth, value: &str) -> Option<Identity> {
+    if is_authorized(&auth.config, value) {
+        return Some(Identity::Full);
+    }
+    let (scheme, credentials) = value.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("Bearer") {
+        return None;
+    }
+    // Compare against every token, so timing does not reveal which matched
+    let credentials = credentials.trim().as_bytes();
+    auth.tenants
+        .iter()
+        .fold(None, |found, (token, filter)| {
+            let matches = bool::from(token.as_bytes().ct_eq(credentials));
+            found.or(matches.then(|| filter.clone()))
+        })
+        .map(Identity::Tenant)
+}
+
+/// Check an `Authorization` header value against the configuration
+fn is_authorized(auth: &AuthConfig, value: &str) -> bool {
+    let Some((scheme, credentials)) = value.split_once(' ') else {
+        return false;
+    };
+    let credentials = credentials.trim();
+
+    if scheme.eq_ignore_ascii_case("Bearer") {
+        return auth
+            .bearer_token
+            .as_deref()
+            .is_some_and(|token| bool::from(token.as_bytes().ct_eq(credentials.as_bytes())));
+    }
+
+    if scheme.eq_ignore_ascii_case("Basic") {
+        let (Some(username), Some(password_sha256)) = (&auth.username, &auth.password_sha256)
+        else {
+            return false;
+        };
+        let Some((user, password)) = STANDARD
+            .decode(credentials)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .and_then(|s| {
+                s.split_once(':')
+                    .map(|(u, p)| (u.to_string(), p.to_string()))
+            })
+        else {
+            return false;
+        };
+
+        let digest = hex_sha256(password.as_bytes());
+        let user_ok = user.as_bytes().ct_eq(username.as_bytes());
+        let password_ok = digest
+            .as_bytes()
+            .ct_eq(password_sha256.to_ascii_lowercase().as_bytes());
+        return bool::from(user_ok & password_ok);
+    }
+
+    false
+}
+
+/// Lowercase hex SHA-256 digest
+fn hex_sha256(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 401 response with a challenge for the configured scheme
+fn unauthorized(auth: &AuthConfig) -> Response {
+    let challenge = if auth.username.is_some() {
+        format!("Basic realm=\"{}\"", REALM)
+    } else {
+        format!("Bearer realm=\"{}\"", REALM)
+    };
+
+    let mut response = (StatusCode::UNAUTHORIZED, "Unauthorized").into_response();
+    if let Ok(value) = HeaderValue::from_str(&challenge) {
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth_config() -> AuthConfig {
+        AuthConfig {
+            username: Some("prometheus".to_string()),
+            // printf '%s' secret | sha256sum
+            password_sha256: Some(
+                "2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b".to_string(),
+            ),
+            bearer_token: Some("token".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn basic(user: &str, password: &str) -> String {
+        format!(
+            "Basic {}",
+            STANDARD.encode(format!("{}:{}", user, password))
+        )
+    }
+
+    #[test]
+    fn test_basic_auth() {
+        let auth = auth_config();
+        assert!(is_authorized(&auth, &basic("prometheus", "secret")));
+        assert!(!is_authorized(&auth, &basic("prometheus", "wrong")));
+        assert!(!is_authorized(&auth, &basic("other", "secret")));
+        assert!(!is_authorized(&auth, "Basic not-base64!"));
+    }
+
+    #[test]
+    fn test_bearer_auth() {
+        let auth = auth_config();
+        assert!(is_authorized(&auth, "Bearer token"));
+        assert!(is_authorized(&auth, "bearer token"));
+        assert!(!is_authorized(&auth, "Bearer other"));
+        assert!(!is_authorized(&auth, "Bearer"));
+        assert!(!is_authorized(&auth, "Digest token"));
+
+        let basic_only = AuthConfig {
+            bearer_token: None,
+            ..auth_config()
+        };
+        assert!(!is_authorized(&basic_only, "Bearer token"));
+    }
+
+    #[test]
+    fn test_tenants() {
+        let config = AuthConfig {
+            tenants: vec![TenantConfig {
+                name: "payments".to_string(),
+                bearer_token: Some("payments-token".to_string()),
+                metric_prefixes: vec!["payments_".to_string(), "jvm_".to_string()],
+                match_labels: [("app".to_string(), "payments|checkout".to_string())].into(),
+                ..Default::default()
+            }],
+            ..auth_config()
+        };
+        let auth = Auth::new(&config, &["/metrics"]);
+
+        assert!(matches!(
+            authenticate(&auth, "Bearer token"),
+            Some(Identity::Full)
+        ));
+        assert!(authenticate(&auth, "Bearer other").is_none());
+        let Some(Identity::Tenant(filter)) = authenticate(&auth, "Bearer payments-token") else {
+            panic!("tenant token not recognized");
+        };
+        assert_eq!(filter.tenant, "payments");
+
+        let metric =
+            |name: &str, app: &str| PrometheusMetric::new(name, 1.0).with_label("app", app);
+        assert!(filter.allows(&metric("payments_total", "payments")));
+        assert!(filter.allows(&metric("jvm_threads", "checkout")));
+        assert!(!filter.allows(&metric("jvm_threads", "checkout-v2")));
+        assert!(!filter.allows(&metric("orders_total", "payments")));
+        assert!(!filter.allows(&PrometheusMetric::new("payments_total", 1.0)));
+    }
+}
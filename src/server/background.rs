@@ -0,0 +1,314 @@
+//! Background collection
+//!
+//! Scrapes every target on its own schedule and keeps the latest result of
+//! each, which `/metrics` serves instead of scraping on request. A target
+//! waits `background.interval_seconds` (or its `__scrape_interval__` label)
+//! between scrapes, give or take `background.jitter`. When a scrape fails or
+//! takes longer than that interval, the interval doubles, up to
+//! `background.max_interval_seconds`, until a scrape is healthy again. The
+//! current interval is exported as `rjmx_target_scrape_interval_seconds`.
+//!
+//! A target with a cron schedule (`background.schedule` or its
+//! `__scrape_schedule__` label) is instead scraped at the minutes the schedule
+//! selects, without jitter or backoff; it is first scraped at its first
+//! scheduled minute. The next run of each target is listed by
+//! `/api/v1/targets`.
+//!
+//! Requests selecting a module or collect groups still scrape on request.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use rand::Rng;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+use super::file_sd::TargetState;
+use super::handlers::{sanitize_url_for_label, scrape_target, ScrapeTarget, TargetScrape};
+use super::{AppState, LastScrape};
+use crate::collector::{CronSchedule, MBeanSelection};
+use crate::config::BackgroundConfig;
+use crate::metrics::internal_metrics;
+
+/// How often the set of targets is checked for added targets
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Scheduled scrapes of every target and their latest results
+pub struct Background {
+    interval: Duration,
+    jitter: f64,
+    max_interval: Duration,
+    schedule: Option<CronSchedule>,
+    /// Latest labelled scrape of each target, keyed by target name
+    results: RwLock<HashMap<String, TargetScrape>>,
+    /// When each target is scraped next, keyed by target name
+    next_runs: RwLock<HashMap<String, SystemTime>>,
+}
+
+impl Background {
+    /// Background collection with the given schedule; nothing runs until [`Background::spawn`]
+    pub fn new(config: &BackgroundConfig) -> Self {
+        Self {
+            interval: Duration::from_secs(config.interval_seconds),
+            jitter: config.jitter,
+            max_interval: Duration::from_secs(config.max_interval_seconds),
+            schedule: config.schedule.clone(),
+            results: RwLock::default(),
+            next_runs: RwLock::default(),
+        }
+    }
+
+    /// When a target is scraped next, once its first wait has started
+    pub(super) fn next_run(&self, name: &str) -> Option<SystemTime> {
+        self.next_runs.read().ok()?.get(name).copied()
+    }
+
+    /// Latest scrapes of the current targets, in target order, without responses
+    ///
+    /// Returns `None` until a target has been scraped.
+    pub(super) fn results(&self, state: &AppState) -> Option<Vec<TargetScrape>> {
+        let results = self.results.read().ok()?;
+        let scrapes: Vec<TargetScrape> = target_names(state)
+            .iter()
+            .filter_map(|name| results.get(name))
+            .map(|scrape| TargetScrape {
+                metrics: scrape.metrics.clone(),
+                responses: Vec::new(),
+                skipped: Vec::new(),
+                errors: scrape.errors.clone(),
+                stats: scrape.stats,
+            })
+            .collect();
+        (!scrapes.is_empty()).then_some(scrapes)
+    }
+
+    /// Scrape every target on its schedule until shutdown starts
+    pub fn spawn(self: Arc<Self>, state: AppState) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut tasks = TargetTasks::default();
+            let mut ticker = tokio::time::interval(SUPERVISE_INTERVAL);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                if state.shutdown.is_draining() {
+                    break;
+                }
+                // Tasks end on their own when their target is removed
+                tasks.0.retain(|_, task| !task.is_finished());
+                for name in target_names(&state) {
+                    tasks.0.entry(name.clone()).or_insert_with(|| {
+                        tokio::spawn(Arc::clone(&self).run_target(state.clone(), name))
+                    });
+                }
+            }
+        })
+    }
+
+    /// Scrape one target until it is removed or shutdown starts
+    async fn run_target(self: Arc<Self>, state: AppState, name: String) {
+        let mut interval = None;
+        let mut due = false;
+        loop {
+            let discovered = match &state.file_sd {
+                Some(file_sd) => match file_sd.targets().iter().find(|t| t.name == name) {
+                    Some(target) => Some(Arc::clone(target)),
+                    None => break,
+                },
+                None => None,
+            };
+            let base = discovered
+                .as_ref()
+                .and_then(|target| target.target.interval)
+                .unwrap_or(self.interval);
+            let schedule = discovered
+                .as_ref()
+                .and_then(|target| target.target.schedule.clone())
+                .or_else(|| self.schedule.clone());
+
+            if let Some(schedule) = &schedule {
+                if !due {
+                    // Re-read the target after waiting, in case it was removed or changed
+                    let now = SystemTime::now();
+                    let at = schedule.next_after(now).unwrap_or(now + self.max_interval);
+                    self.set_next_run(&name, at);
+                    tokio::select! {
+                        _ = tokio::time::sleep(at.duration_since(now).unwrap_or_default()) => {}
+                        _ = state.shutdown.triggered() => return,
+                    }
+                    due = true;
+                    continue;
+                }
+                due = false;
+                let scrape = self.scrape(&state, discovered.as_deref(), base).await;
+                self.store(&state, &name, scrape);
+                continue;
+            }
+
+            let current = interval.unwrap_or(base);
+            let start = Instant::now();
+            let scrape = self.scrape(&state, discovered.as_deref(), current).await;
+            let healthy = scrape.errors.is_empty() && start.elapsed() <= base;
+            let next = next_interval(current, base, self.max_interval.max(base), healthy);
+            if next > current {
+                debug!(
+                    target = %name,
+                    interval_seconds = next.as_secs_f64(),
+                    "Backing off background scrapes"
+                );
+            }
+            internal_metrics().record_scrape_interval(&name, next.as_secs_f64());
+            self.store(&state, &name, scrape);
+            interval = Some(next);
+
+            let wait = jittered(next, self.jitter);
+            self.set_next_run(&name, SystemTime::now() + wait);
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = state.shutdown.triggered() => return,
+            }
+        }
+
+        // The target was removed from file_sd
+        if let Ok(mut results) = self.results.write() {
+            results.remove(&name);
+        }
+        if let Ok(mut next_runs) = self.next_runs.write() {
+            next_runs.remove(&name);
+        }
+        internal_metrics().remove_target(&name);
+    }
+
+    fn set_next_run(&self, name: &str, at: SystemTime) {
+        if let Ok(mut next_runs) = self.next_runs.write() {
+            next_runs.insert(name.to_string(), at);
+        }
+    }
+
+    /// Scrape all MBeans of a target, giving up after its current interval
+    async fn scrape(
+        &self,
+        state: &AppState,
+        discovered: Option<&TargetState>,
+        interval: Duration,
+    ) -> TargetScrape {
+        let target = match discovered {
+            Some(target) => ScrapeTarget::discovered(target),
+            None => ScrapeTarget::new(
+                &state.config.jolokia.url,
+                &state.collector,
+                &state.engine,
+                &state.config.jolokia.labels,
+            ),
+        };
+        match scrape_target(state, &target, &MBeanSelection::all(), None, Some(interval)).await {
+            Ok(scrape) => target.label(state, scrape),
+            Err(deadline) => target.timed_out(deadline),
+        }
+    }
+
+    /// Keep a target's scrape and refresh the responses for `/debug/mbeans`
+    fn store(&self, state: &AppState, name: &str, scrape: TargetScrape) {
+        let (responses, skipped) = match self.results.write() {
+            Ok(mut results) => {
+                results.insert(name.to_string(), scrape);
+                let scrapes: Vec<&TargetScrape> = target_names(state)
+                    .iter()
+                    .filter_map(|name| results.get(name))
+                    .collect();
+                (
+                    scrapes
+                        .iter()
+                        .flat_map(|scrape| scrape.responses.iter().cloned())
+                        .collect(),
+                    scrapes
+                        .iter()
+                        .flat_map(|scrape| scrape.skipped.iter().cloned())
+                        .collect(),
+                )
+            }
+            Err(_) => return,
+        };
+        if let Ok(mut last_scrape) = state.last_scrape.write() {
+            *last_scrape = Some(LastScrape {
+                module: None,
+                responses,
+                skipped,
+            });
+        }
+    }
+}
+
+/// Scrape tasks of the targets, keyed by target name
+///
+/// The tasks are aborted when the supervisor stops, e.g. when a reload
+/// replaces the background collection.
+#[derive(Default)]
+struct TargetTasks(HashMap<String, JoinHandle<()>>);
+
+impl Drop for TargetTasks {
+    fn drop(&mut self) {
+        for task in self.0.values() {
+            task.abort();
+        }
+    }
+}
+
+/// Names of the targets to scrape: the discovered ones, or `jolokia.url`
+fn target_names(state: &AppState) -> Vec<String> {
+    match &state.file_sd {
+        Some(file_sd) => file_sd
+            .targets()
+            .iter()
+            .map(|target| target.name.clone())
+            .collect(),
+        None => vec![sanitize_url_for_label(&state.config.jolokia.url)],
+    }
+}
+
+/// Interval until the next scrape of a target
+///
+/// Healthy targets go back to `base`; others wait twice as long as before,
+/// up to `max`.
+pub fn next_interval(current: Duration, base: Duration, max: Duration, healthy: bool) -> Duration {
+    if healthy {
+        base
+    } else {
+        current.saturating_mul(2).min(max).max(base)
+    }
+}
+
+/// Randomly lengthen or shorten an interval by up to `jitter` of it
+fn jittered(interval: Duration, jitter: f64) -> Duration {
+    if jitter <= 0.0 {
+        return interval;
+    }
+    interval.mul_f64(1.0 + jitter * rand::rng().random_range(-1.0..=1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_interval() {
+        let secs = Duration::from_secs;
+        let (base, max) = (secs(15), secs(100));
+
+        assert_eq!(next_interval(secs(15), base, max, false), secs(30));
+        assert_eq!(next_interval(secs(30), base, max, false), secs(60));
+        assert_eq!(next_interval(secs(60), base, max, false), max);
+        assert_eq!(next_interval(max, base, max, false), max);
+        assert_eq!(next_interval(max, base, max, true), base);
+    }
+
+    #[test]
+    fn test_jittered() {
+        let interval = Duration::from_secs(10);
+        assert_eq!(jittered(interval, 0.0), interval);
+        for _ in 0..100 {
+            let wait = jittered(interval, 0.5);
+            assert!(wait >= Duration::from_secs(5) && wait <= Duration::from_secs(15));
+        }
+    }
+}
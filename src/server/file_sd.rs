@@ -0,0 +1,674 @@
+//! File-based target discovery
+//!
+//! Reads Prometheus `file_sd` target files (`*.json`, `*.yml`, `*.yaml`)
+//! from a directory. Each file holds a list of target groups:
+//!
+//! ```json
+//! [{"targets": ["kafka-1:8778", "kafka-2:8778"], "labels": {"cluster": "prod"}}]
+//! ```
+//!
+//! A target is `host:port` or a full Jolokia URL. For `host:port`, the
+//! `__scheme__` and `__metrics_path__` labels replace the default `http` and
+//! `/jolokia`, `__scrape_interval__` (`30`, `30s`, `2m`) sets the target's
+//! interval in background collection and `__scrape_schedule__` (`*/5 * * * *`)
+//! its cron schedule; other labels starting with `__` are dropped. The directory is
+//! polled and re-read when a file changes; a file that no longer parses keeps
+//! its previous targets.
+//!
+//! With `targets_api`, targets can also be added and removed at runtime
+//! through `/api/v1/targets`. They are scraped alongside the file targets
+//! and, with `targets_api.state_file`, saved in `file_sd` format so they
+//! survive restarts.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use super::handlers::sanitize_url_for_label;
+use super::{build_client, build_collector, build_engine};
+use crate::collector::{Collector, CronSchedule};
+use crate::config::Config;
+use crate::metrics::internal_metrics;
+use crate::transformer::TransformEngine;
+
+/// Group of targets sharing labels, as written in a target file
+#[derive(Debug, Serialize, Deserialize)]
+struct TargetGroup {
+    #[serde(default)]
+    targets: Vec<String>,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+}
+
+/// Jolokia endpoint listed in a target file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredTarget {
+    /// Jolokia URL
+    pub url: String,
+    /// Labels added to every metric of the target
+    pub labels: BTreeMap<String, String>,
+    /// Interval between background scrapes, from `__scrape_interval__`
+    pub interval: Option<Duration>,
+    /// Cron schedule of background scrapes, from `__scrape_schedule__`
+    pub schedule: Option<CronSchedule>,
+}
+
+impl DiscoveredTarget {
+    /// Target from a `host:port` or URL and the labels of its group
+    pub fn new(target: &str, group_labels: &BTreeMap<String, String>) -> Self {
+        let url = if target.contains("://") {
+            target.to_string()
+        } else {
+            let scheme = group_labels
+                .get("__scheme__")
+                .map_or("http", String::as_str);
+            let path = group_labels
+                .get("__metrics_path__")
+                .map_or("/jolokia", String::as_str);
+            format!("{}://{}{}", scheme, target, path)
+        };
+        let labels = group_labels
+            .iter()
+            .filter(|(name, _)| !name.starts_with("__"))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        let interval =
+            group_labels
+                .get("__scrape_interval__")
+                .and_then(|value| match parse_interval(value) {
+                    Some(interval) => Some(interval),
+                    None => {
+                        warn!(target, value = %value, "Ignoring invalid __scrape_interval__");
+                        None
+                    }
+                });
+        let schedule =
+            group_labels
+                .get("__scrape_schedule__")
+                .and_then(|value| match CronSchedule::parse(value) {
+                    Ok(schedule) => Some(schedule),
+                    Err(e) => {
+                        warn!(target, value = %value, error = %e, "Ignoring invalid __scrape_schedule__");
+                        None
+                    }
+                });
+        Self {
+            url,
+            labels,
+            interval,
+            schedule,
+        }
+    }
+}
+
+impl DiscoveredTarget {
+    /// Target group listing only this target, as saved in the state file
+    fn to_group(&self) -> TargetGroup {
+        let mut labels = self.labels.clone();
+        if let Some(interval) = self.interval {
+            labels.insert(
+                "__scrape_interval__".to_string(),
+                format!("{}s", interval.as_secs()),
+            );
+        }
+        if let Some(schedule) = &self.schedule {
+            labels.insert(
+                "__scrape_schedule__".to_string(),
+                String::from(schedule.clone()),
+            );
+        }
+        TargetGroup {
+            targets: vec![self.url.clone()],
+            labels,
+        }
+    }
+}
+
+/// Parse a positive interval in seconds, optionally suffixed with `s`, `m` or `h`
+fn parse_interval(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (number, unit) = match value.char_indices().find(|(_, c)| c.is_ascii_alphabetic()) {
+        Some((at, _)) => value.split_at(at),
+        None => (value, "s"),
+    };
+    let seconds = number.trim().parse::<u64>().ok()?
+        * match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 3600,
+            _ => return None,
+        };
+    (seconds > 0).then(|| Duration::from_secs(seconds))
+}
+
+/// Parse the target groups of one file
+pub fn parse_target_file(path: &Path) -> Result<Vec<DiscoveredTarget>, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let groups: Vec<TargetGroup> = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str(&contents).map_err(|e| e.to_string())?,
+        _ => serde_yaml::from_str(&contents).map_err(|e| e.to_string())?,
+    };
+    Ok(groups
+        .iter()
+        .flat_map(|group| {
+            group
+                .targets
+                .iter()
+                .map(|target| DiscoveredTarget::new(target, &group.labels))
+        })
+        .collect())
+}
+
+/// Target files in `directory` with their modification time and size
+fn target_files(directory: &Path) -> Vec<(PathBuf, Option<SystemTime>, u64)> {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return Vec::new();
+    };
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("json" | "yml" | "yaml")
+            )
+        })
+        .filter_map(|path| {
+            let metadata = std::fs::metadata(&path).ok()?;
+            metadata
+                .is_file()
+                .then(|| (path, metadata.modified().ok(), metadata.len()))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Discovered target with its own collector and transform engine
+///
+/// Each target gets its own engine, so cached values and counter history
+/// are never mixed between JVMs.
+pub struct TargetState {
+    /// Target as listed in its file
+    pub target: DiscoveredTarget,
+    /// Value of the `target` label (host and port of the URL)
+    pub name: String,
+    /// Collector reading the target
+    pub collector: Arc<Collector>,
+    /// Transform engine of the target
+    pub engine: Arc<TransformEngine>,
+}
+
+/// Rejected change of the API targets
+#[derive(Debug, Error)]
+pub enum TargetChangeError {
+    #[error("Invalid target: {0}")]
+    Invalid(String),
+
+    #[error("Target '{0}' already exists")]
+    Exists(String),
+
+    #[error("Target '{0}' is listed in a file_sd file and cannot be removed through the API")]
+    FromFile(String),
+
+    #[error("Unknown target '{0}'")]
+    NotFound(String),
+
+    #[error("Failed to save targets_api.state_file: {0}")]
+    Persist(String),
+}
+
+/// Targets discovered from a directory of `file_sd` files or added through the API
+pub struct FileSd {
+    config: Config,
+    /// Directory of target files, with `file_sd`
+    directory: Option<PathBuf>,
+    refresh_interval: Duration,
+    /// Files seen by the last refresh, with their modification time and size
+    seen: Mutex<Vec<(PathBuf, Option<SystemTime>, u64)>>,
+    /// Targets of every file that parsed, keyed by path
+    files: Mutex<BTreeMap<PathBuf, Vec<DiscoveredTarget>>>,
+    /// Targets added through `/api/v1/targets`, in the order they were added
+    api: Mutex<Vec<DiscoveredTarget>>,
+    /// File the API targets are saved to, with `targets_api.state_file`
+    state_file: Option<PathBuf>,
+    targets: RwLock<Arc<Vec<Arc<TargetState>>>>,
+}
+
+impl FileSd {
+    /// Discover targets from `file_sd.directory` and `targets_api.state_file`,
+    /// reading them once
+    pub fn new(config: &Config) -> Self {
+        let file_sd = config.file_sd.as_ref();
+        let state_file = config
+            .targets_api
+            .as_ref()
+            .and_then(|api| api.state_file.as_ref())
+            .map(PathBuf::from);
+        let api = match &state_file {
+            Some(path) if path.exists() => parse_target_file(path).unwrap_or_else(|e| {
+                warn!(file = %path.display(), error = %e, "Failed to load targets_api.state_file");
+                Vec::new()
+            }),
+            _ => Vec::new(),
+        };
+        let discovery = Self {
+            config: config.clone(),
+            directory: file_sd.map(|file_sd| PathBuf::from(&file_sd.directory)),
+            refresh_interval: Duration::from_secs(
+                file_sd.map_or(30, |file_sd| file_sd.refresh_interval_seconds),
+            ),
+            seen: Mutex::default(),
+            files: Mutex::default(),
+            api: Mutex::new(api),
+            state_file,
+            targets: RwLock::default(),
+        };
+        if let Some(directory) = &discovery.directory {
+            if !directory.is_dir() {
+                warn!(directory = %directory.display(), "file_sd directory does not exist yet");
+            }
+        }
+        if !discovery.refresh() {
+            discovery.rebuild(&BTreeMap::new(), &discovery.api_targets());
+        }
+        discovery
+    }
+
+    /// Current targets, ordered by file and position in the file
+    pub fn targets(&self) -> Arc<Vec<Arc<TargetState>>> {
+        self.targets
+            .read()
+            .map(|targets| Arc::clone(&targets))
+            .unwrap_or_default()
+    }
+
+    /// Re-read the directory if a target file was added, removed or changed
+    ///
+    /// Returns whether the files changed.
+    pub fn refresh(&self) -> bool {
+        let Some(directory) = &self.directory else {
+            return false;
+        };
+        let current = target_files(directory);
+        {
+            let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+            if *seen == current {
+                return false;
+            }
+            *seen = current.clone();
+        }
+
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files.retain(|path, _| current.iter().any(|(file, _, _)| file == path));
+        for (path, _, _) in &current {
+            match parse_target_file(path) {
+                Ok(targets) => {
+                    files.insert(path.clone(), targets);
+                }
+                Err(e) => warn!(
+                    file = %path.display(),
+                    error = %e,
+                    "Failed to parse file_sd target file, keeping its previous targets"
+                ),
+            }
+        }
+
+        let api = self.api.lock().unwrap_or_else(|e| e.into_inner());
+        self.rebuild(&files, &api);
+        info!(
+            directory = %directory.display(),
+            files = files.len(),
+            targets = self.targets().len(),
+            "Loaded file_sd targets"
+        );
+        true
+    }
+
+    /// Targets added through the API
+    pub fn api_targets(&self) -> Vec<DiscoveredTarget> {
+        self.api.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Add a target through the API, saving it to the state file
+    pub fn add(&self, target: DiscoveredTarget) -> Result<Arc<TargetState>, TargetChangeError> {
+        let name = sanitize_url_for_label(&target.url);
+        if !matches!(url::Url::parse(&target.url), Ok(url) if matches!(url.scheme(), "http" | "https"))
+        {
+            return Err(TargetChangeError::Invalid(format!(
+                "not an http(s) URL: {}",
+                target.url
+            )));
+        }
+        // Held while checking and adding, so concurrent changes are serialized
+        let _files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        let mut api = self.api.lock().unwrap_or_else(|e| e.into_inner());
+        if self.targets().iter().any(|state| state.name == name) {
+            return Err(TargetChangeError::Exists(name));
+        }
+        let state = self
+            .build_target(&target)
+            .map_err(|e| TargetChangeError::Invalid(e.to_string()))?;
+
+        let mut added = api.clone();
+        added.push(target);
+        self.save(&added)?;
+        *api = added;
+        // Appended rather than rebuilt, so the rules are compiled once
+        let state = Arc::new(state);
+        if let Ok(mut current) = self.targets.write() {
+            let mut targets = current.as_ref().clone();
+            targets.push(Arc::clone(&state));
+            *current = Arc::new(targets);
+        }
+        info!(target = %name, "Added target through the API");
+        Ok(state)
+    }
+
+    /// Remove a target added through the API, by name or URL
+    pub fn remove(&self, target: &str) -> Result<DiscoveredTarget, TargetChangeError> {
+        let files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        let mut api = self.api.lock().unwrap_or_else(|e| e.into_inner());
+        let matches = |url: &str| url == target || sanitize_url_for_label(url) == target;
+        let Some(index) = api.iter().position(|added| matches(&added.url)) else {
+            return Err(
+                if files.values().flatten().any(|listed| matches(&listed.url)) {
+                    TargetChangeError::FromFile(target.to_string())
+                } else {
+                    TargetChangeError::NotFound(target.to_string())
+                },
+            );
+        };
+
+        let mut remaining = api.clone();
+        let removed = remaining.remove(index);
+        self.save(&remaining)?;
+        *api = remaining;
+        self.rebuild(&files, &api);
+        info!(target = %sanitize_url_for_label(&removed.url), "Removed target through the API");
+        Ok(removed)
+    }
+
+    /// Write the API targets to the state file, if configured
+    fn save(&self, targets: &[DiscoveredTarget]) -> Result<(), TargetChangeError> {
+        let Some(path) = &self.state_file else {
+            return Ok(());
+        };
+        let groups: Vec<TargetGroup> = targets.iter().map(DiscoveredTarget::to_group).collect();
+        let data = serde_json::to_vec_pretty(&groups)
+            .map_err(|e| TargetChangeError::Persist(e.to_string()))?;
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        std::fs::write(&temp, data)
+            .and_then(|()| std::fs::rename(&temp, path))
+            .map_err(|e| TargetChangeError::Persist(e.to_string()))
+    }
+
+    /// Replace the target list with the file and API targets
+    ///
+    /// Unchanged targets keep their engine state; the first listing of a
+    /// URL wins, files before the API.
+    fn rebuild(&self, files: &BTreeMap<PathBuf, Vec<DiscoveredTarget>>, api: &[DiscoveredTarget]) {
+        let previous = self.targets();
+        let mut by_url: HashMap<&str, &Arc<TargetState>> = previous
+            .iter()
+            .map(|state| (state.target.url.as_str(), state))
+            .collect();
+        let mut targets: Vec<Arc<TargetState>> = Vec::new();
+        for target in files.values().flatten().chain(api) {
+            if targets.iter().any(|state| state.target.url == target.url) {
+                continue;
+            }
+            match by_url.remove(target.url.as_str()) {
+                Some(state) if state.target == *target => targets.push(Arc::clone(state)),
+                _ => match self.build_target(target) {
+                    Ok(state) => targets.push(Arc::new(state)),
+                    Err(e) => warn!(url = %target.url, error = %e, "Skipping file_sd target"),
+                },
+            }
+        }
+
+        // Removed targets must not hold back readiness or heartbeats
+        for state in previous.iter() {
+            if !targets.iter().any(|kept| kept.name == state.name) {
+                internal_metrics().remove_target(&state.name);
+            }
+        }
+
+        if let Ok(mut current) = self.targets.write() {
+            *current = Arc::new(targets);
+        }
+    }
+
+    fn build_target(&self, target: &DiscoveredTarget) -> anyhow::Result<TargetState> {
+        let engine = build_engine(&self.config)?;
+        let client = build_client(&self.config, &target.url)?
+            .with_attribute_filter(engine.attribute_filter());
+        Ok(TargetState {
+            target: target.clone(),
+            name: sanitize_url_for_label(&target.url),
+            collector: Arc::new(build_collector(&self.config, Arc::new(client))),
+            engine: Arc::new(engine),
+        })
+    }
+
+    /// Poll the directory for changes in the background
+    pub fn spawn(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            if self.directory.is_none() {
+                return;
+            }
+            let mut ticker = tokio::time::interval(self.refresh_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // The first tick completes immediately; targets were read on creation
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let discovery = Arc::clone(&self);
+                // Building engines compiles rules; keep it off the runtime threads
+                let _ = tokio::task::spawn_blocking(move || discovery.refresh()).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{FileSdConfig, TargetsApiConfig};
+
+    fn file_sd(directory: &Path) -> FileSd {
+        let config = Config {
+            file_sd: Some(FileSdConfig {
+                directory: directory.display().to_string(),
+                refresh_interval_seconds: 30,
+            }),
+            ..Config::default()
+        };
+        FileSd::new(&config)
+    }
+
+    #[test]
+    fn test_discovered_target() {
+        let labels: BTreeMap<String, String> = [
+            ("__scheme__", "https"),
+            ("__metrics_path__", "/api/jolokia"),
+            ("__meta_dc", "eu"),
+            ("cluster", "prod"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        let target = DiscoveredTarget::new("kafka-1:8778", &labels);
+        assert_eq!(target.url, "https://kafka-1:8778/api/jolokia");
+        assert_eq!(target.labels.len(), 1);
+        assert_eq!(target.labels["cluster"], "prod");
+
+        let target = DiscoveredTarget::new("http://kafka-2:8778/jolokia", &BTreeMap::new());
+        assert_eq!(target.url, "http://kafka-2:8778/jolokia");
+        assert_eq!(target.interval, None);
+
+        let labels = BTreeMap::from([("__scrape_interval__".to_string(), "2m".to_string())]);
+        let target = DiscoveredTarget::new("kafka-3:8778", &labels);
+        assert_eq!(target.interval, Some(Duration::from_secs(120)));
+        assert_eq!(target.schedule, None);
+        assert!(target.labels.is_empty());
+
+        let labels =
+            BTreeMap::from([("__scrape_schedule__".to_string(), "0 */6 * * *".to_string())]);
+        let target = DiscoveredTarget::new("kafka-4:8778", &labels);
+        assert_eq!(
+            target.schedule,
+            Some(CronSchedule::parse("0 */6 * * *").unwrap())
+        );
+        assert!(target.labels.is_empty());
+
+        let labels = BTreeMap::from([("__scrape_schedule__".to_string(), "hourly".to_string())]);
+        assert_eq!(
+            DiscoveredTarget::new("kafka-5:8778", &labels).schedule,
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("30"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_interval("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_interval("1h"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_interval("0s"), None);
+        assert_eq!(parse_interval("5d"), None);
+        assert_eq!(parse_interval("soon"), None);
+    }
+
+    #[test]
+    fn test_parse_target_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("kafka.json");
+        std::fs::write(
+            &json,
+            r#"[{"targets": ["kafka-1:8778", "kafka-2:8778"], "labels": {"cluster": "prod"}}]"#,
+        )
+        .unwrap();
+        let yaml = dir.path().join("tomcat.yml");
+        std::fs::write(&yaml, "- targets: [\"tomcat:8778\"]\n").unwrap();
+
+        let targets = parse_target_file(&json).unwrap();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[1].url, "http://kafka-2:8778/jolokia");
+        assert_eq!(targets[1].labels["cluster"], "prod");
+
+        let targets = parse_target_file(&yaml).unwrap();
+        assert_eq!(targets[0].url, "http://tomcat:8778/jolokia");
+        assert!(targets[0].labels.is_empty());
+    }
+
+    #[test]
+    fn test_refresh() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("targets.json");
+        std::fs::write(&file, r#"[{"targets": ["a:8778", "b:8778"]}]"#).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let discovery = file_sd(dir.path());
+        let names: Vec<String> = discovery
+            .targets()
+            .iter()
+            .map(|state| state.name.clone())
+            .collect();
+        assert_eq!(names, vec!["a:8778", "b:8778"]);
+        assert!(!discovery.refresh());
+
+        // Unchanged targets are kept, removed ones dropped
+        let kept = Arc::clone(&discovery.targets()[0]);
+        std::fs::write(&file, r#"[{"targets": ["a:8778", "c:8778"]}]"#).unwrap();
+        assert!(discovery.refresh());
+        let targets = discovery.targets();
+        assert!(Arc::ptr_eq(&targets[0], &kept));
+        assert_eq!(targets[1].name, "c:8778");
+
+        // A broken file keeps its previous targets
+        std::fs::write(&file, "[{").unwrap();
+        assert!(discovery.refresh());
+        assert_eq!(discovery.targets().len(), 2);
+
+        std::fs::remove_file(&file).unwrap();
+        assert!(discovery.refresh());
+        assert!(discovery.targets().is_empty());
+    }
+
+    #[test]
+    fn test_api_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("targets")).unwrap();
+        std::fs::write(
+            dir.path().join("targets/kafka.json"),
+            r#"[{"targets": ["a:8778"]}]"#,
+        )
+        .unwrap();
+        let state_file = dir.path().join("api-targets.json");
+        let config = Config {
+            file_sd: Some(FileSdConfig {
+                directory: dir.path().join("targets").display().to_string(),
+                refresh_interval_seconds: 30,
+            }),
+            targets_api: Some(TargetsApiConfig {
+                state_file: Some(state_file.display().to_string()),
+            }),
+            ..Config::default()
+        };
+
+        let discovery = FileSd::new(&config);
+        let labels = BTreeMap::from([
+            ("cluster".to_string(), "prod".to_string()),
+            ("__scrape_interval__".to_string(), "1m".to_string()),
+            ("__scrape_schedule__".to_string(), "30 2 * * *".to_string()),
+        ]);
+        let added = discovery
+            .add(DiscoveredTarget::new("b:8778", &labels))
+            .unwrap();
+        assert_eq!(added.name, "b:8778");
+        assert_eq!(discovery.targets().len(), 2);
+        assert!(matches!(
+            discovery.add(DiscoveredTarget::new("a:8778", &BTreeMap::new())),
+            Err(TargetChangeError::Exists(_))
+        ));
+        assert!(matches!(
+            discovery.add(DiscoveredTarget::new(
+                "ftp://c:21/jolokia",
+                &BTreeMap::new()
+            )),
+            Err(TargetChangeError::Invalid(_))
+        ));
+        assert!(matches!(
+            discovery.remove("a:8778"),
+            Err(TargetChangeError::FromFile(_))
+        ));
+        assert!(matches!(
+            discovery.remove("c:8778"),
+            Err(TargetChangeError::NotFound(_))
+        ));
+
+        // Added targets are loaded from the state file after a restart
+        let restarted = FileSd::new(&config);
+        let targets = restarted.targets();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[1].target.labels["cluster"], "prod");
+        assert_eq!(targets[1].target.interval, Some(Duration::from_secs(60)));
+        assert_eq!(
+            targets[1].target.schedule,
+            Some(CronSchedule::parse("30 2 * * *").unwrap())
+        );
+
+        let removed = restarted.remove("http://b:8778/jolokia").unwrap();
+        assert_eq!(removed.url, "http://b:8778/jolokia");
+        assert_eq!(restarted.targets().len(), 1);
+        assert!(FileSd::new(&config).api_targets().is_empty());
+    }
+}
@@ -0,0 +1,419 @@
+//! gRPC admin API
+//!
+//! Serves the `rjmx.admin.v1.Admin` service from `proto/admin.proto` on
+//! `grpc.listen_address`, so fleet-management tooling can inspect and drive
+//! an exporter without files or signals. Calls share the state of the HTTP
+//! handlers: a triggered scrape updates the same internal metrics and
+//! `/debug/mbeans`.
+//!
+//! `ReloadConfig` loads the configuration file again and swaps in the state
+//! built from it (see [`super::reload`]). A configuration that fails to load
+//! or validate is answered with `INVALID_ARGUMENT` and leaves the running
+//! one in place; one rolled back after its first scrape failed is answered
+//! with `ABORTED`.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use axum::http::HeaderMap;
+use subtle::ConstantTimeEq;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+use tracing::info;
+
+use super::handlers::{self, sanitize_url_for_label};
+use super::reload::{ReloadError, Reloader};
+use super::shutdown::Shutdown;
+use crate::collector::MBeanSelection;
+use crate::config::redact::{redact_url, redacted};
+use crate::config::GrpcConfig;
+use crate::metrics::internal_metrics;
+
+/// Code generated from `proto/admin.proto`
+pub mod proto {
+    tonic::include_proto!("rjmx.admin.v1");
+}
+
+use proto::admin_server::{Admin, AdminServer};
+use proto::{
+    GetConfigRequest, GetConfigResponse, GetRuleStatsRequest, GetRuleStatsResponse,
+    ListTargetsRequest, ListTargetsResponse, ReloadConfigRequest, ReloadConfigResponse, RuleStats,
+    Target, TriggerScrapeRequest, TriggerScrapeResponse,
+};
+
+/// Admin service backed by the HTTP server's state
+struct AdminService {
+    reloader: Arc<Reloader>,
+}
+
+/// Bind `grpc.listen_address`, so a taken port fails startup
+pub(super) fn bind(config: &GrpcConfig) -> Result<TcpIncoming> {
+    let addr: SocketAddr = config
+        .listen_address
+        .parse()
+        .with_context(|| format!("Invalid grpc.listen_address: {}", config.listen_address))?;
+    let incoming = TcpIncoming::bind(addr)
+        .with_context(|| format!("Failed to bind gRPC admin API to {}", addr))?;
+    info!(address = %addr, "Serving gRPC admin API");
+    Ok(incoming)
+}
+
+/// Serve the admin API until shutdown
+pub(super) async fn serve(
+    reloader: Arc<Reloader>,
+    incoming: TcpIncoming,
+    token: Option<String>,
+    shutdown: Shutdown,
+) -> Result<()> {
+    let service = AdminServer::with_interceptor(AdminService { reloader }, move |request| {
+        authorize(token.as_deref(), request)
+    });
+    Server::builder()
+        .add_service(service)
+        .serve_with_incoming_shutdown(incoming, async move { shutdown.triggered().await })
+        .await
+        .context("gRPC admin API failed")
+}
+
+/// Require `authorization: Bearer <token>` metadata when a token is configured
+fn authorize(token: Option<&str>, request: Request<()>) -> Result<Request<()>, Status> {
+    let Some(token) = token else {
+        return Ok(request);
+    };
+    let presented = request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if bool::from(presented.as_bytes().ct_eq(token.as_bytes())) {
+        Ok(request)
+    } else {
+        Err(Status::unauthenticated("invalid or missing bearer token"))
+    }
+}
+
+#[tonic::async_trait]
+impl Admin for AdminService {
+    async fn get_config(
+        &self,
+        _request: Request<GetConfigRequest>,
+    ) -> Result<Response<GetConfigResponse>, Status> {
+        let value =
+            redacted(&self.reloader.state().config).map_err(|e| Status::internal(e.to_string()))?;
+        let yaml = serde_yaml::to_string(&value).map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(GetConfigResponse { yaml }))
+    }
+
+    async fn reload_config(
+        &self,
+        _request: Request<ReloadConfigRequest>,
+    ) -> Result<Response<ReloadConfigResponse>, Status> {
+        match self.reloader.reload().await {
+            Ok(reloaded) => Ok(Response::new(ReloadConfigResponse {
+                rules: reloaded.rules as u32,
+                restart_required: reloaded
+                    .restart_required
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect(),
+                version: reloaded.version,
+            })),
+            Err(e @ ReloadError::NoSource) => Err(Status::failed_precondition(e.to_string())),
+            Err(e @ ReloadError::Config(_)) => Err(Status::invalid_argument(e.to_string())),
+            Err(e @ ReloadError::Build(_)) => Err(Status::internal(e.to_string())),
+            Err(e @ ReloadError::RolledBack { .. }) => Err(Status::aborted(e.to_string())),
+        }
+    }
+
+    async fn list_targets(
+        &self,
+        _request: Request<ListTargetsRequest>,
+    ) -> Result<Response<ListTargetsResponse>, Status> {
+        let state = &self.reloader.state();
+        let targets: Vec<(String, String, HashMap<String, String>)> = match &state.file_sd {
+            Some(file_sd) => file_sd
+                .targets()
+                .iter()
+                .map(|target| {
+                    let labels = target.target.labels.clone().into_iter().collect();
+                    (target.name.clone(), target.target.url.clone(), labels)
+                })
+                .collect(),
+            None => {
+                let url = &state.config.jolokia.url;
+                let labels = state.config.jolokia.labels.clone().into_iter().collect();
+                vec![(sanitize_url_for_label(url), url.clone(), labels)]
+            }
+        };
+
+        let registry: HashMap<_, _> = internal_metrics().targets().into_iter().collect();
+        let targets = targets
+            .into_iter()
+            .map(|(name, url, labels)| {
+                let metrics = registry.get(&name);
+                let last_scrape = metrics.and_then(|metrics| metrics.last_scrape());
+                let version = metrics.and_then(|metrics| metrics.agent_version());
+                Target {
+                    url: redact_url(&url),
+                    labels,
+                    last_scrape_success: last_scrape.map(|(_, success)| success),
+                    last_scrape_age_seconds: last_scrape.map(|(at, _)| at.elapsed().as_secs_f64()),
+                    agent_version: version.as_ref().map(|(agent, _)| agent.clone()),
+                    protocol_version: version.map(|(_, protocol)| protocol),
+                    name,
+                }
+            })
+            .collect();
+        Ok(Response::new(ListTargetsResponse { targets }))
+    }
+
+    async fn trigger_scrape(
+        &self,
+        request: Request<TriggerScrapeRequest>,
+    ) -> Result<Response<TriggerScrapeResponse>, Status> {
+        let state = &self.reloader.state();
+        if state.shutdown.is_draining() {
+            return Err(Status::unavailable("exporter is shutting down"));
+        }
+        let module = Some(request.into_inner().module).filter(|module| !module.is_empty());
+        let (collector, engine) = match &module {
+            None => (&state.collector, &state.engine),
+            Some(name) => match state.modules.get(name) {
+                Some(module) => (&module.collector, &module.engine),
+                None => {
+                    return Err(Status::invalid_argument(format!(
+                        "Unknown module '{}'",
+                        name
+                    )))
+                }
+            },
+        };
+
+        let discovered = state.file_sd.as_ref().map(|file_sd| file_sd.targets());
+        let targets = handlers::scrape_targets(
+            state,
+            discovered.as_deref().map(Vec::as_slice),
+            collector,
+            engine,
+        );
+        let start = Instant::now();
+        let output = handlers::run_scrape(
+            state,
+            &targets,
+            module,
+            &MBeanSelection::all(),
+            None,
+            None,
+            &HeaderMap::new(),
+        )
+        .await
+        .map_err(|deadline| {
+            Status::deadline_exceeded(format!(
+                "Scrape exceeded deadline of {}s",
+                deadline.as_secs_f64()
+            ))
+        })?;
+
+        Ok(Response::new(TriggerScrapeResponse {
+            series: output.metrics.len() as u64,
+            errors: output.errors,
+            duration_seconds: start.elapsed().as_secs_f64(),
+        }))
+    }
+
+    async fn get_rule_stats(
+        &self,
+        _request: Request<GetRuleStatsRequest>,
+    ) -> Result<Response<GetRuleStatsResponse>, Status> {
+        let state = &self.reloader.state();
+        let mut modules: Vec<(&str, _)> = vec![("", &state.engine)];
+        let mut names: Vec<&String> = state.modules.keys().collect();
+        names.sort_unstable();
+        modules.extend(
+            names
+                .into_iter()
+                .map(|name| (name.as_str(), &state.modules[name].engine)),
+        );
+
+        let rules = modules
+            .into_iter()
+            .flat_map(|(module, engine)| {
+                handlers::rules_debug(engine)
+                    .into_iter()
+                    .map(move |rule| RuleStats {
+                        module: module.to_string(),
+                        index: rule.index as u32,
+                        pattern: rule.pattern,
+                        name: rule.name,
+                        r#type: rule.r#type,
+                        matches: rule.matches,
+                        errors: rule.errors,
+                        recent_mbeans: rule.recent_mbeans,
+                    })
+            })
+            .collect();
+        Ok(Response::new(GetRuleStatsResponse { rules }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::proto::admin_client::AdminClient;
+    use super::*;
+    use crate::config::{Config, ConfigOverrides};
+    use crate::server::ConfigSource;
+    use tonic::transport::Channel;
+
+    /// Serve the admin API of a config on a free port
+    async fn client(config: Config, token: Option<&str>) -> AdminClient<Channel> {
+        client_with_source(config, token, None).await
+    }
+
+    /// Serve the admin API of a config loaded from `source`
+    async fn client_with_source(
+        config: Config,
+        token: Option<&str>,
+        source: Option<ConfigSource>,
+    ) -> AdminClient<Channel> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = super::super::tests::test_state(config);
+        tokio::spawn(serve(
+            Reloader::new(state.clone(), source),
+            TcpIncoming::from(listener),
+            token.map(str::to_string),
+            state.shutdown.clone(),
+        ));
+        AdminClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_admin_api() {
+        let mut config = Config::default();
+        config.jolokia.url = "http://127.0.0.1:1/jolokia".to_string();
+        config.jolokia.password = Some("hunter2".to_string());
+        let mut client = client(config, None).await;
+
+        let yaml = client
+            .get_config(GetConfigRequest {})
+            .await
+            .unwrap()
+            .into_inner()
+            .yaml;
+        assert!(yaml.contains("http://127.0.0.1:1/jolokia"));
+        assert!(!yaml.contains("hunter2"));
+
+        let status = client
+            .reload_config(ReloadConfigRequest {})
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+        let status = client
+            .trigger_scrape(TriggerScrapeRequest {
+                module: "missing".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let scrape = client
+            .trigger_scrape(TriggerScrapeRequest::default())
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!scrape.errors.is_empty());
+
+        let targets = client
+            .list_targets(ListTargetsRequest {})
+            .await
+            .unwrap()
+            .into_inner()
+            .targets;
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].name, "127.0.0.1:1");
+        assert_eq!(targets[0].last_scrape_success, Some(false));
+
+        let rules = client
+            .get_rule_stats(GetRuleStatsRequest {})
+            .await
+            .unwrap()
+            .into_inner()
+            .rules;
+        assert!(rules.iter().all(|rule| rule.module.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn test_reload_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(&path, "jolokia:\n  url: http://127.0.0.1:1/jolokia\n").unwrap();
+        let source = ConfigSource {
+            path: path.clone(),
+            overrides: ConfigOverrides::default(),
+        };
+        let mut client = client_with_source(Config::load(&path).unwrap(), None, Some(source)).await;
+
+        std::fs::write(
+            &path,
+            "jolokia:\n  url: http://127.0.0.1:2/jolokia\nrules:\n  - pattern: 'java.lang<type=Memory><HeapMemoryUsage>used'\n    name: jvm_heap_used_bytes\n",
+        )
+        .unwrap();
+        let reloaded = client
+            .reload_config(ReloadConfigRequest {})
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(reloaded.version, 2);
+        assert_eq!(reloaded.rules, 1);
+        assert!(reloaded.restart_required.is_empty());
+        let yaml = client
+            .get_config(GetConfigRequest {})
+            .await
+            .unwrap()
+            .into_inner()
+            .yaml;
+        assert!(yaml.contains("http://127.0.0.1:2/jolokia"), "{}", yaml);
+        let targets = client
+            .list_targets(ListTargetsRequest {})
+            .await
+            .unwrap()
+            .into_inner()
+            .targets;
+        assert_eq!(targets[0].name, "127.0.0.1:2");
+
+        std::fs::write(&path, "rules: [").unwrap();
+        let status = client
+            .reload_config(ReloadConfigRequest {})
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        let yaml = client
+            .get_config(GetConfigRequest {})
+            .await
+            .unwrap()
+            .into_inner()
+            .yaml;
+        assert!(yaml.contains("jvm_heap_used_bytes"), "{}", yaml);
+    }
+
+    #[tokio::test]
+    async fn test_bearer_token() {
+        let mut client = client(Config::default(), Some("secret")).await;
+        let status = client.get_config(GetConfigRequest {}).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let mut request = Request::new(GetConfigRequest {});
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer secret".parse().unwrap());
+        assert!(client.get_config(request).await.is_ok());
+    }
+}
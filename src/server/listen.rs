@@ -0,0 +1,69 @@
+//! Listening sockets
+//!
+//! The server binds one listener per configured address. IPv6 sockets are
+//! bound IPv6-only, so `0.0.0.0:9090` and `[::]:9090` can be listed together
+//! for dual-stack serving instead of the second bind failing with "address
+//! in use".
+
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+
+use anyhow::{Context, Result};
+use socket2::{Domain, Protocol, Socket, Type};
+
+/// Pending connection queue length, as used by tokio's `TcpListener::bind`
+const BACKLOG: i32 = 1024;
+
+/// Bind every address, failing on the first that cannot be bound
+pub fn bind_all(addresses: &[SocketAddr]) -> Result<Vec<TcpListener>> {
+    addresses
+        .iter()
+        .map(|address| bind(*address).with_context(|| format!("Failed to bind {}", address)))
+        .collect()
+}
+
+/// Bind a non-blocking listener
+pub fn bind(address: SocketAddr) -> io::Result<TcpListener> {
+    let socket = Socket::new(
+        Domain::for_address(address),
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )?;
+    if address.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    // Allow rebinding right after a restart, like tokio does
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    socket.bind(&address.into())?;
+    socket.listen(BACKLOG)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dual_stack_same_port() {
+        let v4 = bind("0.0.0.0:0".parse().unwrap()).unwrap();
+        let port = v4.local_addr().unwrap().port();
+
+        // Without IPv6-only, this bind would collide with the IPv4 wildcard.
+        // IPv6 may be unavailable in the test environment.
+        let Ok(v6) = bind(SocketAddr::new("::".parse().unwrap(), port)) else {
+            assert!(bind(SocketAddr::new("::1".parse().unwrap(), 0)).is_err());
+            return;
+        };
+        assert_eq!(v6.local_addr().unwrap().port(), port);
+    }
+
+    #[test]
+    fn test_bind_all_reports_address() {
+        let taken = bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let address = taken.local_addr().unwrap();
+        let err = bind_all(&[address]).unwrap_err();
+        assert!(err.to_string().contains(&address.to_string()));
+    }
+}
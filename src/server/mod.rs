@@ -3,22 +3,56 @@
 //! Provides the Axum-based HTTP server for serving metrics.
 //! Supports both HTTP and HTTPS (TLS) modes.
 
+mod access_log;
+mod auth;
+pub mod background;
+pub mod file_sd;
+#[cfg(feature = "grpc")]
+mod grpc;
 pub mod handlers;
+pub mod listen;
+#[cfg(feature = "pprof")]
+mod pprof;
+mod rate_limit;
+pub mod reload;
+pub mod shutdown;
+pub mod snapshot;
+pub mod statsd;
+mod status;
+pub mod systemd;
+mod targets_api;
+pub mod tls;
 
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use axum::{routing::get, Router};
+use axum::{http::HeaderMap, middleware, routing::get, Router};
 use axum_server::tls_rustls::RustlsConfig;
-use tokio::signal;
+use serde::Serialize;
+use tokio::task::JoinSet;
+use tower_http::compression::CompressionLayer;
 use tower_http::trace::TraceLayer;
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::collector::JolokiaClient;
-use crate::config::Config;
-use crate::transformer::{MetricType, Rule, RuleSet, TransformEngine};
+pub(crate) use access_log::utc_fields;
+pub use reload::ConfigSource;
+
+use crate::collector::{
+    is_pattern, search_pattern, AuthProvider, AwsSigV4Auth, BasicAuth, BearerAuth, CollectConfig,
+    Collector, HeaderAuth, JolokiaClient, JolokiaResponse, MBeanQuery, MBeanSelection,
+    ProxyOptions, RetryConfig, DEFAULT_MBEANS, SUPPORTED_PROTOCOLS,
+};
+use crate::config::{AccessLogFormat, Config, JolokiaAuthConfig, RuleAction};
+use crate::heartbeat::Heartbeat;
+use crate::server::shutdown::Shutdown;
+use crate::transformer::{
+    DerivedMetric, Expr, KeyOrder, MetricRenames, MetricType, RelabelRule, Rule, RuleResult,
+    RuleSet, SeriesLimits, TransformEngine,
+};
 
 /// Application state shared across handlers
 #[derive(Clone)]
@@ -27,20 +61,58 @@ pub struct AppState {
     pub config: Arc<Config>,
     /// Jolokia HTTP client
     pub client: Arc<JolokiaClient>,
+    /// Collector driven by the configured MBean queries
+    pub collector: Arc<Collector>,
     /// Metric transformation engine
     pub engine: Arc<TransformEngine>,
+    /// Named rule modules, selected per scrape with `?module=<name>`
+    pub modules: Arc<HashMap<String, ModuleState>>,
+    /// Targets discovered from `file_sd` files or added through `targets_api`,
+    /// scraped instead of `jolokia.url`
+    pub file_sd: Option<Arc<file_sd::FileSd>>,
+    /// Scheduled scrapes whose results `/metrics` serves, with `background`
+    pub background: Option<Arc<background::Background>>,
+    /// Snapshot of the latest scrape, served after a restart, with `snapshot`
+    pub snapshot: Option<Arc<snapshot::Snapshot>>,
+    /// Server start, from which `start_delay_seconds` is measured
+    pub started_at: Instant,
+    /// Responses of the last scrape, for `/debug/mbeans`
+    pub last_scrape: Arc<RwLock<Option<LastScrape>>>,
+    /// Set once shutdown starts, so scrapes and readiness get 503
+    pub shutdown: Shutdown,
+}
+
+/// Jolokia responses of a scrape and the module that scraped them
+#[derive(Debug)]
+pub struct LastScrape {
+    /// Module selected with `?module=<name>`, if any
+    pub module: Option<String>,
+    /// Successful Jolokia responses
+    pub responses: Vec<JolokiaResponse>,
+    /// Flattened names of numeric values left out of `responses` because
+    /// no rule matches them
+    pub skipped: Vec<String>,
+}
+
+/// Collector and transform engine serving one named module
+#[derive(Clone)]
+pub struct ModuleState {
+    /// Collector driven by the module's MBean queries
+    pub collector: Arc<Collector>,
+    /// Transformation engine with the module's rules
+    pub engine: Arc<TransformEngine>,
 }
 
 /// Convert config rules to transformer RuleSet
 fn config_to_ruleset(config: &Config) -> RuleSet {
     let rules: Vec<Rule> = config
-        .rules
-        .iter()
+        .effective_rules()
         .map(|r| {
             let rule_type = r.r#type.to_lowercase();
             let metric_type = match rule_type.as_str() {
                 "gauge" => MetricType::Gauge,
                 "counter" => MetricType::Counter,
+                "histogram" => MetricType::Histogram,
                 _ => {
                     tracing::warn!(
                         rule_type = %r.r#type,
@@ -69,6 +141,35 @@ fn config_to_ruleset(config: &Config) -> RuleSet {
                 rule = rule.with_value_factor(factor);
             }
 
+            if let Some(ref transform) = r.value_transform {
+                match Expr::parse(transform) {
+                    Ok(expr) => rule = rule.with_value_transform(expr),
+                    Err(e) => tracing::warn!(
+                        rule_name = %r.name,
+                        error = %e,
+                        "Invalid valueTransform; ignoring"
+                    ),
+                }
+            }
+
+            for label in &r.label_drops {
+                rule = rule.with_label_drop(label);
+            }
+
+            for (from, to) in &r.label_renames {
+                rule = rule.with_label_rename(from, to);
+            }
+
+            rule = rule
+                .with_cache(r.cache)
+                .with_attr_name_snake_case(r.attr_name_snake_case)
+                .with_normalize_label_values(r.normalize_label_values)
+                .with_drop(r.action == RuleAction::Drop);
+
+            if let Some(ref native) = r.native_histogram {
+                rule = rule.with_native_histogram_schema(native.resolved_schema());
+            }
+
             rule
         })
         .collect();
@@ -76,6 +177,288 @@ fn config_to_ruleset(config: &Config) -> RuleSet {
     RuleSet::from_rules(rules)
 }
 
+/// Compile config relabel configs into transformer relabel rules
+fn config_to_relabel_rules(config: &Config) -> RuleResult<Vec<RelabelRule>> {
+    config
+        .relabel_configs
+        .iter()
+        .map(|rc| {
+            let mut rule = RelabelRule::new(rc.action, &rc.regex)?
+                .with_source_labels(rc.source_labels.clone())
+                .with_separator(&rc.separator)
+                .with_replacement(&rc.replacement);
+            if let Some(ref target) = rc.target_label {
+                rule = rule.with_target_label(target);
+            }
+            Ok(rule)
+        })
+        .collect()
+}
+
+/// Build the collection configuration from config
+///
+/// Explicit `collect` entries take precedence over `whitelistObjectNames`,
+/// which in turn take precedence over the built-in default MBeans.
+/// Blacklisted MBeans are removed from the resulting query list.
+fn config_to_collect_config(config: &Config) -> CollectConfig {
+    let queries: Vec<MBeanQuery> = if !config.collect.is_empty() {
+        config
+            .collect
+            .iter()
+            .map(|entry| MBeanQuery {
+                object_name: entry.object_name.clone(),
+                attributes: entry.attributes.clone(),
+                quiet_hours: entry.quiet_hours.clone(),
+                search: false,
+                exclude: Vec::new(),
+                // Checked when the config was validated
+                attribute_patterns: entry.attribute_patterns().unwrap_or_default(),
+                poll_interval: entry.poll_interval_seconds.map(Duration::from_secs),
+                history: entry.history,
+            })
+            .collect()
+    } else if !config.whitelist_object_names.is_empty() {
+        // Patterns are resolved at query time, skipping blacklisted MBeans
+        config
+            .whitelist_object_names
+            .iter()
+            .map(|glob| {
+                let pattern = search_pattern(glob);
+                if is_pattern(&pattern) {
+                    MBeanQuery::new(pattern).with_search(config.blacklist_object_names.clone())
+                } else {
+                    MBeanQuery::new(pattern)
+                }
+            })
+            .collect()
+    } else {
+        DEFAULT_MBEANS.iter().map(|m| MBeanQuery::new(*m)).collect()
+    };
+
+    let mbeans = queries
+        .into_iter()
+        .filter(|query| {
+            let blacklisted = config
+                .blacklist_object_names
+                .iter()
+                .any(|b| query.object_name.contains(b));
+            if blacklisted {
+                tracing::debug!(mbean = %query.object_name, "Skipping blacklisted MBean");
+            }
+            !blacklisted
+        })
+        .collect();
+
+    CollectConfig {
+        mbeans,
+        timeout_ms: config.jolokia.timeout_ms,
+    }
+}
+
+/// Convert the config retry policy to the client's
+fn config_to_retry_config(config: &Config) -> RetryConfig {
+    let retry = &config.jolokia.retry;
+    RetryConfig {
+        max_retries: retry.max_retries,
+        initial_delay: std::time::Duration::from_millis(retry.initial_delay_ms),
+        max_delay: std::time::Duration::from_millis(retry.max_delay_ms),
+        multiplier: retry.multiplier,
+        jitter: retry.jitter,
+        max_backoff: std::time::Duration::from_millis(retry.max_backoff_ms),
+    }
+}
+
+/// Convert config renames to transformer renames
+fn config_to_renames(config: &Config) -> MetricRenames {
+    config
+        .renames
+        .iter()
+        .fold(MetricRenames::new(), |renames, (from, rename)| {
+            renames.with_rename(from, rename.to(), rename.keep_old())
+        })
+}
+
+/// Convert config fault injection settings to a collector fault injector
+#[cfg(feature = "fault-injection")]
+fn config_to_fault_injector(config: &Config) -> Option<crate::collector::FaultInjector> {
+    let faults = &config.fault_injection;
+    faults.enabled.then(|| {
+        crate::collector::FaultInjector::new()
+            .with_latency(std::time::Duration::from_millis(faults.latency_ms))
+            .with_error_rate(faults.error_rate)
+            .with_malformed_rate(faults.malformed_rate)
+            .with_mbeans(faults.mbeans.clone())
+    })
+}
+
+/// Convert config derived metrics to transformer derived metrics
+fn config_to_derived_metrics(config: &Config) -> Result<Vec<DerivedMetric>> {
+    config
+        .derived_metrics
+        .iter()
+        .map(|d| {
+            d.to_derived()
+                .map_err(|e| anyhow::anyhow!("derived metric '{}': {}", d.name, e))
+        })
+        .collect()
+}
+
+/// Build the transform engine (rules, relabeling, renames, limits) from config
+pub(crate) fn build_engine(config: &Config) -> Result<TransformEngine> {
+    let ruleset = config_to_ruleset(config);
+    ruleset.compile_all()?;
+    let relabel_rules = config_to_relabel_rules(config)?;
+
+    Ok(TransformEngine::new(ruleset)
+        .with_lowercase_names(config.lowercase_output_name)
+        .with_lowercase_labels(config.lowercase_output_label_names)
+        .with_key_order(config.key_order)
+        .with_counter_total_suffix(config.counter_total_suffix)
+        .with_counter_resets(config.counter_resets)
+        .with_export_unmatched(config.export_unmatched)
+        .with_conflict_policy(config.metric_conflict_policy)
+        .with_parallel(config.parallel_rule_matching)
+        .with_series_ttl(
+            config
+                .series_ttl_seconds
+                .map(std::time::Duration::from_secs),
+        )
+        .with_relabel_rules(relabel_rules)
+        .with_renames(config_to_renames(config))
+        .with_derived_metrics(config_to_derived_metrics(config)?)
+        .with_series_limits(
+            SeriesLimits::new()
+                .with_max_series_per_scrape(config.limits.max_series_per_scrape)
+                .with_max_label_value_length(config.limits.max_label_value_length),
+        ))
+}
+
+/// Build a Jolokia client for one endpoint URL with the configured settings
+pub(crate) fn build_client(config: &Config, url: &str) -> Result<JolokiaClient> {
+    let mut options = config.jolokia.http.clone();
+    options.proxy = config
+        .jolokia
+        .proxy_url
+        .as_ref()
+        .map(|proxy_url| ProxyOptions {
+            url: proxy_url.clone(),
+            credentials: config
+                .jolokia
+                .proxy_username
+                .clone()
+                .zip(config.jolokia.proxy_password.clone()),
+        });
+    let mut client = JolokiaClient::with_options(url, config.jolokia.timeout_ms, &options)?
+        .with_retry(config_to_retry_config(config))
+        .with_http_method(config.jolokia.http_method)
+        .with_canonical_naming(config.key_order == KeyOrder::Canonical)
+        .with_headers(
+            config
+                .jolokia
+                .request_headers()
+                .map_err(anyhow::Error::msg)?,
+        );
+    if let Some(size) = config.jolokia.bulk_batch_size {
+        client = client.with_bulk_batch_size(size);
+    }
+    if let Some(bytes) = config.jolokia.max_response_bytes {
+        client = client.with_max_response_bytes(bytes);
+    }
+    if let Some(auth) = &config.jolokia.auth {
+        client = client.with_auth_provider(build_auth_provider(auth)?);
+    } else if let (Some(username), Some(password)) =
+        (&config.jolokia.username, &config.jolokia.password)
+    {
+        client = client.with_auth(username, password);
+    }
+    Ok(client)
+}
+
+/// Build the provider authenticating Jolokia requests
+///
+/// Secrets from `*_file` options have been read when the config was loaded;
+/// missing AWS credentials are taken from the standard environment variables.
+fn build_auth_provider(auth: &JolokiaAuthConfig) -> Result<Arc<dyn AuthProvider>> {
+    let secret = |value: &Option<String>, key: &str| {
+        value
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("jolokia.auth.{} is not set", key))
+    };
+    let provider: Arc<dyn AuthProvider> = match auth {
+        JolokiaAuthConfig::Basic {
+            username, password, ..
+        } => Arc::new(BasicAuth::new(username, &secret(password, "password")?)),
+        JolokiaAuthConfig::Bearer { token, .. } => {
+            Arc::new(BearerAuth::new(&secret(token, "token")?)?)
+        }
+        JolokiaAuthConfig::Header { name, value, .. } => {
+            Arc::new(HeaderAuth::new(name, &secret(value, "value")?)?)
+        }
+        JolokiaAuthConfig::AwsSigv4 {
+            region,
+            service,
+            access_key_id,
+            secret_access_key,
+            session_token,
+        } => {
+            let from_env = |value: &Option<String>, var: &str| {
+                value.clone().or_else(|| std::env::var(var).ok())
+            };
+            let (Some(access_key_id), Some(secret_access_key)) = (
+                from_env(access_key_id, "AWS_ACCESS_KEY_ID"),
+                from_env(secret_access_key, "AWS_SECRET_ACCESS_KEY"),
+            ) else {
+                anyhow::bail!(
+                    "jolokia.auth: AWS credentials are not configured and \
+                     AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY are not set"
+                );
+            };
+            let mut signer = AwsSigV4Auth::new(&access_key_id, &secret_access_key, region, service);
+            if let Some(token) = from_env(session_token, "AWS_SESSION_TOKEN") {
+                signer = signer.with_session_token(&token);
+            }
+            Arc::new(signer)
+        }
+    };
+    Ok(provider)
+}
+
+/// Build a collector for the configured MBean queries
+pub(crate) fn build_collector(config: &Config, client: Arc<JolokiaClient>) -> Collector {
+    let collector = Collector::with_client(client, config_to_collect_config(config));
+    #[cfg(feature = "fault-injection")]
+    if let Some(faults) = config_to_fault_injector(config) {
+        return collector.with_fault_injector(faults);
+    }
+    collector
+}
+
+/// Build the collector and engine for every named module
+///
+/// Module clients share the connection pool of `client`, but each skips the
+/// wildcard attributes its own rules cannot match.
+fn build_modules(config: &Config, client: &JolokiaClient) -> Result<HashMap<String, ModuleState>> {
+    config
+        .modules
+        .keys()
+        .map(|name| {
+            let module_config = config.module(name).expect("module is configured");
+            let engine = build_engine(&module_config)
+                .map_err(|e| anyhow::anyhow!("Module '{}': {}", name, e))?;
+            let client = Arc::new(
+                client
+                    .clone()
+                    .with_attribute_filter(engine.attribute_filter()),
+            );
+            let state = ModuleState {
+                collector: Arc::new(build_collector(&module_config, client)),
+                engine: Arc::new(engine),
+            };
+            Ok((name.clone(), state))
+        })
+        .collect()
+}
+
 /// Run the HTTP server
 ///
 /// Starts either an HTTP or HTTPS server based on TLS configuration.
@@ -89,86 +472,357 @@ fn config_to_ruleset(config: &Config) -> RuleSet {
 /// Returns an error if:
 /// - The server fails to start
 /// - TLS is enabled but certificate files cannot be loaded
-pub async fn run(config: Config) -> Result<()> {
-    let port = config.server.port;
-    let bind_address = config.server.bind_address.clone();
+pub async fn run(config: Config, source: Option<ConfigSource>) -> Result<()> {
+    let addresses = config.server.listen_addresses()?;
     let metrics_path = config.server.path.clone();
     let tls_config = config.server.tls.clone();
 
-    // Create Jolokia client
-    let mut client = JolokiaClient::new(&config.jolokia.url, config.jolokia.timeout_ms)?;
-    if let (Some(ref username), Some(ref password)) =
-        (&config.jolokia.username, &config.jolokia.password)
-    {
-        client = client.with_auth(username, password);
+    // Prefer a socket passed by systemd socket activation over binding
+    let inherited = systemd::take_listener()?;
+    if config.server.systemd_socket && inherited.is_none() {
+        anyhow::bail!(
+            "systemd_socket is enabled but no socket was passed by systemd (LISTEN_FDS is not set for this process)"
+        );
+    }
+
+    let heartbeat = Heartbeat::from_config(&config.heartbeat)?;
+    let shutdown = Shutdown::new(Duration::from_secs(config.shutdown.grace_period_secs));
+    let state = build_state(config, shutdown.clone())?;
+
+    // Start the dead-man's-switch heartbeat, if configured
+    if let Some(heartbeat) = heartbeat {
+        heartbeat.spawn();
+    }
+    let reloader = reload::Reloader::new(state.clone(), source);
+    reloader.start();
+    if state.file_sd.is_none() {
+        tokio::spawn(log_agent_version(state.clone()));
+    }
+    #[cfg(feature = "grpc")]
+    if let Some(config) = &state.config.grpc {
+        let incoming = grpc::bind(config)?;
+        let server = grpc::serve(
+            Arc::clone(&reloader),
+            incoming,
+            config.bearer_token.clone(),
+            shutdown.clone(),
+        );
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                tracing::error!(error = %e, "gRPC admin API stopped");
+            }
+        });
+    }
+    shutdown.listen_for_signals();
+
+    let app = reloader.app();
+
+    // One listener per address, unless systemd passed the socket
+    let systemd = inherited.is_some();
+    let listeners = match inherited {
+        Some(listener) => vec![listener],
+        None => listen::bind_all(&addresses)?,
+    };
+
+    // Start server with or without TLS
+    if tls_config.enabled {
+        run_https_server(
+            app,
+            listeners,
+            systemd,
+            &metrics_path,
+            &tls_config,
+            shutdown,
+        )
+        .await
+    } else {
+        run_http_server(app, listeners, systemd, &metrics_path, shutdown).await
+    }
+}
+
+/// Summary of a [`scrape_once`] run
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ScrapeSummary {
+    /// Series produced by the rules, without the exporter's own metrics
+    pub series: usize,
+    /// Concrete MBeans returned by Jolokia
+    pub mbeans_discovered: usize,
+    /// MBeans that produced at least one series
+    pub mbeans_scraped: usize,
+    /// MBeans read but matched by no rule
+    pub unmatched_mbeans: Vec<String>,
+    /// MBean reads and transforms that failed
+    pub errors: Vec<String>,
+    /// Time taken by the scrape
+    pub duration_seconds: f64,
+}
+
+impl ScrapeSummary {
+    /// Write the summary as pretty-printed JSON
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
     }
+}
+
+/// Log the Jolokia agent version of `jolokia.url`, warning about unsupported protocols
+async fn log_agent_version(state: AppState) {
+    let target = handlers::sanitize_url_for_label(&state.config.jolokia.url);
+    match handlers::check_agent_version(&state.client, &target).await {
+        Ok(version) if version.is_supported() => info!(
+            target = %target,
+            agent = %version.agent,
+            protocol = %version.protocol,
+            "Connected to Jolokia agent"
+        ),
+        Ok(version) => warn!(
+            target = %target,
+            agent = %version.agent,
+            protocol = %version.protocol,
+            supported = ?SUPPORTED_PROTOCOLS,
+            "Unsupported Jolokia protocol version; metrics may be missing or wrong"
+        ),
+        Err(e) => warn!(target = %target, error = %e, "Jolokia version check failed"),
+    }
+}
+
+/// Run a single scrape of all configured MBeans without serving HTTP
+///
+/// Goes through the same collect, transform and format steps as the metrics
+/// endpoint. Returns the Prometheus text exposition and a summary of the
+/// scrape; failed MBean reads are listed in the summary, not in the text.
+pub async fn scrape_once(config: Config) -> Result<(String, ScrapeSummary)> {
+    let shutdown = Shutdown::new(Duration::from_secs(config.shutdown.grace_period_secs));
+    let state = build_state(config, shutdown)?;
+
+    let discovered = state.file_sd.as_ref().map(|file_sd| file_sd.targets());
+    let targets = handlers::scrape_targets(
+        &state,
+        discovered.as_deref().map(Vec::as_slice),
+        &state.collector,
+        &state.engine,
+    );
+
+    let start = Instant::now();
+    let output = handlers::run_scrape(
+        &state,
+        &targets,
+        None,
+        &MBeanSelection::all(),
+        None,
+        None,
+        &HeaderMap::new(),
+    )
+    .await
+    .map_err(|deadline| anyhow::anyhow!("Scrape exceeded deadline of {:?}", deadline))?;
+    let duration = start.elapsed();
+
+    let unmatched_mbeans = state
+        .last_scrape
+        .read()
+        .ok()
+        .and_then(|last| {
+            last.as_ref()
+                .map(|last| state.engine.unmatched_mbeans(&last.responses))
+        })
+        .unwrap_or_default();
+    let summary = ScrapeSummary {
+        series: output.metrics.len(),
+        mbeans_discovered: output.stats.mbeans_discovered,
+        mbeans_scraped: output.stats.mbeans_scraped,
+        unmatched_mbeans,
+        errors: output.errors.clone(),
+        duration_seconds: duration.as_secs_f64(),
+    };
+
+    Ok((output.render(), summary))
+}
+
+/// Create the Jolokia client, transform engine and modules shared by handlers
+fn build_state(config: Config, shutdown: Shutdown) -> Result<AppState> {
+    // Create Jolokia client
+    let client = build_client(&config, &config.jolokia.url)?
+        .with_fallback_urls(config.jolokia.fallback_urls());
 
     // Create transform engine with rules from config
-    let ruleset = config_to_ruleset(&config);
-    ruleset.compile_all()?;
+    let engine = build_engine(&config)?;
 
-    let engine = TransformEngine::new(ruleset)
-        .with_lowercase_names(config.lowercase_output_name)
-        .with_lowercase_labels(config.lowercase_output_label_names);
+    let modules = build_modules(&config, &client)?;
+    if !modules.is_empty() {
+        let mut names: Vec<&str> = modules.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        info!(modules = ?names, "Loaded rule modules");
+    }
 
-    let state = AppState {
+    // Skip converting wildcard attributes that no rule can match
+    let client = Arc::new(client.with_attribute_filter(engine.attribute_filter()));
+    let collector = build_collector(&config, Arc::clone(&client));
+    #[cfg(feature = "fault-injection")]
+    if config.fault_injection.enabled {
+        tracing::warn!("Fault injection is enabled; collected metrics are intentionally degraded");
+    }
+
+    // API targets are kept with the file_sd ones, replacing jolokia.url too
+    let file_sd = (config.file_sd.is_some() || config.targets_api.is_some())
+        .then(|| Arc::new(file_sd::FileSd::new(&config)));
+    let background = config
+        .background
+        .as_ref()
+        .map(|background| Arc::new(background::Background::new(background)));
+    let snapshot = config
+        .snapshot
+        .as_ref()
+        .map(|snapshot| Arc::new(snapshot::Snapshot::load(snapshot)));
+
+    Ok(AppState {
         config: Arc::new(config),
-        client: Arc::new(client),
+        client,
+        collector: Arc::new(collector),
         engine: Arc::new(engine),
-    };
+        modules: Arc::new(modules),
+        file_sd,
+        background,
+        snapshot,
+        started_at: Instant::now(),
+        last_scrape: Arc::default(),
+        shutdown,
+    })
+}
+
+/// Build the router with the configured metrics path, auth and compression
+fn build_router(state: AppState) -> Router {
+    let server = &state.config.server;
+
+    let mut metrics = Router::new()
+        .route(&server.path, get(handlers::metrics))
+        .route("/metrics.json", get(handlers::metrics_json));
+    if let Some(rate_limit) = &server.rate_limit {
+        metrics = metrics.route_layer(middleware::from_fn_with_state(
+            Arc::new(rate_limit::RateLimiter::new(rate_limit)),
+            rate_limit::limit_rate,
+        ));
+    }
 
-    // Build router with configurable metrics path
+    // The metrics, debug and API endpoints share the optional auth layer
+    metrics = metrics
+        .route("/debug/rules", get(handlers::debug_rules))
+        .route("/debug/mbeans", get(handlers::debug_mbeans));
+    #[cfg(feature = "pprof")]
+    {
+        metrics = metrics.route("/debug/pprof/profile", get(pprof::profile));
+    }
+    if state.config.targets_api.is_some() {
+        metrics = metrics.route(
+            "/api/v1/targets",
+            get(targets_api::list)
+                .post(targets_api::add)
+                .delete(targets_api::remove),
+        );
+    }
+    if let Some(auth) = &server.auth {
+        metrics = metrics.route_layer(middleware::from_fn_with_state(
+            Arc::new(auth::Auth::new(auth, &[&server.path, "/metrics.json"])),
+            auth::require_auth,
+        ));
+    }
+
+    let compression = server.compression;
+    let access_log = server.access_log;
     let app = Router::new()
         .route("/", get(handlers::root))
         .route("/health", get(handlers::health))
-        .route(&metrics_path, get(handlers::metrics))
+        .route("/livez", get(handlers::livez))
+        .route("/readyz", get(handlers::readyz))
+        .merge(metrics)
         .layer(TraceLayer::new_for_http())
         .with_state(state);
 
-    // Parse bind address from config
-    // Handle "localhost" specially, otherwise parse as IP address
-    let bind_addr: std::net::IpAddr = if bind_address == "localhost" {
-        std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST)
+    // Compress large metric payloads for clients that accept gzip/deflate
+    let app = if compression {
+        app.layer(CompressionLayer::new())
     } else {
-        bind_address
-            .parse()
-            .map_err(|e| anyhow::anyhow!("Invalid bind_address '{}': {}. Use an IP address (e.g., '0.0.0.0', '127.0.0.1') or 'localhost'.", bind_address, e))?
+        app
     };
-    let addr = SocketAddr::from((bind_addr, port));
 
-    // Start server with or without TLS
-    if tls_config.enabled {
-        run_https_server(app, addr, &metrics_path, &tls_config).await
+    // Outermost, so rejected requests are logged and bytes are counted as sent
+    if access_log == AccessLogFormat::Off {
+        app
     } else {
-        run_http_server(app, addr, &metrics_path).await
+        app.layer(middleware::from_fn_with_state(
+            Arc::new(access_log::AccessLog::new(access_log)),
+            access_log::log_requests,
+        ))
     }
 }
 
 /// Run a plain HTTP server
-async fn run_http_server(app: Router, addr: SocketAddr, metrics_path: &str) -> Result<()> {
-    info!(
-        address = %addr,
-        metrics_path = %metrics_path,
-        tls = false,
-        "Server listening (HTTP)"
-    );
+///
+/// Serves `app` on every listener. On shutdown, connections still open
+/// after the grace period are dropped.
+async fn run_http_server(
+    app: Router,
+    listeners: Vec<std::net::TcpListener>,
+    systemd: bool,
+    metrics_path: &str,
+    shutdown: Shutdown,
+) -> Result<()> {
+    let mut servers = JoinSet::new();
+    for listener in listeners {
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        info!(
+            address = %listener.local_addr()?,
+            metrics_path = %metrics_path,
+            tls = false,
+            systemd,
+            "Server listening (HTTP)"
+        );
 
-    let listener = tokio::net::TcpListener::bind(addr).await?;
+        let server = axum::serve(
+            listener,
+            app.clone()
+                .into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown({
+            let shutdown = shutdown.clone();
+            async move { shutdown.triggered().await }
+        });
+        servers.spawn(async move { server.await });
+    }
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    tokio::select! {
+        result = join_servers(&mut servers) => result?,
+        _ = shutdown.deadline() => {
+            warn!(
+                grace_period_secs = shutdown.grace_period().as_secs(),
+                "Grace period elapsed, dropping in-flight scrapes"
+            );
+        }
+    }
 
     info!("Server shutdown complete");
     Ok(())
 }
 
+/// Wait for every server, returning the first error
+///
+/// The remaining servers are aborted when the set is dropped.
+async fn join_servers(servers: &mut JoinSet<std::io::Result<()>>) -> Result<()> {
+    while let Some(result) = servers.join_next().await {
+        result??;
+    }
+    Ok(())
+}
+
 /// Run an HTTPS server with TLS
+///
+/// Serves `app` on every listener with the same certificate.
 async fn run_https_server(
     app: Router,
-    addr: SocketAddr,
+    listeners: Vec<std::net::TcpListener>,
+    systemd: bool,
     metrics_path: &str,
     tls_config: &crate::config::TlsConfig,
+    shutdown: Shutdown,
 ) -> Result<()> {
     // Get certificate and key file paths (already validated in config)
     let cert_file = tls_config
@@ -192,62 +846,1898 @@ async fn run_https_server(
         .map_err(|_| anyhow::anyhow!("TLS private key file not found: {}", key_file))?;
 
     // Load TLS configuration
+    tls::install_crypto_provider();
     let rustls_config = RustlsConfig::from_pem_file(cert_path, key_path)
         .await
         .map_err(|e| anyhow::anyhow!("Failed to load TLS certificates: {}", e))?;
+    tls::record_cert_expiry(cert_path).await;
 
-    info!(
-        address = %addr,
-        metrics_path = %metrics_path,
-        tls = true,
-        cert_file = %cert_file,
-        "Server listening (HTTPS)"
-    );
+    // Pick up rotated certificates without restarting
+    if tls_config.reload_interval_secs > 0 {
+        tls::CertReloader::new(rustls_config.clone(), cert_path, key_path)
+            .await
+            .spawn(std::time::Duration::from_secs(
+                tls_config.reload_interval_secs,
+            ));
+    }
+
+    let mut servers = JoinSet::new();
+    let mut handles = Vec::with_capacity(listeners.len());
+    for listener in listeners {
+        info!(
+            address = %listener.local_addr()?,
+            metrics_path = %metrics_path,
+            tls = true,
+            systemd,
+            cert_file = %cert_file,
+            "Server listening (HTTPS)"
+        );
 
-    // Create and run the HTTPS server with graceful shutdown
-    let handle = axum_server::Handle::new();
-    let shutdown_handle = handle.clone();
+        let handle = axum_server::Handle::new();
+        let server = axum_server::from_tcp_rustls(listener, rustls_config.clone())
+            .handle(handle.clone())
+            .serve(
+                app.clone()
+                    .into_make_service_with_connect_info::<SocketAddr>(),
+            );
+        servers.spawn(server);
+        handles.push(handle);
+    }
 
-    // Spawn shutdown signal handler
+    // Drain every listener once shutdown starts
     tokio::spawn(async move {
-        shutdown_signal().await;
-        shutdown_handle.graceful_shutdown(Some(std::time::Duration::from_secs(10)));
+        shutdown.triggered().await;
+        for handle in handles {
+            handle.graceful_shutdown(Some(shutdown.grace_period()));
+        }
     });
 
-    axum_server::bind_rustls(addr, rustls_config)
-        .handle(handle)
-        .serve(app.into_make_service())
-        .await?;
+    join_servers(&mut servers).await?;
 
     info!("Server shutdown complete");
     Ok(())
 }
 
-/// Wait for shutdown signal
-async fn shutdown_signal() {
-    let ctrl_c = async {
-        signal::ctrl_c()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AuthConfig, CollectEntry};
+    use std::time::Duration;
+
+    pub(super) fn test_state(config: Config) -> AppState {
+        let client = Arc::new(JolokiaClient::new(&config.jolokia.url, 1000).unwrap());
+        let collector =
+            Collector::with_client(Arc::clone(&client), config_to_collect_config(&config));
+        let modules = build_modules(&config, &client).unwrap();
+        let engine = build_engine(&config).unwrap();
+        AppState {
+            config: Arc::new(config),
+            client,
+            collector: Arc::new(collector),
+            engine: Arc::new(engine),
+            modules: Arc::new(modules),
+            file_sd: None,
+            background: None,
+            snapshot: None,
+            started_at: Instant::now(),
+            last_scrape: Arc::default(),
+            shutdown: Shutdown::new(Duration::from_secs(10)),
+        }
+    }
+
+    /// Serve the router on an ephemeral port, returning its address
+    async fn serve(config: Config) -> SocketAddr {
+        let app = build_router(test_state(config));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
             .await
-            .expect("Failed to install Ctrl+C handler");
-    };
+        });
+        addr
+    }
 
-    #[cfg(unix)]
-    let terminate = async {
-        signal::unix::signal(signal::unix::SignalKind::terminate())
-            .expect("Failed to install signal handler")
-            .recv()
+    async fn root_content_encoding(compression: bool) -> Option<String> {
+        let mut config = Config::default();
+        config.server.compression = compression;
+        let addr = serve(config).await;
+
+        let response = reqwest::Client::new()
+            .get(format!("http://{}/", addr))
+            .header(reqwest::header::ACCEPT_ENCODING, "gzip")
+            .send()
+            .await
+            .unwrap();
+        response
+            .headers()
+            .get(reqwest::header::CONTENT_ENCODING)
+            .map(|v| v.to_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_root_status_page() {
+        let config = Config::from_yaml(
+            r#"
+rules:
+  - pattern: "java.lang<type=Memory><HeapMemoryUsage>(\\w+)"
+    name: "jvm_memory_heap_$1_bytes"
+modules:
+  kafka:
+    rules:
+      - pattern: "kafka\\.server<type=ReplicaManager><PartitionCount>"
+        name: "kafka_partitions"
+"#,
+        )
+        .unwrap();
+        let addr = serve(config).await;
+
+        let response = reqwest::get(format!("http://{}/", addr)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body = response.text().await.unwrap();
+        assert!(body.contains("<h2>Rules</h2>"));
+        assert!(body.contains("java.lang&lt;type=Memory&gt;&lt;HeapMemoryUsage&gt;(\\w+)"));
+        assert!(body.contains("jvm_memory_heap_$1_bytes"));
+        assert!(body.contains(r#"<a href="/metrics">Metrics</a>"#));
+        assert!(body.contains("http://localhost:8778/jolokia"));
+        assert!(body.contains(r#"<a href="/metrics?module=kafka">"#));
+    }
+
+    async fn readyz(jolokia_url: &str) -> (reqwest::StatusCode, serde_json::Value) {
+        let mut config = Config::default();
+        config.jolokia.url = jolokia_url.to_string();
+        let addr = serve(config).await;
+
+        let response = reqwest::get(format!("http://{}/readyz", addr))
+            .await
+            .unwrap();
+        let status = response.status();
+        (status, response.json().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_livez_and_readyz() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let addr = serve(Config::default()).await;
+        let response = reqwest::get(format!("http://{}/livez", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        // No scrape yet: readiness probes Jolokia directly
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"type": "version"},
+                "value": {"agent": "1.7.2", "protocol": "7.2"},
+                "status": 200
+            })))
+            .mount(&mock_server)
             .await;
-    };
+        let (status, body) = readyz(&format!("{}/jolokia", mock_server.uri())).await;
+        assert_eq!(status, reqwest::StatusCode::OK);
+        assert_eq!(body["status"], "ready");
+        assert_eq!(body["checks"]["jolokia"]["status"], "ok");
+        assert_eq!(body["checks"]["tls"]["status"], "skipped");
+        assert!(body.get("backoff").is_none());
+
+        // Unreachable Jolokia
+        let (status, body) = readyz("http://127.0.0.1:1/jolokia").await;
+        assert_eq!(status, reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "not_ready");
+        assert_eq!(body["checks"]["jolokia"]["status"], "failed");
+        assert_eq!(body["checks"]["config"]["status"], "ok");
+    }
 
-    #[cfg(not(unix))]
-    let terminate = std::future::pending::<()>();
+    #[tokio::test]
+    async fn test_wildcard_mbean_errors() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
 
-    tokio::select! {
-        _ = ctrl_c => {
-            info!("Received Ctrl+C, shutting down");
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"mbean": "test.partial:type=Pool,*", "type": "read"},
+                "value": {
+                    "test.partial:name=Ok,type=Pool": {"Size": 3},
+                    "test.partial:name=Broken,type=Pool": {
+                        "error": "java.lang.IllegalStateException : closed",
+                        "error_type": "java.lang.IllegalStateException",
+                        "status": 500
+                    }
+                },
+                "status": 200
+            })))
+            .mount(&mock_server)
+            .await;
+        let mut config = Config::from_yaml(
+            r#"
+collect:
+  - object_name: "test.partial:type=Pool,*"
+rules:
+  - pattern: "test\\.partial<name=(\\w+)><type=Pool><Size>"
+    name: test_pool_size
+    labels:
+      pool: "$1"
+"#,
+        )
+        .unwrap();
+        config.jolokia.url = format!("{}/jolokia", mock_server.uri());
+        let addr = serve(config).await;
+
+        let response = reqwest::get(format!("http://{}/metrics", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body = response.text().await.unwrap();
+        assert!(body.contains(r#"test_pool_size{pool="Ok"} 3"#), "{}", body);
+        assert_eq!(
+            crate::metrics::internal_metrics().mbean_errors("test.partial:name=Broken,type=Pool"),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_poll_interval_replays_last_read() {
+        use wiremock::matchers::{body_string_contains, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("test.polled:type=Runtime"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"mbean": "test.polled:type=Runtime", "type": "read"},
+                "value": {"StartTime": 1700000000000u64},
+                "status": 200
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let mut config = Config::from_yaml(
+            r#"
+collect:
+  - object_name: "test.polled:type=Runtime"
+    poll_interval_seconds: 3600
+rules:
+  - pattern: "test\\.polled<type=Runtime><StartTime>"
+    name: test_polled_start_time
+"#,
+        )
+        .unwrap();
+        config.jolokia.url = format!("{}/jolokia", mock_server.uri());
+        let addr = serve(config).await;
+
+        for _ in 0..2 {
+            let body = reqwest::get(format!("http://{}/metrics", addr))
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+            assert!(
+                body.contains("test_polled_start_time 1700000000000"),
+                "{}",
+                body
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poll_history_replays_unchanged_read() {
+        use wiremock::matchers::{body_partial_json, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        let read = |history: Option<serde_json::Value>| {
+            let mut body = serde_json::json!({
+                "request": {"mbean": "test.history:type=Runtime", "type": "read"},
+                "value": {"StartTime": 1700000000000u64, "VmVersion": "21"},
+                "status": 200
+            });
+            if let Some(history) = history {
+                body["history"] = history;
+            }
+            ResponseTemplate::new(200).set_body_json(body)
+        };
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({
+                "type": "exec",
+                "mbean": "jolokia:type=Config",
+                "operation": "setHistoryEntriesForAttribute"
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"mbean": "jolokia:type=Config", "type": "exec"},
+                "value": null,
+                "status": 200
+            })))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        // The first read after registering has no history yet
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({"type": "read"})))
+            .respond_with(read(None))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({"type": "read"})))
+            .respond_with(read(Some(serde_json::json!({
+                "StartTime": [{"value": 1700000000000u64, "timestamp": 1700000000}],
+                "VmVersion": [{"value": "21", "timestamp": 1700000000}]
+            }))))
+            .with_priority(2)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let mut config = Config::from_yaml(
+            r#"
+collect:
+  - object_name: "test.history:type=Runtime"
+    attributes: [StartTime, VmVersion]
+    poll_interval_seconds: 3600
+    history: true
+rules:
+  - pattern: "test\\.history<type=Runtime><StartTime>"
+    name: test_history_start_time
+"#,
+        )
+        .unwrap();
+        config.jolokia.url = format!("{}/jolokia", mock_server.uri());
+        let addr = serve(config).await;
+
+        // Registered once, read twice, then replayed while unchanged
+        for _ in 0..3 {
+            let body = reqwest::get(format!("http://{}/metrics", addr))
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+            assert!(
+                body.contains("test_history_start_time 1700000000000"),
+                "{}",
+                body
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_agent_version() {
+        use wiremock::matchers::{body_json, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_json(serde_json::json!({"type": "version"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"type": "version"},
+                "value": {"agent": "2.0.1", "protocol": "8.0"},
+                "status": 200
+            })))
+            .mount(&mock_server)
+            .await;
+        let client = JolokiaClient::new(&format!("{}/jolokia", mock_server.uri()), 1000).unwrap();
+
+        let version = handlers::check_agent_version(&client, "agent-version-test")
+            .await
+            .unwrap();
+        assert_eq!(version.agent, "2.0.1");
+        assert!(version.is_supported());
+        assert_eq!(
+            crate::metrics::internal_metrics()
+                .target("agent-version-test")
+                .agent_version(),
+            Some(("2.0.1".to_string(), "8.0".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_readyz_backoff() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "60"))
+            .mount(&mock_server)
+            .await;
+        let mut config = Config::default();
+        config.jolokia.url = format!("{}/jolokia", mock_server.uri());
+        let addr = serve(config).await;
+
+        // The throttled scrape puts the target into backoff
+        let body = reqwest::get(format!("http://{}/metrics", addr))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let target = mock_server.address().to_string();
+        let gauge = format!(r#"rjmx_target_backoff_seconds{{target="{}"}} "#, target);
+        let backoff_seconds: f64 = body
+            .lines()
+            .find_map(|line| line.strip_prefix(&gauge))
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_else(|| panic!("{}", body));
+        assert!(backoff_seconds > 50.0, "{}", body);
+
+        let body: serde_json::Value = reqwest::get(format!("http://{}/readyz", addr))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let backoff = &body["backoff"][&target];
+        assert_eq!(backoff["status"], 429, "{}", body);
+        let retry_after = backoff["retry_after_seconds"].as_f64().unwrap();
+        assert!(retry_after > 50.0 && retry_after <= 60.0, "{}", body);
+    }
+
+    #[tokio::test]
+    async fn test_background_collection() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+        let mut config = Config::default();
+        config.jolokia.url = format!("{}/jolokia", mock_server.uri());
+        config.background = Some(crate::config::BackgroundConfig {
+            interval_seconds: 10,
+            jitter: 0.0,
+            max_interval_seconds: 30,
+            schedule: None,
+        });
+        let mut state = test_state(config);
+        let background = Arc::new(background::Background::new(
+            state.config.background.as_ref().unwrap(),
+        ));
+        state.background = Some(Arc::clone(&background));
+        background.spawn(state.clone());
+
+        let app = build_router(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        // The failed background scrape doubles the interval
+        let gauge = format!(
+            r#"rjmx_target_scrape_interval_seconds{{target="{}"}} "#,
+            mock_server.address()
+        );
+        let mut interval = None;
+        for _ in 0..50 {
+            let body = reqwest::get(format!("http://{}/metrics", addr))
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+            interval = body
+                .lines()
+                .find_map(|line| line.strip_prefix(&gauge))
+                .map(str::to_string);
+            if interval.is_some() {
+                assert!(body.contains("rjmx_exporter_scrape_errors 6"), "{}", body);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(interval.as_deref(), Some("20"));
+
+        // Later requests are served from the stored result
+        let requests = mock_server.received_requests().await.unwrap().len();
+        for _ in 0..3 {
+            reqwest::get(format!("http://{}/metrics", addr))
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            mock_server.received_requests().await.unwrap().len(),
+            requests
+        );
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_after_restart() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({
+                        "request": {"mbean": "java.lang:type=Threading", "type": "read"},
+                        "value": {"ThreadCount": 42},
+                        "status": 200
+                    }))
+                    .set_delay(Duration::from_millis(300)),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.json");
+        let saved_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        std::fs::write(
+            &path,
+            serde_json::json!({
+                "saved_at": saved_at,
+                "metrics": [{"name": "jvm_threads_current", "type": "gauge", "labels": {}, "value": 41}]
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let config = Config::from_yaml(&format!(
+            r#"
+jolokia:
+  url: "{}/jolokia"
+snapshot:
+  path: "{}"
+rules:
+  - pattern: "java\\.lang<type=Threading><ThreadCount>"
+    name: jvm_threads_current
+    type: gauge
+"#,
+            mock_server.uri(),
+            path.display()
+        ))
+        .unwrap();
+        let mut state = test_state(config);
+        state.snapshot = Some(Arc::new(snapshot::Snapshot::load(
+            state.config.snapshot.as_ref().unwrap(),
+        )));
+        let app = build_router(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let get = || async {
+            reqwest::get(format!("http://{}/metrics", addr))
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap()
+        };
+
+        // Served from the snapshot while the first live scrape is in flight
+        let body = get().await;
+        assert!(body.contains("jvm_threads_current 41"), "{}", body);
+        assert!(body.contains("rjmx_exporter_snapshot_stale 1"), "{}", body);
+
+        let mut body = String::new();
+        for _ in 0..50 {
+            body = get().await;
+            if !body.contains("rjmx_exporter_snapshot_stale") {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(body.contains("jvm_threads_current 42"), "{}", body);
+        assert!(!body.contains("rjmx_exporter_snapshot_stale"), "{}", body);
+
+        // The file is written in the background
+        let mut saved = serde_json::Value::Null;
+        for _ in 0..50 {
+            if let Ok(data) = std::fs::read(&path) {
+                saved = serde_json::from_slice(&data).unwrap();
+                if saved["metrics"][0]["value"] == 42.0 {
+                    break;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(saved["metrics"][0]["value"], 42.0);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_auth() {
+        let mut config = Config::default();
+        config.server.auth = Some(AuthConfig {
+            bearer_token: Some("token".to_string()),
+            ..Default::default()
+        });
+        let addr = serve(config).await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .get(format!("http://{}/metrics", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        assert!(response
+            .headers()
+            .contains_key(reqwest::header::WWW_AUTHENTICATE));
+
+        let response = client
+            .get(format!("http://{}/metrics", addr))
+            .bearer_auth("token")
+            .send()
+            .await
+            .unwrap();
+        assert_ne!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        // Health and index stay public
+        let response = client
+            .get(format!("http://{}/health", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_background_schedule() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+        let config = Config::from_yaml(
+            r#"
+targets_api: {}
+server:
+  auth:
+    bearer_token: admin
+background:
+  schedule: "0 0 1 1 *"
+"#,
+        )
+        .unwrap();
+        let mut state = test_state(config);
+        state.file_sd = Some(Arc::new(file_sd::FileSd::new(&state.config)));
+        let background = Arc::new(background::Background::new(
+            state.config.background.as_ref().unwrap(),
+        ));
+        state.background = Some(Arc::clone(&background));
+        background.spawn(state.clone());
+        let app = build_router(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+        });
+        let url = format!("http://{}/api/v1/targets", addr);
+        let client = reqwest::Client::new();
+        for (target, labels) in [
+            ("127.0.0.1", serde_json::json!({})),
+            (
+                "localhost",
+                serde_json::json!({"__scrape_schedule__": "*/5 * * * *"}),
+            ),
+        ] {
+            let body = serde_json::json!({
+                "target": format!("{}:{}", target, mock_server.address().port()),
+                "labels": labels,
+            });
+            let response = client
+                .post(&url)
+                .bearer_auth("admin")
+                .json(&body)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+        }
+
+        let mut listed = serde_json::Value::Null;
+        for _ in 0..50 {
+            listed = client
+                .get(&url)
+                .bearer_auth("admin")
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            if listed
+                .as_array()
+                .unwrap()
+                .iter()
+                .all(|target| target["next_run"].is_string())
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let next_run = |index: usize| listed[index]["next_run"].as_str().unwrap().to_string();
+        assert!(next_run(0).ends_with("-01-01T00:00:00Z"), "{}", listed);
+        assert_eq!(listed[1]["schedule"], "*/5 * * * *");
+        let minute: u32 = next_run(1)[14..16].parse().unwrap();
+        assert!(
+            minute.is_multiple_of(5) && next_run(1).ends_with(":00Z"),
+            "{}",
+            listed
+        );
+
+        // Scheduled targets wait for their first run
+        assert!(mock_server.received_requests().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_targets_api() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"mbean": "java.lang:type=Threading", "type": "read"},
+                "value": {"ThreadCount": 42},
+                "status": 200
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let config = Config::from_yaml(
+            r#"
+targets_api: {}
+server:
+  auth:
+    bearer_token: admin
+rules:
+  - pattern: "java\\.lang<type=Threading><ThreadCount>"
+    name: jvm_threads_current
+"#,
+        )
+        .unwrap();
+        let mut state = test_state(config);
+        state.file_sd = Some(Arc::new(file_sd::FileSd::new(&state.config)));
+        let app = build_router(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+        });
+        let url = format!("http://{}/api/v1/targets", addr);
+        let client = reqwest::Client::new();
+        let target = mock_server.address().to_string();
+        let body = serde_json::json!({"target": target, "labels": {"cluster": "prod"}});
+
+        let response = client.post(&url).json(&body).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let response = client
+            .post(&url)
+            .bearer_auth("admin")
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+        let added: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(added["name"], target);
+        assert_eq!(added["source"], "api");
+        let response = client
+            .post(&url)
+            .bearer_auth("admin")
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CONFLICT);
+
+        let metrics = |client: reqwest::Client| async move {
+            client
+                .get(format!("http://{}/metrics", addr))
+                .bearer_auth("admin")
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap()
+        };
+        let text = metrics(client.clone()).await;
+        assert!(text.contains("jvm_threads_current{"), "{}", text);
+        assert!(text.contains(r#"cluster="prod""#), "{}", text);
+
+        let listed: serde_json::Value = client
+            .get(&url)
+            .bearer_auth("admin")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+
+        let delete = || {
+            client
+                .delete(&url)
+                .query(&[("target", &target)])
+                .bearer_auth("admin")
+                .send()
+        };
+        assert_eq!(delete().await.unwrap().status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            delete().await.unwrap().status(),
+            reqwest::StatusCode::NOT_FOUND
+        );
+        let text = metrics(client.clone()).await;
+        assert!(!text.contains("jvm_threads_current{"), "{}", text);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_tenant_filter() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"mbean": "java.lang:type=Threading", "type": "read"},
+                "value": {"ThreadCount": 42, "DaemonThreadCount": 7},
+                "status": 200
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let config = Config::from_yaml(&format!(
+            r#"
+jolokia:
+  url: "{}/jolokia"
+server:
+  auth:
+    bearer_token: admin
+    tenants:
+      - name: threads
+        bearer_token: tenant
+        metric_prefixes: [jvm_threads_daemon]
+rules:
+  - pattern: "java\\.lang<type=Threading><ThreadCount>"
+    name: jvm_threads_current
+  - pattern: "java\\.lang<type=Threading><DaemonThreadCount>"
+    name: jvm_threads_daemon
+"#,
+            mock_server.uri()
+        ))
+        .unwrap();
+        let addr = serve(config).await;
+        let client = reqwest::Client::new();
+        let get = |path: &str, token: &str| {
+            client
+                .get(format!("http://{}{}", addr, path))
+                .bearer_auth(token)
+                .send()
+        };
+
+        let body = get("/metrics", "admin")
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(body.contains("jvm_threads_current 42"), "{}", body);
+        assert!(body.contains("rjmx_exporter_build_info"), "{}", body);
+
+        let response = get("/metrics", "tenant").await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body = response.text().await.unwrap();
+        assert!(body.contains("jvm_threads_daemon 7"), "{}", body);
+        assert!(!body.contains("jvm_threads_current"), "{}", body);
+        assert!(!body.contains("rjmx_"), "{}", body);
+
+        let snapshot: serde_json::Value = get("/metrics.json", "tenant")
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let metrics = snapshot["metrics"].as_array().unwrap();
+        assert!(!metrics.is_empty());
+        assert!(metrics.iter().all(|m| m["name"] == "jvm_threads_daemon"));
+
+        let response = get("/debug/rules", "tenant").await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drain() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({
+                        "request": {"mbean": "drain.test:type=Pool", "attribute": "Size", "type": "read"},
+                        "value": 8,
+                        "status": 200
+                    }))
+                    .set_delay(Duration::from_millis(500)),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let config = Config::from_yaml(&format!(
+            r#"
+jolokia:
+  url: "{}/jolokia"
+rules:
+  - pattern: "drain\\.test<type=(\\w+)><Size>"
+    name: "drain_$1_size"
+collect:
+  - object_name: "drain.test:type=Pool"
+    attributes: [Size]
+"#,
+            mock_server.uri()
+        ))
+        .unwrap();
+        let state = test_state(config);
+        let shutdown = state.shutdown.clone();
+        let listener = listen::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(run_http_server(
+            build_router(state),
+            vec![listener],
+            false,
+            "/metrics",
+            shutdown.clone(),
+        ));
+
+        // A scrape in flight when shutdown starts still completes
+        let scrape = tokio::spawn(reqwest::get(format!("http://{}/metrics", addr)));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        shutdown.trigger();
+
+        let response = scrape.await.unwrap().unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert!(response.text().await.unwrap().contains("drain_Pool_size 8"));
+
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("server did not stop after draining")
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_multiple_listeners() {
+        let state = test_state(Config::default());
+        let shutdown = state.shutdown.clone();
+        let mut addresses = vec![
+            "127.0.0.1:0".parse().unwrap(),
+            "127.0.0.2:0".parse().unwrap(),
+        ];
+        // IPv6 may be unavailable in the test environment
+        if listen::bind("[::1]:0".parse().unwrap()).is_ok() {
+            addresses.push("[::1]:0".parse().unwrap());
+        }
+        let listeners = listen::bind_all(&addresses).unwrap();
+        let bound: Vec<SocketAddr> = listeners
+            .iter()
+            .map(|listener| listener.local_addr().unwrap())
+            .collect();
+        let server = tokio::spawn(run_http_server(
+            build_router(state),
+            listeners,
+            false,
+            "/metrics",
+            shutdown.clone(),
+        ));
+
+        for addr in &bound {
+            let response = reqwest::get(format!("http://{}/livez", addr))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::OK, "{}", addr);
+        }
+
+        // Shutdown stops every listener
+        shutdown.trigger();
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("server did not stop")
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_draining_rejects_scrapes() {
+        let state = test_state(Config::default());
+        state.shutdown.trigger();
+        let app = build_router(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+        });
+
+        let response = reqwest::get(format!("http://{}/metrics", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.text().await.unwrap(), "Shutting down");
+
+        let response = reqwest::get(format!("http://{}/readyz", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["checks"]["shutdown"]["status"], "failed");
+
+        // Liveness is unaffected
+        let response = reqwest::get(format!("http://{}/livez", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_debug_endpoints() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"mbean": "debug.test:type=Pool", "attribute": "Size", "type": "read"},
+                "value": 8,
+                "status": 200
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let config = Config::from_yaml(&format!(
+            r#"
+jolokia:
+  url: "{}/jolokia"
+server:
+  auth:
+    bearer_token: token
+rules:
+  - pattern: "debug\\.test<type=(\\w+)><Size>"
+    name: "debug_$1_size"
+collect:
+  - object_name: "debug.test:type=Pool"
+    attributes: [Size]
+"#,
+            mock_server.uri()
+        ))
+        .unwrap();
+        let addr = serve(config).await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .get(format!("http://{}/debug/rules", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let response = client
+            .get(format!("http://{}/debug/mbeans", addr))
+            .bearer_auth("token")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+        let response = client
+            .get(format!("http://{}/metrics", addr))
+            .bearer_auth("token")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let response = client
+            .get(format!("http://{}/debug/rules", addr))
+            .bearer_auth("token")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        let rule = &body["rules"][0];
+        assert_eq!(rule["index"], 0);
+        assert_eq!(rule["pattern"], "debug\\.test<type=(\\w+)><Size>");
+        assert!(rule["regex"].is_string());
+        assert_eq!(rule["name"], "debug_$1_size");
+        assert_eq!(rule["matches"], 1);
+        assert_eq!(rule["errors"], 0);
+        assert_eq!(
+            rule["recent_mbeans"],
+            serde_json::json!(["debug.test:type=Pool"])
+        );
+        assert_eq!(body["modules"], serde_json::json!({}));
+
+        let response = client
+            .get(format!("http://{}/debug/mbeans", addr))
+            .bearer_auth("token")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "module": null,
+                "mbeans": [{"name": "debug.test<type=Pool><Size>", "matched": true, "rule": 0}]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_debug_mbeans_lists_skipped_attributes() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"mbean": "java.lang:type=GarbageCollector,*", "type": "read"},
+                "value": {
+                    "java.lang:name=G1 Young Generation,type=GarbageCollector": {
+                        "CollectionCount": 5,
+                        "Name": "G1 Young Generation",
+                        "LastGcInfo": {"duration": 3}
+                    }
+                },
+                "status": 200
+            })))
+            .mount(&mock_server)
+            .await;
+
+        // No rule matches the collector, so the attribute filter skips its values
+        let config = Config::from_yaml(&format!(
+            r#"
+jolokia:
+  url: "{}/jolokia"
+rules:
+  - pattern: "java\\.lang<type=Memory><HeapMemoryUsage>(\\w+)"
+    name: "jvm_memory_heap_$1_bytes"
+collect:
+  - object_name: "java.lang:type=GarbageCollector,*"
+"#,
+            mock_server.uri()
+        ))
+        .unwrap();
+        let addr = serve(config).await;
+        reqwest::get(format!("http://{}/metrics", addr))
+            .await
+            .unwrap();
+
+        let body: serde_json::Value = reqwest::get(format!("http://{}/debug/mbeans", addr))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(
+            body["mbeans"],
+            serde_json::json!([
+                {
+                    "name": "java.lang<name=G1 Young Generation><type=GarbageCollector><CollectionCount>",
+                    "matched": false,
+                    "rule": null
+                },
+                {
+                    "name": "java.lang<name=G1 Young Generation><type=GarbageCollector><LastGcInfo><duration>",
+                    "matched": false,
+                    "rule": null
+                }
+            ])
+        );
+    }
+
+    async fn slow_jolokia(delay: Duration) -> wiremock::MockServer {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({
+                        "request": {"mbean": "java.lang:type=Threading", "type": "read"},
+                        "value": {"ThreadCount": 42},
+                        "status": 200
+                    }))
+                    .set_delay(delay),
+            )
+            .mount(&mock_server)
+            .await;
+        mock_server
+    }
+
+    fn slow_jolokia_config(mock_server: &wiremock::MockServer) -> Config {
+        let mut config = Config::default();
+        config.jolokia.url = format!("{}/jolokia", mock_server.uri());
+        config.jolokia.retry.max_retries = 0;
+        config
+    }
+
+    #[tokio::test]
+    async fn test_scrape_timeout_header() {
+        let mock_server = slow_jolokia(Duration::from_secs(5)).await;
+        let addr = serve(slow_jolokia_config(&mock_server)).await;
+
+        let start = std::time::Instant::now();
+        let response = reqwest::Client::new()
+            .get(format!("http://{}/metrics", addr))
+            .header("X-Prometheus-Scrape-Timeout-Seconds", "0.3")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::GATEWAY_TIMEOUT);
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_client_disconnect_cancels_scrape() {
+        let mock_server = slow_jolokia(Duration::from_secs(1)).await;
+        let config = slow_jolokia_config(&mock_server);
+        let target = handlers::sanitize_url_for_label(&config.jolokia.url);
+        let addr = serve(config).await;
+
+        let result = reqwest::Client::new()
+            .get(format!("http://{}/metrics", addr))
+            .timeout(Duration::from_millis(200))
+            .send()
+            .await;
+        assert!(result.unwrap_err().is_timeout());
+
+        let cancelled = || {
+            crate::metrics::internal_metrics()
+                .targets()
+                .into_iter()
+                .find(|(name, _)| *name == target)
+                .map_or(0, |(_, metrics)| metrics.scrape_cancelled_total.get())
+        };
+        let deadline = std::time::Instant::now() + Duration::from_secs(2);
+        while cancelled() == 0 && std::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
         }
-        _ = terminate => {
-            info!("Received terminate signal, shutting down");
+        assert_eq!(cancelled(), 1);
+
+        // Nothing is sent to Jolokia after the scrape is dropped
+        let sent = mock_server.received_requests().await.unwrap().len();
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert_eq!(mock_server.received_requests().await.unwrap().len(), sent);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_rate_limit() {
+        let mut config = Config::default();
+        config.jolokia.url = "http://127.0.0.1:1/jolokia".to_string();
+        config.start_delay_seconds = 3600;
+        config.server.rate_limit = Some(crate::config::RateLimitConfig {
+            max_scrapes_per_minute: 1,
+            burst: Some(2),
+            per: crate::config::RateLimitScope::SourceIp,
+        });
+        let addr = serve(config).await;
+        let url = format!("http://{}/metrics", addr);
+
+        // The start delay answers without touching Jolokia
+        for _ in 0..2 {
+            let response = reqwest::get(&url).await.unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
         }
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.headers()[reqwest::header::RETRY_AFTER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((59..=60).contains(&retry_after));
+
+        // Other endpoints are not limited
+        let response = reqwest::get(format!("http://{}/livez", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_start_delay() {
+        let mut config = Config::default();
+        config.jolokia.url = "http://127.0.0.1:1/jolokia".to_string();
+        config.start_delay_seconds = 3600;
+        let addr = serve(config).await;
+
+        let response = reqwest::get(format!("http://{}/metrics", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let retry_after: u64 = response.headers()[reqwest::header::RETRY_AFTER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(retry_after > 3500 && retry_after <= 3600);
+
+        let response = reqwest::get(format!("http://{}/readyz", addr))
+            .await
+            .unwrap();
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["checks"]["startup"]["status"], "failed");
+    }
+
+    #[tokio::test]
+    async fn test_metrics_unknown_collect_group() {
+        let mut config = Config::default();
+        config.collect_groups.insert(
+            "memory".to_string(),
+            vec!["java.lang:type=Memory".to_string()],
+        );
+        let addr = serve(config).await;
+
+        let response = reqwest::get(format!("http://{}/metrics?collect[]=kafka", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        assert!(response.text().await.unwrap().contains("'kafka'"));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_collect_group_cached_values() {
+        use wiremock::matchers::{body_string_contains, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        for (mbean, attribute, value) in [
+            ("test.group:type=Runtime", "StartTime", 1000),
+            ("test.group:type=Memory", "Used", 42),
+        ] {
+            Mock::given(method("POST"))
+                .and(body_string_contains(mbean))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "request": {"mbean": mbean, "type": "read"},
+                    "value": {attribute: value},
+                    "status": 200
+                })))
+                .mount(&mock_server)
+                .await;
+        }
+        let mut config = Config::from_yaml(
+            r#"
+collect:
+  - object_name: "test.group:type=Runtime"
+  - object_name: "test.group:type=Memory"
+collect_groups:
+  runtime: ["test.group:type=Runtime"]
+rules:
+  - pattern: "test\\.group<type=Runtime><StartTime>"
+    name: test_group_start_time
+    cache: true
+  - pattern: "test\\.group<type=Memory><Used>"
+    name: test_group_used
+"#,
+        )
+        .unwrap();
+        config.jolokia.url = format!("{}/jolokia", mock_server.uri());
+        let addr = serve(config).await;
+
+        let scrape = |query: &'static str| async move {
+            reqwest::get(format!("http://{}/metrics{}", addr, query))
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap()
+        };
+        let body = scrape("").await;
+        assert!(body.contains("test_group_start_time 1000"), "{}", body);
+
+        let body = scrape("?exclude[]=runtime").await;
+        assert!(body.contains("test_group_used 42"), "{}", body);
+        assert!(!body.contains("test_group_start_time"), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_shards_split_reads() {
+        use wiremock::matchers::{body_string_contains, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        let mbeans = [
+            "test.shard:name=a",
+            "test.shard:name=b",
+            "test.shard:name=c",
+        ];
+        for mbean in mbeans {
+            Mock::given(method("POST"))
+                .and(body_string_contains(mbean))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "request": {"mbean": mbean, "type": "read"},
+                    "value": {"Value": 1},
+                    "status": 200
+                })))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+        let mut config = Config::from_yaml(
+            r#"
+collect:
+  - object_name: "test.shard:name=a"
+  - object_name: "test.shard:name=b"
+  - object_name: "test.shard:name=c"
+rules:
+  - pattern: "test\\.shard<name=(\\w+)><Value>"
+    name: test_shard_value
+    labels:
+      name: "$1"
+"#,
+        )
+        .unwrap();
+        config.jolokia.url = format!("{}/jolokia", mock_server.uri());
+        let addr = serve(config).await;
+
+        // Together the shards read and export every MBean exactly once
+        let mut series = 0;
+        for shard in ["0/2", "1/2"] {
+            let body = reqwest::get(format!("http://{}/metrics?shard={}", addr, shard))
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+            series += body
+                .lines()
+                .filter(|line| line.starts_with("test_shard_value{"))
+                .count();
+        }
+        assert_eq!(series, mbeans.len());
+    }
+
+    #[tokio::test]
+    async fn test_metrics_invalid_shard() {
+        let mut config = Config::default();
+        config.jolokia.url = "http://127.0.0.1:1/jolokia".to_string();
+        let addr = serve(config).await;
+
+        let response = reqwest::get(format!("http://{}/metrics?shard=8/8", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        assert!(response.text().await.unwrap().contains("'8/8'"));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_module_selection() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"mbean": "kafka.server:type=ReplicaManager", "attribute": "PartitionCount", "type": "read"},
+                "value": 12,
+                "status": 200
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let config = Config::from_yaml(&format!(
+            r#"
+jolokia:
+  url: "{}/jolokia"
+modules:
+  kafka:
+    rules:
+      - pattern: "kafka\\.server<type=ReplicaManager><PartitionCount>"
+        name: "kafka_partitions"
+    collect:
+      - object_name: "kafka.server:type=ReplicaManager"
+        attributes: [PartitionCount]
+"#,
+            mock_server.uri()
+        ))
+        .unwrap();
+        let addr = serve(config).await;
+
+        let response = reqwest::get(format!("http://{}/metrics?module=kafka", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body = response.text().await.unwrap();
+        assert!(body.contains("kafka_partitions 12"), "{}", body);
+
+        let response = reqwest::get(format!("http://{}/metrics?module=tomcat", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_json() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"mbean": "kafka.server:type=ReplicaManager", "attribute": "PartitionCount", "type": "read"},
+                "value": 12,
+                "status": 200
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let config = Config::from_yaml(&format!(
+            r#"
+jolokia:
+  url: "{}/jolokia"
+rules:
+  - pattern: "kafka\\.server<type=ReplicaManager><PartitionCount>"
+    name: "kafka_partitions"
+    type: gauge
+    help: "Partitions on this broker"
+    labels:
+      broker: "1"
+collect:
+  - object_name: "kafka.server:type=ReplicaManager"
+    attributes: [PartitionCount]
+"#,
+            mock_server.uri()
+        ))
+        .unwrap();
+        let addr = serve(config).await;
+
+        let response = reqwest::get(format!("http://{}/metrics.json", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let snapshot: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(
+            snapshot,
+            serde_json::json!({
+                "metrics": [{
+                    "name": "kafka_partitions",
+                    "type": "gauge",
+                    "help": "Partitions on this broker",
+                    "labels": {"broker": "1"},
+                    "value": 12.0
+                }],
+                "errors": []
+            })
+        );
+
+        let response = reqwest::get(format!("http://{}/metrics.json?module=kafka", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_scrape_error_categories() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&mock_server)
+            .await;
+
+        let config = Config::from_yaml(&format!(
+            r#"
+jolokia:
+  url: "{}/jolokia"
+collect:
+  - object_name: "java.lang:type=Memory"
+"#,
+            mock_server.uri()
+        ))
+        .unwrap();
+        let addr = serve(config).await;
+
+        let body = reqwest::get(format!("http://{}/metrics", addr))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        // Counters are global; an earlier test may have used the same port
+        let target = mock_server.address().to_string();
+        let errors = |category: &str| -> f64 {
+            let series = format!(
+                r#"rjmx_scrape_errors_total{{category="{}",target="{}"}} "#,
+                category, target
+            );
+            body.lines()
+                .find_map(|line| line.strip_prefix(&series))
+                .and_then(|value| value.parse().ok())
+                .unwrap_or_else(|| panic!("{}", body))
+        };
+        assert!(errors("auth") >= 1.0, "{}", body);
+        assert_eq!(errors("connect"), 0.0, "{}", body);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_external_labels() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"mbean": "kafka.server:type=ReplicaManager", "attribute": "PartitionCount", "type": "read"},
+                "value": 12,
+                "status": 200
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let config = Config::from_yaml(&format!(
+            r#"
+jolokia:
+  url: "{}/jolokia"
+  labels:
+    broker: "kafka-1"
+external_labels:
+  cluster: "prod"
+  broker: "ignored"
+rules:
+  - pattern: "kafka\\.server<type=ReplicaManager><PartitionCount>"
+    name: "kafka_partitions"
+    labels:
+      cluster: "from-rule"
+collect:
+  - object_name: "kafka.server:type=ReplicaManager"
+    attributes: [PartitionCount]
+"#,
+            mock_server.uri()
+        ))
+        .unwrap();
+        let addr = serve(config).await;
+
+        let body = reqwest::get(format!("http://{}/metrics", addr))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        // Labels set by rules and targets win over external labels
+        assert!(
+            body.contains(r#"kafka_partitions{broker="kafka-1",cluster="from-rule"} 12"#),
+            "{}",
+            body
+        );
+        assert!(
+            body.contains(r#"rjmx_exporter_scrape_errors{broker="ignored",cluster="prod"} 0"#),
+            "{}",
+            body
+        );
+    }
+
+    #[test]
+    fn test_assemble_series_limit_covers_all_targets() {
+        let state = test_state(Config::from_yaml("limits:\n  max_series_per_scrape: 3\n").unwrap());
+        let scrape = |target: &str| handlers::TargetScrape {
+            metrics: (0..3)
+                .map(|i| {
+                    crate::transformer::PrometheusMetric::new("test_series", f64::from(i))
+                        .with_label("target", target)
+                        .with_label("id", i.to_string())
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        let output = handlers::assemble(
+            &state,
+            vec![scrape("a"), scrape("b")],
+            Instant::now(),
+            &axum::http::HeaderMap::new(),
+        );
+        assert_eq!(output.metrics.len(), 3);
+        assert!(output
+            .metrics
+            .iter()
+            .all(|m| m.labels.get("target") == Some("a")));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_protobuf() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"mbean": "java.lang:type=Threading", "attribute": "ThreadCount", "type": "read"},
+                "value": 42,
+                "status": 200
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let config = Config::from_yaml(&format!(
+            r#"
+jolokia:
+  url: "{}/jolokia"
+collect:
+  - object_name: "java.lang:type=Threading"
+    attributes: [ThreadCount]
+rules:
+  - pattern: "java\\.lang<type=Threading><ThreadCount>"
+    name: "jvm_threads_current"
+    type: gauge
+"#,
+            mock_server.uri()
+        ))
+        .unwrap();
+        let addr = serve(config).await;
+
+        let response = reqwest::Client::new()
+            .get(format!("http://{}/metrics", addr))
+            .header(
+                reqwest::header::ACCEPT,
+                "application/vnd.google.protobuf;proto=io.prometheus.client.MetricFamily;encoding=delimited;q=0.7,text/plain;version=0.0.4;q=0.3",
+            )
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.headers()[reqwest::header::CONTENT_TYPE],
+            crate::transformer::protobuf::CONTENT_TYPE
+        );
+        let body = response.bytes().await.unwrap();
+        // First message: length, then field 1 (name) of the first family
+        let name = b"jvm_threads_current";
+        assert_eq!(body[1], 0x0a);
+        assert_eq!(&body[3..3 + name.len()], name);
+
+        let response = reqwest::get(format!("http://{}/metrics", addr))
+            .await
+            .unwrap();
+        assert!(response.headers()[reqwest::header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain"));
+        let body = response.text().await.unwrap();
+        assert!(body.contains("jvm_threads_current 42"), "{}", body);
+        assert!(body.contains("rjmx_exporter_info{version="), "{}", body);
+        assert!(body.contains("rjmx_exporter_build_info{"), "{}", body);
+        assert!(
+            body.contains("rjmx_process_start_time_seconds "),
+            "{}",
+            body
+        );
+    }
+
+    #[tokio::test]
+    async fn test_router_compression_toggle() {
+        assert_eq!(root_content_encoding(true).await.as_deref(), Some("gzip"));
+        assert_eq!(root_content_encoding(false).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_access_log_passthrough() {
+        let mut config = Config::default();
+        config.server.access_log = AccessLogFormat::Common;
+        config.server.auth = Some(AuthConfig {
+            bearer_token: Some("token".to_string()),
+            ..Default::default()
+        });
+        let addr = serve(config).await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .get(format!("http://{}/health", addr))
+            .header(reqwest::header::ACCEPT_ENCODING, "gzip")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.headers()[reqwest::header::CONTENT_ENCODING],
+            "gzip"
+        );
+
+        let response = client
+            .get(format!("http://{}/metrics", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        assert_eq!(response.text().await.unwrap(), "Unauthorized");
+    }
+
+    #[test]
+    fn test_collect_config_defaults() {
+        let config = Config::default();
+        let collect = config_to_collect_config(&config);
+        assert_eq!(collect.mbeans.len(), DEFAULT_MBEANS.len());
+        assert_eq!(collect.timeout_ms, config.jolokia.timeout_ms);
+    }
+
+    #[test]
+    fn test_collect_config_whitelist_search() {
+        let config = Config {
+            whitelist_object_names: vec![
+                "java.lang:type=Memory".to_string(),
+                "kafka.server".to_string(),
+            ],
+            blacklist_object_names: vec!["kafka.server:type=ReplicaManager,*".to_string()],
+            ..Default::default()
+        };
+
+        let collect = config_to_collect_config(&config);
+        assert_eq!(
+            collect.mbeans,
+            vec![
+                MBeanQuery::new("java.lang:type=Memory"),
+                MBeanQuery::new("kafka.server:*")
+                    .with_search(vec!["kafka.server:type=ReplicaManager,*".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_config_precedence_and_blacklist() {
+        let config = Config {
+            whitelist_object_names: vec!["java.lang:type=Memory".to_string()],
+            blacklist_object_names: vec!["kafka.server".to_string()],
+            collect: vec![
+                CollectEntry {
+                    object_name: "java.lang:type=Threading".to_string(),
+                    attributes: Some(vec!["ThreadCount".to_string()]),
+                    quiet_hours: vec![],
+                    include_attributes: vec![],
+                    exclude_attributes: vec![],
+                    poll_interval_seconds: None,
+                    history: false,
+                },
+                CollectEntry {
+                    object_name: "kafka.server:type=BrokerTopicMetrics,*".to_string(),
+                    attributes: None,
+                    quiet_hours: vec![],
+                    include_attributes: vec![],
+                    exclude_attributes: vec![],
+                    poll_interval_seconds: None,
+                    history: false,
+                },
+            ],
+            ..Default::default()
+        };
+
+        let collect = config_to_collect_config(&config);
+        assert_eq!(
+            collect.mbeans,
+            vec![MBeanQuery::new("java.lang:type=Threading")
+                .with_attributes(vec!["ThreadCount".to_string()])]
+        );
     }
 }
@@ -0,0 +1,214 @@
+//! CPU profiling endpoint (`pprof` feature)
+//!
+//! `/debug/pprof/profile` samples the exporter's own threads for a number of
+//! seconds and returns either a pprof protobuf, readable with
+//! `go tool pprof`, or an SVG flamegraph.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use axum::{
+    extract::Query,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use pprof::protos::Message;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+/// Longest profile that can be requested
+const MAX_SECONDS: u64 = 300;
+
+/// Highest sampling frequency that can be requested, in Hz
+const MAX_FREQUENCY: i32 = 1000;
+
+/// Frames from these libraries are left out of the report
+const BLOCKLIST: &[&str] = &["libc", "libgcc", "pthread", "vdso"];
+
+/// Set while a profile is running; the profiler is process-wide
+static PROFILING: AtomicBool = AtomicBool::new(false);
+
+/// Output format of a profile
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileFormat {
+    /// pprof protobuf
+    #[default]
+    Pprof,
+    /// SVG flamegraph
+    Flamegraph,
+}
+
+/// Query parameters of `/debug/pprof/profile`
+#[derive(Debug, Deserialize)]
+pub struct ProfileParams {
+    /// Profile duration in seconds
+    #[serde(default = "default_seconds")]
+    seconds: u64,
+    /// Sampling frequency in Hz
+    #[serde(default = "default_frequency")]
+    frequency: i32,
+    /// Output format
+    #[serde(default)]
+    format: ProfileFormat,
+}
+
+fn default_seconds() -> u64 {
+    30
+}
+
+fn default_frequency() -> i32 {
+    100
+}
+
+impl ProfileParams {
+    /// Check the parameters against the allowed ranges
+    fn validate(&self) -> Result<(), String> {
+        if self.seconds == 0 || self.seconds > MAX_SECONDS {
+            return Err(format!("seconds must be between 1 and {}", MAX_SECONDS));
+        }
+        if self.frequency <= 0 || self.frequency > MAX_FREQUENCY {
+            return Err(format!("frequency must be between 1 and {}", MAX_FREQUENCY));
+        }
+        Ok(())
+    }
+}
+
+/// Clears [`PROFILING`] when the profile ends, even if the request is dropped
+struct ProfilingSlot;
+
+impl ProfilingSlot {
+    fn acquire() -> Option<Self> {
+        PROFILING
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .ok()
+            .map(|_| Self)
+    }
+}
+
+impl Drop for ProfilingSlot {
+    fn drop(&mut self) {
+        PROFILING.store(false, Ordering::Release);
+    }
+}
+
+/// CPU profile endpoint
+///
+/// Returns 409 if another profile is still running.
+pub async fn profile(Query(params): Query<ProfileParams>) -> Response {
+    if let Err(message) = params.validate() {
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+    let Some(_slot) = ProfilingSlot::acquire() else {
+        return (StatusCode::CONFLICT, "A profile is already running").into_response();
+    };
+
+    info!(
+        seconds = params.seconds,
+        frequency = params.frequency,
+        format = ?params.format,
+        "Starting CPU profile"
+    );
+
+    let guard = match pprof::ProfilerGuardBuilder::default()
+        .frequency(params.frequency)
+        .blocklist(BLOCKLIST)
+        .build()
+    {
+        Ok(guard) => guard,
+        Err(e) => return profile_error(e),
+    };
+    tokio::time::sleep(Duration::from_secs(params.seconds)).await;
+
+    let report = match guard.report().build() {
+        Ok(report) => report,
+        Err(e) => return profile_error(e),
+    };
+    drop(guard);
+
+    match params.format {
+        ProfileFormat::Pprof => {
+            let profile = match report.pprof() {
+                Ok(profile) => profile,
+                Err(e) => return profile_error(e),
+            };
+            let mut body = Vec::new();
+            if let Err(e) = profile.encode(&mut body) {
+                return profile_error(e);
+            }
+            (
+                StatusCode::OK,
+                [
+                    (header::CONTENT_TYPE, "application/octet-stream"),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        "attachment; filename=\"profile.pb\"",
+                    ),
+                ],
+                body,
+            )
+                .into_response()
+        }
+        ProfileFormat::Flamegraph => {
+            let mut body = Vec::new();
+            if let Err(e) = report.flamegraph(&mut body) {
+                return profile_error(e);
+            }
+            (
+                StatusCode::OK,
+                [(header::CONTENT_TYPE, "image/svg+xml")],
+                body,
+            )
+                .into_response()
+        }
+    }
+}
+
+fn profile_error(error: impl std::fmt::Display) -> Response {
+    warn!(error = %error, "CPU profile failed");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Profiling failed: {}", error),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str) -> Option<ProfileParams> {
+        let uri: axum::http::Uri = format!("/debug/pprof/profile?{}", query).parse().unwrap();
+        Query::try_from_uri(&uri).ok().map(|Query(params)| params)
+    }
+
+    fn params(query: &str) -> ProfileParams {
+        parse(query).unwrap()
+    }
+
+    #[test]
+    fn test_profile_params() {
+        let defaults = params("");
+        assert_eq!(defaults.seconds, 30);
+        assert_eq!(defaults.frequency, 100);
+        assert_eq!(defaults.format, ProfileFormat::Pprof);
+        assert!(defaults.validate().is_ok());
+
+        let flamegraph = params("seconds=5&format=flamegraph");
+        assert_eq!(flamegraph.seconds, 5);
+        assert_eq!(flamegraph.format, ProfileFormat::Flamegraph);
+
+        assert!(params("seconds=0").validate().is_err());
+        assert!(params("seconds=301").validate().is_err());
+        assert!(params("frequency=5000").validate().is_err());
+        assert!(parse("format=svg").is_none());
+    }
+
+    #[test]
+    fn test_profiling_slot_is_exclusive() {
+        let slot = ProfilingSlot::acquire().unwrap();
+        assert!(ProfilingSlot::acquire().is_none());
+        drop(slot);
+        assert!(ProfilingSlot::acquire().is_some());
+    }
+}
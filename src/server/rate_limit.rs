@@ -0,0 +1,191 @@
+//! Rate limiting middleware for the metrics endpoint
+//!
+//! A token bucket holds up to `burst` scrapes and refills at
+//! `max_scrapes_per_minute`. With `per: source_ip`, every peer address gets
+//! its own bucket; behind a proxy that is the proxy's address.
+//!
+//! At most [`MAX_BUCKETS`] per-IP buckets are kept. When a new address
+//! arrives at the cap, full buckets are dropped first, then the least
+//! recently used one, so a flood of distinct sources cannot grow the map.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::debug;
+
+use crate::config::{RateLimitConfig, RateLimitScope};
+
+/// Per-IP buckets kept before old ones are evicted
+const MAX_BUCKETS: usize = 1024;
+
+/// Token buckets for the metrics endpoint
+#[derive(Debug)]
+pub struct RateLimiter {
+    scope: RateLimitScope,
+    capacity: f64,
+    /// Tokens added per second
+    refill_rate: f64,
+    /// Buckets by source IP; `None` is the global bucket
+    buckets: Mutex<HashMap<Option<IpAddr>, Bucket>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter from the configuration
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            scope: config.per,
+            capacity: f64::from(config.burst()),
+            refill_rate: f64::from(config.max_scrapes_per_minute) / 60.0,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for `source`, or return the seconds until one is available
+    fn acquire(&self, source: Option<IpAddr>, now: Instant) -> Result<(), u64> {
+        let key = match self.scope {
+            RateLimitScope::Global => None,
+            RateLimitScope::SourceIp => source,
+        };
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        if buckets.len() >= MAX_BUCKETS && !buckets.contains_key(&key) {
+            buckets.retain(|_, bucket| self.refill(*bucket, now).tokens < self.capacity);
+            if buckets.len() >= MAX_BUCKETS {
+                let oldest = buckets
+                    .iter()
+                    .min_by_key(|(_, bucket)| bucket.updated)
+                    .map(|(key, _)| *key);
+                if let Some(oldest) = oldest {
+                    buckets.remove(&oldest);
+                }
+            }
+        }
+
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: self.capacity,
+            updated: now,
+        });
+        *bucket = self.refill(*bucket, now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let wait = (1.0 - bucket.tokens) / self.refill_rate;
+            Err(wait.ceil().max(1.0) as u64)
+        }
+    }
+
+    fn refill(&self, bucket: Bucket, now: Instant) -> Bucket {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        Bucket {
+            tokens: (bucket.tokens + elapsed * self.refill_rate).min(self.capacity),
+            updated: now,
+        }
+    }
+}
+
+/// Reject scrapes over the configured rate with 429
+pub async fn limit_rate(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let source = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+
+    match limiter.acquire(source, Instant::now()) {
+        Ok(()) => next.run(request).await,
+        Err(seconds) => {
+            debug!(source = ?source, retry_after = seconds, "Rejecting rate-limited scrape");
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, seconds.to_string())],
+                "Too many scrapes",
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn limiter(max_scrapes_per_minute: u32, burst: u32, per: RateLimitScope) -> RateLimiter {
+        RateLimiter::new(&RateLimitConfig {
+            max_scrapes_per_minute,
+            burst: Some(burst),
+            per,
+        })
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = limiter(6, 2, RateLimitScope::Global);
+        let now = Instant::now();
+
+        assert!(limiter.acquire(None, now).is_ok());
+        assert!(limiter.acquire(None, now).is_ok());
+        assert_eq!(limiter.acquire(None, now), Err(10));
+
+        // One token every 10 seconds
+        let later = now + Duration::from_secs(4);
+        assert_eq!(limiter.acquire(None, later), Err(6));
+        assert!(limiter.acquire(None, now + Duration::from_secs(10)).is_ok());
+        assert!(limiter
+            .acquire(None, now + Duration::from_secs(10))
+            .is_err());
+    }
+
+    #[test]
+    fn test_scope() {
+        let a: IpAddr = "10.0.0.1".parse().unwrap();
+        let b: IpAddr = "10.0.0.2".parse().unwrap();
+        let now = Instant::now();
+
+        let global = limiter(1, 1, RateLimitScope::Global);
+        assert!(global.acquire(Some(a), now).is_ok());
+        assert!(global.acquire(Some(b), now).is_err());
+
+        let per_ip = limiter(1, 1, RateLimitScope::SourceIp);
+        assert!(per_ip.acquire(Some(a), now).is_ok());
+        assert!(per_ip.acquire(Some(a), now).is_err());
+        assert!(per_ip.acquire(Some(b), now).is_ok());
+    }
+
+    #[test]
+    fn test_bucket_cap() {
+        let limiter = limiter(1, 1, RateLimitScope::SourceIp);
+        let now = Instant::now();
+
+        // Every bucket is drained, so none can be dropped as full
+        for i in 0..MAX_BUCKETS as u32 * 2 {
+            let ip = IpAddr::from(std::net::Ipv4Addr::from(i));
+            let at = now + Duration::from_millis(u64::from(i));
+            assert!(limiter.acquire(Some(ip), at).is_ok());
+        }
+        assert_eq!(limiter.buckets.lock().unwrap().len(), MAX_BUCKETS);
+
+        // The most recent sources keep their drained buckets
+        let last = IpAddr::from(std::net::Ipv4Addr::from(MAX_BUCKETS as u32 * 2 - 1));
+        assert!(limiter.acquire(Some(last), now).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+//! Graceful shutdown drain
+//!
+//! A shutdown signal flips a shared flag. From then on the listener accepts
+//! no new connections, scrapes arriving on open keep-alive connections get
+//! 503, and `/readyz` reports not ready so load balancers stop routing here.
+//! In-flight scrapes (and their Jolokia requests) run to completion, or until
+//! `shutdown.grace_period_secs` elapses, whichever comes first.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::signal;
+use tokio::sync::watch;
+use tracing::info;
+
+/// Shutdown state shared by the server and the handlers
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    draining: Arc<watch::Sender<bool>>,
+    grace_period: Duration,
+}
+
+impl Shutdown {
+    /// Create a running (not draining) state
+    pub fn new(grace_period: Duration) -> Self {
+        Self {
+            draining: Arc::new(watch::Sender::new(false)),
+            grace_period,
+        }
+    }
+
+    /// Time in-flight requests get to finish
+    pub fn grace_period(&self) -> Duration {
+        self.grace_period
+    }
+
+    /// Start draining
+    pub fn trigger(&self) {
+        self.draining.send_replace(true);
+    }
+
+    /// Check whether shutdown has started
+    pub fn is_draining(&self) -> bool {
+        *self.draining.borrow()
+    }
+
+    /// Wait until shutdown starts
+    pub async fn triggered(&self) {
+        let mut draining = self.draining.subscribe();
+        // The sender lives in `self`, so the channel cannot close
+        let _ = draining.wait_for(|draining| *draining).await;
+    }
+
+    /// Wait until the grace period after the start of shutdown has elapsed
+    pub async fn deadline(&self) {
+        self.triggered().await;
+        tokio::time::sleep(self.grace_period).await;
+    }
+
+    /// Start draining on SIGTERM or Ctrl+C
+    pub fn listen_for_signals(&self) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            shutdown_signal().await;
+            info!(
+                grace_period_secs = shutdown.grace_period.as_secs(),
+                "Draining in-flight scrapes"
+            );
+            shutdown.trigger();
+        });
+    }
+}
+
+/// Wait for shutdown signal
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to install signal handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {
+            info!("Received Ctrl+C, shutting down");
+        }
+        _ = terminate => {
+            info!("Received terminate signal, shutting down");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_trigger_and_deadline() {
+        let shutdown = Shutdown::new(Duration::from_millis(200));
+        assert!(!shutdown.is_draining());
+
+        let deadline = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.deadline().await }
+        });
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(!deadline.is_finished());
+
+        shutdown.trigger();
+        assert!(shutdown.is_draining());
+        shutdown.triggered().await;
+        assert!(!deadline.is_finished());
+
+        tokio::time::timeout(Duration::from_secs(5), deadline)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -0,0 +1,349 @@
+//! Snapshot cache
+//!
+//! Writes the metrics of every complete full scrape to a file, in the JSON
+//! form of `/metrics.json`. After a restart, `/metrics` serves the saved
+//! metrics while the first live scrape is in flight, so a slow JVM does not
+//! leave a gap in dashboards. Saved metrics are marked stale with
+//! `rjmx_exporter_snapshot_stale` and `rjmx_exporter_snapshot_age_seconds`.
+//!
+//! Snapshots older than `snapshot.max_age_seconds` are not served. Scrapes
+//! with errors are not saved, so a partial scrape never replaces a complete
+//! one.
+//!
+//! The file is written on the blocking thread pool, at most once per
+//! `snapshot.write_interval_seconds`. Scrapes completing while a write is in
+//! flight or within the interval are not saved.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::config::SnapshotConfig;
+use crate::transformer::{MetricType, PrometheusMetric};
+
+/// Snapshot file as written
+#[derive(Serialize)]
+struct SnapshotFile<'a> {
+    /// Seconds since the epoch when the scrape completed
+    saved_at: u64,
+    metrics: &'a [PrometheusMetric],
+}
+
+/// Snapshot file as read back
+#[derive(Deserialize)]
+struct SavedSnapshot {
+    saved_at: u64,
+    metrics: Vec<SavedMetric>,
+}
+
+/// Metric in the JSON form of `/metrics.json`
+#[derive(Deserialize)]
+struct SavedMetric {
+    name: String,
+    #[serde(rename = "type")]
+    metric_type: MetricType,
+    #[serde(default)]
+    help: Option<String>,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+    value: SavedValue,
+}
+
+/// Sample value; non-finite values are written as strings
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedValue {
+    Number(f64),
+    Special(String),
+}
+
+impl SavedMetric {
+    fn into_metric(self) -> Option<PrometheusMetric> {
+        let value = match self.value {
+            SavedValue::Number(value) => value,
+            SavedValue::Special(value) => match value.as_str() {
+                "NaN" => f64::NAN,
+                "+Inf" => f64::INFINITY,
+                "-Inf" => f64::NEG_INFINITY,
+                _ => return None,
+            },
+        };
+        Some(PrometheusMetric {
+            name: self.name,
+            metric_type: self.metric_type,
+            help: self.help,
+            labels: self.labels.into_iter().collect(),
+            value,
+            timestamp: None,
+            native_schema: None,
+        })
+    }
+}
+
+/// Metrics loaded from a snapshot file
+pub(super) struct Stale {
+    pub metrics: Vec<PrometheusMetric>,
+    /// Time since the snapshot was saved
+    pub age: Duration,
+    saved_at: SystemTime,
+}
+
+/// Snapshot file of the latest complete scrape
+pub struct Snapshot {
+    path: PathBuf,
+    /// Metrics loaded at startup, served until a live full scrape completes
+    loaded: RwLock<Option<(SystemTime, Vec<PrometheusMetric>)>>,
+    /// Set while a live scrape started to replace the loaded metrics runs
+    refreshing: AtomicBool,
+    /// Minimum time between two writes
+    write_interval: Duration,
+    /// Time the latest write started; held while claiming a write
+    last_write: Mutex<Option<Instant>>,
+    /// Set while a write is in flight
+    writing: AtomicBool,
+}
+
+impl Snapshot {
+    /// Load the snapshot at `config.path`, if it exists and is recent enough
+    pub fn load(config: &SnapshotConfig) -> Self {
+        let path = PathBuf::from(&config.path);
+        let loaded = match read(&path) {
+            Ok(Some((saved_at, metrics))) => {
+                let age = saved_at.elapsed().unwrap_or_default();
+                if age > Duration::from_secs(config.max_age_seconds) {
+                    info!(
+                        path = %path.display(),
+                        age_seconds = age.as_secs(),
+                        "Ignoring snapshot older than snapshot.max_age_seconds"
+                    );
+                    None
+                } else {
+                    info!(
+                        path = %path.display(),
+                        metrics = metrics.len(),
+                        age_seconds = age.as_secs(),
+                        "Loaded snapshot"
+                    );
+                    Some((saved_at, metrics))
+                }
+            }
+            Ok(None) => None,
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Failed to load snapshot");
+                None
+            }
+        };
+        Self {
+            path,
+            loaded: RwLock::new(loaded),
+            refreshing: AtomicBool::new(false),
+            write_interval: Duration::from_secs(config.write_interval_seconds),
+            last_write: Mutex::new(None),
+            writing: AtomicBool::new(false),
+        }
+    }
+
+    /// Loaded metrics, until a live full scrape has completed
+    pub(super) fn stale(&self) -> Option<Stale> {
+        let loaded = self.loaded.read().ok()?;
+        let (saved_at, metrics) = loaded.as_ref()?;
+        Some(Stale {
+            metrics: metrics.clone(),
+            age: saved_at.elapsed().unwrap_or_default(),
+            saved_at: *saved_at,
+        })
+    }
+
+    /// Claim the live scrape replacing the loaded metrics
+    ///
+    /// Returns `false` if another request already started it.
+    pub(super) fn start_refresh(&self) -> bool {
+        !self.refreshing.swap(true, Ordering::AcqRel)
+    }
+
+    /// Allow another refresh after the claimed one failed
+    pub(super) fn refresh_failed(&self) {
+        self.refreshing.store(false, Ordering::Release);
+    }
+
+    /// Record a live full scrape, saving its metrics if it had no errors
+    ///
+    /// Returns the write started on the blocking thread pool, if any.
+    pub(super) fn store(
+        self: &Arc<Self>,
+        metrics: &[PrometheusMetric],
+        errors: &[String],
+    ) -> Option<tokio::task::JoinHandle<()>> {
+        if let Ok(mut loaded) = self.loaded.write() {
+            *loaded = None;
+        }
+        if !errors.is_empty() {
+            debug!(
+                errors = errors.len(),
+                "Not saving snapshot of a scrape with errors"
+            );
+            return None;
+        }
+        if !self.claim_write() {
+            return None;
+        }
+
+        let snapshot = Arc::clone(self);
+        let metrics = metrics.to_vec();
+        Some(tokio::task::spawn_blocking(move || {
+            if let Err(e) = write(&snapshot.path, &metrics) {
+                warn!(path = %snapshot.path.display(), error = %e, "Failed to save snapshot");
+            }
+            snapshot.writing.store(false, Ordering::Release);
+        }))
+    }
+
+    /// Claim the next write, unless one is in flight or the last one started
+    /// less than `write_interval` ago
+    fn claim_write(&self) -> bool {
+        let Ok(mut last_write) = self.last_write.lock() else {
+            return false;
+        };
+        if last_write.is_some_and(|at| at.elapsed() < self.write_interval)
+            || self.writing.swap(true, Ordering::AcqRel)
+        {
+            return false;
+        }
+        *last_write = Some(Instant::now());
+        true
+    }
+}
+
+impl Stale {
+    /// Gauges marking the served metrics as stale
+    pub fn markers(&self) -> Vec<PrometheusMetric> {
+        let saved_at = self
+            .saved_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        vec![
+            PrometheusMetric::new("rjmx_exporter_snapshot_stale", 1.0)
+                .with_type(MetricType::Gauge)
+                .with_help("Whether the metrics are served from a snapshot saved before a restart"),
+            PrometheusMetric::new("rjmx_exporter_snapshot_age_seconds", self.age.as_secs_f64())
+                .with_type(MetricType::Gauge)
+                .with_help("Age of the served snapshot"),
+            PrometheusMetric::new("rjmx_exporter_snapshot_saved_timestamp_seconds", saved_at)
+                .with_type(MetricType::Gauge)
+                .with_help("Time the served snapshot was saved"),
+        ]
+    }
+}
+
+/// Read a snapshot file; `None` if it does not exist
+fn read(path: &Path) -> std::io::Result<Option<(SystemTime, Vec<PrometheusMetric>)>> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let snapshot: SavedSnapshot = serde_json::from_slice(&data)?;
+    let metrics = snapshot
+        .metrics
+        .into_iter()
+        .filter_map(SavedMetric::into_metric)
+        .collect();
+    Ok(Some((
+        UNIX_EPOCH + Duration::from_secs(snapshot.saved_at),
+        metrics,
+    )))
+}
+
+/// Write a snapshot file, replacing the previous one atomically
+fn write(path: &Path, metrics: &[PrometheusMetric]) -> std::io::Result<()> {
+    let saved_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let data = serde_json::to_vec(&SnapshotFile { saved_at, metrics })?;
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    std::fs::write(&temp, data)?;
+    std::fs::rename(&temp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(path: &Path, max_age_seconds: u64) -> SnapshotConfig {
+        SnapshotConfig {
+            path: path.display().to_string(),
+            max_age_seconds,
+            write_interval_seconds: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.json");
+        assert!(Snapshot::load(&config(&path, 60)).stale().is_none());
+
+        let metrics = vec![
+            PrometheusMetric::new("jvm_threads_current", 42.0)
+                .with_type(MetricType::Gauge)
+                .with_help("Current threads")
+                .with_label("instance", "app-1"),
+            PrometheusMetric::new("jvm_gc_pause_max_seconds", f64::NAN),
+        ];
+        let snapshot = Arc::new(Snapshot::load(&config(&path, 60)));
+        assert!(snapshot.store(&metrics, &["failed".to_string()]).is_none());
+        assert!(!path.exists());
+        snapshot.store(&metrics, &[]).unwrap().await.unwrap();
+
+        let loaded = Arc::new(Snapshot::load(&config(&path, 60)));
+        let stale = loaded.stale().unwrap();
+        assert_eq!(stale.metrics.len(), 2);
+        assert_eq!(stale.metrics[0].name, "jvm_threads_current");
+        assert_eq!(stale.metrics[0].metric_type, MetricType::Gauge);
+        assert_eq!(stale.metrics[0].help.as_deref(), Some("Current threads"));
+        assert_eq!(stale.metrics[0].labels.get("instance"), Some("app-1"));
+        assert_eq!(stale.metrics[0].value, 42.0);
+        assert!(stale.metrics[1].value.is_nan());
+        assert_eq!(stale.markers()[0].value, 1.0);
+
+        // A live scrape replaces the loaded metrics
+        assert!(loaded.start_refresh());
+        assert!(!loaded.start_refresh());
+        loaded.store(&metrics, &[]).unwrap().await.unwrap();
+        assert!(loaded.stale().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_write_interval() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.json");
+        let mut config = config(&path, 60);
+        config.write_interval_seconds = 3600;
+        let snapshot = Arc::new(Snapshot::load(&config));
+        let metrics = vec![PrometheusMetric::new("jvm_threads_current", 42.0)];
+
+        snapshot.store(&metrics, &[]).unwrap().await.unwrap();
+        assert!(path.exists());
+        // Within the interval the file is left alone
+        assert!(snapshot.store(&metrics, &[]).is_none());
+    }
+
+    #[test]
+    fn test_expired_and_corrupt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.json");
+        std::fs::write(&path, r#"{"saved_at": 1000, "metrics": []}"#).unwrap();
+        assert!(Snapshot::load(&config(&path, 60)).stale().is_none());
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(Snapshot::load(&config(&path, 60)).stale().is_none());
+    }
+}
@@ -0,0 +1,258 @@
+//! StatsD push sink
+//!
+//! Pushes the metrics of a full scrape to a StatsD server every
+//! `statsd.interval_seconds`, for pipelines without a Prometheus server.
+//! Samples are sent over UDP as DogStatsD gauges (`name:value|g|#k:v`),
+//! packed into datagrams of at most [`MAX_DATAGRAM`] bytes. Counters are sent
+//! as gauges too, since a StatsD counter is an increment rather than a total.
+//!
+//! With `statsd.delta`, a [`DeltaTracker`] leaves out series whose value did
+//! not change since the previous push, with a full push every
+//! `statsd.full_resync_seconds`. With background collection, the latest
+//! background results are pushed instead of scraping again.
+
+use std::time::{Duration, Instant};
+
+use axum::http::HeaderMap;
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use super::handlers::{assemble, run_scrape, scrape_targets};
+use super::AppState;
+use crate::collector::MBeanSelection;
+use crate::config::StatsdConfig;
+use crate::transformer::{DeltaTracker, PrometheusMetric};
+
+/// Largest datagram sent, safe for the common 1500-byte MTU
+const MAX_DATAGRAM: usize = 1432;
+
+/// Periodic push of scraped metrics to StatsD
+pub struct StatsdSink {
+    address: String,
+    interval: Duration,
+    /// Last pushed values, with `statsd.delta`
+    delta: Option<DeltaTracker>,
+}
+
+impl StatsdSink {
+    /// Create a sink from configuration
+    pub fn new(config: &StatsdConfig) -> Self {
+        Self {
+            address: config.address.clone(),
+            interval: Duration::from_secs(config.interval_seconds),
+            delta: config
+                .delta
+                .then(|| DeltaTracker::new(Duration::from_secs(config.full_resync_seconds))),
+        }
+    }
+
+    /// Push on every interval until shutdown starts
+    pub fn spawn(self, state: AppState) -> JoinHandle<()> {
+        info!(
+            address = %self.address,
+            interval_seconds = self.interval.as_secs(),
+            delta = self.delta.is_some(),
+            "StatsD push enabled"
+        );
+        tokio::spawn(async move {
+            let socket = match UdpSocket::bind("0.0.0.0:0").await {
+                Ok(socket) => socket,
+                Err(e) => {
+                    warn!(error = %e, "Failed to open StatsD socket");
+                    return;
+                }
+            };
+            let mut ticker = tokio::time::interval(self.interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = state.shutdown.triggered() => return,
+                }
+                if let Err(e) = self.push(&state, &socket).await {
+                    warn!(address = %self.address, error = %e, "Failed to push to StatsD");
+                }
+            }
+        })
+    }
+
+    /// Scrape and send one push, returning the number of series sent
+    async fn push(&self, state: &AppState, socket: &UdpSocket) -> std::io::Result<usize> {
+        let Some(metrics) = self.scrape(state).await else {
+            return Ok(0);
+        };
+        let metrics = match &self.delta {
+            Some(tracker) => {
+                let delta = tracker.delta(metrics);
+                debug!(
+                    full = delta.full,
+                    unchanged = delta.unchanged,
+                    "Computed StatsD delta"
+                );
+                delta.metrics
+            }
+            None => metrics,
+        };
+
+        let datagrams = datagrams(&metrics);
+        for datagram in &datagrams {
+            socket.send_to(datagram.as_bytes(), &self.address).await?;
+        }
+        debug!(
+            series = metrics.len(),
+            datagrams = datagrams.len(),
+            "Pushed metrics to StatsD"
+        );
+        Ok(metrics.len())
+    }
+
+    /// Metrics of a full scrape, or `None` if it timed out
+    async fn scrape(&self, state: &AppState) -> Option<Vec<PrometheusMetric>> {
+        let headers = HeaderMap::new();
+        if let Some(background) = &state.background {
+            let scrapes = background.results(state)?;
+            return Some(assemble(state, scrapes, Instant::now(), &headers).metrics);
+        }
+
+        let discovered = state.file_sd.as_ref().map(|file_sd| file_sd.targets());
+        let targets = scrape_targets(
+            state,
+            discovered.as_deref().map(Vec::as_slice),
+            &state.collector,
+            &state.engine,
+        );
+        run_scrape(
+            state,
+            &targets,
+            None,
+            &MBeanSelection::all(),
+            None,
+            Some(self.interval),
+            &headers,
+        )
+        .await
+        .ok()
+        .map(|output| output.metrics)
+    }
+}
+
+/// DogStatsD line of a sample, or `None` for non-finite values
+fn line(metric: &PrometheusMetric) -> Option<String> {
+    if !metric.value.is_finite() {
+        return None;
+    }
+    let mut line = format!("{}:{}|g", metric.name, metric.value);
+    let mut tags = metric.labels.iter().peekable();
+    if tags.peek().is_some() {
+        line.push_str("|#");
+        for (i, (name, value)) in tags.enumerate() {
+            if i > 0 {
+                line.push(',');
+            }
+            // `,` and `|` separate tags and fields
+            line.push_str(name);
+            line.push(':');
+            line.extend(
+                value
+                    .chars()
+                    .map(|c| if c == ',' || c == '|' { '_' } else { c }),
+            );
+        }
+    }
+    Some(line)
+}
+
+/// Newline-separated lines packed into datagrams of at most [`MAX_DATAGRAM`] bytes
+fn datagrams(metrics: &[PrometheusMetric]) -> Vec<String> {
+    let mut datagrams = Vec::new();
+    let mut current = String::new();
+    for line in metrics.iter().filter_map(line) {
+        if !current.is_empty() && current.len() + 1 + line.len() > MAX_DATAGRAM {
+            datagrams.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(&line);
+    }
+    if !current.is_empty() {
+        datagrams.push(current);
+    }
+    datagrams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_line() {
+        let metric = PrometheusMetric::new("jvm_memory_pool_used_bytes", 1024.0)
+            .with_label("pool", "G1,Eden|Space");
+        assert_eq!(
+            line(&metric).unwrap(),
+            "jvm_memory_pool_used_bytes:1024|g|#pool:G1_Eden_Space"
+        );
+        assert_eq!(line(&PrometheusMetric::new("up", 1.0)).unwrap(), "up:1|g");
+        assert!(line(&PrometheusMetric::new("ratio", f64::NAN)).is_none());
+    }
+
+    #[test]
+    fn test_datagrams() {
+        let metrics: Vec<PrometheusMetric> = (0..200)
+            .map(|i| PrometheusMetric::new("test_series", 1.0).with_label("id", i.to_string()))
+            .collect();
+        let datagrams = datagrams(&metrics);
+        assert!(datagrams.len() > 1);
+        assert!(datagrams.iter().all(|d| d.len() <= MAX_DATAGRAM));
+        let lines: usize = datagrams.iter().map(|d| d.lines().count()).sum();
+        assert_eq!(lines, 200);
+    }
+
+    #[tokio::test]
+    async fn test_push_delta() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"mbean": "test.statsd:type=Runtime", "type": "read"},
+                "value": {"Uptime": 5},
+                "status": 200
+            })))
+            .mount(&mock_server)
+            .await;
+        let receiver = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut config = Config::from_yaml(&format!(
+            r#"
+statsd:
+  address: "{}"
+  delta: true
+collect:
+  - object_name: "test.statsd:type=Runtime"
+rules:
+  - pattern: "test\\.statsd<type=Runtime><Uptime>"
+    name: test_statsd_uptime
+"#,
+            receiver.local_addr().unwrap()
+        ))
+        .unwrap();
+        config.jolokia.url = format!("{}/jolokia", mock_server.uri());
+        let sink = StatsdSink::new(config.statsd.as_ref().unwrap());
+        let state = super::super::tests::test_state(config);
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        // The first push is full, the second leaves the unchanged series out
+        assert_eq!(sink.push(&state, &socket).await.unwrap(), 1);
+        let mut buf = [0; MAX_DATAGRAM];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"test_statsd_uptime:5|g");
+        assert_eq!(sink.push(&state, &socket).await.unwrap(), 0);
+    }
+}
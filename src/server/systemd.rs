@@ -0,0 +1,116 @@
+//! systemd socket activation
+//!
+//! When started by a systemd `.socket` unit, the listening socket is passed
+//! as file descriptor 3, announced through `LISTEN_PID` and `LISTEN_FDS`.
+//! Serving from the inherited socket lets systemd start the exporter on the
+//! first scrape and keep queueing connections while the service restarts.
+//!
+//! Like `sd_listen_fds(1)`, the `LISTEN_*` variables are removed once the
+//! socket is taken, so child processes do not try to take it as well.
+
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Result;
+use tracing::{info, warn};
+
+/// Set once the inherited socket has been taken, so it is wrapped only once
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Take the listening socket passed by systemd, if the process was socket-activated
+///
+/// Only the first passed socket is used. Returns `None` if no socket was
+/// passed to this process.
+pub fn take_listener() -> Result<Option<TcpListener>> {
+    let count = passed_fds(
+        std::env::var("LISTEN_PID").ok().as_deref(),
+        std::env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    );
+    if count == 0 || TAKEN.swap(true, Ordering::SeqCst) {
+        return Ok(None);
+    }
+    unset_listen_env();
+    if count > 1 {
+        warn!(count, "systemd passed multiple sockets, using the first");
+    }
+
+    let listener = listener_from_fd()?;
+    listener.set_nonblocking(true)?;
+    info!(address = %listener.local_addr()?, "Using socket passed by systemd");
+    Ok(Some(listener))
+}
+
+/// Number of sockets passed to the process `pid`
+///
+/// Returns 0 when the variables are missing, malformed, or addressed to
+/// another process (e.g. inherited from a parent).
+fn passed_fds(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> usize {
+    if listen_pid.and_then(|p| p.trim().parse::<u32>().ok()) != Some(pid) {
+        return 0;
+    }
+    listen_fds.and_then(|n| n.trim().parse().ok()).unwrap_or(0)
+}
+
+/// Remove the variables announcing the passed sockets
+fn unset_listen_env() {
+    for name in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        std::env::remove_var(name);
+    }
+}
+
+#[cfg(unix)]
+fn listener_from_fd() -> Result<TcpListener> {
+    use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+
+    /// First descriptor passed by systemd (`SD_LISTEN_FDS_START`)
+    const LISTEN_FDS_START: RawFd = 3;
+
+    // SAFETY: LISTEN_PID confirmed that systemd passed descriptor 3 to this
+    // process, and TAKEN guarantees it is only taken over once.
+    let fd = unsafe { OwnedFd::from_raw_fd(LISTEN_FDS_START) };
+    let listener = TcpListener::from(fd);
+    listener.local_addr().map_err(|e| {
+        anyhow::anyhow!(
+            "Socket passed by systemd is not a TCP listener: {}. Use ListenStream with an address or port.",
+            e
+        )
+    })?;
+    Ok(listener)
+}
+
+#[cfg(not(unix))]
+fn listener_from_fd() -> Result<TcpListener> {
+    anyhow::bail!("systemd socket activation is only supported on Unix")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passed_fds() {
+        assert_eq!(passed_fds(Some("42"), Some("1"), 42), 1);
+        assert_eq!(passed_fds(Some("42"), Some("2"), 42), 2);
+        // Addressed to another process
+        assert_eq!(passed_fds(Some("41"), Some("1"), 42), 0);
+        // Missing or malformed variables
+        assert_eq!(passed_fds(None, Some("1"), 42), 0);
+        assert_eq!(passed_fds(Some("42"), None, 42), 0);
+        assert_eq!(passed_fds(Some("42"), Some("many"), 42), 0);
+    }
+
+    #[test]
+    fn test_unset_listen_env() {
+        // LISTEN_PID is left alone, so it cannot activate a concurrent test
+        std::env::set_var("LISTEN_FDNAMES", "rjmx-exporter.socket");
+        unset_listen_env();
+        assert!(std::env::var_os("LISTEN_FDNAMES").is_none());
+    }
+
+    #[test]
+    fn test_take_listener_without_activation() {
+        // The test process is never socket-activated
+        assert!(take_listener().unwrap().is_none());
+    }
+}
@@ -0,0 +1,175 @@
+//! Runtime target management at `/api/v1/targets`
+//!
+//! With `targets_api`, `GET` lists the scraped targets, `POST` adds one and
+//! `DELETE ?target=<name or URL>` removes one added through the API. In
+//! background collection, listed targets include their next scrape.
+//! Targets listed in `file_sd` files are managed through their files.
+//! The routes sit behind `server.auth` like the debug endpoints.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use super::file_sd::{DiscoveredTarget, FileSd, TargetChangeError};
+use super::AppState;
+use crate::collector::format_rfc3339;
+
+/// Body of `POST /api/v1/targets`
+#[derive(Debug, Deserialize)]
+pub struct AddTarget {
+    /// `host:port` or Jolokia URL, as in a `file_sd` file
+    target: String,
+    /// Labels of the target; `__scheme__`, `__metrics_path__`,
+    /// `__scrape_interval__` and `__scrape_schedule__` work as in `file_sd` files
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+}
+
+/// Query of `DELETE /api/v1/targets`
+#[derive(Debug, Deserialize)]
+pub struct RemoveTarget {
+    /// Target name (`host:port`) or URL
+    target: String,
+}
+
+/// Target as listed by the API
+#[derive(Debug, Serialize)]
+pub struct TargetInfo {
+    /// Value of the `target` label
+    name: String,
+    url: String,
+    labels: BTreeMap<String, String>,
+    /// Interval in background collection, from `__scrape_interval__`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interval_seconds: Option<u64>,
+    /// Cron schedule in background collection, from `__scrape_schedule__`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schedule: Option<String>,
+    /// Next background scrape, as an RFC 3339 UTC timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_run: Option<String>,
+    /// `api` if added through the API, `file_sd` if listed in a file
+    source: &'static str,
+}
+
+impl TargetInfo {
+    fn new(name: String, target: &DiscoveredTarget, source: &'static str) -> Self {
+        Self {
+            name,
+            url: target.url.clone(),
+            labels: target.labels.clone(),
+            interval_seconds: target.interval.map(|interval| interval.as_secs()),
+            schedule: target.schedule.clone().map(String::from),
+            next_run: None,
+            source,
+        }
+    }
+
+    /// Add the next background scrape of the target, if one is scheduled
+    fn with_next_run(mut self, state: &AppState) -> Self {
+        self.next_run = state
+            .background
+            .as_ref()
+            .and_then(|background| background.next_run(&self.name))
+            .map(format_rfc3339);
+        self
+    }
+}
+
+/// List the scraped targets
+pub async fn list(State(state): State<AppState>) -> Response {
+    let Some(targets) = targets(&state) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let api = targets.api_targets();
+    let listed: Vec<TargetInfo> = targets
+        .targets()
+        .iter()
+        .map(|target| {
+            let source = if api.contains(&target.target) {
+                "api"
+            } else {
+                "file_sd"
+            };
+            TargetInfo::new(target.name.clone(), &target.target, source).with_next_run(&state)
+        })
+        .collect();
+    Json(listed).into_response()
+}
+
+/// Add a target, scraped from the next scrape on
+pub async fn add(State(state): State<AppState>, Json(request): Json<AddTarget>) -> Response {
+    let Some(targets) = targets(&state) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if let Some(name) = request.labels.keys().find(|name| !is_label_name(name)) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid label name '{}'", name),
+        )
+            .into_response();
+    }
+    let target = DiscoveredTarget::new(&request.target, &request.labels);
+    // Building the target compiles the rules; keep it off the runtime threads
+    match tokio::task::spawn_blocking(move || targets.add(target)).await {
+        Ok(Ok(added)) => (
+            StatusCode::CREATED,
+            Json(TargetInfo::new(added.name.clone(), &added.target, "api")),
+        )
+            .into_response(),
+        Ok(Err(e)) => change_error(e),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Remove a target added through the API
+pub async fn remove(
+    State(state): State<AppState>,
+    Query(request): Query<RemoveTarget>,
+) -> Response {
+    let Some(targets) = targets(&state) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    match targets.remove(&request.target) {
+        Ok(removed) => {
+            let name = super::handlers::sanitize_url_for_label(&removed.url);
+            Json(TargetInfo::new(name, &removed, "api")).into_response()
+        }
+        Err(e) => change_error(e),
+    }
+}
+
+fn targets(state: &AppState) -> Option<Arc<FileSd>> {
+    state
+        .config
+        .targets_api
+        .as_ref()
+        .and(state.file_sd.as_ref())
+        .map(Arc::clone)
+}
+
+fn change_error(error: TargetChangeError) -> Response {
+    let status = match error {
+        TargetChangeError::Invalid(_) => StatusCode::BAD_REQUEST,
+        TargetChangeError::Exists(_) | TargetChangeError::FromFile(_) => StatusCode::CONFLICT,
+        TargetChangeError::NotFound(_) => StatusCode::NOT_FOUND,
+        TargetChangeError::Persist(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, error.to_string()).into_response()
+}
+
+/// Prometheus label name, or a `__` label read like in `file_sd` files
+fn is_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -0,0 +1,190 @@
+//! TLS certificate reloading
+//!
+//! Certificates issued by cert-manager (or any other rotation tool) are
+//! replaced on disk before they expire. The reloader periodically checks the
+//! certificate and key files and swaps them into the running server's
+//! [`RustlsConfig`] without dropping connections. The served certificate's
+//! expiry is exported as `rjmx_tls_cert_expiry_timestamp`.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use axum_server::tls_rustls::RustlsConfig;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use x509_parser::pem::parse_x509_pem;
+
+use crate::metrics::internal_metrics;
+
+/// Modification time and size of a file, used to detect rotation
+type FileStamp = (SystemTime, u64);
+
+/// Select the process-wide rustls crypto provider
+///
+/// Both `ring` (via reqwest) and `aws-lc-rs` (via axum-server) are compiled
+/// in, so rustls cannot choose a default on its own. Safe to call repeatedly.
+pub fn install_crypto_provider() {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+}
+
+/// Read the expiry (notAfter) of the first certificate in a PEM document
+///
+/// Returns the expiry as a Unix timestamp in seconds.
+pub fn cert_expiry_timestamp(pem: &[u8]) -> Result<i64, String> {
+    let (_, pem) = parse_x509_pem(pem).map_err(|e| format!("invalid PEM: {}", e))?;
+    let cert = pem
+        .parse_x509()
+        .map_err(|e| format!("invalid certificate: {}", e))?;
+    Ok(cert.validity().not_after.timestamp())
+}
+
+/// Record the expiry of the certificate file in internal metrics
+pub async fn record_cert_expiry(cert_path: &Path) {
+    let result = match tokio::fs::read(cert_path).await {
+        Ok(pem) => cert_expiry_timestamp(&pem),
+        Err(e) => Err(e.to_string()),
+    };
+
+    match result {
+        Ok(expiry) => internal_metrics().set_tls_cert_expiry(expiry as f64),
+        Err(e) => warn!(
+            cert_file = %cert_path.display(),
+            error = %e,
+            "Failed to read TLS certificate expiry"
+        ),
+    }
+}
+
+/// Watches certificate files and reloads them into a running server
+pub struct CertReloader {
+    config: RustlsConfig,
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    stamps: Option<(FileStamp, FileStamp)>,
+}
+
+impl CertReloader {
+    /// Create a reloader for the files currently loaded into `config`
+    pub async fn new(
+        config: RustlsConfig,
+        cert_path: impl Into<PathBuf>,
+        key_path: impl Into<PathBuf>,
+    ) -> Self {
+        let cert_path = cert_path.into();
+        let key_path = key_path.into();
+        let stamps = file_stamps(&cert_path, &key_path).await;
+        Self {
+            config,
+            cert_path,
+            key_path,
+            stamps,
+        }
+    }
+
+    /// Reload the certificate if either file changed since the last load
+    ///
+    /// Returns `true` if a new certificate was loaded. A failed reload keeps
+    /// serving the previous certificate and is retried on the next check,
+    /// which covers rotations that write the cert and key non-atomically.
+    pub async fn check(&mut self) -> bool {
+        let Some(stamps) = file_stamps(&self.cert_path, &self.key_path).await else {
+            warn!(
+                cert_file = %self.cert_path.display(),
+                key_file = %self.key_path.display(),
+                "TLS certificate files are not readable, keeping current certificate"
+            );
+            return false;
+        };
+
+        if self.stamps == Some(stamps) {
+            return false;
+        }
+
+        if let Err(e) = self
+            .config
+            .reload_from_pem_file(&self.cert_path, &self.key_path)
+            .await
+        {
+            warn!(error = %e, "Failed to reload TLS certificate, keeping current certificate");
+            return false;
+        }
+
+        self.stamps = Some(stamps);
+        internal_metrics().record_tls_reload();
+        record_cert_expiry(&self.cert_path).await;
+        info!(cert_file = %self.cert_path.display(), "Reloaded TLS certificate");
+        true
+    }
+
+    /// Check for rotated certificates every `interval` in the background
+    pub fn spawn(mut self, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // The first tick completes immediately; the files were just loaded
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                self.check().await;
+            }
+        })
+    }
+}
+
+/// Stamps of the certificate and key files, or `None` if either is unreadable
+async fn file_stamps(cert_path: &Path, key_path: &Path) -> Option<(FileStamp, FileStamp)> {
+    Some((file_stamp(cert_path).await?, file_stamp(key_path).await?))
+}
+
+async fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = tokio::fs::metadata(path).await.ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CERT1: &str = include_str!("../../tests/fixtures/tls/cert1.pem");
+    const KEY1: &str = include_str!("../../tests/fixtures/tls/key1.pem");
+    const CERT2: &str = include_str!("../../tests/fixtures/tls/cert2.pem");
+    const KEY2: &str = include_str!("../../tests/fixtures/tls/key2.pem");
+
+    #[test]
+    fn test_cert_expiry_timestamp() {
+        // notAfter=Sep 23 07:48:15 2126 GMT
+        assert_eq!(cert_expiry_timestamp(CERT1.as_bytes()), Ok(4_945_823_295));
+        assert!(cert_expiry_timestamp(b"not a certificate").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reload_on_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let cert_path = dir.path().join("cert.pem");
+        let key_path = dir.path().join("key.pem");
+        std::fs::write(&cert_path, CERT1).unwrap();
+        std::fs::write(&key_path, KEY1).unwrap();
+
+        install_crypto_provider();
+        let config = RustlsConfig::from_pem_file(&cert_path, &key_path)
+            .await
+            .unwrap();
+        let mut reloader = CertReloader::new(config, &cert_path, &key_path).await;
+
+        // Unchanged files are not reloaded
+        assert!(!reloader.check().await);
+
+        // A rotated certificate is picked up
+        std::fs::write(&cert_path, CERT2).unwrap();
+        std::fs::write(&key_path, KEY2).unwrap();
+        assert!(reloader.check().await);
+        assert_eq!(
+            internal_metrics().tls_cert_expiry(),
+            Some(cert_expiry_timestamp(CERT2.as_bytes()).unwrap() as f64)
+        );
+
+        // A broken rotation keeps the current certificate
+        std::fs::write(&cert_path, "garbage").unwrap();
+        assert!(!reloader.check().await);
+    }
+}
@@ -0,0 +1,196 @@
+//! OpenTelemetry trace export (`otlp` feature)
+//!
+//! Spans recorded with `tracing` (one trace per `/metrics` request, with
+//! children for the Jolokia fetch, parsing, transform and formatting) are
+//! exported over OTLP/HTTP when `tracing.otlp_endpoint` is set. W3C trace
+//! context is read from incoming requests and passed on to Jolokia, so a
+//! scrape can be followed from Prometheus into the JVM.
+//!
+//! Logging is initialized before the configuration is loaded, so the
+//! subscriber gets a [`DeferredLayer`] that forwards to the OpenTelemetry
+//! layer once [`init`] has built it.
+
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use anyhow::{Context as _, Result};
+use axum::http::HeaderMap;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use reqwest::RequestBuilder;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Span};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::Registry;
+
+use crate::config::TracingConfig;
+
+/// OpenTelemetry layer, set once by [`init`]
+static OTEL_LAYER: OnceLock<OpenTelemetryLayer<Registry, SdkTracer>> = OnceLock::new();
+
+/// Layer forwarding to the OpenTelemetry layer once it is set
+///
+/// Unlike `tracing_subscriber::reload`, this forwards `downcast_raw`, which
+/// [`OpenTelemetrySpanExt`] needs to read and set span contexts. That is
+/// sound because the value in a `OnceLock` never moves or changes.
+pub(crate) struct DeferredLayer;
+
+impl Layer<Registry> for DeferredLayer {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, Registry>) {
+        if let Some(layer) = OTEL_LAYER.get() {
+            layer.on_new_span(attrs, id, ctx);
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, Registry>) {
+        if let Some(layer) = OTEL_LAYER.get() {
+            layer.on_record(id, values, ctx);
+        }
+    }
+
+    fn on_follows_from(&self, id: &Id, follows: &Id, ctx: Context<'_, Registry>) {
+        if let Some(layer) = OTEL_LAYER.get() {
+            layer.on_follows_from(id, follows, ctx);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, Registry>) {
+        if let Some(layer) = OTEL_LAYER.get() {
+            layer.on_event(event, ctx);
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, Registry>) {
+        if let Some(layer) = OTEL_LAYER.get() {
+            layer.on_enter(id, ctx);
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, Registry>) {
+        if let Some(layer) = OTEL_LAYER.get() {
+            layer.on_exit(id, ctx);
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, Registry>) {
+        if let Some(layer) = OTEL_LAYER.get() {
+            layer.on_close(id, ctx);
+        }
+    }
+
+    unsafe fn downcast_raw(&self, id: TypeId) -> Option<*const ()> {
+        if id == TypeId::of::<Self>() {
+            return Some(self as *const _ as *const ());
+        }
+        // SAFETY: the layer lives in a static and is never replaced
+        OTEL_LAYER
+            .get()
+            .and_then(|layer| unsafe { layer.downcast_raw(id) })
+    }
+}
+
+/// Start exporting spans if `tracing.otlp_endpoint` is set
+///
+/// Returns the tracer provider, which must be shut down on exit to flush
+/// buffered spans. Spans only reach it through the subscriber installed by
+/// [`init_logging`](crate::init_logging).
+pub fn init(config: &TracingConfig) -> Result<Option<SdkTracerProvider>> {
+    let Some(endpoint) = &config.otlp_endpoint else {
+        return Ok(None);
+    };
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+        .context("Failed to build OTLP span exporter")?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            config.sample_ratio,
+        ))))
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build(),
+        )
+        .build();
+
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    let tracer = provider.tracer("rjmx-exporter");
+    OTEL_LAYER
+        .set(tracing_opentelemetry::layer().with_tracer(tracer))
+        .map_err(|_| anyhow::anyhow!("OTLP export is already initialized"))?;
+
+    Ok(Some(provider))
+}
+
+/// Continue the trace of an incoming request, if it carries one
+pub fn set_parent(span: &Span, headers: &HeaderMap) {
+    let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(headers))
+    });
+    let _ = span.set_parent(parent);
+}
+
+/// Add the current trace context to an outgoing request
+pub fn inject(request: RequestBuilder) -> RequestBuilder {
+    let context = Span::current().context();
+    let mut carrier = HashMap::new();
+    opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut carrier)
+    });
+    carrier.into_iter().fold(request, |request, (name, value)| {
+        request.header(name, value)
+    })
+}
+
+/// Reads trace context from HTTP headers
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::propagation::TextMapPropagator;
+    use opentelemetry::trace::TraceContextExt;
+
+    #[test]
+    fn test_header_extractor() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                .parse()
+                .unwrap(),
+        );
+
+        let extractor = HeaderExtractor(&headers);
+        assert_eq!(extractor.keys(), vec!["traceparent"]);
+
+        let context = TraceContextPropagator::new().extract(&extractor);
+        assert_eq!(
+            context.span().span_context().trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+    }
+
+    #[test]
+    fn test_init_without_endpoint() {
+        assert!(init(&TracingConfig::default()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,266 @@
+//! Golden-file regression testing for rule sets
+//!
+//! Runs recorded Jolokia responses through the same transform pipeline the
+//! server uses (rules, relabeling, renames, limits, formatting) and compares
+//! the exposition output against a checked-in golden file. Downstream users
+//! can protect their own configurations against rule regressions:
+//!
+//! ```ignore
+//! use rjmx_exporter::testing;
+//!
+//! #[test]
+//! fn kafka_rules() {
+//!     testing::assert_fixture_matches(
+//!         "config.yaml",
+//!         "tests/golden/kafka.json",
+//!         "tests/golden/kafka.prom",
+//!     );
+//! }
+//! ```
+//!
+//! Fixtures hold a single Jolokia response or a bulk response array, as
+//! returned by `curl -d '<request>' http://host:8778/jolokia`. Run the tests
+//! with `RJMX_UPDATE_GOLDEN=1` to write golden files from the current output.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::collector::{parse_bulk_response, parse_response, JolokiaResponse};
+use crate::config::Config;
+use crate::transformer::PrometheusFormatter;
+
+/// Environment variable that switches golden assertions to update mode
+pub const UPDATE_ENV: &str = "RJMX_UPDATE_GOLDEN";
+
+/// Lines of unchanged context shown around each difference
+const DIFF_CONTEXT: usize = 2;
+
+/// Load and validate a configuration file
+pub fn load_config(path: impl AsRef<Path>) -> Result<Config> {
+    let path = path.as_ref();
+    Config::load(path).with_context(|| format!("failed to load config {}", path.display()))
+}
+
+/// Load recorded Jolokia responses from a JSON file
+pub fn load_fixture(path: impl AsRef<Path>) -> Result<Vec<JolokiaResponse>> {
+    let path = path.as_ref();
+    let json = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read fixture {}", path.display()))?;
+    parse_fixture(&json).with_context(|| format!("invalid fixture {}", path.display()))
+}
+
+/// Parse a single Jolokia response or a bulk response array
+pub fn parse_fixture(json: &str) -> Result<Vec<JolokiaResponse>> {
+    if json.trim_start().starts_with('[') {
+        Ok(parse_bulk_response(json)?)
+    } else {
+        Ok(vec![parse_response(json)?])
+    }
+}
+
+/// Run responses through the configured pipeline and format the result
+///
+/// Series are sorted by name and labels so the output is stable across runs.
+pub fn render(config: &Config, responses: &[JolokiaResponse]) -> Result<String> {
+    let engine = crate::server::build_engine(config)?;
+    let mut metrics = engine.transform(responses)?;
+
+    metrics.sort_by_cached_key(|metric| (metric.name.clone(), metric.labels.clone()));
+
+    Ok(PrometheusFormatter::new().format(&metrics))
+}
+
+/// Compare output against a golden file, panicking with a diff on mismatch
+///
+/// With `RJMX_UPDATE_GOLDEN` set, the golden file is (re)written instead.
+pub fn assert_golden(actual: &str, golden: impl AsRef<Path>) {
+    let golden = golden.as_ref();
+
+    if update_requested() {
+        if let Some(parent) = golden.parent() {
+            std::fs::create_dir_all(parent).unwrap_or_else(|e| {
+                panic!("failed to create {}: {}", parent.display(), e);
+            });
+        }
+        std::fs::write(golden, actual).unwrap_or_else(|e| {
+            panic!("failed to write golden file {}: {}", golden.display(), e);
+        });
+        return;
+    }
+
+    let expected = std::fs::read_to_string(golden).unwrap_or_else(|e| {
+        panic!(
+            "failed to read golden file {}: {} (run with {}=1 to create it)",
+            golden.display(),
+            e,
+            UPDATE_ENV
+        );
+    });
+
+    if expected != actual {
+        panic!(
+            "output does not match golden file {} (run with {}=1 to update)\n{}",
+            golden.display(),
+            UPDATE_ENV,
+            diff(&expected, actual)
+        );
+    }
+}
+
+/// Render a fixture with a config file and compare it against a golden file
+pub fn assert_fixture_matches(
+    config: impl AsRef<Path>,
+    fixture: impl AsRef<Path>,
+    golden: impl AsRef<Path>,
+) {
+    let config = load_config(config).unwrap_or_else(|e| panic!("{:#}", e));
+    let responses = load_fixture(fixture).unwrap_or_else(|e| panic!("{:#}", e));
+    let actual = render(&config, &responses).unwrap_or_else(|e| panic!("{:#}", e));
+    assert_golden(&actual, golden);
+}
+
+fn update_requested() -> bool {
+    std::env::var(UPDATE_ENV).is_ok_and(|v| !v.is_empty() && v != "0")
+}
+
+/// Line diff between expected and actual output
+///
+/// Removed lines are prefixed with `-`, added lines with `+`, and unchanged
+/// context with two spaces. Distant hunks are separated by `...`.
+pub fn diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+
+    // Longest common subsequence lengths of the suffixes
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines: Vec<(char, &str)> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(('-', old[i]));
+            i += 1;
+        } else {
+            lines.push(('+', new[j]));
+            j += 1;
+        }
+    }
+
+    let changed: Vec<usize> = (0..lines.len()).filter(|&k| lines[k].0 != ' ').collect();
+    let mut output = String::new();
+    let mut last_shown: Option<usize> = None;
+    for (k, (tag, line)) in lines.iter().enumerate() {
+        let near_change = changed
+            .iter()
+            .any(|&c| k + DIFF_CONTEXT >= c && k <= c + DIFF_CONTEXT);
+        if !near_change {
+            continue;
+        }
+        if last_shown.is_some_and(|last| k > last + 1) {
+            output.push_str("...\n");
+        }
+        output.push(*tag);
+        output.push(' ');
+        output.push_str(line);
+        output.push('\n');
+        last_shown = Some(k);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = r#"[
+        {
+            "request": {"mbean": "java.lang:type=Memory", "attribute": "HeapMemoryUsage", "type": "read"},
+            "value": {"used": 100, "max": 400},
+            "status": 200
+        },
+        {
+            "request": {"mbean": "java.lang:type=Threading", "attribute": "ThreadCount", "type": "read"},
+            "value": 12,
+            "status": 200
+        }
+    ]"#;
+
+    fn config() -> Config {
+        Config::from_yaml(
+            r#"
+rules:
+  - pattern: "java\\.lang<type=Memory><HeapMemoryUsage><(\\w+)>"
+    name: "jvm_memory_heap_$1_bytes"
+    type: gauge
+  - pattern: "java\\.lang<type=Threading><ThreadCount>"
+    name: "jvm_threads_current"
+    type: gauge
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_render_is_sorted() {
+        let responses = parse_fixture(FIXTURE).unwrap();
+        let output = render(&config(), &responses).unwrap();
+        assert_eq!(
+            output,
+            "# TYPE jvm_memory_heap_max_bytes gauge\n\
+             jvm_memory_heap_max_bytes 400\n\
+             # TYPE jvm_memory_heap_used_bytes gauge\n\
+             jvm_memory_heap_used_bytes 100\n\
+             # TYPE jvm_threads_current gauge\n\
+             jvm_threads_current 12\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_fixture_single() {
+        let single =
+            r#"{"request": {"mbean": "a:b=c", "type": "read"}, "value": 1, "status": 200}"#;
+        assert_eq!(parse_fixture(single).unwrap().len(), 1);
+        assert_eq!(parse_fixture(FIXTURE).unwrap().len(), 2);
+        assert!(parse_fixture("{").is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(diff("a\nb\n", "a\nb\n"), "");
+
+        let expected = "1\n2\n3\n4\n5\n6\n7\n8\n";
+        let actual = "1\n2\n3\n4\nfive\n6\n7\n8\n";
+        assert_eq!(diff(expected, actual), "  3\n  4\n- 5\n+ five\n  6\n  7\n");
+
+        let d = diff("a\nb\nc\nd\ne\nf\ng\nh\n", "x\nb\nc\nd\ne\nf\ng\ny\n");
+        assert!(d.contains("- a\n+ x\n"));
+        assert!(d.contains("...\n"));
+        assert!(d.ends_with("- h\n+ y\n"));
+    }
+
+    #[test]
+    fn test_assert_golden() {
+        let dir = tempfile::tempdir().unwrap();
+        let golden = dir.path().join("out.prom");
+        std::fs::write(&golden, "metric 1\n").unwrap();
+
+        assert_golden("metric 1\n", &golden);
+        let result = std::panic::catch_unwind(|| assert_golden("metric 2\n", &golden));
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("- metric 1\n+ metric 2\n"));
+    }
+}
@@ -0,0 +1,331 @@
+//! Cached rule values
+//!
+//! Rules marked `cache: true` (jmx_exporter compatible) match attributes
+//! that never change at runtime, such as versions and max sizes. Their
+//! values are collected once, stored here, and replayed on every later
+//! scrape. The cache lives inside the [`TransformEngine`], so rebuilding the
+//! engine on reload starts from an empty cache.
+//!
+//! Besides replaying values, the cache lets the collector skip Jolokia reads
+//! for MBeans (or individual attributes) whose metrics all come from cached
+//! rules.
+//!
+//! With a TTL, values older than the TTL are neither replayed nor used to
+//! skip reads. The attribute is read again, and if its MBean has disappeared
+//! the series is no longer exported.
+//!
+//! [`TransformEngine`]: super::TransformEngine
+
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use crate::collector::MBeanQuery;
+
+use super::engine::PrometheusMetric;
+
+/// Thread-safe store of metrics produced by caching rules
+#[derive(Debug, Default)]
+pub struct ValueCache {
+    state: RwLock<CacheState>,
+    /// Age after which a value is collected again (never if unset)
+    ttl: Option<Duration>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    /// Cached metrics, their MBean and the time they were stored, keyed by
+    /// flattened MBean name
+    metrics: HashMap<String, CachedMetric>,
+    /// Top-level attributes with cached values and the time they were stored, per MBean
+    cached: HashMap<String, HashMap<String, Instant>>,
+    /// Top-level attributes that produced uncached metrics, per MBean
+    live: HashMap<String, HashSet<String>>,
+}
+
+#[derive(Debug)]
+struct CachedMetric {
+    metric: PrometheusMetric,
+    mbean: String,
+    stored_at: Instant,
+}
+
+impl ValueCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expire values older than `ttl`
+    pub fn with_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Check whether a value stored at `stored_at` is still served
+    fn is_fresh(&self, stored_at: Instant) -> bool {
+        self.ttl.is_none_or(|ttl| stored_at.elapsed() < ttl)
+    }
+
+    /// Check whether a flattened name already has a fresh cached value
+    pub fn contains(&self, flattened: &str) -> bool {
+        self.state
+            .read()
+            .map(|s| {
+                s.metrics
+                    .get(flattened)
+                    .is_some_and(|cached| self.is_fresh(cached.stored_at))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Store a metric produced by a caching rule
+    pub fn insert(&self, mbean: &str, attribute: &str, flattened: &str, metric: PrometheusMetric) {
+        let Ok(mut state) = self.state.write() else {
+            tracing::error!("RwLock poisoned while caching value");
+            return;
+        };
+        let now = Instant::now();
+        state.metrics.insert(
+            flattened.to_string(),
+            CachedMetric {
+                metric,
+                mbean: mbean.to_string(),
+                stored_at: now,
+            },
+        );
+        state
+            .cached
+            .entry(mbean.to_string())
+            .or_default()
+            .insert(attribute.to_string(), now);
+    }
+
+    /// Record that an attribute produced a metric from a non-caching rule
+    ///
+    /// Such attributes must keep being read on every scrape.
+    pub fn mark_live(&self, mbean: &str, attribute: &str) {
+        let Ok(mut state) = self.state.write() else {
+            tracing::error!("RwLock poisoned while tracking live attribute");
+            return;
+        };
+        state
+            .live
+            .entry(mbean.to_string())
+            .or_default()
+            .insert(attribute.to_string());
+    }
+
+    /// All fresh cached metrics, ordered by flattened name
+    pub fn metrics(&self) -> Vec<PrometheusMetric> {
+        self.metrics_of(|_| true)
+    }
+
+    /// Fresh cached metrics of the MBeans accepted by `selected`, ordered by
+    /// flattened name
+    pub fn metrics_of(&self, selected: impl Fn(&str) -> bool) -> Vec<PrometheusMetric> {
+        let Ok(state) = self.state.read() else {
+            return Vec::new();
+        };
+        let mut entries: Vec<_> = state
+            .metrics
+            .iter()
+            .filter(|(_, cached)| self.is_fresh(cached.stored_at) && selected(&cached.mbean))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        entries
+            .into_iter()
+            .map(|(_, cached)| cached.metric.clone())
+            .collect()
+    }
+
+    /// Remove expired values, returning how many metrics were dropped
+    ///
+    /// Expired values are already ignored; this only frees their memory.
+    pub fn remove_expired(&self) -> usize {
+        if self.ttl.is_none() {
+            return 0;
+        }
+        let Ok(mut state) = self.state.write() else {
+            tracing::error!("RwLock poisoned while expiring cached values");
+            return 0;
+        };
+
+        let before = state.metrics.len();
+        state
+            .metrics
+            .retain(|_, cached| self.is_fresh(cached.stored_at));
+        state.cached.retain(|_, attributes| {
+            attributes.retain(|_, stored_at| self.is_fresh(*stored_at));
+            !attributes.is_empty()
+        });
+        before - state.metrics.len()
+    }
+
+    /// Number of cached metrics
+    pub fn len(&self) -> usize {
+        self.state.read().map(|s| s.metrics.len()).unwrap_or(0)
+    }
+
+    /// Check if the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop all cached values
+    pub fn clear(&self) {
+        if let Ok(mut state) = self.state.write() {
+            *state = CacheState::default();
+        }
+    }
+
+    /// Reduce a query to the attributes that still need to be read
+    ///
+    /// Returns `None` when everything the query would return is served from
+    /// the cache. Wildcard patterns are always read in full, since the set
+    /// of matching MBeans can change between scrapes.
+    pub fn plan_query(&self, query: &MBeanQuery) -> Option<MBeanQuery> {
+        if is_pattern(&query.object_name) {
+            return Some(query.clone());
+        }
+
+        let Ok(state) = self.state.read() else {
+            return Some(query.clone());
+        };
+        let Some(cached) = state.cached.get(&query.object_name) else {
+            return Some(query.clone());
+        };
+        let live = state.live.get(&query.object_name);
+        let is_cached = |attribute: &String| {
+            cached
+                .get(attribute)
+                .is_some_and(|stored_at| self.is_fresh(*stored_at))
+        };
+
+        match &query.attributes {
+            Some(attributes) if !attributes.is_empty() => {
+                let remaining: Vec<String> = attributes
+                    .iter()
+                    .filter(|a| !is_cached(a) || live.is_some_and(|l| l.contains(*a)))
+                    .cloned()
+                    .collect();
+                if remaining.is_empty() {
+                    None
+                } else {
+                    let mut planned = query.clone();
+                    planned.attributes = Some(remaining);
+                    Some(planned)
+                }
+            }
+            // Reading all attributes: skip only if nothing uncached was ever
+            // produced and no cached value has expired
+            _ if live.is_none() && cached.keys().all(is_cached) => None,
+            _ => Some(query.clone()),
+        }
+    }
+}
+
+/// Check whether an ObjectName is a pattern (contains wildcards)
+fn is_pattern(object_name: &str) -> bool {
+    object_name.contains('*') || object_name.contains('?')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_replay() {
+        let cache = ValueCache::new();
+        assert!(cache.is_empty());
+
+        cache.insert(
+            "java.lang:type=Runtime",
+            "StartTime",
+            "java.lang<type=Runtime><StartTime>",
+            PrometheusMetric::new("jvm_start_time", 1.0),
+        );
+
+        assert!(cache.contains("java.lang<type=Runtime><StartTime>"));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.metrics()[0].name, "jvm_start_time");
+        assert_eq!(cache.metrics_of(|mbean| mbean.contains("Runtime")).len(), 1);
+        assert!(cache
+            .metrics_of(|mbean| mbean.contains("Memory"))
+            .is_empty());
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_plan_query() {
+        let cache = ValueCache::new();
+        cache.insert(
+            "java.lang:type=Memory",
+            "HeapMemoryUsage",
+            "java.lang<type=Memory><HeapMemoryUsage><max>",
+            PrometheusMetric::new("jvm_heap_max", 1.0),
+        );
+
+        // Unknown MBeans are read as-is
+        let other = MBeanQuery::new("java.lang:type=Threading");
+        assert_eq!(cache.plan_query(&other), Some(other.clone()));
+
+        // Fully cached MBean is skipped
+        let all = MBeanQuery::new("java.lang:type=Memory");
+        assert_eq!(cache.plan_query(&all), None);
+
+        // Cached attributes are removed from explicit attribute lists
+        let explicit = MBeanQuery::new("java.lang:type=Memory").with_attributes(vec![
+            "HeapMemoryUsage".to_string(),
+            "NonHeapMemoryUsage".to_string(),
+        ]);
+        assert_eq!(
+            cache.plan_query(&explicit),
+            Some(
+                MBeanQuery::new("java.lang:type=Memory")
+                    .with_attributes(vec!["NonHeapMemoryUsage".to_string()])
+            )
+        );
+
+        // An attribute that also feeds uncached rules keeps being read
+        cache.mark_live("java.lang:type=Memory", "HeapMemoryUsage");
+        assert_eq!(cache.plan_query(&all), Some(all.clone()));
+
+        // Patterns are always read
+        let pattern = MBeanQuery::new("java.lang:type=Memory*");
+        assert_eq!(cache.plan_query(&pattern), Some(pattern.clone()));
+    }
+
+    #[test]
+    fn test_ttl() {
+        let cache = ValueCache::new().with_ttl(Some(Duration::ZERO));
+        cache.insert(
+            "java.lang:type=Runtime",
+            "StartTime",
+            "java.lang<type=Runtime><StartTime>",
+            PrometheusMetric::new("jvm_start_time", 1.0),
+        );
+
+        // Expired values are neither replayed nor used to skip reads
+        assert!(!cache.contains("java.lang<type=Runtime><StartTime>"));
+        assert!(cache.metrics().is_empty());
+        let query = MBeanQuery::new("java.lang:type=Runtime");
+        assert_eq!(cache.plan_query(&query), Some(query.clone()));
+
+        assert_eq!(cache.remove_expired(), 1);
+        assert!(cache.is_empty());
+
+        // Without a TTL values never expire
+        let cache = ValueCache::new();
+        cache.insert(
+            "java.lang:type=Runtime",
+            "StartTime",
+            "java.lang<type=Runtime><StartTime>",
+            PrometheusMetric::new("jvm_start_time", 1.0),
+        );
+        assert_eq!(cache.remove_expired(), 0);
+        assert_eq!(cache.metrics().len(), 1);
+    }
+}
@@ -0,0 +1,246 @@
+//! TYPE/HELP conflict resolution
+//!
+//! The exposition format has a single TYPE and HELP line per metric name, but
+//! two rules (or a rename) can produce the same name with different types or
+//! help texts. Conflicts are resolved per scrape according to a
+//! [`ConflictPolicy`], so every sample of a name carries the same metadata:
+//!
+//! - `first_wins` (default): the first sample's type and help apply to all.
+//! - `untyped`: a conflicting type becomes `untyped`, conflicting help is dropped.
+//! - `error`: the scrape fails.
+
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::engine::PrometheusMetric;
+use super::rules::MetricType;
+
+/// How to resolve samples of one metric name with different TYPE or HELP
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Use the type and help of the first sample
+    #[default]
+    FirstWins,
+    /// Fail the scrape
+    Error,
+    /// Fall back to `untyped` and drop conflicting help
+    Untyped,
+}
+
+impl ConflictPolicy {
+    /// Returns the configuration string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConflictPolicy::FirstWins => "first_wins",
+            ConflictPolicy::Error => "error",
+            ConflictPolicy::Untyped => "untyped",
+        }
+    }
+}
+
+impl std::fmt::Display for ConflictPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Metadata conflict found for one metric name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricConflict {
+    /// Metric name
+    pub name: String,
+    /// Samples disagree on the type
+    pub type_conflict: bool,
+    /// Samples disagree on the help text
+    pub help_conflict: bool,
+}
+
+impl std::fmt::Display for MetricConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let what = match (self.type_conflict, self.help_conflict) {
+            (true, true) => "type and help",
+            (true, false) => "type",
+            _ => "help",
+        };
+        write!(f, "{} has conflicting {}", self.name, what)
+    }
+}
+
+/// Find metric names whose samples disagree on type or help
+///
+/// Conflicts are returned in order of the name's first sample.
+pub fn find_conflicts(metrics: &[PrometheusMetric]) -> Vec<MetricConflict> {
+    // First sample of each name, and the index of its conflict if any
+    let mut first: HashMap<&str, (&PrometheusMetric, Option<usize>)> = HashMap::new();
+    let mut conflicts: Vec<MetricConflict> = Vec::new();
+
+    for metric in metrics {
+        if let Some((reference, slot)) = first.get_mut(metric.name.as_str()) {
+            let type_conflict = metric.metric_type != reference.metric_type;
+            let help_conflict = metric.help != reference.help;
+            if !type_conflict && !help_conflict {
+                continue;
+            }
+            match slot {
+                Some(index) => {
+                    let conflict = &mut conflicts[*index];
+                    conflict.type_conflict |= type_conflict;
+                    conflict.help_conflict |= help_conflict;
+                }
+                None => {
+                    *slot = Some(conflicts.len());
+                    conflicts.push(MetricConflict {
+                        name: metric.name.clone(),
+                        type_conflict,
+                        help_conflict,
+                    });
+                }
+            }
+        } else {
+            first.insert(&metric.name, (metric, None));
+        }
+    }
+
+    conflicts
+}
+
+/// Resolve conflicts in place, giving every sample of a name the same metadata
+///
+/// Returns the conflicts found. With [`ConflictPolicy::Error`] the metrics are
+/// left untouched and the caller is expected to fail the scrape.
+pub fn resolve_conflicts(
+    metrics: &mut [PrometheusMetric],
+    policy: ConflictPolicy,
+) -> Vec<MetricConflict> {
+    let conflicts = find_conflicts(metrics);
+    if conflicts.is_empty() || policy == ConflictPolicy::Error {
+        return conflicts;
+    }
+
+    let by_name: HashMap<&str, &MetricConflict> =
+        conflicts.iter().map(|c| (c.name.as_str(), c)).collect();
+    let mut resolved: HashMap<String, (MetricType, Option<String>)> = HashMap::new();
+
+    for metric in metrics.iter_mut() {
+        let Some(conflict) = by_name.get(metric.name.as_str()) else {
+            continue;
+        };
+        let (metric_type, help) =
+            resolved
+                .entry(metric.name.clone())
+                .or_insert_with(|| match policy {
+                    ConflictPolicy::Untyped => (
+                        if conflict.type_conflict {
+                            MetricType::Untyped
+                        } else {
+                            metric.metric_type
+                        },
+                        if conflict.help_conflict {
+                            None
+                        } else {
+                            metric.help.clone()
+                        },
+                    ),
+                    _ => (metric.metric_type, metric.help.clone()),
+                });
+        metric.metric_type = *metric_type;
+        metric.help.clone_from(help);
+    }
+
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(name: &str, metric_type: MetricType, help: &str) -> PrometheusMetric {
+        PrometheusMetric::new(name, 1.0)
+            .with_type(metric_type)
+            .with_help(help)
+    }
+
+    fn metrics() -> Vec<PrometheusMetric> {
+        vec![
+            sample("requests", MetricType::Counter, "Requests"),
+            sample("heap", MetricType::Gauge, "Heap"),
+            sample("requests", MetricType::Gauge, "Requests"),
+            sample("heap", MetricType::Gauge, "Heap bytes"),
+            sample("requests", MetricType::Counter, "Request count"),
+        ]
+    }
+
+    #[test]
+    fn test_find_conflicts() {
+        let conflicts = find_conflicts(&metrics());
+        assert_eq!(
+            conflicts,
+            vec![
+                MetricConflict {
+                    name: "requests".to_string(),
+                    type_conflict: true,
+                    help_conflict: true,
+                },
+                MetricConflict {
+                    name: "heap".to_string(),
+                    type_conflict: false,
+                    help_conflict: true,
+                },
+            ]
+        );
+        assert_eq!(conflicts[1].to_string(), "heap has conflicting help");
+
+        let consistent = vec![
+            sample("a", MetricType::Gauge, "A"),
+            sample("a", MetricType::Gauge, "A"),
+        ];
+        assert!(find_conflicts(&consistent).is_empty());
+    }
+
+    #[test]
+    fn test_first_wins() {
+        let mut metrics = metrics();
+        let conflicts = resolve_conflicts(&mut metrics, ConflictPolicy::FirstWins);
+        assert_eq!(conflicts.len(), 2);
+        for metric in metrics.iter().filter(|m| m.name == "requests") {
+            assert_eq!(metric.metric_type, MetricType::Counter);
+            assert_eq!(metric.help.as_deref(), Some("Requests"));
+        }
+        assert!(find_conflicts(&metrics).is_empty());
+    }
+
+    #[test]
+    fn test_untyped_fallback() {
+        let mut metrics = metrics();
+        resolve_conflicts(&mut metrics, ConflictPolicy::Untyped);
+        for metric in metrics.iter().filter(|m| m.name == "requests") {
+            assert_eq!(metric.metric_type, MetricType::Untyped);
+            assert!(metric.help.is_none());
+        }
+        // Only the help of heap conflicts, so its type is kept
+        for metric in metrics.iter().filter(|m| m.name == "heap") {
+            assert_eq!(metric.metric_type, MetricType::Gauge);
+            assert!(metric.help.is_none());
+        }
+    }
+
+    #[test]
+    fn test_error_policy_leaves_metrics() {
+        let mut metrics = metrics();
+        let conflicts = resolve_conflicts(&mut metrics, ConflictPolicy::Error);
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(metrics[2].metric_type, MetricType::Gauge);
+    }
+
+    #[test]
+    fn test_policy_parsing() {
+        let policy: ConflictPolicy = serde_yaml::from_str("first_wins").unwrap();
+        assert_eq!(policy, ConflictPolicy::FirstWins);
+        let policy: ConflictPolicy = serde_yaml::from_str("untyped").unwrap();
+        assert_eq!(policy, ConflictPolicy::Untyped);
+        assert!(serde_yaml::from_str::<ConflictPolicy>("last_wins").is_err());
+    }
+}
@@ -0,0 +1,292 @@
+//! Counter monotonicity checks
+//!
+//! Prometheus treats every decrease of a counter as a reset, so an attribute
+//! mapped to `type: counter` that is really a gauge (a queue size, a current
+//! connection count) silently produces bogus `rate()` results. The tracker
+//! remembers the last value of every counter series and reports decreases,
+//! so such rules can be spotted and retyped.
+//!
+//! A restarted JVM legitimately resets its counters and is reported too.
+//! Decreases are passed on unchanged by default, which Prometheus treats as
+//! a reset. With [`CounterResetMode::Monotonic`] the value before the reset
+//! is added to every later value of the series instead, so the exported
+//! counter keeps going up across restarts.
+//!
+//! The tracker lives inside the [`TransformEngine`], so rebuilding the
+//! engine on reload starts from scratch. With a TTL, series that were not
+//! seen for longer than the TTL are forgotten, so MBeans that disappear do
+//! not accumulate.
+//!
+//! [`TransformEngine`]: super::TransformEngine
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::engine::PrometheusMetric;
+use super::rules::MetricType;
+
+/// What to export when a counter series decreases
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CounterResetMode {
+    /// Export the value read from the MBean untouched
+    #[default]
+    Raw,
+    /// Add the values before each reset, so the series never decreases
+    Monotonic,
+}
+
+impl CounterResetMode {
+    /// Returns the configuration string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CounterResetMode::Raw => "raw",
+            CounterResetMode::Monotonic => "monotonic",
+        }
+    }
+}
+
+impl std::fmt::Display for CounterResetMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A counter series whose value went down between two scrapes
+#[derive(Debug, Clone, PartialEq)]
+pub struct CounterDecrease {
+    /// Metric name of the series
+    pub name: String,
+    /// Series identity, e.g. `requests_total{method="GET"}`
+    pub series: String,
+    /// Value at the previous scrape
+    pub previous: f64,
+    /// Value at this scrape
+    pub current: f64,
+}
+
+/// State of one counter series
+#[derive(Debug)]
+struct SeriesState {
+    /// Value read at the last scrape containing the series
+    value: f64,
+    /// Sum of the values before each reset
+    offset: f64,
+    /// Time of the last scrape containing the series
+    seen_at: Instant,
+}
+
+/// Last seen value of every counter series
+#[derive(Debug, Default)]
+pub struct CounterTracker {
+    /// State of each series, keyed by series identity
+    last: Mutex<HashMap<String, SeriesState>>,
+    /// Time after which an unseen series is forgotten (never if unset)
+    ttl: Option<Duration>,
+}
+
+impl CounterTracker {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget series not seen for longer than `ttl`
+    pub fn with_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Record the counters of a scrape, returning the series that decreased
+    ///
+    /// Non-counter metrics are ignored. With [`CounterResetMode::Monotonic`],
+    /// counter values are raised by the values before earlier resets.
+    pub fn check(
+        &self,
+        metrics: &mut [PrometheusMetric],
+        mode: CounterResetMode,
+    ) -> Vec<CounterDecrease> {
+        let mut decreases = Vec::new();
+        let Ok(mut last) = self.last.lock() else {
+            tracing::error!("Mutex poisoned while checking counters");
+            return decreases;
+        };
+
+        let now = Instant::now();
+        if let Some(ttl) = self.ttl {
+            last.retain(|_, state| now.duration_since(state.seen_at) <= ttl);
+        }
+
+        for metric in metrics {
+            if metric.metric_type != MetricType::Counter {
+                continue;
+            }
+            let series = series_key(metric);
+            let value = metric.value;
+            match last.get_mut(&series) {
+                Some(state) => {
+                    if value < state.value {
+                        state.offset += state.value;
+                        decreases.push(CounterDecrease {
+                            name: metric.name.clone(),
+                            series,
+                            previous: state.value,
+                            current: value,
+                        });
+                    }
+                    state.value = value;
+                    state.seen_at = now;
+                    if mode == CounterResetMode::Monotonic {
+                        metric.value += state.offset;
+                    }
+                }
+                None => {
+                    last.insert(
+                        series,
+                        SeriesState {
+                            value,
+                            offset: 0.0,
+                            seen_at: now,
+                        },
+                    );
+                }
+            }
+        }
+
+        decreases
+    }
+
+    /// Number of tracked counter series
+    pub fn len(&self) -> usize {
+        self.last.lock().map(|last| last.len()).unwrap_or(0)
+    }
+
+    /// Check whether no counter series has been seen yet
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Series identity: metric name plus labels sorted by name
+pub(super) fn series_key(metric: &PrometheusMetric) -> String {
+    let mut key = metric.name.clone();
+    if !metric.labels.is_empty() {
+        key.push('{');
+        for (i, (name, value)) in metric.labels.iter().enumerate() {
+            if i > 0 {
+                key.push(',');
+            }
+            let _ = write!(key, "{}={:?}", name, value);
+        }
+        key.push('}');
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counter(value: f64) -> PrometheusMetric {
+        PrometheusMetric::new("requests_total", value)
+            .with_type(MetricType::Counter)
+            .with_label("method", "GET")
+    }
+
+    #[test]
+    fn test_increasing_counter() {
+        let tracker = CounterTracker::new();
+        assert!(tracker
+            .check(&mut [counter(1.0)], CounterResetMode::Raw)
+            .is_empty());
+        assert!(tracker
+            .check(&mut [counter(1.0)], CounterResetMode::Raw)
+            .is_empty());
+        assert!(tracker
+            .check(&mut [counter(5.0)], CounterResetMode::Raw)
+            .is_empty());
+        assert_eq!(tracker.len(), 1);
+    }
+
+    #[test]
+    fn test_decreasing_counter() {
+        let tracker = CounterTracker::new();
+        tracker.check(&mut [counter(10.0)], CounterResetMode::Raw);
+
+        let decreases = tracker.check(&mut [counter(3.0)], CounterResetMode::Raw);
+        assert_eq!(
+            decreases,
+            vec![CounterDecrease {
+                name: "requests_total".to_string(),
+                series: "requests_total{method=\"GET\"}".to_string(),
+                previous: 10.0,
+                current: 3.0,
+            }]
+        );
+
+        // The lower value becomes the new baseline
+        assert!(tracker
+            .check(&mut [counter(4.0)], CounterResetMode::Raw)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_monotonic_mode() {
+        let tracker = CounterTracker::new();
+        let check = |value| {
+            let mut metrics = [counter(value)];
+            let decreases = tracker.check(&mut metrics, CounterResetMode::Monotonic);
+            (metrics[0].value, decreases.len())
+        };
+
+        assert_eq!(check(10.0), (10.0, 0));
+        assert_eq!(check(12.0), (12.0, 0));
+        // Restart: the value before the reset is carried over
+        assert_eq!(check(2.0), (14.0, 1));
+        assert_eq!(check(5.0), (17.0, 0));
+        assert_eq!(check(1.0), (18.0, 1));
+    }
+
+    #[test]
+    fn test_series_are_tracked_separately() {
+        let tracker = CounterTracker::new();
+        let post = |value| counter(value).with_label("method", "POST");
+        tracker.check(&mut [counter(10.0), post(1.0)], CounterResetMode::Raw);
+        assert!(tracker
+            .check(&mut [counter(11.0), post(2.0)], CounterResetMode::Raw)
+            .is_empty());
+        assert_eq!(tracker.len(), 2);
+    }
+
+    #[test]
+    fn test_ttl_forgets_unseen_series() {
+        let tracker = CounterTracker::new().with_ttl(Some(Duration::ZERO));
+        tracker.check(&mut [counter(10.0)], CounterResetMode::Raw);
+        std::thread::sleep(Duration::from_millis(1));
+
+        // The series was forgotten, so the lower value is a new baseline
+        let other = PrometheusMetric::new("other_total", 1.0).with_type(MetricType::Counter);
+        assert!(tracker
+            .check(&mut [other], CounterResetMode::Raw)
+            .is_empty());
+        assert!(tracker
+            .check(&mut [counter(3.0)], CounterResetMode::Raw)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_gauges_are_ignored() {
+        let tracker = CounterTracker::new();
+        let gauge = |value| PrometheusMetric::new("queue_size", value).with_type(MetricType::Gauge);
+        tracker.check(&mut [gauge(10.0)], CounterResetMode::Raw);
+        assert!(tracker
+            .check(&mut [gauge(1.0)], CounterResetMode::Raw)
+            .is_empty());
+        assert!(tracker.is_empty());
+    }
+}
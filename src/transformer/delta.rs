@@ -0,0 +1,173 @@
+//! Delta tracking for push sinks
+//!
+//! A sink that pushes every series on every interval (remote_write, StatsD)
+//! mostly resends values that did not change. The tracker remembers the
+//! last value sent for every series and passes on only the ones that
+//! changed. Every `full_resync` interval it passes everything and starts
+//! over, so a receiver that lost state catches up and series that
+//! disappeared are forgotten.
+//!
+//! Delta output is meant for push sinks only: Prometheus marks a series
+//! stale when a scrape omits it, so `/metrics` always serves full snapshots.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::counters::series_key;
+use super::engine::PrometheusMetric;
+
+/// Series of one interval that need to be sent
+#[derive(Debug, Clone)]
+pub struct Delta {
+    /// Series that changed, or all series on a full resync
+    pub metrics: Vec<PrometheusMetric>,
+    /// Whether this interval is a full resync
+    pub full: bool,
+    /// Series left out because their value did not change
+    pub unchanged: usize,
+}
+
+/// Last sent value of every series
+#[derive(Debug)]
+pub struct DeltaTracker {
+    state: Mutex<DeltaState>,
+    /// Time between full resyncs
+    full_resync: Duration,
+}
+
+#[derive(Debug, Default)]
+struct DeltaState {
+    /// Value bits of each series at the last interval that sent it
+    last: HashMap<String, u64>,
+    /// Time of the last full resync
+    resynced_at: Option<Instant>,
+}
+
+impl DeltaTracker {
+    /// Create a tracker that resyncs fully every `full_resync`
+    pub fn new(full_resync: Duration) -> Self {
+        Self {
+            state: Mutex::new(DeltaState::default()),
+            full_resync,
+        }
+    }
+
+    /// Record a snapshot, returning the series to send
+    pub fn delta(&self, metrics: Vec<PrometheusMetric>) -> Delta {
+        self.delta_at(metrics, Instant::now())
+    }
+
+    fn delta_at(&self, metrics: Vec<PrometheusMetric>, now: Instant) -> Delta {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        let full = state
+            .resynced_at
+            .is_none_or(|at| now.saturating_duration_since(at) >= self.full_resync);
+        if full {
+            state.last = metrics
+                .iter()
+                .map(|metric| (series_key(metric), metric.value.to_bits()))
+                .collect();
+            state.resynced_at = Some(now);
+            return Delta {
+                metrics,
+                full: true,
+                unchanged: 0,
+            };
+        }
+
+        let total = metrics.len();
+        let changed: Vec<PrometheusMetric> = metrics
+            .into_iter()
+            .filter(|metric| {
+                // Compare bits, so a NaN that stays NaN is unchanged
+                let bits = metric.value.to_bits();
+                state.last.insert(series_key(metric), bits) != Some(bits)
+            })
+            .collect();
+
+        Delta {
+            unchanged: total - changed.len(),
+            metrics: changed,
+            full: false,
+        }
+    }
+
+    /// Number of tracked series
+    pub fn len(&self) -> usize {
+        self.state.lock().map(|state| state.last.len()).unwrap_or(0)
+    }
+
+    /// Check whether no series has been sent yet
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gauge(pool: &str, value: f64) -> PrometheusMetric {
+        PrometheusMetric::new("jvm_memory_pool_used_bytes", value).with_label("pool", pool)
+    }
+
+    fn pools(delta: &Delta) -> Vec<&str> {
+        delta
+            .metrics
+            .iter()
+            .filter_map(|metric| metric.labels.get("pool"))
+            .collect()
+    }
+
+    #[test]
+    fn test_only_changed_series() {
+        let tracker = DeltaTracker::new(Duration::from_secs(300));
+        let start = Instant::now();
+
+        let first = tracker.delta_at(vec![gauge("eden", 1.0), gauge("old", 2.0)], start);
+        assert!(first.full);
+        assert_eq!(first.metrics.len(), 2);
+
+        let second = tracker.delta_at(
+            vec![
+                gauge("eden", 5.0),
+                gauge("old", 2.0),
+                gauge("survivor", 0.0),
+            ],
+            start + Duration::from_secs(15),
+        );
+        assert!(!second.full);
+        assert_eq!(pools(&second), vec!["eden", "survivor"]);
+        assert_eq!(second.unchanged, 1);
+        assert_eq!(tracker.len(), 3);
+
+        let nan = tracker.delta_at(
+            vec![gauge("eden", f64::NAN)],
+            start + Duration::from_secs(30),
+        );
+        assert_eq!(nan.metrics.len(), 1);
+        let nan = tracker.delta_at(
+            vec![gauge("eden", f64::NAN)],
+            start + Duration::from_secs(45),
+        );
+        assert!(nan.metrics.is_empty());
+    }
+
+    #[test]
+    fn test_full_resync() {
+        let tracker = DeltaTracker::new(Duration::from_secs(60));
+        let start = Instant::now();
+        tracker.delta_at(vec![gauge("eden", 1.0), gauge("old", 2.0)], start);
+
+        let unchanged = tracker.delta_at(vec![gauge("eden", 1.0)], start + Duration::from_secs(30));
+        assert!(unchanged.metrics.is_empty());
+
+        // Everything is resent, and the vanished series is forgotten
+        let resync = tracker.delta_at(vec![gauge("eden", 1.0)], start + Duration::from_secs(60));
+        assert!(resync.full);
+        assert_eq!(pools(&resync), vec!["eden"]);
+        assert_eq!(tracker.len(), 1);
+    }
+}
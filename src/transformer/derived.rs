@@ -0,0 +1,180 @@
+//! Derived metrics
+//!
+//! Computes new series from the metrics of a scrape, such as a heap
+//! utilization ratio from the used and maximum heap sizes, without a
+//! recording rule in Prometheus. Derived metrics are evaluated after
+//! relabeling and renames, so expressions refer to metrics by their exposed
+//! names.
+//!
+//! Series are matched by labels: a derived series is produced for every
+//! label set that all referenced metrics have, and carries those labels.
+//! Later derived metrics may refer to earlier ones.
+//!
+//! # Example Configuration (YAML)
+//!
+//! ```yaml
+//! derived_metrics:
+//!   - name: jvm_memory_heap_utilization_ratio
+//!     expr: jvm_memory_heap_used_bytes / jvm_memory_heap_max_bytes
+//!     help: "Fraction of the maximum heap in use"
+//! ```
+
+use std::collections::HashMap;
+
+use super::engine::PrometheusMetric;
+use super::expr::Expr;
+use super::labels::Labels;
+use super::rules::MetricType;
+
+/// Metric computed from other metrics of the same scrape
+#[derive(Debug, Clone)]
+pub struct DerivedMetric {
+    /// Name of the derived metric
+    pub name: String,
+    /// Expression over metric names
+    pub expr: Expr,
+    /// Type of the derived metric
+    pub metric_type: MetricType,
+    /// Help text
+    pub help: Option<String>,
+}
+
+impl DerivedMetric {
+    /// Create a gauge computed by `expr`
+    pub fn new(name: impl Into<String>, expr: Expr) -> Self {
+        Self {
+            name: name.into(),
+            expr,
+            metric_type: MetricType::Gauge,
+            help: None,
+        }
+    }
+
+    /// Set the metric type
+    pub fn with_type(mut self, metric_type: MetricType) -> Self {
+        self.metric_type = metric_type;
+        self
+    }
+
+    /// Set the help text
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Evaluate the metric over `metrics`, one series per matching label set
+    fn evaluate(&self, metrics: &[PrometheusMetric]) -> Vec<PrometheusMetric> {
+        let names = self.expr.metrics();
+        let Some(&first) = names.first() else {
+            return Vec::new();
+        };
+
+        // Value of each referenced series; the first sample of a series wins
+        let mut values: HashMap<&str, HashMap<&Labels, f64>> = HashMap::new();
+        let mut label_sets = Vec::new();
+        for metric in metrics {
+            let Some(&name) = names.iter().find(|&&name| name == metric.name) else {
+                continue;
+            };
+            let series = values.entry(name).or_default();
+            if !series.contains_key(&metric.labels) {
+                series.insert(&metric.labels, metric.value);
+                if name == first {
+                    label_sets.push(&metric.labels);
+                }
+            }
+        }
+
+        label_sets
+            .into_iter()
+            .filter_map(|labels| {
+                let value = self.expr.eval_metrics(&|name| {
+                    values
+                        .get(name)
+                        .and_then(|series| series.get(labels))
+                        .copied()
+                })?;
+                Some(PrometheusMetric {
+                    name: self.name.clone(),
+                    metric_type: self.metric_type,
+                    help: self.help.clone(),
+                    labels: labels.clone(),
+                    value,
+                    timestamp: None,
+                    native_schema: None,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Append the series of each derived metric, in order
+pub fn apply_derived_metrics(derived: &[DerivedMetric], metrics: &mut Vec<PrometheusMetric>) {
+    for metric in derived {
+        let series = metric.evaluate(metrics);
+        metrics.extend(series);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn derived(name: &str, expr: &str) -> DerivedMetric {
+        DerivedMetric::new(name, Expr::parse_metrics(expr).unwrap())
+    }
+
+    #[test]
+    fn test_ratio_by_labels() {
+        let mut metrics = vec![
+            PrometheusMetric::new("pool_used", 1.0).with_label("pool", "eden"),
+            PrometheusMetric::new("pool_used", 3.0).with_label("pool", "old"),
+            PrometheusMetric::new("pool_used", 5.0).with_label("pool", "survivor"),
+            PrometheusMetric::new("pool_max", 4.0).with_label("pool", "old"),
+            PrometheusMetric::new("pool_max", 4.0).with_label("pool", "eden"),
+        ];
+        apply_derived_metrics(
+            &[derived("pool_utilization_ratio", "pool_used / pool_max").with_help("Pool usage")],
+            &mut metrics,
+        );
+
+        let derived: Vec<_> = metrics[5..].iter().collect();
+        assert_eq!(derived.len(), 2);
+        assert_eq!(derived[0].name, "pool_utilization_ratio");
+        assert_eq!(derived[0].labels.get("pool"), Some("eden"));
+        assert_eq!(derived[0].value, 0.25);
+        assert_eq!(derived[0].metric_type, MetricType::Gauge);
+        assert_eq!(derived[0].help.as_deref(), Some("Pool usage"));
+        assert_eq!(derived[1].labels.get("pool"), Some("old"));
+        assert_eq!(derived[1].value, 0.75);
+    }
+
+    #[test]
+    fn test_chained_and_missing() {
+        let mut metrics = vec![
+            PrometheusMetric::new("heap_used", 2.0),
+            PrometheusMetric::new("nonheap_used", 1.0),
+        ];
+        apply_derived_metrics(
+            &[
+                derived("memory_used", "heap_used + nonheap_used"),
+                derived("memory_used_kibibytes", "memory_used / 1024"),
+                derived("heap_ratio", "heap_used / heap_max"),
+            ],
+            &mut metrics,
+        );
+
+        let names: Vec<&str> = metrics.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "heap_used",
+                "nonheap_used",
+                "memory_used",
+                "memory_used_kibibytes"
+            ]
+        );
+        assert_eq!(metrics[2].value, 3.0);
+        assert_eq!(metrics[3].value, 3.0 / 1024.0);
+    }
+}
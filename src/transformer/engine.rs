@@ -3,12 +3,66 @@
 //! This module provides the core transformation logic that converts
 //! JMX MBean data into Prometheus exposition format.
 
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use crate::collector::{AttributeValue, JolokiaResponse, MBeanValue, ObjectName};
+use schemars::JsonSchema;
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+
+use crate::collector::{AttributeFilter, AttributeValue, JolokiaResponse, MBeanValue, ObjectName};
 use crate::error::TransformError;
 
-use super::rules::{MetricType, RuleSet};
+use super::cache::ValueCache;
+use super::conflicts::{resolve_conflicts, ConflictPolicy};
+use super::counters::{CounterResetMode, CounterTracker};
+use super::derived::{apply_derived_metrics, DerivedMetric};
+use super::labels::{purge_interned, Labels};
+use super::limits::SeriesLimits;
+use super::relabel::{apply_relabel_rules, RelabelRule};
+use super::renames::MetricRenames;
+use super::rules::{push_snake_case, MetricType, RuleError, RuleMatch, RuleSet};
+
+/// Coverage statistics for a single transform
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransformStats {
+    /// Concrete MBeans returned by Jolokia
+    pub mbeans_discovered: usize,
+    /// MBeans that produced at least one metric
+    pub mbeans_scraped: usize,
+    /// Attribute values converted into metrics
+    pub attributes_transformed: usize,
+}
+
+impl TransformStats {
+    /// Record one MBean and the number of metrics it produced
+    fn record_mbean(&mut self, metric_count: usize) {
+        self.mbeans_discovered += 1;
+        if metric_count > 0 {
+            self.mbeans_scraped += 1;
+        }
+        self.attributes_transformed += metric_count;
+    }
+}
+
+/// Flattened attribute name and the rule it matches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlattenedName {
+    /// Name in `domain<key=value><attribute>` form, as seen by rule patterns
+    pub name: String,
+    /// Index of the first matching rule
+    pub rule: Option<usize>,
+}
+
+/// Values of one MBean, transformed as a unit
+enum Batch<'a> {
+    /// A non-wildcard Jolokia response
+    Response(&'a JolokiaResponse),
+    /// One MBean of a wildcard response
+    MBean(&'a str, &'a HashMap<String, AttributeValue>),
+}
 
 /// Transform Engine configuration and state
 ///
@@ -22,6 +76,62 @@ pub struct TransformEngine {
     lowercase_names: bool,
     /// Convert label names to lowercase
     lowercase_labels: bool,
+    /// Order of ObjectName properties in flattened names
+    key_order: KeyOrder,
+    /// Append `_total` to counter names that lack it
+    counter_total_suffix: bool,
+    /// Export values no rule matches under a generated name
+    export_unmatched: bool,
+    /// What to export when a counter series decreases
+    counter_resets: CounterResetMode,
+    /// How samples of one name with different TYPE/HELP are reconciled
+    conflict_policy: ConflictPolicy,
+    /// Relabel rules applied to every metric after rule application
+    relabel_rules: Vec<RelabelRule>,
+    /// Metric renames applied after relabeling
+    renames: MetricRenames,
+    /// Metrics computed from the renamed metrics
+    derived: Vec<DerivedMetric>,
+    /// Cardinality guardrails applied after renaming
+    limits: SeriesLimits,
+    /// Transform MBeans in parallel (`parallel` feature)
+    parallel: bool,
+    /// Whether any rule has `cache: true`
+    caching: bool,
+    /// Values produced by caching rules, shared across clones
+    cache: Arc<ValueCache>,
+    /// Last counter values, shared across clones
+    counters: Arc<CounterTracker>,
+    /// Names the attribute filter skipped since they were last taken,
+    /// shared across clones
+    skipped: Arc<SkippedNames>,
+}
+
+/// Most skipped names kept between two scrapes
+const MAX_SKIPPED_NAMES: usize = 10_000;
+
+/// Flattened names of numeric values the attribute filter skipped
+///
+/// Kept for `/debug/mbeans`, since skipped values never reach the responses
+/// it lists.
+#[derive(Debug, Default)]
+struct SkippedNames(Mutex<BTreeSet<String>>);
+
+impl SkippedNames {
+    fn record(&self, name: String) {
+        if let Ok(mut names) = self.0.lock() {
+            if names.len() < MAX_SKIPPED_NAMES {
+                names.insert(name);
+            }
+        }
+    }
+
+    fn take(&self) -> Vec<String> {
+        match self.0.lock() {
+            Ok(mut names) => std::mem::take(&mut *names).into_iter().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
 }
 
 impl TransformEngine {
@@ -40,10 +150,25 @@ impl TransformEngine {
     /// let engine = TransformEngine::new(rules);
     /// ```
     pub fn new(rules: RuleSet) -> Self {
+        let caching = rules.iter().any(|rule| rule.cache);
         Self {
             rules,
             lowercase_names: false,
             lowercase_labels: false,
+            key_order: KeyOrder::default(),
+            counter_total_suffix: false,
+            export_unmatched: false,
+            counter_resets: CounterResetMode::default(),
+            conflict_policy: ConflictPolicy::default(),
+            relabel_rules: Vec::new(),
+            renames: MetricRenames::default(),
+            derived: Vec::new(),
+            limits: SeriesLimits::default(),
+            parallel: false,
+            caching,
+            cache: Arc::new(ValueCache::new()),
+            counters: Arc::new(CounterTracker::new()),
+            skipped: Arc::default(),
         }
     }
 
@@ -64,11 +189,224 @@ impl TransformEngine {
         self
     }
 
+    /// Set the order of ObjectName properties in flattened names
+    pub fn with_key_order(mut self, key_order: KeyOrder) -> Self {
+        self.key_order = key_order;
+        self
+    }
+
+    /// Set whether to append `_total` to counter names that lack it
+    pub fn with_counter_total_suffix(mut self, suffix: bool) -> Self {
+        self.counter_total_suffix = suffix;
+        self
+    }
+
+    /// Set whether values no rule matches are exported under a generated name
+    pub fn with_export_unmatched(mut self, export: bool) -> Self {
+        self.export_unmatched = export;
+        self
+    }
+
+    /// Set what is exported when a counter series decreases
+    pub fn with_counter_resets(mut self, mode: CounterResetMode) -> Self {
+        self.counter_resets = mode;
+        self
+    }
+
+    /// Set how TYPE/HELP conflicts between samples of one name are resolved
+    pub fn with_conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.conflict_policy = policy;
+        self
+    }
+
+    /// Set relabel rules applied after rule application
+    pub fn with_relabel_rules(mut self, relabel_rules: Vec<RelabelRule>) -> Self {
+        self.relabel_rules = relabel_rules;
+        self
+    }
+
+    /// Set metric renames applied after relabeling
+    pub fn with_renames(mut self, renames: MetricRenames) -> Self {
+        self.renames = renames;
+        self
+    }
+
+    /// Set metrics computed from the other metrics after renames
+    pub fn with_derived_metrics(mut self, derived: Vec<DerivedMetric>) -> Self {
+        self.derived = derived;
+        self
+    }
+
+    /// Expire cached values and tracked counter series after `ttl`
+    ///
+    /// Only state kept across scrapes is affected: series of MBeans that
+    /// disappear stop being replayed from the value cache.
+    pub fn with_series_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.cache = Arc::new(ValueCache::new().with_ttl(ttl));
+        self.counters = Arc::new(CounterTracker::new().with_ttl(ttl));
+        self
+    }
+
+    /// Set cardinality limits enforced on every transform
+    pub fn with_series_limits(mut self, limits: SeriesLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set whether MBeans are transformed on the rayon thread pool
+    ///
+    /// Each MBean's flattened names are matched against the rules as one
+    /// batch; batches run in parallel and their metrics keep response order.
+    /// Ignored without the `parallel` feature.
+    pub fn with_parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
     /// Get a reference to the rule set
     pub fn rules(&self) -> &RuleSet {
         &self.rules
     }
 
+    /// Get the cache of values produced by `cache: true` rules
+    pub fn value_cache(&self) -> &ValueCache {
+        &self.cache
+    }
+
+    /// Filter that skips wildcard attributes no rule can match, or that are dropped
+    ///
+    /// Attribute paths are flattened exactly as during transformation, so a
+    /// rejected attribute could never have produced a metric. Pattern errors
+    /// are left for the transform to report. With `export_unmatched`, only
+    /// dropped attributes are skipped. Skipped numeric values are recorded
+    /// for [`take_skipped_names`](Self::take_skipped_names).
+    pub fn attribute_filter(&self) -> AttributeFilter {
+        let rules = self.rules.clone();
+        let key_order = self.key_order;
+        let export_unmatched = self.export_unmatched;
+        let skipped = Arc::clone(&self.skipped);
+        AttributeFilter::new(move |mbean, attribute| {
+            let flattened = flatten_name(mbean, Some(attribute), key_order);
+            rules.find_match(&flattened).map_or(true, |m| match m {
+                Some(m) => !m.rule.drop,
+                None => export_unmatched,
+            })
+        })
+        .with_rejected_hook(move |mbean, attribute| {
+            skipped.record(flatten_name(mbean, Some(attribute), key_order));
+        })
+    }
+
+    /// Flattened names of the numeric values the attribute filter skipped
+    /// since the last call, sorted
+    pub fn take_skipped_names(&self) -> Vec<String> {
+        self.skipped.take()
+    }
+
+    /// Flattened names of the numeric values in Jolokia responses
+    ///
+    /// Walks the responses the same way [`transform`](Self::transform) does
+    /// and reports the first rule matching each name, without producing
+    /// metrics. Names are sorted and deduplicated.
+    pub fn flattened_names(&self, responses: &[JolokiaResponse]) -> Vec<FlattenedName> {
+        self.flattened_names_with(responses, Vec::new())
+    }
+
+    /// [`flattened_names`](Self::flattened_names) together with `skipped`,
+    /// the names the attribute filter skipped while parsing the responses
+    pub fn flattened_names_with(
+        &self,
+        responses: &[JolokiaResponse],
+        skipped: Vec<String>,
+    ) -> Vec<FlattenedName> {
+        let mut names = skipped;
+        for response in responses.iter().filter(|r| r.status == 200) {
+            let mbean = response.request.mbean.as_str();
+            let attributes = self.extract_attributes(&response.request.attribute);
+            match &response.value {
+                MBeanValue::Number(_) => {
+                    names.push(flatten_name(
+                        mbean,
+                        attributes.first().map(|s| s.as_str()),
+                        self.key_order,
+                    ));
+                }
+                MBeanValue::Composite(map) if attributes.len() <= 1 => {
+                    let attr = attributes.first().map(|s| s.as_str());
+                    collect_composite_names(self.key_order, mbean, attr, map, &mut names);
+                }
+                MBeanValue::Array(items) if attributes.len() <= 1 => {
+                    let attr = attributes.first().map(|s| s.as_str());
+                    let mut leaves = Vec::new();
+                    array_leaves(attr, items, &mut leaves);
+                    collect_leaf_names(self.key_order, mbean, leaves, &mut names);
+                }
+                MBeanValue::Composite(map) => {
+                    for attr in &attributes {
+                        if let Some(value) = map.get(attr) {
+                            collect_attribute_names(self.key_order, mbean, attr, value, &mut names);
+                        }
+                    }
+                }
+                MBeanValue::Wildcard(wildcard) => {
+                    for (mbean, attrs) in wildcard {
+                        for (attr, value) in attrs {
+                            collect_attribute_names(self.key_order, mbean, attr, value, &mut names);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        names.sort_unstable();
+        names.dedup();
+        names
+            .into_iter()
+            .map(|name| {
+                let rule = self.rules.find_match_index(&name).ok().flatten();
+                FlattenedName { name, rule }
+            })
+            .collect()
+    }
+
+    /// MBeans none of whose numeric values match a rule
+    ///
+    /// Wildcard responses are checked per concrete MBean. Such MBeans are
+    /// read on every scrape but produce no metrics. Names are sorted.
+    pub fn unmatched_mbeans(&self, responses: &[JolokiaResponse]) -> Vec<String> {
+        let matches = |names: &[String]| {
+            names
+                .iter()
+                .any(|name| matches!(self.rules.find_match_index(name), Ok(Some(_))))
+        };
+
+        let mut unmatched = Vec::new();
+        for response in responses.iter().filter(|r| r.status == 200) {
+            if let MBeanValue::Wildcard(wildcard) = &response.value {
+                for (mbean, attrs) in wildcard {
+                    let mut names = Vec::new();
+                    for (attr, value) in attrs {
+                        collect_attribute_names(self.key_order, mbean, attr, value, &mut names);
+                    }
+                    if !matches(&names) {
+                        unmatched.push(mbean.clone());
+                    }
+                }
+            } else if self
+                .flattened_names(std::slice::from_ref(response))
+                .iter()
+                .all(|name| name.rule.is_none())
+            {
+                unmatched.push(response.request.mbean.clone());
+            }
+        }
+
+        unmatched.sort_unstable();
+        unmatched.dedup();
+        unmatched
+    }
+
     /// Transform Jolokia responses into Prometheus metrics
     ///
     /// # Arguments
@@ -82,8 +420,30 @@ impl TransformEngine {
         &self,
         responses: &[JolokiaResponse],
     ) -> Result<Vec<PrometheusMetric>, TransformError> {
-        let mut metrics = Vec::new();
+        self.transform_with_stats(responses)
+            .map(|(metrics, _stats)| metrics)
+    }
+
+    /// Transform Jolokia responses, also reporting how much of the JMX
+    /// surface was covered by the rules
+    pub fn transform_with_stats(
+        &self,
+        responses: &[JolokiaResponse],
+    ) -> Result<(Vec<PrometheusMetric>, TransformStats), TransformError> {
+        self.transform_selected_with_stats(responses, |_| true)
+    }
 
+    /// Transform Jolokia responses of a partial scrape
+    ///
+    /// Cached values are only replayed for the MBeans accepted by
+    /// `selected`, so a scrape restricted to some MBeans does not export the
+    /// cached series of the others.
+    pub fn transform_selected_with_stats(
+        &self,
+        responses: &[JolokiaResponse],
+        selected: impl Fn(&str) -> bool,
+    ) -> Result<(Vec<PrometheusMetric>, TransformStats), TransformError> {
+        let mut batches = Vec::new();
         for response in responses {
             // Skip error responses
             if response.status != 200 {
@@ -96,11 +456,122 @@ impl TransformEngine {
                 continue;
             }
 
-            let response_metrics = self.transform_response(response)?;
-            metrics.extend(response_metrics);
+            // Wildcard responses contain one entry per concrete MBean
+            if let MBeanValue::Wildcard(wildcard) = &response.value {
+                batches.extend(
+                    wildcard
+                        .iter()
+                        .map(|(mbean_name, attrs)| Batch::MBean(mbean_name, attrs)),
+                );
+                continue;
+            }
+            batches.push(Batch::Response(response));
         }
 
-        Ok(metrics)
+        let mut metrics = Vec::new();
+        let mut stats = TransformStats::default();
+        for batch_metrics in self.transform_batches(&batches)? {
+            stats.record_mbean(batch_metrics.len());
+            metrics.extend(batch_metrics);
+        }
+
+        if self.caching {
+            let expired = self.cache.remove_expired();
+            if expired > 0 {
+                tracing::debug!(expired, "Expired cached values");
+            }
+            metrics.extend(self.cache.metrics_of(selected));
+        }
+
+        if !self.relabel_rules.is_empty() {
+            metrics.retain_mut(|metric| apply_relabel_rules(&self.relabel_rules, metric));
+        }
+
+        self.renames.apply(&mut metrics);
+        apply_derived_metrics(&self.derived, &mut metrics);
+        self.resolve_conflicts(&mut metrics)?;
+
+        if self.limits.is_enabled() {
+            self.limits.enforce(&mut metrics);
+        }
+
+        self.check_counters(&mut metrics);
+
+        let purged = purge_interned();
+        if purged > 0 {
+            tracing::trace!(purged, "Dropped unused label strings");
+        }
+
+        Ok((metrics, stats))
+    }
+
+    /// Transform each batch, in parallel if enabled
+    fn transform_batches(
+        &self,
+        batches: &[Batch<'_>],
+    ) -> Result<Vec<Vec<PrometheusMetric>>, TransformError> {
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            use rayon::prelude::*;
+            return batches
+                .par_iter()
+                .map(|batch| self.transform_batch(batch))
+                .collect();
+        }
+        batches
+            .iter()
+            .map(|batch| self.transform_batch(batch))
+            .collect()
+    }
+
+    fn transform_batch(&self, batch: &Batch<'_>) -> Result<Vec<PrometheusMetric>, TransformError> {
+        match batch {
+            Batch::Response(response) => self.transform_response(response),
+            Batch::MBean(mbean_name, attrs) => self.transform_attributes(mbean_name, attrs),
+        }
+    }
+
+    /// Give every sample of a metric name the same TYPE and HELP
+    fn resolve_conflicts(&self, metrics: &mut [PrometheusMetric]) -> Result<(), TransformError> {
+        let conflicts = resolve_conflicts(metrics, self.conflict_policy);
+        let Some(first) = conflicts.first() else {
+            return Ok(());
+        };
+
+        if self.conflict_policy == ConflictPolicy::Error {
+            return Err(TransformError::MetadataConflict {
+                name: first.name.clone(),
+                reason: first.to_string(),
+            });
+        }
+
+        let names: Vec<String> = conflicts.iter().map(ToString::to_string).collect();
+        tracing::warn!(
+            policy = %self.conflict_policy,
+            conflicts = %names.join("; "),
+            "Metrics with conflicting TYPE/HELP"
+        );
+        Ok(())
+    }
+
+    /// Warn about counters that went down since the previous scrape
+    fn check_counters(&self, metrics: &mut [PrometheusMetric]) {
+        let decreases = self.counters.check(metrics, self.counter_resets);
+        if decreases.is_empty() {
+            return;
+        }
+
+        let registry = crate::metrics::internal_metrics();
+        for decrease in &decreases {
+            registry.record_counter_reset(&decrease.name);
+            tracing::warn!(
+                series = %decrease.series,
+                previous = decrease.previous,
+                current = decrease.current,
+                "Counter decreased; the JVM restarted or the rule should use type gauge"
+            );
+        }
+        registry.record_counter_decreases(decreases.len() as u64);
     }
 
     /// Transform a single Jolokia response
@@ -159,6 +630,14 @@ impl TransformEngine {
                                     )?;
                                     metrics.append(&mut m);
                                 }
+                                AttributeValue::Array(items) => {
+                                    let mut m = self.transform_array(
+                                        &response.request.mbean,
+                                        Some(attr.as_str()),
+                                        items,
+                                    )?;
+                                    metrics.append(&mut m);
+                                }
                                 _ => {}
                             }
                         }
@@ -166,6 +645,10 @@ impl TransformEngine {
                     Ok(metrics)
                 }
             }
+            MBeanValue::Array(items) if attributes.len() <= 1 => {
+                let attr = attributes.first().map(|s| s.as_str());
+                self.transform_array(&response.request.mbean, attr, items)
+            }
             MBeanValue::Wildcard(wildcard) => self.transform_wildcard(wildcard),
             _ => Ok(vec![]),
         }
@@ -193,41 +676,18 @@ impl TransformEngine {
     ) -> Result<Vec<PrometheusMetric>, TransformError> {
         let flattened = self.flatten_mbean_name(mbean, attribute);
 
-        if let Some(rule_match) = self.rules.find_match(&flattened).map_err(|e| {
-            // Convert rules::RuleError to crate::error::RuleError, preserving original context
-            match e {
-                super::rules::RuleError::InvalidPattern { pattern, source } => {
-                    TransformError::Rule(crate::error::RuleError::InvalidPattern {
-                        pattern,
-                        source,
-                    })
-                }
-                super::rules::RuleError::UnsupportedJavaFeature { pattern, feature } => {
-                    TransformError::Rule(crate::error::RuleError::UnsupportedSyntax {
-                        pattern,
-                        feature,
-                    })
-                }
-                super::rules::RuleError::CompilationFailed(msg) => {
-                    TransformError::Rule(crate::error::RuleError::InvalidPattern {
-                        pattern: msg.clone(),
-                        source: regex::Error::Syntax(msg),
-                    })
-                }
-                super::rules::RuleError::InvalidNameTemplate { template, reason } => {
-                    TransformError::InvalidMetricName {
-                        name: template,
-                        reason,
-                    }
-                }
-                super::rules::RuleError::ValidationError(msg) => {
-                    TransformError::InvalidMetricName {
-                        name: String::new(),
-                        reason: msg,
-                    }
-                }
+        // Cached values are replayed at the end of transform()
+        if self.caching && self.cache.contains(&flattened) {
+            return Ok(vec![]);
+        }
+
+        if let Some(rule_match) = self.rules.find_match(&flattened).map_err(rule_error)? {
+            let registry = crate::metrics::internal_metrics();
+            registry.record_rule_match(&rule_match.rule.pattern, mbean);
+            if rule_match.rule.drop {
+                return Ok(vec![]);
             }
-        })? {
+
             // Warn if the rule has a 'value' field set (not yet implemented)
             if rule_match.value().is_some() {
                 tracing::warn!(
@@ -236,35 +696,25 @@ impl TransformEngine {
                 );
             }
 
-            let mut metric_name = rule_match.metric_name();
-            if self.lowercase_names {
-                metric_name = metric_name.to_lowercase();
-            }
-
-            let validated_name = self.validate_metric_name(&metric_name)?;
-
-            let mut labels = rule_match.labels();
-            if self.lowercase_labels {
-                labels = labels
-                    .into_iter()
-                    .map(|(k, v)| (k.to_lowercase(), v))
-                    .collect();
+            let metric = self
+                .rule_metric(&rule_match, value)
+                .inspect_err(|_| registry.record_rule_error(&rule_match.rule.pattern))?;
+
+            if self.caching {
+                // Top-level attribute, without composite keys
+                let top_attribute = attribute
+                    .and_then(|a| a.split('<').next())
+                    .unwrap_or_default();
+                if rule_match.rule.cache {
+                    self.cache.insert(mbean, top_attribute, &flattened, metric);
+                    return Ok(vec![]);
+                }
+                self.cache.mark_live(mbean, top_attribute);
             }
-            let validated_labels = self.validate_labels(&labels)?;
-
-            let final_value = match rule_match.value_factor() {
-                Some(factor) => value * factor,
-                None => value,
-            };
 
-            Ok(vec![PrometheusMetric {
-                name: validated_name,
-                metric_type: rule_match.metric_type(),
-                help: rule_match.help().map(|s| s.to_string()),
-                labels: validated_labels,
-                value: final_value,
-                timestamp: None,
-            }])
+            Ok(vec![metric])
+        } else if self.export_unmatched {
+            Ok(vec![self.default_metric(mbean, attribute, value)?])
         } else {
             // No matching rule - skip this metric
             tracing::trace!(mbean = %mbean, "No matching rule found");
@@ -272,6 +722,33 @@ impl TransformEngine {
         }
     }
 
+    /// Untyped metric for a value no rule matches (see [`default_export`])
+    fn default_metric(
+        &self,
+        mbean: &str,
+        attribute: Option<&str>,
+        value: f64,
+    ) -> Result<PrometheusMetric, TransformError> {
+        let (name, labels) = default_export(mbean, attribute, self.key_order);
+        let labels: HashMap<String, String> = if self.lowercase_labels {
+            labels
+                .into_iter()
+                .map(|(k, v)| (k.to_lowercase(), v))
+                .collect()
+        } else {
+            labels.into_iter().collect()
+        };
+        Ok(PrometheusMetric {
+            name: self.validate_metric_name(&name)?,
+            metric_type: MetricType::Untyped,
+            help: None,
+            labels: self.validate_labels(&labels)?,
+            value,
+            timestamp: None,
+            native_schema: None,
+        })
+    }
+
     /// Transform a composite value (e.g., HeapMemoryUsage)
     ///
     /// For composite values, the flattened name format is:
@@ -280,35 +757,49 @@ impl TransformEngine {
     /// Example: For MBean "java.lang:type=Memory" with attribute "HeapMemoryUsage"
     /// and composite key "used", the flattened name will be:
     /// `java.lang<type=Memory><HeapMemoryUsage><used>`
+    ///
+    /// Nested composites and arrays add one `<key>` per level, e.g.
+    /// `java.lang<name=G1 Young Generation><type=GarbageCollector><LastGcInfo><memoryUsageAfterGc><G1 Eden Space><used>`
+    /// (see [`numeric_leaves`]).
     fn transform_composite(
         &self,
         mbean: &str,
         attribute: Option<&str>,
         composite: &HashMap<String, AttributeValue>,
     ) -> Result<Vec<PrometheusMetric>, TransformError> {
-        let mut metrics = Vec::new();
+        let mut leaves = Vec::new();
+        composite_leaves(attribute, composite, &mut leaves);
+        self.transform_leaves(mbean, leaves)
+    }
 
-        for (key, value) in composite {
-            if let Some(num) = value.as_f64() {
-                // Build the full attribute path: attribute + composite key
-                // e.g., "HeapMemoryUsage" + "used" -> flatten as <HeapMemoryUsage><used>
-                let full_attr = match attribute {
-                    Some(attr) => format!("{}<{}>", attr, key),
-                    None => key.clone(),
-                };
-                let mut new_metrics = self.transform_simple(mbean, Some(&full_attr), num)?;
-                metrics.append(&mut new_metrics);
-            }
-        }
+    /// Transform an array value, such as TabularData without a single index
+    /// column, with one `<element>` per element (see [`numeric_leaves`])
+    fn transform_array(
+        &self,
+        mbean: &str,
+        attribute: Option<&str>,
+        items: &[AttributeValue],
+    ) -> Result<Vec<PrometheusMetric>, TransformError> {
+        let mut leaves = Vec::new();
+        array_leaves(attribute, items, &mut leaves);
+        self.transform_leaves(mbean, leaves)
+    }
 
+    /// Transform the numeric leaves of a composite or array
+    fn transform_leaves(
+        &self,
+        mbean: &str,
+        leaves: Vec<(String, f64)>,
+    ) -> Result<Vec<PrometheusMetric>, TransformError> {
+        let mut metrics = Vec::new();
+        for (path, value) in leaves {
+            let mut new_metrics = self.transform_simple(mbean, Some(&path), value)?;
+            metrics.append(&mut new_metrics);
+        }
         Ok(metrics)
     }
 
     /// Transform a wildcard response
-    ///
-    /// For wildcard responses, we need to handle each attribute type appropriately:
-    /// - Numeric values (Integer/Float) -> transform_simple
-    /// - Object values (nested composites) -> transform_composite recursively
     fn transform_wildcard(
         &self,
         wildcard: &HashMap<String, HashMap<String, AttributeValue>>,
@@ -316,80 +807,121 @@ impl TransformEngine {
         let mut metrics = Vec::new();
 
         for (mbean_name, attrs) in wildcard {
-            // Handle each attribute based on its type
-            for (attr_name, attr_value) in attrs {
-                match attr_value {
-                    AttributeValue::Integer(n) => {
-                        let mut m =
-                            self.transform_simple(mbean_name, Some(attr_name), *n as f64)?;
-                        metrics.append(&mut m);
-                    }
-                    AttributeValue::Float(n) => {
-                        let mut m = self.transform_simple(mbean_name, Some(attr_name), *n)?;
-                        metrics.append(&mut m);
-                    }
-                    AttributeValue::Object(nested) => {
-                        // Recursively handle nested composite objects
-                        let mut m =
-                            self.transform_composite(mbean_name, Some(attr_name), nested)?;
-                        metrics.append(&mut m);
-                    }
-                    _ => {
-                        // Skip non-numeric types (String, Boolean, Array, Null)
-                    }
-                }
-            }
+            let mut m = self.transform_attributes(mbean_name, attrs)?;
+            metrics.append(&mut m);
         }
 
         Ok(metrics)
     }
 
-    /// Flatten MBean name to jmx_exporter format
-    ///
-    /// Format: `domain<key1=value1><key2=value2><attribute>`
+    /// Transform the attributes of a single MBean
     ///
-    /// Example: "java.lang:type=Memory" with attribute "HeapMemoryUsage<used>"
-    /// becomes: "java.lang<type=Memory><HeapMemoryUsage><used>"
-    fn flatten_mbean_name(&self, mbean: &str, attribute: Option<&str>) -> String {
-        // Parse ObjectName to get domain and properties
-        let object_name = match ObjectName::parse(mbean) {
-            Ok(on) => on,
-            Err(_) => {
-                // Fallback: just use the raw name
-                if let Some(attr) = attribute {
-                    return format!("{}<{}>", mbean, attr);
+    /// Each attribute is handled according to its type:
+    /// - Numeric values (Integer/Float) -> transform_simple
+    /// - Object values (nested composites) -> transform_composite recursively
+    /// - Array values -> transform_array
+    fn transform_attributes(
+        &self,
+        mbean_name: &str,
+        attrs: &HashMap<String, AttributeValue>,
+    ) -> Result<Vec<PrometheusMetric>, TransformError> {
+        let mut metrics = Vec::new();
+
+        for (attr_name, attr_value) in attrs {
+            match attr_value {
+                AttributeValue::Integer(n) => {
+                    let mut m = self.transform_simple(mbean_name, Some(attr_name), *n as f64)?;
+                    metrics.append(&mut m);
+                }
+                AttributeValue::Float(n) => {
+                    let mut m = self.transform_simple(mbean_name, Some(attr_name), *n)?;
+                    metrics.append(&mut m);
+                }
+                AttributeValue::Object(nested) => {
+                    // Recursively handle nested composite objects
+                    let mut m = self.transform_composite(mbean_name, Some(attr_name), nested)?;
+                    metrics.append(&mut m);
+                }
+                AttributeValue::Array(items) => {
+                    let mut m = self.transform_array(mbean_name, Some(attr_name), items)?;
+                    metrics.append(&mut m);
+                }
+                _ => {
+                    // Skip non-numeric types (String, Boolean, Null)
                 }
-                return mbean.to_string();
             }
-        };
+        }
 
-        let mut result = object_name.domain.clone();
+        Ok(metrics)
+    }
 
-        // Sort properties for deterministic output
-        let mut props: Vec<_> = object_name.properties.iter().collect();
-        props.sort_by_key(|(k, _)| *k);
+    /// Metric for a flattened name, with the index of the rule producing it
+    ///
+    /// The name and labels are the ones a scrape would produce before
+    /// relabeling and renames; the value is `0`. The metric is `None` if a
+    /// drop rule matches, and the result is `None` if no rule matches.
+    pub fn preview(
+        &self,
+        flattened: &str,
+    ) -> Result<Option<(usize, Option<PrometheusMetric>)>, TransformError> {
+        let Some(index) = self.rules.find_match_index(flattened).map_err(rule_error)? else {
+            return Ok(None);
+        };
+        let rule = &self.rules.rules()[index];
+        if rule.drop {
+            return Ok(Some((index, None)));
+        }
+        match rule.matches(flattened).map_err(rule_error)? {
+            Some(rule_match) => Ok(Some((index, Some(self.rule_metric(&rule_match, 0.0)?)))),
+            None => Ok(None),
+        }
+    }
 
-        // Add properties in <key=value> format
-        for (key, value) in props {
-            result.push_str(&format!("<{}={}>", key, value));
-        }
-
-        // Add attribute if present
-        // The attribute may already contain nested <> for composite keys
-        // e.g., "HeapMemoryUsage<used>" should become "<HeapMemoryUsage><used>"
-        if let Some(attr) = attribute {
-            // Check if attribute already has angle brackets (composite path)
-            if let Some(bracket_pos) = attr.find('<') {
-                // Split at the first '<' to get base attribute and composite key
-                let base_attr = &attr[..bracket_pos];
-                let composite_part = &attr[bracket_pos..];
-                result.push_str(&format!("<{}>{}", base_attr, composite_part));
-            } else {
-                result.push_str(&format!("<{}>", attr));
-            }
+    /// Build the metric for a rule match: name and labels as configured, value scaled
+    fn rule_metric(
+        &self,
+        rule_match: &RuleMatch<'_>,
+        value: f64,
+    ) -> Result<PrometheusMetric, TransformError> {
+        let mut metric_name = rule_match.metric_name();
+        if self.lowercase_names {
+            metric_name = metric_name.to_lowercase();
         }
+        if self.counter_total_suffix
+            && rule_match.metric_type() == MetricType::Counter
+            && !metric_name.ends_with("_total")
+        {
+            metric_name.push_str("_total");
+        }
+        let name = self.validate_metric_name(&metric_name)?;
+
+        let mut labels = rule_match.labels();
+        if self.lowercase_labels {
+            labels = labels
+                .into_iter()
+                .map(|(k, v)| (k.to_lowercase(), v))
+                .collect();
+        }
+        let labels = self.validate_labels(&labels)?;
+
+        Ok(PrometheusMetric {
+            name,
+            metric_type: rule_match.metric_type(),
+            help: rule_match.help().map(|s| s.to_string()),
+            labels,
+            value: match (rule_match.value_transform(), rule_match.value_factor()) {
+                (Some(transform), _) => transform.eval(value),
+                (None, Some(factor)) => value * factor,
+                (None, None) => value,
+            },
+            timestamp: None,
+            native_schema: rule_match.rule.native_histogram_schema,
+        })
+    }
 
-        result
+    /// Flatten MBean name to jmx_exporter format (see [`flatten_name`])
+    fn flatten_mbean_name(&self, mbean: &str, attribute: Option<&str>) -> String {
+        flatten_name(mbean, attribute, self.key_order)
     }
 
     /// Validate and sanitize Prometheus metric name
@@ -445,10 +977,7 @@ impl TransformEngine {
     /// Validate and sanitize label names
     ///
     /// Prometheus label names must match: `[a-zA-Z_][a-zA-Z0-9_]*`
-    fn validate_labels(
-        &self,
-        labels: &HashMap<String, String>,
-    ) -> Result<HashMap<String, String>, TransformError> {
+    fn validate_labels(&self, labels: &HashMap<String, String>) -> Result<Labels, TransformError> {
         use std::sync::OnceLock;
 
         static LABEL_NAME_RE: OnceLock<regex::Regex> = OnceLock::new();
@@ -456,10 +985,10 @@ impl TransformEngine {
             regex::Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").expect("invalid label name regex")
         });
 
-        let mut validated = HashMap::new();
+        let mut validated = Labels::new();
         for (k, v) in labels {
             let key = if re.is_match(k) {
-                k.clone()
+                Cow::Borrowed(k.as_str())
             } else {
                 let sanitized: String = k
                     .chars()
@@ -484,9 +1013,9 @@ impl TransformEngine {
                     sanitized = %sanitized,
                     "Label name sanitized"
                 );
-                sanitized
+                Cow::Owned(sanitized)
             };
-            validated.insert(key, v.clone());
+            validated.insert(&key, v);
         }
 
         Ok(validated)
@@ -499,21 +1028,283 @@ impl Default for TransformEngine {
     }
 }
 
-/// A single Prometheus metric ready for output
-#[derive(Debug, Clone)]
-pub struct PrometheusMetric {
-    /// Metric name
-    pub name: String,
-    /// Metric type (gauge, counter, untyped)
-    pub metric_type: MetricType,
-    /// Help text
-    pub help: Option<String>,
-    /// Labels
-    pub labels: HashMap<String, String>,
-    /// Metric value
-    pub value: f64,
-    /// Optional timestamp (milliseconds since epoch)
-    pub timestamp: Option<i64>,
+/// Flattened names of an attribute, as produced by `transform_attributes`
+fn collect_attribute_names(
+    key_order: KeyOrder,
+    mbean: &str,
+    attribute: &str,
+    value: &AttributeValue,
+    names: &mut Vec<String>,
+) {
+    match value {
+        AttributeValue::Integer(_) | AttributeValue::Float(_) => {
+            names.push(flatten_name(mbean, Some(attribute), key_order));
+        }
+        AttributeValue::Object(nested) => {
+            collect_composite_names(key_order, mbean, Some(attribute), nested, names);
+        }
+        AttributeValue::Array(items) => {
+            let mut leaves = Vec::new();
+            array_leaves(Some(attribute), items, &mut leaves);
+            collect_leaf_names(key_order, mbean, leaves, names);
+        }
+        _ => {}
+    }
+}
+
+/// Flattened names of a composite value, as produced by `transform_composite`
+fn collect_composite_names(
+    key_order: KeyOrder,
+    mbean: &str,
+    attribute: Option<&str>,
+    composite: &HashMap<String, AttributeValue>,
+    names: &mut Vec<String>,
+) {
+    let mut leaves = Vec::new();
+    composite_leaves(attribute, composite, &mut leaves);
+    collect_leaf_names(key_order, mbean, leaves, names);
+}
+
+/// Flattened names of the leaves of a composite or array
+fn collect_leaf_names(
+    key_order: KeyOrder,
+    mbean: &str,
+    leaves: Vec<(String, f64)>,
+    names: &mut Vec<String>,
+) {
+    names.extend(
+        leaves
+            .into_iter()
+            .map(|(path, _)| flatten_name(mbean, Some(&path), key_order)),
+    );
+}
+
+/// Numeric leaves of a composite, with their attribute paths
+fn composite_leaves(
+    attribute: Option<&str>,
+    composite: &HashMap<String, AttributeValue>,
+    leaves: &mut Vec<(String, f64)>,
+) {
+    for (key, value) in composite {
+        numeric_leaves(child_path(attribute, key), value, leaves);
+    }
+}
+
+/// Numeric leaves of an array, with their attribute paths
+fn array_leaves(
+    attribute: Option<&str>,
+    items: &[AttributeValue],
+    leaves: &mut Vec<(String, f64)>,
+) {
+    for (index, item) in items.iter().enumerate() {
+        let (segment, value) = item.array_element(index);
+        numeric_leaves(child_path(attribute, &segment), value, leaves);
+    }
+}
+
+/// Numeric leaves of a value at `path`
+///
+/// Each level of composites adds `<key>` and each level of arrays
+/// `<element>` to the path: the key of TabularData rows, the index
+/// otherwise (see [`AttributeValue::array_element`]). A `LastGcInfo` yields
+/// `LastGcInfo<duration>` and
+/// `LastGcInfo<memoryUsageAfterGc><G1 Eden Space><used>`.
+fn numeric_leaves(path: String, value: &AttributeValue, leaves: &mut Vec<(String, f64)>) {
+    match value {
+        AttributeValue::Object(nested) => composite_leaves(Some(&path), nested, leaves),
+        AttributeValue::Array(items) => array_leaves(Some(&path), items, leaves),
+        value => {
+            if let Some(number) = value.as_f64() {
+                leaves.push((path, number));
+            }
+        }
+    }
+}
+
+/// Attribute path of a composite key or array element
+fn child_path(attribute: Option<&str>, segment: &str) -> String {
+    match attribute {
+        Some(attribute) => format!("{}<{}>", attribute, segment),
+        None => segment.to_string(),
+    }
+}
+
+/// Order of ObjectName properties in flattened names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyOrder {
+    /// Sorted by key, as in canonical ObjectNames
+    #[default]
+    Canonical,
+    /// As declared when the MBean was registered (jmx_exporter behavior)
+    Declared,
+}
+
+impl KeyOrder {
+    /// Returns the configuration string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyOrder::Canonical => "canonical",
+            KeyOrder::Declared => "declared",
+        }
+    }
+}
+
+impl std::fmt::Display for KeyOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Convert a rule error into a transform error, preserving its context
+fn rule_error(e: RuleError) -> TransformError {
+    match e {
+        RuleError::InvalidPattern { pattern, source } => {
+            TransformError::Rule(crate::error::RuleError::InvalidPattern { pattern, source })
+        }
+        RuleError::UnsupportedJavaFeature { pattern, feature } => {
+            TransformError::Rule(crate::error::RuleError::UnsupportedSyntax { pattern, feature })
+        }
+        RuleError::CompilationFailed(msg) => {
+            TransformError::Rule(crate::error::RuleError::InvalidPattern {
+                pattern: msg.clone(),
+                source: regex::Error::Syntax(msg),
+            })
+        }
+        RuleError::InvalidNameTemplate { template, reason } => TransformError::InvalidMetricName {
+            name: template,
+            reason,
+        },
+        RuleError::ValidationError(msg) => TransformError::InvalidMetricName {
+            name: String::new(),
+            reason: msg,
+        },
+    }
+}
+
+/// Name and labels of a value no rule matches, like jmx_exporter's default export
+///
+/// The name joins the domain, the `type` property (or the first property in
+/// `key_order`), the attribute and its composite keys in snake_case; the
+/// other properties become labels. `java.lang:type=GarbageCollector,name=G1`
+/// with attribute `CollectionCount` becomes
+/// `java_lang_garbage_collector_collection_count{name="G1"}`.
+fn default_export(
+    mbean: &str,
+    attribute: Option<&str>,
+    key_order: KeyOrder,
+) -> (String, Vec<(String, String)>) {
+    let mut parts = Vec::new();
+    let mut labels = Vec::new();
+    match ObjectName::parse(mbean) {
+        Ok(object_name) => {
+            parts.push(object_name.domain);
+            let mut properties: Vec<(String, String)> =
+                object_name.properties.into_iter().collect();
+            if key_order == KeyOrder::Canonical {
+                properties.sort();
+            }
+            let first = properties
+                .iter()
+                .position(|(key, _)| key == "type")
+                .or((!properties.is_empty()).then_some(0));
+            if let Some(first) = first {
+                parts.push(properties.remove(first).1);
+            }
+            labels = properties;
+        }
+        Err(_) => parts.push(mbean.to_string()),
+    }
+    // Composite keys are nested as `attribute<key>`
+    parts.extend(
+        attribute
+            .into_iter()
+            .flat_map(|attribute| attribute.split(['<', '>']))
+            .map(str::to_string),
+    );
+
+    let mut snake = String::new();
+    for part in parts.iter().filter(|part| !part.is_empty()) {
+        snake.push('_');
+        push_snake_case(part, &mut snake);
+    }
+    let mut name = String::with_capacity(snake.len());
+    for c in snake.chars() {
+        let c = if c.is_ascii_alphanumeric() { c } else { '_' };
+        if c != '_' || !name.ends_with('_') {
+            name.push(c);
+        }
+    }
+    (name.trim_matches('_').to_string(), labels)
+}
+
+/// Flatten MBean name to jmx_exporter format
+///
+/// Format: `domain<key1=value1><key2=value2><attribute>`, with properties
+/// in `key_order`
+///
+/// Example: "java.lang:type=Memory" with attribute "HeapMemoryUsage<used>"
+/// becomes: "java.lang<type=Memory><HeapMemoryUsage><used>"
+fn flatten_name(mbean: &str, attribute: Option<&str>, key_order: KeyOrder) -> String {
+    // Parse ObjectName to get domain and properties
+    let object_name = match ObjectName::parse(mbean) {
+        Ok(on) => on,
+        Err(_) => {
+            // Fallback: just use the raw name
+            if let Some(attr) = attribute {
+                return format!("{}<{}>", mbean, attr);
+            }
+            return mbean.to_string();
+        }
+    };
+
+    let mut result = object_name.domain.clone();
+
+    let mut props: Vec<_> = object_name.properties.iter().collect();
+    if key_order == KeyOrder::Canonical {
+        props.sort_by_key(|(k, _)| *k);
+    }
+
+    // Add properties in <key=value> format
+    for (key, value) in props {
+        result.push_str(&format!("<{}={}>", key, value));
+    }
+
+    // Add attribute if present
+    // The attribute may already contain nested <> for composite keys
+    // e.g., "HeapMemoryUsage<used>" should become "<HeapMemoryUsage><used>"
+    if let Some(attr) = attribute {
+        // Check if attribute already has angle brackets (composite path)
+        if let Some(bracket_pos) = attr.find('<') {
+            // Split at the first '<' to get base attribute and composite key
+            let base_attr = &attr[..bracket_pos];
+            let composite_part = &attr[bracket_pos..];
+            result.push_str(&format!("<{}>{}", base_attr, composite_part));
+        } else {
+            result.push_str(&format!("<{}>", attr));
+        }
+    }
+
+    result
+}
+
+/// A single Prometheus metric ready for output
+#[derive(Debug, Clone)]
+pub struct PrometheusMetric {
+    /// Metric name
+    pub name: String,
+    /// Metric type (gauge, counter, untyped)
+    pub metric_type: MetricType,
+    /// Help text
+    pub help: Option<String>,
+    /// Labels, sorted by name
+    pub labels: Labels,
+    /// Metric value
+    pub value: f64,
+    /// Optional timestamp (milliseconds since epoch)
+    pub timestamp: Option<i64>,
+    /// Native histogram schema of a histogram series (protobuf exposition only)
+    pub native_schema: Option<i8>,
 }
 
 impl PrometheusMetric {
@@ -523,9 +1314,10 @@ impl PrometheusMetric {
             name: name.into(),
             metric_type: MetricType::Untyped,
             help: None,
-            labels: HashMap::new(),
+            labels: Labels::new(),
             value,
             timestamp: None,
+            native_schema: None,
         }
     }
 
@@ -542,8 +1334,8 @@ impl PrometheusMetric {
     }
 
     /// Add a label
-    pub fn with_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
-        self.labels.insert(key.into(), value.into());
+    pub fn with_label(mut self, key: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.labels.insert(key.as_ref(), value.as_ref());
         self
     }
 
@@ -552,12 +1344,50 @@ impl PrometheusMetric {
         self.timestamp = Some(timestamp);
         self
     }
+
+    /// Also expose this histogram series as a native histogram
+    pub fn with_native_schema(mut self, schema: i8) -> Self {
+        self.native_schema = Some(schema);
+        self
+    }
+}
+
+/// JSON form of a metric, as served by `/metrics.json`
+///
+/// Non-finite values are written as the strings `NaN`, `+Inf` and `-Inf`,
+/// which JSON numbers cannot hold.
+impl Serialize for PrometheusMetric {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut fields = 4 + usize::from(self.help.is_some());
+        fields += usize::from(self.timestamp.is_some());
+        let mut metric = serializer.serialize_struct("PrometheusMetric", fields)?;
+        metric.serialize_field("name", &self.name)?;
+        metric.serialize_field("type", &self.metric_type)?;
+        if let Some(help) = &self.help {
+            metric.serialize_field("help", help)?;
+        }
+        metric.serialize_field("labels", &self.labels)?;
+        if self.value.is_finite() {
+            metric.serialize_field("value", &self.value)?;
+        } else if self.value.is_nan() {
+            metric.serialize_field("value", "NaN")?;
+        } else if self.value > 0.0 {
+            metric.serialize_field("value", "+Inf")?;
+        } else {
+            metric.serialize_field("value", "-Inf")?;
+        }
+        if let Some(timestamp) = self.timestamp {
+            metric.serialize_field("timestamp", &timestamp)?;
+        }
+        metric.end()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::transformer::rules::{Rule, RuleSet};
+    use crate::transformer::Expr;
 
     fn create_test_engine() -> TransformEngine {
         let mut ruleset = RuleSet::new();
@@ -578,6 +1408,28 @@ mod tests {
         TransformEngine::new(ruleset)
     }
 
+    #[test]
+    fn test_metric_json() {
+        let metric = PrometheusMetric::new("jvm_threads", 42.0)
+            .with_type(MetricType::Gauge)
+            .with_label("area", "heap");
+        assert_eq!(
+            serde_json::to_value(&metric).unwrap(),
+            serde_json::json!({
+                "name": "jvm_threads",
+                "type": "gauge",
+                "labels": {"area": "heap"},
+                "value": 42.0
+            })
+        );
+
+        let metric = PrometheusMetric::new("ratio", f64::NAN).with_timestamp(1000);
+        let json = serde_json::to_value(&metric).unwrap();
+        assert_eq!(json["value"], "NaN");
+        assert_eq!(json["timestamp"], 1000);
+        let metric = PrometheusMetric::new("max", f64::NEG_INFINITY);
+        assert_eq!(serde_json::to_value(&metric).unwrap()["value"], "-Inf");
+    }
     #[test]
     fn test_transform_simple() {
         let engine = create_test_engine();
@@ -595,6 +1447,130 @@ mod tests {
         assert_eq!(metrics[0].metric_type, MetricType::Gauge);
     }
 
+    #[test]
+    fn test_default_export() {
+        let (name, labels) = default_export(
+            "java.lang:type=GarbageCollector,name=G1 Young Generation",
+            Some("CollectionCount"),
+            KeyOrder::Canonical,
+        );
+        assert_eq!(name, "java_lang_garbage_collector_collection_count");
+        assert_eq!(
+            labels,
+            [("name".to_string(), "G1 Young Generation".to_string())]
+        );
+
+        let (name, labels) = default_export(
+            "kafka.server:name=BytesInPerSec,topic=orders",
+            Some("Count"),
+            KeyOrder::Declared,
+        );
+        assert_eq!(name, "kafka_server_bytes_in_per_sec_count");
+        assert_eq!(labels, [("topic".to_string(), "orders".to_string())]);
+
+        let (name, _) = default_export(
+            "java.lang:type=Memory",
+            Some("HeapMemoryUsage<used>"),
+            KeyOrder::Canonical,
+        );
+        assert_eq!(name, "java_lang_memory_heap_memory_usage_used");
+    }
+
+    #[test]
+    fn test_export_unmatched() {
+        let engine = create_test_engine()
+            .with_export_unmatched(true)
+            .with_lowercase_labels(true);
+
+        let metrics = engine
+            .transform_simple("java.nio:type=BufferPool,Name=direct", Some("Count"), 2.0)
+            .unwrap();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].name, "java_nio_buffer_pool_count");
+        assert_eq!(metrics[0].metric_type, MetricType::Untyped);
+        assert_eq!(metrics[0].labels.get("name"), Some("direct"));
+        assert_eq!(metrics[0].value, 2.0);
+
+        // Rules still take precedence
+        let metrics = engine
+            .transform_simple("java.lang:type=Threading", Some("ThreadCount"), 42.0)
+            .unwrap();
+        assert_eq!(metrics[0].name, "jvm_threads_ThreadCount");
+
+        assert!(engine
+            .attribute_filter()
+            .accepts("java.nio:type=BufferPool,name=direct", "Count"));
+        assert!(!create_test_engine()
+            .attribute_filter()
+            .accepts("java.nio:type=BufferPool,name=direct", "Count"));
+    }
+
+    #[test]
+    fn test_drop_rules() {
+        let mut engine = create_test_engine();
+        let mut rules: Vec<Rule> = engine.rules().iter().cloned().collect();
+        rules.push(Rule::new(r"<(Daemon)?ThreadCount>", "", MetricType::Gauge).with_drop(true));
+        engine.rules = RuleSet::from_rules(rules);
+
+        let dropped = engine
+            .transform_simple("java.lang:type=Threading", Some("DaemonThreadCount"), 3.0)
+            .unwrap();
+        assert!(dropped.is_empty());
+        let kept = engine
+            .transform_simple("java.lang:type=Threading", Some("PeakThreadCount"), 5.0)
+            .unwrap();
+        assert_eq!(kept[0].name, "jvm_threads_PeakThreadCount");
+
+        let filter = engine.attribute_filter();
+        assert!(!filter.accepts("java.lang:type=Threading", "ThreadCount"));
+        assert!(filter.accepts("java.lang:type=Threading", "PeakThreadCount"));
+
+        let (index, metric) = engine
+            .preview("java.lang<type=Threading><ThreadCount>")
+            .unwrap()
+            .unwrap();
+        assert_eq!(index, 2);
+        assert!(metric.is_none());
+    }
+
+    #[test]
+    fn test_value_transform() {
+        let engine = TransformEngine::new(RuleSet::from_rules(vec![Rule::new(
+            r"java\.lang<type=Memory><HeapMemoryUsage><used>",
+            "jvm_memory_heap_used_mebibytes",
+            MetricType::Gauge,
+        )
+        .with_value_transform(Expr::parse("x / 1048576").unwrap())]));
+
+        let metrics = engine
+            .transform_simple(
+                "java.lang:type=Memory",
+                Some("HeapMemoryUsage<used>"),
+                3145728.0,
+            )
+            .unwrap();
+        assert_eq!(metrics[0].value, 3.0);
+    }
+
+    #[test]
+    fn test_attribute_filter() {
+        let rules = RuleSet::from_rules(vec![Rule::builder(
+            r"java\.lang<name=(\w+)><type=MemoryPool><Usage><used>",
+        )
+        .name("jvm_memory_pool_used_bytes")
+        .build()]);
+        let filter = TransformEngine::new(rules).attribute_filter();
+
+        let mbean = "java.lang:type=MemoryPool,name=Eden";
+        assert!(filter.accepts(mbean, "Usage<used>"));
+        assert!(!filter.accepts(mbean, "Usage<max>"));
+        assert!(!filter.accepts(mbean, "CollectionUsageThreshold"));
+
+        assert!(!TransformEngine::empty()
+            .attribute_filter()
+            .accepts(mbean, "Usage<used>"));
+    }
+
     #[test]
     fn test_flatten_mbean_name() {
         let engine = TransformEngine::empty();
@@ -603,6 +1579,33 @@ mod tests {
         assert!(flattened.contains("java.lang"));
         assert!(flattened.contains("type=Memory"));
         assert!(flattened.contains("HeapMemoryUsage"));
+
+        // Quoted values are flattened unquoted, commas included
+        let flattened = engine.flatten_mbean_name(
+            r#"org.apache.cassandra.metrics:type=Table,scope="a,b",name=Reads"#,
+            Some("Count"),
+        );
+        assert_eq!(
+            flattened,
+            "org.apache.cassandra.metrics<name=Reads><scope=a,b><type=Table><Count>"
+        );
+    }
+
+    #[test]
+    fn test_flatten_mbean_name_declared_order() {
+        let mbean = "kafka.server:type=BrokerTopicMetrics,name=BytesInPerSec,topic=orders";
+
+        let engine = TransformEngine::empty();
+        assert_eq!(
+            engine.flatten_mbean_name(mbean, Some("Count")),
+            "kafka.server<name=BytesInPerSec><topic=orders><type=BrokerTopicMetrics><Count>"
+        );
+
+        let engine = TransformEngine::empty().with_key_order(KeyOrder::Declared);
+        assert_eq!(
+            engine.flatten_mbean_name(mbean, Some("Count")),
+            "kafka.server<type=BrokerTopicMetrics><name=BytesInPerSec><topic=orders><Count>"
+        );
     }
 
     #[test]
@@ -675,6 +1678,119 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_transform_nested_composites_and_arrays() {
+        use crate::testing::parse_fixture;
+
+        let engine = TransformEngine::new(RuleSet::from_rules(vec![
+            Rule::new(
+                r"java\.lang<name=([^<>]+)><type=GarbageCollector><LastGcInfo><duration>",
+                "jvm_gc_last_duration_ms",
+                MetricType::Gauge,
+            )
+            .with_label("gc", "$1"),
+            Rule::new(
+                r"java\.lang<name=([^<>]+)><type=GarbageCollector><LastGcInfo><memoryUsage(\w+)Gc><([^<>]+)><used>",
+                "jvm_gc_last_memory_used_$2_bytes",
+                MetricType::Gauge,
+            )
+            .with_label("gc", "$1")
+            .with_label("pool", "$3"),
+            Rule::new(
+                r"test<type=Tabular><Rows><(\w+)><(\w+)>",
+                "test_rows_$2",
+                MetricType::Gauge,
+            )
+            .with_label("row", "$1"),
+        ]));
+        // Jolokia serializes single-index TabularData as an object keyed by
+        // the index, other TabularData as an array of key/value rows
+        let responses = parse_fixture(
+            r#"[
+                {
+                    "request": {"mbean": "java.lang:type=GarbageCollector,*", "type": "read"},
+                    "value": {
+                        "java.lang:name=G1 Young Generation,type=GarbageCollector": {
+                            "LastGcInfo": {
+                                "duration": 12,
+                                "id": 3,
+                                "memoryUsageBeforeGc": {"G1 Eden Space": {"used": 100, "max": -1}},
+                                "memoryUsageAfterGc": {"G1 Eden Space": {"used": 0, "max": -1}}
+                            }
+                        }
+                    },
+                    "status": 200
+                },
+                {
+                    "request": {"mbean": "test:type=Tabular", "attribute": "Rows", "type": "read"},
+                    "value": [
+                        {"key": "first", "value": {"count": 1}},
+                        {"count": 2}
+                    ],
+                    "status": 200
+                }
+            ]"#,
+        )
+        .unwrap();
+
+        let metrics = engine.transform(&responses).unwrap();
+        let find = |name: &str| {
+            metrics
+                .iter()
+                .find(|m| m.name == name)
+                .unwrap_or_else(|| panic!("{} missing from {:?}", name, metrics))
+        };
+        assert_eq!(find("jvm_gc_last_duration_ms").value, 12.0);
+        let before = find("jvm_gc_last_memory_used_Before_bytes");
+        assert_eq!(before.value, 100.0);
+        assert_eq!(before.labels.get("pool"), Some("G1 Eden Space"));
+        assert_eq!(find("jvm_gc_last_memory_used_After_bytes").value, 0.0);
+
+        let rows: Vec<(&str, f64)> = metrics
+            .iter()
+            .filter(|m| m.name == "test_rows_count")
+            .map(|m| (m.labels.get("row").unwrap(), m.value))
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert!(rows.contains(&("first", 1.0)));
+        // Elements that are not rows are named by their index
+        assert!(rows.contains(&("1", 2.0)));
+
+        let names: Vec<String> = engine
+            .flattened_names(&responses)
+            .into_iter()
+            .map(|name| name.name)
+            .collect();
+        assert!(names.contains(
+            &"java.lang<name=G1 Young Generation><type=GarbageCollector><LastGcInfo><memoryUsageAfterGc><G1 Eden Space><max>".to_string()
+        ));
+        assert!(names.contains(&"test<type=Tabular><Rows><first><count>".to_string()));
+    }
+
+    #[test]
+    fn test_preview() {
+        let engine = create_test_engine().with_lowercase_names(true);
+
+        let (index, metric) = engine
+            .preview("java.lang<type=Threading><ThreadCount>")
+            .unwrap()
+            .unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(metric.unwrap().name, "jvm_threads_threadcount");
+
+        let (index, metric) = engine
+            .preview("java.lang<type=Memory><HeapMemoryUsage><used>")
+            .unwrap()
+            .unwrap();
+        assert_eq!(index, 0);
+        assert_eq!(metric.unwrap().labels.get("area"), Some("heap"));
+
+        assert!(engine
+            .preview("java.nio<type=BufferPool><Count>")
+            .unwrap()
+            .is_none());
+    }
+
     #[test]
     fn test_lowercase_options() {
         let engine = TransformEngine::empty()
@@ -696,7 +1812,7 @@ mod tests {
         assert_eq!(metric.name, "test_metric");
         assert_eq!(metric.metric_type, MetricType::Gauge);
         assert_eq!(metric.help, Some("Test help".to_string()));
-        assert_eq!(metric.labels.get("env"), Some(&"prod".to_string()));
+        assert_eq!(metric.labels.get("env"), Some("prod"));
         assert_eq!(metric.timestamp, Some(1609459200000));
     }
 
@@ -720,6 +1836,7 @@ mod tests {
             timestamp: 1609459200,
             error: None,
             error_type: None,
+            mbean_errors: Vec::new(),
         }];
 
         let metrics = engine.transform(&responses).unwrap();
@@ -756,6 +1873,7 @@ mod tests {
             timestamp: 1609459200,
             error: None,
             error_type: None,
+            mbean_errors: Vec::new(),
         }];
 
         let metrics = engine.transform(&responses).unwrap();
@@ -781,7 +1899,7 @@ mod tests {
         for metric in &metrics {
             assert_eq!(
                 metric.labels.get("area"),
-                Some(&"heap".to_string()),
+                Some("heap"),
                 "Label 'area=heap' should be set from rule"
             );
         }
@@ -824,6 +1942,468 @@ mod tests {
         assert!(attrs.is_empty());
     }
 
+    #[test]
+    fn test_transform_applies_relabel_rules() {
+        use crate::collector::RequestInfo;
+        use crate::transformer::relabel::{RelabelAction, METRIC_NAME_LABEL};
+
+        let engine = create_test_engine().with_relabel_rules(vec![
+            RelabelRule::new(RelabelAction::Drop, ".*_max_.*")
+                .unwrap()
+                .with_source_labels(vec![METRIC_NAME_LABEL.to_string()]),
+            RelabelRule::new(RelabelAction::LabelDrop, "area").unwrap(),
+        ]);
+
+        let mut composite_value = HashMap::new();
+        composite_value.insert("used".to_string(), AttributeValue::Integer(100));
+        composite_value.insert("max".to_string(), AttributeValue::Integer(200));
+
+        let responses = vec![JolokiaResponse {
+            request: RequestInfo {
+                mbean: "java.lang:type=Memory".to_string(),
+                attribute: Some(serde_json::json!("HeapMemoryUsage")),
+                request_type: "read".to_string(),
+            },
+            value: MBeanValue::Composite(composite_value),
+            status: 200,
+            timestamp: 1609459200,
+            error: None,
+            error_type: None,
+            mbean_errors: Vec::new(),
+        }];
+
+        let metrics = engine.transform(&responses).unwrap();
+
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].name, "jvm_memory_heap_used_bytes");
+        assert!(metrics[0].labels.is_empty());
+    }
+
+    #[test]
+    fn test_parallel_transform() {
+        use crate::collector::RequestInfo;
+
+        let engine = create_test_engine();
+
+        let mut wildcard = HashMap::new();
+        let mut threading = HashMap::new();
+        threading.insert("ThreadCount".to_string(), AttributeValue::Integer(10));
+        wildcard.insert("java.lang:type=Threading".to_string(), threading);
+        let mut unmatched = HashMap::new();
+        unmatched.insert("Foo".to_string(), AttributeValue::Integer(1));
+        wildcard.insert("com.example:type=Unmatched".to_string(), unmatched);
+
+        let mut heap = HashMap::new();
+        heap.insert("used".to_string(), AttributeValue::Integer(100));
+        heap.insert("max".to_string(), AttributeValue::Integer(200));
+
+        let responses = vec![
+            JolokiaResponse {
+                request: RequestInfo {
+                    mbean: "java.lang:*".to_string(),
+                    attribute: None,
+                    request_type: "read".to_string(),
+                },
+                value: MBeanValue::Wildcard(wildcard),
+                status: 200,
+                timestamp: 1609459200,
+                error: None,
+                error_type: None,
+                mbean_errors: Vec::new(),
+            },
+            JolokiaResponse {
+                request: RequestInfo {
+                    mbean: "java.lang:type=Memory".to_string(),
+                    attribute: Some(serde_json::json!("HeapMemoryUsage")),
+                    request_type: "read".to_string(),
+                },
+                value: MBeanValue::Composite(heap),
+                status: 200,
+                timestamp: 1609459200,
+                error: None,
+                error_type: None,
+                mbean_errors: Vec::new(),
+            },
+        ];
+
+        let names = |engine: &TransformEngine| {
+            let mut names: Vec<(String, f64)> = engine
+                .transform(&responses)
+                .unwrap()
+                .into_iter()
+                .map(|m| (m.name, m.value))
+                .collect();
+            names.sort_by(|a, b| a.0.cmp(&b.0));
+            names
+        };
+
+        let sequential = names(&engine);
+        assert_eq!(sequential.len(), 3);
+        assert_eq!(names(&create_test_engine().with_parallel(true)), sequential);
+    }
+
+    #[test]
+    fn test_transform_with_stats() {
+        use crate::collector::RequestInfo;
+
+        let engine = create_test_engine();
+
+        let mut wildcard = HashMap::new();
+        let mut threading = HashMap::new();
+        threading.insert("ThreadCount".to_string(), AttributeValue::Integer(10));
+        wildcard.insert("java.lang:type=Threading".to_string(), threading);
+        let mut unmatched = HashMap::new();
+        unmatched.insert("Foo".to_string(), AttributeValue::Integer(1));
+        wildcard.insert("com.example:type=Unmatched".to_string(), unmatched);
+
+        let mut heap = HashMap::new();
+        heap.insert("used".to_string(), AttributeValue::Integer(100));
+        heap.insert("max".to_string(), AttributeValue::Integer(200));
+
+        let responses = vec![
+            JolokiaResponse {
+                request: RequestInfo {
+                    mbean: "java.lang:*".to_string(),
+                    attribute: None,
+                    request_type: "read".to_string(),
+                },
+                value: MBeanValue::Wildcard(wildcard),
+                status: 200,
+                timestamp: 1609459200,
+                error: None,
+                error_type: None,
+                mbean_errors: Vec::new(),
+            },
+            JolokiaResponse {
+                request: RequestInfo {
+                    mbean: "java.lang:type=Memory".to_string(),
+                    attribute: Some(serde_json::json!("HeapMemoryUsage")),
+                    request_type: "read".to_string(),
+                },
+                value: MBeanValue::Composite(heap),
+                status: 200,
+                timestamp: 1609459200,
+                error: None,
+                error_type: None,
+                mbean_errors: Vec::new(),
+            },
+        ];
+
+        let (metrics, stats) = engine.transform_with_stats(&responses).unwrap();
+        assert_eq!(stats.mbeans_discovered, 3);
+        assert_eq!(stats.mbeans_scraped, 2);
+        assert_eq!(stats.attributes_transformed, metrics.len());
+    }
+
+    #[test]
+    fn test_transform_replays_cached_values() {
+        use crate::collector::RequestInfo;
+
+        let rules = RuleSet::from_rules(vec![
+            Rule::new(
+                r"java\.lang<type=Memory><HeapMemoryUsage><max>",
+                "jvm_memory_heap_max_bytes",
+                MetricType::Gauge,
+            )
+            .with_cache(true),
+            Rule::new(
+                r"java\.lang<type=Memory><HeapMemoryUsage><used>",
+                "jvm_memory_heap_used_bytes",
+                MetricType::Gauge,
+            ),
+        ]);
+        let engine = TransformEngine::new(rules.clone());
+
+        let response = |used: i64, max: i64| {
+            let mut composite = HashMap::new();
+            composite.insert("used".to_string(), AttributeValue::Integer(used));
+            composite.insert("max".to_string(), AttributeValue::Integer(max));
+            JolokiaResponse {
+                request: RequestInfo {
+                    mbean: "java.lang:type=Memory".to_string(),
+                    attribute: Some(serde_json::json!("HeapMemoryUsage")),
+                    request_type: "read".to_string(),
+                },
+                value: MBeanValue::Composite(composite),
+                status: 200,
+                timestamp: 1609459200,
+                error: None,
+                error_type: None,
+                mbean_errors: Vec::new(),
+            }
+        };
+
+        let first = engine.transform(&[response(100, 200)]).unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(engine.value_cache().len(), 1);
+
+        // Cached max stays at its first value, used is refreshed
+        let second = engine.transform(&[response(150, 999)]).unwrap();
+        let value_of = |name: &str| second.iter().find(|m| m.name == name).unwrap().value;
+        assert_eq!(second.len(), 2);
+        assert_eq!(value_of("jvm_memory_heap_max_bytes"), 200.0);
+        assert_eq!(value_of("jvm_memory_heap_used_bytes"), 150.0);
+
+        // Cached values are replayed even when the MBean is not read
+        let third = engine.transform(&[]).unwrap();
+        assert_eq!(third.len(), 1);
+        assert_eq!(third[0].name, "jvm_memory_heap_max_bytes");
+
+        // Expired values are collected again and dropped once the MBean is gone
+        let ttl = Duration::from_millis(20);
+        let engine = TransformEngine::new(rules).with_series_ttl(Some(ttl));
+        engine.transform(&[response(100, 200)]).unwrap();
+        std::thread::sleep(ttl * 2);
+        let refreshed = engine.transform(&[response(150, 999)]).unwrap();
+        assert!(refreshed
+            .iter()
+            .any(|m| m.name == "jvm_memory_heap_max_bytes" && m.value == 999.0));
+        std::thread::sleep(ttl * 2);
+        assert!(engine.transform(&[]).unwrap().is_empty());
+        assert!(engine.value_cache().is_empty());
+    }
+
+    #[test]
+    fn test_transform_applies_renames() {
+        let engine = create_test_engine().with_renames(MetricRenames::new().with_rename(
+            "jvm_threads_ThreadCount",
+            "jvm_threads_current",
+            true,
+        ));
+
+        let mut attrs = HashMap::new();
+        attrs.insert("ThreadCount".to_string(), AttributeValue::Integer(42));
+        let mut wildcard = HashMap::new();
+        wildcard.insert("java.lang:type=Threading".to_string(), attrs);
+        let responses = vec![JolokiaResponse {
+            request: crate::collector::RequestInfo {
+                mbean: "java.lang:type=Threading".to_string(),
+                attribute: None,
+                request_type: "read".to_string(),
+            },
+            value: MBeanValue::Wildcard(wildcard),
+            status: 200,
+            timestamp: 1609459200,
+            error: None,
+            error_type: None,
+            mbean_errors: Vec::new(),
+        }];
+
+        let metrics = engine.transform(&responses).unwrap();
+        let names: Vec<&str> = metrics.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["jvm_threads_current", "jvm_threads_ThreadCount"]
+        );
+    }
+
+    #[test]
+    fn test_transform_applies_derived_metrics() {
+        let engine = create_test_engine()
+            .with_renames(MetricRenames::new().with_rename(
+                "jvm_threads_ThreadCount",
+                "jvm_threads_current",
+                false,
+            ))
+            .with_derived_metrics(vec![DerivedMetric::new(
+                "jvm_threads_daemon_ratio",
+                Expr::parse_metrics("jvm_threads_DaemonThreadCount / jvm_threads_current").unwrap(),
+            )]);
+
+        let mut attrs = HashMap::new();
+        attrs.insert("ThreadCount".to_string(), AttributeValue::Integer(40));
+        attrs.insert("DaemonThreadCount".to_string(), AttributeValue::Integer(10));
+        let mut wildcard = HashMap::new();
+        wildcard.insert("java.lang:type=Threading".to_string(), attrs);
+        let responses = vec![JolokiaResponse {
+            request: crate::collector::RequestInfo {
+                mbean: "java.lang:type=Threading".to_string(),
+                attribute: None,
+                request_type: "read".to_string(),
+            },
+            value: MBeanValue::Wildcard(wildcard),
+            status: 200,
+            timestamp: 1609459200,
+            error: None,
+            error_type: None,
+            mbean_errors: Vec::new(),
+        }];
+
+        let metrics = engine.transform(&responses).unwrap();
+        let ratio = metrics
+            .iter()
+            .find(|m| m.name == "jvm_threads_daemon_ratio")
+            .unwrap();
+        assert_eq!(ratio.value, 0.25);
+        assert_eq!(ratio.metric_type, MetricType::Gauge);
+    }
+
+    #[test]
+    fn test_transform_enforces_series_limits() {
+        use crate::collector::RequestInfo;
+
+        let engine = create_test_engine()
+            .with_series_limits(SeriesLimits::new().with_max_series_per_scrape(Some(1)));
+
+        let mut composite_value = HashMap::new();
+        composite_value.insert("used".to_string(), AttributeValue::Integer(100));
+        composite_value.insert("max".to_string(), AttributeValue::Integer(200));
+
+        let responses = vec![JolokiaResponse {
+            request: RequestInfo {
+                mbean: "java.lang:type=Memory".to_string(),
+                attribute: Some(serde_json::json!("HeapMemoryUsage")),
+                request_type: "read".to_string(),
+            },
+            value: MBeanValue::Composite(composite_value),
+            status: 200,
+            timestamp: 1609459200,
+            error: None,
+            error_type: None,
+            mbean_errors: Vec::new(),
+        }];
+
+        let metrics = engine.transform(&responses).unwrap();
+        assert_eq!(metrics.len(), 1);
+    }
+
+    fn counter_engine() -> TransformEngine {
+        TransformEngine::new(RuleSet::from_rules(vec![
+            Rule::builder(r"java\.lang<type=Threading><(TotalStartedThreadCount)>")
+                .name("jvm_threads_started")
+                .metric_type(MetricType::Counter)
+                .build(),
+            Rule::builder(r"java\.lang<type=Threading><(\w+)>")
+                .name("jvm_threads_$1")
+                .metric_type(MetricType::Gauge)
+                .build(),
+        ]))
+    }
+
+    #[test]
+    fn test_counter_total_suffix() {
+        let engine = counter_engine();
+        let metrics = engine
+            .transform_simple(
+                "java.lang:type=Threading",
+                Some("TotalStartedThreadCount"),
+                7.0,
+            )
+            .unwrap();
+        assert_eq!(metrics[0].name, "jvm_threads_started");
+
+        let engine = counter_engine().with_counter_total_suffix(true);
+        let metrics = engine
+            .transform_simple(
+                "java.lang:type=Threading",
+                Some("TotalStartedThreadCount"),
+                7.0,
+            )
+            .unwrap();
+        assert_eq!(metrics[0].name, "jvm_threads_started_total");
+
+        // Gauges keep their names
+        let metrics = engine
+            .transform_simple("java.lang:type=Threading", Some("ThreadCount"), 7.0)
+            .unwrap();
+        assert_eq!(metrics[0].name, "jvm_threads_ThreadCount");
+    }
+
+    #[test]
+    fn test_transform_checks_counters() {
+        use crate::collector::RequestInfo;
+
+        let response = |started: i64| JolokiaResponse {
+            request: RequestInfo {
+                mbean: "java.lang:type=Threading".to_string(),
+                attribute: Some(serde_json::json!("TotalStartedThreadCount")),
+                request_type: "read".to_string(),
+            },
+            value: MBeanValue::Number(started as f64),
+            status: 200,
+            timestamp: 1609459200,
+            error: None,
+            error_type: None,
+            mbean_errors: Vec::new(),
+        };
+
+        let engine = counter_engine();
+        let registry = crate::metrics::internal_metrics();
+        let before = registry.semantics.counter_decreases_total.get();
+        let resets_before = registry.counter_resets("jvm_threads_started");
+
+        engine.transform(&[response(10)]).unwrap();
+        // Clones share the tracker
+        engine.clone().transform(&[response(12)]).unwrap();
+        assert_eq!(engine.counters.len(), 1);
+
+        let metrics = engine.transform(&[response(3)]).unwrap();
+        assert_eq!(metrics[0].value, 3.0);
+        assert!(registry.semantics.counter_decreases_total.get() > before);
+        assert!(registry.counter_resets("jvm_threads_started") > resets_before);
+
+        let engine = counter_engine().with_counter_resets(CounterResetMode::Monotonic);
+        engine.transform(&[response(10)]).unwrap();
+        let metrics = engine.transform(&[response(3)]).unwrap();
+        assert_eq!(metrics[0].value, 13.0);
+    }
+
+    #[test]
+    fn test_transform_resolves_metadata_conflicts() {
+        let engine = || {
+            TransformEngine::new(RuleSet::from_rules(vec![
+                Rule::builder(r"java\.lang<type=Threading><DaemonThreadCount>")
+                    .name("jvm_threads")
+                    .metric_type(MetricType::Gauge)
+                    .build(),
+                Rule::builder(r"java\.lang<type=Threading><TotalStartedThreadCount>")
+                    .name("jvm_threads")
+                    .metric_type(MetricType::Counter)
+                    .build(),
+            ]))
+        };
+
+        let mut attrs = HashMap::new();
+        attrs.insert("DaemonThreadCount".to_string(), AttributeValue::Integer(5));
+        attrs.insert(
+            "TotalStartedThreadCount".to_string(),
+            AttributeValue::Integer(50),
+        );
+        let mut wildcard = HashMap::new();
+        wildcard.insert("java.lang:type=Threading".to_string(), attrs);
+        let responses = vec![JolokiaResponse {
+            request: crate::collector::RequestInfo {
+                mbean: "java.lang:type=*".to_string(),
+                attribute: None,
+                request_type: "read".to_string(),
+            },
+            value: MBeanValue::Wildcard(wildcard),
+            status: 200,
+            timestamp: 1609459200,
+            error: None,
+            error_type: None,
+            mbean_errors: Vec::new(),
+        }];
+
+        let metrics = engine()
+            .with_conflict_policy(ConflictPolicy::Untyped)
+            .transform(&responses)
+            .unwrap();
+        assert_eq!(metrics.len(), 2);
+        assert!(metrics.iter().all(|m| m.metric_type == MetricType::Untyped));
+
+        let metrics = engine().transform(&responses).unwrap();
+        assert_eq!(metrics[0].metric_type, metrics[1].metric_type);
+
+        let err = engine()
+            .with_conflict_policy(ConflictPolicy::Error)
+            .transform(&responses)
+            .unwrap_err();
+        assert!(
+            matches!(err, TransformError::MetadataConflict { ref name, .. } if name == "jvm_threads")
+        );
+    }
+
     /// Test transformation with multiple attributes in a single response
     #[test]
     fn test_transform_multiple_attributes() {
@@ -847,6 +2427,7 @@ mod tests {
             timestamp: 1609459200,
             error: None,
             error_type: None,
+            mbean_errors: Vec::new(),
         }];
 
         let metrics = engine.transform(&responses).unwrap();
@@ -864,4 +2445,74 @@ mod tests {
             metric_names
         );
     }
+
+    #[test]
+    fn test_flattened_names() {
+        use crate::collector::RequestInfo;
+
+        let engine = create_test_engine();
+
+        let mut heap = HashMap::new();
+        heap.insert("used".to_string(), AttributeValue::Integer(1024));
+        heap.insert("max".to_string(), AttributeValue::Integer(4096));
+        let mut gc = HashMap::new();
+        gc.insert("CollectionCount".to_string(), AttributeValue::Integer(7));
+        gc.insert("Name".to_string(), AttributeValue::String("G1".to_string()));
+        let mut wildcard = HashMap::new();
+        wildcard.insert("java.lang:type=GarbageCollector,name=G1".to_string(), gc);
+
+        let response = |mbean: &str, attribute: Option<&str>, value: MBeanValue| JolokiaResponse {
+            request: RequestInfo {
+                mbean: mbean.to_string(),
+                attribute: attribute.map(|a| serde_json::json!(a)),
+                request_type: "read".to_string(),
+            },
+            value,
+            status: 200,
+            timestamp: 1609459200,
+            error: None,
+            error_type: None,
+            mbean_errors: Vec::new(),
+        };
+        let responses = vec![
+            response(
+                "java.lang:type=Memory",
+                Some("HeapMemoryUsage"),
+                MBeanValue::Composite(heap),
+            ),
+            response(
+                "java.lang:type=Threading",
+                Some("ThreadCount"),
+                MBeanValue::Number(42.0),
+            ),
+            response("java.lang:*", None, MBeanValue::Wildcard(wildcard)),
+        ];
+
+        let names = engine.flattened_names(&responses);
+        let expected = [
+            (
+                "java.lang<name=G1><type=GarbageCollector><CollectionCount>",
+                None,
+            ),
+            ("java.lang<type=Memory><HeapMemoryUsage><max>", Some(0)),
+            ("java.lang<type=Memory><HeapMemoryUsage><used>", Some(0)),
+            ("java.lang<type=Threading><ThreadCount>", Some(1)),
+        ];
+        assert_eq!(
+            names,
+            expected
+                .iter()
+                .map(|(name, rule)| FlattenedName {
+                    name: name.to_string(),
+                    rule: *rule,
+                })
+                .collect::<Vec<_>>()
+        );
+
+        // Only the wildcard MBean has no matching value
+        assert_eq!(
+            engine.unmatched_mbeans(&responses),
+            vec!["java.lang:type=GarbageCollector,name=G1"]
+        );
+    }
 }
@@ -0,0 +1,397 @@
+//! Value expressions
+//!
+//! A tiny arithmetic language for unit conversions in rules. `valueFactor`
+//! accepts a constant expression (`1/1024`), and `valueTransform` an
+//! expression of the attribute value `x` (`x / 1048576`, `(x - 32) * 5 / 9`).
+//!
+//! Expressions consist of numbers (`2`, `0.5`, `1e-3`), `x`, the operators
+//! `+ - * /` with the usual precedence, unary minus and parentheses. There
+//! are no functions or loops, so evaluating one cannot fail: division
+//! by zero yields an infinite or NaN value as in IEEE arithmetic.
+//!
+//! Derived metrics use the same language with metric names in place of `x`
+//! (`jvm_memory_heap_used_bytes / jvm_memory_heap_max_bytes`), see
+//! [`Expr::parse_metrics`].
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Maximum nesting of parentheses and unary minus
+const MAX_DEPTH: usize = 32;
+
+/// Parsed value expression, kept with its source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Expr {
+    source: String,
+    node: Node,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f64),
+    Value,
+    Metric(String),
+    Neg(Box<Node>),
+    Binary(Op, Box<Node>, Box<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Expr {
+    /// Parse an expression
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first syntax error.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        Self::parse_tokens(source, tokenize(source, false)?)
+    }
+
+    /// Parse an expression over metric names instead of `x`
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first syntax error.
+    pub fn parse_metrics(source: &str) -> Result<Self, String> {
+        Self::parse_tokens(source, tokenize(source, true)?)
+    }
+
+    fn parse_tokens(source: &str, tokens: Vec<Token>) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            depth: 0,
+        };
+        let node = parser.expr()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("unexpected '{}'", token));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            node,
+        })
+    }
+
+    /// Parse an expression that does not use `x`, and evaluate it
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the syntax error, or of the use of `x`.
+    pub fn constant(source: &str) -> Result<f64, String> {
+        let expr = Self::parse(source)?;
+        if expr.node.uses_value() {
+            return Err("x is not allowed in a constant expression".to_string());
+        }
+        Ok(expr.eval(0.0))
+    }
+
+    /// Evaluate the expression with `x` set to `value`
+    pub fn eval(&self, value: f64) -> f64 {
+        self.node.eval(value, &|_| None).unwrap_or(f64::NAN)
+    }
+
+    /// Evaluate an expression of metric names, looking up each value
+    ///
+    /// Returns `None` if a metric has no value.
+    pub fn eval_metrics(&self, lookup: &dyn Fn(&str) -> Option<f64>) -> Option<f64> {
+        self.node.eval(f64::NAN, lookup)
+    }
+
+    /// Metric names referenced by the expression, without duplicates
+    pub fn metrics(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.node.collect_metrics(&mut names);
+        names
+    }
+
+    /// Source text of the expression
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl TryFrom<String> for Expr {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Self::parse(&source)
+    }
+}
+
+impl From<Expr> for String {
+    fn from(expr: Expr) -> Self {
+        expr.source
+    }
+}
+
+impl Node {
+    fn eval(&self, value: f64, lookup: &dyn Fn(&str) -> Option<f64>) -> Option<f64> {
+        Some(match self {
+            Node::Number(n) => *n,
+            Node::Value => value,
+            Node::Metric(name) => lookup(name)?,
+            Node::Neg(node) => -node.eval(value, lookup)?,
+            Node::Binary(op, left, right) => {
+                let (left, right) = (left.eval(value, lookup)?, right.eval(value, lookup)?);
+                match op {
+                    Op::Add => left + right,
+                    Op::Sub => left - right,
+                    Op::Mul => left * right,
+                    Op::Div => left / right,
+                }
+            }
+        })
+    }
+
+    fn collect_metrics<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Node::Number(_) | Node::Value => {}
+            Node::Metric(name) => {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+            Node::Neg(node) => node.collect_metrics(names),
+            Node::Binary(_, left, right) => {
+                left.collect_metrics(names);
+                right.collect_metrics(names);
+            }
+        }
+    }
+
+    fn uses_value(&self) -> bool {
+        match self {
+            Node::Number(_) | Node::Metric(_) => false,
+            Node::Value => true,
+            Node::Neg(node) => node.uses_value(),
+            Node::Binary(_, left, right) => left.uses_value() || right.uses_value(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Value,
+    Metric(String),
+    Op(char),
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Value => f.write_str("x"),
+            Token::Metric(name) => f.write_str(name),
+            Token::Op(c) => write!(f, "{}", c),
+            Token::Open => f.write_str("("),
+            Token::Close => f.write_str(")"),
+        }
+    }
+}
+
+/// Split an expression into tokens; names are metric names if `metrics` is
+/// set, otherwise only `x` is accepted
+fn tokenize(source: &str, metrics: bool) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '+' | '-' | '*' | '/' => tokens.push(Token::Op(c)),
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            c if c.is_ascii_alphabetic() || c == '_' || c == ':' => {
+                let mut end = start + 1;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_' || c == ':') {
+                        break;
+                    }
+                    end = i + 1;
+                    chars.next();
+                }
+                let name = &source[start..end];
+                if metrics {
+                    tokens.push(Token::Metric(name.to_string()));
+                } else if name == "x" {
+                    tokens.push(Token::Value);
+                } else {
+                    return Err(format!("unexpected '{}'", name));
+                }
+            }
+            '0'..='9' | '.' => {
+                let mut end = start + 1;
+                let mut previous = c;
+                while let Some(&(i, c)) = chars.peek() {
+                    let exponent_sign = matches!(c, '+' | '-') && matches!(previous, 'e' | 'E');
+                    if !(c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E') || exponent_sign) {
+                        break;
+                    }
+                    previous = c;
+                    end = i + 1;
+                    chars.next();
+                }
+                let number = &source[start..end];
+                let value = number
+                    .parse()
+                    .map_err(|_| format!("invalid number '{}'", number))?;
+                tokens.push(Token::Number(value));
+            }
+            c => return Err(format!("unexpected '{}'", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser over the tokens
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn next_op(&mut self, ops: [char; 2]) -> Option<Op> {
+        let op = match self.tokens.get(self.pos) {
+            Some(Token::Op(c)) if ops.contains(c) => match c {
+                '+' => Op::Add,
+                '-' => Op::Sub,
+                '*' => Op::Mul,
+                _ => Op::Div,
+            },
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(op)
+    }
+
+    /// `term (('+' | '-') term)*`
+    fn expr(&mut self) -> Result<Node, String> {
+        let mut node = self.term()?;
+        while let Some(op) = self.next_op(['+', '-']) {
+            node = Node::Binary(op, Box::new(node), Box::new(self.term()?));
+        }
+        Ok(node)
+    }
+
+    /// `unary (('*' | '/') unary)*`
+    fn term(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        while let Some(op) = self.next_op(['*', '/']) {
+            node = Node::Binary(op, Box::new(node), Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+
+    /// `'-' unary | number | 'x' | metric | '(' expr ')'`
+    fn unary(&mut self) -> Result<Node, String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err("expression is nested too deeply".to_string());
+        }
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        let node = match token {
+            Some(Token::Op('-')) => Node::Neg(Box::new(self.unary()?)),
+            Some(Token::Number(n)) => Node::Number(n),
+            Some(Token::Value) => Node::Value,
+            Some(Token::Metric(name)) => Node::Metric(name),
+            Some(Token::Open) => {
+                let node = self.expr()?;
+                if self.tokens.get(self.pos) != Some(&Token::Close) {
+                    return Err("missing ')'".to_string());
+                }
+                self.pos += 1;
+                node
+            }
+            Some(token) => return Err(format!("unexpected '{}'", token)),
+            None => return Err("unexpected end of expression".to_string()),
+        };
+        self.depth -= 1;
+        Ok(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str, x: f64) -> f64 {
+        Expr::parse(source).unwrap().eval(x)
+    }
+
+    #[test]
+    fn test_eval() {
+        assert_eq!(eval("x / 1048576", 2097152.0), 2.0);
+        assert_eq!(eval("(x - 32) * 5 / 9", 212.0), 100.0);
+        assert_eq!(eval("1 + 2 * 3", 0.0), 7.0);
+        assert_eq!(eval("-x - -1", 3.0), -2.0);
+        assert_eq!(eval("1e-3*x", 1500.0), 1.5);
+        assert_eq!(eval("2.5E+2", 0.0), 250.0);
+        assert_eq!(eval("x / 0", 1.0), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_constant() {
+        assert_eq!(Expr::constant("1/1024"), Ok(1.0 / 1024.0));
+        assert_eq!(Expr::constant("0.001"), Ok(0.001));
+        assert!(Expr::constant("x * 2").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (source, error) in [
+            ("", "unexpected end of expression"),
+            ("x +", "unexpected end of expression"),
+            ("(x * 2", "missing ')'"),
+            ("x 2", "unexpected '2'"),
+            ("y * 2", "unexpected 'y'"),
+            ("1..2", "invalid number '1..2'"),
+            ("* 2", "unexpected '*'"),
+        ] {
+            assert_eq!(Expr::parse(source), Err(error.to_string()), "{}", source);
+        }
+        assert!(Expr::parse(&format!("{}x", "-".repeat(40))).is_err());
+    }
+
+    #[test]
+    fn test_metric_expressions() {
+        let expr = Expr::parse_metrics("(used + committed) / max - used").unwrap();
+        assert_eq!(expr.metrics(), vec!["used", "committed", "max"]);
+
+        let lookup = |name: &str| match name {
+            "used" => Some(2.0),
+            "committed" => Some(6.0),
+            "max" => Some(4.0),
+            _ => None,
+        };
+        assert_eq!(expr.eval_metrics(&lookup), Some(0.0));
+        let missing = Expr::parse_metrics("used / jvm:heap_max").unwrap();
+        assert_eq!(missing.eval_metrics(&lookup), None);
+        assert!(Expr::parse("used / 2").is_err());
+    }
+
+    #[test]
+    fn test_serde() {
+        let expr: Expr = serde_yaml::from_str("x / 1024").unwrap();
+        assert_eq!(expr.eval(2048.0), 2.0);
+        assert_eq!(serde_yaml::to_string(&expr).unwrap().trim(), "x / 1024");
+        assert!(serde_yaml::from_str::<Expr>("x /").is_err());
+    }
+}
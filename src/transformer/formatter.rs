@@ -11,6 +11,7 @@
 //! <metric_name>{<label1>="<value1>",<label2>="<value2>"} <value> [<timestamp>]
 //! ```
 
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 
 use super::engine::PrometheusMetric;
@@ -68,67 +69,27 @@ impl PrometheusFormatter {
     /// - Metrics with the same name are grouped together
     /// - Histogram metrics are grouped by base name (without _bucket/_sum/_count suffixes)
     pub fn format(&self, metrics: &[PrometheusMetric]) -> String {
-        use crate::transformer::rules::MetricType;
-
-        if metrics.is_empty() {
-            return String::new();
-        }
-
         let mut output = String::with_capacity(metrics.len() * 100);
-        let mut seen_metrics: HashSet<String> = HashSet::new();
-        let mut seen_histogram_bases: HashSet<String> = HashSet::new();
-
-        // Group metrics by name for proper HELP/TYPE ordering
-        let grouped = Self::group_by_name(metrics);
-
-        for (name, group) in grouped {
-            let is_histogram = group[0].metric_type == MetricType::Histogram;
-
-            if is_histogram {
-                // For histogram metrics, emit HELP/TYPE for the base name only
-                let base_name = Self::get_histogram_base_name(&name);
-                if !seen_histogram_bases.contains(&base_name) {
-                    seen_histogram_bases.insert(base_name.clone());
-
-                    // HELP line
-                    if let Some(help) = &group[0].help {
-                        output.push_str(&format!(
-                            "# HELP {} {}\n",
-                            base_name,
-                            Self::escape_help(help)
-                        ));
-                    }
-
-                    // TYPE line with histogram type
-                    output.push_str(&format!("# TYPE {} histogram\n", base_name));
-                }
-            } else {
-                // Non-histogram metrics: HELP/TYPE are emitted once per metric name
-                if !seen_metrics.contains(&name) {
-                    seen_metrics.insert(name.clone());
-
-                    // HELP line
-                    if let Some(help) = &group[0].help {
-                        output.push_str(&format!("# HELP {} {}\n", name, Self::escape_help(help)));
-                    }
-
-                    // TYPE line
-                    output.push_str(&format!(
-                        "# TYPE {} {}\n",
-                        name,
-                        group[0].metric_type.as_str()
-                    ));
-                }
-            }
+        self.chunks(metrics).write_next(&mut output, usize::MAX);
+        output
+    }
 
-            // Metric lines
-            for metric in group {
-                output.push_str(&self.format_metric_line(metric));
-                output.push('\n');
-            }
+    /// Format metrics incrementally, in chunks of about [`CHUNK_SIZE`] bytes
+    ///
+    /// The chunks add up to the output of [`format`](Self::format), but only
+    /// one is held in memory at a time, e.g. while streaming a response body.
+    /// Pass a `Vec` to get chunks that own their metrics.
+    pub fn chunks<'a>(&self, metrics: impl Into<Cow<'a, [PrometheusMetric]>>) -> FormatChunks<'a> {
+        let metrics = metrics.into();
+        let groups = Self::group_by_name(&metrics);
+        FormatChunks {
+            formatter: self.clone(),
+            metrics,
+            groups: groups.into_iter(),
+            group: Vec::new(),
+            line: 0,
+            seen_histogram_bases: HashSet::new(),
         }
-
-        output
     }
 
     /// Get the base name for histogram metrics by removing suffixes
@@ -144,56 +105,84 @@ impl PrometheusFormatter {
         }
     }
 
-    /// Group metrics by name, preserving order of first occurrence
-    fn group_by_name(metrics: &[PrometheusMetric]) -> Vec<(String, Vec<&PrometheusMetric>)> {
-        let mut groups: HashMap<String, Vec<&PrometheusMetric>> = HashMap::new();
-        let mut order: Vec<String> = Vec::new();
-
-        for metric in metrics {
-            if !groups.contains_key(&metric.name) {
-                order.push(metric.name.clone());
-            }
-            groups.entry(metric.name.clone()).or_default().push(metric);
+    /// Group metric indices by name, preserving order of first occurrence
+    fn group_by_name(metrics: &[PrometheusMetric]) -> Vec<Vec<usize>> {
+        let mut positions: HashMap<&str, usize> = HashMap::new();
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+
+        for (index, metric) in metrics.iter().enumerate() {
+            let position = *positions.entry(metric.name.as_str()).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[position].push(index);
         }
 
-        order
-            .into_iter()
-            .filter_map(|name| groups.remove(&name).map(|g| (name, g)))
-            .collect()
+        groups
     }
 
-    /// Format a single metric line
-    fn format_metric_line(&self, metric: &PrometheusMetric) -> String {
-        let mut line = metric.name.clone();
+    /// Write HELP and TYPE lines for a group of metrics sharing a name
+    ///
+    /// Histogram series are announced once under their base name (without
+    /// `_bucket`/`_sum`/`_count`), tracked in `seen_histogram_bases`.
+    fn write_header(
+        first: &PrometheusMetric,
+        seen_histogram_bases: &mut HashSet<String>,
+        output: &mut String,
+    ) {
+        use crate::transformer::rules::MetricType;
 
-        // Labels (sorted for deterministic output)
-        if !metric.labels.is_empty() {
-            let mut sorted_labels: Vec<(&String, &String)> = metric.labels.iter().collect();
-            sorted_labels.sort_by_key(|(k, _)| *k);
+        let (name, metric_type) = if first.metric_type == MetricType::Histogram {
+            let base_name = Self::get_histogram_base_name(&first.name);
+            if !seen_histogram_bases.insert(base_name.clone()) {
+                return;
+            }
+            (Cow::Owned(base_name), "histogram")
+        } else {
+            (
+                Cow::Borrowed(first.name.as_str()),
+                first.metric_type.as_str(),
+            )
+        };
+
+        if let Some(help) = &first.help {
+            output.push_str(&format!("# HELP {} {}\n", name, Self::escape_help(help)));
+        }
+        output.push_str(&format!("# TYPE {} {}\n", name, metric_type));
+    }
 
-            let label_pairs: Vec<String> = sorted_labels
-                .iter()
-                .map(|(k, v)| format!("{}=\"{}\"", k, Self::escape_label_value(v)))
-                .collect();
+    /// Write a single metric line, including the trailing newline
+    fn write_metric_line(&self, metric: &PrometheusMetric, output: &mut String) {
+        output.push_str(&metric.name);
 
-            line.push('{');
-            line.push_str(&label_pairs.join(","));
-            line.push('}');
+        // Labels (kept sorted, so output is deterministic)
+        if !metric.labels.is_empty() {
+            output.push('{');
+            for (i, (k, v)) in metric.labels.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                output.push_str(k);
+                output.push_str("=\"");
+                Self::push_escaped_label_value(v, output);
+                output.push('"');
+            }
+            output.push('}');
         }
 
         // Value
-        line.push(' ');
-        line.push_str(&Self::format_value(metric.value));
+        output.push(' ');
+        output.push_str(&Self::format_value(metric.value));
 
         // Timestamp (optional)
         if self.include_timestamp {
             if let Some(ts) = metric.timestamp {
-                line.push(' ');
-                line.push_str(&ts.to_string());
+                output.push(' ');
+                output.push_str(&ts.to_string());
             }
         }
 
-        line
+        output.push('\n');
     }
 
     /// Format a numeric value for Prometheus
@@ -231,20 +220,72 @@ impl PrometheusFormatter {
         help.replace('\\', "\\\\").replace('\n', "\\n")
     }
 
-    /// Escape label value
+    /// Append an escaped label value to `output`
     ///
     /// Escapes backslash, double-quote, and newline characters.
-    fn escape_label_value(value: &str) -> String {
-        let mut escaped = String::with_capacity(value.len());
+    fn push_escaped_label_value(value: &str, output: &mut String) {
         for c in value.chars() {
             match c {
-                '\\' => escaped.push_str("\\\\"),
-                '"' => escaped.push_str("\\\""),
-                '\n' => escaped.push_str("\\n"),
-                _ => escaped.push(c),
+                '\\' => output.push_str("\\\\"),
+                '"' => output.push_str("\\\""),
+                '\n' => output.push_str("\\n"),
+                _ => output.push(c),
+            }
+        }
+    }
+}
+
+/// Bytes after which a chunk of [`FormatChunks`] is cut
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Formatted output in chunks, created by [`PrometheusFormatter::chunks`]
+///
+/// Chunks end on line boundaries, also within large groups of one metric.
+#[derive(Debug)]
+pub struct FormatChunks<'a> {
+    formatter: PrometheusFormatter,
+    metrics: Cow<'a, [PrometheusMetric]>,
+    /// Groups not started yet
+    groups: std::vec::IntoIter<Vec<usize>>,
+    /// Group being written
+    group: Vec<usize>,
+    /// Next line of `group`
+    line: usize,
+    /// Histogram base names whose HELP/TYPE were written
+    seen_histogram_bases: HashSet<String>,
+}
+
+impl FormatChunks<'_> {
+    /// Write lines to `output` until it holds at least `limit` bytes or all
+    /// metrics are written
+    fn write_next(&mut self, output: &mut String, limit: usize) {
+        while output.len() < limit {
+            if self.line == self.group.len() {
+                let Some(group) = self.groups.next() else {
+                    return;
+                };
+                self.group = group;
+                self.line = 0;
+                PrometheusFormatter::write_header(
+                    &self.metrics[self.group[0]],
+                    &mut self.seen_histogram_bases,
+                    output,
+                );
             }
+            let metric = &self.metrics[self.group[self.line]];
+            self.formatter.write_metric_line(metric, output);
+            self.line += 1;
         }
-        escaped
+    }
+}
+
+impl Iterator for FormatChunks<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let mut chunk = String::with_capacity(CHUNK_SIZE + 1024);
+        self.write_next(&mut chunk, CHUNK_SIZE);
+        (!chunk.is_empty()).then_some(chunk)
     }
 }
 
@@ -382,23 +423,17 @@ mod tests {
 
     #[test]
     fn test_escape_label_value() {
-        assert_eq!(PrometheusFormatter::escape_label_value("simple"), "simple");
-        assert_eq!(
-            PrometheusFormatter::escape_label_value("with\"quote"),
-            "with\\\"quote"
-        );
-        assert_eq!(
-            PrometheusFormatter::escape_label_value("with\\backslash"),
-            "with\\\\backslash"
-        );
-        assert_eq!(
-            PrometheusFormatter::escape_label_value("with\nnewline"),
-            "with\\nnewline"
-        );
-        assert_eq!(
-            PrometheusFormatter::escape_label_value("all\"\\\n"),
-            "all\\\"\\\\\\n"
-        );
+        let escape = |value: &str| {
+            let mut escaped = String::new();
+            PrometheusFormatter::push_escaped_label_value(value, &mut escaped);
+            escaped
+        };
+
+        assert_eq!(escape("simple"), "simple");
+        assert_eq!(escape("with\"quote"), "with\\\"quote");
+        assert_eq!(escape("with\\backslash"), "with\\\\backslash");
+        assert_eq!(escape("with\nnewline"), "with\\nnewline");
+        assert_eq!(escape("all\"\\\n"), "all\\\"\\\\\\n");
     }
 
     #[test]
@@ -406,6 +441,38 @@ mod tests {
         let formatter = PrometheusFormatter::new();
         let output = formatter.format(&[]);
         assert!(output.is_empty());
+        assert_eq!(formatter.chunks(&[][..]).count(), 0);
+    }
+
+    #[test]
+    fn test_format_chunks() {
+        let mut metrics: Vec<PrometheusMetric> = (0..5000)
+            .map(|i| {
+                PrometheusMetric::new("jvm_threads_current", i as f64)
+                    .with_type(MetricType::Gauge)
+                    .with_help("Current thread count")
+                    .with_label("pod", format!("pod-{}", i))
+            })
+            .collect();
+        metrics.push(
+            PrometheusMetric::new("request_seconds_bucket", 1.0)
+                .with_type(MetricType::Histogram)
+                .with_label("le", "+Inf"),
+        );
+        metrics.push(
+            PrometheusMetric::new("request_seconds_count", 1.0).with_type(MetricType::Histogram),
+        );
+
+        let formatter = PrometheusFormatter::new();
+        let chunks: Vec<String> = formatter.chunks(metrics.clone()).collect();
+
+        // One metric name spans several chunks, each ending on a line
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.ends_with('\n')));
+        assert!(chunks[..chunks.len() - 1]
+            .iter()
+            .all(|chunk| chunk.len() < CHUNK_SIZE + 100));
+        assert_eq!(chunks.concat(), formatter.format(&metrics));
     }
 
     #[test]
@@ -0,0 +1,191 @@
+//! Label storage for Prometheus metrics
+//!
+//! Label names and values repeat across series and scrapes, so they are
+//! interned as shared `Arc<str>`: cloning a metric, or caching it across
+//! scrapes, only bumps reference counts. A metric's labels are kept inline
+//! in a small sorted vector instead of a `HashMap`, which saves the map
+//! allocation for the usual handful of labels and makes output order stable
+//! without sorting.
+
+use std::fmt;
+use std::sync::Arc;
+
+use dashmap::DashSet;
+use once_cell::sync::Lazy;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use smallvec::SmallVec;
+
+/// Labels stored inline before spilling to the heap
+const INLINE_LABELS: usize = 4;
+
+/// Interned label strings shared by all metrics
+static INTERNER: Lazy<DashSet<Arc<str>>> = Lazy::new(DashSet::new);
+
+/// Get the shared copy of `s`, interning it if needed
+pub fn intern(s: &str) -> Arc<str> {
+    if let Some(interned) = INTERNER.get(s) {
+        return Arc::clone(&interned);
+    }
+    let interned: Arc<str> = Arc::from(s);
+    INTERNER.insert(Arc::clone(&interned));
+    interned
+}
+
+/// Drop interned strings no longer used by any metric
+///
+/// Called after each transform, so values that disappear (restarted
+/// threads, removed pools) do not accumulate. Returns how many were dropped.
+pub fn purge_interned() -> usize {
+    let before = INTERNER.len();
+    INTERNER.retain(|s| Arc::strong_count(s) > 1);
+    before.saturating_sub(INTERNER.len())
+}
+
+/// Number of interned label strings
+pub fn interned_count() -> usize {
+    INTERNER.len()
+}
+
+/// Labels of a metric, sorted by name
+#[derive(Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Labels(SmallVec<[(Arc<str>, Arc<str>); INLINE_LABELS]>);
+
+impl Labels {
+    /// Create an empty label set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of labels
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check whether there are no labels
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn position(&self, name: &str) -> Result<usize, usize> {
+        self.0.binary_search_by(|(k, _)| (**k).cmp(name))
+    }
+
+    /// Get the value of a label
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.position(name).ok().map(|i| &*self.0[i].1)
+    }
+
+    /// Check whether a label is set
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.position(name).is_ok()
+    }
+
+    /// Set a label, returning the previous value
+    pub fn insert(&mut self, name: &str, value: &str) -> Option<Arc<str>> {
+        match self.position(name) {
+            Ok(i) => Some(std::mem::replace(&mut self.0[i].1, intern(value))),
+            Err(i) => {
+                self.0.insert(i, (intern(name), intern(value)));
+                None
+            }
+        }
+    }
+
+    /// Set a label unless it is already set
+    pub fn insert_if_absent(&mut self, name: &str, value: &str) {
+        if let Err(i) = self.position(name) {
+            self.0.insert(i, (intern(name), intern(value)));
+        }
+    }
+
+    /// Remove a label, returning its value
+    pub fn remove(&mut self, name: &str) -> Option<Arc<str>> {
+        self.position(name).ok().map(|i| self.0.remove(i).1)
+    }
+
+    /// Keep only the labels for which `f` returns true
+    pub fn retain(&mut self, mut f: impl FnMut(&str, &str) -> bool) {
+        self.0.retain(|(k, v)| f(k, v));
+    }
+
+    /// Iterate over `(name, value)` pairs in name order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (&**k, &**v))
+    }
+
+    /// Iterate over label names in order
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|(k, _)| &**k)
+    }
+
+    /// Iterate over label values in name order
+    pub fn values(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|(_, v)| &**v)
+    }
+}
+
+/// Serialized as a JSON object in name order
+impl Serialize for Labels {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (name, value) in self.iter() {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+impl fmt::Debug for Labels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<str>> FromIterator<(K, V)> for Labels {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut labels = Labels::new();
+        for (k, v) in iter {
+            labels.insert(k.as_ref(), v.as_ref());
+        }
+        labels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_sorted() {
+        let mut labels: Labels = [("pool", "eden"), ("area", "heap")].into_iter().collect();
+        labels.insert("gc", "G1");
+
+        assert_eq!(
+            labels.keys().collect::<Vec<_>>(),
+            vec!["area", "gc", "pool"]
+        );
+        assert_eq!(labels.get("gc"), Some("G1"));
+        assert_eq!(labels.insert("gc", "ZGC").as_deref(), Some("G1"));
+        assert_eq!(labels.len(), 3);
+
+        labels.insert_if_absent("gc", "Serial");
+        assert_eq!(labels.get("gc"), Some("ZGC"));
+
+        assert_eq!(labels.remove("area").as_deref(), Some("heap"));
+        labels.retain(|k, _| k != "pool");
+        assert_eq!(labels.iter().collect::<Vec<_>>(), vec![("gc", "ZGC")]);
+        assert!(!labels.contains_key("pool"));
+        assert_eq!(format!("{:?}", labels), r#"{"gc": "ZGC"}"#);
+    }
+
+    #[test]
+    fn test_intern() {
+        let a = intern("test_intern_value");
+        let b = intern("test_intern_value");
+        assert!(Arc::ptr_eq(&a, &b));
+
+        // Still referenced, so it survives a purge
+        purge_interned();
+        assert!(Arc::ptr_eq(&a, &intern("test_intern_value")));
+    }
+}
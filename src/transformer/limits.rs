@@ -0,0 +1,178 @@
+//! Cardinality guardrails
+//!
+//! Protects Prometheus from pathological series explosions (e.g. wildcard
+//! MBeans registering one bean per client connection) by capping the number
+//! of series emitted per scrape and the length of label values.
+//!
+//! Offending series are dropped rather than truncated, so a partial value
+//! never masquerades as a real one.
+//!
+//! Each target's engine enforces the limits on its own metrics. The series
+//! limit is enforced once more on the combined output of a scrape, so a
+//! scrape of several targets stays within it as well.
+
+use super::engine::PrometheusMetric;
+
+/// Reason label value for series dropped by `max_series_per_scrape`
+pub const REASON_SERIES_LIMIT: &str = "max_series_per_scrape";
+
+/// Reason label value for series dropped by `max_label_value_length`
+pub const REASON_LABEL_VALUE_LENGTH: &str = "max_label_value_length";
+
+/// Per-scrape series limits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SeriesLimits {
+    /// Maximum number of series emitted per scrape (None = unlimited)
+    pub max_series_per_scrape: Option<usize>,
+    /// Maximum length of any label value in bytes (None = unlimited)
+    pub max_label_value_length: Option<usize>,
+}
+
+/// Number of series dropped by each limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LimitOutcome {
+    /// Series dropped because a label value was too long
+    pub dropped_label_value_length: usize,
+    /// Series dropped because the scrape exceeded the series limit
+    pub dropped_series_limit: usize,
+}
+
+impl LimitOutcome {
+    /// Total number of dropped series
+    pub fn total(&self) -> usize {
+        self.dropped_label_value_length + self.dropped_series_limit
+    }
+}
+
+impl SeriesLimits {
+    /// Create limits with no restrictions
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of series per scrape
+    pub fn with_max_series_per_scrape(mut self, max: Option<usize>) -> Self {
+        self.max_series_per_scrape = max;
+        self
+    }
+
+    /// Set the maximum label value length
+    pub fn with_max_label_value_length(mut self, max: Option<usize>) -> Self {
+        self.max_label_value_length = max;
+        self
+    }
+
+    /// Check whether any limit is configured
+    pub fn is_enabled(&self) -> bool {
+        self.max_series_per_scrape.is_some() || self.max_label_value_length.is_some()
+    }
+
+    /// Enforce limits on a scrape's metrics, dropping offending series in place
+    ///
+    /// Label value length is checked first; the series limit then keeps the
+    /// first `max_series_per_scrape` remaining series in emission order.
+    pub fn apply(&self, metrics: &mut Vec<PrometheusMetric>) -> LimitOutcome {
+        let mut outcome = LimitOutcome::default();
+
+        if let Some(max_len) = self.max_label_value_length {
+            let before = metrics.len();
+            metrics.retain(|m| m.labels.values().all(|v| v.len() <= max_len));
+            outcome.dropped_label_value_length = before - metrics.len();
+        }
+
+        if let Some(max_series) = self.max_series_per_scrape {
+            if metrics.len() > max_series {
+                outcome.dropped_series_limit = metrics.len() - max_series;
+                metrics.truncate(max_series);
+            }
+        }
+
+        outcome
+    }
+
+    /// Enforce limits, logging and recording dropped series in internal metrics
+    pub fn enforce(&self, metrics: &mut Vec<PrometheusMetric>) -> LimitOutcome {
+        let outcome = self.apply(metrics);
+        if outcome.total() == 0 {
+            return outcome;
+        }
+
+        tracing::warn!(
+            dropped_label_value_length = outcome.dropped_label_value_length,
+            dropped_series_limit = outcome.dropped_series_limit,
+            max_series_per_scrape = ?self.max_series_per_scrape,
+            max_label_value_length = ?self.max_label_value_length,
+            "Cardinality limits exceeded, dropping series"
+        );
+
+        let registry = crate::metrics::internal_metrics();
+        registry.record_series_dropped(
+            REASON_LABEL_VALUE_LENGTH,
+            outcome.dropped_label_value_length as u64,
+        );
+        registry.record_series_dropped(REASON_SERIES_LIMIT, outcome.dropped_series_limit as u64);
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(n: usize) -> Vec<PrometheusMetric> {
+        (0..n)
+            .map(|i| PrometheusMetric::new("test_metric", i as f64).with_label("id", i.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_no_limits() {
+        let limits = SeriesLimits::new();
+        assert!(!limits.is_enabled());
+
+        let mut m = metrics(100);
+        let outcome = limits.apply(&mut m);
+        assert_eq!(outcome.total(), 0);
+        assert_eq!(m.len(), 100);
+    }
+
+    #[test]
+    fn test_series_limit_keeps_first_series() {
+        let limits = SeriesLimits::new().with_max_series_per_scrape(Some(10));
+
+        let mut m = metrics(25);
+        let outcome = limits.apply(&mut m);
+        assert_eq!(outcome.dropped_series_limit, 15);
+        assert_eq!(m.len(), 10);
+        assert_eq!(m[9].value, 9.0);
+    }
+
+    #[test]
+    fn test_label_value_length_limit() {
+        let limits = SeriesLimits::new().with_max_label_value_length(Some(5));
+
+        let mut m = vec![
+            PrometheusMetric::new("a", 1.0).with_label("name", "short"),
+            PrometheusMetric::new("b", 2.0).with_label("name", "much-too-long"),
+        ];
+        let outcome = limits.apply(&mut m);
+        assert_eq!(outcome.dropped_label_value_length, 1);
+        assert_eq!(m.len(), 1);
+        assert_eq!(m[0].name, "a");
+    }
+
+    #[test]
+    fn test_label_length_checked_before_series_limit() {
+        let limits = SeriesLimits::new()
+            .with_max_series_per_scrape(Some(2))
+            .with_max_label_value_length(Some(1));
+
+        // ids 0-9 are one char long, 10+ are two chars
+        let mut m = metrics(12);
+        let outcome = limits.apply(&mut m);
+        assert_eq!(outcome.dropped_label_value_length, 2);
+        assert_eq!(outcome.dropped_series_limit, 8);
+        assert_eq!(outcome.total(), 10);
+        assert_eq!(m.len(), 2);
+    }
+}
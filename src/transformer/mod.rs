@@ -12,6 +12,16 @@
 //! - **RuleSet**: Collection of rules with batch operations
 //! - **TransformEngine**: Applies rules to convert MBean data to metrics
 //! - **PrometheusFormatter**: Formats metrics into Prometheus text format
+//! - **protobuf**: Encodes metrics in the Prometheus protobuf format
+//! - **Labels**: Interned, sorted label storage of a metric
+//! - **Expr**: Arithmetic expressions for `valueFactor` and `valueTransform`
+//! - **RelabelRule**: Prometheus-style relabeling applied after rule application
+//! - **DerivedMetric**: New series computed from the metrics of a scrape
+//! - **SeriesLimits**: Cardinality guardrails enforced on every scrape
+//! - **ValueCache**: Values of `cache: true` rules reused across scrapes
+//! - **CounterTracker**: Detects counters that decrease between scrapes
+//! - **DeltaTracker**: Passes on only changed series, for push sinks
+//! - **ConflictPolicy**: Resolves samples of one name with different TYPE/HELP
 //!
 //! # Example
 //!
@@ -40,14 +50,36 @@
 //! let output = formatter.format(&metrics);
 //! ```
 
+pub mod cache;
+pub mod conflicts;
+pub mod counters;
+pub mod delta;
+pub mod derived;
 pub mod engine;
+pub mod expr;
 pub mod formatter;
+pub mod labels;
+pub mod limits;
+pub mod protobuf;
+pub mod relabel;
+pub mod renames;
 pub mod rules;
 
-pub use engine::{PrometheusMetric, TransformEngine};
-pub use formatter::PrometheusFormatter;
+pub use cache::ValueCache;
+pub use conflicts::{find_conflicts, resolve_conflicts, ConflictPolicy, MetricConflict};
+pub use counters::{CounterDecrease, CounterResetMode, CounterTracker};
+pub use delta::{Delta, DeltaTracker};
+pub use derived::{apply_derived_metrics, DerivedMetric};
+pub use engine::{FlattenedName, KeyOrder, PrometheusMetric, TransformEngine, TransformStats};
+pub use expr::Expr;
+pub use formatter::{FormatChunks, PrometheusFormatter};
+pub use labels::Labels;
+pub use limits::{LimitOutcome, SeriesLimits};
+pub use relabel::{apply_relabel_rules, RelabelAction, RelabelRule};
+pub use renames::{MetricRename, MetricRenames};
 pub use rules::{
-    convert_java_regex, MetricType, Rule, RuleBuilder, RuleError, RuleMatch, RuleResult, RuleSet,
+    convert_java_regex, template_parts, MetricType, Rule, RuleBuilder, RuleError, RuleMatch,
+    RuleResult, RuleSet, TemplatePart,
 };
 
 /// Legacy transformer alias for backwards compatibility
@@ -0,0 +1,668 @@
+//! Prometheus protobuf exposition format
+//!
+//! Encodes metrics as a stream of length-delimited
+//! `io.prometheus.client.MetricFamily` messages, the format Prometheus asks
+//! for with `Accept: application/vnd.google.protobuf;
+//! proto=io.prometheus.client.MetricFamily; encoding=delimited`.
+//!
+//! The handful of messages involved are written by hand:
+//!
+//! ```text
+//! MetricFamily { 1: name, 2: help, 3: type, 4: repeated Metric }
+//! Metric       { 1: repeated LabelPair, 2: Gauge, 3: Counter, 5: Untyped, 7: Histogram }
+//! Histogram    { 1: sample_count, 2: sample_sum, 3: repeated Bucket,
+//!                5: schema, 6: zero_threshold, 7: zero_count,
+//!                12: repeated positive_span, 13: packed positive_delta }
+//! Bucket       { 1: cumulative_count, 2: upper_bound }
+//! BucketSpan   { 1: offset, 2: length }
+//! ```
+//!
+//! Histogram series (`_bucket`, `_sum`, `_count`) are reassembled into one
+//! `Histogram` message per label set. Like the text format, no timestamps
+//! are sent.
+//!
+//! Series from rules with `nativeHistogram` set carry a schema, and their
+//! histograms additionally get the native (sparse) bucket fields. Each
+//! classic bucket's observations are counted in the native bucket holding
+//! its upper bound, so the native resolution is never finer than the
+//! configured buckets.
+
+use std::collections::{BTreeMap, HashMap};
+
+use super::engine::PrometheusMetric;
+use super::rules::MetricType;
+
+/// Content type of a protobuf exposition body
+pub const CONTENT_TYPE: &str =
+    "application/vnd.google.protobuf; proto=io.prometheus.client.MetricFamily; encoding=delimited";
+
+/// Wire types used by the messages
+const WIRE_VARINT: u64 = 0;
+const WIRE_FIXED64: u64 = 1;
+const WIRE_LEN: u64 = 2;
+
+/// `io.prometheus.client.MetricType` values
+const TYPE_COUNTER: u64 = 0;
+const TYPE_GAUGE: u64 = 1;
+const TYPE_UNTYPED: u64 = 3;
+const TYPE_HISTOGRAM: u64 = 4;
+
+/// Width of the native zero bucket, the Go client library's default (2^-128)
+const ZERO_THRESHOLD: f64 = 2.938735877055719e-39;
+
+/// Metrics sharing a family name
+#[derive(Debug)]
+struct Family<'a> {
+    name: &'a str,
+    help: Option<&'a str>,
+    kind: FamilyKind<'a>,
+}
+
+#[derive(Debug)]
+enum FamilyKind<'a> {
+    /// One sample per metric
+    Samples(MetricType, Vec<&'a PrometheusMetric>),
+    /// Histograms keyed by their labels without `le`
+    Histograms(Vec<Histogram<'a>>),
+}
+
+/// One histogram reassembled from its series
+#[derive(Debug)]
+struct Histogram<'a> {
+    labels: Vec<(&'a str, &'a str)>,
+    buckets: Vec<(f64, f64)>,
+    sum: f64,
+    count: Option<f64>,
+    /// Native histogram schema, if any series asked for one
+    schema: Option<i8>,
+}
+
+/// Encode metrics as length-delimited `MetricFamily` messages
+///
+/// Families are written in the order their names first appear.
+pub fn encode(metrics: &[PrometheusMetric]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(metrics.len() * 64);
+    let mut message = Vec::new();
+    for family in group_families(metrics) {
+        message.clear();
+        encode_family(&family, &mut message);
+        put_varint(&mut output, message.len() as u64);
+        output.extend_from_slice(&message);
+    }
+    output
+}
+
+/// Group metrics into families, merging histogram series by base name
+fn group_families(metrics: &[PrometheusMetric]) -> Vec<Family<'_>> {
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    let mut families: Vec<Family<'_>> = Vec::new();
+
+    for metric in metrics {
+        let histogram = (metric.metric_type == MetricType::Histogram)
+            .then(|| histogram_series(&metric.name))
+            .flatten();
+        let name = histogram.map_or(metric.name.as_str(), |(base, _)| base);
+
+        let position = *positions.entry(name).or_insert_with(|| {
+            let kind = match histogram {
+                Some(_) => FamilyKind::Histograms(Vec::new()),
+                None => FamilyKind::Samples(metric.metric_type, Vec::new()),
+            };
+            families.push(Family {
+                name,
+                help: None,
+                kind,
+            });
+            families.len() - 1
+        });
+        let family = &mut families[position];
+        if family.help.is_none() {
+            family.help = metric.help.as_deref();
+        }
+
+        match (&mut family.kind, histogram) {
+            (FamilyKind::Histograms(histograms), Some((_, suffix))) => {
+                add_histogram_series(histograms, metric, suffix);
+            }
+            (FamilyKind::Samples(_, samples), _) => samples.push(metric),
+            // A plain series named like an existing histogram family
+            (FamilyKind::Histograms(_), None) => {}
+        }
+    }
+
+    families
+}
+
+/// Split a histogram series name into base name and suffix
+fn histogram_series(name: &str) -> Option<(&str, &'static str)> {
+    ["_bucket", "_sum", "_count"]
+        .into_iter()
+        .find_map(|suffix| name.strip_suffix(suffix).map(|base| (base, suffix)))
+}
+
+/// Add one `_bucket`, `_sum` or `_count` series to its histogram
+fn add_histogram_series<'a>(
+    histograms: &mut Vec<Histogram<'a>>,
+    metric: &'a PrometheusMetric,
+    suffix: &str,
+) {
+    let labels: Vec<(&str, &str)> = metric.labels.iter().filter(|(k, _)| *k != "le").collect();
+    let index = match histograms.iter().position(|h| h.labels == labels) {
+        Some(index) => index,
+        None => {
+            histograms.push(Histogram {
+                labels,
+                buckets: Vec::new(),
+                sum: 0.0,
+                count: None,
+                schema: None,
+            });
+            histograms.len() - 1
+        }
+    };
+    let histogram = &mut histograms[index];
+    if metric.native_schema.is_some() {
+        histogram.schema = metric.native_schema;
+    }
+
+    match suffix {
+        "_bucket" => {
+            let upper_bound = match metric.labels.get("le") {
+                Some("+Inf") => f64::INFINITY,
+                Some(le) => match le.parse() {
+                    Ok(bound) => bound,
+                    Err(_) => return,
+                },
+                None => return,
+            };
+            histogram.buckets.push((upper_bound, metric.value));
+        }
+        "_sum" => histogram.sum = metric.value,
+        _ => histogram.count = Some(metric.value),
+    }
+}
+
+fn encode_family(family: &Family<'_>, output: &mut Vec<u8>) {
+    put_string(output, 1, family.name);
+    if let Some(help) = family.help {
+        put_string(output, 2, help);
+    }
+
+    match &family.kind {
+        FamilyKind::Samples(metric_type, samples) => {
+            let (type_value, field) = match metric_type {
+                MetricType::Counter => (TYPE_COUNTER, 3),
+                MetricType::Gauge => (TYPE_GAUGE, 2),
+                MetricType::Histogram | MetricType::Untyped => (TYPE_UNTYPED, 5),
+            };
+            put_uint64(output, 3, type_value);
+            for metric in samples {
+                put_message(output, 4, |m| {
+                    put_labels(m, metric.labels.iter());
+                    // Gauge, Counter and Untyped all carry `double value = 1`
+                    put_message(m, field, |v| put_double(v, 1, metric.value));
+                });
+            }
+        }
+        FamilyKind::Histograms(histograms) => {
+            put_uint64(output, 3, TYPE_HISTOGRAM);
+            for histogram in histograms {
+                put_message(output, 4, |m| {
+                    put_labels(m, histogram.labels.iter().copied());
+                    put_message(m, 7, |h| encode_histogram(histogram, h));
+                });
+            }
+        }
+    }
+}
+
+fn encode_histogram(histogram: &Histogram<'_>, output: &mut Vec<u8>) {
+    let mut buckets = histogram.buckets.clone();
+    buckets.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // The +Inf bucket is implied by the sample count
+    let infinite = buckets
+        .iter()
+        .find(|(bound, _)| *bound == f64::INFINITY)
+        .map(|(_, count)| *count);
+    let count = histogram.count.or(infinite).unwrap_or(0.0);
+
+    put_count(output, 1, 4, count);
+    put_double(output, 2, histogram.sum);
+    for (upper_bound, cumulative) in buckets.iter().filter(|(b, _)| b.is_finite()) {
+        put_message(output, 3, |b| {
+            put_count(b, 1, 4, *cumulative);
+            put_double(b, 2, *upper_bound);
+        });
+    }
+
+    if let Some(schema) = histogram.schema {
+        encode_native(schema, &buckets, count, output);
+    }
+}
+
+/// Write the native histogram fields derived from sorted classic buckets
+///
+/// Bounds within the zero threshold (including negative ones) feed the zero
+/// bucket; observations above the largest finite bound go to the native
+/// bucket after it.
+fn encode_native(schema: i8, buckets: &[(f64, f64)], count: f64, output: &mut Vec<u8>) {
+    let mut zero_count = 0.0;
+    let mut counts: BTreeMap<i32, f64> = BTreeMap::new();
+    let mut previous = 0.0;
+    let mut last_index = None;
+    for &(upper_bound, cumulative) in buckets.iter().filter(|(b, _)| b.is_finite()) {
+        let observations = (cumulative - previous).max(0.0);
+        previous = cumulative.max(previous);
+        if upper_bound <= ZERO_THRESHOLD {
+            zero_count += observations;
+            continue;
+        }
+        let index = native_index(schema, upper_bound);
+        last_index = Some(index);
+        if observations > 0.0 {
+            *counts.entry(index).or_default() += observations;
+        }
+    }
+    let overflow = count - previous;
+    if overflow > 0.0 {
+        *counts.entry(last_index.map_or(0, |i| i + 1)).or_default() += overflow;
+    }
+
+    put_tag(output, 5, WIRE_VARINT);
+    put_varint(output, zigzag(i64::from(schema)));
+    put_double(output, 6, ZERO_THRESHOLD);
+
+    let mut spans: Vec<(i32, u32)> = Vec::new();
+    let mut previous_index: Option<i32> = None;
+    for &index in counts.keys() {
+        match previous_index {
+            Some(p) if index == p + 1 => {
+                if let Some(span) = spans.last_mut() {
+                    span.1 += 1;
+                }
+            }
+            Some(p) => spans.push((index - p - 1, 1)),
+            None => spans.push((index, 1)),
+        }
+        previous_index = Some(index);
+    }
+
+    // Whole counts use the integer fields, anything else the float ones
+    let whole = |c: &f64| c.fract() == 0.0 && *c < i64::MAX as f64;
+    if whole(&zero_count) && counts.values().all(whole) {
+        put_uint64(output, 7, zero_count as u64);
+        put_spans(output, &spans);
+        put_message(output, 13, |p| {
+            let mut previous = 0;
+            for &c in counts.values() {
+                put_varint(p, zigzag(c as i64 - previous));
+                previous = c as i64;
+            }
+        });
+    } else {
+        put_double(output, 8, zero_count);
+        put_spans(output, &spans);
+        put_message(output, 14, |p| {
+            for &c in counts.values() {
+                p.extend_from_slice(&c.to_le_bytes());
+            }
+        });
+    }
+}
+
+/// Index of the native bucket `(base^(i-1), base^i]` holding `value`, with
+/// `base = 2^(2^-schema)`
+fn native_index(schema: i8, value: f64) -> i32 {
+    (value.log2() * 2f64.powi(i32::from(schema))).ceil() as i32
+}
+
+fn put_spans(output: &mut Vec<u8>, spans: &[(i32, u32)]) {
+    for &(offset, length) in spans {
+        put_message(output, 12, |s| {
+            put_tag(s, 1, WIRE_VARINT);
+            put_varint(s, zigzag(i64::from(offset)));
+            put_uint64(s, 2, u64::from(length));
+        });
+    }
+}
+
+/// ZigZag encoding used by `sint32` and `sint64` fields
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Write a count as `uint64`, or as the `double` field if not a whole number
+fn put_count(output: &mut Vec<u8>, int_field: u64, float_field: u64, count: f64) {
+    if count >= 0.0 && count.fract() == 0.0 && count < u64::MAX as f64 {
+        put_uint64(output, int_field, count as u64);
+    } else {
+        put_double(output, float_field, count);
+    }
+}
+
+fn put_labels<'a>(output: &mut Vec<u8>, labels: impl Iterator<Item = (&'a str, &'a str)>) {
+    for (name, value) in labels {
+        put_message(output, 1, |pair| {
+            put_string(pair, 1, name);
+            put_string(pair, 2, value);
+        });
+    }
+}
+
+fn put_varint(output: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        output.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+fn put_tag(output: &mut Vec<u8>, field: u64, wire_type: u64) {
+    put_varint(output, (field << 3) | wire_type);
+}
+
+fn put_uint64(output: &mut Vec<u8>, field: u64, value: u64) {
+    put_tag(output, field, WIRE_VARINT);
+    put_varint(output, value);
+}
+
+fn put_double(output: &mut Vec<u8>, field: u64, value: f64) {
+    put_tag(output, field, WIRE_FIXED64);
+    output.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_string(output: &mut Vec<u8>, field: u64, value: &str) {
+    put_tag(output, field, WIRE_LEN);
+    put_varint(output, value.len() as u64);
+    output.extend_from_slice(value.as_bytes());
+}
+
+/// Write an embedded message built by `build`
+fn put_message(output: &mut Vec<u8>, field: u64, build: impl FnOnce(&mut Vec<u8>)) {
+    let mut message = Vec::new();
+    build(&mut message);
+    put_tag(output, field, WIRE_LEN);
+    put_varint(output, message.len() as u64);
+    output.extend_from_slice(&message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decoded protobuf field value
+    #[derive(Debug, Clone, PartialEq)]
+    enum Value {
+        Varint(u64),
+        Double(f64),
+        Bytes(Vec<u8>),
+    }
+
+    impl Value {
+        fn message(&self) -> Vec<(u64, Value)> {
+            match self {
+                Value::Bytes(bytes) => decode_message(bytes),
+                other => panic!("not a message: {:?}", other),
+            }
+        }
+
+        fn string(&self) -> String {
+            match self {
+                Value::Bytes(bytes) => String::from_utf8(bytes.clone()).unwrap(),
+                other => panic!("not a string: {:?}", other),
+            }
+        }
+    }
+
+    fn read_varint(bytes: &[u8], pos: &mut usize) -> u64 {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = bytes[*pos];
+            *pos += 1;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte < 0x80 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    fn decode_message(bytes: &[u8]) -> Vec<(u64, Value)> {
+        let mut fields = Vec::new();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            let tag = read_varint(bytes, &mut pos);
+            let value = match tag & 7 {
+                WIRE_VARINT => Value::Varint(read_varint(bytes, &mut pos)),
+                WIRE_FIXED64 => {
+                    let raw: [u8; 8] = bytes[pos..pos + 8].try_into().unwrap();
+                    pos += 8;
+                    Value::Double(f64::from_le_bytes(raw))
+                }
+                WIRE_LEN => {
+                    let len = read_varint(bytes, &mut pos) as usize;
+                    pos += len;
+                    Value::Bytes(bytes[pos - len..pos].to_vec())
+                }
+                other => panic!("unexpected wire type {}", other),
+            };
+            fields.push((tag >> 3, value));
+        }
+        fields
+    }
+
+    /// Split a delimited stream into messages
+    fn decode_stream(bytes: &[u8]) -> Vec<Vec<(u64, Value)>> {
+        let mut messages = Vec::new();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            let len = read_varint(bytes, &mut pos) as usize;
+            messages.push(decode_message(&bytes[pos..pos + len]));
+            pos += len;
+        }
+        messages
+    }
+
+    fn field(message: &[(u64, Value)], number: u64) -> &Value {
+        &message.iter().find(|(n, _)| *n == number).unwrap().1
+    }
+
+    fn fields(message: &[(u64, Value)], number: u64) -> Vec<&Value> {
+        message
+            .iter()
+            .filter(|(n, _)| *n == number)
+            .map(|(_, v)| v)
+            .collect()
+    }
+
+    #[test]
+    fn test_varint() {
+        let mut output = Vec::new();
+        put_varint(&mut output, 1);
+        put_varint(&mut output, 300);
+        assert_eq!(output, vec![0x01, 0xac, 0x02]);
+    }
+
+    #[test]
+    fn test_encode_samples() {
+        let metrics = vec![
+            PrometheusMetric::new("jvm_threads_current", 42.0)
+                .with_type(MetricType::Gauge)
+                .with_help("Current thread count"),
+            PrometheusMetric::new("jvm_gc_collections_total", 7.0)
+                .with_type(MetricType::Counter)
+                .with_label("gc", "G1 Young"),
+            PrometheusMetric::new("jvm_gc_collections_total", 2.0)
+                .with_type(MetricType::Counter)
+                .with_label("gc", "G1 Old"),
+            PrometheusMetric::new("legacy_value", 1.5),
+        ];
+
+        let families = decode_stream(&encode(&metrics));
+        assert_eq!(families.len(), 3);
+
+        let gauge = &families[0];
+        assert_eq!(field(gauge, 1).string(), "jvm_threads_current");
+        assert_eq!(field(gauge, 2).string(), "Current thread count");
+        assert_eq!(field(gauge, 3), &Value::Varint(TYPE_GAUGE));
+        let metric = field(gauge, 4).message();
+        assert_eq!(field(&metric, 2).message(), vec![(1, Value::Double(42.0))]);
+
+        let counter = &families[1];
+        assert_eq!(field(counter, 3), &Value::Varint(TYPE_COUNTER));
+        let metrics = fields(counter, 4);
+        assert_eq!(metrics.len(), 2);
+        let first = metrics[0].message();
+        let label = field(&first, 1).message();
+        assert_eq!(field(&label, 1).string(), "gc");
+        assert_eq!(field(&label, 2).string(), "G1 Young");
+        assert_eq!(field(&first, 3).message(), vec![(1, Value::Double(7.0))]);
+
+        let untyped = &families[2];
+        assert_eq!(field(untyped, 3), &Value::Varint(TYPE_UNTYPED));
+        let metric = field(untyped, 4).message();
+        assert_eq!(field(&metric, 5).message(), vec![(1, Value::Double(1.5))]);
+    }
+
+    #[test]
+    fn test_encode_histogram() {
+        let series = |name: &str, le: Option<&str>, value: f64| {
+            let metric = PrometheusMetric::new(name, value)
+                .with_type(MetricType::Histogram)
+                .with_help("Scrape duration")
+                .with_label("target", "app");
+            match le {
+                Some(le) => metric.with_label("le", le),
+                None => metric,
+            }
+        };
+        let metrics = vec![
+            series("scrape_seconds_bucket", Some("1"), 3.0),
+            series("scrape_seconds_bucket", Some("0.5"), 1.0),
+            series("scrape_seconds_bucket", Some("+Inf"), 4.0),
+            series("scrape_seconds_sum", None, 2.5),
+            series("scrape_seconds_count", None, 4.0),
+        ];
+
+        let families = decode_stream(&encode(&metrics));
+        assert_eq!(families.len(), 1);
+        let family = &families[0];
+        assert_eq!(field(family, 1).string(), "scrape_seconds");
+        assert_eq!(field(family, 3), &Value::Varint(TYPE_HISTOGRAM));
+
+        let metrics = fields(family, 4);
+        assert_eq!(metrics.len(), 1);
+        let metric = metrics[0].message();
+        let labels = fields(&metric, 1);
+        assert_eq!(labels.len(), 1);
+        assert_eq!(field(&labels[0].message(), 1).string(), "target");
+
+        let histogram = field(&metric, 7).message();
+        assert_eq!(field(&histogram, 1), &Value::Varint(4));
+        assert_eq!(field(&histogram, 2), &Value::Double(2.5));
+        let buckets: Vec<_> = fields(&histogram, 3)
+            .into_iter()
+            .map(|bucket| bucket.message())
+            .collect();
+        assert_eq!(
+            buckets,
+            vec![
+                vec![(1, Value::Varint(1)), (2, Value::Double(0.5))],
+                vec![(1, Value::Varint(3)), (2, Value::Double(1.0))],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_native_index() {
+        // Schema 0: buckets (0.5, 1], (1, 2], (2, 4], ...
+        assert_eq!(native_index(0, 1.0), 0);
+        assert_eq!(native_index(0, 1.5), 1);
+        assert_eq!(native_index(0, 2.0), 1);
+        assert_eq!(native_index(0, 0.25), -2);
+        // Schema 3: eight buckets per power of two
+        assert_eq!(native_index(3, 2.0), 8);
+        assert_eq!(native_index(3, 0.5), -8);
+        // Schema -1: factor 4
+        assert_eq!(native_index(-1, 16.0), 2);
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(2), 4);
+    }
+
+    #[test]
+    fn test_encode_native_histogram() {
+        let series = |name: &str, le: Option<&str>, value: f64| {
+            let metric = PrometheusMetric::new(name, value)
+                .with_type(MetricType::Histogram)
+                .with_native_schema(0);
+            match le {
+                Some(le) => metric.with_label("le", le),
+                None => metric,
+            }
+        };
+        let metrics = vec![
+            series("latency_seconds_bucket", Some("0"), 1.0),
+            series("latency_seconds_bucket", Some("1"), 3.0),
+            series("latency_seconds_bucket", Some("2"), 3.0),
+            series("latency_seconds_bucket", Some("8"), 6.0),
+            series("latency_seconds_bucket", Some("+Inf"), 7.0),
+            series("latency_seconds_sum", None, 20.0),
+            series("latency_seconds_count", None, 7.0),
+        ];
+
+        let families = decode_stream(&encode(&metrics));
+        let metric = field(&families[0], 4).message();
+        let histogram = field(&metric, 7).message();
+
+        // Classic buckets are still sent
+        assert_eq!(field(&histogram, 1), &Value::Varint(7));
+        assert_eq!(fields(&histogram, 3).len(), 4);
+
+        assert_eq!(field(&histogram, 5), &Value::Varint(0));
+        assert_eq!(field(&histogram, 6), &Value::Double(ZERO_THRESHOLD));
+        assert_eq!(field(&histogram, 7), &Value::Varint(1));
+        // Buckets 0 (le 1), 3 (le 8) and 4 (overflow): spans [0, 1] and [2, 2]
+        let spans: Vec<_> = fields(&histogram, 12)
+            .into_iter()
+            .map(|span| span.message())
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                vec![(1, Value::Varint(0)), (2, Value::Varint(1))],
+                vec![(1, Value::Varint(zigzag(2))), (2, Value::Varint(2))],
+            ]
+        );
+        // Counts 2, 3, 1 as deltas 2, +1, -2
+        let deltas = match field(&histogram, 13) {
+            Value::Bytes(bytes) => bytes.clone(),
+            other => panic!("not packed: {:?}", other),
+        };
+        assert_eq!(
+            deltas,
+            vec![zigzag(2) as u8, zigzag(1) as u8, zigzag(-2) as u8]
+        );
+    }
+
+    #[test]
+    fn test_encode_classic_histogram_has_no_native_fields() {
+        let metrics = vec![
+            PrometheusMetric::new("latency_seconds_bucket", 1.0)
+                .with_type(MetricType::Histogram)
+                .with_label("le", "+Inf"),
+            PrometheusMetric::new("latency_seconds_count", 1.0).with_type(MetricType::Histogram),
+        ];
+        let families = decode_stream(&encode(&metrics));
+        let metric = field(&families[0], 4).message();
+        let histogram = field(&metric, 7).message();
+        assert!(fields(&histogram, 5).is_empty());
+        assert!(fields(&histogram, 12).is_empty());
+    }
+
+    #[test]
+    fn test_encode_empty() {
+        assert!(encode(&[]).is_empty());
+    }
+}
@@ -0,0 +1,358 @@
+//! Metric relabeling
+//!
+//! This module applies Prometheus-style relabel configurations to metrics
+//! after rule application. It lets users trim or rewrite labels globally
+//! without touching rule patterns.
+//!
+//! # Supported Actions
+//!
+//! - `replace`: Set `target_label` to `replacement` when `regex` matches the source value
+//! - `keep`: Drop the series unless `regex` matches the source value
+//! - `drop`: Drop the series when `regex` matches the source value
+//! - `labeldrop`: Remove every label whose name matches `regex`
+//! - `labelkeep`: Remove every label whose name does not match `regex`
+//!
+//! The special label name `__name__` refers to the metric name.
+//!
+//! # Example Configuration (YAML)
+//!
+//! ```yaml
+//! relabel_configs:
+//!   - action: labeldrop
+//!     regex: "thread_id"
+//!   - action: replace
+//!     source_labels: [pool]
+//!     regex: "G1 (.*)"
+//!     target_label: pool
+//!     replacement: "$1"
+//! ```
+
+use std::borrow::Cow;
+
+use regex::Regex;
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::engine::PrometheusMetric;
+use super::rules::{RuleError, RuleResult};
+
+/// Label name that refers to the metric name in relabel configurations
+pub const METRIC_NAME_LABEL: &str = "__name__";
+
+/// Relabel action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RelabelAction {
+    /// Replace the target label with the expanded replacement
+    #[default]
+    Replace,
+    /// Keep only series whose source value matches the regex
+    Keep,
+    /// Drop series whose source value matches the regex
+    Drop,
+    /// Drop labels whose name matches the regex
+    LabelDrop,
+    /// Keep only labels whose name matches the regex
+    LabelKeep,
+}
+
+impl RelabelAction {
+    /// Returns the configuration string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RelabelAction::Replace => "replace",
+            RelabelAction::Keep => "keep",
+            RelabelAction::Drop => "drop",
+            RelabelAction::LabelDrop => "labeldrop",
+            RelabelAction::LabelKeep => "labelkeep",
+        }
+    }
+}
+
+impl Serialize for RelabelAction {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for RelabelAction {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        match s.to_lowercase().as_str() {
+            "replace" => Ok(RelabelAction::Replace),
+            "keep" => Ok(RelabelAction::Keep),
+            "drop" => Ok(RelabelAction::Drop),
+            "labeldrop" => Ok(RelabelAction::LabelDrop),
+            "labelkeep" => Ok(RelabelAction::LabelKeep),
+            other => Err(serde::de::Error::custom(format!(
+                "unknown relabel action '{}', expected one of: replace, keep, drop, labeldrop, labelkeep",
+                other
+            ))),
+        }
+    }
+}
+
+impl JsonSchema for RelabelAction {
+    fn schema_name() -> Cow<'static, str> {
+        "RelabelAction".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "enum": ["replace", "keep", "drop", "labeldrop", "labelkeep"]
+        })
+    }
+}
+
+impl std::fmt::Display for RelabelAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A compiled relabel rule
+///
+/// The regex is anchored on both ends, matching Prometheus semantics.
+#[derive(Debug, Clone)]
+pub struct RelabelRule {
+    /// Action to perform
+    pub action: RelabelAction,
+    /// Labels whose values are concatenated to form the source value
+    pub source_labels: Vec<String>,
+    /// Separator placed between concatenated source label values
+    pub separator: String,
+    /// Label to write for `replace` actions
+    pub target_label: Option<String>,
+    /// Replacement template (supports `$1`, `${1}`, `${name}`)
+    pub replacement: String,
+    /// Compiled, anchored regex
+    regex: Regex,
+}
+
+impl RelabelRule {
+    /// Create a new relabel rule
+    ///
+    /// # Errors
+    ///
+    /// Returns `RuleError::InvalidPattern` if the regex is invalid.
+    pub fn new(action: RelabelAction, regex: &str) -> RuleResult<Self> {
+        let anchored = format!("^(?:{})$", regex);
+        let compiled = Regex::new(&anchored).map_err(|e| RuleError::InvalidPattern {
+            pattern: regex.to_string(),
+            source: e,
+        })?;
+
+        Ok(Self {
+            action,
+            source_labels: Vec::new(),
+            separator: ";".to_string(),
+            target_label: None,
+            replacement: "$1".to_string(),
+            regex: compiled,
+        })
+    }
+
+    /// Set the source labels
+    pub fn with_source_labels(mut self, labels: Vec<String>) -> Self {
+        self.source_labels = labels;
+        self
+    }
+
+    /// Set the separator
+    pub fn with_separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Set the target label
+    pub fn with_target_label(mut self, target: impl Into<String>) -> Self {
+        self.target_label = Some(target.into());
+        self
+    }
+
+    /// Set the replacement template
+    pub fn with_replacement(mut self, replacement: impl Into<String>) -> Self {
+        self.replacement = replacement.into();
+        self
+    }
+
+    /// Apply the rule to a metric
+    ///
+    /// Returns `false` if the metric should be dropped.
+    pub fn apply(&self, metric: &mut PrometheusMetric) -> bool {
+        match self.action {
+            RelabelAction::Replace => {
+                let source = self.source_value(metric);
+                let Some(target) = &self.target_label else {
+                    return true;
+                };
+                if let Some(caps) = self.regex.captures(&source) {
+                    let mut value = String::new();
+                    caps.expand(&self.replacement, &mut value);
+                    if target == METRIC_NAME_LABEL {
+                        if !value.is_empty() {
+                            metric.name = value;
+                        }
+                    } else if value.is_empty() {
+                        metric.labels.remove(target);
+                    } else {
+                        metric.labels.insert(target, &value);
+                    }
+                }
+                true
+            }
+            RelabelAction::Keep => self.regex.is_match(&self.source_value(metric)),
+            RelabelAction::Drop => !self.regex.is_match(&self.source_value(metric)),
+            RelabelAction::LabelDrop => {
+                metric.labels.retain(|k, _| !self.regex.is_match(k));
+                true
+            }
+            RelabelAction::LabelKeep => {
+                metric.labels.retain(|k, _| self.regex.is_match(k));
+                true
+            }
+        }
+    }
+
+    /// Build the source value by joining source label values
+    fn source_value(&self, metric: &PrometheusMetric) -> String {
+        self.source_labels
+            .iter()
+            .map(|name| {
+                if name == METRIC_NAME_LABEL {
+                    metric.name.as_str()
+                } else {
+                    metric.labels.get(name).unwrap_or("")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(&self.separator)
+    }
+}
+
+/// Apply a sequence of relabel rules to a metric
+///
+/// Returns `false` as soon as any rule drops the metric.
+pub fn apply_relabel_rules(rules: &[RelabelRule], metric: &mut PrometheusMetric) -> bool {
+    rules.iter().all(|rule| rule.apply(metric))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_metric() -> PrometheusMetric {
+        PrometheusMetric::new("jvm_gc_collection_count", 5.0)
+            .with_label("gc", "G1 Young Generation")
+            .with_label("thread_id", "42")
+    }
+
+    #[test]
+    fn test_relabel_action_deserialize() {
+        let action: RelabelAction = serde_yaml::from_str("labeldrop").unwrap();
+        assert_eq!(action, RelabelAction::LabelDrop);
+
+        let action: RelabelAction = serde_yaml::from_str("KEEP").unwrap();
+        assert_eq!(action, RelabelAction::Keep);
+
+        let result: Result<RelabelAction, _> = serde_yaml::from_str("hashmod");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        assert!(RelabelRule::new(RelabelAction::Drop, "(unclosed").is_err());
+    }
+
+    #[test]
+    fn test_replace() {
+        let rule = RelabelRule::new(RelabelAction::Replace, "G1 (.*)")
+            .unwrap()
+            .with_source_labels(vec!["gc".to_string()])
+            .with_target_label("gc");
+
+        let mut metric = sample_metric();
+        assert!(rule.apply(&mut metric));
+        assert_eq!(metric.labels.get("gc"), Some("Young Generation"));
+    }
+
+    #[test]
+    fn test_replace_metric_name() {
+        let rule = RelabelRule::new(RelabelAction::Replace, "jvm_(.*)")
+            .unwrap()
+            .with_source_labels(vec![METRIC_NAME_LABEL.to_string()])
+            .with_target_label(METRIC_NAME_LABEL)
+            .with_replacement("java_$1");
+
+        let mut metric = sample_metric();
+        assert!(rule.apply(&mut metric));
+        assert_eq!(metric.name, "java_gc_collection_count");
+    }
+
+    #[test]
+    fn test_replace_empty_removes_label() {
+        let rule = RelabelRule::new(RelabelAction::Replace, ".*")
+            .unwrap()
+            .with_target_label("thread_id")
+            .with_replacement("");
+
+        let mut metric = sample_metric();
+        assert!(rule.apply(&mut metric));
+        assert!(!metric.labels.contains_key("thread_id"));
+    }
+
+    #[test]
+    fn test_keep_and_drop() {
+        let keep = RelabelRule::new(RelabelAction::Keep, "G1 .*")
+            .unwrap()
+            .with_source_labels(vec!["gc".to_string()]);
+        let drop = RelabelRule::new(RelabelAction::Drop, "G1 .*")
+            .unwrap()
+            .with_source_labels(vec!["gc".to_string()]);
+
+        assert!(keep.apply(&mut sample_metric()));
+        assert!(!drop.apply(&mut sample_metric()));
+
+        // Regex is anchored, so a partial match does not count
+        let partial = RelabelRule::new(RelabelAction::Drop, "G1")
+            .unwrap()
+            .with_source_labels(vec!["gc".to_string()]);
+        assert!(partial.apply(&mut sample_metric()));
+    }
+
+    #[test]
+    fn test_labeldrop_and_labelkeep() {
+        let labeldrop = RelabelRule::new(RelabelAction::LabelDrop, "thread_.*").unwrap();
+        let mut metric = sample_metric();
+        assert!(labeldrop.apply(&mut metric));
+        assert!(!metric.labels.contains_key("thread_id"));
+        assert!(metric.labels.contains_key("gc"));
+
+        let labelkeep = RelabelRule::new(RelabelAction::LabelKeep, "thread_id").unwrap();
+        let mut metric = sample_metric();
+        assert!(labelkeep.apply(&mut metric));
+        assert_eq!(metric.labels.len(), 1);
+        assert!(metric.labels.contains_key("thread_id"));
+    }
+
+    #[test]
+    fn test_apply_relabel_rules_stops_on_drop() {
+        let rules = vec![
+            RelabelRule::new(RelabelAction::Drop, "jvm_.*")
+                .unwrap()
+                .with_source_labels(vec![METRIC_NAME_LABEL.to_string()]),
+            RelabelRule::new(RelabelAction::LabelDrop, ".*").unwrap(),
+        ];
+
+        let mut metric = sample_metric();
+        assert!(!apply_relabel_rules(&rules, &mut metric));
+        // Second rule never ran
+        assert_eq!(metric.labels.len(), 2);
+    }
+}
@@ -0,0 +1,130 @@
+//! Metric renames
+//!
+//! Renames final metric names after rule application, so a metric can be
+//! renamed without touching (or duplicating) the rules that produce it.
+//! During a migration window a rename can keep emitting the old name as
+//! well, letting dashboards move over before the old series disappears.
+//!
+//! # Example Configuration (YAML)
+//!
+//! ```yaml
+//! renames:
+//!   jvm_threads_ThreadCount: jvm_threads_current
+//!   jvm_memory_heap_used_bytes:
+//!     to: jvm_memory_used_bytes
+//!     keep_old: true
+//! ```
+
+use std::collections::HashMap;
+
+use super::engine::PrometheusMetric;
+
+/// Target of a single rename
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricRename {
+    /// New metric name
+    pub to: String,
+    /// Also emit the series under the old name
+    pub keep_old: bool,
+}
+
+/// Metric renames keyed by old name
+#[derive(Debug, Clone, Default)]
+pub struct MetricRenames {
+    renames: HashMap<String, MetricRename>,
+}
+
+impl MetricRenames {
+    /// Create an empty rename map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rename `from` to `to`, optionally keeping the old series
+    pub fn with_rename(
+        mut self,
+        from: impl Into<String>,
+        to: impl Into<String>,
+        keep_old: bool,
+    ) -> Self {
+        self.renames.insert(
+            from.into(),
+            MetricRename {
+                to: to.into(),
+                keep_old,
+            },
+        );
+        self
+    }
+
+    /// Look up the rename for a metric name
+    pub fn get(&self, name: &str) -> Option<&MetricRename> {
+        self.renames.get(name)
+    }
+
+    /// Number of renames
+    pub fn len(&self) -> usize {
+        self.renames.len()
+    }
+
+    /// Check if there are no renames
+    pub fn is_empty(&self) -> bool {
+        self.renames.is_empty()
+    }
+
+    /// Rename metrics in place
+    ///
+    /// Renames are not chained: a metric is renamed at most once. Series kept
+    /// under their old name are appended after the renamed ones.
+    pub fn apply(&self, metrics: &mut Vec<PrometheusMetric>) {
+        if self.renames.is_empty() {
+            return;
+        }
+
+        let mut kept = Vec::new();
+        for metric in metrics.iter_mut() {
+            if let Some(rename) = self.renames.get(&metric.name) {
+                if rename.keep_old {
+                    kept.push(metric.clone());
+                }
+                metric.name = rename.to.clone();
+            }
+        }
+        metrics.append(&mut kept);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_renames() {
+        let renames = MetricRenames::new()
+            .with_rename("old_a", "new_a", false)
+            .with_rename("old_b", "new_b", true)
+            .with_rename("new_a", "newer_a", false);
+
+        let mut metrics = vec![
+            PrometheusMetric::new("old_a", 1.0),
+            PrometheusMetric::new("old_b", 2.0).with_label("pool", "eden"),
+            PrometheusMetric::new("untouched", 3.0),
+        ];
+        renames.apply(&mut metrics);
+
+        let names: Vec<&str> = metrics.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["new_a", "new_b", "untouched", "old_b"]);
+        assert_eq!(metrics[1].labels.get("pool"), metrics[3].labels.get("pool"));
+        assert_eq!(metrics[3].value, 2.0);
+    }
+
+    #[test]
+    fn test_empty_renames() {
+        let renames = MetricRenames::new();
+        assert!(renames.is_empty());
+
+        let mut metrics = vec![PrometheusMetric::new("a", 1.0)];
+        renames.apply(&mut metrics);
+        assert_eq!(metrics.len(), 1);
+    }
+}
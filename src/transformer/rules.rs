@@ -21,11 +21,14 @@
 //! ```
 
 use once_cell::sync::OnceCell;
+use regex::bytes::{RegexSet, RegexSetBuilder};
 use regex::Regex;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
 use thiserror::Error;
 
+use super::expr::Expr;
+
 /// Errors that can occur during rule processing
 #[derive(Error, Debug)]
 pub enum RuleError {
@@ -150,6 +153,9 @@ impl std::fmt::Display for MetricType {
 /// help: "JVM heap memory usage"
 /// labels:
 ///   area: "heap"
+/// labelDrops: ["thread_id"]
+/// labelRenames:
+///   area: "memory_area"
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Rule {
@@ -191,6 +197,73 @@ pub struct Rule {
     #[serde(rename = "valueFactor", default)]
     pub value_factor: Option<f64>,
 
+    /// Expression of the value `x` replacing it, used instead of `value_factor`
+    #[serde(
+        rename = "valueTransform",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub value_transform: Option<Expr>,
+
+    /// Label names to drop from metrics produced by this rule
+    ///
+    /// Drops are applied to label names before renames.
+    #[serde(rename = "labelDrops", default, skip_serializing_if = "Vec::is_empty")]
+    pub label_drops: Vec<String>,
+
+    /// Label renames (old name -> new name) applied to metrics produced by this rule
+    #[serde(
+        rename = "labelRenames",
+        default,
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub label_renames: HashMap<String, String>,
+
+    /// Collect matched values once and reuse them across scrapes
+    ///
+    /// Intended for static attributes such as versions and max sizes.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cache: bool,
+
+    /// Convert captured values to snake_case when substituting them into the
+    /// metric name (jmx_exporter's `attrNameSnakeCase`)
+    ///
+    /// `HeapMemoryUsage` becomes `heap_memory_usage`.
+    #[serde(
+        rename = "attrNameSnakeCase",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub attr_name_snake_case: bool,
+
+    /// Replace whitespace in captured values with `_` when substituting them
+    /// into label values
+    ///
+    /// `G1 Eden Space` becomes `G1_Eden_Space`.
+    #[serde(
+        rename = "normalizeLabelValues",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub normalize_label_values: bool,
+
+    /// Native histogram schema for histogram series of this rule
+    ///
+    /// Only the protobuf exposition format carries native histograms; the
+    /// text format keeps exposing the classic buckets.
+    #[serde(
+        rename = "nativeHistogramSchema",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub native_histogram_schema: Option<i8>,
+
+    /// Discard matching values instead of producing a metric
+    ///
+    /// Drop rules take precedence over every other rule in a [`RuleSet`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub drop: bool,
+
     /// Compiled regex pattern (internal, not serialized)
     #[serde(skip)]
     compiled_pattern: OnceCell<Regex>,
@@ -229,6 +302,14 @@ impl Rule {
             help: None,
             value: None,
             value_factor: None,
+            value_transform: None,
+            label_drops: Vec::new(),
+            label_renames: HashMap::new(),
+            cache: false,
+            attr_name_snake_case: false,
+            normalize_label_values: false,
+            native_histogram_schema: None,
+            drop: false,
             compiled_pattern: OnceCell::new(),
         }
     }
@@ -262,6 +343,54 @@ impl Rule {
         self
     }
 
+    /// Set the value transform expression
+    pub fn with_value_transform(mut self, transform: Expr) -> Self {
+        self.value_transform = Some(transform);
+        self
+    }
+
+    /// Drop a label from metrics produced by this rule
+    pub fn with_label_drop(mut self, label: impl Into<String>) -> Self {
+        self.label_drops.push(label.into());
+        self
+    }
+
+    /// Rename a label on metrics produced by this rule
+    pub fn with_label_rename(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.label_renames.insert(from.into(), to.into());
+        self
+    }
+
+    /// Cache matched values across scrapes
+    pub fn with_cache(mut self, cache: bool) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Convert captured values to snake_case in the metric name
+    pub fn with_attr_name_snake_case(mut self, snake_case: bool) -> Self {
+        self.attr_name_snake_case = snake_case;
+        self
+    }
+
+    /// Replace whitespace in captured label values with `_`
+    pub fn with_normalize_label_values(mut self, normalize: bool) -> Self {
+        self.normalize_label_values = normalize;
+        self
+    }
+
+    /// Also emit histogram series as native histograms with this schema
+    pub fn with_native_histogram_schema(mut self, schema: i8) -> Self {
+        self.native_histogram_schema = Some(schema);
+        self
+    }
+
+    /// Discard matching values instead of producing a metric
+    pub fn with_drop(mut self, drop: bool) -> Self {
+        self.drop = drop;
+        self
+    }
+
     /// Compile the regex pattern
     ///
     /// This method lazily compiles the pattern on first call.
@@ -313,21 +442,40 @@ impl Rule {
 
     /// Apply the rule to generate a metric name from captures
     ///
-    /// Substitutes `$1`, `$2`, etc. and named groups `$name` with captured values.
+    /// Substitutes `$1`, `$2`, etc. and named groups `$name` with captured values,
+    /// converted to snake_case if `attrNameSnakeCase` is set.
     pub fn apply_name(&self, captures: &regex::Captures<'_>) -> String {
-        apply_substitution(&self.name, captures)
+        if self.attr_name_snake_case {
+            substitute(&self.name, captures, push_snake_case)
+        } else {
+            apply_substitution(&self.name, captures)
+        }
     }
 
     /// Apply substitution to labels
+    ///
+    /// Captured values in label values have their whitespace replaced with
+    /// `_` if `normalizeLabelValues` is set. After substitution, `labelDrops`
+    /// removes labels and `labelRenames` renames the remaining ones.
     pub fn apply_labels(&self, captures: &regex::Captures<'_>) -> HashMap<String, String> {
+        let push_value = if self.normalize_label_values {
+            push_without_whitespace
+        } else {
+            |value: &str, result: &mut String| result.push_str(value)
+        };
         self.labels
             .iter()
             .map(|(k, v)| {
                 (
                     apply_substitution(k, captures),
-                    apply_substitution(v, captures),
+                    substitute(v, captures, push_value),
                 )
             })
+            .filter(|(k, _)| !self.label_drops.contains(k))
+            .map(|(k, v)| match self.label_renames.get(&k) {
+                Some(renamed) => (renamed.clone(), v),
+                None => (k, v),
+            })
             .collect()
     }
 
@@ -339,7 +487,7 @@ impl Rule {
         self.compile()?;
 
         // Validate name is not empty
-        if self.name.is_empty() {
+        if self.name.is_empty() && !self.drop {
             return Err(RuleError::ValidationError(
                 "Rule name cannot be empty".to_string(),
             ));
@@ -368,6 +516,14 @@ impl Default for Rule {
             help: None,
             value: None,
             value_factor: None,
+            value_transform: None,
+            label_drops: Vec::new(),
+            label_renames: HashMap::new(),
+            cache: false,
+            attr_name_snake_case: false,
+            normalize_label_values: false,
+            native_histogram_schema: None,
+            drop: false,
             compiled_pattern: OnceCell::new(),
         }
     }
@@ -382,6 +538,10 @@ pub struct RuleBuilder {
     help: Option<String>,
     value: Option<String>,
     value_factor: Option<f64>,
+    label_drops: Vec<String>,
+    label_renames: HashMap<String, String>,
+    cache: bool,
+    attr_name_snake_case: bool,
 }
 
 impl RuleBuilder {
@@ -395,6 +555,10 @@ impl RuleBuilder {
             help: None,
             value: None,
             value_factor: None,
+            label_drops: Vec::new(),
+            label_renames: HashMap::new(),
+            cache: false,
+            attr_name_snake_case: false,
         }
     }
 
@@ -434,6 +598,30 @@ impl RuleBuilder {
         self
     }
 
+    /// Drop a label from generated metrics
+    pub fn label_drop(mut self, label: impl Into<String>) -> Self {
+        self.label_drops.push(label.into());
+        self
+    }
+
+    /// Rename a label on generated metrics
+    pub fn label_rename(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.label_renames.insert(from.into(), to.into());
+        self
+    }
+
+    /// Cache matched values across scrapes
+    pub fn cache(mut self, cache: bool) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Convert captured values to snake_case in the metric name
+    pub fn attr_name_snake_case(mut self, snake_case: bool) -> Self {
+        self.attr_name_snake_case = snake_case;
+        self
+    }
+
     /// Build the rule
     pub fn build(self) -> Rule {
         Rule {
@@ -444,6 +632,14 @@ impl RuleBuilder {
             help: self.help,
             value: self.value,
             value_factor: self.value_factor,
+            value_transform: None,
+            label_drops: self.label_drops,
+            label_renames: self.label_renames,
+            cache: self.cache,
+            attr_name_snake_case: self.attr_name_snake_case,
+            normalize_label_values: false,
+            native_histogram_schema: None,
+            drop: false,
             compiled_pattern: OnceCell::new(),
         }
     }
@@ -498,36 +694,95 @@ impl<'a> RuleMatch<'a> {
         self.rule.value_factor
     }
 
+    /// Get the value transform expression
+    pub fn value_transform(&self) -> Option<&Expr> {
+        self.rule.value_transform.as_ref()
+    }
+
     /// Get the value expression
     pub fn value(&self) -> Option<&str> {
         self.rule.value.as_deref()
     }
 }
 
+/// Rule count from which matching is narrowed down by a [`RegexSet`] first
+const PREFILTER_MIN_RULES: usize = 8;
+
 /// Collection of transformation rules
 ///
 /// Manages a set of rules for matching and transforming metrics.
 /// Supports pre-compilation of all patterns for performance.
+///
+/// Larger sets also compile all patterns into one [`RegexSet`], which finds
+/// the rules that can match an input in a single pass, so only those are run
+/// for captures. The set is built without Unicode support, which keeps it
+/// small enough to stay fast with hundreds of `\w`-heavy patterns; on ASCII
+/// input it matches exactly like the Unicode patterns, so other input is
+/// matched rule by rule.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RuleSet {
     /// The collection of rules
     rules: Vec<Rule>,
+
+    /// All patterns as one set, `None` if any pattern fails to compile
+    #[serde(skip)]
+    prefilter: OnceCell<Option<RegexSet>>,
 }
 
 impl RuleSet {
     /// Create a new empty rule set
     pub fn new() -> Self {
-        Self { rules: Vec::new() }
+        Self::default()
     }
 
     /// Create a rule set from a vector of rules
     pub fn from_rules(rules: Vec<Rule>) -> Self {
-        Self { rules }
+        Self {
+            rules,
+            prefilter: OnceCell::new(),
+        }
     }
 
     /// Add a rule to the set
     pub fn add(&mut self, rule: Rule) {
         self.rules.push(rule);
+        self.prefilter = OnceCell::new();
+    }
+
+    /// Pattern set used to skip rules that cannot match `input`
+    ///
+    /// Small sets and non-ASCII input are matched rule by rule. Without a set
+    /// (including when a pattern is invalid or needs Unicode), matching falls
+    /// back to trying every rule, which also reports pattern errors.
+    fn prefilter(&self, input: &str) -> Option<&RegexSet> {
+        if !input.is_ascii() {
+            return None;
+        }
+        self.pattern_set()
+    }
+
+    /// All patterns as one set, built on first use
+    fn pattern_set(&self) -> Option<&RegexSet> {
+        if self.rules.len() < PREFILTER_MIN_RULES {
+            return None;
+        }
+        self.prefilter
+            .get_or_init(|| {
+                let patterns = self
+                    .rules
+                    .iter()
+                    .map(|rule| rule.compile().map(|regex| regex.as_str()))
+                    .collect::<RuleResult<Vec<_>>>()
+                    .ok()?;
+                RegexSetBuilder::new(patterns)
+                    .unicode(false)
+                    .build()
+                    .inspect_err(|e| {
+                        tracing::debug!(error = %e, "Rule pattern set not built; matching rules one by one")
+                    })
+                    .ok()
+            })
+            .as_ref()
     }
 
     /// Get the number of rules
@@ -542,7 +797,8 @@ impl RuleSet {
 
     /// Compile all rule patterns
     ///
-    /// This method pre-compiles all regex patterns in the rule set.
+    /// This method pre-compiles all regex patterns in the rule set, and the
+    /// pattern set used to pre-filter rules in [`find_match`](Self::find_match).
     /// It's recommended to call this at startup for better performance.
     ///
     /// # Errors
@@ -557,9 +813,15 @@ impl RuleSet {
                 ))
             })?;
         }
+        self.pattern_set();
         Ok(())
     }
 
+    /// Whether matching is pre-filtered by a pattern set
+    pub fn is_prefiltered(&self) -> bool {
+        self.prefilter.get().is_some_and(Option::is_some)
+    }
+
     /// Validate all rules in the set
     ///
     /// Checks that all rules have valid patterns and configurations.
@@ -574,6 +836,9 @@ impl RuleSet {
 
     /// Find the first rule that matches the input
     ///
+    /// A matching drop rule is returned before any other rule, wherever it is
+    /// in the set.
+    ///
     /// # Arguments
     ///
     /// * `input` - The MBean object name or attribute path to match
@@ -582,6 +847,24 @@ impl RuleSet {
     ///
     /// Returns `Some(RuleMatch)` for the first matching rule, `None` if no rules match.
     pub fn find_match<'a>(&'a self, input: &'a str) -> RuleResult<Option<RuleMatch<'a>>> {
+        if let Some(set) = self.prefilter(input) {
+            let matches = set.matches(input.as_bytes());
+            let index = matches
+                .iter()
+                .find(|&index| self.rules[index].drop)
+                .or_else(|| matches.iter().next());
+            return match index {
+                Some(index) => self.rules[index].matches(input),
+                None => Ok(None),
+            };
+        }
+        if self.has_drops() {
+            for rule in self.rules.iter().filter(|rule| rule.drop) {
+                if let Some(m) = rule.matches(input)? {
+                    return Ok(Some(m));
+                }
+            }
+        }
         for rule in &self.rules {
             if let Some(m) = rule.matches(input)? {
                 return Ok(Some(m));
@@ -590,6 +873,37 @@ impl RuleSet {
         Ok(None)
     }
 
+    /// Index of the first rule that matches the input
+    ///
+    /// Like [`find_match`](Self::find_match), without extracting captures.
+    pub fn find_match_index(&self, input: &str) -> RuleResult<Option<usize>> {
+        if let Some(set) = self.prefilter(input) {
+            let matches = set.matches(input.as_bytes());
+            return Ok(matches
+                .iter()
+                .find(|&index| self.rules[index].drop)
+                .or_else(|| matches.iter().next()));
+        }
+        let mut first = None;
+        for (index, rule) in self.rules.iter().enumerate() {
+            if first.is_some() && !rule.drop {
+                continue;
+            }
+            if rule.compile()?.is_match(input) {
+                if rule.drop {
+                    return Ok(Some(index));
+                }
+                first = Some(index);
+            }
+        }
+        Ok(first)
+    }
+
+    /// Whether any rule of the set is a drop rule
+    fn has_drops(&self) -> bool {
+        self.rules.iter().any(|rule| rule.drop)
+    }
+
     /// Find all rules that match the input
     ///
     /// # Arguments
@@ -601,6 +915,12 @@ impl RuleSet {
     /// Returns a vector of all matching rules with their captures.
     pub fn find_all_matches<'a>(&'a self, input: &'a str) -> RuleResult<Vec<RuleMatch<'a>>> {
         let mut matches = Vec::new();
+        if let Some(set) = self.prefilter(input) {
+            for index in set.matches(input.as_bytes()).iter() {
+                matches.extend(self.rules[index].matches(input)?);
+            }
+            return Ok(matches);
+        }
         for rule in &self.rules {
             if let Some(m) = rule.matches(input)? {
                 matches.push(m);
@@ -645,9 +965,7 @@ impl<'a> IntoIterator for &'a RuleSet {
 
 impl FromIterator<Rule> for RuleSet {
     fn from_iter<I: IntoIterator<Item = Rule>>(iter: I) -> Self {
-        Self {
-            rules: iter.into_iter().collect(),
-        }
+        Self::from_rules(iter.into_iter().collect())
     }
 }
 
@@ -770,63 +1088,118 @@ pub fn convert_java_regex(pattern: &str) -> RuleResult<String> {
 /// Replaces `$1`, `$2`, etc. with the corresponding capture group values.
 /// Also supports named groups via `$name` syntax.
 fn apply_substitution(template: &str, captures: &regex::Captures<'_>) -> String {
+    substitute(template, captures, |value, result| result.push_str(value))
+}
+
+/// Capture group substitution, appending each captured value with `push`
+///
+/// References to groups the pattern does not have are replaced with nothing.
+fn substitute(
+    template: &str,
+    captures: &regex::Captures<'_>,
+    push: fn(&str, &mut String),
+) -> String {
     let mut result = String::with_capacity(template.len());
-    let mut chars = template.chars().peekable();
 
-    while let Some(c) = chars.next() {
-        if c == '$' {
-            // Check what follows the $
-            match chars.peek() {
-                Some(&first) if first.is_ascii_digit() => {
-                    // Numeric group reference ($1, $2, $12, etc.)
-                    let mut group_num = String::new();
-                    while let Some(&next) = chars.peek() {
-                        if next.is_ascii_digit() {
-                            group_num.push(next);
-                            chars.next();
-                        } else {
-                            break;
-                        }
-                    }
-                    if let Ok(index) = group_num.parse::<usize>() {
-                        if let Some(m) = captures.get(index) {
-                            result.push_str(m.as_str());
-                        }
-                        // If group doesn't exist, substitute with empty string
-                    }
-                }
-                Some(&first) if first.is_alphabetic() => {
-                    // Named group reference ($name)
-                    // Note: We only accept letters and digits after the first letter,
-                    // NOT underscores. This allows patterns like "$type_$attr" to work
-                    // correctly (where $type is substituted, then _, then $attr).
-                    let mut group_name = String::new();
-                    while let Some(&next) = chars.peek() {
-                        if next.is_alphanumeric() {
-                            group_name.push(next);
-                            chars.next();
-                        } else {
-                            break;
-                        }
-                    }
-                    if let Some(m) = captures.name(&group_name) {
-                        result.push_str(m.as_str());
-                    }
-                    // If group doesn't exist, substitute with empty string
-                }
-                _ => {
-                    // Literal $ (at end of string or followed by non-identifier char)
-                    result.push('$');
-                }
+    for part in template_parts(template) {
+        let captured = match part {
+            TemplatePart::Literal(text) => {
+                result.push_str(text);
+                continue;
             }
-        } else {
-            result.push(c);
+            TemplatePart::Index(index) => captures.get(index),
+            TemplatePart::Name(name) => captures.name(name),
+        };
+        if let Some(m) = captured {
+            push(m.as_str(), &mut result);
         }
     }
 
     result
 }
 
+/// Piece of a name or label template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplatePart<'a> {
+    /// Text copied as is
+    Literal(&'a str),
+    /// Numbered capture group reference (`$1`)
+    Index(usize),
+    /// Named capture group reference (`$name`)
+    Name(&'a str),
+}
+
+/// Split a name or label template into literal text and group references
+///
+/// A `$` followed by digits references a numbered group, and a `$` followed
+/// by a letter references a named group. Names take letters and digits but
+/// NOT underscores, so `$type_$attr` references `type`, then `attr`. Any
+/// other `$` is literal.
+pub fn template_parts(template: &str) -> impl Iterator<Item = TemplatePart<'_>> {
+    let mut rest = template;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let Some(reference) = rest.strip_prefix('$') else {
+            let end = rest.find('$').unwrap_or(rest.len());
+            let (literal, tail) = rest.split_at(end);
+            rest = tail;
+            return Some(TemplatePart::Literal(literal));
+        };
+
+        let part = match reference.chars().next() {
+            Some(first) if first.is_ascii_digit() => {
+                let end = reference
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(reference.len());
+                let (digits, tail) = reference.split_at(end);
+                rest = tail;
+                // An out-of-range number references no group
+                TemplatePart::Index(digits.parse().unwrap_or(usize::MAX))
+            }
+            Some(first) if first.is_alphabetic() => {
+                let end = reference
+                    .find(|c: char| !c.is_alphanumeric())
+                    .unwrap_or(reference.len());
+                let (name, tail) = reference.split_at(end);
+                rest = tail;
+                TemplatePart::Name(name)
+            }
+            _ => {
+                rest = reference;
+                TemplatePart::Literal("$")
+            }
+        };
+        Some(part)
+    })
+}
+
+/// Append a camelCase value in snake_case, following jmx_exporter:
+/// an underscore is inserted before an upper-case letter unless the previous
+/// character was upper-case or an underscore, so `HeapMemoryUsage` becomes
+/// `heap_memory_usage` and `HTTPRequests` becomes `httprequests`.
+pub(crate) fn push_snake_case(value: &str, result: &mut String) {
+    let mut previous_upper_or_underscore = true;
+    for c in value.chars() {
+        let upper = c.is_uppercase();
+        if upper && !previous_upper_or_underscore {
+            result.push('_');
+        }
+        result.extend(c.to_lowercase());
+        previous_upper_or_underscore = upper || c == '_';
+    }
+}
+
+/// Append `value` with each whitespace character replaced by `_`
+fn push_without_whitespace(value: &str, result: &mut String) {
+    result.extend(
+        value
+            .chars()
+            .map(|c| if c.is_whitespace() { '_' } else { c }),
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1013,6 +1386,64 @@ mod tests {
         assert_eq!(name, "jvm_Memory_HeapMemoryUsage_used_bytes");
     }
 
+    #[test]
+    fn test_rule_apply_name_snake_case() {
+        let rule = Rule::new(
+            r"java\.lang<type=(\w+)><(?P<attr>\w+)>(\w+)",
+            "jvm_$1_$attr_$3",
+            MetricType::Gauge,
+        )
+        .with_label("attr", "$attr")
+        .with_attr_name_snake_case(true);
+
+        let regex = rule.compile().unwrap();
+        let caps = regex
+            .captures("java.lang<type=Memory><HeapMemoryUsage>used")
+            .unwrap();
+
+        assert_eq!(rule.apply_name(&caps), "jvm_memory_heap_memory_usage_used");
+        // Label values are substituted unchanged
+        assert_eq!(rule.apply_labels(&caps)["attr"], "HeapMemoryUsage");
+    }
+
+    #[test]
+    fn test_rule_apply_labels_normalized() {
+        let rule = Rule::new(
+            r"java\.lang<name=([^<>]+)><type=MemoryPool><Usage><used>",
+            "jvm_memory_pool_bytes_used",
+            MetricType::Gauge,
+        )
+        .with_label("pool", "$1")
+        .with_label("source", "pool $1");
+
+        let regex = rule.compile().unwrap();
+        let caps = regex
+            .captures("java.lang<name=G1 Eden Space><type=MemoryPool><Usage><used>")
+            .unwrap();
+        assert_eq!(rule.apply_labels(&caps)["pool"], "G1 Eden Space");
+
+        let rule = rule.with_normalize_label_values(true);
+        let labels = rule.apply_labels(&caps);
+        assert_eq!(labels["pool"], "G1_Eden_Space");
+        // Only captured values are normalized
+        assert_eq!(labels["source"], "pool G1_Eden_Space");
+    }
+
+    #[test]
+    fn test_snake_case() {
+        let snake = |value: &str| {
+            let mut result = String::new();
+            push_snake_case(value, &mut result);
+            result
+        };
+        assert_eq!(snake("HeapMemoryUsage"), "heap_memory_usage");
+        assert_eq!(snake("collectionCount"), "collection_count");
+        assert_eq!(snake("HTTPRequests"), "httprequests");
+        assert_eq!(snake("already_snake"), "already_snake");
+        assert_eq!(snake("G1_YoungGen"), "g1_young_gen");
+        assert_eq!(snake(""), "");
+    }
+
     #[test]
     fn test_rule_apply_labels() {
         let rule = Rule::new(r"java\.lang<type=(\w+)>", "metric", MetricType::Gauge)
@@ -1027,6 +1458,28 @@ mod tests {
         assert_eq!(labels.get("static"), Some(&"value".to_string()));
     }
 
+    #[test]
+    fn test_rule_apply_labels_drops_and_renames() {
+        let rule = Rule::builder(r"java\.lang<type=(\w+),name=(\w+)>")
+            .name("metric")
+            .label("type", "$1")
+            .label("name", "$2")
+            .label("area", "heap")
+            .label_drop("name")
+            .label_rename("type", "mbean_type")
+            .build();
+
+        let regex = rule.compile().unwrap();
+        let caps = regex.captures("java.lang<type=Memory,name=Pool>").unwrap();
+        let labels = rule.apply_labels(&caps);
+
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels.get("mbean_type"), Some(&"Memory".to_string()));
+        assert_eq!(labels.get("area"), Some(&"heap".to_string()));
+        assert!(!labels.contains_key("name"));
+        assert!(!labels.contains_key("type"));
+    }
+
     #[test]
     fn test_rule_validate_empty_name() {
         let rule = Rule::new(r"pattern", "", MetricType::Gauge);
@@ -1165,6 +1618,115 @@ mod tests {
         assert!(m.is_none());
     }
 
+    #[test]
+    fn test_ruleset_prefilter() {
+        let mut rules: Vec<Rule> = (0..PREFILTER_MIN_RULES)
+            .map(|i| {
+                Rule::new(
+                    format!(r"app{}<type=(\w+)>", i),
+                    "unused",
+                    MetricType::Gauge,
+                )
+            })
+            .collect();
+        rules.push(Rule::new(
+            r"java\.lang<type=(\w+)>",
+            "jvm_$1",
+            MetricType::Gauge,
+        ));
+        rules.push(Rule::new(r"java\.(\w+)", "java_$1", MetricType::Gauge));
+        let ruleset = RuleSet::from_rules(rules);
+        assert!(!ruleset.is_prefiltered());
+        ruleset.compile_all().unwrap();
+        assert!(ruleset.is_prefiltered());
+        assert!(ruleset.prefilter("java.lang").is_some());
+        assert!(ruleset.prefilter("java.lang<type=Café>").is_none());
+
+        // The first matching rule wins, with its own captures
+        let m = ruleset
+            .find_match("java.lang<type=Memory>")
+            .unwrap()
+            .unwrap();
+        assert_eq!(m.metric_name(), "jvm_Memory");
+        assert_eq!(
+            ruleset
+                .find_all_matches("java.lang<type=Memory>")
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(ruleset.find_match("other<type=Memory>").unwrap().is_none());
+        let m = ruleset.find_match("java.lang<type=Café>").unwrap().unwrap();
+        assert_eq!(m.metric_name(), "jvm_Café");
+        let jvm = PREFILTER_MIN_RULES;
+        assert_eq!(
+            ruleset.find_match_index("java.lang<type=Memory>").unwrap(),
+            Some(jvm)
+        );
+        assert_eq!(
+            ruleset.find_match_index("java.lang<type=Café>").unwrap(),
+            Some(jvm)
+        );
+        assert_eq!(
+            ruleset.find_match_index("java.util").unwrap(),
+            Some(jvm + 1)
+        );
+        assert_eq!(ruleset.find_match_index("other").unwrap(), None);
+
+        // An invalid pattern disables the set and is still reported
+        let mut ruleset = ruleset;
+        ruleset.add(Rule::new("invalid[", "name", MetricType::Gauge));
+        assert!(ruleset.prefilter("other").is_none());
+        assert!(!ruleset.is_prefiltered());
+        assert!(ruleset.compile_all().is_err());
+        assert!(ruleset.find_match("other").is_err());
+    }
+
+    #[test]
+    fn test_ruleset_drop_rules() {
+        let rules = |count: usize| {
+            let mut rules: Vec<Rule> = (0..count)
+                .map(|i| Rule::new(format!("app{}<", i), "unused", MetricType::Gauge))
+                .collect();
+            rules.push(Rule::new(
+                r"java\.lang<type=(\w+)>",
+                "jvm_$1",
+                MetricType::Gauge,
+            ));
+            rules.push(Rule::new(r"<type=Compilation>", "", MetricType::Gauge).with_drop(true));
+            RuleSet::from_rules(rules)
+        };
+
+        // Drop rules win wherever they are, with and without the pattern set
+        for ruleset in [rules(0), rules(PREFILTER_MIN_RULES)] {
+            ruleset.compile_all().unwrap();
+            let jvm = ruleset.len() - 2;
+            let m = ruleset
+                .find_match("java.lang<type=Compilation>")
+                .unwrap()
+                .unwrap();
+            assert!(m.rule.drop);
+            assert_eq!(
+                ruleset
+                    .find_match_index("java.lang<type=Compilation>")
+                    .unwrap(),
+                Some(jvm + 1)
+            );
+            assert_eq!(
+                ruleset.find_match_index("java.lang<type=Memory>").unwrap(),
+                Some(jvm)
+            );
+            assert!(
+                !ruleset
+                    .find_match("java.lang<type=Memory>")
+                    .unwrap()
+                    .unwrap()
+                    .rule
+                    .drop
+            );
+        }
+    }
+
     #[test]
     fn test_ruleset_find_all_matches() {
         let ruleset = RuleSet::from_rules(vec![
@@ -1381,6 +1943,24 @@ mod tests {
         assert_eq!(result, "price_Memory_$");
     }
 
+    #[test]
+    fn test_template_parts() {
+        let parts: Vec<_> = template_parts("jvm_$type_$12$-x$").collect();
+        assert_eq!(
+            parts,
+            vec![
+                TemplatePart::Literal("jvm_"),
+                TemplatePart::Name("type"),
+                TemplatePart::Literal("_"),
+                TemplatePart::Index(12),
+                TemplatePart::Literal("$"),
+                TemplatePart::Literal("-x"),
+                TemplatePart::Literal("$"),
+            ]
+        );
+        assert_eq!(template_parts("").count(), 0);
+    }
+
     // ==========================================================================
     // Integration tests
     // ==========================================================================
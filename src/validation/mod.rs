@@ -0,0 +1,1267 @@
+//! Configuration validation reports
+//!
+//! Collects every configuration problem in one pass (instead of stopping at
+//! the first error) so `--validate` can print them all, and `--report` can
+//! write a machine-readable artifact for CI annotations.
+//!
+//! Each issue carries a stable `code`, a location (e.g. `rules[3]`) and, where
+//! possible, a suggestion for fixing it.
+//!
+//! Rule sets are also analyzed statically: rules shadowed by an earlier
+//! catch-all pattern, `$` references to capture groups a pattern does not
+//! have, and static rules emitting the same series are reported as warnings.
+//! Examples embedded in rules (`tests:`) are run through the transform engine
+//! (see [`check_rule_tests`]). With `--online`, the configuration is also
+//! checked against the running Jolokia agent (see [`online`]). With
+//! `--dry-run --profile-rules`, rule patterns are timed against sample
+//! responses (see [`profile`]).
+
+use std::collections::{hash_map::Entry, BTreeMap, HashMap};
+use std::path::Path;
+
+use regex::Regex;
+use regex_syntax::hir::{Class, Hir, HirKind, Look};
+use serde::Serialize;
+
+use crate::config::{presets, Config, ConfigError, Rule, RuleAction, RuleTest, RESERVED_PATHS};
+use crate::server::build_engine;
+use crate::transformer::{
+    convert_java_regex, template_parts, ConflictPolicy, PrometheusMetric, RelabelAction,
+    RelabelRule, TemplatePart,
+};
+
+pub mod online;
+pub mod profile;
+
+pub use online::{check_online, OnlineReport};
+pub use profile::{live_sample, profile_rules, ProfileReport};
+
+/// Issue severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Prevents the exporter from starting
+    Error,
+    /// Accepted, but likely not what was intended
+    Warning,
+}
+
+/// A single validation finding
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    /// Issue severity
+    pub severity: Severity,
+    /// Stable machine-readable code (e.g. `invalid_rule_regex`)
+    pub code: &'static str,
+    /// Human-readable message
+    pub message: String,
+    /// Location in the configuration (e.g. `rules[3]`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// Zero-based rule index, for rule-related issues
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule_index: Option<usize>,
+    /// Suggested fix
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+impl Issue {
+    /// Create an error issue
+    pub fn error(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            location: None,
+            rule_index: None,
+            suggestion: None,
+        }
+    }
+
+    /// Create a warning issue
+    pub fn warning(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(code, message)
+        }
+    }
+
+    /// Set the configuration location
+    pub fn with_location(mut self, location: impl Into<String>) -> Self {
+        self.location = Some(location.into());
+        self
+    }
+
+    /// Attach a rule index (also sets the location)
+    pub fn with_rule(mut self, index: usize) -> Self {
+        self.rule_index = Some(index);
+        self.with_location(format!("rules[{}]", index))
+    }
+
+    /// Set the suggested fix
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+}
+
+/// Full validation report
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationReport {
+    /// True if there are no errors (warnings are allowed)
+    pub valid: bool,
+    /// Configuration file that was validated
+    pub config_file: String,
+    /// Number of errors
+    pub error_count: usize,
+    /// Number of warnings
+    pub warning_count: usize,
+    /// All findings, in configuration order
+    pub issues: Vec<Issue>,
+    /// Results of the online checks, if they ran
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub online: Option<OnlineReport>,
+}
+
+impl ValidationReport {
+    /// Build a report from a list of issues
+    pub fn new(config_file: impl Into<String>, issues: Vec<Issue>) -> Self {
+        let error_count = issues
+            .iter()
+            .filter(|i| i.severity == Severity::Error)
+            .count();
+        Self {
+            valid: error_count == 0,
+            config_file: config_file.into(),
+            error_count,
+            warning_count: issues.len() - error_count,
+            issues,
+            online: None,
+        }
+    }
+
+    /// Attach the results of the online checks
+    pub fn with_online(mut self, online: OnlineReport) -> Self {
+        self.online = Some(online);
+        self
+    }
+
+    /// Build a report for a configuration that could not be loaded
+    pub fn load_failure(config_file: impl Into<String>, error: &ConfigError) -> Self {
+        let issue = match error {
+            ConfigError::ReadError(_) => Issue::error("config_read_error", error.to_string())
+                .with_suggestion("Check that the file exists and is readable"),
+            ConfigError::ParseError(_) => Issue::error("config_parse_error", error.to_string())
+                .with_suggestion("Fix the YAML syntax or field types at the reported position"),
+            ConfigError::ValidationError(_) => {
+                Issue::error("config_validation_error", error.to_string())
+            }
+        };
+        Self::new(config_file, vec![issue])
+    }
+
+    /// Iterate over errors
+    pub fn errors(&self) -> impl Iterator<Item = &Issue> {
+        self.issues.iter().filter(|i| i.severity == Severity::Error)
+    }
+
+    /// Iterate over warnings
+    pub fn warnings(&self) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == Severity::Warning)
+    }
+
+    /// Write the report as pretty-printed JSON
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+}
+
+/// Check a configuration, collecting every error and warning
+pub fn check_config(config: &Config) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    // Keys that match no option, usually typos; rejected in strict mode
+    for key in &config.unknown_keys {
+        let message = format!("Unknown key '{}'", key);
+        let issue = if config.strict {
+            Issue::error("unknown_key", message)
+        } else {
+            Issue::warning("unknown_key", message)
+        };
+        issues.push(
+            issue
+                .with_location(key)
+                .with_suggestion("Check the spelling, or remove the key"),
+        );
+    }
+
+    // Port (overrides already applied to config)
+    if let Err(e) = Config::validate_port(config.server.port) {
+        issues.push(
+            Issue::error("invalid_port", format!("Invalid port: {}", e))
+                .with_location("server.port")
+                .with_suggestion("Use a port between 1 and 65535"),
+        );
+    }
+
+    // Listen addresses
+    if let Err(e) = config.server.listen_addresses() {
+        let location = if config.server.listen.is_empty() {
+            "server.bind_address"
+        } else {
+            "server.listen"
+        };
+        issues.push(Issue::error("invalid_listen_address", e.to_string()).with_location(location));
+    }
+
+    // Metrics path
+    if !config.server.path.starts_with('/') {
+        issues.push(
+            Issue::error("invalid_metrics_path", "Metrics path must start with '/'")
+                .with_location("server.path")
+                .with_suggestion(format!("Use '/{}'", config.server.path)),
+        );
+    } else if RESERVED_PATHS.contains(&config.server.path.as_str()) {
+        issues.push(
+            Issue::error(
+                "invalid_metrics_path",
+                format!(
+                    "Metrics path must not conflict with reserved paths ({})",
+                    RESERVED_PATHS.join(", ")
+                ),
+            )
+            .with_location("server.path")
+            .with_suggestion("Use '/metrics'"),
+        );
+    }
+
+    // TLS
+    if config.server.tls.enabled {
+        if config.server.tls.cert_file.is_none() {
+            issues.push(
+                Issue::error(
+                    "tls_missing_cert_file",
+                    "TLS is enabled but cert_file is not specified",
+                )
+                .with_location("server.tls.cert_file"),
+            );
+        }
+        if config.server.tls.key_file.is_none() {
+            issues.push(
+                Issue::error(
+                    "tls_missing_key_file",
+                    "TLS is enabled but key_file is not specified",
+                )
+                .with_location("server.tls.key_file"),
+            );
+        }
+    }
+
+    let catch_all = check_rules(&config.rules, &mut issues);
+    check_metadata_conflicts(config, &config.rules, &mut issues);
+    check_duplicate_series(config, &config.rules, &mut issues);
+
+    // Presets are appended after `rules`
+    if let (Some(first), false) = (catch_all, config.presets.is_empty()) {
+        issues.push(
+            Issue::warning(
+                "unreachable_rule",
+                format!(
+                    "Presets are unreachable: rule {} matches every MBean",
+                    first
+                ),
+            )
+            .with_location("presets")
+            .with_suggestion(format!("Narrow the pattern of rule {}", first)),
+        );
+    }
+
+    // Bundled rule presets
+    for (i, name) in config.presets.iter().enumerate() {
+        if !presets::exists(name) {
+            issues.push(
+                Issue::error("unknown_preset", format!("Unknown preset '{}'", name))
+                    .with_location(format!("presets[{}]", i))
+                    .with_suggestion(format!("Use one of: {}", presets::NAMES.join(", "))),
+            );
+        }
+    }
+
+    // Named modules, in name order for stable reports
+    let mut modules: Vec<_> = config.modules.iter().collect();
+    modules.sort_by_key(|(name, _)| name.as_str());
+    for (name, module) in modules {
+        if module.rules.is_empty() && module.collect.is_empty() {
+            issues.push(
+                Issue::error(
+                    "empty_module",
+                    format!("Module '{}' defines no rules or collect entries", name),
+                )
+                .with_location(format!("modules.{}", name))
+                .with_suggestion("Add rules or collect entries, or remove the module"),
+            );
+        }
+
+        let mut module_issues = Vec::new();
+        check_rules(&module.rules, &mut module_issues);
+        check_metadata_conflicts(config, &module.rules, &mut module_issues);
+        check_duplicate_series(config, &module.rules, &mut module_issues);
+        issues.extend(module_issues.into_iter().map(|mut issue| {
+            if let Some(i) = issue.rule_index {
+                issue.location = Some(format!("modules.{}.rules[{}]", name, i));
+            }
+            issue.message = format!("Module '{}': {}", name, issue.message);
+            issue
+        }));
+    }
+
+    // Explicit MBean queries
+    for (i, entry) in config.collect.iter().enumerate() {
+        if entry.object_name.trim().is_empty() {
+            issues.push(
+                Issue::error(
+                    "empty_object_name",
+                    format!("Collect entry {} has empty object_name", i),
+                )
+                .with_location(format!("collect[{}]", i)),
+            );
+        }
+    }
+    if !config.collect.is_empty() && !config.whitelist_object_names.is_empty() {
+        issues.push(
+            Issue::warning(
+                "whitelist_ignored",
+                "whitelistObjectNames is ignored because collect is set",
+            )
+            .with_location("whitelistObjectNames")
+            .with_suggestion("Move the whitelisted MBeans into collect"),
+        );
+    }
+
+    // Cardinality limits
+    if config.limits.max_series_per_scrape == Some(0) {
+        issues.push(
+            Issue::error(
+                "invalid_limit",
+                "limits.max_series_per_scrape must be greater than 0",
+            )
+            .with_location("limits.max_series_per_scrape")
+            .with_suggestion("Remove the option to disable the limit"),
+        );
+    }
+    if config.limits.max_label_value_length == Some(0) {
+        issues.push(
+            Issue::error(
+                "invalid_limit",
+                "limits.max_label_value_length must be greater than 0",
+            )
+            .with_location("limits.max_label_value_length")
+            .with_suggestion("Remove the option to disable the limit"),
+        );
+    }
+
+    // Relabel configs
+    for (i, relabel) in config.relabel_configs.iter().enumerate() {
+        let location = format!("relabel_configs[{}]", i);
+        if let Err(e) = RelabelRule::new(relabel.action, &relabel.regex) {
+            issues.push(
+                Issue::error(
+                    "invalid_relabel_regex",
+                    format!("Relabel config {}: {}", i, e),
+                )
+                .with_location(&location),
+            );
+        }
+        if relabel.action == RelabelAction::Replace && relabel.target_label.is_none() {
+            issues.push(
+                Issue::error(
+                    "relabel_missing_target_label",
+                    format!(
+                        "Relabel config {}: action 'replace' requires target_label",
+                        i
+                    ),
+                )
+                .with_location(&location)
+                .with_suggestion("Set target_label or use a different action"),
+            );
+        }
+    }
+
+    issues.extend(check_rule_tests(config));
+
+    // Lenient loading skips `Config::validate`, which also covers settings
+    // without a check above. It stops at its first problem, which the checks
+    // above report in more detail when they find one.
+    if !issues.iter().any(|issue| issue.severity == Severity::Error) {
+        if let Err(e) = config.validate() {
+            issues.push(Issue::error("config_validation_error", e.to_string()));
+        }
+    }
+
+    issues
+}
+
+/// Run the `tests:` examples of every rule through the transform engine
+///
+/// Rules are tried in order, as during a scrape, so an example taken by an
+/// earlier rule fails. Module rules are tried before the top-level rules.
+pub fn check_rule_tests(config: &Config) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    run_rule_tests(config, &config.rules, None, &mut issues);
+
+    let mut modules: Vec<_> = config.modules.iter().collect();
+    modules.sort_by_key(|(name, _)| name.as_str());
+    for (name, module) in modules {
+        if let Some(module_config) = config.module(name) {
+            run_rule_tests(&module_config, &module.rules, Some(name), &mut issues);
+        }
+    }
+    issues
+}
+
+/// Run the examples of `rules`, which come first in `config`
+fn run_rule_tests(config: &Config, rules: &[Rule], module: Option<&str>, issues: &mut Vec<Issue>) {
+    if rules.iter().all(|rule| rule.tests.is_empty()) {
+        return;
+    }
+    // Rules that do not compile are reported by check_rules
+    let Ok(engine) = build_engine(config) else {
+        return;
+    };
+
+    for (i, rule) in rules.iter().enumerate() {
+        for (j, test) in rule.tests.iter().enumerate() {
+            let failure = match engine.preview(&test.input) {
+                Err(e) => Some(e.to_string()),
+                Ok(None) => Some(format!("'{}' matches no rule", test.input)),
+                Ok(Some((index, None))) if index != i => {
+                    Some(format!("'{}' is dropped by rule {}", test.input, index))
+                }
+                Ok(Some((index, _))) if index != i => Some(format!(
+                    "'{}' is matched by rule {} first",
+                    test.input, index
+                )),
+                Ok(Some((_, None))) => (test.expect_name.is_some() || test.expect_labels.is_some())
+                    .then(|| "drop rules produce no metric to compare".to_string()),
+                Ok(Some((_, Some(metric)))) => rule_test_failure(test, &metric),
+            };
+            let Some(failure) = failure else {
+                continue;
+            };
+            let (message, location) = match module {
+                Some(name) => (
+                    format!("Module '{}': rule {} test {}: {}", name, i, j, failure),
+                    format!("modules.{}.rules[{}].tests[{}]", name, i, j),
+                ),
+                None => (
+                    format!("Rule {} test {}: {}", i, j, failure),
+                    format!("rules[{}].tests[{}]", i, j),
+                ),
+            };
+            issues.push(
+                Issue::error("rule_test_failed", message)
+                    .with_rule(i)
+                    .with_location(location),
+            );
+        }
+    }
+}
+
+/// Difference between the expected and the produced metric, if any
+fn rule_test_failure(test: &RuleTest, metric: &PrometheusMetric) -> Option<String> {
+    if let Some(expect_name) = &test.expect_name {
+        if &metric.name != expect_name {
+            return Some(format!(
+                "expected name '{}', got '{}'",
+                expect_name, metric.name
+            ));
+        }
+    }
+    let expected = test.expect_labels.as_ref()?;
+    let labels: BTreeMap<String, String> = metric
+        .labels
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    (&labels != expected).then(|| {
+        format!(
+            "expected labels {}, got {}",
+            format_labels(expected),
+            format_labels(&labels)
+        )
+    })
+}
+
+/// Labels in exposition format, e.g. `{gc="G1"}`
+fn format_labels(labels: &BTreeMap<String, String>) -> String {
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, v))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+/// Check rule patterns, types and duplicates
+///
+/// Returns the index of the first rule matching every MBean, if any.
+fn check_rules(rules: &[Rule], issues: &mut Vec<Issue>) -> Option<usize> {
+    let mut seen_patterns: HashMap<&str, usize> = HashMap::new();
+    let mut catch_all = None;
+
+    for (i, rule) in rules.iter().enumerate() {
+        let drop = rule.action == RuleAction::Drop;
+
+        // The first matching rule wins, so nothing after a catch-all is
+        // reached; drop rules are tried before all others
+        if let (Some(first), false) = (catch_all, drop) {
+            issues.push(
+                Issue::warning(
+                    "unreachable_rule",
+                    format!(
+                        "Rule {}: unreachable, rule {} matches every MBean",
+                        i, first
+                    ),
+                )
+                .with_rule(i)
+                .with_suggestion(format!("Move this rule before rule {}", first)),
+            );
+        }
+
+        if !drop && rule.name.is_empty() {
+            issues.push(
+                Issue::error("missing_rule_name", format!("Rule {} has no name", i))
+                    .with_rule(i)
+                    .with_suggestion("Set name, or action: drop to discard the matched values"),
+            );
+        }
+
+        if rule.pattern.is_empty() {
+            issues.push(
+                Issue::error(
+                    "empty_rule_pattern",
+                    format!("Rule {} has empty pattern", i),
+                )
+                .with_rule(i),
+            );
+            continue;
+        }
+
+        // Convert Java regex to Rust regex
+        match convert_java_regex(&rule.pattern) {
+            Ok(converted_pattern) => match Regex::new(&converted_pattern) {
+                Ok(regex) => {
+                    check_group_references(i, rule, &regex, issues);
+                    if catch_all.is_none() && !drop && is_catch_all(&converted_pattern) {
+                        catch_all = Some(i);
+                    }
+                }
+                Err(e) => {
+                    issues.push(
+                        Issue::error(
+                            "invalid_rule_regex",
+                            format!(
+                                "Rule {}: Invalid regex after conversion: {} (original: {}, converted: {})",
+                                i, e, rule.pattern, converted_pattern
+                            ),
+                        )
+                        .with_rule(i),
+                    );
+                }
+            },
+            Err(e) => {
+                issues.push(
+                    Issue::error(
+                        "regex_conversion_error",
+                        format!("Rule {}: Regex conversion error: {}", i, e),
+                    )
+                    .with_rule(i),
+                );
+            }
+        }
+
+        if !matches!(
+            rule.r#type.to_lowercase().as_str(),
+            "gauge" | "counter" | "histogram" | "untyped"
+        ) {
+            issues.push(
+                Issue::warning(
+                    "unknown_metric_type",
+                    format!(
+                        "Rule {}: unknown metric type '{}', treated as untyped",
+                        i, rule.r#type
+                    ),
+                )
+                .with_rule(i)
+                .with_suggestion("Use gauge, counter, histogram or untyped"),
+            );
+        }
+
+        if let Some(first) = seen_patterns.get(rule.pattern.as_str()) {
+            issues.push(
+                Issue::warning(
+                    "duplicate_rule_pattern",
+                    format!(
+                        "Rule {}: pattern duplicates rule {} and will never match",
+                        i, first
+                    ),
+                )
+                .with_rule(i)
+                .with_suggestion(format!("Remove this rule or merge it into rule {}", first)),
+            );
+        } else {
+            seen_patterns.insert(&rule.pattern, i);
+        }
+    }
+
+    catch_all
+}
+
+/// Flag `$` references in the name and labels to groups the pattern lacks
+///
+/// Such references are replaced with nothing at scrape time.
+fn check_group_references(index: usize, rule: &Rule, regex: &Regex, issues: &mut Vec<Issue>) {
+    let mut labels: Vec<_> = rule.labels.iter().collect();
+    labels.sort();
+    let templates = std::iter::once(("name", &rule.name)).chain(
+        labels
+            .into_iter()
+            .flat_map(|(k, v)| [("label", k), ("label", v)]),
+    );
+
+    for (field, template) in templates {
+        for part in template_parts(template) {
+            let reference = match part {
+                TemplatePart::Index(group) if group >= regex.captures_len() => group.to_string(),
+                TemplatePart::Name(name) if !regex.capture_names().flatten().any(|n| n == name) => {
+                    name.to_string()
+                }
+                _ => continue,
+            };
+            issues.push(
+                Issue::warning(
+                    "unknown_capture_group",
+                    format!(
+                        "Rule {}: {} '{}' references ${}, which the pattern does not capture",
+                        index, field, template, reference
+                    ),
+                )
+                .with_rule(index)
+                .with_suggestion(format!(
+                    "The pattern has {} capture group(s)",
+                    regex.captures_len() - 1
+                )),
+            );
+        }
+    }
+}
+
+/// Check whether a (converted) pattern matches every MBean
+///
+/// Patterns are searched unanchored, so one that can match the empty string
+/// matches everything. Anchored at both ends it must match any text, as
+/// `^(.*)$` does.
+fn is_catch_all(pattern: &str) -> bool {
+    let Ok(hir) = regex_syntax::parse(pattern) else {
+        return false;
+    };
+    let parts = match hir.kind() {
+        HirKind::Concat(parts) => parts.as_slice(),
+        _ => std::slice::from_ref(&hir),
+    };
+
+    let starts = matches!(
+        parts.first().map(Hir::kind),
+        Some(HirKind::Look(Look::Start))
+    );
+    let ends = parts.len() > usize::from(starts)
+        && matches!(parts.last().map(Hir::kind), Some(HirKind::Look(Look::End)));
+    let inner = &parts[usize::from(starts)..parts.len() - usize::from(ends)];
+
+    let optional = |hir: &Hir| {
+        hir.properties().minimum_len() == Some(0) && hir.properties().look_set().is_empty()
+    };
+    if starts && ends {
+        inner
+            .iter()
+            .all(|hir| optional(hir) || matches_any_text(hir))
+            && inner.iter().any(matches_any_text)
+    } else {
+        inner.iter().all(optional)
+    }
+}
+
+/// Check whether a pattern piece matches any (non-empty) text, like `.*` or `(.+)`
+fn matches_any_text(hir: &Hir) -> bool {
+    match hir.kind() {
+        HirKind::Capture(capture) => matches_any_text(&capture.sub),
+        HirKind::Repetition(repetition) => {
+            repetition.min <= 1 && repetition.max.is_none() && is_any_char(&repetition.sub)
+        }
+        _ => false,
+    }
+}
+
+/// Check whether a class matches every char, except at most `\n` (like `.`)
+///
+/// MBean names never contain a newline.
+fn is_any_char(hir: &Hir) -> bool {
+    let HirKind::Class(Class::Unicode(class)) = hir.kind() else {
+        return false;
+    };
+    let mut excluded = class.clone();
+    excluded.negate();
+    excluded
+        .ranges()
+        .iter()
+        .all(|range| range.start() == '\n' && range.end() == '\n')
+}
+
+/// Flag rules that give the same metric name a different type or help text
+///
+/// Names are compared as emitted (after `lowercaseOutputName` and
+/// `counter_total_suffix`). Names with `$` substitutions depend on the
+/// matched MBean and are skipped. Conflicts are errors with
+/// `metric_conflict_policy: error`, and warnings otherwise since the policy
+/// resolves them at scrape time.
+fn check_metadata_conflicts(config: &Config, rules: &[Rule], issues: &mut Vec<Issue>) {
+    let issue = |code, message| match config.metric_conflict_policy {
+        ConflictPolicy::Error => Issue::error(code, message),
+        _ => Issue::warning(code, message),
+    };
+    let mut seen: HashMap<String, (usize, &'static str, Option<&str>)> = HashMap::new();
+
+    for (i, rule) in rules.iter().enumerate() {
+        if rule.action == RuleAction::Drop || rule.name.contains('$') {
+            continue;
+        }
+
+        let (name, metric_type) = emitted_metric(config, rule);
+        let Some(&(first, first_type, first_help)) = seen.get(&name) else {
+            seen.insert(name, (i, metric_type, rule.help.as_deref()));
+            continue;
+        };
+        if first_type != metric_type {
+            issues.push(
+                issue(
+                    "metric_type_conflict",
+                    format!(
+                        "Rule {}: metric '{}' is typed {} here but {} by rule {}",
+                        i, name, metric_type, first_type, first
+                    ),
+                )
+                .with_rule(i)
+                .with_suggestion(format!("Use type {} or a different name", first_type)),
+            );
+        }
+        if first_help != rule.help.as_deref() {
+            issues.push(
+                issue(
+                    "metric_help_conflict",
+                    format!(
+                        "Rule {}: metric '{}' has different help text than rule {}",
+                        i, name, first
+                    ),
+                )
+                .with_rule(i)
+                .with_suggestion(format!("Use the help text of rule {}", first)),
+            );
+        }
+    }
+}
+
+/// Flag static rules that emit the same series
+///
+/// A rule without `$` references in its name and labels emits the same
+/// series for every MBean it matches, so two such rules with the same name
+/// and labels produce duplicate samples.
+fn check_duplicate_series(config: &Config, rules: &[Rule], issues: &mut Vec<Issue>) {
+    let mut seen: HashMap<(String, Vec<(String, String)>), usize> = HashMap::new();
+
+    for (i, rule) in rules.iter().enumerate() {
+        let is_static = |template: &str| {
+            template_parts(template).all(|part| matches!(part, TemplatePart::Literal(_)))
+        };
+        if rule.action == RuleAction::Drop
+            || !is_static(&rule.name)
+            || !rule
+                .labels
+                .iter()
+                .all(|(k, v)| is_static(k) && is_static(v))
+        {
+            continue;
+        }
+
+        let (name, _) = emitted_metric(config, rule);
+        let mut labels: Vec<(String, String)> = rule
+            .labels
+            .iter()
+            .filter(|(k, _)| !rule.label_drops.contains(k))
+            .map(|(k, v)| {
+                let k = rule.label_renames.get(k).unwrap_or(k);
+                if config.lowercase_output_label_names {
+                    (k.to_lowercase(), v.clone())
+                } else {
+                    (k.clone(), v.clone())
+                }
+            })
+            .collect();
+        labels.sort();
+
+        match seen.entry((name, labels)) {
+            Entry::Occupied(entry) => {
+                let first = *entry.get();
+                issues.push(
+                    Issue::warning(
+                        "duplicate_metric_series",
+                        format!(
+                            "Rule {}: emits the same series as rule {} (metric '{}' with the same labels)",
+                            i,
+                            first,
+                            entry.key().0
+                        ),
+                    )
+                    .with_rule(i)
+                    .with_suggestion("Add a distinguishing label or use a different name"),
+                );
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(i);
+            }
+        }
+    }
+}
+
+/// Metric name and type as emitted for a rule without `$` references
+///
+/// Applies `lowercaseOutputName` and `counter_total_suffix`.
+fn emitted_metric(config: &Config, rule: &Rule) -> (String, &'static str) {
+    let metric_type = match rule.r#type.to_lowercase().as_str() {
+        "gauge" => "gauge",
+        "counter" => "counter",
+        "histogram" => "histogram",
+        _ => "untyped",
+    };
+    let mut name = if config.lowercase_output_name {
+        rule.name.to_lowercase()
+    } else {
+        rule.name.clone()
+    };
+    if config.counter_total_suffix && metric_type == "counter" && !name.ends_with("_total") {
+        name.push_str("_total");
+    }
+    (name, metric_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_from(yaml: &str) -> Config {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_rule_tests() {
+        let config = Config::from_yaml(
+            r#"
+lowercaseOutputName: true
+rules:
+  - pattern: "java.lang<type=GarbageCollector, name=([^>]+)><CollectionCount>"
+    name: "JVM_GC_Collection_Count"
+    type: counter
+    labels:
+      gc: "$1"
+    tests:
+      - input: "java.lang<type=GarbageCollector, name=G1 Young><CollectionCount>"
+        expect_name: jvm_gc_collection_count
+        expect_labels: { gc: G1 Young }
+      - input: "java.lang<type=GarbageCollector, name=G1 Old><CollectionCount>"
+        expect_name: jvm_gc_collection_count
+        expect_labels: { gc: G1 Young }
+      - input: "java.nio<type=BufferPool, name=direct><Count>"
+        expect_name: jvm_buffer_pool_count
+  - pattern: "java.lang<type=(\\w+)><(\\w+)>"
+    name: "jvm_$1_$2"
+    tests:
+      - input: "java.lang<type=GarbageCollector, name=G1><CollectionCount>"
+        expect_name: jvm_gc
+modules:
+  threads:
+    rules:
+      - pattern: "java.lang<type=Threading><(\\w+)>"
+        name: "jvm_threads_$1"
+        tests:
+          - input: "java.lang<type=Threading><ThreadCount>"
+            expect_name: jvm_threads_ThreadCount
+"#,
+        )
+        .unwrap();
+
+        let issues = check_rule_tests(&config);
+        let messages: Vec<&str> = issues.iter().map(|i| i.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "Rule 0 test 1: expected labels {gc=\"G1 Young\"}, got {gc=\"G1 Old\"}",
+                "Rule 0 test 2: 'java.nio<type=BufferPool, name=direct><Count>' matches no rule",
+                "Rule 1 test 0: 'java.lang<type=GarbageCollector, name=G1><CollectionCount>' \
+                 is matched by rule 0 first",
+                "Module 'threads': rule 0 test 0: expected name 'jvm_threads_ThreadCount', \
+                 got 'jvm_threads_threadcount'",
+            ]
+        );
+        assert_eq!(issues[0].code, "rule_test_failed");
+        assert_eq!(issues[0].location.as_deref(), Some("rules[0].tests[1]"));
+        assert_eq!(
+            issues[3].location.as_deref(),
+            Some("modules.threads.rules[0].tests[0]")
+        );
+        assert_eq!(check_config(&config).len(), issues.len());
+    }
+
+    #[test]
+    fn test_drop_rules() {
+        let config = config_from(
+            r#"
+rules:
+  - pattern: ".*"
+    name: "everything"
+    tests:
+      - input: "java.lang<type=Compilation><TotalCompilationTime>"
+  - pattern: "java.lang<type=Compilation>"
+    action: drop
+    tests:
+      - input: "java.lang<type=Compilation><TotalCompilationTime>"
+      - input: "java.lang<type=Compilation><Name>"
+        expect_name: jvm_compiler
+  - pattern: "java.lang<type=Memory>"
+"#,
+        );
+
+        let issues = check_config(&config);
+        let found: Vec<(&str, &str)> = issues
+            .iter()
+            .map(|i| (i.code, i.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                // Rule 1 drops values before the catch-all is tried
+                (
+                    "unreachable_rule",
+                    "Rule 2: unreachable, rule 0 matches every MBean"
+                ),
+                ("missing_rule_name", "Rule 2 has no name"),
+                (
+                    "rule_test_failed",
+                    "Rule 0 test 0: 'java.lang<type=Compilation><TotalCompilationTime>' \
+                     is dropped by rule 1"
+                ),
+                (
+                    "rule_test_failed",
+                    "Rule 1 test 1: drop rules produce no metric to compare"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_valid_config_has_no_issues() {
+        let report = ValidationReport::new("config.yaml", check_config(&Config::default()));
+        assert!(report.valid);
+        assert_eq!(report.error_count, 0);
+        assert_eq!(report.warning_count, 0);
+    }
+
+    #[test]
+    fn test_collects_all_errors() {
+        let config = config_from(
+            r#"
+server:
+  path: "metrics"
+rules:
+  - pattern: "(unclosed"
+    name: "a"
+  - pattern: ""
+    name: "b"
+relabel_configs:
+  - action: replace
+    regex: "(.*)"
+"#,
+        );
+        let report = ValidationReport::new("config.yaml", check_config(&config));
+        assert!(!report.valid);
+        assert_eq!(report.error_count, 4);
+
+        let codes: Vec<&str> = report.errors().map(|i| i.code).collect();
+        assert!(codes.contains(&"invalid_metrics_path"));
+        assert!(codes.contains(&"invalid_rule_regex"));
+        assert!(codes.contains(&"empty_rule_pattern"));
+        assert!(codes.contains(&"relabel_missing_target_label"));
+
+        let rule_issue = report
+            .errors()
+            .find(|i| i.code == "empty_rule_pattern")
+            .unwrap();
+        assert_eq!(rule_issue.rule_index, Some(1));
+        assert_eq!(rule_issue.location.as_deref(), Some("rules[1]"));
+    }
+
+    #[test]
+    fn test_load_time_checks() {
+        // Settings only `Config::validate` checks, as loaded by `--lenient`
+        let config = config_from(
+            r#"
+server:
+  scrape_timeout_offset_seconds: -3
+  rate_limit:
+    max_scrapes_per_minute: 0
+targets_api: {}
+"#,
+        );
+        let issues = check_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].code, "config_validation_error");
+        assert!(issues[0]
+            .message
+            .contains("server.scrape_timeout_offset_seconds must not be negative"));
+
+        // Problems the checks above report are not repeated
+        let config = config_from("server: {path: metrics, scrape_timeout_offset_seconds: -3}");
+        let codes: Vec<&str> = check_config(&config).iter().map(|i| i.code).collect();
+        assert_eq!(codes, vec!["invalid_metrics_path"]);
+    }
+
+    #[test]
+    fn test_invalid_listen_address() {
+        let config = config_from(r#"server: {listen: ["0.0.0.0:9090", ":::9090"]}"#);
+        let issues = check_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].code, "invalid_listen_address");
+        assert_eq!(issues[0].location.as_deref(), Some("server.listen"));
+        assert!(issues[0].message.contains("server.listen[1]"));
+    }
+
+    #[test]
+    fn test_unknown_preset() {
+        let config = config_from("presets: [kafka, weblogic]");
+        let report = ValidationReport::new("config.yaml", check_config(&config));
+        assert_eq!(report.error_count, 1);
+
+        let issue = report.errors().next().unwrap();
+        assert_eq!(issue.code, "unknown_preset");
+        assert_eq!(issue.location.as_deref(), Some("presets[1]"));
+    }
+
+    #[test]
+    fn test_module_issues() {
+        let config = config_from(
+            r#"
+modules:
+  empty: {}
+  kafka:
+    rules:
+      - pattern: "ok"
+        name: "a"
+      - pattern: "(unclosed"
+        name: "b"
+"#,
+        );
+        let report = ValidationReport::new("config.yaml", check_config(&config));
+        assert_eq!(report.error_count, 2);
+
+        let empty = report.errors().find(|i| i.code == "empty_module").unwrap();
+        assert_eq!(empty.location.as_deref(), Some("modules.empty"));
+
+        let regex = report
+            .errors()
+            .find(|i| i.code == "invalid_rule_regex")
+            .unwrap();
+        assert_eq!(regex.location.as_deref(), Some("modules.kafka.rules[1]"));
+        assert!(regex.message.starts_with("Module 'kafka': Rule 1"));
+    }
+
+    #[test]
+    fn test_metric_metadata_conflicts() {
+        let yaml = r#"
+rules:
+  - pattern: "a"
+    name: "requests"
+    type: counter
+    help: "Requests"
+    labels: {source: a}
+  - pattern: "b"
+    name: "requests"
+    type: counter
+    help: "Requests"
+    labels: {source: b}
+  - pattern: "c"
+    name: "requests"
+    type: gauge
+    help: "Requests"
+    labels: {source: c}
+  - pattern: "d"
+    name: "requests"
+    type: counter
+    labels: {source: d}
+  - pattern: "e<(\\w+)>"
+    name: "requests_$1"
+    type: gauge
+"#;
+        // Resolved at scrape time by the default policy
+        let report = ValidationReport::new("config.yaml", check_config(&config_from(yaml)));
+        assert!(report.valid);
+        let codes: Vec<(&str, Option<usize>)> =
+            report.warnings().map(|i| (i.code, i.rule_index)).collect();
+        assert_eq!(
+            codes,
+            vec![
+                ("metric_type_conflict", Some(2)),
+                ("metric_help_conflict", Some(3))
+            ]
+        );
+        let issue = report.warnings().next().unwrap();
+        assert!(issue.message.contains("by rule 0"), "{}", issue.message);
+
+        let strict = format!("metric_conflict_policy: error\n{}", yaml);
+        let report = ValidationReport::new("config.yaml", check_config(&config_from(&strict)));
+        assert_eq!(report.error_count, 2);
+
+        // The suffix makes a counter collide with a gauge of the suffixed name
+        let config = config_from(
+            r#"
+counter_total_suffix: true
+metric_conflict_policy: error
+rules:
+  - pattern: "a"
+    name: "requests"
+    type: counter
+  - pattern: "b"
+    name: "requests_total"
+    type: gauge
+"#,
+        );
+        let report = ValidationReport::new("config.yaml", check_config(&config));
+        assert_eq!(report.error_count, 1);
+        assert!(report
+            .errors()
+            .next()
+            .unwrap()
+            .message
+            .contains("'requests_total'"));
+    }
+
+    #[test]
+    fn test_warnings_do_not_invalidate() {
+        let config = config_from(
+            r#"
+rules:
+  - pattern: "java.lang<type=Memory>"
+    name: "a"
+    type: summary
+  - pattern: "java.lang<type=Memory>"
+    name: "b"
+"#,
+        );
+        let report = ValidationReport::new("config.yaml", check_config(&config));
+        assert!(report.valid);
+        assert_eq!(report.warning_count, 2);
+
+        let duplicate = report
+            .warnings()
+            .find(|i| i.code == "duplicate_rule_pattern")
+            .unwrap();
+        assert_eq!(duplicate.rule_index, Some(1));
+        assert!(duplicate.suggestion.is_some());
+    }
+
+    #[test]
+    fn test_rule_set_analysis() {
+        let config = config_from(
+            r#"
+rules:
+  - pattern: "java.lang<type=(\\w+)>"
+    name: "jvm_$1_$2"
+    labels:
+      kind: "$kind"
+  - pattern: "kafka.server<type=BrokerTopicMetrics>"
+    name: "kafka_messages"
+    labels: {topic: all}
+  - pattern: "kafka.server<type=ReplicaManager>"
+    name: "kafka_messages"
+    labels: {topic: all}
+  - pattern: "^(.*)$"
+    name: "jmx_$1"
+  - pattern: "java.lang<type=Threading>"
+    name: "jvm_threads"
+presets: [tomcat]
+"#,
+        );
+        let report = ValidationReport::new("config.yaml", check_config(&config));
+        assert!(report.valid);
+
+        let found: Vec<(&str, Option<usize>)> =
+            report.warnings().map(|i| (i.code, i.rule_index)).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("unknown_capture_group", Some(0)),
+                ("unknown_capture_group", Some(0)),
+                ("unreachable_rule", Some(4)),
+                ("duplicate_metric_series", Some(2)),
+                ("unreachable_rule", None),
+            ]
+        );
+        assert!(report.warnings().next().unwrap().message.contains("$2"));
+    }
+
+    #[test]
+    fn test_catch_all_patterns() {
+        for pattern in [".*", "(.*)", "^.*$", "^(.+)$", "^(?:.*)(.*)$", "a*", "x|.*"] {
+            assert!(is_catch_all(pattern), "{}", pattern);
+        }
+        for pattern in [".*foo", "^$", "java.lang.*", "^[^<]*$", "^a.*$", "^.{1,5}$"] {
+            assert!(!is_catch_all(pattern), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let mut config = Config {
+            unknown_keys: vec!["rules[0].valuefactor".to_string()],
+            ..Config::default()
+        };
+        let report = ValidationReport::new("config.yaml", check_config(&config));
+        assert!(report.valid);
+        let issue = report.warnings().next().unwrap();
+        assert_eq!(issue.code, "unknown_key");
+        assert_eq!(issue.location.as_deref(), Some("rules[0].valuefactor"));
+
+        config.strict = true;
+        let report = ValidationReport::new("config.yaml", check_config(&config));
+        assert_eq!(report.error_count, 1);
+    }
+
+    #[test]
+    fn test_report_json() {
+        let report = ValidationReport::load_failure(
+            "missing.yaml",
+            &ConfigError::ValidationError("bad".to_string()),
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.json");
+        report.write_json(&path).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["valid"], false);
+        assert_eq!(json["error_count"], 1);
+        assert_eq!(json["issues"][0]["severity"], "error");
+        assert_eq!(json["issues"][0]["code"], "config_validation_error");
+        assert!(json["issues"][0].get("rule_index").is_none());
+    }
+}
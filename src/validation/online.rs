@@ -0,0 +1,389 @@
+//! Online validation (`--validate --online`)
+//!
+//! Static checks cannot tell whether a configuration produces metrics
+//! against a real JVM. Online validation asks every configured Jolokia
+//! endpoint for its version (which also checks credentials), reads the
+//! configured MBeans once and matches the values against the rules, so
+//! unreachable agents, missing MBeans and rules that match nothing show up
+//! before deploying.
+//!
+//! Only the top-level rules and MBean queries are checked, not modules.
+
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use super::Issue;
+use crate::collector::AgentVersion;
+use crate::config::Config;
+use crate::error::CollectorError;
+use crate::server::{build_client, build_collector, build_engine};
+
+/// Result of checking one Jolokia endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointCheck {
+    /// Endpoint URL
+    pub url: String,
+    /// Agent version, if the endpoint answered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<AgentVersion>,
+    /// Why the endpoint could not be used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of reading one configured MBean query
+#[derive(Debug, Clone, Serialize)]
+pub struct MBeanCheck {
+    /// Queried ObjectName (may be a pattern)
+    pub object_name: String,
+    /// Whether the read succeeded
+    pub ok: bool,
+    /// Numeric values read
+    pub values: usize,
+    /// Values matched by a rule
+    pub matched: usize,
+    /// Why the read failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Number of collected values a rule matched
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleCoverage {
+    /// Zero-based index in matching order (configured rules, then presets)
+    pub index: usize,
+    /// Rule pattern
+    pub pattern: String,
+    /// Values for which this was the first matching rule
+    pub matches: usize,
+}
+
+/// Findings of online validation
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OnlineReport {
+    /// Configured endpoints, in priority order
+    pub endpoints: Vec<EndpointCheck>,
+    /// Configured MBean queries, in collection order
+    pub mbeans: Vec<MBeanCheck>,
+    /// Every rule, in matching order
+    pub rules: Vec<RuleCoverage>,
+}
+
+impl OnlineReport {
+    /// Number of rules that matched at least one value
+    pub fn covered_rules(&self) -> usize {
+        self.rules.iter().filter(|rule| rule.matches > 0).count()
+    }
+}
+
+/// Connect to Jolokia and check the configuration against it
+///
+/// Collection is skipped if no endpoint answers. Rule pattern errors are
+/// left to the static checks.
+pub async fn check_online(config: &Config) -> (OnlineReport, Vec<Issue>) {
+    let mut report = OnlineReport::default();
+    let mut issues = Vec::new();
+
+    let urls = std::iter::once(&config.jolokia.url).chain(config.jolokia.fallback_urls());
+    for (i, url) in urls.enumerate() {
+        let location = if config.jolokia.urls.is_empty() {
+            "jolokia.url".to_string()
+        } else {
+            format!("jolokia.urls[{}]", i)
+        };
+        let result = match build_client(config, url) {
+            Ok(client) => client.version().await.map_err(anyhow::Error::from),
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(version) => report.endpoints.push(EndpointCheck {
+                url: url.clone(),
+                version: Some(version),
+                error: None,
+            }),
+            Err(e) => {
+                issues.push(endpoint_issue(url, &e).with_location(location));
+                report.endpoints.push(EndpointCheck {
+                    url: url.clone(),
+                    version: None,
+                    error: Some(e.to_string()),
+                });
+            }
+        }
+    }
+    if report
+        .endpoints
+        .iter()
+        .all(|endpoint| endpoint.error.is_some())
+    {
+        return (report, issues);
+    }
+
+    let (Ok(engine), Ok(client)) = (
+        build_engine(config),
+        build_client(config, &config.jolokia.url),
+    ) else {
+        return (report, issues);
+    };
+    let client = client.with_fallback_urls(config.jolokia.fallback_urls());
+    let collector = build_collector(config, Arc::new(client));
+
+    let mut matches = vec![0; engine.rules().len()];
+    for (object_name, result) in collector.collect().await {
+        let location = config
+            .collect
+            .iter()
+            .position(|entry| entry.object_name == object_name)
+            .map(|i| format!("collect[{}]", i));
+
+        let response = match result {
+            Ok(response) if response.status == 200 => response,
+            Ok(response) => {
+                let error = response
+                    .error
+                    .unwrap_or_else(|| format!("Jolokia status {}", response.status));
+                report
+                    .mbeans
+                    .push(failed_read(&object_name, location, error, &mut issues));
+                continue;
+            }
+            Err(e) => {
+                report.mbeans.push(failed_read(
+                    &object_name,
+                    location,
+                    e.to_string(),
+                    &mut issues,
+                ));
+                continue;
+            }
+        };
+
+        let names = engine.flattened_names(std::slice::from_ref(&response));
+        for rule in names.iter().filter_map(|name| name.rule) {
+            matches[rule] += 1;
+        }
+        let matched = names.iter().filter(|name| name.rule.is_some()).count();
+        if matched == 0 {
+            let issue = Issue::warning(
+                "mbean_unmatched",
+                format!(
+                    "No rule matches any of the {} values of MBean '{}'",
+                    names.len(),
+                    object_name
+                ),
+            )
+            .with_suggestion("Add a rule for the MBean, or stop collecting it");
+            issues.push(match &location {
+                Some(location) => issue.with_location(location),
+                None => issue,
+            });
+        }
+        report.mbeans.push(MBeanCheck {
+            object_name,
+            ok: true,
+            values: names.len(),
+            matched,
+            error: None,
+        });
+    }
+
+    for (index, (rule, matches)) in engine.rules().iter().zip(matches).enumerate() {
+        // Presets cover many setups, so only configured rules are reported
+        if matches == 0 && index < config.rules.len() {
+            issues.push(
+                Issue::warning(
+                    "rule_unmatched",
+                    format!("Rule {}: matches none of the collected values", index),
+                )
+                .with_rule(index)
+                .with_suggestion(
+                    "Check the pattern against GET /debug/mbeans of a running exporter",
+                ),
+            );
+        }
+        report.rules.push(RuleCoverage {
+            index,
+            pattern: rule.pattern.clone(),
+            matches,
+        });
+    }
+
+    (report, issues)
+}
+
+/// Issue for an endpoint that did not answer the version request
+fn endpoint_issue(url: &str, error: &anyhow::Error) -> Issue {
+    match error.downcast_ref::<CollectorError>() {
+        Some(CollectorError::HttpStatus(status @ (401 | 403))) => Issue::error(
+            "jolokia_auth_failed",
+            format!(
+                "Jolokia at {} rejected the credentials (HTTP {})",
+                url, status
+            ),
+        )
+        .with_suggestion("Check jolokia.username and jolokia.password, or jolokia.auth"),
+        _ => Issue::error(
+            "jolokia_unreachable",
+            format!("Jolokia at {} is not usable: {}", url, error),
+        )
+        .with_suggestion("Check the URL and that the Jolokia agent is running"),
+    }
+}
+
+/// Record an MBean query that could not be read
+fn failed_read(
+    object_name: &str,
+    location: Option<String>,
+    error: String,
+    issues: &mut Vec<Issue>,
+) -> MBeanCheck {
+    let issue = Issue::warning(
+        "mbean_read_failed",
+        format!("MBean '{}' could not be read: {}", object_name, error),
+    )
+    .with_suggestion("Check the ObjectName, e.g. with a Jolokia search request");
+    issues.push(match location {
+        Some(location) => issue.with_location(location),
+        None => issue,
+    });
+
+    MBeanCheck {
+        object_name: object_name.to_string(),
+        ok: false,
+        values: 0,
+        matched: 0,
+        error: Some(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn codes(issues: &[Issue]) -> Vec<&str> {
+        issues.iter().map(|issue| issue.code).collect()
+    }
+
+    #[tokio::test]
+    async fn test_check_online() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({"type": "version"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"type": "version"},
+                "value": {"agent": "2.0.2", "protocol": "7.3"},
+                "status": 200
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({"mbean": "java.lang:type=Memory"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"mbean": "java.lang:type=Memory", "attribute": "HeapMemoryUsage", "type": "read"},
+                "value": {"used": 100, "max": 200},
+                "status": 200
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"mbean": "java.lang:type=Missing"}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"mbean": "java.lang:type=Missing", "type": "read"},
+                "error_type": "javax.management.InstanceNotFoundException",
+                "error": "javax.management.InstanceNotFoundException : java.lang:type=Missing",
+                "status": 404
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let config = Config::from_yaml(&format!(
+            r#"
+jolokia:
+  url: "{}/jolokia"
+rules:
+  - pattern: "java\\.lang<type=Memory><HeapMemoryUsage><(\\w+)>"
+    name: "jvm_heap_$1"
+  - pattern: "kafka\\.server<.*>"
+    name: "kafka"
+collect:
+  - object_name: "java.lang:type=Memory"
+    attributes: [HeapMemoryUsage]
+  - object_name: "java.lang:type=Missing"
+"#,
+            mock_server.uri()
+        ))
+        .unwrap();
+
+        let (report, issues) = check_online(&config).await;
+        assert_eq!(report.endpoints.len(), 1);
+        assert_eq!(report.endpoints[0].version.as_ref().unwrap().agent, "2.0.2");
+
+        assert_eq!(report.mbeans.len(), 2);
+        let memory = report
+            .mbeans
+            .iter()
+            .find(|mbean| mbean.object_name == "java.lang:type=Memory")
+            .unwrap();
+        assert!(memory.ok);
+        assert_eq!((memory.values, memory.matched), (2, 2));
+
+        assert_eq!(report.rules[0].matches, 2);
+        assert_eq!(report.covered_rules(), 1);
+
+        let mut found = codes(&issues);
+        found.sort();
+        assert_eq!(found, vec!["mbean_read_failed", "rule_unmatched"]);
+        let read_failed = issues
+            .iter()
+            .find(|issue| issue.code == "mbean_read_failed")
+            .unwrap();
+        assert_eq!(read_failed.location.as_deref(), Some("collect[1]"));
+        let unmatched = issues
+            .iter()
+            .find(|issue| issue.code == "rule_unmatched")
+            .unwrap();
+        assert_eq!(unmatched.rule_index, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_check_online_endpoint_errors() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&mock_server)
+            .await;
+
+        let config = Config::from_yaml(&format!(
+            r#"
+jolokia:
+  url: "{}/jolokia"
+collect:
+  - object_name: "java.lang:type=Memory"
+"#,
+            mock_server.uri()
+        ))
+        .unwrap();
+        let (report, issues) = check_online(&config).await;
+        assert_eq!(codes(&issues), vec!["jolokia_auth_failed"]);
+        assert_eq!(issues[0].location.as_deref(), Some("jolokia.url"));
+        // Collection is skipped without a usable endpoint
+        assert!(report.mbeans.is_empty());
+
+        let config = Config::from_yaml(
+            r#"
+jolokia:
+  url: "http://127.0.0.1:1/jolokia"
+"#,
+        )
+        .unwrap();
+        let (report, issues) = check_online(&config).await;
+        assert_eq!(codes(&issues), vec!["jolokia_unreachable"]);
+        assert!(report.endpoints[0].error.is_some());
+    }
+}
@@ -0,0 +1,178 @@
+//! Rule performance profile (`--dry-run --profile-rules`)
+//!
+//! Times how long each rule pattern takes to compile and to match the
+//! flattened names of sample Jolokia responses, recorded (`--sample`) or
+//! read live from the configured agent. Every rule is timed against every
+//! name, the worst case of first-match-wins, so a catastrophic pattern shows
+//! up even if an earlier rule usually matches first.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use regex::Regex;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::collector::JolokiaResponse;
+use crate::config::Config;
+use crate::server::{build_client, build_collector, build_engine};
+use crate::transformer::convert_java_regex;
+
+/// Times each name is matched, to average out timer resolution
+const ITERATIONS: u32 = 10;
+
+/// Timing of one rule
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleProfile {
+    /// Position in the rule list, starting at 1 as in the dry-run output
+    pub index: usize,
+    /// Pattern as configured
+    pub pattern: String,
+    /// Time to convert and compile the pattern, in microseconds
+    pub compile_micros: f64,
+    /// Average time to match one name, in microseconds
+    pub average_match_micros: f64,
+    /// Sample names the pattern matches
+    pub matches: usize,
+}
+
+/// Timing of every rule against a sample
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileReport {
+    /// Distinct flattened names in the sample
+    pub names: usize,
+    /// Rules, slowest average match first
+    pub rules: Vec<RuleProfile>,
+}
+
+/// Time the rules of a configuration against sample responses
+///
+/// Rules whose pattern does not compile are skipped; the dry run reports
+/// them already.
+pub fn profile_rules(config: &Config, responses: &[JolokiaResponse]) -> Result<ProfileReport> {
+    let engine = build_engine(config)?;
+    let names: Vec<String> = engine
+        .flattened_names(responses)
+        .into_iter()
+        .map(|name| name.name)
+        .collect();
+
+    let mut rules = Vec::new();
+    for (i, rule) in config.effective_rules().enumerate() {
+        let start = Instant::now();
+        let Some(regex) = convert_java_regex(&rule.pattern)
+            .ok()
+            .and_then(|converted| Regex::new(&converted).ok())
+        else {
+            continue;
+        };
+        let compile = start.elapsed();
+
+        let (elapsed, matches) = time_matches(&regex, &names);
+        let samples = names.len() as u32 * ITERATIONS;
+        rules.push(RuleProfile {
+            index: i + 1,
+            pattern: rule.pattern.clone(),
+            compile_micros: micros(compile),
+            average_match_micros: if samples == 0 {
+                0.0
+            } else {
+                micros(elapsed / samples)
+            },
+            matches,
+        });
+    }
+    rules.sort_by(|a, b| b.average_match_micros.total_cmp(&a.average_match_micros));
+
+    Ok(ProfileReport {
+        names: names.len(),
+        rules,
+    })
+}
+
+/// Read the configured MBeans once, as a sample for [`profile_rules`]
+pub async fn live_sample(config: &Config) -> Result<Vec<JolokiaResponse>> {
+    let client = build_client(config, &config.jolokia.url)?
+        .with_fallback_urls(config.jolokia.fallback_urls());
+    let collector = build_collector(config, Arc::new(client));
+    let mut responses = Vec::new();
+    for (object_name, result) in collector.collect().await {
+        match result {
+            Ok(response) if response.status == 200 => responses.push(response),
+            Ok(response) => warn!(
+                mbean = %object_name,
+                status = response.status,
+                "Skipping MBean in rule profile"
+            ),
+            Err(e) => warn!(mbean = %object_name, error = %e, "Skipping MBean in rule profile"),
+        }
+    }
+    if responses.is_empty() {
+        bail!("no MBean could be read from {}", config.jolokia.url);
+    }
+    Ok(responses)
+}
+
+/// Time spent matching every name `ITERATIONS` times, and the names matched
+fn time_matches(regex: &Regex, names: &[String]) -> (Duration, usize) {
+    let matches = names.iter().filter(|name| regex.is_match(name)).count();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        for name in names {
+            // Captures, like the engine, so capture-heavy patterns are not flattered
+            std::hint::black_box(regex.captures(name));
+        }
+    }
+    (start.elapsed(), matches)
+}
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::parse_fixture;
+
+    #[test]
+    fn test_profile_rules() {
+        let config = Config::from_yaml(
+            r#"
+rules:
+  - pattern: "java\\.lang<type=Threading><ThreadCount>"
+    name: jvm_threads_current
+  - pattern: "java\\.lang<type=Memory><HeapMemoryUsage><(\\w+)>"
+    name: jvm_memory_heap_$1_bytes
+  - pattern: "kafka\\.server<.*>"
+    name: kafka_server
+"#,
+        )
+        .unwrap();
+        let responses = parse_fixture(
+            r#"[
+                {"request": {"mbean": "java.lang:type=Threading", "attribute": "ThreadCount", "type": "read"}, "value": 12, "status": 200},
+                {"request": {"mbean": "java.lang:type=Memory", "attribute": "HeapMemoryUsage", "type": "read"}, "value": {"used": 1, "max": 4}, "status": 200}
+            ]"#,
+        )
+        .unwrap();
+
+        let report = profile_rules(&config, &responses).unwrap();
+        assert_eq!(report.names, 3);
+        assert_eq!(report.rules.len(), 3);
+        let matches = |index: usize| {
+            report
+                .rules
+                .iter()
+                .find(|rule| rule.index == index)
+                .unwrap()
+                .matches
+        };
+        assert_eq!((matches(1), matches(2), matches(3)), (1, 2, 0));
+        assert!(report
+            .rules
+            .windows(2)
+            .all(|pair| pair[0].average_match_micros >= pair[1].average_match_micros));
+    }
+}
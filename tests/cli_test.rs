@@ -486,3 +486,511 @@ rules:
         .stdout(predicate::str::contains("Dry run completed"))
         .stdout(predicate::str::contains("1 valid"));
 }
+
+/// Test that dry run executes rule examples and fails on a mismatch
+#[test]
+fn test_dry_run_rule_tests() {
+    let config = |expect_name: &str| {
+        format!(
+            r#"
+rules:
+  - pattern: "java\\.lang<type=Memory><HeapMemoryUsage><(\\w+)>"
+    name: "jvm_memory_heap_$1_bytes"
+    tests:
+      - input: "java.lang<type=Memory><HeapMemoryUsage><used>"
+        expect_name: "{}"
+        expect_labels: {{}}
+"#,
+            expect_name
+        )
+    };
+
+    let file = create_temp_config(&config("jvm_memory_heap_used_bytes"));
+    cmd()
+        .arg("-c")
+        .arg(file.path())
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tests: 1"));
+
+    let file = create_temp_config(&config("jvm_memory_heap_bytes"));
+    cmd()
+        .arg("-c")
+        .arg(file.path())
+        .arg("--dry-run")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "Rule 0 test 0: expected name 'jvm_memory_heap_bytes', got 'jvm_memory_heap_used_bytes'",
+        ));
+}
+
+/// Test lenient validation writes a report and never fails the process
+#[test]
+fn test_validate_lenient_report() {
+    let config = r#"
+server:
+  path: "metrics"
+rules:
+  - pattern: "java\\.lang<type=Memory><HeapMemoryUsage>(\\w+)"
+    name: "jvm_memory_heap_$1_bytes"
+  - pattern: ""
+    name: "broken"
+"#;
+    let file = create_temp_config(config);
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let report_path = dir.path().join("report.json");
+
+    cmd()
+        .arg("-c")
+        .arg(file.path())
+        .arg("--validate")
+        .arg("--lenient")
+        .arg("--report")
+        .arg(&report_path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Configuration validation failed"));
+
+    let report: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report_path).unwrap()).unwrap();
+    assert_eq!(report["valid"], false);
+    assert_eq!(report["error_count"], 2);
+    assert_eq!(report["issues"][1]["code"], "empty_rule_pattern");
+    assert_eq!(report["issues"][1]["rule_index"], 1);
+}
+
+/// Test lenient validation still applies the checks made when loading
+#[test]
+fn test_validate_lenient_load_time_checks() {
+    let config = r#"
+server:
+  scrape_timeout_offset_seconds: -3
+  rate_limit:
+    max_scrapes_per_minute: 0
+targets_api: {}
+fault_injection:
+  enabled: true
+  error_rate: 5
+"#;
+    let file = create_temp_config(config);
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let report_path = dir.path().join("report.json");
+
+    cmd()
+        .arg("-c")
+        .arg(file.path())
+        .arg("--validate")
+        .arg("--lenient")
+        .arg("--report")
+        .arg(&report_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Configuration is valid").not())
+        .stderr(predicate::str::contains("Configuration validation failed"));
+
+    let report: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report_path).unwrap()).unwrap();
+    assert_eq!(report["valid"], false);
+    assert_eq!(report["issues"][0]["code"], "config_validation_error");
+}
+
+/// Test that a report is written even when the config cannot be parsed
+#[test]
+fn test_validate_report_on_parse_error() {
+    let file = create_temp_config("jolokia:\n  url: [not valid yaml\n");
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let report_path = dir.path().join("report.json");
+
+    cmd()
+        .arg("-c")
+        .arg(file.path())
+        .arg("--validate")
+        .arg("--report")
+        .arg(&report_path)
+        .assert()
+        .failure();
+
+    let report: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report_path).unwrap()).unwrap();
+    assert_eq!(report["issues"][0]["code"], "config_parse_error");
+}
+
+/// Test that the schema subcommand prints the config JSON Schema
+#[test]
+fn test_schema_command() {
+    let output = cmd().arg("schema").assert().success().get_output().clone();
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(schema["title"], "Config");
+    assert!(schema["properties"]["jolokia"].is_object());
+}
+
+/// Test that unknown keys are reported by --validate, and rejected in strict mode
+#[test]
+fn test_validate_unknown_keys() {
+    let config = r#"
+rules:
+  - pattern: "java\\.lang<type=Memory><HeapMemoryUsage>(\\w+)"
+    name: "jvm_memory_heap_$1_bytes"
+    valuefactor: 0.001
+"#;
+    let file = create_temp_config(config);
+    cmd()
+        .arg("-c")
+        .arg(file.path())
+        .arg("--validate")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Unknown key 'rules[0].valuefactor'",
+        ));
+
+    let file = create_temp_config(&format!("strict: true\n{}", config));
+    cmd()
+        .arg("-c")
+        .arg(file.path())
+        .arg("--validate")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("rules[0].valuefactor"));
+}
+
+/// Test that generate-config writes a valid starter config from a mock JVM
+#[tokio::test(flavor = "multi_thread")]
+async fn test_generate_config_command() {
+    use wiremock::matchers::{body_partial_json, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({"type": "search"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "request": {"mbean": "*:*", "type": "search"},
+            "value": ["java.lang:type=Memory", "JMImplementation:type=MBeanServerDelegate"],
+            "status": 200
+        })))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(
+            serde_json::json!({"mbean": "java.lang:*"}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "request": {"mbean": "java.lang:*", "type": "read"},
+            "value": {
+                "java.lang:type=Memory": {
+                    "HeapMemoryUsage": {"used": 100, "max": 200},
+                    "Verbose": false
+                }
+            },
+            "status": 200
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("starter.yaml");
+    cmd()
+        .arg("generate-config")
+        .arg("--jolokia-url")
+        .arg(format!("{}/jolokia", mock_server.uri()))
+        .arg("-o")
+        .arg(&path)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Configuration written to"));
+
+    let yaml = std::fs::read_to_string(&path).unwrap();
+    assert!(yaml.contains("object_name: 'java.lang:*'"));
+    assert!(!yaml.contains("JMImplementation"));
+    assert!(yaml.contains("name: 'java_lang_memory_$1_$2'"));
+
+    cmd()
+        .arg("-c")
+        .arg(&path)
+        .arg("--validate")
+        .assert()
+        .success();
+}
+
+/// Test that scrape-once prints the metrics of a single scrape
+#[tokio::test(flavor = "multi_thread")]
+async fn test_scrape_once_command() {
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "request": {"mbean": "java.lang:type=Threading", "attribute": "ThreadCount", "type": "read"},
+            "value": 42,
+            "status": 200
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let mut config = NamedTempFile::new().unwrap();
+    write!(
+        config,
+        r#"
+jolokia:
+  url: "{}/jolokia"
+collect:
+  - object_name: "java.lang:type=Threading"
+    attributes: [ThreadCount]
+rules:
+  - pattern: "java\\.lang<type=Threading><ThreadCount>"
+    name: "jvm_threads_current"
+    type: gauge
+"#,
+        mock_server.uri()
+    )
+    .unwrap();
+
+    cmd()
+        .arg("-c")
+        .arg(config.path())
+        .arg("scrape-once")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("jvm_threads_current 42"))
+        .stdout(predicate::str::contains("rjmx_exporter_scrape_errors 0"));
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("jvm.prom");
+    cmd()
+        .arg("-c")
+        .arg(config.path())
+        .arg("scrape-once")
+        .arg("--output-file")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.contains("# TYPE jvm_threads_current"));
+    assert!(!dir.path().join("jvm.prom.tmp").exists());
+}
+
+/// Test that scrape-once fails when MBeans cannot be read
+#[test]
+fn test_scrape_once_unreachable() {
+    let mut config = NamedTempFile::new().unwrap();
+    writeln!(
+        config,
+        r#"
+jolokia:
+  url: "http://127.0.0.1:1/jolokia"
+  timeout_ms: 1000
+"#
+    )
+    .unwrap();
+
+    cmd()
+        .arg("-c")
+        .arg(config.path())
+        .arg("scrape-once")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("rjmx_exporter_info"))
+        .stderr(predicate::str::contains("Scrape finished with"));
+
+    // The report is written even though the scrape failed
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("summary.json");
+    cmd()
+        .arg("-c")
+        .arg(config.path())
+        .arg("scrape-once")
+        .arg("--report")
+        .arg(&report)
+        .assert()
+        .failure();
+    let summary: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+    assert_eq!(summary["series"], 0);
+    assert!(!summary["errors"].as_array().unwrap().is_empty());
+}
+
+/// Test the scrape-once JSON report and --fail-on-unmatched
+#[tokio::test(flavor = "multi_thread")]
+async fn test_scrape_once_report() {
+    use wiremock::matchers::{body_partial_json, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    for (mbean, attribute, value) in [
+        ("java.lang:type=Threading", "ThreadCount", 42),
+        ("java.lang:type=ClassLoading", "LoadedClassCount", 9000),
+    ] {
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({"mbean": mbean})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "request": {"mbean": mbean, "attribute": attribute, "type": "read"},
+                "value": value,
+                "status": 200
+            })))
+            .mount(&mock_server)
+            .await;
+    }
+
+    let mut config = NamedTempFile::new().unwrap();
+    write!(
+        config,
+        r#"
+jolokia:
+  url: "{}/jolokia"
+collect:
+  - object_name: "java.lang:type=Threading"
+    attributes: [ThreadCount]
+  - object_name: "java.lang:type=ClassLoading"
+    attributes: [LoadedClassCount]
+rules:
+  - pattern: "java\\.lang<type=Threading><ThreadCount>"
+    name: "jvm_threads_current"
+    type: gauge
+"#,
+        mock_server.uri()
+    )
+    .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("summary.json");
+    cmd()
+        .arg("-c")
+        .arg(config.path())
+        .arg("scrape-once")
+        .arg("--report")
+        .arg(&report)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("jvm_threads_current 42"));
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+    assert_eq!(summary["series"], 1);
+    assert_eq!(summary["mbeans_discovered"], 2);
+    assert_eq!(summary["mbeans_scraped"], 1);
+    assert_eq!(
+        summary["unmatched_mbeans"],
+        serde_json::json!(["java.lang:type=ClassLoading"])
+    );
+    assert_eq!(summary["errors"], serde_json::json!([]));
+
+    cmd()
+        .arg("-c")
+        .arg(config.path())
+        .arg("scrape-once")
+        .arg("--fail-on-unmatched")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("java.lang:type=ClassLoading"))
+        .stderr(predicate::str::contains("1 MBean(s) matched by no rule"));
+}
+
+/// Test that the diff subcommand reports the series changed by a rule edit
+#[test]
+fn test_diff_command() {
+    let old = create_temp_config(
+        r#"
+rules:
+  - pattern: "java\\.lang<type=Threading><ThreadCount>"
+    name: jvm_threads
+"#,
+    );
+    let new = create_temp_config(
+        r#"
+rules:
+  - pattern: "java\\.lang<type=Threading><ThreadCount>"
+    name: jvm_threads_current
+"#,
+    );
+    let input = create_temp_config(
+        r#"{"request": {"mbean": "java.lang:type=Threading", "attribute": "ThreadCount", "type": "read"}, "value": 42, "status": 200}"#,
+    );
+
+    cmd()
+        .arg("diff")
+        .arg("--old")
+        .arg(old.path())
+        .arg("--new")
+        .arg(new.path())
+        .arg("--input")
+        .arg(input.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Series: 1 -> 1 (0 added, 0 removed, 1 renamed, 0 changed)",
+        ))
+        .stdout(predicate::str::contains(
+            "~ jvm_threads -> jvm_threads_current",
+        ));
+
+    let output = cmd()
+        .arg("--output-format")
+        .arg("json")
+        .arg("diff")
+        .arg("--old")
+        .arg(old.path())
+        .arg("--new")
+        .arg(old.path())
+        .arg("--input")
+        .arg(input.path())
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let diff: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(diff["old_series"], 1);
+    assert_eq!(diff["renamed"], serde_json::json!([]));
+}
+
+#[test]
+fn test_dry_run_profile_rules() {
+    let config = create_temp_config(
+        r#"
+rules:
+  - pattern: "java\\.lang<type=Threading><ThreadCount>"
+    name: jvm_threads_current
+  - pattern: "kafka\\.server<.*>"
+    name: kafka_server
+"#,
+    );
+    let sample = create_temp_config(
+        r#"[{"request": {"mbean": "java.lang:type=Threading", "attribute": "ThreadCount", "type": "read"}, "value": 42, "status": 200}]"#,
+    );
+
+    cmd()
+        .arg("--dry-run")
+        .arg("--profile-rules")
+        .arg("--sample")
+        .arg(sample.path())
+        .arg("-c")
+        .arg(config.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Rule profile (1 sample names, slowest first):",
+        ));
+
+    let output = cmd()
+        .arg("--output-format")
+        .arg("json")
+        .arg("--dry-run")
+        .arg("--profile-rules")
+        .arg("--sample")
+        .arg(sample.path())
+        .arg("-c")
+        .arg(config.path())
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["profile"]["names"], 1);
+    let rules = report["profile"]["rules"].as_array().unwrap();
+    assert_eq!(rules.len(), 2);
+    let threads = rules.iter().find(|rule| rule["index"] == 1).unwrap();
+    assert_eq!(threads["matches"], 1);
+}
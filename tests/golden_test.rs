@@ -0,0 +1,31 @@
+//! Golden-file regression tests
+//!
+//! Renders recorded Jolokia responses with a fixture config and compares the
+//! output against a checked-in exposition file, using the public
+//! `rjmx_exporter::testing` helpers. Regenerate with `RJMX_UPDATE_GOLDEN=1`.
+
+use rjmx_exporter::testing;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/golden");
+
+#[test]
+fn test_jvm_rules_golden() {
+    testing::assert_fixture_matches(
+        format!("{}/config.yaml", FIXTURES),
+        format!("{}/jvm.json", FIXTURES),
+        format!("{}/jvm.prom", FIXTURES),
+    );
+}
+
+#[test]
+fn test_golden_mismatch_reports_diff() {
+    let config = testing::load_config(format!("{}/config.yaml", FIXTURES)).unwrap();
+    let mut responses = testing::load_fixture(format!("{}/jvm.json", FIXTURES)).unwrap();
+    responses.truncate(1);
+    let actual = testing::render(&config, &responses).unwrap();
+
+    let expected = std::fs::read_to_string(format!("{}/jvm.prom", FIXTURES)).unwrap();
+    let diff = testing::diff(&expected, &actual);
+    assert!(diff.contains("- jvm_threads_current 25"));
+    assert!(!diff.contains("+ "));
+}
@@ -145,6 +145,7 @@ async fn test_rule_transformation() {
         timestamp: 1609459200,
         error: None,
         error_type: None,
+        mbean_errors: Vec::new(),
     };
 
     let engine = create_test_transform_engine();
@@ -175,7 +176,7 @@ async fn test_rule_transformation() {
     for metric in &metrics {
         assert_eq!(
             metric.labels.get("area"),
-            Some(&"heap".to_string()),
+            Some("heap"),
             "Each metric should have area=heap label"
         );
         assert_eq!(
@@ -273,6 +274,7 @@ async fn test_multi_mbean_transformation() {
             timestamp: 1609459200,
             error: None,
             error_type: None,
+            mbean_errors: Vec::new(),
         },
         JolokiaResponse {
             request: rjmx_exporter::collector::RequestInfo {
@@ -285,6 +287,7 @@ async fn test_multi_mbean_transformation() {
             timestamp: 1609459200,
             error: None,
             error_type: None,
+            mbean_errors: Vec::new(),
         },
     ];
 
@@ -392,6 +395,7 @@ async fn test_transform_skips_error_responses() {
             timestamp: 1609459200,
             error: Some("Not found".to_string()),
             error_type: Some("javax.management.InstanceNotFoundException".to_string()),
+            mbean_errors: Vec::new(),
         },
         // Valid response - should be processed
         JolokiaResponse {
@@ -405,6 +409,7 @@ async fn test_transform_skips_error_responses() {
             timestamp: 1609459200,
             error: None,
             error_type: None,
+            mbean_errors: Vec::new(),
         },
     ];
 
@@ -452,6 +457,7 @@ async fn test_lowercase_option() {
         timestamp: 1609459200,
         error: None,
         error_type: None,
+        mbean_errors: Vec::new(),
     };
 
     let metrics = engine.transform(&[response]).expect("Transform failed");